@@ -87,6 +87,8 @@ pub enum LLMProviderError {
     APIError(String),
     DatabaseError(String),
     ImageProcessingError(String),
+    UsageBudgetExceeded(String),
+    JobCancelled,
 }
 
 impl fmt::Display for LLMProviderError {
@@ -182,6 +184,8 @@ impl fmt::Display for LLMProviderError {
             LLMProviderError::APIError(s) => write!(f, "{}", s),
             LLMProviderError::DatabaseError(s) => write!(f, "{}", s),
             LLMProviderError::ImageProcessingError(s) => write!(f, "Image processing error: {}", s),
+            LLMProviderError::UsageBudgetExceeded(s) => write!(f, "Usage budget exceeded: {}", s),
+            LLMProviderError::JobCancelled => write!(f, "Job cancelled by user request"),
         }
     }
 }
@@ -265,6 +269,8 @@ impl LLMProviderError {
             LLMProviderError::APIError(_) => "APIError",
             LLMProviderError::DatabaseError(_) => "DatabaseError",
             LLMProviderError::ImageProcessingError(_) => "ImageProcessingError",
+            LLMProviderError::UsageBudgetExceeded(_) => "UsageBudgetExceeded",
+            LLMProviderError::JobCancelled => "JobCancelled",
         };
 
         format!("Error {} with message: {}", error_name, self)
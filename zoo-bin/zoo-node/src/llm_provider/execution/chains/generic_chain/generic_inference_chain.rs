@@ -1,7 +1,8 @@
 use crate::llm_provider::error::LLMProviderError;
 use crate::llm_provider::execution::chains::inference_chain_trait::{
-    InferenceChain, InferenceChainContext, InferenceChainContextTrait, InferenceChainResult,
+    FunctionCall, InferenceChain, InferenceChainContext, InferenceChainContextTrait, InferenceChainResult,
 };
+use crate::llm_provider::execution::conversation_memory::ConversationMemory;
 use crate::llm_provider::execution::prompts::general_prompts::JobPromptGenerator;
 use crate::llm_provider::execution::user_message_parser::ParsedUserMessage;
 use crate::llm_provider::job_callback_manager::JobCallbackManager;
@@ -12,6 +13,7 @@ use crate::managers::model_capabilities_manager::ModelCapabilitiesManager;
 use crate::managers::tool_router::{ToolCallFunctionResponse, ToolRouter};
 use crate::network::agent_payments_manager::external_agent_offerings_manager::ExtAgentOfferingsManager;
 use crate::network::agent_payments_manager::my_agent_offerings_manager::MyAgentOfferingsManager;
+use crate::tools::tool_implementation::native_tools::agent_memory::{AGENT_RECALL_TOOL_KEY, AGENT_REMEMBER_TOOL_KEY};
 use zoo_fs::zoo_file_manager::ZooFileManager;
 use zoo_message_primitives::schemas::tool_router_key::ToolRouterKey;
 
@@ -24,7 +26,7 @@ use zoo_message_primitives::schemas::job::{Job, JobLike};
 use zoo_message_primitives::schemas::llm_providers::common_agent_llm_provider::ProviderOrAgent;
 use zoo_message_primitives::schemas::zoo_fs::ZooFileChunkCollection;
 use zoo_message_primitives::schemas::zoo_name::ZooName;
-use zoo_message_primitives::schemas::ws_types::WSUpdateHandler;
+use zoo_message_primitives::schemas::ws_types::{ToolStatusType, WSUpdateHandler};
 use zoo_message_primitives::zoo_utils::job_scope::MinimalJobScope;
 use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
 use zoo_message_primitives::zoo_utils::zoo_path::ZooPath;
@@ -33,14 +35,36 @@ use zoo_sqlite::SqliteManager;
 
 use base64::Engine;
 use chrono;
+use futures::StreamExt;
 use serde_json::json;
 use std::fmt;
+use std::future::Future;
 use std::path::PathBuf;
 use std::result::Result::Ok;
 use std::time::Instant;
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::Mutex;
 
+/// Default number of tool calls from a single LLM turn that run at the same time.
+/// Can be overridden with the `max_parallel_tool_calls` preference.
+const DEFAULT_MAX_PARALLEL_TOOL_CALLS: usize = 4;
+
+/// How many valid tool names are suggested to the LLM when it calls a function that doesn't exist.
+const MAX_SUGGESTED_TOOL_NAMES: usize = 5;
+
+/// Runs the calls at most `max_parallel` at a time and returns their results in the order of
+/// `calls`, whichever finishes first.
+async fn run_in_call_order<C, F, Fut>(calls: Vec<C>, max_parallel: usize, run: F) -> Vec<Fut::Output>
+where
+    F: FnMut(C) -> Fut,
+    Fut: Future,
+{
+    futures::stream::iter(calls.into_iter().map(run))
+        .buffered(max_parallel)
+        .collect()
+        .await
+}
+
 #[derive(Clone)]
 pub struct GenericInferenceChain {
     pub context: InferenceChainContext,
@@ -715,10 +739,7 @@ impl GenericInferenceChain {
                 if let ProviderOrAgent::Agent(agent) = &llm_provider {
                     for tool in &agent.tools {
                         if let Some(tool_router) = &tool_router {
-                            match tool_router
-                                .get_tool_by_name_and_version(&tool.to_string_without_version(), tool.version())
-                                .await
-                            {
+                            match tool_router.get_tool_by_router_key(tool).await {
                                 Ok(Some(tool)) => tools.push(tool),
                                 Ok(None) => {
                                     return Err(LLMProviderError::ToolNotFound(format!(
@@ -735,6 +756,35 @@ impl GenericInferenceChain {
                             }
                         }
                     }
+
+                    // Every agent can remember and recall, its memory outlives the job
+                    if let Some(tool_router) = &tool_router {
+                        for memory_tool_key in [AGENT_REMEMBER_TOOL_KEY, AGENT_RECALL_TOOL_KEY] {
+                            let has_memory_tool = tools
+                                .iter()
+                                .any(|tool| tool.tool_router_key().to_string_without_version() == memory_tool_key);
+                            if has_memory_tool {
+                                continue;
+                            }
+                            match tool_router.get_tool_by_name(memory_tool_key).await {
+                                Ok(Some(memory_tool)) => tools.push(memory_tool),
+                                Ok(None) => {
+                                    zoo_log(
+                                        ZooLogOption::JobExecution,
+                                        ZooLogLevel::Error,
+                                        &format!("Agent memory tool not found: {}", memory_tool_key),
+                                    );
+                                }
+                                Err(e) => {
+                                    zoo_log(
+                                        ZooLogOption::JobExecution,
+                                        ZooLogLevel::Error,
+                                        &format!("Error retrieving agent memory tool: {:?}", e),
+                                    );
+                                }
+                            }
+                        }
+                    }
                 } else {
                     // CASE 2.2: For regular LLM providers, perform vector search
                     // to find the most relevant tools for the user's message
@@ -930,6 +980,17 @@ impl GenericInferenceChain {
             additional_files
         );
 
+        let memory_strategy = ConversationMemory::strategy(&full_job, &llm_provider);
+        let conversation_memory = ConversationMemory::load(
+            &db,
+            &full_job,
+            &llm_provider,
+            memory_strategy,
+            &user_message,
+            max_tokens_in_prompt,
+        )
+        .await;
+
         // We'll keep a record of *every* function call + response across all iterations:
         let mut all_function_responses = Vec::new();
 
@@ -947,8 +1008,10 @@ impl GenericInferenceChain {
             video_files.clone(),
             audio_files.clone(),
             ret_nodes.clone(),
-            None,
-            Some(full_job.step_history.clone()),
+            conversation_memory.summary.clone(),
+            conversation_memory.recalled_messages.clone(),
+            conversation_memory.agent_memories.clone(),
+            Some(conversation_memory.step_history.clone()),
             tools.clone(),
             Some(all_function_responses.clone()),
             full_job.job_id.clone(),
@@ -956,6 +1019,10 @@ impl GenericInferenceChain {
         )
         .await;
 
+        let max_parallel_tool_calls = Self::max_parallel_tool_calls(&db);
+        let job_token = llm_stopper
+            .inbox_cancellation_token(&InboxName::get_job_inbox_name_from_params(full_job.job_id.clone()).ok());
+
         let mut iteration_count = 0;
         let mut tool_calls_history = Vec::new();
         loop {
@@ -1001,6 +1068,21 @@ impl GenericInferenceChain {
                 Ok(name) => Some(name),
                 Err(_) => None,
             };
+
+            // A stopped job doesn't start another iteration
+            if job_token.is_cancelled() {
+                return Ok(Self::cancelled_inference_result(
+                    &db,
+                    &message_hash_id,
+                    &inbox_name,
+                    iteration_count,
+                    &all_llm_messages,
+                    &all_reasoning_content,
+                    start_time,
+                    tool_calls_history,
+                    all_generated_files,
+                ));
+            }
             let response_res = JobManager::inference_with_llm_provider(
                 llm_provider.clone(),
                 filled_prompt.clone(),
@@ -1014,7 +1096,19 @@ impl GenericInferenceChain {
             .await;
 
             // Error Codes
-            if let Err(LLMProviderError::LLMServiceInferenceLimitReached(e)) = &response_res {
+            if let Err(LLMProviderError::JobCancelled) = &response_res {
+                return Ok(Self::cancelled_inference_result(
+                    &db,
+                    &message_hash_id,
+                    &inbox_name,
+                    iteration_count,
+                    &all_llm_messages,
+                    &all_reasoning_content,
+                    start_time,
+                    tool_calls_history,
+                    all_generated_files,
+                ));
+            } else if let Err(LLMProviderError::LLMServiceInferenceLimitReached(e)) = &response_res {
                 return Err(LLMProviderError::LLMServiceInferenceLimitReached(e.to_string()));
             } else if let Err(LLMProviderError::LLMServiceUnexpectedError(e)) = &response_res {
                 return Err(LLMProviderError::LLMServiceUnexpectedError(e.to_string()));
@@ -1033,7 +1127,11 @@ impl GenericInferenceChain {
                     "llm_response",
                     &trace_info,
                 ) {
-                    eprintln!("failed to add response trace: {:?}", e);
+                    zoo_log(
+                        ZooLogOption::JobExecution,
+                        ZooLogLevel::Error,
+                        &format!("Failed to add response trace: {:?}", e),
+                    );
                 }
             }
 
@@ -1049,137 +1147,293 @@ impl GenericInferenceChain {
                 let mut iteration_function_responses = Vec::new();
                 let mut should_retry = false;
 
-                for function_call in response.function_calls {
-                    let parsed_message = ParsedUserMessage::new(user_message.clone());
-                    let image_files = HashMap::new();
-                    let context = InferenceChainContext::new(
-                        db.clone(),
-                        full_job.clone(),
-                        parsed_message,
-                        None,
-                        force_tools_scope.clone(),
-                        fs_files_paths.clone(),
-                        job_filenames.clone(),
-                        message_hash_id.clone(),
-                        image_files.clone(),
-                        video_files.clone(),
-                        audio_files.clone(),
-                        llm_provider.clone(),
-                        generator.clone(),
-                        user_profile.clone(),
-                        max_iterations,
-                        max_tokens_in_prompt,
-                        ws_manager_trait.clone(),
-                        tool_router.clone(),
-                        my_agent_payments_manager.clone(),
-                        ext_agent_payments_manager.clone(),
-                        job_callback_manager.clone(),
-                        // sqlite_logger.clone(),
-                        llm_stopper.clone(),
-                    );
+                let parsed_message = ParsedUserMessage::new(user_message.clone());
+                let context = InferenceChainContext::new(
+                    db.clone(),
+                    full_job.clone(),
+                    parsed_message,
+                    None,
+                    force_tools_scope.clone(),
+                    fs_files_paths.clone(),
+                    job_filenames.clone(),
+                    message_hash_id.clone(),
+                    HashMap::new(),
+                    video_files.clone(),
+                    audio_files.clone(),
+                    llm_provider.clone(),
+                    generator.clone(),
+                    user_profile.clone(),
+                    max_iterations,
+                    max_tokens_in_prompt,
+                    ws_manager_trait.clone(),
+                    tool_router.clone(),
+                    my_agent_payments_manager.clone(),
+                    ext_agent_payments_manager.clone(),
+                    job_callback_manager.clone(),
+                    // sqlite_logger.clone(),
+                    llm_stopper.clone(),
+                );
 
-                    // 6) Call workflow or tooling
-                    // Find the ZooTool that has a tool with the function name
+                // 6) Call workflow or tooling
+                // Find the ZooTool that has a tool with the function name for every call of this turn
+                let mut resolved_calls = Vec::new();
+                for function_call in response.function_calls {
                     let zoo_tool = tools.iter().find(|tool| {
                         ToolRouterKey::sanitize(&tool.tool_router_key().name) == function_call.name
                             || tool.tool_router_key().to_string_without_version()
                                 == function_call.tool_router_key.clone().unwrap_or_default()
                     });
 
-                    if zoo_tool.is_none() {
-                        eprintln!("Function not found: {}", function_call.name);
-                        return Err(LLMProviderError::FunctionNotFound(function_call.name.clone()));
-                    }
-                    let zoo_tool = zoo_tool.unwrap();
-
-                    if let Some(ref msg_id) = message_hash_id {
-                        let trace_info = json!({
-                            "tool": zoo_tool.tool_router_key().to_string_without_version(),
-                            "function": function_call.name
-                        });
-                        if let Err(e) = db.add_tracing(
-                            msg_id,
-                            inbox_name.as_ref().map(|i| i.get_value()).as_deref(),
-                            "tool_call",
-                            &trace_info,
-                        ) {
-                            eprintln!("failed to add tool call trace: {:?}", e);
+                    // A hallucinated function has no tool. It doesn't run, but it is reported back to
+                    // the LLM so it can pick a valid one.
+                    resolved_calls.push((function_call, zoo_tool.cloned()));
+                }
+
+                // Calculate safe token limit for function responses (reserve space for context)
+                let provider_interface = match &llm_provider {
+                    ProviderOrAgent::LLMProvider(provider) => provider.model.clone(),
+                    ProviderOrAgent::Agent(agent) => {
+                        // For agents, we need to get the underlying LLM provider's model
+                        let llm_provider = db
+                            .get_llm_provider(&agent.llm_provider_id, &agent.full_identity_name)
+                            .map_err(|_e| LLMProviderError::AgentNotFound(agent.llm_provider_id.clone()))?;
+                        match llm_provider {
+                            Some(provider) => provider.model,
+                            None => return Err(LLMProviderError::AgentNotFound(agent.llm_provider_id.clone())),
                         }
                     }
+                };
+                let max_input_tokens = ModelCapabilitiesManager::get_max_input_tokens(&provider_interface);
+                let max_tokens_for_response = ((max_input_tokens as f64 * 0.9) as usize).max(1024); // Allow 90% of the context window, minimum 1024 tokens
+
+                // The calls of one LLM turn are independent, so they run concurrently (at most
+                // max_parallel_tool_calls at a time). `buffered` yields the results in the order the
+                // model emitted the calls, which keeps the follow-up prompt deterministic.
+                let tool_router_ref = tool_router
+                    .as_ref()
+                    .ok_or_else(|| LLMProviderError::ToolRouterError("The tool router is not available".to_string()))?;
+                let context_ref = &context;
+                let db_ref = &db;
+                let inbox_name_ref = &inbox_name;
+                let message_hash_id_ref = &message_hash_id;
+                let ws_manager_ref = &ws_manager_trait;
+                let user_profile_ref = &user_profile;
+                let job_id = full_job.job_id.clone();
+                let job_id_ref = &job_id;
+                let job_token_ref = &job_token;
+                let call_results = run_in_call_order(
+                    resolved_calls,
+                    max_parallel_tool_calls,
+                    |(function_call, zoo_tool)| async move {
+                        let zoo_tool = match zoo_tool {
+                            Some(zoo_tool) => zoo_tool,
+                            None => {
+                                let error = LLMProviderError::FunctionNotFound(function_call.name.clone());
+                                return (function_call, None, Err(error));
+                            }
+                        };
+                        let tool_router_key = zoo_tool.tool_router_key().to_string_without_version();
+
+                        if let Some(ref msg_id) = message_hash_id_ref {
+                            let trace_info = json!({
+                                "tool": tool_router_key,
+                                "function": function_call.name,
+                                "index": function_call.index
+                            });
+                            if let Err(e) = db_ref.add_tracing(
+                                msg_id,
+                                inbox_name_ref.as_ref().map(|i| i.get_value()).as_deref(),
+                                "tool_call",
+                                &trace_info,
+                            ) {
+                                zoo_log(
+                                    ZooLogOption::JobExecution,
+                                    ZooLogLevel::Error,
+                                    &format!("Failed to add tool call trace: {:?}", e),
+                                );
+                            }
+                        }
 
-                    // Note: here we can add logic to handle the case that we have network tools
-                    // TODO: if zoo_tool is None we need to retry with the LLM (hallucination)
-                    let mut function_response = match tool_router
-                        .as_ref()
-                        .unwrap()
-                        .call_function(function_call.clone(), &context, &zoo_tool, user_profile.clone())
-                        .await
-                    {
-                        Ok(response) => response,
-                        Err(e) => {
-                            match &e {
-                                LLMProviderError::ToolRouterError(ref error_msg)
-                                    if error_msg.contains("Invalid function arguments") =>
-                                {
-                                    if let Some(ref msg_id) = message_hash_id {
-                                        let trace_info = json!({
-                                            "error": error_msg,
-                                            "function": function_call.name
-                                        });
-                                        if let Err(e) = db.add_tracing(
-                                            msg_id,
-                                            inbox_name.as_ref().map(|i| i.get_value()).as_deref(),
-                                            "tool_error_invalid_arguments",
-                                            &trace_info,
-                                        ) {
-                                            eprintln!("failed to add tool error trace: {:?}", e);
-                                        }
+                        // Note: here we can add logic to handle the case that we have network tools
+                        // Stopping the job drops the call, which kills its subprocess or MCP request
+                        let result = tokio::select! {
+                            result = tool_router_ref.call_function(
+                                function_call.clone(),
+                                context_ref,
+                                &zoo_tool,
+                                user_profile_ref.clone(),
+                            ) => result,
+                            _ = job_token_ref.cancelled() => Err(LLMProviderError::JobCancelled),
+                        };
+
+                        let result = match result {
+                            Ok(mut function_response) => {
+                                if let Some(ref msg_id) = message_hash_id_ref {
+                                    let trace_info = json!({
+                                        "response": function_response.response,
+                                        "function": function_call.name,
+                                        "index": function_call.index
+                                    });
+                                    if let Err(e) = db_ref.add_tracing(
+                                        msg_id,
+                                        inbox_name_ref.as_ref().map(|i| i.get_value()).as_deref(),
+                                        "tool_response",
+                                        &trace_info,
+                                    ) {
+                                        zoo_log(
+                                            ZooLogOption::JobExecution,
+                                            ZooLogLevel::Error,
+                                            &format!("Failed to add tool response trace: {:?}", e),
+                                        );
                                     }
+                                }
 
-                                    // For invalid arguments, we'll retry with the LLM by including the error
-                                    // message in the next prompt to help it fix
-                                    // the parameters
-                                    let mut function_call_with_error = function_call.clone();
-                                    function_call_with_error.response = Some(error_msg.clone());
-                                    tool_calls_history.push(function_call_with_error);
-
-                                    // Store the error response to be included in the next prompt
-                                    iteration_function_responses.push(ToolCallFunctionResponse {
-                                        function_call: function_call.clone(),
-                                        response: error_msg.clone(),
-                                    });
+                                let response_tokens = count_tokens_from_message_llama3(&function_response.response);
+                                if response_tokens > max_tokens_for_response {
+                                    function_response.response = json!({
+                                        "max_tokens_for_response": max_tokens_for_response,
+                                        "max_input_tokens": max_input_tokens,
+                                        "response_tokens": response_tokens,
+                                        "response": "IMPORTANT: Function response exceeded model context window, try again with a smaller response or a more capable model.",
+                                    }).to_string();
+                                }
+
+                                // Trigger WS update after receiving function_response
+                                Self::trigger_ws_update(
+                                    ws_manager_ref,
+                                    &Some(job_id_ref.clone()),
+                                    &function_response,
+                                    tool_router_key,
+                                )
+                                .await;
 
-                                    // Update prompt with error information for retry
-                                    filled_prompt = JobPromptGenerator::generic_inference_prompt(
-                                        db.clone(),
-                                        custom_system_prompt.clone(),
-                                        custom_prompt.clone(),
-                                        user_message.clone(),
-                                        image_files.clone(),
-                                        video_files.clone(),
-                                        audio_files.clone(),
-                                        ret_nodes.clone(),
-                                        None,
-                                        Some(full_job.step_history.clone()),
-                                        tools.clone(),
-                                        // Pass all function responses (including the error) to keep context
-                                        Some(
-                                            all_function_responses
-                                                .iter()
-                                                .chain(iteration_function_responses.iter())
-                                                .cloned()
-                                                .collect(),
-                                        ),
-                                        full_job.job_id.clone(),
-                                        additional_files.clone(),
-                                    )
-                                    .await;
-
-                                    // Set flag to retry and break out of the function calls loop
-                                    iteration_count += 1;
-                                    should_retry = true;
-                                    break;
+                                Ok(function_response)
+                            }
+                            Err(e) => Err(e),
+                        };
+
+                        (function_call, Some(zoo_tool), result)
+                    },
+                )
+                .await;
+
+                // Collect the results in call order. The first hard error fails the turn once every
+                // call has settled.
+                let mut first_error = None;
+                for (function_call, zoo_tool, result) in call_results {
+                    match result {
+                        Ok(function_response) => {
+                            let mut function_call_with_router_key = function_call.clone();
+                            function_call_with_router_key.tool_router_key =
+                                zoo_tool.map(|tool| tool.tool_router_key().to_string_without_version());
+                            function_call_with_router_key.response = Some(function_response.response.clone());
+                            tool_calls_history.push(function_call_with_router_key);
+
+                            // Store all function responses to use in the next prompt
+                            iteration_function_responses.push(function_response);
+                        }
+                        Err(LLMProviderError::FunctionNotFound(ref function_name)) => {
+                            let closest_tools = tool_router_ref
+                                .closest_tool_names(function_name, &tools, MAX_SUGGESTED_TOOL_NAMES)
+                                .await;
+
+                            if let Some(ref msg_id) = message_hash_id {
+                                let trace_info = json!({
+                                    "function": function_name,
+                                    "closest_tools": closest_tools
+                                });
+                                if let Err(e) = db.add_tracing(
+                                    msg_id,
+                                    inbox_name.as_ref().map(|i| i.get_value()).as_deref(),
+                                    "tool_error_function_not_found",
+                                    &trace_info,
+                                ) {
+                                    zoo_log(
+                                        ZooLogOption::JobExecution,
+                                        ZooLogLevel::Error,
+                                        &format!("Failed to add tool error trace: {:?}", e),
+                                    );
+                                }
+                            }
+
+                            zoo_log(
+                                ZooLogOption::JobExecution,
+                                ZooLogLevel::Info,
+                                &format!(
+                                    "Function not found: {}. Closest tools: {:?}",
+                                    function_name, closest_tools
+                                ),
+                            );
+
+                            // Same as invalid arguments: tell the LLM what went wrong so it can retry
+                            // with one of the tools it actually has
+                            let error_response = json!({
+                                "error": format!("Function not found: {}", function_name),
+                                "closest_valid_tools": closest_tools,
+                                "hint": "Only call the functions that are listed in the available tools.",
+                            })
+                            .to_string();
+
+                            let mut function_call_with_error = function_call.clone();
+                            function_call_with_error.response = Some(error_response.clone());
+                            tool_calls_history.push(function_call_with_error);
+
+                            iteration_function_responses.push(ToolCallFunctionResponse {
+                                function_call: function_call.clone(),
+                                response: error_response,
+                            });
+                            should_retry = true;
+                        }
+                        Err(LLMProviderError::JobCancelled) => {
+                            let mut cancelled_function_call = function_call.clone();
+                            cancelled_function_call.tool_router_key =
+                                zoo_tool.map(|tool| tool.tool_router_key().to_string_without_version());
+                            let _ = send_tool_ws_update_with_status(
+                                &ws_manager_trait,
+                                inbox_name.clone(),
+                                &cancelled_function_call,
+                                None,
+                                Some(ToolStatusType::Incomplete),
+                            )
+                            .await;
+                        }
+                        Err(LLMProviderError::ToolRouterError(ref error_msg))
+                            if error_msg.contains("Invalid function arguments") =>
+                        {
+                            if let Some(ref msg_id) = message_hash_id {
+                                let trace_info = json!({
+                                    "error": error_msg,
+                                    "function": function_call.name
+                                });
+                                if let Err(e) = db.add_tracing(
+                                    msg_id,
+                                    inbox_name.as_ref().map(|i| i.get_value()).as_deref(),
+                                    "tool_error_invalid_arguments",
+                                    &trace_info,
+                                ) {
+                                    zoo_log(
+                                        ZooLogOption::JobExecution,
+                                        ZooLogLevel::Error,
+                                        &format!("Failed to add tool error trace: {:?}", e),
+                                    );
                                 }
+                            }
+
+                            // For invalid arguments, we'll retry with the LLM by including the error
+                            // message in the next prompt to help it fix the parameters
+                            let mut function_call_with_error = function_call.clone();
+                            function_call_with_error.response = Some(error_msg.clone());
+                            tool_calls_history.push(function_call_with_error);
+
+                            // Store the error response to be included in the next prompt
+                            iteration_function_responses.push(ToolCallFunctionResponse {
+                                function_call: function_call.clone(),
+                                response: error_msg.clone(),
+                            });
+                            should_retry = true;
+                        }
+                        Err(e) => {
+                            match &e {
                                 LLMProviderError::ToolRouterError(ref error_msg)
                                     if error_msg.contains("MissingConfigError") =>
                                 {
@@ -1194,81 +1448,53 @@ impl GenericInferenceChain {
                                             "tool_error_missing_config",
                                             &trace_info,
                                         ) {
-                                            eprintln!("failed to add tool error trace: {:?}", e);
+                                            zoo_log(
+                                                ZooLogOption::JobExecution,
+                                                ZooLogLevel::Error,
+                                                &format!("Failed to add tool error trace: {:?}", e),
+                                            );
                                         }
                                     }
 
                                     // For missing config, we'll pass through the error directly
                                     // This will show up in the UI prompting the user to update their config
-                                    eprintln!("Missing config error: {:?}", error_msg);
-                                    return Err(e);
+                                    zoo_log(
+                                        ZooLogOption::JobExecution,
+                                        ZooLogLevel::Error,
+                                        &format!("Missing config error: {:?}", error_msg),
+                                    );
                                 }
                                 _ => {
-                                    eprintln!("Error calling function: {:?}", e);
-                                    return Err(e);
+                                    zoo_log(
+                                        ZooLogOption::JobExecution,
+                                        ZooLogLevel::Error,
+                                        &format!("Error calling function: {:?}", e),
+                                    );
                                 }
                             }
-                        }
-                    };
-                    if let Some(ref msg_id) = message_hash_id {
-                        let trace_info = json!({
-                            "response": function_response.response,
-                            "function": function_call.name
-                        });
-                        if let Err(e) = db.add_tracing(
-                            msg_id,
-                            inbox_name.as_ref().map(|i| i.get_value()).as_deref(),
-                            "tool_response",
-                            &trace_info,
-                        ) {
-                            eprintln!("failed to add tool response trace: {:?}", e);
-                        }
-                    }
-
-                    // Calculate safe token limit for function responses (reserve space for context)
-                    let provider_interface = match &llm_provider {
-                        ProviderOrAgent::LLMProvider(provider) => provider.model.clone(),
-                        ProviderOrAgent::Agent(agent) => {
-                            // For agents, we need to get the underlying LLM provider's model
-                            let llm_provider =
-                                db.get_llm_provider(&agent.llm_provider_id, &agent.full_identity_name)
-                                    .map_err(|_e| LLMProviderError::AgentNotFound(agent.llm_provider_id.clone()))?;
-                            match llm_provider {
-                                Some(provider) => provider.model,
-                                None => return Err(LLMProviderError::AgentNotFound(agent.llm_provider_id.clone())),
+                            if first_error.is_none() {
+                                first_error = Some(e);
                             }
                         }
-                    };
-
-                    let max_input_tokens = ModelCapabilitiesManager::get_max_input_tokens(&provider_interface);
-                    let max_tokens_for_response = ((max_input_tokens as f64 * 0.9) as usize).max(1024); // Allow 90% of the context window, minimum 1024 tokens
-                    let response_tokens = count_tokens_from_message_llama3(&function_response.response);
-                    if response_tokens > max_tokens_for_response {
-                        function_response.response = json!({
-                            "max_tokens_for_response": max_tokens_for_response,
-                            "max_input_tokens": max_input_tokens,
-                            "response_tokens": response_tokens,
-                            "response": "IMPORTANT: Function response exceeded model context window, try again with a smaller response or a more capable model.",
-                        }).to_string();
                     }
+                }
 
-                    let mut function_call_with_router_key = function_call.clone();
-                    function_call_with_router_key.tool_router_key =
-                        Some(zoo_tool.tool_router_key().to_string_without_version());
-                    function_call_with_router_key.response = Some(function_response.response.clone());
-                    tool_calls_history.push(function_call_with_router_key);
-
-                    // Trigger WS update after receiving function_response
-                    Self::trigger_ws_update(
-                        &ws_manager_trait,
-                        &Some(full_job.job_id.clone()),
-                        &function_response,
-                        zoo_tool.tool_router_key().to_string_without_version(),
-                    )
-                    .await;
+                if job_token.is_cancelled() {
+                    return Ok(Self::cancelled_inference_result(
+                        &db,
+                        &message_hash_id,
+                        &inbox_name,
+                        iteration_count,
+                        &all_llm_messages,
+                        &all_reasoning_content,
+                        start_time,
+                        tool_calls_history,
+                        all_generated_files,
+                    ));
+                }
 
-                    // Store all function responses to use in the next prompt
-                    iteration_function_responses.push(function_response);
+                if let Some(e) = first_error {
+                    return Err(e);
                 }
 
                 let additional_files = Self::get_additional_files(
@@ -1279,14 +1505,38 @@ impl GenericInferenceChain {
                     merged_fs_folder_paths.clone(),
                 )?;
 
+                // Keep this turn's responses, including the ones of the calls that worked, so
+                // the next turns don't run them again
+                all_function_responses.extend(iteration_function_responses);
+
                 // If we need to retry, continue the outer loop
                 if should_retry {
+                    // Update prompt with error information for retry
+                    filled_prompt = JobPromptGenerator::generic_inference_prompt(
+                        db.clone(),
+                        custom_system_prompt.clone(),
+                        custom_prompt.clone(),
+                        user_message.clone(),
+                        image_files.clone(),
+                        video_files.clone(),
+                        audio_files.clone(),
+                        ret_nodes.clone(),
+                        conversation_memory.summary.clone(),
+                        conversation_memory.recalled_messages.clone(),
+                        conversation_memory.agent_memories.clone(),
+                        Some(conversation_memory.step_history.clone()),
+                        tools.clone(),
+                        // Pass all function responses (including the errors) to keep context
+                        Some(all_function_responses.clone()),
+                        full_job.job_id.clone(),
+                        additional_files,
+                    )
+                    .await;
+
+                    iteration_count += 1;
                     continue;
                 }
 
-                // Add this iteration's responses to our cumulative collection
-                all_function_responses.extend(iteration_function_responses);
-
                 // Call LLM again with ALL responses from all iterations
                 filled_prompt = JobPromptGenerator::generic_inference_prompt(
                     db.clone(),
@@ -1297,8 +1547,10 @@ impl GenericInferenceChain {
                     video_files.clone(),
                     audio_files.clone(),
                     ret_nodes.clone(),
-                    None,
-                    Some(full_job.step_history.clone()),
+                    conversation_memory.summary.clone(),
+                    conversation_memory.recalled_messages.clone(),
+                    conversation_memory.agent_memories.clone(),
+                    Some(conversation_memory.step_history.clone()),
                     tools.clone(),
                     Some(all_function_responses.clone()),
                     full_job.job_id.clone(),
@@ -1329,6 +1581,76 @@ impl GenericInferenceChain {
         }
     }
 
+    /// The answer of a job stopped by the user: what the LLM said in the iterations that ran. The
+    /// iterations that would have followed are traced as cancelled.
+    #[allow(clippy::too_many_arguments)]
+    fn cancelled_inference_result(
+        db: &SqliteManager,
+        message_hash_id: &Option<String>,
+        inbox_name: &Option<InboxName>,
+        iteration_count: u64,
+        all_llm_messages: &[String],
+        all_reasoning_content: &[String],
+        start_time: Instant,
+        tool_calls_history: Vec<FunctionCall>,
+        generated_files: Vec<ZooPath>,
+    ) -> InferenceChainResult {
+        zoo_log(
+            ZooLogOption::JobExecution,
+            ZooLogLevel::Info,
+            &format!("Job stopped by user request after {} iterations", iteration_count),
+        );
+        if let Some(ref msg_id) = message_hash_id {
+            let trace_info = json!({
+                "iteration": iteration_count,
+                "tool_calls": tool_calls_history.len(),
+            });
+            if let Err(e) = db.add_tracing(
+                msg_id,
+                inbox_name.as_ref().map(|i| i.get_value()).as_deref(),
+                "job_cancelled",
+                &trace_info,
+            ) {
+                zoo_log(
+                    ZooLogOption::JobExecution,
+                    ZooLogLevel::Error,
+                    &format!("Failed to add cancellation trace: {:?}", e),
+                );
+            }
+        }
+
+        let conversation = all_llm_messages
+            .iter()
+            .map(|msg| msg.trim())
+            .filter(|msg| !msg.is_empty())
+            .collect::<Vec<&str>>()
+            .join("\n\n");
+
+        InferenceChainResult::with_full_details(
+            conversation,
+            Some(all_reasoning_content.join("\n\n")),
+            None,
+            Some(format!("{:.2}", start_time.elapsed().as_millis())),
+            Some(tool_calls_history),
+            generated_files,
+        )
+    }
+
+    /// Reads how many tool calls of a single LLM turn may run at the same time.
+    /// Try first as u64, then as String (in case it's stored as a string)
+    fn max_parallel_tool_calls(db: &SqliteManager) -> usize {
+        let value = match db.get_preference::<u64>("max_parallel_tool_calls") {
+            Ok(Some(value)) => value,
+            _ => match db.get_preference::<String>("max_parallel_tool_calls") {
+                Ok(Some(str_value)) => str_value
+                    .parse::<u64>()
+                    .unwrap_or(DEFAULT_MAX_PARALLEL_TOOL_CALLS as u64),
+                _ => DEFAULT_MAX_PARALLEL_TOOL_CALLS as u64,
+            },
+        };
+        (value as usize).max(1)
+    }
+
     /// Triggers a WebSocket update after receiving a function response.
     async fn trigger_ws_update(
         ws_manager_trait: &Option<Arc<Mutex<dyn WSUpdateHandler + Send>>>,
@@ -1408,3 +1730,31 @@ impl GenericInferenceChain {
         Ok(additional_files)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_parallel_tool_calls_keep_their_order() {
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+        let (running, max_running) = (&running, &max_running);
+
+        // The first calls take the longest, so they finish last
+        let calls = vec![("first", 60), ("second", 30), ("third", 10), ("fourth", 0)];
+        let results = run_in_call_order(calls, 3, |(name, delay_ms)| async move {
+            let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now_running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+            name
+        })
+        .await;
+
+        assert_eq!(results, vec!["first", "second", "third", "fourth"]);
+        assert_eq!(max_running.load(Ordering::SeqCst), 3);
+    }
+}
@@ -16,7 +16,9 @@ use std::sync::Arc;
 
 impl JobPromptGenerator {
     /// A basic generic prompt generator
-    /// summary_text is the content generated by an LLM on parsing (if exist)
+    /// conversation_summary and recalled_messages stand in for the summarized part of job_step_history
+    /// when the job memory strategy summarizes. agent_memories are the memories of the agent related
+    /// to the user message
     #[allow(clippy::too_many_arguments)]
     pub async fn generic_inference_prompt(
         db: Arc<SqliteManager>,
@@ -27,7 +29,9 @@ impl JobPromptGenerator {
         video_files: HashMap<String, String>,
         audio_files: HashMap<String, String>,
         ret_nodes: ZooFileChunkCollection,
-        _summary_text: Option<String>,
+        conversation_summary: Option<String>,
+        recalled_messages: Vec<String>,
+        agent_memories: Vec<String>,
         job_step_history: Option<Vec<ZooMessage>>,
         tools: Vec<ZooTool>,
         function_calls: Option<Vec<ToolCallFunctionResponse>>,
@@ -45,6 +49,44 @@ impl JobPromptGenerator {
 
         let has_ret_nodes = !ret_nodes.is_empty();
 
+        // Add what the agent knows and remembered from its previous jobs
+        if !agent_memories.is_empty() {
+            prompt.add_content(
+                format!(
+                    "<agent_memory>\nThings you know or remembered in previous conversations:\n{}\n</agent_memory>\n",
+                    agent_memories
+                        .iter()
+                        .map(|memory| format!("- {}", memory))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+                SubPromptType::ExtraContext,
+                97,
+            );
+        }
+
+        // Add the summary of the older messages, it outlives the messages it replaces
+        if let Some(summary) = conversation_summary {
+            prompt.add_content(
+                format!(
+                    "<conversation_summary>\nSummary of the earlier part of this conversation:\n{}\n</conversation_summary>\n",
+                    summary
+                ),
+                SubPromptType::ExtraContext,
+                98,
+            );
+        }
+        if !recalled_messages.is_empty() {
+            prompt.add_content(
+                format!(
+                    "<recalled_messages>\nEarlier messages of this conversation related to the question:\n{}\n</recalled_messages>\n",
+                    recalled_messages.join("\n---\n")
+                ),
+                SubPromptType::ExtraContext,
+                96,
+            );
+        }
+
         // Add previous messages
         if let Some(step_history) = job_step_history {
             prompt.add_step_history(step_history, 97);
@@ -425,7 +425,7 @@ impl FunctionCall {
 }
 
 /// A struct that holds the response from inference an LLM.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LLMInferenceResponse {
     pub response_string: String,
     pub reasoning_content: Option<String>,
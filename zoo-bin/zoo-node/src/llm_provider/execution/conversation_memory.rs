@@ -0,0 +1,397 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::Arc;
+
+use lazy_static::lazy_static;
+use zoo_message_primitives::schemas::inbox_name::InboxName;
+use zoo_message_primitives::schemas::job::{Job, JobLike};
+use zoo_message_primitives::schemas::job_config::ConversationMemoryStrategy;
+use zoo_message_primitives::schemas::llm_providers::common_agent_llm_provider::ProviderOrAgent;
+use zoo_message_primitives::schemas::message_search::SearchMessagesRequest;
+use zoo_message_primitives::schemas::subprompts::SubPromptType;
+use zoo_message_primitives::zoo_message::zoo_message::ZooMessage;
+use zoo_message_primitives::zoo_utils::utils::count_tokens_from_message_llama3;
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
+use zoo_sqlite::job_memory_manager::JobConversationSummary;
+use zoo_sqlite::message_search_manager::searchable_message_text;
+use zoo_sqlite::SqliteManager;
+
+use super::prompts::general_prompts::JobPromptGenerator;
+use crate::llm_provider::error::LLMProviderError;
+use crate::llm_provider::job_manager::JobManager;
+use crate::llm_provider::llm_stopper::LLMStopper;
+use crate::managers::model_capabilities_manager::ModelCapabilitiesManager;
+
+/// The history goes to the prompt as it is while it takes at most this share of the input tokens
+/// of the model. The rest is left to the system prompt, the tools and the files.
+const HISTORY_SHARE_OF_PROMPT: f64 = 0.5;
+/// The newest messages always go to the prompt as they are, they are never summarized.
+const RECENT_MESSAGES_KEPT: usize = 10;
+/// Once the history is over its budget, the summary waits for this many messages to leave the
+/// recent window, so the LLM isn't called after every turn.
+const MIN_MESSAGES_PER_UPDATE: usize = 6;
+/// Bounds the prompt of a single update. A longer backlog is folded in over several updates.
+const MAX_MESSAGES_PER_UPDATE: usize = 40;
+/// Long messages, like pasted documents, are cut before they are summarized.
+const MAX_SUMMARIZED_MESSAGE_CHARS: usize = 4000;
+const RECALLED_MESSAGES: usize = 4;
+const MAX_RECALLED_MESSAGE_CHARS: usize = 2000;
+const AGENT_MEMORIES_IN_PROMPT: usize = 5;
+
+lazy_static! {
+    /// Jobs whose summary is being updated, so back to back turns don't summarize the same messages twice.
+    static ref SUMMARIES_IN_PROGRESS: std::sync::Mutex<HashSet<String>> = std::sync::Mutex::new(HashSet::new());
+}
+
+/// The conversation history and the agent memories that go into the prompt of a job.
+#[derive(Debug, Clone)]
+pub struct ConversationMemory {
+    pub summary: Option<String>,
+    /// The messages the summary doesn't cover, oldest first.
+    pub step_history: Vec<ZooMessage>,
+    /// Summarized messages related to the new message, brought back by summarize+retrieve.
+    pub recalled_messages: Vec<String>,
+    /// The memories of the agent related to the new message, whatever the strategy.
+    pub agent_memories: Vec<String>,
+}
+
+impl ConversationMemory {
+    /// The strategy of the job config, or else the one of its agent.
+    pub fn strategy(job: &Job, llm_provider: &ProviderOrAgent) -> ConversationMemoryStrategy {
+        let agent_strategy = match llm_provider {
+            ProviderOrAgent::Agent(agent) => agent.config.as_ref().and_then(|config| config.memory_strategy),
+            ProviderOrAgent::LLMProvider(_) => None,
+        };
+        job.config()
+            .and_then(|config| config.memory_strategy)
+            .or(agent_strategy)
+            .unwrap_or_default()
+    }
+
+    /// Replaces the summarized part of the job history with its summary. The whole history is used
+    /// when truncating, while it fits in the prompt of the model, or while the job has no summary
+    /// that matches its current messages.
+    pub async fn load(
+        db: &SqliteManager,
+        job: &Job,
+        llm_provider: &ProviderOrAgent,
+        strategy: ConversationMemoryStrategy,
+        user_message: &str,
+        max_tokens_in_prompt: usize,
+    ) -> Self {
+        let agent_memories = match llm_provider {
+            ProviderOrAgent::Agent(agent) => Self::agent_memories(db, &agent.agent_id, user_message).await,
+            ProviderOrAgent::LLMProvider(_) => Vec::new(),
+        };
+        let full_history = Self {
+            summary: None,
+            step_history: job.step_history.clone(),
+            recalled_messages: Vec::new(),
+            agent_memories: agent_memories.clone(),
+        };
+        if strategy == ConversationMemoryStrategy::Truncate {
+            return full_history;
+        }
+        let history_tokens: usize = job.step_history.iter().map(message_tokens).sum();
+        if history_tokens <= history_token_budget(max_tokens_in_prompt) {
+            return full_history;
+        }
+
+        let summary = match db.get_job_conversation_summary(&job.job_id) {
+            Ok(Some(summary)) => summary,
+            Ok(None) => return full_history,
+            Err(e) => {
+                zoo_log(
+                    ZooLogOption::JobExecution,
+                    ZooLogLevel::Error,
+                    &format!("Failed to load the conversation summary of job {}: {}", job.job_id, e),
+                );
+                return full_history;
+            }
+        };
+        // A retry or an edit branched the conversation before the last summarized message
+        let Some(summarized) = summarized_prefix_len(&job.step_history, &summary.last_message_hash) else {
+            return full_history;
+        };
+
+        let recalled_messages = if strategy == ConversationMemoryStrategy::SummarizeAndRetrieve {
+            Self::recall_messages(db, &job.job_id, &job.step_history[..summarized], user_message).await
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            summary: Some(summary.summary),
+            step_history: job.step_history[summarized..].to_vec(),
+            recalled_messages,
+            agent_memories,
+        }
+    }
+
+    /// The memories of the agent closest to the new message. They persist across the jobs of the agent.
+    async fn agent_memories(db: &SqliteManager, agent_id: &str, user_message: &str) -> Vec<String> {
+        if user_message.trim().is_empty() {
+            return Vec::new();
+        }
+        match db
+            .search_agent_memories(agent_id, user_message, AGENT_MEMORIES_IN_PROMPT)
+            .await
+        {
+            Ok(memories) => memories
+                .iter()
+                .map(|memory| truncate_chars(&memory.content, MAX_RECALLED_MESSAGE_CHARS))
+                .collect(),
+            Err(e) => {
+                zoo_log(
+                    ZooLogOption::JobExecution,
+                    ZooLogLevel::Error,
+                    &format!("Failed to retrieve the memories of agent {}: {}", agent_id, e),
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    /// Searches the job inbox for the new message and keeps the results that are already summarized,
+    /// the others are in the prompt anyway.
+    async fn recall_messages(
+        db: &SqliteManager,
+        job_id: &str,
+        summarized: &[ZooMessage],
+        user_message: &str,
+    ) -> Vec<String> {
+        let Ok(inbox_name) = InboxName::get_job_inbox_name_from_params(job_id.to_string()) else {
+            return Vec::new();
+        };
+        if user_message.trim().is_empty() || summarized.is_empty() {
+            return Vec::new();
+        }
+
+        let request = SearchMessagesRequest {
+            query: user_message.to_string(),
+            inbox_name: Some(inbox_name.to_string()),
+            limit: Some(RECALLED_MESSAGES * 5),
+            semantic: db.message_search_embeddings_enabled(),
+            match_any: true,
+            ..Default::default()
+        };
+        let results = match db.search_messages(&request).await {
+            Ok(results) => results,
+            Err(e) => {
+                zoo_log(
+                    ZooLogOption::JobExecution,
+                    ZooLogLevel::Error,
+                    &format!("Failed to recall the summarized messages of job {}: {}", job_id, e),
+                );
+                return Vec::new();
+            }
+        };
+
+        let summarized_by_hash: HashMap<String, &ZooMessage> = summarized
+            .iter()
+            .map(|message| (message.calculate_message_hash_for_pagination(), message))
+            .collect();
+        results
+            .iter()
+            .filter_map(|result| summarized_by_hash.get(&result.message_hash))
+            .filter_map(|message| searchable_message_text(message))
+            .take(RECALLED_MESSAGES)
+            .map(|text| truncate_chars(&text, MAX_RECALLED_MESSAGE_CHARS))
+            .collect()
+    }
+}
+
+impl JobManager {
+    /// Folds the messages that left the recent window into the rolling summary of the job, once the
+    /// history doesn't fit in its share of the prompt. Runs in the background after each turn of the
+    /// jobs whose memory strategy summarizes, and stops with the job.
+    pub async fn update_conversation_summary(
+        db: Arc<SqliteManager>,
+        job_id: String,
+        llm_provider: ProviderOrAgent,
+        llm_stopper: Arc<LLMStopper>,
+    ) -> Result<(), LLMProviderError> {
+        if !SUMMARIES_IN_PROGRESS.lock().unwrap().insert(job_id.clone()) {
+            return Ok(());
+        }
+        let result = Self::fold_messages_into_summary(db, &job_id, llm_provider, llm_stopper).await;
+        SUMMARIES_IN_PROGRESS.lock().unwrap().remove(&job_id);
+
+        if let Err(e) = &result {
+            zoo_log(
+                ZooLogOption::JobExecution,
+                ZooLogLevel::Error,
+                &format!("Failed to update the conversation summary of job {}: {}", job_id, e),
+            );
+        }
+        result
+    }
+
+    async fn fold_messages_into_summary(
+        db: Arc<SqliteManager>,
+        job_id: &str,
+        llm_provider: ProviderOrAgent,
+        llm_stopper: Arc<LLMStopper>,
+    ) -> Result<(), LLMProviderError> {
+        let token_budget = history_token_budget(provider_max_input_tokens(&db, &llm_provider)?);
+        let inbox_name = InboxName::get_job_inbox_name_from_params(job_id.to_string()).ok();
+        // A stop of the job while the summary runs must still reach it after the job finished
+        let _running = inbox_name
+            .as_ref()
+            .map(|inbox_name| llm_stopper.job_running(&inbox_name.to_string()));
+        let history = db.get_step_history(job_id, true)?.unwrap_or_default();
+        let history_tokens: Vec<usize> = history.iter().map(message_tokens).collect();
+        let stored = db.get_job_conversation_summary(job_id)?;
+        // Starts over when there is no summary yet or the conversation branched before its end
+        let (mut summary, mut summarized) = stored
+            .and_then(|stored| {
+                summarized_prefix_len(&history, &stored.last_message_hash).map(|len| (Some(stored.summary), len))
+            })
+            .unwrap_or((None, 0));
+
+        while let Some(batch) = pending_summary_batch(&history_tokens, summarized, token_budget) {
+            let prompt = JobPromptGenerator::conversation_summary_prompt(
+                summary.clone(),
+                conversation_transcript(&history[batch.clone()]),
+            );
+            let response = Self::inference_with_llm_provider(
+                llm_provider.clone(),
+                prompt,
+                inbox_name.clone(),
+                None,
+                None,
+                llm_stopper.clone(),
+                db.clone(),
+                None,
+            )
+            .await?;
+
+            let new_summary = response.response_string.trim().to_string();
+            if new_summary.is_empty() {
+                break;
+            }
+            summarized = batch.end;
+            db.set_job_conversation_summary(&JobConversationSummary {
+                job_id: job_id.to_string(),
+                summary: new_summary.clone(),
+                last_message_hash: history[summarized - 1].calculate_message_hash_for_pagination(),
+                summarized_messages: summarized as u64,
+                updated_at: chrono::Utc::now().to_rfc3339(),
+            })?;
+            summary = Some(new_summary);
+        }
+        Ok(())
+    }
+}
+
+/// How many messages at the start of `history` the summary covers, if its last message is still there.
+fn summarized_prefix_len(history: &[ZooMessage], last_message_hash: &str) -> Option<usize> {
+    history
+        .iter()
+        .rposition(|message| message.calculate_message_hash_for_pagination() == last_message_hash)
+        .map(|position| position + 1)
+}
+
+/// The input tokens of the model the history can take.
+fn history_token_budget(max_tokens_in_prompt: usize) -> usize {
+    (max_tokens_in_prompt as f64 * HISTORY_SHARE_OF_PROMPT) as usize
+}
+
+/// The model of the provider, or of the provider of the agent, bounds the history.
+fn provider_max_input_tokens(db: &SqliteManager, llm_provider: &ProviderOrAgent) -> Result<usize, LLMProviderError> {
+    let model = match llm_provider {
+        ProviderOrAgent::LLMProvider(provider) => provider.model.clone(),
+        ProviderOrAgent::Agent(agent) => {
+            db.get_llm_provider(&agent.llm_provider_id, &agent.full_identity_name)
+                .map_err(|_e| LLMProviderError::AgentNotFound(agent.llm_provider_id.clone()))?
+                .ok_or_else(|| LLMProviderError::AgentNotFound(agent.llm_provider_id.clone()))?
+                .model
+        }
+    };
+    Ok(ModelCapabilitiesManager::get_max_input_tokens(&model))
+}
+
+fn message_tokens(message: &ZooMessage) -> usize {
+    message
+        .to_prompt()
+        .sub_prompts
+        .iter()
+        .map(|sub_prompt| count_tokens_from_message_llama3(&sub_prompt.get_content()))
+        .sum()
+}
+
+/// The messages the next update folds into the summary: none while the messages the summary doesn't
+/// cover fit in `token_budget`, or until enough of them left the recent window.
+fn pending_summary_batch(message_tokens: &[usize], summarized: usize, token_budget: usize) -> Option<Range<usize>> {
+    if message_tokens[summarized..].iter().sum::<usize>() <= token_budget {
+        return None;
+    }
+    let window_start = message_tokens.len().saturating_sub(RECENT_MESSAGES_KEPT);
+    if window_start < summarized + MIN_MESSAGES_PER_UPDATE {
+        return None;
+    }
+    Some(summarized..window_start.min(summarized + MAX_MESSAGES_PER_UPDATE))
+}
+
+fn conversation_transcript(messages: &[ZooMessage]) -> String {
+    messages
+        .iter()
+        .flat_map(|message| message.to_prompt().sub_prompts)
+        .map(|sub_prompt| {
+            let (prompt_type, _, _) = sub_prompt.extract_generic_subprompt_data();
+            let speaker = match prompt_type {
+                SubPromptType::Assistant => "Assistant",
+                _ => "User",
+            };
+            format!(
+                "{}: {}",
+                speaker,
+                truncate_chars(&sub_prompt.get_content(), MAX_SUMMARIZED_MESSAGE_CHARS)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_summary_batch_waits_for_enough_old_messages() {
+        let over_budget = |len: usize| vec![100; len];
+        // The 10 newest messages stay out of the summary
+        assert_eq!(pending_summary_batch(&over_budget(10), 0, 0), None);
+        assert_eq!(pending_summary_batch(&over_budget(15), 0, 0), None);
+        assert_eq!(pending_summary_batch(&over_budget(16), 0, 0), Some(0..6));
+        assert_eq!(pending_summary_batch(&over_budget(20), 6, 0), None);
+        assert_eq!(pending_summary_batch(&over_budget(22), 6, 0), Some(6..12));
+        // A long backlog is folded in over several updates
+        assert_eq!(pending_summary_batch(&over_budget(100), 0, 0), Some(0..40));
+        assert_eq!(pending_summary_batch(&over_budget(100), 40, 0), Some(40..80));
+        assert_eq!(pending_summary_batch(&over_budget(100), 80, 0), None);
+    }
+
+    #[test]
+    fn test_pending_summary_batch_waits_for_the_token_budget() {
+        // A long conversation is kept as it is while it fits
+        assert_eq!(pending_summary_batch(&[100; 100], 0, 10_000), None);
+        assert_eq!(pending_summary_batch(&[100; 101], 0, 10_000), Some(0..40));
+        // Only the messages the summary doesn't cover count
+        assert_eq!(pending_summary_batch(&[100; 140], 40, 10_000), None);
+        assert_eq!(history_token_budget(128_000), 64_000);
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("héllo", 10), "héllo");
+        assert_eq!(truncate_chars("héllo", 2), "hé…");
+    }
+}
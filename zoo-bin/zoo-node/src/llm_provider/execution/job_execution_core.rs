@@ -1,8 +1,10 @@
 use crate::llm_provider::error::LLMProviderError;
 use crate::llm_provider::execution::chains::inference_chain_trait::InferenceChainResult;
+use crate::llm_provider::execution::conversation_memory::ConversationMemory;
 use crate::llm_provider::job_callback_manager::JobCallbackManager;
 use crate::llm_provider::job_manager::JobManager;
-use crate::llm_provider::llm_stopper::LLMStopper;
+use crate::llm_provider::llm_stopper::{LLMStopper, CANCELLED_DONE_REASON};
+use crate::llm_provider::providers::shared::shared_model_logic::send_ws_update;
 
 use crate::managers::model_capabilities_manager::{ModelCapabilitiesManager, ModelCapability};
 use crate::managers::tool_router::ToolRouter;
@@ -13,7 +15,9 @@ use ed25519_dalek::SigningKey;
 use zoo_embedding::embedding_generator::RemoteEmbeddingGenerator;
 use zoo_fs::zoo_file_manager::ZooFileManager;
 use zoo_job_queue_manager::job_queue_manager::{JobForProcessing, JobQueueManager};
+use zoo_message_primitives::schemas::inbox_name::InboxName;
 use zoo_message_primitives::schemas::job::{Job, JobLike};
+use zoo_message_primitives::schemas::job_config::ConversationMemoryStrategy;
 use zoo_message_primitives::schemas::llm_providers::common_agent_llm_provider::ProviderOrAgent;
 use zoo_message_primitives::schemas::ws_types::WSUpdateHandler;
 use zoo_message_primitives::zoo_message::zoo_message_schemas::{CallbackAction, MessageMetadata};
@@ -55,6 +59,15 @@ impl JobManager {
             &format!("Processing job: {} with JobMessage: {:?}", job_id, job_message),
         );
 
+        // A stop applies to the message that was running when it was requested, not to this one
+        let inbox_name = InboxName::get_job_inbox_name_from_params(job_id.clone()).ok();
+        if let Some(inbox_name) = &inbox_name {
+            llm_stopper.reset(&inbox_name.to_string());
+        }
+        let _running = inbox_name
+            .as_ref()
+            .map(|inbox_name| llm_stopper.job_running(&inbox_name.to_string()));
+
         // Fetch data we need to execute job step
         let fetch_data_result = JobManager::fetch_relevant_job_data(&job_message.job_message.job_id, db.clone()).await;
         let (full_job, llm_provider_found, _, user_profile) = match fetch_data_result {
@@ -107,6 +120,23 @@ impl JobManager {
             return Self::handle_error(&db, Some(user_profile), &job_id, &identity_secret_key, e, ws_manager).await;
         }
 
+        // Let the UI know the job ended because it was stopped
+        if let Some(inbox_name) = inbox_name {
+            if llm_stopper.should_stop(&inbox_name.to_string()) {
+                let _ = send_ws_update(
+                    &ws_manager,
+                    Some(inbox_name.clone()),
+                    &job_id,
+                    String::new(),
+                    false,
+                    true,
+                    Some(CANCELLED_DONE_REASON.to_string()),
+                )
+                .await;
+                llm_stopper.reset(&inbox_name.to_string());
+            }
+        }
+
         Ok(job_id)
     }
 
@@ -322,6 +352,12 @@ impl JobManager {
             &format!("Retrieved {} image files", image_files.len()),
         );
 
+        // Jobs whose memory strategy summarizes update their summary once the response is stored
+        let summarizing_llm_provider = llm_provider_found
+            .as_ref()
+            .filter(|provider| ConversationMemory::strategy(&full_job, provider) != ConversationMemoryStrategy::Truncate)
+            .cloned();
+
         let start = Instant::now();
 
         // Call the inference chain router to choose which chain to use, and call it
@@ -398,6 +434,16 @@ impl JobManager {
         db.add_message_to_job_inbox(&job_message.job_id.clone(), &zoo_message, None, ws_manager)
             .await?;
 
+        if let Some(llm_provider) = summarizing_llm_provider {
+            let db_clone = db.clone();
+            let job_id_clone = job_id.clone();
+            let llm_stopper = llm_stopper.clone();
+            tokio::spawn(async move {
+                let _ =
+                    JobManager::update_conversation_summary(db_clone, job_id_clone, llm_provider, llm_stopper).await;
+            });
+        }
+
         // Check for callbacks and add them to the JobManagerQueue if required
         if let Some(callback) = &job_message.callback {
             if let CallbackAction::ImplementationCheck(tool_type, available_tools) = callback.as_ref() {
@@ -1,7 +1,7 @@
 use super::chains::inference_chain_trait::LLMInferenceResponse;
 use crate::llm_provider::error::LLMProviderError;
 use crate::llm_provider::job_manager::JobManager;
-use crate::llm_provider::llm_provider::LLMProvider;
+use crate::llm_provider::llm_response_cache::inference_with_cache;
 use crate::llm_provider::llm_stopper::LLMStopper;
 use zoo_message_primitives::schemas::inbox_name::InboxName;
 use zoo_message_primitives::schemas::job::Job;
@@ -33,17 +33,19 @@ impl JobManager {
         let prompt_cloned = filled_prompt.clone();
 
         let task_response = tokio::spawn(async move {
-            let llm_provider = LLMProvider::from_provider_or_agent(llm_provider_cloned, db.clone()).await?;
-            llm_provider
-                .inference(
-                    prompt_cloned,
-                    inbox_name,
-                    ws_manager_trait,
-                    config,
-                    llm_stopper,
-                    tracing_message_id,
-                )
-                .await
+            // Goes through the response cache (if enabled), then retries and provider fallbacks are
+            // driven by the node's LLMFallbackConfig
+            inference_with_cache(
+                llm_provider_cloned,
+                prompt_cloned,
+                inbox_name,
+                ws_manager_trait,
+                config,
+                llm_stopper,
+                db,
+                tracing_message_id,
+            )
+            .await
         })
         .await;
 
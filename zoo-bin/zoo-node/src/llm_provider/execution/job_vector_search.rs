@@ -85,13 +85,6 @@ impl JobManager {
         let mut total_tokens: i64 = 0;
         let mut all_files_have_token_count = true;
 
-        let query_embedding = match embedding_generator.generate_embedding_default(&query_text).await {
-            Ok(embedding) => embedding,
-            Err(e) => {
-                return Err(SqliteManagerError::SomeError(e.to_string()));
-            }
-        };
-
         // Process fs_files_paths
         for path in &fs_files_paths {
             if let Some(parsed_file) = sqlite_manager.get_parsed_file_by_zoo_path(path).unwrap() {
@@ -170,7 +163,8 @@ impl JobManager {
         }
 
         // Determine the vector search mode configured in the job scope.
-        let max_tokens_in_prompt = if scope.vector_search_mode == VectorSearchMode::FillUpTo25k {
+        let search_mode = &scope.vector_search_mode;
+        let max_tokens_in_prompt = if search_mode.fills_prompt() {
             if max_tokens_in_prompt > 60000 {
                 60000
             } else if max_tokens_in_prompt > 25000 {
@@ -183,8 +177,8 @@ impl JobManager {
         };
 
         // If we have token counts for all files and they fit within the limit,
-        // we can include all chunks from all files
-        if all_files_have_token_count && total_tokens <= max_tokens_in_prompt as i64 {
+        // we can include all chunks from all files (unless only the top chunks were requested)
+        if search_mode.fills_prompt() && all_files_have_token_count && total_tokens <= max_tokens_in_prompt as i64 {
             let mut all_chunks = Vec::new();
             for file_id in parsed_file_ids {
                 let file_chunks = sqlite_manager.get_chunks_for_parsed_file(file_id)?;
@@ -197,8 +191,36 @@ impl JobManager {
             });
         }
 
-        // Perform a vector search on all parsed files
-        let search_results = sqlite_manager.search_chunks(&parsed_file_ids, query_embedding, num_of_top_results)?;
+        let query_embedding = if search_mode.uses_embeddings() {
+            match embedding_generator.generate_embedding_default(&query_text).await {
+                Ok(embedding) => Some(embedding),
+                Err(e) => {
+                    return Err(SqliteManagerError::SomeError(e.to_string()));
+                }
+            }
+        } else {
+            None
+        };
+
+        // Rank the chunks of all parsed files as configured by the search mode
+        let search_results = match (search_mode, query_embedding) {
+            (VectorSearchMode::KeywordOnly, _) => {
+                sqlite_manager.search_chunks_by_keywords(&parsed_file_ids, &query_text, num_of_top_results)?
+            }
+            (VectorSearchMode::Hybrid, Some(query_embedding)) => sqlite_manager.search_chunks_hybrid(
+                &parsed_file_ids,
+                &query_text,
+                query_embedding,
+                num_of_top_results,
+            )?,
+            (VectorSearchMode::TopK(top_k), Some(query_embedding)) => {
+                sqlite_manager.search_chunks_hybrid(&parsed_file_ids, &query_text, query_embedding, (*top_k).max(1))?
+            }
+            (_, Some(query_embedding)) => {
+                sqlite_manager.search_chunks(&parsed_file_ids, query_embedding, num_of_top_results)?
+            }
+            (_, None) => Vec::new(),
+        };
 
         // If there are no initial results, just return early
         if search_results.is_empty() {
@@ -209,6 +231,14 @@ impl JobManager {
             });
         }
 
+        // Only the best chunks were requested, so there is no need to add their neighbors
+        if !search_mode.fills_prompt() {
+            return Ok(ZooFileChunkCollection {
+                chunks: search_results.into_iter().map(|(chunk, _score)| chunk).collect(),
+                paths: Some(paths_map),
+            });
+        }
+
         // Count the total number of characters in the search results using map-reduce
         let total_characters: usize = search_results
             .iter()
@@ -1,4 +1,5 @@
 pub mod chains;
+pub mod conversation_memory;
 pub mod job_execution_core;
 pub mod job_execution_helpers;
 pub mod job_scope_helpers;
@@ -32,6 +32,37 @@ impl JobPromptGenerator {
         prompt
     }
 
+    /// Prompt for folding older messages of a conversation into its rolling summary
+    pub fn conversation_summary_prompt(previous_summary: Option<String>, transcript: String) -> Prompt {
+        let mut prompt = Prompt::new();
+        prompt.add_content(
+            "You maintain the memory of a long conversation between a user and an assistant. Write a concise summary that keeps the goals of the user, the facts, decisions and conclusions reached, and the open questions. Leave out greetings and repetition. Only answer with the summary.".to_string(),
+            SubPromptType::System,
+            100,
+        );
+
+        if let Some(previous_summary) = previous_summary {
+            prompt.add_content(
+                format!("Summary of the conversation so far:\n{}", previous_summary),
+                SubPromptType::User,
+                100,
+            );
+        }
+        prompt.add_content(
+            format!("Messages that followed:\n{}", transcript),
+            SubPromptType::User,
+            100,
+        );
+        prompt.add_content(
+            "Write the updated summary of the whole conversation, including what the previous summary already covered."
+                .to_string(),
+            SubPromptType::User,
+            100,
+        );
+
+        prompt
+    }
+
     /// Prompt for having the description of a cron translated to a cron expression
     pub fn image_to_text_analysis(description: String, image: String) -> Prompt {
         let mut prompt = Prompt::new();
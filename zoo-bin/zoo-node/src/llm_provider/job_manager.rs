@@ -9,7 +9,7 @@ use ed25519_dalek::SigningKey;
 use futures::Future;
 use zoo_embedding::embedding_generator::RemoteEmbeddingGenerator;
 use zoo_fs::zoo_file_manager::ZooFileManager;
-use zoo_job_queue_manager::job_queue_manager::{JobForProcessing, JobQueueManager};
+use zoo_job_queue_manager::job_queue_manager::{JobForProcessing, JobQueueManager, LeaseKeeper};
 use zoo_message_primitives::schemas::inbox_name::InboxName;
 use zoo_message_primitives::schemas::job::JobLike;
 use zoo_message_primitives::schemas::ws_types::WSUpdateHandler;
@@ -27,9 +27,15 @@ use std::result::Result::Ok;
 use std::sync::Weak;
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinError;
+use tokio::time::{Duration, MissedTickBehavior};
 
 const NUM_THREADS: usize = 4;
 
+/// How often the idle job loop looks for items queued again or whose lease expired, which aren't
+/// announced to the subscribers.
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
 pub trait JobManagerTrait {
     fn create_job<'a>(
         &'a mut self,
@@ -219,32 +225,17 @@ impl JobManager {
                 ZooLogLevel::Info,
                 "Starting job queue processing loop",
             );
+            let mut poll_interval = tokio::time::interval(QUEUE_POLL_INTERVAL);
+            poll_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
             loop {
-                // 1) Pull any immediate jobs if available
-                let immediate_jobs_to_process: Vec<(String, JobForProcessing)> = {
-                    let mut processing_lock = processing_jobs.lock().await;
-                    let mgr = queue_immediate.lock().await;
-                    let all_imm = mgr.get_all_elements_interleave().await.unwrap_or_else(|_| vec![]);
-                    drop(mgr);
-
-                    all_imm
-                        .into_iter()
-                        .filter_map(|job| {
-                            let jid = job.job_message.job_id.clone();
-                            if !processing_lock.contains(&jid) {
-                                processing_lock.insert(jid.clone());
-                                Some((jid, job))
-                            } else {
-                                None
-                            }
-                        })
-                        .collect()
-                };
+                // 1) Lease any immediate jobs if available
+                let immediate_jobs_to_process =
+                    Self::lease_jobs(&queue_immediate, &processing_jobs, max_parallel_jobs * 2).await;
 
                 if !immediate_jobs_to_process.is_empty() {
                     // 1A) Spawn all immediate jobs
-                    for (job_id, job) in immediate_jobs_to_process {
+                    for (job_id, item_id, job, lease) in immediate_jobs_to_process {
                         let permit = immediate_semaphore.clone().acquire_owned().await.unwrap();
                         let job_processing_fn = Arc::clone(&job_processing_fn);
                         let db_clone = db_clone.clone();
@@ -261,7 +252,8 @@ impl JobManager {
                         let in_progress = processing_jobs.clone();
 
                         tokio::spawn(async move {
-                            let _ = (job_processing_fn)(
+                            // A job that panics is retried like one whose node crashed
+                            let result = tokio::spawn((job_processing_fn)(
                                 job,
                                 db_clone,
                                 node_profile_name,
@@ -274,10 +266,11 @@ impl JobManager {
                                 my_agent_payments_manager,
                                 ext_agent_payments_manager,
                                 llm_stopper,
-                            )
+                            ))
                             .await;
 
-                            let _ = queue_immediate.lock().await.dequeue(&job_id).await;
+                            Self::finish_leased_job(&queue_immediate, item_id, &result).await;
+                            drop(lease);
                             let mut inprog = in_progress.lock().await;
                             inprog.remove(&job_id);
                             drop(permit);
@@ -287,27 +280,9 @@ impl JobManager {
                     continue;
                 }
 
-                // 2) If no immediate jobs, let's gather normal jobs
-                let normal_jobs_to_process: Vec<(String, JobForProcessing)> = {
-                    let mut processing_lock = processing_jobs.lock().await;
-                    let mgr = queue_normal.lock().await;
-                    let all_norm = mgr.get_all_elements_interleave().await.unwrap_or_else(|_| vec![]);
-                    drop(mgr);
-
-                    all_norm
-                        .into_iter()
-                        .filter_map(|job| {
-                            let jid = job.job_message.job_id.clone();
-                            if !processing_lock.contains(&jid) {
-                                processing_lock.insert(jid.clone());
-                                Some((jid, job))
-                            } else {
-                                None
-                            }
-                        })
-                        .take(max_parallel_jobs)
-                        .collect()
-                };
+                // 2) If no immediate jobs, let's lease normal jobs
+                let normal_jobs_to_process =
+                    Self::lease_jobs(&queue_normal, &processing_jobs, max_parallel_jobs).await;
 
                 if normal_jobs_to_process.is_empty() {
                     // 2A) Wait for any new job events from either queue
@@ -336,11 +311,12 @@ impl JobManager {
                                 break;
                             }
                         }
+                        _ = poll_interval.tick() => {}
                     }
                 } else {
                     // 2B) We have normal jobs; but we check again for immediate jobs while
                     // waiting for the normal semaphore
-                    for (job_id, job) in normal_jobs_to_process {
+                    for (job_id, item_id, job, lease) in normal_jobs_to_process {
                         loop {
                             tokio::select! {
                                 permit = normal_semaphore.clone().acquire_owned() => {
@@ -360,7 +336,8 @@ impl JobManager {
                                     let in_progress = processing_jobs.clone();
 
                                     tokio::spawn(async move {
-                                        (job_processing_fn)(
+                                        // A job that panics is retried like one whose node crashed
+                                        let result = tokio::spawn((job_processing_fn)(
                                             job,
                                             db_clone,
                                             node_profile_name,
@@ -373,10 +350,11 @@ impl JobManager {
                                             my_agent_payments_manager,
                                             ext_agent_payments_manager,
                                             llm_stopper,
-                                        )
+                                        ))
                                         .await;
 
-                                        let _ = queue_normal.lock().await.dequeue(&job_id).await;
+                                        Self::finish_leased_job(&queue_normal, item_id, &result).await;
+                                        drop(lease);
                                         let mut inprog = in_progress.lock().await;
                                         inprog.remove(&job_id);
                                         drop(permit);
@@ -389,50 +367,55 @@ impl JobManager {
                                 maybe_imm = rx_immediate.recv() => {
                                     // A new immediate job arrived
                                     if let Some(imm_job) = maybe_imm {
-                                        let imm_id = imm_job.job_message.job_id.clone();
                                         zoo_log(
                                             ZooLogOption::JobExecution,
                                             ZooLogLevel::Info,
-                                            &format!("Received new immediate job {:?} while waiting for normal job permit", imm_id),
+                                            &format!("Received new immediate job {:?} while waiting for normal job permit", imm_job.job_message.job_id),
                                         );
 
-                                        let permit = immediate_semaphore.clone().acquire_owned().await.unwrap();
-                                        let job_processing_fn = Arc::clone(&job_processing_fn);
-                                        let db_clone = db_clone.clone();
-                                        let node_profile_name = node_profile_name.clone();
-                                        let identity_sk = clone_signature_secret_key(&identity_sk);
-                                        let generator = generator.clone();
-                                        let ws_manager = ws_manager.clone();
-                                        let tool_router = tool_router.clone();
-                                        let callback_manager = callback_manager.clone();
-                                        let queue_immediate = queue_immediate.clone();
-                                        let my_agent_payments_manager = my_agent_payments_manager.clone();
-                                        let ext_agent_payments_manager = ext_agent_payments_manager.clone();
-                                        let llm_stopper = llm_stopper.clone();
-                                        let in_progress = processing_jobs.clone();
-
-                                        tokio::spawn(async move {
-                                            (job_processing_fn)(
-                                                imm_job,
-                                                db_clone,
-                                                node_profile_name,
-                                                identity_sk,
-                                                generator,
-                                                ws_manager,
-                                                tool_router,
-                                                callback_manager,
-                                                queue_immediate.clone(),
-                                                my_agent_payments_manager,
-                                                ext_agent_payments_manager,
-                                                llm_stopper,
-                                            )
-                                            .await;
-
-                                            let _ = queue_immediate.lock().await.dequeue(&imm_id).await;
-                                            let mut inprog = in_progress.lock().await;
-                                            inprog.remove(&imm_id);
-                                            drop(permit);
-                                        });
+                                        let leased_immediate =
+                                            Self::lease_jobs(&queue_immediate, &processing_jobs, max_parallel_jobs * 2).await;
+                                        for (imm_id, imm_item_id, imm_job, imm_lease) in leased_immediate {
+                                            let permit = immediate_semaphore.clone().acquire_owned().await.unwrap();
+                                            let job_processing_fn = Arc::clone(&job_processing_fn);
+                                            let db_clone = db_clone.clone();
+                                            let node_profile_name = node_profile_name.clone();
+                                            let identity_sk = clone_signature_secret_key(&identity_sk);
+                                            let generator = generator.clone();
+                                            let ws_manager = ws_manager.clone();
+                                            let tool_router = tool_router.clone();
+                                            let callback_manager = callback_manager.clone();
+                                            let queue_immediate = queue_immediate.clone();
+                                            let my_agent_payments_manager = my_agent_payments_manager.clone();
+                                            let ext_agent_payments_manager = ext_agent_payments_manager.clone();
+                                            let llm_stopper = llm_stopper.clone();
+                                            let in_progress = processing_jobs.clone();
+
+                                            tokio::spawn(async move {
+                                                // A job that panics is retried like one whose node crashed
+                                                let result = tokio::spawn((job_processing_fn)(
+                                                    imm_job,
+                                                    db_clone,
+                                                    node_profile_name,
+                                                    identity_sk,
+                                                    generator,
+                                                    ws_manager,
+                                                    tool_router,
+                                                    callback_manager,
+                                                    queue_immediate.clone(),
+                                                    my_agent_payments_manager,
+                                                    ext_agent_payments_manager,
+                                                    llm_stopper,
+                                                ))
+                                                .await;
+
+                                                Self::finish_leased_job(&queue_immediate, imm_item_id, &result).await;
+                                                drop(imm_lease);
+                                                let mut inprog = in_progress.lock().await;
+                                                inprog.remove(&imm_id);
+                                                drop(permit);
+                                            });
+                                        }
                                     } else {
                                         eprintln!("rx_immediate closed, shutting down...");
                                         return;
@@ -455,6 +438,64 @@ impl JobManager {
         })
     }
 
+    /// Leases up to `limit` jobs from `queue`, skipping the ones still running in this process (a
+    /// job whose lease expired while it was running). The lease of each job is renewed until its
+    /// `LeaseKeeper` is dropped, once the job is finished.
+    async fn lease_jobs(
+        queue: &Arc<Mutex<JobQueueManager<JobForProcessing>>>,
+        processing_jobs: &Arc<Mutex<HashSet<String>>>,
+        limit: usize,
+    ) -> Vec<(String, i64, JobForProcessing, LeaseKeeper)> {
+        let mut processing_lock = processing_jobs.lock().await;
+        let queue = queue.lock().await;
+        let leased = match queue.lease(limit).await {
+            Ok(leased) => leased,
+            Err(e) => {
+                zoo_log(
+                    ZooLogOption::JobExecution,
+                    ZooLogLevel::Error,
+                    &format!("Failed to lease jobs from the queue: {}", e),
+                );
+                return vec![];
+            }
+        };
+
+        leased
+            .into_iter()
+            .filter_map(|item| {
+                let jid = item.value.job_message.job_id.clone();
+                if processing_lock.insert(jid.clone()) {
+                    Some((jid, item.id, item.value, queue.keep_lease_alive(item.id)))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Releases the lease of a processed job. A job that failed already posted its error to the job
+    /// inbox, so it's dead lettered instead of being run (and posting it) again. Only a job that
+    /// panicked is leased again later, until it used the attempts of the queue.
+    async fn finish_leased_job(
+        queue: &Arc<Mutex<JobQueueManager<JobForProcessing>>>,
+        item_id: i64,
+        result: &Result<Result<String, LLMProviderError>, JoinError>,
+    ) {
+        let queue = queue.lock().await;
+        let finished = match result {
+            Ok(Ok(_)) => queue.complete(item_id).await,
+            Ok(Err(e)) => queue.dead_letter(item_id, &e.to_string()).await,
+            Err(e) => queue.fail(item_id, &e.to_string()).await.map(|_| ()),
+        };
+        if let Err(e) = finished {
+            zoo_log(
+                ZooLogOption::JobExecution,
+                ZooLogLevel::Error,
+                &format!("Failed to release the lease of job queue item {}: {}", item_id, e),
+            );
+        }
+    }
+
     pub async fn process_job_message(
         &mut self,
         message: ZooMessage,
@@ -0,0 +1,512 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::json;
+use tokio::sync::Mutex;
+use zoo_message_primitives::schemas::inbox_name::InboxName;
+use zoo_message_primitives::schemas::job_config::JobConfig;
+use zoo_message_primitives::schemas::llm_providers::common_agent_llm_provider::ProviderOrAgent;
+use zoo_message_primitives::schemas::llm_providers::llm_fallback::{LLMErrorClass, LLMFallbackConfig};
+use zoo_message_primitives::schemas::llm_providers::serialized_llm_provider::LLMProviderInterface;
+use zoo_message_primitives::schemas::prompts::Prompt;
+use zoo_message_primitives::schemas::ws_types::{WSMessageType, WSTopic, WSUpdateHandler};
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
+use zoo_sqlite::SqliteManager;
+
+use super::error::LLMProviderError;
+use super::execution::chains::inference_chain_trait::LLMInferenceResponse;
+use super::llm_provider::LLMProvider;
+use super::llm_stopper::LLMStopper;
+use super::usage_budget::{enforce_usage_budgets, record_usage, UsageContext};
+
+/// How long a stopped inference may take to hand back what it streamed so far before it's dropped.
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// Sorts an inference error into the class used to pick its retry rule. Most providers only give
+/// us the error message, so besides the typed errors this falls back to looking for well known
+/// phrases in it.
+pub fn classify_inference_error(error: &LLMProviderError) -> LLMErrorClass {
+    match error {
+        LLMProviderError::ReqwestError(err) => {
+            if err.is_timeout() || err.is_connect() {
+                return LLMErrorClass::Timeout;
+            }
+            match err.status().map(|status| status.as_u16()) {
+                Some(429) => LLMErrorClass::RateLimit,
+                Some(401) | Some(403) => LLMErrorClass::Auth,
+                Some(status) if status >= 500 => LLMErrorClass::Overloaded,
+                _ => LLMErrorClass::Other,
+            }
+        }
+        LLMProviderError::MessageTooLargeForLLM { .. } | LLMProviderError::TokenLimit(_) => {
+            LLMErrorClass::ContextLength
+        }
+        LLMProviderError::ApiKeyNotSet | LLMProviderError::ZooBackendInvalidAuthentication(_) => LLMErrorClass::Auth,
+        LLMProviderError::LLMServiceInferenceLimitReached(_) | LLMProviderError::ZooBackendInferenceLimitReached(_) => {
+            LLMErrorClass::QuotaExceeded
+        }
+        LLMProviderError::APIError(message)
+        | LLMProviderError::LLMServiceUnexpectedError(message)
+        | LLMProviderError::ZooBackendAIProviderError(message)
+        | LLMProviderError::ZooBackendUnexpectedError(message)
+        | LLMProviderError::NetworkError(message) => classify_error_message(message),
+        LLMProviderError::ZooBackendUnexpectedStatusCode(429) => LLMErrorClass::RateLimit,
+        LLMProviderError::ZooBackendUnexpectedStatusCode(status) if *status >= 500 => LLMErrorClass::Overloaded,
+        _ => LLMErrorClass::Other,
+    }
+}
+
+fn classify_error_message(message: &str) -> LLMErrorClass {
+    let message = message.to_lowercase();
+    let contains_any = |phrases: &[&str]| phrases.iter().any(|phrase| message.contains(phrase));
+
+    // Context errors go first: they are 400s that often mention tokens and limits
+    if contains_any(&[
+        "context length",
+        "context_length",
+        "context window",
+        "maximum context",
+        "prompt is too long",
+        "too many tokens",
+        "reduce the length",
+    ]) {
+        LLMErrorClass::ContextLength
+    } else if contains_any(&["quota", "insufficient_quota", "billing"]) {
+        LLMErrorClass::QuotaExceeded
+    } else if contains_any(&[
+        "unauthorized",
+        "invalid api key",
+        "invalid_api_key",
+        "incorrect api key",
+        "authentication",
+        "permission denied",
+        "forbidden",
+    ]) {
+        LLMErrorClass::Auth
+    } else if contains_any(&["rate limit", "rate_limit", "too many requests"]) {
+        LLMErrorClass::RateLimit
+    } else if contains_any(&[
+        "overloaded",
+        "internal server error",
+        "bad gateway",
+        "service unavailable",
+        "gateway timeout",
+        "server_error",
+    ]) {
+        LLMErrorClass::Overloaded
+    } else if contains_any(&["timed out", "timeout", "connection refused", "connection reset"]) {
+        LLMErrorClass::Timeout
+    } else {
+        LLMErrorClass::Other
+    }
+}
+
+/// Passes the updates of an inference on to the UI and notes whether it sent any. Once the user saw
+/// part of an answer, running the inference again would show it twice.
+struct StreamWatcher {
+    inner: Arc<Mutex<dyn WSUpdateHandler + Send>>,
+    streamed: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl WSUpdateHandler for StreamWatcher {
+    async fn queue_message(
+        &self,
+        topic: WSTopic,
+        subtopic: String,
+        update: String,
+        metadata: WSMessageType,
+        is_stream: bool,
+    ) {
+        if !update.is_empty() {
+            self.streamed.store(true, Ordering::SeqCst);
+        }
+        let inner = self.inner.lock().await;
+        inner.queue_message(topic, subtopic, update, metadata, is_stream).await;
+    }
+}
+
+/// Runs the inference against `provider_or_agent` following its `LLMFallbackPolicy`: errors are
+/// retried with backoff as their rule says, then the downgrade model and the fallback providers
+/// are tried in order. An inference that already streamed part of its answer isn't run again.
+/// Every failed attempt (and the one that finally works) is traced.
+/// The usage budgets are checked before every provider and the answer is added to the usage ledger.
+/// Stopping the job aborts the inference (and the retries) with `LLMProviderError::JobCancelled`.
+#[allow(clippy::too_many_arguments)]
+pub async fn inference_with_fallback(
+    provider_or_agent: ProviderOrAgent,
+    prompt: Prompt,
+    inbox_name: Option<InboxName>,
+    ws_manager_trait: Option<Arc<Mutex<dyn WSUpdateHandler + Send>>>,
+    config: Option<JobConfig>,
+    llm_stopper: Arc<LLMStopper>,
+    db: Arc<SqliteManager>,
+    tracing_message_id: Option<String>,
+) -> Result<LLMInferenceResponse, LLMProviderError> {
+    let usage_context = UsageContext::new(&provider_or_agent, &inbox_name);
+    let primary = LLMProvider::from_provider_or_agent(provider_or_agent, db.clone()).await?;
+    let primary = enforce_usage_budgets(db.clone(), &usage_context, primary)?;
+    let fallback_config = db
+        .get_preference::<LLMFallbackConfig>(LLMFallbackConfig::PREFERENCE_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let policy = fallback_config.policy_for(&primary.id).clone();
+
+    // Build the chain: the provider itself, its cheaper model and then the fallback providers
+    let mut chain = vec![primary.clone()];
+    if let Some(downgrade_model) = &policy.downgrade_model {
+        match LLMProviderInterface::from_str(downgrade_model) {
+            Ok(model) => {
+                let mut downgraded = primary.clone();
+                downgraded.model = model;
+                chain.push(downgraded);
+            }
+            Err(_) => zoo_log(
+                ZooLogOption::JobExecution,
+                ZooLogLevel::Error,
+                &format!("Ignoring invalid downgrade model for {}: {}", primary.id, downgrade_model),
+            ),
+        }
+    }
+    for fallback_id in &policy.fallback_provider_ids {
+        match db.get_llm_provider(fallback_id, &primary.full_identity_name) {
+            Ok(Some(serialized)) => chain.push(LLMProvider::from_serialized_llm_provider(serialized, db.clone())),
+            _ => zoo_log(
+                ZooLogOption::JobExecution,
+                ZooLogLevel::Error,
+                &format!("Fallback LLM provider {} of {} not found", fallback_id, primary.id),
+            ),
+        }
+    }
+
+    let chain_len = chain.len();
+    let job_token = llm_stopper.inbox_cancellation_token(&inbox_name);
+    let mut total_attempts = 0;
+    let mut last_error = None;
+    for (position, llm_provider) in chain.into_iter().enumerate() {
+        // The first one was checked above. The others may be pricier, or the budgets may have run out
+        // while the previous ones were retried.
+        let llm_provider = if position == 0 {
+            llm_provider
+        } else {
+            enforce_usage_budgets(db.clone(), &usage_context, llm_provider)?
+        };
+        let mut retry = 0;
+        loop {
+            if job_token.is_cancelled() {
+                return Err(LLMProviderError::JobCancelled);
+            }
+
+            let streamed = Arc::new(AtomicBool::new(false));
+            let watched_ws_manager = ws_manager_trait.clone().map(|inner| {
+                Arc::new(Mutex::new(StreamWatcher {
+                    inner,
+                    streamed: streamed.clone(),
+                })) as Arc<Mutex<dyn WSUpdateHandler + Send>>
+            });
+
+            // Providers notice the stop on their next chunk and return what they streamed so far.
+            // The ones that don't answer in time are dropped, which aborts their HTTP call.
+            let result = tokio::select! {
+                result = llm_provider.inference(
+                    prompt.clone(),
+                    inbox_name.clone(),
+                    watched_ws_manager,
+                    config.clone(),
+                    llm_stopper.clone(),
+                    tracing_message_id.clone(),
+                ) => result,
+                _ = async {
+                    job_token.cancelled().await;
+                    tokio::time::sleep(STOP_GRACE_PERIOD).await;
+                } => {
+                    zoo_log(
+                        ZooLogOption::JobExecution,
+                        ZooLogLevel::Info,
+                        &format!("Inference with {} aborted by user request", llm_provider.id),
+                    );
+                    return Err(LLMProviderError::JobCancelled);
+                }
+            };
+            total_attempts += 1;
+
+            let error = match result {
+                Ok(response) => {
+                    // Only trace when something went wrong before, the plain path is already traced
+                    if total_attempts > 1 {
+                        add_attempt_trace(&db, &tracing_message_id, &inbox_name, &llm_provider, retry, None);
+                    }
+                    record_usage(&db, &usage_context, &llm_provider, &prompt, &response);
+                    return Ok(response);
+                }
+                Err(e) => e,
+            };
+
+            let error_class = classify_inference_error(&error);
+            let rule = policy.rule_for(error_class);
+            add_attempt_trace(
+                &db,
+                &tracing_message_id,
+                &inbox_name,
+                &llm_provider,
+                retry,
+                Some((error_class, &error)),
+            );
+
+            // Errors caused by the stop itself (e.g. the aborted request) aren't worth a retry
+            if job_token.is_cancelled() {
+                return Err(LLMProviderError::JobCancelled);
+            }
+            if streamed.load(Ordering::SeqCst) {
+                return Err(error);
+            }
+
+            if retry < rule.max_retries {
+                let backoff = rule.backoff(retry);
+                zoo_log(
+                    ZooLogOption::JobExecution,
+                    ZooLogLevel::Info,
+                    &format!(
+                        "Inference with {} failed ({:?}): {}. Retrying in {:?}",
+                        llm_provider.id, error_class, error, backoff
+                    ),
+                );
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = job_token.cancelled() => return Err(LLMProviderError::JobCancelled),
+                }
+                retry += 1;
+                continue;
+            }
+
+            if !rule.fallback || position + 1 == chain_len {
+                return Err(error);
+            }
+
+            zoo_log(
+                ZooLogOption::JobExecution,
+                ZooLogLevel::Info,
+                &format!(
+                    "Inference with {} failed ({:?}): {}. Falling back to the next provider",
+                    llm_provider.id, error_class, error
+                ),
+            );
+            last_error = Some(error);
+            break;
+        }
+    }
+
+    // Only reachable if the chain was empty, which can't happen as it starts with the provider
+    Err(last_error.unwrap_or(LLMProviderError::InferenceFailed))
+}
+
+fn add_attempt_trace(
+    db: &SqliteManager,
+    tracing_message_id: &Option<String>,
+    inbox_name: &Option<InboxName>,
+    llm_provider: &LLMProvider,
+    retry: u32,
+    error: Option<(LLMErrorClass, &LLMProviderError)>,
+) {
+    if let Some(msg_id) = tracing_message_id {
+        let trace_info = json!({
+            "llm_provider_id": llm_provider.id,
+            "model": llm_provider.model,
+            "retry": retry,
+            "success": error.is_none(),
+            "error_class": error.as_ref().map(|(class, _)| class),
+            "error": error.as_ref().map(|(_, e)| e.to_string()),
+        });
+        if let Err(e) = db.add_tracing(
+            msg_id,
+            inbox_name.as_ref().map(|i| i.get_value()).as_deref(),
+            "llm_inference_attempt",
+            &trace_info,
+        ) {
+            zoo_log(
+                ZooLogOption::JobExecution,
+                ZooLogLevel::Error,
+                &format!("failed to add inference attempt trace: {:?}", e),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::NamedTempFile;
+    use tokio::net::TcpListener;
+    use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+    use zoo_message_primitives::schemas::llm_providers::llm_fallback::{LLMFallbackPolicy, LLMRetryRule};
+    use zoo_message_primitives::schemas::llm_providers::serialized_llm_provider::{Ollama, SerializedLLMProvider};
+    use zoo_message_primitives::schemas::prompts::SubPromptType;
+    use zoo_message_primitives::schemas::zoo_name::ZooName;
+
+    fn ollama_provider(id: &str, external_url: String) -> SerializedLLMProvider {
+        SerializedLLMProvider {
+            id: id.to_string(),
+            name: None,
+            description: None,
+            full_identity_name: ZooName::new(format!("@@test.zoo/main/agent/{}", id)).unwrap(),
+            external_url: Some(external_url),
+            api_key: None,
+            model: LLMProviderInterface::Ollama(Ollama {
+                model_type: "llama3.1:8b".to_string(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_classify_inference_error() {
+        let cases = vec![
+            (
+                LLMProviderError::APIError(
+                    "AI Provider API Error: This model's maximum context length is 8192 tokens".to_string(),
+                ),
+                LLMErrorClass::ContextLength,
+            ),
+            (
+                LLMProviderError::APIError("AI Provider API Error: Rate limit reached for gpt-4o".to_string()),
+                LLMErrorClass::RateLimit,
+            ),
+            (
+                LLMProviderError::LLMServiceUnexpectedError("Rate limit exceeded".to_string()),
+                LLMErrorClass::RateLimit,
+            ),
+            (
+                LLMProviderError::APIError("AI Provider API Error (overloaded_error): Overloaded".to_string()),
+                LLMErrorClass::Overloaded,
+            ),
+            (
+                LLMProviderError::APIError("AI Provider API Error: Incorrect API key provided".to_string()),
+                LLMErrorClass::Auth,
+            ),
+            (
+                LLMProviderError::APIError("You exceeded your current quota".to_string()),
+                LLMErrorClass::QuotaExceeded,
+            ),
+            (
+                LLMProviderError::LLMServiceInferenceLimitReached("Daily quota exceeded".to_string()),
+                LLMErrorClass::QuotaExceeded,
+            ),
+            (
+                LLMProviderError::MessageTooLargeForLLM {
+                    max_tokens: 10,
+                    used_tokens: 20,
+                },
+                LLMErrorClass::ContextLength,
+            ),
+            (LLMProviderError::ApiKeyNotSet, LLMErrorClass::Auth),
+            (LLMProviderError::ZooBackendUnexpectedStatusCode(503), LLMErrorClass::Overloaded),
+            (
+                LLMProviderError::APIError("AI Provider API Error: Invalid tool schema".to_string()),
+                LLMErrorClass::Other,
+            ),
+            (LLMProviderError::JobNotFound, LLMErrorClass::Other),
+            // A number alone isn't a status code
+            (
+                LLMProviderError::APIError("AI Provider API Error: Invalid value 4290 for max_tokens".to_string()),
+                LLMErrorClass::Other,
+            ),
+        ];
+
+        for (error, expected) in cases {
+            assert_eq!(classify_inference_error(&error), expected, "{}", error);
+        }
+    }
+    #[tokio::test]
+    async fn test_stopping_the_job_aborts_the_retries_and_the_fallbacks() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db = Arc::new(
+            SqliteManager::new(
+                std::path::PathBuf::from(temp_file.path()),
+                String::new(),
+                EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM),
+            )
+            .unwrap(),
+        );
+
+        // Nothing listens on the primary, the fallback records whether it is reached
+        let unreachable = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let primary_url = format!("http://{}", unreachable.local_addr().unwrap());
+        drop(unreachable);
+        let fallback = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let fallback_url = format!("http://{}", fallback.local_addr().unwrap());
+
+        let primary = ollama_provider("primary", primary_url);
+        let fallback_provider = ollama_provider("fallback", fallback_url);
+        db.add_llm_provider(fallback_provider, &primary.full_identity_name)
+            .unwrap();
+
+        // Every error is retried after a wait much longer than the test
+        let slow_retry = LLMRetryRule {
+            max_retries: 1,
+            initial_backoff_ms: 60_000,
+            max_backoff_ms: 60_000,
+            fallback: true,
+        };
+        let rules = [
+            LLMErrorClass::RateLimit,
+            LLMErrorClass::Overloaded,
+            LLMErrorClass::Timeout,
+            LLMErrorClass::ContextLength,
+            LLMErrorClass::Auth,
+            LLMErrorClass::QuotaExceeded,
+            LLMErrorClass::Other,
+        ]
+        .into_iter()
+        .map(|class| (class, slow_retry.clone()))
+        .collect::<HashMap<_, _>>();
+        let fallback_config = LLMFallbackConfig {
+            default: LLMFallbackPolicy {
+                fallback_provider_ids: vec!["fallback".to_string()],
+                downgrade_model: None,
+                rules,
+            },
+            providers: HashMap::new(),
+        };
+        db.set_preference(LLMFallbackConfig::PREFERENCE_KEY, &fallback_config, None)
+            .unwrap();
+
+        let inbox_name = InboxName::get_job_inbox_name_from_params("job_to_stop".to_string()).unwrap();
+        let llm_stopper = Arc::new(LLMStopper::new());
+        let stopper = llm_stopper.clone();
+        let key = inbox_name.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            stopper.stop(&key);
+        });
+
+        let mut prompt = Prompt::new();
+        prompt.add_content("Hello".to_string(), SubPromptType::User, 100);
+        let result = tokio::time::timeout(
+            Duration::from_secs(10),
+            inference_with_fallback(
+                ProviderOrAgent::LLMProvider(primary),
+                prompt,
+                Some(inbox_name),
+                None,
+                None,
+                llm_stopper,
+                db,
+                None,
+            ),
+        )
+        .await
+        .expect("the stop should end the inference while it waits to retry");
+
+        assert!(matches!(result, Err(LLMProviderError::JobCancelled)));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), fallback.accept())
+                .await
+                .is_err(),
+            "the fallback provider shouldn't be tried once the job is stopped"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use std::sync::Arc;
+
+use serde_json::json;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use zoo_embedding::embedding_generator::{EmbeddingGenerator, RemoteEmbeddingGenerator};
+use zoo_message_primitives::schemas::inbox_name::InboxName;
+use zoo_message_primitives::schemas::job_config::JobConfig;
+use zoo_message_primitives::schemas::llm_providers::common_agent_llm_provider::ProviderOrAgent;
+use zoo_message_primitives::schemas::llm_providers::llm_response_cache::LLMResponseCacheConfig;
+use zoo_message_primitives::schemas::llm_providers::serialized_llm_provider::LLMProviderInterface;
+use zoo_message_primitives::schemas::prompts::Prompt;
+use zoo_message_primitives::schemas::ws_types::WSUpdateHandler;
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
+use zoo_sqlite::SqliteManager;
+
+use super::error::LLMProviderError;
+use super::execution::chains::inference_chain_trait::LLMInferenceResponse;
+use super::llm_fallback::inference_with_fallback;
+use super::llm_provider::LLMProvider;
+use super::llm_stopper::LLMStopper;
+use super::providers::shared::shared_model_logic::send_ws_update;
+use crate::utils::environment::fetch_node_environment;
+
+/// The part of the job config that changes what the model answers. The custom prompts are left out
+/// because they are already part of the generated prompt, and `stream` only changes the transport.
+fn sampling_params(config: &Option<JobConfig>) -> serde_json::Value {
+    match config {
+        Some(config) => json!({
+            "temperature": config.temperature,
+            "max_tokens": config.max_tokens,
+            "seed": config.seed,
+            "top_k": config.top_k,
+            "top_p": config.top_p,
+            "other_model_params": config.other_model_params,
+            "use_tools": config.use_tools,
+            "thinking": config.thinking,
+            "reasoning_effort": config.reasoning_effort,
+            "web_search_enabled": config.web_search_enabled,
+        }),
+        None => serde_json::Value::Null,
+    }
+}
+
+/// Key shared by all the prompts sent to the same model with the same sampling params. Semantic
+/// matches are only looked for within it.
+pub fn cache_scope_key(model: &LLMProviderInterface, config: &Option<JobConfig>) -> String {
+    let scope = json!({
+        "model": model,
+        "sampling_params": sampling_params(config),
+    });
+    blake3::hash(scope.to_string().as_bytes()).to_hex().to_string()
+}
+
+/// Key of an exact match: the prompt within its scope.
+pub fn cache_key(prompt: &Prompt, model: &LLMProviderInterface, config: &Option<JobConfig>) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(cache_scope_key(model, config).as_bytes());
+    hasher.update(serde_json::to_string(prompt).unwrap_or_default().as_bytes());
+    hasher.finalize().to_hex().to_string()
+}
+
+/// The generator for the semantic mode, using the node's embeddings server and current default model.
+fn semantic_embedding_generator(db: &SqliteManager) -> Option<RemoteEmbeddingGenerator> {
+    let node_env = fetch_node_environment();
+    let api_url = node_env.embeddings_server_url?;
+    let model_type = db.get_default_embedding_model().ok()?;
+    Some(RemoteEmbeddingGenerator::new(
+        model_type,
+        &api_url,
+        node_env.embeddings_server_api_key,
+    ))
+}
+
+/// Runs the inference through the response cache configured in the `llm_response_cache`
+/// preference. When the cache is disabled this is just `inference_with_fallback`. Cache errors are
+/// logged and never fail the inference.
+#[allow(clippy::too_many_arguments)]
+pub async fn inference_with_cache(
+    provider_or_agent: ProviderOrAgent,
+    prompt: Prompt,
+    inbox_name: Option<InboxName>,
+    ws_manager_trait: Option<Arc<Mutex<dyn WSUpdateHandler + Send>>>,
+    config: Option<JobConfig>,
+    llm_stopper: Arc<LLMStopper>,
+    db: Arc<SqliteManager>,
+    tracing_message_id: Option<String>,
+) -> Result<LLMInferenceResponse, LLMProviderError> {
+    let cache_config = db
+        .get_preference::<LLMResponseCacheConfig>(LLMResponseCacheConfig::PREFERENCE_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    if !cache_config.enabled {
+        return inference_with_fallback(
+            provider_or_agent,
+            prompt,
+            inbox_name,
+            ws_manager_trait,
+            config,
+            llm_stopper,
+            db,
+            tracing_message_id,
+        )
+        .await;
+    }
+
+    let model = LLMProvider::from_provider_or_agent(provider_or_agent.clone(), db.clone())
+        .await?
+        .model;
+    let key = cache_key(&prompt, &model, &config);
+    let scope_key = cache_scope_key(&model, &config);
+
+    let mut cached = match db.get_cached_llm_response(&key, cache_config.ttl_secs) {
+        Ok(cached) => cached.map(|response| (response, 1.0)),
+        Err(e) => {
+            log_cache_error("read", &e.to_string());
+            None
+        }
+    };
+
+    // Semantic mode: embed the prompt to look for a close enough one (and to store it afterwards)
+    let mut semantic_embedding = None;
+    if cached.is_none() && cache_config.semantic {
+        if let Some(generator) = semantic_embedding_generator(&db) {
+            let prompt_text = prompt.generate_single_output_string().unwrap_or_default();
+            match generator.generate_embedding_default(&prompt_text).await {
+                Ok(embedding) => {
+                    let embedding_model = generator.model_type().to_string();
+                    match db.find_similar_cached_llm_response(
+                        &scope_key,
+                        &embedding,
+                        &embedding_model,
+                        cache_config.similarity_threshold,
+                        cache_config.ttl_secs,
+                    ) {
+                        Ok(similar) => cached = similar,
+                        Err(e) => log_cache_error("read", &e.to_string()),
+                    }
+                    semantic_embedding = Some((embedding, embedding_model));
+                }
+                Err(e) => log_cache_error("embed the prompt for", &e.to_string()),
+            }
+        }
+    }
+
+    if let Some((cached_response, similarity)) = cached {
+        match serde_json::from_str::<LLMInferenceResponse>(&cached_response) {
+            // Entries stored before tool calls were left out of the cache
+            Ok(response) if !response.function_calls.is_empty() => {}
+            Ok(mut response) => {
+                response.tps = None;
+                trace_cache_hit(&db, &tracing_message_id, &inbox_name, &key, similarity);
+                replay_cached_response(&ws_manager_trait, inbox_name, &response).await;
+                return Ok(response);
+            }
+            Err(e) => log_cache_error("parse", &e.to_string()),
+        }
+    }
+
+    let response = inference_with_fallback(
+        provider_or_agent,
+        prompt,
+        inbox_name,
+        ws_manager_trait,
+        config,
+        llm_stopper,
+        db.clone(),
+        tracing_message_id,
+    )
+    .await?;
+
+    // An empty answer means the inference was stopped, that's not worth reusing. Tool calls aren't
+    // either: replaying one would run the tool again on whatever it returned the first time.
+    if !response.response_string.is_empty() && response.function_calls.is_empty() {
+        let stored = serde_json::to_string(&response)
+            .map_err(|e| e.to_string())
+            .and_then(|serialized| {
+                db.add_cached_llm_response(
+                    &key,
+                    &scope_key,
+                    &serialized,
+                    semantic_embedding.as_ref().map(|(embedding, _)| embedding.as_slice()),
+                    semantic_embedding.as_ref().map(|(_, model)| model.as_str()),
+                    cache_config.ttl_secs,
+                    cache_config.max_entries,
+                )
+                .map_err(|e| e.to_string())
+            });
+        if let Err(e) = stored {
+            log_cache_error("write", &e);
+        }
+    }
+
+    Ok(response)
+}
+
+/// Sends the cached answer to the UI in one go, as a provider would have streamed it.
+async fn replay_cached_response(
+    ws_manager_trait: &Option<Arc<Mutex<dyn WSUpdateHandler + Send>>>,
+    inbox_name: Option<InboxName>,
+    response: &LLMInferenceResponse,
+) {
+    let session_id = Uuid::new_v4().to_string();
+    let _ = send_ws_update(
+        ws_manager_trait,
+        inbox_name,
+        &session_id,
+        response.response_string.clone(),
+        false,
+        true,
+        None,
+    )
+    .await;
+}
+
+fn trace_cache_hit(
+    db: &SqliteManager,
+    tracing_message_id: &Option<String>,
+    inbox_name: &Option<InboxName>,
+    cache_key: &str,
+    similarity: f32,
+) {
+    if let Some(msg_id) = tracing_message_id {
+        let trace_info = json!({
+            "cache_key": cache_key,
+            "similarity": similarity,
+        });
+        if let Err(e) = db.add_tracing(
+            msg_id,
+            inbox_name.as_ref().map(|i| i.get_value()).as_deref(),
+            "llm_response_cache_hit",
+            &trace_info,
+        ) {
+            zoo_log(
+                ZooLogOption::JobExecution,
+                ZooLogLevel::Error,
+                &format!("Failed to add the response cache trace: {:?}", e),
+            );
+        }
+    }
+}
+
+fn log_cache_error(action: &str, error: &str) {
+    zoo_log(
+        ZooLogOption::JobExecution,
+        ZooLogLevel::Error,
+        &format!("Failed to {} the LLM response cache: {}", action, error),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zoo_message_primitives::schemas::llm_providers::serialized_llm_provider::OpenAI;
+    use zoo_message_primitives::schemas::subprompts::SubPromptType;
+
+    fn job_config(temperature: f64, stream: bool) -> Option<JobConfig> {
+        Some(JobConfig {
+            custom_system_prompt: None,
+            custom_prompt: None,
+            temperature: Some(temperature),
+            max_tokens: None,
+            seed: None,
+            top_k: None,
+            top_p: None,
+            stream: Some(stream),
+            other_model_params: None,
+            use_tools: None,
+            thinking: None,
+            reasoning_effort: None,
+            web_search_enabled: None,
+            memory_strategy: None,
+        })
+    }
+
+    #[test]
+    fn test_cache_key_depends_on_prompt_model_and_sampling_params() {
+        let mut prompt = Prompt::new();
+        prompt.add_content("What is the capital of France?".to_string(), SubPromptType::User, 100);
+        let mut other_prompt = Prompt::new();
+        other_prompt.add_content("What is the capital of Spain?".to_string(), SubPromptType::User, 100);
+
+        let model = LLMProviderInterface::OpenAI(OpenAI {
+            model_type: "gpt-4o".to_string(),
+        });
+        let other_model = LLMProviderInterface::OpenAI(OpenAI {
+            model_type: "gpt-4o-mini".to_string(),
+        });
+
+        let key = cache_key(&prompt, &model, &job_config(0.2, false));
+        assert_eq!(key, cache_key(&prompt, &model, &job_config(0.2, false)));
+        // Streaming doesn't change the answer
+        assert_eq!(key, cache_key(&prompt, &model, &job_config(0.2, true)));
+
+        assert_ne!(key, cache_key(&other_prompt, &model, &job_config(0.2, false)));
+        assert_ne!(key, cache_key(&prompt, &other_model, &job_config(0.2, false)));
+        assert_ne!(key, cache_key(&prompt, &model, &job_config(0.9, false)));
+        assert_ne!(key, cache_key(&prompt, &model, &None));
+
+        // Both prompts share the scope used by the semantic mode
+        assert_eq!(
+            cache_scope_key(&model, &job_config(0.2, false)),
+            cache_scope_key(&model, &job_config(0.2, true))
+        );
+        assert_ne!(
+            cache_scope_key(&model, &job_config(0.2, false)),
+            cache_scope_key(&other_model, &job_config(0.2, false))
+        );
+    }
+}
@@ -1,29 +1,102 @@
+use std::sync::Arc;
+
 use dashmap::DashMap;
+use tokio_util::sync::CancellationToken;
+use zoo_message_primitives::schemas::inbox_name::InboxName;
 
 pub type JobId = String;
 
+/// Final `done_reason` sent over WS when a job is stopped by the user.
+pub const CANCELLED_DONE_REASON: &str = "cancelled";
+
+/// Cancellation of the jobs, keyed by their inbox name. Every job has a `CancellationToken` that
+/// is cancelled by `stop`: the in-flight inference, the tool calls and the rest of the job are
+/// raced against it so they are dropped right away instead of waiting for the next poll.
+///
+/// The state of a job is kept while it runs (see `job_running`) and dropped once it finishes.
 pub struct LLMStopper {
     pub stop_signal: DashMap<JobId, bool>,
+    tokens: DashMap<JobId, CancellationToken>,
+    running: DashMap<JobId, usize>,
+}
+
+/// Marks a job as running until it is dropped.
+pub struct RunningJob {
+    stopper: Arc<LLMStopper>,
+    key: JobId,
+}
+
+impl Drop for RunningJob {
+    fn drop(&mut self) {
+        self.stopper.job_finished(&self.key);
+    }
 }
 
 impl LLMStopper {
     pub fn new() -> Self {
         LLMStopper {
             stop_signal: DashMap::new(),
+            tokens: DashMap::new(),
+            running: DashMap::new(),
         }
     }
 
     pub fn stop(&self, key: &str) {
         self.stop_signal.insert(key.to_string(), true);
+        self.tokens.entry(key.to_string()).or_default().cancel();
     }
 
+    /// Clears the stop of a job so its next message runs normally.
     pub fn reset(&self, key: &str) {
         self.stop_signal.insert(key.to_string(), false);
+        self.tokens.remove_if(key, |_, token| token.is_cancelled());
     }
 
     pub fn should_stop(&self, key: &str) -> bool {
         self.stop_signal.get(key).map_or(false, |v| *v)
     }
+
+    /// The token of the job, already cancelled if the job was stopped and not reset since.
+    pub fn cancellation_token(&self, key: &str) -> CancellationToken {
+        self.tokens.entry(key.to_string()).or_default().clone()
+    }
+
+    /// The token of the job of `inbox_name`. Inferences without an inbox can't be stopped, they get
+    /// a token nobody cancels.
+    pub fn inbox_cancellation_token(&self, inbox_name: &Option<InboxName>) -> CancellationToken {
+        match inbox_name {
+            Some(inbox_name) => self.cancellation_token(&inbox_name.to_string()),
+            None => CancellationToken::new(),
+        }
+    }
+
+    /// Resolves once the job is stopped.
+    pub async fn cancelled(&self, key: &str) {
+        self.cancellation_token(key).cancelled().await
+    }
+
+    /// Keeps the stop and the token of the job until the returned guard, and every other guard of
+    /// the job, is dropped.
+    pub fn job_running(self: &Arc<Self>, key: &str) -> RunningJob {
+        *self.running.entry(key.to_string()).or_insert(0) += 1;
+        RunningJob {
+            stopper: self.clone(),
+            key: key.to_string(),
+        }
+    }
+
+    fn job_finished(&self, key: &str) {
+        // The entry stays locked while the state is dropped so a job starting now keeps its state
+        if let Some(mut running) = self.running.get_mut(key) {
+            *running -= 1;
+            if *running > 0 {
+                return;
+            }
+            self.tokens.remove(key);
+            self.stop_signal.remove(key);
+        }
+        self.running.remove_if(key, |_, running| *running == 0);
+    }
 }
 
 #[cfg(test)]
@@ -49,4 +122,44 @@ mod tests {
         // Test non-existent key
         assert!(!stopper.should_stop("non_existent_job"));
     }
+
+    #[tokio::test]
+    async fn test_llm_stopper_cancellation_token() {
+        let stopper = LLMStopper::new();
+        let job_id = "test_job";
+
+        let token = stopper.cancellation_token(job_id);
+        assert!(!token.is_cancelled());
+
+        // Stopping cancels the tokens handed out before
+        stopper.stop(job_id);
+        assert!(token.is_cancelled());
+        stopper.cancelled(job_id).await;
+
+        // After a reset the job gets a fresh token
+        stopper.reset(job_id);
+        assert!(!stopper.cancellation_token(job_id).is_cancelled());
+        assert!(!stopper.cancellation_token("other_job").is_cancelled());
+    }
+
+    #[test]
+    fn test_llm_stopper_drops_finished_jobs() {
+        let stopper = Arc::new(LLMStopper::new());
+        let job_id = "test_job";
+
+        let job = stopper.job_running(job_id);
+        let summary = stopper.job_running(job_id);
+        stopper.stop(job_id);
+
+        // The job is still running while the summary isn't done
+        drop(job);
+        assert!(stopper.should_stop(job_id));
+        assert!(stopper.cancellation_token(job_id).is_cancelled());
+
+        drop(summary);
+        assert!(!stopper.should_stop(job_id));
+        assert!(stopper.tokens.is_empty());
+        assert!(stopper.stop_signal.is_empty());
+        assert!(stopper.running.is_empty());
+    }
 }
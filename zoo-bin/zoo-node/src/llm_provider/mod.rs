@@ -4,7 +4,10 @@ pub mod llm_provider_to_serialization;
 pub mod error;
 pub mod execution;
 pub mod job_manager;
+pub mod llm_fallback;
+pub mod llm_response_cache;
 pub mod parsing_helper;
 pub mod providers;
 pub mod job_callback_manager;
 pub mod llm_stopper;
+pub mod usage_budget;
@@ -280,7 +280,6 @@ async fn handle_streaming_response(
         if let Some(ref inbox_name) = inbox_name {
             if llm_stopper.should_stop(&inbox_name.to_string()) {
                 eprintln!("LLM job stopped by user request");
-                llm_stopper.reset(&inbox_name.to_string());
 
                 // Send WS message indicating the job is done
                 let _ = send_ws_update(
@@ -458,7 +457,6 @@ async fn handle_non_streaming_response(
                 if let Some(ref inbox_name) = inbox_name {
                     if llm_stopper.should_stop(&inbox_name.to_string()) {
                         eprintln!("LLM job stopped by user request");
-                        llm_stopper.reset(&inbox_name.to_string());
 
                         return Ok(LLMInferenceResponse::new("".to_string(), None, json!({}), Vec::new(), Vec::new(), None));
                     }
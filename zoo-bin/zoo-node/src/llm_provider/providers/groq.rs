@@ -259,7 +259,6 @@ async fn handle_streaming_response(
                     ZooLogLevel::Info,
                     "LLM job stopped by user request",
                 );
-                llm_stopper.reset(&inbox_name.to_string());
 
                 return Ok(LLMInferenceResponse::new(
                     response_text,
@@ -549,7 +548,6 @@ async fn handle_non_streaming_response(
                             ZooLogLevel::Info,
                             "LLM job stopped by user request",
                         );
-                        llm_stopper.reset(&inbox_name.to_string());
 
                         return Ok(LLMInferenceResponse::new("".to_string(), None, json!({}), Vec::new(), Vec::new(), None));
                     }
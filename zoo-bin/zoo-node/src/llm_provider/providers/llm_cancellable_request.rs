@@ -17,15 +17,18 @@ impl CancellableRequest {
     }
 }
 
+/// Sends `payload` to `url`. The request is aborted when `job_token` (the token of the job, see
+/// `LLMStopper::cancellation_token`) or the returned `CancellableRequest` is cancelled.
 pub fn make_cancellable_request(
     client: &Client,
     url: String,
     payload: Value,
+    job_token: &CancellationToken,
 ) -> (
     CancellableRequest,
     impl std::future::Future<Output = Result<reqwest::Response, Error>>,
 ) {
-    let cancellation_token = CancellationToken::new();
+    let cancellation_token = job_token.child_token();
     let child_token = cancellation_token.child_token();
 
     let request = client.post(url.clone()).json(&payload);
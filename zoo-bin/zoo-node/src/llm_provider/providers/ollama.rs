@@ -191,31 +191,24 @@ async fn handle_streaming_response(
     session_id: String,
     tools: Option<Vec<JsonValue>>,
 ) -> Result<LLMInferenceResponse, LLMProviderError> {
-    // Create a cancellable request
-    let (cancellable_request, response_future) = make_cancellable_request(client, url.clone(), payload);
-
-    let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(500));
+    // Create a request that is aborted as soon as the job is stopped
+    let job_token = llm_stopper.inbox_cancellation_token(&inbox_name);
+    let (_cancellable_request, response_future) = make_cancellable_request(client, url.clone(), payload, &job_token);
     tokio::pin!(response_future);
 
     // Wait for response or cancellation
     loop {
         tokio::select! {
-            _ = interval.tick() => {
-                if let Some(ref inbox_name) = inbox_name {
-                    if llm_stopper.should_stop(&inbox_name.to_string()) {
-                        // Cancel the in-flight request
-                        cancellable_request.cancel();
-                        zoo_log(
-                            ZooLogOption::JobExecution,
-                            ZooLogLevel::Info,
-                            "LLM job stopped by user request before response arrived",
-                        );
-                        llm_stopper.reset(&inbox_name.to_string());
+            biased;
+            _ = job_token.cancelled() => {
+                zoo_log(
+                    ZooLogOption::JobExecution,
+                    ZooLogLevel::Info,
+                    "LLM job stopped by user request before response arrived",
+                );
 
-                        // Return early since we never got a response
-                        return Ok(LLMInferenceResponse::new("".to_string(), None, json!({}), Vec::new(), Vec::new(), None));
-                    }
-                }
+                // Return early since we never got a response
+                return Ok(LLMInferenceResponse::new("".to_string(), None, json!({}), Vec::new(), Vec::new(), None));
             },
             result = &mut response_future => {
                 // If we got a result, break from the loop
@@ -260,7 +253,6 @@ async fn process_stream(
                     ZooLogLevel::Info,
                     "LLM job stopped by user request during streaming",
                 );
-                llm_stopper.reset(&inbox_name.to_string());
 
                 // Send WS message indicating the job is done
                 let _ = send_ws_update(
@@ -493,26 +485,20 @@ async fn handle_non_streaming_response(
     llm_stopper: Arc<LLMStopper>,
     tools: Option<Vec<JsonValue>>,
 ) -> Result<LLMInferenceResponse, LLMProviderError> {
-    let (cancellable_request, response_future) = make_cancellable_request(client, url, payload);
-    let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(500));
+    let job_token = llm_stopper.inbox_cancellation_token(&inbox_name);
+    let (_cancellable_request, response_future) = make_cancellable_request(client, url, payload, &job_token);
     tokio::pin!(response_future);
 
     let res = loop {
         tokio::select! {
-            _ = interval.tick() => {
-                if let Some(ref inbox_name) = inbox_name {
-                    if llm_stopper.should_stop(&inbox_name.to_string()) {
-                        // Cancel the in-flight request
-                        cancellable_request.cancel();
-                        zoo_log(
-                            ZooLogOption::JobExecution,
-                            ZooLogLevel::Info,
-                            "LLM job stopped by user request before response arrived",
-                        );
-                        llm_stopper.reset(&inbox_name.to_string());
-                        return Ok(LLMInferenceResponse::new("".to_string(), None, json!({}), Vec::new(), Vec::new(), None));
-                    }
-                }
+            biased;
+            _ = job_token.cancelled() => {
+                zoo_log(
+                    ZooLogOption::JobExecution,
+                    ZooLogLevel::Info,
+                    "LLM job stopped by user request before response arrived",
+                );
+                return Ok(LLMInferenceResponse::new("".to_string(), None, json!({}), Vec::new(), Vec::new(), None));
             },
             result = &mut response_future => {
                 let res = result?;
@@ -887,7 +887,6 @@ pub async fn handle_streaming_response(
                     ZooLogLevel::Info,
                     "LLM job stopped by user request",
                 );
-                llm_stopper.reset(&inbox_name.to_string());
 
                 // Send WS message indicating the job is done
 
@@ -1098,7 +1097,6 @@ pub async fn handle_non_streaming_response(
                             ZooLogLevel::Info,
                             "LLM job stopped by user request",
                         );
-                        llm_stopper.reset(&inbox_name.to_string());
 
                         return Ok(LLMInferenceResponse::new("".to_string(), None, json!({}), Vec::new(), Vec::new(), None));
                     }
@@ -0,0 +1,232 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use zoo_message_primitives::schemas::inbox_name::InboxName;
+use zoo_message_primitives::schemas::llm_message::LlmMessage;
+use zoo_message_primitives::schemas::llm_providers::common_agent_llm_provider::ProviderOrAgent;
+use zoo_message_primitives::schemas::prompts::Prompt;
+use zoo_message_primitives::schemas::usage::{
+    BudgetEnforcement, BudgetScope, ModelPrice, UsageBudgetStatus, UsagePricing, UsageRecord,
+};
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
+use zoo_sqlite::usage_manager::usage_timestamp;
+use zoo_sqlite::SqliteManager;
+
+use super::error::LLMProviderError;
+use super::execution::chains::inference_chain_trait::LLMInferenceResponse;
+use super::llm_provider::LLMProvider;
+use crate::managers::model_capabilities_manager::{ModelCapabilitiesManager, ModelCost};
+
+/// Who an inference is charged to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UsageContext {
+    pub profile: String,
+    pub agent_id: Option<String>,
+    pub job_id: Option<String>,
+}
+
+impl UsageContext {
+    pub fn new(provider_or_agent: &ProviderOrAgent, inbox_name: &Option<InboxName>) -> Self {
+        let identity = provider_or_agent.get_full_identity_name();
+        let profile = identity
+            .extract_profile()
+            .map(|profile| profile.full_name)
+            .unwrap_or_else(|_| identity.full_name.clone());
+        let agent_id = match provider_or_agent {
+            ProviderOrAgent::Agent(agent) => Some(agent.agent_id.clone()),
+            ProviderOrAgent::LLMProvider(_) => None,
+        };
+        Self {
+            profile,
+            agent_id,
+            job_id: inbox_name.as_ref().and_then(|inbox_name| inbox_name.get_job_id()),
+        }
+    }
+
+    fn budget_scopes(&self) -> Vec<(BudgetScope, &str)> {
+        let mut scopes = vec![(BudgetScope::Profile, self.profile.as_str())];
+        if let Some(agent_id) = &self.agent_id {
+            scopes.push((BudgetScope::Agent, agent_id.as_str()));
+        }
+        scopes
+    }
+}
+
+/// Checks the budgets of the profile and agent before an inference. A used up hard budget rejects
+/// the inference; a used up soft budget swaps `llm_provider` for its downgrade provider, if any.
+/// Returns the provider to run the inference with.
+pub fn enforce_usage_budgets(
+    db: Arc<SqliteManager>,
+    usage_context: &UsageContext,
+    llm_provider: LLMProvider,
+) -> Result<LLMProvider, LLMProviderError> {
+    let now = Utc::now();
+    let model = ledger_model(&llm_provider);
+    let priced = model_price(&db, &llm_provider, &model).is_some();
+    let mut exceeded: Vec<UsageBudgetStatus> = Vec::new();
+    for (scope, scope_id) in usage_context.budget_scopes() {
+        let budgets = db
+            .get_usage_budgets_for(scope, scope_id)
+            .map_err(|e| LLMProviderError::DatabaseError(e.to_string()))?;
+        for budget in budgets {
+            if budget.max_cost_usd.is_some() && !priced {
+                zoo_log(
+                    ZooLogOption::JobExecution,
+                    ZooLogLevel::Error,
+                    &format!(
+                        "{} has no price in the {} preference and an unknown cost tier, its inferences don't count towards the cost budget of {} {}",
+                        model,
+                        UsagePricing::PREFERENCE_KEY,
+                        scope.as_str(),
+                        scope_id
+                    ),
+                );
+            }
+            let status = db
+                .get_usage_budget_status(budget, now)
+                .map_err(|e| LLMProviderError::DatabaseError(e.to_string()))?;
+            if status.exceeded {
+                exceeded.push(status);
+            }
+        }
+    }
+
+    if let Some(status) = exceeded
+        .iter()
+        .find(|status| status.budget.enforcement == BudgetEnforcement::Hard)
+    {
+        return Err(LLMProviderError::UsageBudgetExceeded(describe_budget(status)));
+    }
+
+    for status in &exceeded {
+        match &status.budget.downgrade_llm_provider_id {
+            Some(downgrade_id) if downgrade_id != &llm_provider.id => {
+                match db.get_llm_provider(downgrade_id, &llm_provider.full_identity_name) {
+                    Ok(Some(serialized)) => {
+                        zoo_log(
+                            ZooLogOption::JobExecution,
+                            ZooLogLevel::Info,
+                            &format!(
+                                "{}. Downgrading from {} to {}",
+                                describe_budget(status),
+                                llm_provider.id,
+                                downgrade_id
+                            ),
+                        );
+                        return Ok(LLMProvider::from_serialized_llm_provider(serialized, db.clone()));
+                    }
+                    _ => zoo_log(
+                        ZooLogOption::JobExecution,
+                        ZooLogLevel::Error,
+                        &format!("Downgrade LLM provider {} of a usage budget not found", downgrade_id),
+                    ),
+                }
+            }
+            Some(_) => {}
+            None => zoo_log(
+                ZooLogOption::JobExecution,
+                ZooLogLevel::Info,
+                &format!("{} (soft limit, the inference goes on)", describe_budget(status)),
+            ),
+        }
+    }
+
+    Ok(llm_provider)
+}
+
+/// Adds the inference to the usage ledger. The token counts are estimated from the prompt and the
+/// answer the same way the providers size their requests, and the cost from `model_price`.
+pub fn record_usage(
+    db: &SqliteManager,
+    usage_context: &UsageContext,
+    llm_provider: &LLMProvider,
+    prompt: &Prompt,
+    response: &LLMInferenceResponse,
+) {
+    let (input_messages, _) =
+        prompt.generate_chat_completion_messages(None, &ModelCapabilitiesManager::num_tokens_from_llama3);
+    let input_tokens = ModelCapabilitiesManager::num_tokens_from_messages(&input_messages) as u64;
+    let mut output_text = response.response_string.clone();
+    if let Some(reasoning) = &response.reasoning_content {
+        output_text.push_str(reasoning);
+    }
+    if !response.function_calls.is_empty() {
+        output_text.push_str(&serde_json::to_string(&response.function_calls).unwrap_or_default());
+    }
+    let output_tokens = ModelCapabilitiesManager::num_tokens_from_messages(&[LlmMessage {
+        role: Some("assistant".to_string()),
+        content: Some(output_text),
+        ..Default::default()
+    }]) as u64;
+    let model = ledger_model(llm_provider);
+    let estimated_cost_usd =
+        model_price(db, llm_provider, &model).map_or(0.0, |price| price.cost_usd(input_tokens, output_tokens));
+
+    let record = UsageRecord {
+        id: None,
+        created_at: usage_timestamp(Utc::now()),
+        profile: usage_context.profile.clone(),
+        agent_id: usage_context.agent_id.clone(),
+        job_id: usage_context.job_id.clone(),
+        llm_provider_id: llm_provider.id.clone(),
+        estimated_cost_usd,
+        model,
+        input_tokens,
+        output_tokens,
+    };
+    if let Err(e) = db.add_usage_record(&record) {
+        zoo_log(
+            ZooLogOption::JobExecution,
+            ZooLogLevel::Error,
+            &format!("Failed to add the inference to the usage ledger: {}", e),
+        );
+    }
+}
+
+/// The model as recorded in the ledger and priced in the `UsagePricing` (e.g. `openai:gpt-4o`).
+fn ledger_model(llm_provider: &LLMProvider) -> String {
+    serde_json::to_value(&llm_provider.model)
+        .ok()
+        .and_then(|model| model.as_str().map(|model| model.to_string()))
+        .unwrap_or_default()
+}
+
+/// The price of the model in the `UsagePricing` of the node. Models without one get a rough upper
+/// price of their cost tier, so a cost budget trips early rather than never. None for the models
+/// of an unknown tier.
+fn model_price(db: &SqliteManager, llm_provider: &LLMProvider, model: &str) -> Option<ModelPrice> {
+    let pricing = db
+        .get_preference::<UsagePricing>(UsagePricing::PREFERENCE_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    if let Some(price) = pricing.models.get(model) {
+        return Some(price.clone());
+    }
+    let (input_usd_per_million, output_usd_per_million) =
+        match ModelCapabilitiesManager::get_llm_provider_cost(&llm_provider.model) {
+            ModelCost::Unknown => return None,
+            ModelCost::Free => (0.0, 0.0),
+            ModelCost::VeryCheap => (0.5, 2.0),
+            ModelCost::Cheap => (3.0, 15.0),
+            ModelCost::GoodValue => (5.0, 20.0),
+            ModelCost::Expensive => (15.0, 75.0),
+        };
+    Some(ModelPrice {
+        input_usd_per_million,
+        output_usd_per_million,
+    })
+}
+
+fn describe_budget(status: &UsageBudgetStatus) -> String {
+    format!(
+        "The {} {} budget of {} {} is used up ({} tokens, ${:.4} since {})",
+        status.budget.period.as_str(),
+        status.budget.enforcement.as_str(),
+        status.budget.scope.as_str(),
+        status.budget.scope_id,
+        status.usage.total_tokens(),
+        status.usage.estimated_cost_usd,
+        status.period_start
+    )
+}
@@ -9,8 +9,9 @@ use crate::network::zip_export_import::zip_export_import::{
 };
 use crate::network::Node;
 use crate::tools::tool_definitions::definition_generation::{generate_tool_definitions, get_rust_tools};
+use crate::tools::tool_implementation::native_tools::agent_memory::{AGENT_RECALL_TOOL_KEY, AGENT_REMEMBER_TOOL_KEY};
 use crate::tools::tool_execution::{
-    execute_agent_dynamic::execute_agent_tool, execution_coordinator::override_tool_config, execution_custom::try_to_execute_rust_tool, execution_header_generator::{check_tool, generate_execution_environment}
+    execute_agent_dynamic::execute_agent_tool, execution_coordinator::{override_tool_config, resolve_secret_references}, oauth_refresh::{is_unauthorized_result, notify_oauth_reauth_required, refresh_tool_oauth_tokens}, execution_custom::try_to_execute_rust_tool, execution_header_generator::{check_tool, generate_execution_environment}
 };
 use crate::utils::environment::{fetch_node_environment, NodeEnvironment};
 use ed25519_dalek::SigningKey;
@@ -23,7 +24,7 @@ use zoo_message_primitives::schemas::zoo_tools::CodeLanguage;
 use zoo_message_primitives::schemas::wallet_mixed::AddressBalanceList;
 use zoo_message_primitives::schemas::x402_types::Network;
 use zoo_message_primitives::schemas::{
-    indexable_version::IndexableVersion, invoices::{Invoice, InvoiceStatusEnum}, job::JobLike, llm_providers::common_agent_llm_provider::ProviderOrAgent, zoo_name::ZooName, zoo_preferences::ZooInternalComms, zoo_tool_offering::{ToolPrice, UsageType, UsageTypeInquiry}, tool_router_key::ToolRouterKey, ws_types::{PaymentMetadata, WSMessageType, WidgetMetadata}, x402_types::PaymentRequirements
+    indexable_version::IndexableVersion, invoices::{Invoice, InvoiceStatusEnum}, job::JobLike, llm_providers::common_agent_llm_provider::ProviderOrAgent, zoo_name::ZooName, zoo_preferences::ZooInternalComms, zoo_tool_offering::{ToolPrice, UsageType, UsageTypeInquiry}, tool_router_key::ToolRouterKey, ws_types::{OAuthMetadata, PaymentMetadata, WSMessageType, WidgetMetadata}, x402_types::PaymentRequirements
 };
 use zoo_message_primitives::zoo_message::zoo_message_schemas::{AssociatedUI, WSTopic};
 use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
@@ -420,20 +421,15 @@ impl ToolRouter {
                     let embedding_generator = embedding_generator.clone();
                     async move {
                         if r#type == "Tool" {
-                            // Try to see if a tool with the same routerKey is already installed.
-                            let do_install = match db.get_tool_by_key(router_key) {
-                                Ok(existing_tool) => {
-                                    // Compare version numbers:
-                                    // The local version is existing_tool.version(),
-                                    // the remote version is new_version (string from the JSON).
-                                    // We parse them into IndexableVersion and compare.
-                                    let local_ver = existing_tool.version_indexable()?;
-                                    let remote_ver = IndexableVersion::from_string(new_version)?;
-                                    Ok(remote_ver > local_ver)
-                                }
-                                Err(SqliteManagerError::ToolNotFound(_)) => Ok(true),
-                                Err(e) => Err(ToolError::DatabaseError(e.to_string())),
-                            }?;
+                            // Install the remote version if it's newer than every installed version.
+                            // It's added next to them, a rolled back tool keeps its active version.
+                            let installed_versions = db
+                                .get_tool_versions(router_key)
+                                .map_err(|e| ToolError::DatabaseError(e.to_string()))?;
+                            let remote_ver = IndexableVersion::from_string(new_version)?;
+                            let do_install = installed_versions
+                                .first()
+                                .is_none_or(|latest| remote_ver.get_version_number() > *latest);
 
                             if !do_install {
                                 return Ok::<_, ToolError>(("skipped", tool_name.clone()));
@@ -717,6 +713,16 @@ impl ToolRouter {
         }
     }
 
+    /// Resolves a tool reference the way agents use them: an exact version, a semver
+    /// requirement like `^1.2` or, without a version, the active version of the tool.
+    pub async fn get_tool_by_router_key(&self, tool_router_key: &ToolRouterKey) -> Result<Option<ZooTool>, ToolError> {
+        match self.sqlite_manager.get_tool_by_router_key(tool_router_key) {
+            Ok(tool) => Ok(Some(tool)),
+            Err(SqliteManagerError::ToolNotFound(_)) => Ok(None),
+            Err(e) => Err(ToolError::DatabaseError(e.to_string())),
+        }
+    }
+
     pub async fn vector_search_enabled_tools(
         &self,
         query: &str,
@@ -762,6 +768,25 @@ impl ToolRouter {
         Ok(tool_headers)
     }
 
+    /// Tools whose OAuth connection isn't authorized (or whose refresh token was rejected) fail with
+    /// the login URL. The user gets a widget to log in again, and the error is passed through.
+    fn notify_oauth_error(context: &dyn InferenceChainContextTrait, tool_key: &str, error: ToolError) -> ToolError {
+        if let ToolError::OAuthError(authorization_url) = &error {
+            let ws_manager = context.ws_manager_trait();
+            let inbox = context.full_job().conversation_inbox_name.to_string();
+            let metadata = OAuthMetadata {
+                tool_key: tool_key.to_string(),
+                connection_name: None,
+                authorization_url: authorization_url.clone(),
+                error_message: Some(error.to_string()),
+            };
+            tokio::spawn(async move {
+                notify_oauth_reauth_required(&ws_manager, inbox, metadata).await;
+            });
+        }
+        error
+    }
+
     pub async fn call_function(
         &self,
         function_call: FunctionCall,
@@ -828,6 +853,14 @@ impl ToolRouter {
             _ => {}
         }
 
+        // Secret references in the config are only resolved for this call
+        let mut zoo_tool = zoo_tool.clone();
+        if let Some(config) = zoo_tool.config_mut() {
+            resolve_secret_references(&self.sqlite_manager, config)?;
+        }
+        resolve_secret_references(&self.sqlite_manager, &mut function_config_vec)?;
+        let zoo_tool = &zoo_tool;
+
         match zoo_tool {
             ZooTool::MCPServer(mcp_server_tool, _is_enabled) => {
                 let mcp_server_ref = mcp_server_tool.mcp_server_ref.clone().parse::<i64>().map_err(|e| {
@@ -881,17 +914,18 @@ impl ToolRouter {
                             ToolError::ExecutionError(format!("Failed to generate tool definitions: {:?}", e))
                         })?;
 
-                let envs = generate_execution_environment(
+                let mut envs = generate_execution_environment(
                     context.db(),
                     context.agent().clone().get_id().to_string(),
                     tool_id.clone(),
                     app_id.clone(),
-                    agent_id,
+                    agent_id.clone(),
                     zoo_tool.tool_router_key().to_string_without_version().clone(),
                     app_id.clone(),
                     &python_tool.oauth,
                 )
-                .await?;
+                .await
+                .map_err(|e| Self::notify_oauth_error(context, &tool_id, e))?;
 
                 check_tool(
                     zoo_tool.tool_router_key().to_string_without_version().clone(),
@@ -901,23 +935,53 @@ impl ToolRouter {
                     &python_tool.oauth,
                 )?;
 
-                let result = python_tool
-                    .run(
-                        envs,
-                        node_env.api_listen_address.ip().to_string(),
-                        node_env.api_listen_address.port(),
-                        support_files,
-                        function_args,
-                        function_config_vec,
-                        node_storage_path,
-                        app_id.clone(),
-                        tool_id.clone(),
-                        node_name,
-                        false,
-                        Some(tool_id),
-                        Some(all_files),
-                    )
-                    .await?;
+                let mut oauth_retried = false;
+                let result = loop {
+                    let result = python_tool
+                        .run(
+                            envs,
+                            node_env.api_listen_address.ip().to_string(),
+                            node_env.api_listen_address.port(),
+                            support_files.clone(),
+                            function_args.clone(),
+                            function_config_vec.clone(),
+                            node_storage_path.clone(),
+                            app_id.clone(),
+                            tool_id.clone(),
+                            node_name.clone(),
+                            false,
+                            Some(tool_id.clone()),
+                            Some(all_files.clone()),
+                        )
+                        .await;
+                    match result {
+                        // The provider didn't accept the access token: refresh it and run the tool once more
+                        _ if !oauth_retried
+                            && is_unauthorized_result(&result)
+                            && refresh_tool_oauth_tokens(
+                                &context.db(),
+                                &zoo_tool.tool_router_key().to_string_without_version(),
+                                &python_tool.oauth,
+                            )
+                            .await =>
+                        {
+                            oauth_retried = true;
+                            envs = generate_execution_environment(
+                                context.db(),
+                                context.agent().clone().get_id().to_string(),
+                                tool_id.clone(),
+                                app_id.clone(),
+                                agent_id.clone(),
+                                zoo_tool.tool_router_key().to_string_without_version().clone(),
+                                app_id.clone(),
+                                &python_tool.oauth,
+                            )
+                            .await
+                            .map_err(|e| Self::notify_oauth_error(context, &tool_id, e))?;
+                        }
+                        result => break result?,
+                    }
+                };
                 let result_str = serde_json::to_string(&result)
                     .map_err(|e| LLMProviderError::FunctionExecutionError(e.to_string()))?;
                 return Ok(ToolCallFunctionResponse {
@@ -959,6 +1023,19 @@ impl ToolRouter {
                     &None,
                 )?;
 
+                // The memory tools act on the memory of the agent running the job, whatever the call says
+                let mut function_args = function_args;
+                if tool_id == AGENT_REMEMBER_TOOL_KEY || tool_id == AGENT_RECALL_TOOL_KEY {
+                    function_args.remove("agent_id");
+                    if let Some(agent_id) = &agent_id {
+                        function_args.insert("agent_id".to_string(), Value::String(agent_id.clone()));
+                        function_args.insert(
+                            "job_id".to_string(),
+                            Value::String(context.full_job().job_id().to_string()),
+                        );
+                    }
+                }
+
                 let result = try_to_execute_rust_tool(
                     &zoo_tool.tool_router_key().to_string_without_version().clone(),
                     function_args,
@@ -1061,17 +1138,18 @@ impl ToolRouter {
                             ToolError::ExecutionError(format!("Failed to generate tool definitions: {:?}", e))
                         })?;
 
-                let envs = generate_execution_environment(
+                let mut envs = generate_execution_environment(
                     context.db(),
                     context.agent().clone().get_id().to_string(),
                     app_id.clone(),
                     tool_id.clone(),
-                    agent_id,
+                    agent_id.clone(),
                     zoo_tool.tool_router_key().to_string_without_version().clone(),
                     app_id.clone(),
                     &deno_tool.oauth,
                 )
-                .await?;
+                .await
+                .map_err(|e| Self::notify_oauth_error(context, &tool_id, e))?;
 
                 check_tool(
                     zoo_tool.tool_router_key().to_string_without_version().clone(),
@@ -1081,23 +1159,53 @@ impl ToolRouter {
                     &deno_tool.oauth,
                 )?;
 
-                let result = deno_tool
-                    .run(
-                        envs,
-                        node_env.api_listen_address.ip().to_string(),
-                        node_env.api_listen_address.port(),
-                        support_files,
-                        function_args,
-                        function_config_vec,
-                        node_storage_path,
-                        app_id,
-                        tool_id.clone(),
-                        node_name,
-                        false,
-                        Some(tool_id),
-                        Some(all_files),
-                    )
-                    .await?;
+                let mut oauth_retried = false;
+                let result = loop {
+                    let result = deno_tool
+                        .run(
+                            envs,
+                            node_env.api_listen_address.ip().to_string(),
+                            node_env.api_listen_address.port(),
+                            support_files.clone(),
+                            function_args.clone(),
+                            function_config_vec.clone(),
+                            node_storage_path.clone(),
+                            app_id.clone(),
+                            tool_id.clone(),
+                            node_name.clone(),
+                            false,
+                            Some(tool_id.clone()),
+                            Some(all_files.clone()),
+                        )
+                        .await;
+                    match result {
+                        // The provider didn't accept the access token: refresh it and run the tool once more
+                        _ if !oauth_retried
+                            && is_unauthorized_result(&result)
+                            && refresh_tool_oauth_tokens(
+                                &context.db(),
+                                &zoo_tool.tool_router_key().to_string_without_version(),
+                                &deno_tool.oauth,
+                            )
+                            .await =>
+                        {
+                            oauth_retried = true;
+                            envs = generate_execution_environment(
+                                context.db(),
+                                context.agent().clone().get_id().to_string(),
+                                app_id.clone(),
+                                tool_id.clone(),
+                                agent_id.clone(),
+                                zoo_tool.tool_router_key().to_string_without_version().clone(),
+                                app_id.clone(),
+                                &deno_tool.oauth,
+                            )
+                            .await
+                            .map_err(|e| Self::notify_oauth_error(context, &tool_id, e))?;
+                        }
+                        result => break result?,
+                    }
+                };
 
                 let result_str = serde_json::to_string(&result)
                     .map_err(|e| LLMProviderError::FunctionExecutionError(e.to_string()))?;
@@ -1406,9 +1514,13 @@ impl ToolRouter {
             return Err(LLMProviderError::FunctionNotFound(js_tool_name.to_string()));
         }
 
-        let zoo_tool = zoo_tool.unwrap();
+        let mut zoo_tool = zoo_tool.unwrap();
         let function_config = zoo_tool.get_config_from_env();
-        let function_config_vec: Vec<ToolConfig> = function_config.into_iter().collect();
+        let mut function_config_vec: Vec<ToolConfig> = function_config.into_iter().collect();
+        if let Some(config) = zoo_tool.config_mut() {
+            resolve_secret_references(&self.sqlite_manager, config)?;
+        }
+        resolve_secret_references(&self.sqlite_manager, &mut function_config_vec)?;
 
         let js_tool = match zoo_tool.clone() {
             ZooTool::Deno(js_tool, _) => js_tool,
@@ -1558,8 +1670,124 @@ impl ToolRouter {
         }
     }
 
+    /// Returns the function names (as the LLM sees them) of the `candidates` that are closest to a
+    /// `name` the LLM tried to call but that doesn't exist. Names with a small edit distance come
+    /// first, followed by the tools whose embeddings are closest to the requested name.
+    pub async fn closest_tool_names(&self, name: &str, candidates: &[ZooTool], limit: usize) -> Vec<String> {
+        let candidate_names: Vec<(String, String)> = candidates
+            .iter()
+            .map(|tool| {
+                (
+                    tool.tool_router_key().to_string_without_version(),
+                    ToolRouterKey::sanitize(&tool.tool_router_key().name),
+                )
+            })
+            .collect();
+
+        let mut closest = rank_names_by_similarity(
+            name,
+            &candidate_names.iter().map(|(_, name)| name.clone()).collect::<Vec<_>>(),
+        );
+
+        // Typos are covered by the edit distance, but a made-up name ("search_internet" instead of
+        // "duckduckgo_search") needs a semantic match.
+        let query = name.replace('_', " ");
+        match self.vector_search_all_tools(&query, (limit * 2) as u64).await {
+            Ok(headers) => {
+                for header in headers {
+                    if let Some((_, function_name)) =
+                        candidate_names.iter().find(|(key, _)| *key == header.tool_router_key)
+                    {
+                        if !closest.contains(function_name) {
+                            closest.push(function_name.clone());
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                zoo_log(
+                    ZooLogOption::Node,
+                    ZooLogLevel::Error,
+                    &format!("Failed to search tools similar to {}: {:?}", name, e),
+                );
+            }
+        }
+
+        closest.truncate(limit);
+        closest
+    }
+
     pub async fn get_default_tool_router_keys_as_set(&self) -> std::collections::HashSet<String> {
         let default_keys = self.default_tool_router_keys.lock().await;
         default_keys.iter().cloned().collect()
     }
 }
+
+/// Returns the `names` that are close enough to `name` to likely be what was meant, closest first.
+/// A name is close if its edit distance is at most a third of the longer name, or if one name
+/// contains the other.
+fn rank_names_by_similarity(name: &str, names: &[String]) -> Vec<String> {
+    let name = name.to_lowercase();
+    let mut ranked: Vec<(usize, &String)> = names
+        .iter()
+        .filter_map(|candidate| {
+            let lowercased = candidate.to_lowercase();
+            let distance = levenshtein_distance(&name, &lowercased);
+            let max_len = name.chars().count().max(lowercased.chars().count());
+            if distance * 3 <= max_len || lowercased.contains(&name) || name.contains(&lowercased) {
+                Some((distance, candidate))
+            } else {
+                None
+            }
+        })
+        .collect();
+    ranked.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(b.1)));
+    ranked.into_iter().map(|(_, candidate)| candidate.clone()).collect()
+}
+
+fn levenshtein_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein_distance() {
+        assert_eq!(levenshtein_distance("", ""), 0);
+        assert_eq!(levenshtein_distance("abc", ""), 3);
+        assert_eq!(levenshtein_distance("kitten", "sitting"), 3);
+        assert_eq!(levenshtein_distance("duckduckgo_search", "duckduckgo_search"), 0);
+    }
+
+    #[test]
+    fn test_rank_names_by_similarity() {
+        let names = vec![
+            "duckduckgo_search".to_string(),
+            "zoo_sqlite_query_executor".to_string(),
+            "youtube_transcript".to_string(),
+            "math_expression_evaluator".to_string(),
+        ];
+
+        assert_eq!(
+            rank_names_by_similarity("duckduckgo_serch", &names),
+            vec!["duckduckgo_search".to_string()]
+        );
+        assert_eq!(
+            rank_names_by_similarity("sqlite_query", &names),
+            vec!["zoo_sqlite_query_executor".to_string()]
+        );
+        assert!(rank_names_by_similarity("send_email", &names).is_empty());
+    }
+}
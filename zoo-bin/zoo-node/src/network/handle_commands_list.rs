@@ -648,6 +648,39 @@ impl Node {
                     .await;
                 });
             }
+            NodeCommand::V2ApiOpenAIChatCompletions { bearer, payload, res } => {
+                let job_manager_clone = self.job_manager.clone().unwrap();
+                let node_name_clone = self.node_name.clone();
+                let db_clone = self.db.clone();
+                let identity_manager_clone = self.identity_manager.clone();
+                let ws_manager_clone = self.ws_manager.clone();
+                let encryption_secret_key_clone = self.encryption_secret_key.clone();
+                let encryption_public_key_clone = self.encryption_public_key;
+                let signing_secret_key_clone = self.identity_secret_key.clone();
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_openai_chat_completions(
+                        db_clone,
+                        node_name_clone,
+                        identity_manager_clone,
+                        job_manager_clone,
+                        ws_manager_clone,
+                        bearer,
+                        payload,
+                        encryption_secret_key_clone,
+                        encryption_public_key_clone,
+                        signing_secret_key_clone,
+                        res,
+                    )
+                    .await;
+                });
+            }
+            NodeCommand::V2ApiOpenAIListModels { bearer, res } => {
+                let db_clone = Arc::clone(&self.db);
+                let node_name_clone = self.node_name.clone();
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_openai_list_models(db_clone, node_name_clone, bearer, res).await;
+                });
+            }
             NodeCommand::V2ApiAddMessagesGodMode {
                 bearer,
                 job_id,
@@ -1761,6 +1794,7 @@ impl Node {
                 llm_provider,
                 extra_config,
                 mounts,
+                max_price,
                 res,
             } => {
                 let db_clone = Arc::clone(&self.db);
@@ -1768,6 +1802,7 @@ impl Node {
                 let node_name = self.node_name.clone();
                 let job_manager = self.job_manager.clone().unwrap();
                 let identity_manager = self.identity_manager.clone();
+                let my_agent_payments_manager = self.my_agent_payments_manager.clone();
                 let encryption_secret_key = self.encryption_secret_key.clone();
                 let encryption_public_key = self.encryption_public_key;
                 let signing_secret_key = self.identity_secret_key.clone();
@@ -1786,10 +1821,12 @@ impl Node {
                         extra_config,
                         identity_manager,
                         job_manager,
+                        my_agent_payments_manager,
                         encryption_secret_key,
                         encryption_public_key,
                         signing_secret_key,
                         mounts,
+                        max_price,
                         res,
                     )
                     .await;
@@ -1809,6 +1846,7 @@ impl Node {
                 let node_name = self.node_name.clone();
                 let job_manager = self.job_manager.clone().unwrap();
                 let identity_manager = self.identity_manager.clone();
+                let my_agent_payments_manager = self.my_agent_payments_manager.clone();
                 let encryption_secret_key = self.encryption_secret_key.clone();
                 let encryption_public_key = self.encryption_public_key;
                 let signing_secret_key = self.identity_secret_key.clone();
@@ -1825,6 +1863,7 @@ impl Node {
                         extra_config,
                         identity_manager,
                         job_manager,
+                        my_agent_payments_manager,
                         encryption_secret_key,
                         encryption_public_key,
                         signing_secret_key,
@@ -2803,6 +2842,269 @@ impl Node {
                     let _ = Node::v2_api_get_zoo_tool(db_clone, bearer, payload, serialize_config, res).await;
                 });
             }
+            NodeCommand::V2ApiGetUsage { bearer, query, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_get_usage(db_clone, bearer, query, res).await;
+                });
+            }
+            NodeCommand::V2ApiGetUsageBudgets { bearer, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_get_usage_budgets(db_clone, bearer, res).await;
+                });
+            }
+            NodeCommand::V2ApiSetUsageBudget { bearer, budget, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_set_usage_budget(db_clone, bearer, budget, res).await;
+                });
+            }
+            NodeCommand::V2ApiRemoveUsageBudget {
+                bearer,
+                scope,
+                scope_id,
+                period,
+                res,
+            } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_remove_usage_budget(db_clone, bearer, scope, scope_id, period, res).await;
+                });
+            }
+            NodeCommand::V2ApiAuthorizeApiKey {
+                bearer,
+                route,
+                scope,
+                res,
+            } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_authorize_api_key(db_clone, bearer, route, scope, res).await;
+                });
+            }
+            NodeCommand::V2ApiCreateApiKey { bearer, request, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_create_api_key(db_clone, bearer, request, res).await;
+                });
+            }
+            NodeCommand::V2ApiListApiKeys { bearer, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_list_api_keys(db_clone, bearer, res).await;
+                });
+            }
+            NodeCommand::V2ApiRevokeApiKey { bearer, id, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_revoke_api_key(db_clone, bearer, id, res).await;
+                });
+            }
+            NodeCommand::V2ApiSetSecret { bearer, request, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_set_secret(db_clone, bearer, request, res).await;
+                });
+            }
+            NodeCommand::V2ApiListSecrets { bearer, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_list_secrets(db_clone, bearer, res).await;
+                });
+            }
+            NodeCommand::V2ApiRemoveSecret { bearer, name, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_remove_secret(db_clone, bearer, name, res).await;
+                });
+            }
+            NodeCommand::V2ApiGetSecretsKey { bearer, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_get_secrets_key(db_clone, bearer, res).await;
+                });
+            }
+            NodeCommand::V2ApiRotateSecretsKey { bearer, request, res } => {
+                let db_clone = Arc::clone(&self.db);
+                let identity_secret_key_clone = self.identity_secret_key.clone();
+                tokio::spawn(async move {
+                    let _ =
+                        Node::v2_api_rotate_secrets_key(db_clone, identity_secret_key_clone, bearer, request, res)
+                            .await;
+                });
+            }
+            NodeCommand::V2ApiExportSecrets { bearer, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_export_secrets(db_clone, bearer, res).await;
+                });
+            }
+            NodeCommand::V2ApiListMcpPrompts { res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_list_mcp_prompts(db_clone, res).await;
+                });
+            }
+            NodeCommand::V2ApiListMcpResources { res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_list_mcp_resources(db_clone, res).await;
+                });
+            }
+            NodeCommand::V2ApiReadMcpResource { uri, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_read_mcp_resource(db_clone, uri, res).await;
+                });
+            }
+            NodeCommand::V2ApiGetMcpResourceVersion { uri, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_get_mcp_resource_version(db_clone, uri, res).await;
+                });
+            }
+            NodeCommand::V2ApiAuthorizeMcpBearer { bearer, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_authorize_mcp_bearer(db_clone, bearer, res).await;
+                });
+            }
+            NodeCommand::V2ApiGetMCPServerLogs {
+                bearer,
+                mcp_server_id,
+                limit,
+                res,
+            } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_get_mcp_server_logs(db_clone, bearer, mcp_server_id, limit, res).await;
+                });
+            }
+            NodeCommand::V2ApiGetToolUpgradePlan {
+                bearer,
+                tool_router_key,
+                from_version,
+                to_version,
+                res,
+            } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_get_tool_upgrade_plan(
+                        db_clone,
+                        bearer,
+                        tool_router_key,
+                        from_version,
+                        to_version,
+                        res,
+                    )
+                    .await;
+                });
+            }
+            NodeCommand::V2ApiRollbackTool {
+                bearer,
+                tool_router_key,
+                res,
+            } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_rollback_tool(db_clone, bearer, tool_router_key, res).await;
+                });
+            }
+            NodeCommand::V2ApiSetToolActiveVersion {
+                bearer,
+                tool_router_key,
+                version,
+                res,
+            } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_set_tool_active_version(db_clone, bearer, tool_router_key, version, res).await;
+                });
+            }
+            NodeCommand::V2ApiCreateNodeBackup { bearer, request, res } => {
+                let db_clone = Arc::clone(&self.db);
+                let node_name = self.node_name.clone();
+                let identity_secret_key = self.identity_secret_key.clone();
+                let secrets_file_path = self.secrets_file_path.clone();
+                let node_env = fetch_node_environment();
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_create_node_backup(
+                        db_clone,
+                        node_name,
+                        identity_secret_key,
+                        secrets_file_path,
+                        node_env,
+                        bearer,
+                        request,
+                        res,
+                    )
+                    .await;
+                });
+            }
+            NodeCommand::V2ApiListNodeBackups { bearer, res } => {
+                let db_clone = Arc::clone(&self.db);
+                let node_env = fetch_node_environment();
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_list_node_backups(db_clone, node_env, bearer, res).await;
+                });
+            }
+            NodeCommand::V2ApiDownloadNodeBackup { bearer, file_name, res } => {
+                let db_clone = Arc::clone(&self.db);
+                let node_env = fetch_node_environment();
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_download_node_backup(db_clone, node_env, bearer, file_name, res).await;
+                });
+            }
+            NodeCommand::V2ApiUploadNodeBackup { bearer, file_data, res } => {
+                let db_clone = Arc::clone(&self.db);
+                let node_env = fetch_node_environment();
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_upload_node_backup(db_clone, node_env, bearer, file_data, res).await;
+                });
+            }
+            NodeCommand::V2ApiRestoreNodeBackup { bearer, request, res } => {
+                let db_clone = Arc::clone(&self.db);
+                let identity_public_key = self.identity_public_key;
+                let secrets_file_path = self.secrets_file_path.clone();
+                let node_env = fetch_node_environment();
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_restore_node_backup(
+                        db_clone,
+                        identity_public_key,
+                        secrets_file_path,
+                        node_env,
+                        bearer,
+                        request,
+                        res,
+                    )
+                    .await;
+                });
+            }
+            NodeCommand::V2ApiSearchMessages { bearer, request, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_search_messages(db_clone, bearer, request, res).await;
+                });
+            }
+            NodeCommand::V2ApiListAgentMemories { bearer, agent_id, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_list_agent_memories(db_clone, bearer, agent_id, res).await;
+                });
+            }
+            NodeCommand::V2ApiUpdateAgentMemory { bearer, request, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_update_agent_memory(db_clone, bearer, request, res).await;
+                });
+            }
+            NodeCommand::V2ApiRemoveAgentMemory { bearer, request, res } => {
+                let db_clone = Arc::clone(&self.db);
+                tokio::spawn(async move {
+                    let _ = Node::v2_api_remove_agent_memory(db_clone, bearer, request, res).await;
+                });
+            }
             _ => (),
         }
     }
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::sync::Weak;
+
+use tokio::sync::broadcast::error::RecvError;
+use zoo_mcp::session_pool::McpSessionPool;
+use zoo_message_primitives::schemas::zoo_name::ZooName;
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
+use zoo_sqlite::SqliteManager;
+use zoo_tools_primitives::tools::mcp_server_tool::MCPServerTool;
+use zoo_tools_primitives::tools::tool_config::{BasicConfig, ToolConfig};
+use zoo_tools_primitives::tools::zoo_tool::ZooTool;
+
+use super::mcp_manager;
+use super::Node;
+
+impl Node {
+    /// Re-imports the tools of an MCP server each time its pooled session reports that the
+    /// server's tool list changed, so the tool router matches what the server offers.
+    pub fn spawn_mcp_tool_refresh_service(db: Weak<SqliteManager>, node_name: ZooName) {
+        let mut changes = McpSessionPool::global().subscribe_tool_list_changes();
+        tokio::spawn(async move {
+            loop {
+                let mcp_server_id = match changes.recv().await {
+                    Ok(mcp_server_id) => mcp_server_id,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+                let Some(db) = db.upgrade() else {
+                    break;
+                };
+
+                match Self::refresh_mcp_server_tools(&db, mcp_server_id, &node_name).await {
+                    Ok(count) => zoo_log(
+                        ZooLogOption::Node,
+                        ZooLogLevel::Info,
+                        &format!("Refreshed {} tools of MCP server {}", count, mcp_server_id),
+                    ),
+                    Err(e) => zoo_log(
+                        ZooLogOption::Node,
+                        ZooLogLevel::Error,
+                        &format!("Failed to refresh the tools of MCP server {}: {}", mcp_server_id, e),
+                    ),
+                }
+            }
+        });
+    }
+
+    /// Replaces the stored tools of an MCP server with the ones it lists now. Tools that are
+    /// still offered keep their configuration and activation.
+    pub async fn refresh_mcp_server_tools(
+        db: &SqliteManager,
+        mcp_server_id: i64,
+        node_name: &ZooName,
+    ) -> Result<usize, String> {
+        let mcp_server = db
+            .get_mcp_server(mcp_server_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("No MCP Server found with ID: {}", mcp_server_id))?;
+        if !mcp_server.is_enabled {
+            return Ok(0);
+        }
+
+        let env = mcp_server.env.clone().unwrap_or_default();
+        let spec = MCPServerTool::session_spec(&mcp_server, env.clone())
+            .ok_or_else(|| format!("MCP Server {} has no ID", mcp_server.name))?;
+        let tools = McpSessionPool::global()
+            .list_tools(&spec)
+            .await
+            .map_err(|e| e.message)?;
+
+        let existing: HashMap<String, MCPServerTool> = db
+            .get_all_tools_from_mcp_server(mcp_server_id.to_string())
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|tool| (tool.mcp_server_tool.clone(), tool))
+            .collect();
+
+        // Same defaults as when the server is added: its env becomes the tools config
+        let tools_config: Vec<ToolConfig> = env
+            .iter()
+            .map(|(key, value)| {
+                ToolConfig::BasicConfig(BasicConfig {
+                    key_name: key.clone(),
+                    description: format!("Configuration for {}", key),
+                    required: true,
+                    type_name: Some("string".to_string()),
+                    key_value: Some(serde_json::Value::String(value.to_string())),
+                })
+            })
+            .collect();
+
+        db.delete_all_tools_from_mcp_server(mcp_server_id.to_string())
+            .map_err(|e| e.to_string())?;
+        let mut count = 0;
+        for tool in tools {
+            let mut zoo_tool = mcp_manager::convert_to_zoo_tool(
+                &tool,
+                &mcp_server.name,
+                &mcp_server_id.to_string(),
+                &mcp_server.get_command_hash(),
+                &node_name.to_string(),
+                tools_config.clone(),
+            );
+            if let (ZooTool::MCPServer(mcp_tool, _), Some(previous)) = (&mut zoo_tool, existing.get(tool.name.as_ref()))
+            {
+                mcp_tool.config = previous.config.clone();
+                mcp_tool.activated = previous.activated;
+                mcp_tool.mcp_enabled = previous.mcp_enabled;
+            }
+            match db.add_tool(zoo_tool).await {
+                Ok(_) => count += 1,
+                Err(e) => zoo_log(
+                    ZooLogOption::Node,
+                    ZooLogLevel::Error,
+                    &format!(
+                        "Failed to add tool {} of MCP server {}: {}",
+                        tool.name, mcp_server_id, e
+                    ),
+                ),
+            }
+        }
+        Ok(count)
+    }
+}
@@ -5,9 +5,12 @@ pub mod handle_commands_list;
 pub mod libp2p_manager;
 
 pub mod mcp_manager;
+pub mod mcp_tool_refresh;
 pub mod network_limiter;
 pub mod network_manager;
 pub mod network_manager_utils;
+pub mod node_backup;
+pub mod node_config_reload;
 pub mod node_error;
 pub mod node_shareable_logic;
 pub mod v1_api;
@@ -15,6 +15,7 @@ use crate::managers::IdentityManager;
 
 use crate::network::libp2p_manager::verifying_key_to_peer_id;
 use crate::network::ws_routes::run_ws_api;
+use crate::tools::tool_execution::oauth_refresh::spawn_oauth_refresh_service;
 use crate::wallet::coinbase_mpc_wallet::CoinbaseMPCWallet;
 use crate::wallet::wallet_manager::WalletManager;
 use async_channel::Receiver;
@@ -42,6 +43,7 @@ use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLo
 use zoo_message_primitives::zoo_utils::zoo_path::ZooPath;
 use zoo_message_primitives::zoo_utils::signatures::clone_signature_secret_key;
 use zoo_sqlite::errors::SqliteManagerError;
+use zoo_sqlite::secrets_vault::SecretsKeySource;
 use zoo_sqlite::SqliteManager;
 use std::fs;
 use std::path::Path;
@@ -137,7 +139,8 @@ pub struct Node {
 
 impl Node {
     // Construct a new node. Returns a `Result` which is `Ok` if the node was successfully created,
-    // and `Err` otherwise.
+    // and `Err` if its identity is invalid, its database can't be opened or its secrets vault can't
+    // be unlocked.
     #[allow(clippy::too_many_arguments)]
     pub async fn new(
         node_name: String,
@@ -158,12 +161,10 @@ impl Node {
         default_embedding_model: EmbeddingModelType,
         supported_embedding_models: Vec<EmbeddingModelType>,
         api_v2_key: Option<String>,
-    ) -> Arc<Mutex<Node>> {
-        // if is_valid_node_identity_name_and_no_subidentities is false panic
-        match ZooName::new(node_name.to_string().clone()) {
-            Ok(_) => (),
-            Err(_) => panic!("Invalid node identity name: {}", node_name),
-        }
+    ) -> Result<Arc<Mutex<Node>>, NodeError> {
+        let node_name = ZooName::new(node_name.clone()).map_err(|_| NodeError {
+            message: format!("Invalid node identity name: {}", node_name),
+        })?;
 
         // Initialize default RemoteEmbeddingGenerator if none provided
         let embedding_generator = embedding_generator.unwrap_or_else(RemoteEmbeddingGenerator::new_default);
@@ -171,30 +172,63 @@ impl Node {
         // Initialize SqliteManager
         let embedding_api_url = embedding_generator.api_url.clone();
         let db_arc = Arc::new(
-            SqliteManager::new(main_db_path.clone(), embedding_api_url, default_embedding_model.clone())
-                .unwrap_or_else(|e| {
+            SqliteManager::new(main_db_path.clone(), embedding_api_url, default_embedding_model.clone()).map_err(
+                |e| {
                     zoo_log(
                         ZooLogOption::Database,
                         ZooLogLevel::Error,
                         &format!("Failed to open database {main_db_path}: {e:?}"),
                     );
-                    panic!("Failed to open database: {}", main_db_path)
-                }),
+                    NodeError {
+                        message: format!("Failed to open database {}: {}", main_db_path, e),
+                    }
+                },
+            )?,
         );
 
         // Get public keys, and update the local node keys in the db
         let identity_public_key = identity_secret_key.verifying_key();
         let encryption_public_key = EncryptionPublicKey::from(&encryption_secret_key);
-        let node_name = ZooName::new(node_name).unwrap();
         {
-            match db_arc.update_local_node_keys(node_name.clone(), encryption_public_key, identity_public_key) {
-                Ok(_) => (),
-                Err(e) => panic!("Failed to update local node keys: {}", e),
-            }
+            db_arc
+                .update_local_node_keys(node_name.clone(), encryption_public_key, identity_public_key)
+                .map_err(|e| NodeError {
+                    message: format!("Failed to update local node keys: {}", e),
+                })?;
             // TODO: maybe check if the keys in the Blockchain match and if not, then prints a warning message to update
             // the keys
         }
 
+        // Unlock the secrets vault so provider API keys, tool configs and OAuth tokens are stored encrypted
+        {
+            let key_source = match std::env::var("NODE_SECRETS_PASSPHRASE") {
+                Ok(passphrase) if !passphrase.is_empty() => SecretsKeySource::Passphrase(passphrase),
+                _ => SecretsKeySource::NodeIdentity(identity_secret_key.to_bytes().to_vec()),
+            };
+            match db_arc.unlock_secrets_vault(&key_source) {
+                Ok(key_info) => zoo_log(
+                    ZooLogOption::Database,
+                    ZooLogLevel::Info,
+                    &format!(
+                        "Secrets vault unlocked with key {} ({})",
+                        key_info.key_id,
+                        key_info.key_source.as_str()
+                    ),
+                ),
+                Err(e) => {
+                    let message = format!(
+                        "Failed to unlock the secrets vault: {}. Start the node with the NODE_SECRETS_PASSPHRASE \
+                         it was protected with, or without one if it was protected by the node identity. To \
+                         change how it's protected, start the node with the current setting and call \
+                         /v2/rotate_secrets_key",
+                        e
+                    );
+                    zoo_log(ZooLogOption::Database, ZooLogLevel::Error, &message);
+                    return Err(NodeError { message });
+                }
+            }
+        }
+
         // Setup Identity Manager
         let db_weak = Arc::downgrade(&db_arc);
         let subidentity_manager = IdentityManager::new(Arc::downgrade(&db_arc), node_name.clone())
@@ -263,7 +297,11 @@ impl Node {
                 }
             },
             Err(SqliteManagerError::WalletManagerNotFound) => None,
-            Err(e) => panic!("Failed to read wallet manager from database: {}", e),
+            Err(e) => {
+                return Err(NodeError {
+                    message: format!("Failed to read wallet manager from database: {}", e),
+                })
+            }
         };
 
         // Update LanceDB in CoinbaseMPCWallet if it exists
@@ -339,7 +377,7 @@ impl Node {
 
         let llm_stopper = Arc::new(LLMStopper::new());
 
-        Arc::new(Mutex::new(Node {
+        Ok(Arc::new(Mutex::new(Node {
             node_name: node_name.clone(),
             identity_secret_key: clone_signature_secret_key(&identity_secret_key),
             identity_public_key,
@@ -375,7 +413,7 @@ impl Node {
             libp2p_manager: None,
             libp2p_event_sender: None,
             libp2p_task: None,
-        }))
+        })))
     }
 
     // Start the node's operations.
@@ -444,6 +482,16 @@ impl Node {
         }
 
         self.initialize_embedding_models().await?;
+        Self::spawn_embedding_migration(self.db.clone());
+        spawn_oauth_refresh_service(Arc::downgrade(&self.db), self.ws_manager_trait.clone());
+        Self::spawn_config_reload_service(
+            Arc::downgrade(&self.db),
+            self.identity_manager.clone(),
+            job_manager.clone(),
+            clone_signature_secret_key(&self.identity_secret_key),
+            self.ws_manager_trait.clone(),
+        );
+        Self::spawn_mcp_tool_refresh_service(Arc::downgrade(&self.db), self.node_name.clone());
         {
             // Starting the WebSocket server
             if let (Some(ws_manager), Some(ws_address)) = (&self.ws_manager, self.ws_address) {
@@ -721,6 +769,38 @@ impl Node {
         }
     }
 
+    // Re-embeds the stored files, tools and prompts in the background if the default
+    // embedding model changed (see SqliteManager::update_default_embedding_model), then embeds
+    // the agent memories that have no vector yet
+    pub fn spawn_embedding_migration(db: Arc<SqliteManager>) {
+        tokio::spawn(async move {
+            match db.run_pending_embedding_migration().await {
+                Ok(Some(migration)) => zoo_log(
+                    ZooLogOption::Database,
+                    ZooLogLevel::Info,
+                    &format!(
+                        "Embedding migration to {} {}: {}/{} files re-embedded",
+                        migration.to_model, migration.status, migration.migrated_files, migration.total_files
+                    ),
+                ),
+                Ok(None) => {}
+                Err(e) => zoo_log(
+                    ZooLogOption::Database,
+                    ZooLogLevel::Error,
+                    &format!("Embedding migration failed: {}", e),
+                ),
+            }
+            // Memories without a vector yet, e.g. the knowledge of agents stored before agent memories
+            if let Err(e) = db.embed_pending_agent_memories(None).await {
+                zoo_log(
+                    ZooLogOption::Database,
+                    ZooLogLevel::Error,
+                    &format!("Failed to embed the pending agent memories: {}", e),
+                );
+            }
+        });
+    }
+
     // A function that initializes the embedding models from the database
     async fn initialize_embedding_models(&self) -> Result<(), Box<dyn std::error::Error + Send>> {
         // Read the default embedding model from the database
@@ -0,0 +1,800 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use reqwest::StatusCode;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+use zoo_http_api::node_api_router::APIError;
+use zoo_message_primitives::schemas::node_backup::{
+    BackupManifest, BackupSection, CreateNodeBackupRequest, NodeBackupInfo, RestoreNodeBackupReport,
+    RestoreNodeBackupRequest, NODE_BACKUP_FORMAT_VERSION,
+};
+use zoo_message_primitives::schemas::zoo_name::ZooName;
+use zoo_message_primitives::zoo_utils::signatures::{
+    hash_signature_public_key, signature_public_key_to_string, string_to_signature_public_key,
+    string_to_signature_secret_key,
+};
+use zoo_message_primitives::zoo_utils::zoo_path::ZooPath;
+use zoo_sqlite::errors::SqliteManagerError;
+use zoo_sqlite::schema_migrations::{latest_migration_version, MIGRATIONS};
+use zoo_sqlite::secrets_vault::{open_with_passphrase, seal_with_passphrase};
+use zoo_sqlite::SqliteManager;
+
+use crate::utils::environment::NodeEnvironment;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const SIGNATURE_ENTRY: &str = "manifest.sig";
+const DATABASE_ENTRY: &str = "database.db";
+const NODE_KEYS_ENTRY: &str = "node_keys.txt";
+const SEALED_NODE_KEYS_ENTRY: &str = "node_keys.sealed";
+const VECTOR_FS_PREFIX: &str = "files/vector_fs/";
+const TOOL_ASSETS_PREFIX: &str = "files/tools/";
+
+fn bad_request(message: String) -> APIError {
+    APIError {
+        code: StatusCode::BAD_REQUEST.as_u16(),
+        error: "Bad Request".to_string(),
+        message,
+    }
+}
+
+fn internal_error(message: String) -> APIError {
+    APIError {
+        code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+        error: "Internal Server Error".to_string(),
+        message,
+    }
+}
+
+fn db_error(context: &str, err: SqliteManagerError) -> APIError {
+    match err {
+        SqliteManagerError::SchemaTooNew { .. } => bad_request(format!("{}: {}", context, err)),
+        _ => internal_error(format!("{}: {}", context, err)),
+    }
+}
+
+/// Backups are kept in `{node_storage_path}/backups`.
+pub fn backups_dir(node_env: &NodeEnvironment) -> PathBuf {
+    PathBuf::from(node_env.node_storage_path.clone().unwrap_or_default()).join("backups")
+}
+
+fn tool_assets_dir(node_env: &NodeEnvironment) -> PathBuf {
+    PathBuf::from(node_env.node_storage_path.clone().unwrap_or_default())
+        .join(".tools_storage")
+        .join("tools")
+}
+
+/// Only plain file names are accepted, anything else could point outside of the backups folder.
+fn is_valid_backup_file_name(file_name: &str) -> bool {
+    file_name.ends_with(".zip")
+        && !file_name.starts_with('.')
+        && file_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Resolves a backup in the backups folder.
+pub fn backup_file_path(node_env: &NodeEnvironment, file_name: &str) -> Result<PathBuf, APIError> {
+    if !is_valid_backup_file_name(file_name) {
+        return Err(bad_request(format!("Invalid backup file name: {}", file_name)));
+    }
+    Ok(backups_dir(node_env).join(file_name))
+}
+
+fn timestamp() -> String {
+    Utc::now().format("%Y%m%dT%H%M%S%3f").to_string()
+}
+
+fn hash_reader<R: Read>(reader: &mut R) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    io::copy(reader, &mut hasher)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Writes zip entries while keeping the hash of each one for the manifest.
+struct BackupWriter {
+    zip: ZipWriter<File>,
+    entries: BTreeMap<String, String>,
+}
+
+impl BackupWriter {
+    fn add_bytes(&mut self, name: &str, data: &[u8]) -> Result<(), APIError> {
+        self.zip
+            .start_file::<_, ()>(name, FileOptions::default())
+            .map_err(|e| internal_error(format!("Failed to add {} to the backup: {}", name, e)))?;
+        self.zip
+            .write_all(data)
+            .map_err(|e| internal_error(format!("Failed to write {} to the backup: {}", name, e)))?;
+        self.entries
+            .insert(name.to_string(), blake3::hash(data).to_hex().to_string());
+        Ok(())
+    }
+
+    /// Streams a file into the archive in chunks, large databases never sit in memory.
+    fn add_file(&mut self, name: &str, path: &Path) -> Result<(), APIError> {
+        let mut file =
+            File::open(path).map_err(|e| internal_error(format!("Failed to open {}: {}", path.display(), e)))?;
+        self.zip
+            .start_file::<_, ()>(name, FileOptions::default().large_file(true))
+            .map_err(|e| internal_error(format!("Failed to add {} to the backup: {}", name, e)))?;
+
+        let mut hasher = blake3::Hasher::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = file
+                .read(&mut buffer)
+                .map_err(|e| internal_error(format!("Failed to read {}: {}", path.display(), e)))?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            self.zip
+                .write_all(&buffer[..read])
+                .map_err(|e| internal_error(format!("Failed to write {} to the backup: {}", name, e)))?;
+        }
+        self.entries
+            .insert(name.to_string(), hasher.finalize().to_hex().to_string());
+        Ok(())
+    }
+
+    /// Adds every file under `dir` with its path relative to it after `prefix`.
+    fn add_dir(&mut self, prefix: &str, dir: &Path) -> Result<(), APIError> {
+        if !dir.exists() {
+            return Ok(());
+        }
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            let read_dir = fs::read_dir(&current)
+                .map_err(|e| internal_error(format!("Failed to read {}: {}", current.display(), e)))?;
+            for entry in read_dir {
+                let path = entry
+                    .map_err(|e| internal_error(format!("Failed to read {}: {}", current.display(), e)))?
+                    .path();
+                if path.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                let Ok(relative) = path.strip_prefix(dir) else {
+                    continue;
+                };
+                let relative = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                self.add_file(&format!("{}{}", prefix, relative), &path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Creates a backup archive of the selected sections in the backups folder. The archive holds a
+/// snapshot of the database with only those sections, the node keys (sealed with the passphrase
+/// if one is given), the vector FS and tool asset files, and a manifest signed with the identity
+/// key.
+pub fn create_node_backup(
+    db: &SqliteManager,
+    node_name: &ZooName,
+    identity_secret_key: &SigningKey,
+    secrets_file_path: &str,
+    node_env: &NodeEnvironment,
+    request: CreateNodeBackupRequest,
+) -> Result<NodeBackupInfo, APIError> {
+    let sections: BTreeSet<BackupSection> = request
+        .sections
+        .unwrap_or_else(|| BackupSection::ALL.to_vec())
+        .into_iter()
+        .collect();
+    if sections.is_empty() {
+        return Err(bad_request("A backup needs at least one section".to_string()));
+    }
+    let passphrase = request.passphrase.filter(|p| !p.is_empty());
+
+    // Read the keys first so a node that gets them from the environment fails before writing
+    let node_keys = if sections.contains(&BackupSection::Identity) {
+        let keys = fs::read(secrets_file_path).map_err(|e| {
+            bad_request(format!(
+                "The node keys aren't in a secrets file ({}), back them up where they are set or leave the identity section out: {}",
+                secrets_file_path, e
+            ))
+        })?;
+        Some(keys)
+    } else {
+        None
+    };
+
+    let dir = backups_dir(node_env);
+    fs::create_dir_all(&dir).map_err(|e| internal_error(format!("Failed to create {}: {}", dir.display(), e)))?;
+    let stem = format!("node-backup-{}", timestamp());
+    let file_name = format!("{}.zip", stem);
+    let path = dir.join(&file_name);
+    let snapshot_path = dir.join(format!(".{}.db.tmp", stem));
+
+    let result = write_backup_archive(
+        db,
+        node_name,
+        identity_secret_key,
+        node_env,
+        &sections,
+        node_keys,
+        passphrase.as_deref(),
+        &path,
+        &snapshot_path,
+    );
+    let _ = fs::remove_file(&snapshot_path);
+    let manifest = match result {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            let _ = fs::remove_file(&path);
+            return Err(err);
+        }
+    };
+
+    let size_bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or_default();
+    Ok(NodeBackupInfo {
+        file_name,
+        size_bytes,
+        manifest,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_backup_archive(
+    db: &SqliteManager,
+    node_name: &ZooName,
+    identity_secret_key: &SigningKey,
+    node_env: &NodeEnvironment,
+    sections: &BTreeSet<BackupSection>,
+    node_keys: Option<Vec<u8>>,
+    passphrase: Option<&str>,
+    path: &Path,
+    snapshot_path: &Path,
+) -> Result<BackupManifest, APIError> {
+    let excluded: Vec<BackupSection> = BackupSection::ALL
+        .into_iter()
+        .filter(|section| !sections.contains(section))
+        .collect();
+    let schema_version = db
+        .snapshot_database(snapshot_path, &excluded)
+        .map_err(|e| db_error("Failed to snapshot the database", e))?;
+
+    let file = File::create(path).map_err(|e| internal_error(format!("Failed to create {}: {}", path.display(), e)))?;
+    let mut writer = BackupWriter {
+        zip: ZipWriter::new(file),
+        entries: BTreeMap::new(),
+    };
+    writer.add_file(DATABASE_ENTRY, snapshot_path)?;
+
+    if let Some(node_keys) = node_keys {
+        match passphrase {
+            Some(passphrase) => {
+                let sealed = seal_with_passphrase(passphrase, &node_keys)
+                    .map_err(|e| internal_error(format!("Failed to encrypt the node keys: {}", e)))?;
+                writer.add_bytes(SEALED_NODE_KEYS_ENTRY, sealed.as_bytes())?;
+            }
+            None => writer.add_bytes(NODE_KEYS_ENTRY, &node_keys)?,
+        }
+    }
+    if sections.contains(&BackupSection::VectorFs) {
+        writer.add_dir(VECTOR_FS_PREFIX, &ZooPath::base_path())?;
+    }
+    if sections.contains(&BackupSection::Tools) {
+        writer.add_dir(TOOL_ASSETS_PREFIX, &tool_assets_dir(node_env))?;
+    }
+
+    let manifest = BackupManifest {
+        format_version: NODE_BACKUP_FORMAT_VERSION,
+        node_name: node_name.get_node_name_string(),
+        identity_public_key: signature_public_key_to_string(identity_secret_key.verifying_key()),
+        node_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version,
+        embedding_model: db.get_default_embedding_model().ok().map(|model| model.to_string()),
+        created_at: Utc::now().to_rfc3339(),
+        sections: sections.iter().copied().collect(),
+        secrets_encrypted: passphrase.is_some() && sections.contains(&BackupSection::Identity),
+        entries: writer.entries.clone(),
+    };
+    let manifest_bytes = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| internal_error(format!("Failed to serialize the manifest: {}", e)))?;
+    let signature = identity_secret_key.sign(&manifest_bytes);
+    writer.add_bytes(MANIFEST_ENTRY, &manifest_bytes)?;
+    writer.add_bytes(SIGNATURE_ENTRY, hex::encode(signature.to_bytes()).as_bytes())?;
+
+    writer
+        .zip
+        .finish()
+        .map_err(|e| internal_error(format!("Failed to finish the backup: {}", e)))?;
+    Ok(manifest)
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, APIError> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| bad_request(format!("The backup has no {}", name)))?;
+    let mut data = Vec::new();
+    entry
+        .read_to_end(&mut data)
+        .map_err(|e| bad_request(format!("Failed to read {} from the backup: {}", name, e)))?;
+    Ok(data)
+}
+
+fn read_manifest(archive: &mut ZipArchive<File>) -> Result<(BackupManifest, Vec<u8>), APIError> {
+    let manifest_bytes = read_entry(archive, MANIFEST_ENTRY)?;
+    let manifest: BackupManifest = serde_json::from_slice(&manifest_bytes)
+        .map_err(|e| bad_request(format!("The backup manifest is invalid: {}", e)))?;
+    Ok((manifest, manifest_bytes))
+}
+
+fn open_archive(path: &Path) -> Result<ZipArchive<File>, APIError> {
+    let file = File::open(path).map_err(|e| APIError {
+        code: StatusCode::NOT_FOUND.as_u16(),
+        error: "Not Found".to_string(),
+        message: format!("Backup not found: {}", e),
+    })?;
+    ZipArchive::new(file).map_err(|e| bad_request(format!("The backup isn't a valid archive: {}", e)))
+}
+
+/// Opens a backup and checks it before anything is read from it: the manifest signature, the
+/// hash of every entry, and that this node understands its format and database schema. The
+/// signature uses the key in the manifest, so it proves the archive is intact, not who made it.
+pub fn open_verified_backup(path: &Path) -> Result<(ZipArchive<File>, BackupManifest), APIError> {
+    let mut archive = open_archive(path)?;
+    let (manifest, manifest_bytes) = read_manifest(&mut archive)?;
+    if manifest.format_version > NODE_BACKUP_FORMAT_VERSION {
+        return Err(bad_request(format!(
+            "The backup format {} is newer than the {} this node supports",
+            manifest.format_version, NODE_BACKUP_FORMAT_VERSION
+        )));
+    }
+
+    let public_key = string_to_signature_public_key(&manifest.identity_public_key)
+        .map_err(|e| bad_request(format!("The backup manifest has an invalid public key: {}", e)))?;
+    let signature_hex = read_entry(&mut archive, SIGNATURE_ENTRY)?;
+    let signature = hex::decode(String::from_utf8_lossy(&signature_hex).trim())
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| bad_request("The backup signature is invalid".to_string()))?;
+    public_key
+        .verify(&manifest_bytes, &signature)
+        .map_err(|_| bad_request("The backup manifest doesn't match its signature".to_string()))?;
+
+    let mut checked = 0;
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|e| bad_request(format!("Failed to read the backup: {}", e)))?;
+        let name = entry.name().to_string();
+        if name == MANIFEST_ENTRY || name == SIGNATURE_ENTRY || entry.is_dir() {
+            continue;
+        }
+        let expected = manifest
+            .entries
+            .get(&name)
+            .ok_or_else(|| bad_request(format!("The backup has an entry missing from its manifest: {}", name)))?;
+        let hash = hash_reader(&mut entry).map_err(|e| bad_request(format!("Failed to read {}: {}", name, e)))?;
+        if &hash != expected {
+            return Err(bad_request(format!(
+                "{} in the backup is corrupted or was changed",
+                name
+            )));
+        }
+        checked += 1;
+    }
+    if checked != manifest.entries.len() {
+        return Err(bad_request(
+            "The backup is missing entries listed in its manifest".to_string(),
+        ));
+    }
+
+    let supported = latest_migration_version(MIGRATIONS);
+    if manifest.schema_version > supported {
+        return Err(bad_request(format!(
+            "The backup has database schema version {}, this node supports up to {}",
+            manifest.schema_version, supported
+        )));
+    }
+    Ok((archive, manifest))
+}
+
+/// Lists the backups in the backups folder, newest first. Only the manifests are read, the
+/// archives are fully checked when they are restored.
+pub fn list_node_backups(node_env: &NodeEnvironment) -> Result<Vec<NodeBackupInfo>, APIError> {
+    let dir = backups_dir(node_env);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let read_dir =
+        fs::read_dir(&dir).map_err(|e| internal_error(format!("Failed to read {}: {}", dir.display(), e)))?;
+
+    let mut backups = Vec::new();
+    for entry in read_dir.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Ok(path) = backup_file_path(node_env, &file_name) else {
+            continue;
+        };
+        let Ok(mut archive) = open_archive(&path) else {
+            continue;
+        };
+        let Ok((manifest, _)) = read_manifest(&mut archive) else {
+            continue;
+        };
+        backups.push(NodeBackupInfo {
+            file_name,
+            size_bytes: entry.metadata().map(|m| m.len()).unwrap_or_default(),
+            manifest,
+        });
+    }
+    backups.sort_by(|a, b| b.manifest.created_at.cmp(&a.manifest.created_at));
+    Ok(backups)
+}
+
+/// Stores an uploaded backup in the backups folder, once it passed the same checks as a restore.
+pub fn save_uploaded_backup(node_env: &NodeEnvironment, data: &[u8]) -> Result<NodeBackupInfo, APIError> {
+    let dir = backups_dir(node_env);
+    fs::create_dir_all(&dir).map_err(|e| internal_error(format!("Failed to create {}: {}", dir.display(), e)))?;
+    let file_name = format!("node-backup-upload-{}.zip", timestamp());
+    let path = dir.join(&file_name);
+    fs::write(&path, data).map_err(|e| internal_error(format!("Failed to save the backup: {}", e)))?;
+
+    match open_verified_backup(&path) {
+        Ok((_, manifest)) => Ok(NodeBackupInfo {
+            file_name,
+            size_bytes: data.len() as u64,
+            manifest,
+        }),
+        Err(err) => {
+            let _ = fs::remove_file(&path);
+            Err(err)
+        }
+    }
+}
+
+/// The node keys of a backup, decrypted if needed. They must belong to the key that signed it.
+fn read_node_keys(
+    archive: &mut ZipArchive<File>,
+    manifest: &BackupManifest,
+    passphrase: Option<&str>,
+) -> Result<Vec<u8>, APIError> {
+    let node_keys = if manifest.secrets_encrypted {
+        let passphrase = passphrase.filter(|p| !p.is_empty()).ok_or_else(|| {
+            bad_request("The node keys of this backup are encrypted, a passphrase is required".to_string())
+        })?;
+        let sealed = read_entry(archive, SEALED_NODE_KEYS_ENTRY)?;
+        let sealed =
+            String::from_utf8(sealed).map_err(|_| bad_request("The encrypted node keys are invalid".to_string()))?;
+        open_with_passphrase(passphrase, &sealed)
+            .map_err(|_| bad_request("Wrong passphrase for the node keys of this backup".to_string()))?
+    } else {
+        read_entry(archive, NODE_KEYS_ENTRY)?
+    };
+
+    let identity_secret_key = String::from_utf8_lossy(&node_keys)
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .find(|(key, _)| key.trim() == "IDENTITY_SECRET_KEY")
+        .and_then(|(_, value)| string_to_signature_secret_key(value.trim()).ok())
+        .ok_or_else(|| bad_request("The node keys of the backup have no valid identity key".to_string()))?;
+    if signature_public_key_to_string(identity_secret_key.verifying_key()) != manifest.identity_public_key {
+        return Err(bad_request(
+            "The node keys of the backup don't belong to the key that signed it".to_string(),
+        ));
+    }
+    Ok(node_keys)
+}
+
+/// Moves `dir` aside and extracts the entries under `prefix` into a fresh one. Returns the number
+/// of files extracted.
+fn replace_dir_from_archive(
+    archive: &mut ZipArchive<File>,
+    prefix: &str,
+    dir: &Path,
+    stamp: &str,
+) -> Result<usize, APIError> {
+    if dir.exists() {
+        let aside = PathBuf::from(format!("{}.pre-restore-{}", dir.display(), stamp));
+        fs::rename(dir, &aside)
+            .map_err(|e| internal_error(format!("Failed to move {} aside: {}", dir.display(), e)))?;
+    }
+    fs::create_dir_all(dir).map_err(|e| internal_error(format!("Failed to create {}: {}", dir.display(), e)))?;
+
+    let mut restored = 0;
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|e| bad_request(format!("Failed to read the backup: {}", e)))?;
+        let Some(relative) = entry.name().strip_prefix(prefix).map(PathBuf::from) else {
+            continue;
+        };
+        if entry.is_dir() || entry.enclosed_name().is_none() {
+            continue;
+        }
+        if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(bad_request(format!("Invalid path in the backup: {}", entry.name())));
+        }
+
+        let target = dir.join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| internal_error(format!("Failed to create {}: {}", parent.display(), e)))?;
+        }
+        let mut file = File::create(&target)
+            .map_err(|e| internal_error(format!("Failed to create {}: {}", target.display(), e)))?;
+        io::copy(&mut entry, &mut file)
+            .map_err(|e| internal_error(format!("Failed to write {}: {}", target.display(), e)))?;
+        restored += 1;
+    }
+    Ok(restored)
+}
+
+fn backup_warnings(manifest: &BackupManifest, sections: &[BackupSection], signed_by_this_node: bool) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if signed_by_this_node {
+        return warnings;
+    }
+    let restores = |section: BackupSection| sections.contains(&section);
+    if (restores(BackupSection::LlmProviders) || restores(BackupSection::Tools)) && !restores(BackupSection::Secrets) {
+        warnings.push(format!(
+            "The API keys and tool secrets from {} stay in its secrets vault, restore the secrets section to use them",
+            manifest.node_name
+        ));
+    }
+    if restores(BackupSection::Secrets) && !restores(BackupSection::Identity) {
+        warnings.push(format!(
+            "The restored secrets vault unlocks with the identity key or passphrase of {}",
+            manifest.node_name
+        ));
+    }
+    if restores(BackupSection::Identity) {
+        warnings.push(format!(
+            "The node becomes {} after a restart, its current keys are kept next to the secrets file",
+            manifest.node_name
+        ));
+    }
+    warnings
+}
+
+/// Anyone can sign an archive with a key of their own, so a backup of another node is only
+/// restored when the caller named its key.
+fn check_backup_signer(
+    manifest: &BackupManifest,
+    signed_by_this_node: bool,
+    trusted_signer: Option<&str>,
+) -> Result<(), APIError> {
+    if signed_by_this_node || trusted_signer.map(str::trim) == Some(manifest.identity_public_key.as_str()) {
+        return Ok(());
+    }
+    Err(APIError {
+        code: StatusCode::FORBIDDEN.as_u16(),
+        error: "Forbidden".to_string(),
+        message: format!(
+            "The backup was signed by {} with the key {}, not by this node. Pass that key as trusted_signer to restore it",
+            manifest.node_name, manifest.identity_public_key
+        ),
+    })
+}
+
+/// Checks a backup and restores the selected sections into this node. Database sections are
+/// replaced in a single transaction and file sections are swapped with their current folder,
+/// which is kept next to it. With `validate_only` nothing is changed.
+pub fn restore_node_backup(
+    db: &SqliteManager,
+    identity_public_key: &VerifyingKey,
+    secrets_file_path: &str,
+    node_env: &NodeEnvironment,
+    request: RestoreNodeBackupRequest,
+) -> Result<RestoreNodeBackupReport, APIError> {
+    let path = backup_file_path(node_env, &request.file_name)?;
+    let (mut archive, manifest) = open_verified_backup(&path)?;
+
+    let sections: Vec<BackupSection> = match request.sections {
+        Some(sections) => sections.into_iter().collect::<BTreeSet<_>>().into_iter().collect(),
+        None => manifest.sections.clone(),
+    };
+    if let Some(missing) = sections.iter().find(|section| !manifest.sections.contains(section)) {
+        return Err(bad_request(format!("The backup has no {} section", missing.as_str())));
+    }
+    let node_keys = if sections.contains(&BackupSection::Identity) {
+        Some(read_node_keys(&mut archive, &manifest, request.passphrase.as_deref())?)
+    } else {
+        None
+    };
+
+    let signed_by_this_node = manifest.identity_public_key == signature_public_key_to_string(*identity_public_key);
+    check_backup_signer(&manifest, signed_by_this_node, request.trusted_signer.as_deref())?;
+    let mut report = RestoreNodeBackupReport {
+        warnings: backup_warnings(&manifest, &sections, signed_by_this_node),
+        manifest,
+        signed_by_this_node,
+        restored_sections: Vec::new(),
+        restored_rows: BTreeMap::new(),
+        restored_files: 0,
+        restart_required: false,
+    };
+    let embedding_model = db.get_default_embedding_model().ok().map(|model| model.to_string());
+    if let Some(backup_model) = &report.manifest.embedding_model {
+        if Some(backup_model) != embedding_model.as_ref() {
+            report.warnings.push(format!(
+                "The backup was embedded with {}, the restored files, tools, prompts and memories are embedded again with the model of this node",
+                backup_model
+            ));
+        }
+    }
+    if request.validate_only {
+        return Ok(report);
+    }
+
+    let stamp = timestamp();
+    let snapshot_path = backups_dir(node_env).join(format!(".restore-{}.db.tmp", stamp));
+    let restored_rows = (|| {
+        let mut entry = archive
+            .by_name(DATABASE_ENTRY)
+            .map_err(|_| bad_request(format!("The backup has no {}", DATABASE_ENTRY)))?;
+        let mut file = File::create(&snapshot_path)
+            .map_err(|e| internal_error(format!("Failed to extract the database snapshot: {}", e)))?;
+        io::copy(&mut entry, &mut file)
+            .map_err(|e| internal_error(format!("Failed to extract the database snapshot: {}", e)))?;
+        drop(file);
+        db.restore_from_snapshot(&snapshot_path, &sections)
+            .map_err(|e| db_error("Failed to restore the database", e))
+    })();
+    let _ = fs::remove_file(&snapshot_path);
+    report.restored_rows = restored_rows?;
+
+    if sections.contains(&BackupSection::VectorFs) {
+        report.restored_files +=
+            replace_dir_from_archive(&mut archive, VECTOR_FS_PREFIX, &ZooPath::base_path(), &stamp)?;
+    }
+    if sections.contains(&BackupSection::Tools) {
+        report.restored_files +=
+            replace_dir_from_archive(&mut archive, TOOL_ASSETS_PREFIX, &tool_assets_dir(node_env), &stamp)?;
+    }
+
+    if let Some(node_keys) = node_keys {
+        let secrets_file = Path::new(secrets_file_path);
+        if secrets_file.exists() {
+            fs::copy(secrets_file, format!("{}.pre-restore-{}", secrets_file_path, stamp))
+                .map_err(|e| internal_error(format!("Failed to keep the current node keys: {}", e)))?;
+        }
+        if !signed_by_this_node {
+            // The node opens the database named after its identity key, which changes on restart
+            let restored_key = string_to_signature_public_key(&report.manifest.identity_public_key)
+                .map_err(|e| bad_request(format!("The backup manifest has an invalid public key: {}", e)))?;
+            let current_db = db
+                .database_path()
+                .map_err(|e| db_error("Failed to locate the database", e))?;
+            let target_db = current_db.with_file_name(format!("{}.db", hash_signature_public_key(&restored_key)));
+            if target_db.exists() {
+                let aside = PathBuf::from(format!("{}.pre-restore-{}", target_db.display(), stamp));
+                fs::rename(&target_db, &aside)
+                    .map_err(|e| internal_error(format!("Failed to move {} aside: {}", target_db.display(), e)))?;
+            }
+            db.snapshot_database(&target_db, &[])
+                .map_err(|e| db_error("Failed to copy the database for the restored identity", e))?;
+        }
+        fs::write(secrets_file, node_keys)
+            .map_err(|e| internal_error(format!("Failed to write the node keys: {}", e)))?;
+    }
+
+    report.restart_required = sections.contains(&BackupSection::Identity) || sections.contains(&BackupSection::Secrets);
+    report.restored_sections = sections;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zoo_message_primitives::zoo_utils::signatures::{ephemeral_signature_keypair, signature_secret_key_to_string};
+
+    fn write_archive(path: &Path, manifest: &BackupManifest, key: &SigningKey, entries: &[(&str, &[u8])]) {
+        let mut writer = BackupWriter {
+            zip: ZipWriter::new(File::create(path).unwrap()),
+            entries: BTreeMap::new(),
+        };
+        for (name, data) in entries {
+            writer.add_bytes(name, data).unwrap();
+        }
+        let manifest = BackupManifest {
+            entries: writer.entries.clone(),
+            ..manifest.clone()
+        };
+        let manifest_bytes = serde_json::to_vec_pretty(&manifest).unwrap();
+        writer.add_bytes(MANIFEST_ENTRY, &manifest_bytes).unwrap();
+        writer
+            .add_bytes(
+                SIGNATURE_ENTRY,
+                hex::encode(key.sign(&manifest_bytes).to_bytes()).as_bytes(),
+            )
+            .unwrap();
+        writer.zip.finish().unwrap();
+    }
+
+    fn test_manifest(key: &SigningKey) -> BackupManifest {
+        BackupManifest {
+            format_version: NODE_BACKUP_FORMAT_VERSION,
+            node_name: "@@node1.sep-zoo".to_string(),
+            identity_public_key: signature_public_key_to_string(key.verifying_key()),
+            node_version: "0.0.0".to_string(),
+            schema_version: 1,
+            embedding_model: None,
+            created_at: Utc::now().to_rfc3339(),
+            sections: vec![BackupSection::Identity],
+            secrets_encrypted: true,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_backup_file_names() {
+        assert!(is_valid_backup_file_name("node-backup-20240101T000000000.zip"));
+        assert!(!is_valid_backup_file_name("../secret.zip"));
+        assert!(!is_valid_backup_file_name("dir/backup.zip"));
+        assert!(!is_valid_backup_file_name(".hidden.zip"));
+        assert!(!is_valid_backup_file_name("backup.db"));
+    }
+
+    #[test]
+    fn test_verify_backup_and_node_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let (secret_key, _) = ephemeral_signature_keypair();
+        let manifest = test_manifest(&secret_key);
+        let node_keys = format!(
+            "IDENTITY_SECRET_KEY={}\n",
+            signature_secret_key_to_string(secret_key.clone())
+        );
+        let sealed = seal_with_passphrase("correct horse", node_keys.as_bytes()).unwrap();
+
+        let path = dir.path().join("backup.zip");
+        write_archive(
+            &path,
+            &manifest,
+            &secret_key,
+            &[(SEALED_NODE_KEYS_ENTRY, sealed.as_bytes())],
+        );
+        let (mut archive, verified) = open_verified_backup(&path).unwrap();
+        assert_eq!(verified.sections, vec![BackupSection::Identity]);
+        assert!(read_node_keys(&mut archive, &verified, None).is_err());
+        assert!(read_node_keys(&mut archive, &verified, Some("wrong")).is_err());
+        assert_eq!(
+            read_node_keys(&mut archive, &verified, Some("correct horse")).unwrap(),
+            node_keys.as_bytes()
+        );
+
+        // Signed by another key than the one in the manifest
+        let (other_key, _) = ephemeral_signature_keypair();
+        let forged = dir.path().join("forged.zip");
+        write_archive(
+            &forged,
+            &manifest,
+            &other_key,
+            &[(SEALED_NODE_KEYS_ENTRY, sealed.as_bytes())],
+        );
+        assert!(open_verified_backup(&forged).is_err());
+
+        // A newer database schema than this node knows
+        let newer = BackupManifest {
+            schema_version: latest_migration_version(MIGRATIONS) + 1,
+            ..manifest.clone()
+        };
+        let newer_path = dir.path().join("newer.zip");
+        write_archive(&newer_path, &newer, &secret_key, &[]);
+        assert!(open_verified_backup(&newer_path).is_err());
+    }
+
+    #[test]
+    fn test_backup_signer() {
+        let (secret_key, _) = ephemeral_signature_keypair();
+        let manifest = test_manifest(&secret_key);
+        assert!(check_backup_signer(&manifest, true, None).is_ok());
+        assert!(check_backup_signer(&manifest, false, None).is_err());
+        assert!(check_backup_signer(&manifest, false, Some(&manifest.identity_public_key)).is_ok());
+
+        let (other_key, _) = ephemeral_signature_keypair();
+        let other_public_key = signature_public_key_to_string(other_key.verifying_key());
+        assert!(check_backup_signer(&manifest, false, Some(&other_public_key)).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use ed25519_dalek::SigningKey;
+use tokio::sync::Mutex;
+use zoo_message_primitives::schemas::identity::Identity;
+use zoo_message_primitives::schemas::ws_types::WSUpdateHandler;
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
+use zoo_sqlite::SqliteManager;
+
+use crate::llm_provider::job_manager::JobManager;
+use crate::managers::IdentityManager;
+use crate::utils::node_config::{
+    apply_logging_config, effective_node_config, format_config_issues, reload_node_config, ConfigReload,
+    LlmProviderConfig,
+};
+
+use super::Node;
+
+/// How often the config file is checked for changes.
+pub const CONFIG_RELOAD_INTERVAL_SECS: u64 = 5;
+
+impl Node {
+    /// Stores the `[preferences]` of the config file, then watches the file and applies the
+    /// settings that can change while the node runs: logging, preferences and LLM providers.
+    pub fn spawn_config_reload_service(
+        db: Weak<SqliteManager>,
+        identity_manager: Arc<Mutex<IdentityManager>>,
+        job_manager: Arc<Mutex<JobManager>>,
+        identity_secret_key: SigningKey,
+        ws_manager: Option<Arc<Mutex<dyn WSUpdateHandler + Send>>>,
+    ) {
+        tokio::spawn(async move {
+            if let Some(db) = db.upgrade() {
+                let (config, _) = effective_node_config();
+                Self::apply_config_preferences(&db, config.preferences.iter().collect());
+            }
+
+            let mut interval = tokio::time::interval(Duration::from_secs(CONFIG_RELOAD_INTERVAL_SECS));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(db) = db.upgrade() else {
+                    break;
+                };
+
+                match reload_node_config() {
+                    None => {}
+                    Some(Err(issues)) => zoo_log(
+                        ZooLogOption::Node,
+                        ZooLogLevel::Error,
+                        &format!(
+                            "Config file change ignored, the node keeps its current settings:\n{}",
+                            format_config_issues(&issues)
+                        ),
+                    ),
+                    Some(Ok(reload)) => {
+                        Self::apply_config_reload(
+                            db,
+                            identity_manager.clone(),
+                            job_manager.clone(),
+                            identity_secret_key.clone(),
+                            ws_manager.clone(),
+                            reload,
+                        )
+                        .await
+                    }
+                }
+            }
+        });
+    }
+
+    async fn apply_config_reload(
+        db: Arc<SqliteManager>,
+        identity_manager: Arc<Mutex<IdentityManager>>,
+        job_manager: Arc<Mutex<JobManager>>,
+        identity_secret_key: SigningKey,
+        ws_manager: Option<Arc<Mutex<dyn WSUpdateHandler + Send>>>,
+        reload: ConfigReload,
+    ) {
+        apply_logging_config(&reload.current.logging);
+        Self::apply_config_preferences(&db, reload.changed_preferences());
+
+        let llm_providers = reload.changed_llm_providers();
+        if !llm_providers.is_empty() {
+            Self::apply_config_llm_providers(
+                db,
+                identity_manager,
+                job_manager,
+                identity_secret_key,
+                ws_manager,
+                llm_providers,
+            )
+            .await;
+        }
+
+        let restart_required = reload.restart_required();
+        if !restart_required.is_empty() {
+            zoo_log(
+                ZooLogOption::Node,
+                ZooLogLevel::Info,
+                &format!(
+                    "Config file changes in [{}] take effect when the node restarts",
+                    restart_required.join("], [")
+                ),
+            );
+        }
+        zoo_log(ZooLogOption::Node, ZooLogLevel::Info, "Config file reloaded");
+    }
+
+    fn apply_config_preferences(db: &SqliteManager, preferences: Vec<(&String, &toml::Value)>) {
+        for (key, value) in preferences {
+            if let Err(e) = db.set_preference(key, value, None) {
+                zoo_log(
+                    ZooLogOption::Node,
+                    ZooLogLevel::Error,
+                    &format!("Failed to set preference {} from the config file: {}", key, e),
+                );
+            }
+        }
+    }
+
+    /// Adds the new providers of the config file to the main profile and updates the changed ones.
+    /// Providers removed from the file are kept.
+    async fn apply_config_llm_providers(
+        db: Arc<SqliteManager>,
+        identity_manager: Arc<Mutex<IdentityManager>>,
+        job_manager: Arc<Mutex<JobManager>>,
+        identity_secret_key: SigningKey,
+        ws_manager: Option<Arc<Mutex<dyn WSUpdateHandler + Send>>>,
+        llm_providers: Vec<&LlmProviderConfig>,
+    ) {
+        let profile = match identity_manager.lock().await.get_main_identity() {
+            Some(Identity::Standard(std_identity)) => std_identity.full_identity_name.clone(),
+            _ => {
+                zoo_log(
+                    ZooLogOption::Node,
+                    ZooLogLevel::Info,
+                    "LLM providers of the config file not applied: the node has no main profile yet",
+                );
+                return;
+            }
+        };
+        let node_name = profile.get_node_name_string();
+
+        for llm_provider in llm_providers {
+            let llm_provider = match llm_provider.to_llm_provider(&node_name) {
+                Ok(llm_provider) => llm_provider,
+                Err(e) => {
+                    zoo_log(
+                        ZooLogOption::Node,
+                        ZooLogLevel::Error,
+                        &format!("Skipping LLM provider {} of the config file: {}", llm_provider.name, e),
+                    );
+                    continue;
+                }
+            };
+
+            let exists = matches!(db.get_llm_provider(&llm_provider.id, &profile), Ok(Some(_)));
+            let result = if exists {
+                match db.update_llm_provider(llm_provider.clone(), &profile) {
+                    Ok(()) => identity_manager
+                        .lock()
+                        .await
+                        .modify_llm_provider_subidentity(llm_provider.clone())
+                        .await
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                }
+            } else {
+                Self::internal_add_llm_provider(
+                    db.clone(),
+                    identity_manager.clone(),
+                    job_manager.clone(),
+                    identity_secret_key.clone(),
+                    llm_provider.clone(),
+                    &profile,
+                    ws_manager.clone(),
+                )
+                .await
+                .map_err(|e| e.to_string())
+            };
+
+            match result {
+                Ok(()) => zoo_log(
+                    ZooLogOption::Node,
+                    ZooLogLevel::Info,
+                    &format!(
+                        "LLM provider {} {} from the config file",
+                        llm_provider.id,
+                        if exists { "updated" } else { "added" }
+                    ),
+                ),
+                Err(e) => zoo_log(
+                    ZooLogOption::Node,
+                    ZooLogLevel::Error,
+                    &format!(
+                        "Failed to apply LLM provider {} from the config file: {}",
+                        llm_provider.id, e
+                    ),
+                ),
+            }
+        }
+    }
+}
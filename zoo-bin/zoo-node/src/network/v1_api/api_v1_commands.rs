@@ -2939,6 +2939,7 @@ impl Node {
         // Update the default embedding model in the database
         match db.update_default_embedding_model(new_default_model) {
             Ok(_) => {
+                Self::spawn_embedding_migration(db.clone());
                 let _ = res
                     .send(Ok("Default embedding model updated successfully".to_string()))
                     .await;
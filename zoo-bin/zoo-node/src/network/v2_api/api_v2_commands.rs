@@ -14,6 +14,7 @@ use crate::{
     utils::update_global_identity::update_global_identity_name,
 };
 use async_channel::Sender;
+use chrono::Utc;
 use ed25519_dalek::ed25519::signature::SignerMut;
 use ed25519_dalek::{SigningKey, VerifyingKey};
 use reqwest::StatusCode;
@@ -30,6 +31,9 @@ use zoo_http_api::{
     node_api_router::{APIError, GetPublicKeysResponse},
 };
 use zoo_mcp::mcp_methods::{list_tools_via_command, list_tools_via_http, list_tools_via_sse};
+use zoo_mcp::session_pool::McpSessionPool;
+use zoo_message_primitives::schemas::agent_memory::{AgentMemory, RemoveAgentMemoryRequest, UpdateAgentMemoryRequest};
+use zoo_message_primitives::schemas::api_keys::API_KEY_PREFIX;
 use zoo_message_primitives::schemas::llm_providers::zoo_backend::QuotaResponse;
 use zoo_message_primitives::schemas::mcp_server::{MCPServer, MCPServerType};
 use zoo_message_primitives::schemas::zoo_preferences::ZooInternalComms;
@@ -55,7 +59,9 @@ use zoo_message_primitives::{
     },
     zoo_utils::{job_scope::MinimalJobScope, zoo_time::ZooStringTime},
 };
+use zoo_sqlite::errors::SqliteManagerError;
 use zoo_sqlite::regex_pattern_manager::RegexPattern;
+use zoo_sqlite::tool_version_manager::ToolVersionSelector;
 use zoo_sqlite::SqliteManager;
 use zoo_tools_primitives::tools::mcp_server_tool::MCPServerTool;
 use zoo_tools_primitives::tools::{
@@ -95,11 +101,70 @@ fn check_bearer_token(api_key: &str, bearer: &str) -> Result<(), ()> {
     }
 }
 
+/// Agents reference their tools without a version, with an exact version or with a semver
+/// requirement like `^1.2`. Anything else would fail when the agent runs.
+fn check_agent_tool_versions(agent: &Agent) -> Result<(), APIError> {
+    for tool in &agent.tools {
+        ToolVersionSelector::from_router_key(tool).map_err(|e| APIError {
+            code: StatusCode::BAD_REQUEST.as_u16(),
+            error: "Bad Request".to_string(),
+            message: format!("Invalid version for tool {}: {}", tool.to_string_without_version(), e),
+        })?;
+    }
+    Ok(())
+}
+
+fn agent_memory_api_error(err: SqliteManagerError, action: &str) -> APIError {
+    match err {
+        SqliteManagerError::DataNotFound => APIError {
+            code: StatusCode::NOT_FOUND.as_u16(),
+            error: "Not Found".to_string(),
+            message: format!("Failed to {}: not found", action),
+        },
+        SqliteManagerError::ValidationError(message) => APIError {
+            code: StatusCode::BAD_REQUEST.as_u16(),
+            error: "Bad Request".to_string(),
+            message,
+        },
+        err => APIError {
+            code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            error: "Internal Server Error".to_string(),
+            message: format!("Failed to {}: {}", action, err),
+        },
+    }
+}
+
 impl Node {
+    /// Accepts the node API key and the active scoped API keys. The scope of a scoped key is
+    /// checked for the route before the request gets here (see `with_api_key_scope`).
     pub async fn validate_bearer_token<T>(
         bearer: &str,
         db: Arc<SqliteManager>,
         res: &Sender<Result<T, APIError>>,
+    ) -> Result<(), ()> {
+        if !bearer.starts_with(API_KEY_PREFIX) {
+            return Self::validate_node_api_key(bearer, db, res).await;
+        }
+
+        match db.get_api_key_by_key(bearer) {
+            Ok(Some(api_key)) if api_key.is_active(Utc::now()) => Ok(()),
+            _ => {
+                let api_error = APIError {
+                    code: StatusCode::UNAUTHORIZED.as_u16(),
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid bearer token".to_string(),
+                };
+                let _ = res.send(Err(api_error)).await;
+                Err(())
+            }
+        }
+    }
+
+    /// Only accepts the node API key, for what scoped API keys can't do.
+    pub async fn validate_node_api_key<T>(
+        bearer: &str,
+        db: Arc<SqliteManager>,
+        res: &Sender<Result<T, APIError>>,
     ) -> Result<(), ()> {
         // Compare bearer token to the environment variable API_V2_KEY
         let api_key = match env::var("API_V2_KEY") {
@@ -448,6 +513,7 @@ impl Node {
         // Update the default embedding model in the database
         match db.update_default_embedding_model(new_default_model) {
             Ok(_) => {
+                Self::spawn_embedding_migration(db.clone());
                 let _ = res
                     .send(Ok("Default embedding model updated successfully".to_string()))
                     .await;
@@ -964,23 +1030,26 @@ impl Node {
                 let job_queue_manager_normal = job_manager.lock().await.job_queue_manager_normal.clone();
                 let job_queue_manager_immediate = job_manager.lock().await.job_queue_manager_immediate.clone();
 
-                // First try to dequeue from immediate queue
-                let dequeue_result_immediate = job_queue_manager_immediate.lock().await.dequeue(&job_id).await;
-                if let Ok(Some(_)) = dequeue_result_immediate {
-                    // Job was successfully dequeued from immediate queue
-                } else {
-                    // If not found in immediate queue, try normal queue
-                    let dequeue_result_normal = job_queue_manager_normal.lock().await.dequeue(&job_id).await;
-                    if let Ok(Some(_)) = dequeue_result_normal {
-                        // Job was successfully dequeued from normal queue
-                    } else {
-                        eprintln!("Job {} not found in either queue", job_id);
-                    }
+                // Cancel the message being processed and whatever is queued after it, in both queues
+                let cancelled_immediate = job_queue_manager_immediate
+                    .lock()
+                    .await
+                    .cancel(&job_id, "Stopped by user request")
+                    .await
+                    .unwrap_or(0);
+                let cancelled_normal = job_queue_manager_normal
+                    .lock()
+                    .await
+                    .cancel(&job_id, "Stopped by user request")
+                    .await
+                    .unwrap_or(0);
+                if cancelled_immediate + cancelled_normal == 0 {
+                    eprintln!("Job {} not found in either queue", job_id);
                 }
             }
         }
 
-        // Stop the LLM
+        // Stop the LLM: aborts the in-flight inference and tool calls of the job
         stopper.stop(&inbox_name.get_value());
 
         let _ = res.send(Ok(())).await;
@@ -1031,7 +1100,10 @@ impl Node {
             let _ = res.send(Err(api_error)).await;
             return Ok(());
         }
-        // TODO: validate tools
+        if let Err(api_error) = check_agent_tool_versions(&agent) {
+            let _ = res.send(Err(api_error)).await;
+            return Ok(());
+        }
         // TODO: validate knowledge
 
         // My created agents are always marked as edited
@@ -1067,6 +1139,8 @@ impl Node {
                 // Add the agent to the database
                 match db.add_agent(agent.clone(), &requester_name) {
                     Ok(_) => {
+                        db.embed_agent_memories(&agent.agent_id.to_lowercase()).await;
+
                         // Create and add Agent tool wrapper
                         let node_name = requester_name.get_node_name_string();
                         let agent_tool_wrapper = AgentToolWrapper::new(
@@ -1303,9 +1377,15 @@ impl Node {
             edited: true,
         };
 
+        if let Err(api_error) = check_agent_tool_versions(&updated_agent) {
+            let _ = res.send(Err(api_error)).await;
+            return Ok(());
+        }
+
         // Update the agent in the database
         match db.update_agent(updated_agent.clone()) {
             Ok(_) => {
+                db.embed_agent_memories(&updated_agent.agent_id).await;
                 let _ = res.send(Ok(updated_agent)).await;
             }
             Err(err) => {
@@ -1367,6 +1447,71 @@ impl Node {
         Ok(())
     }
 
+    pub async fn v2_api_list_agent_memories(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        agent_id: String,
+        res: Sender<Result<Vec<AgentMemory>, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let result = match db.get_agent(&agent_id) {
+            Ok(Some(_)) => db
+                .list_agent_memories(&agent_id)
+                .map_err(|err| agent_memory_api_error(err, "list agent memories")),
+            Ok(None) => Err(agent_memory_api_error(
+                SqliteManagerError::DataNotFound,
+                "list agent memories",
+            )),
+            Err(err) => Err(agent_memory_api_error(err, "list agent memories")),
+        };
+        let _ = res.send(result).await;
+        Ok(())
+    }
+
+    pub async fn v2_api_update_agent_memory(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        request: UpdateAgentMemoryRequest,
+        res: Sender<Result<AgentMemory, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let result = db
+            .update_agent_memory(&request.memory_id, &request.content)
+            .map_err(|err| agent_memory_api_error(err, "update agent memory"));
+        if let Ok(memory) = &result {
+            db.embed_agent_memories(&memory.agent_id).await;
+        }
+        let _ = res.send(result).await;
+        Ok(())
+    }
+
+    pub async fn v2_api_remove_agent_memory(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        request: RemoveAgentMemoryRequest,
+        res: Sender<Result<String, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let result = db
+            .remove_agent_memory(&request.memory_id)
+            .map(|_| "Agent memory removed successfully".to_string())
+            .map_err(|err| agent_memory_api_error(err, "remove agent memory"));
+        let _ = res.send(result).await;
+        Ok(())
+    }
+
     pub async fn v2_api_get_all_agents(
         db: Arc<SqliteManager>,
         bearer: String,
@@ -2717,6 +2862,7 @@ impl Node {
                     updated_mcp_server.name,
                     updated_mcp_server.id
                 );
+                McpSessionPool::global().shutdown(mcp_server.id, "MCP server updated").await;
                 let rows_deleted_result = db.delete_all_tools_from_mcp_server(mcp_server.id.to_string());
                 match rows_deleted_result {
                     Ok(count) => {
@@ -2944,6 +3090,7 @@ impl Node {
             return Ok(());
         }
         let _ = db.delete_mcp_server(mcp_server_id);
+        McpSessionPool::global().shutdown(mcp_server_id, "MCP server deleted").await;
         let rows_deleted_result =
             db.delete_all_tools_from_mcp_server(mcp_server.clone().unwrap().id.unwrap_or_default().to_string());
 
@@ -2996,6 +3143,9 @@ impl Node {
         // Update the MCP server's enabled status
         match db.update_mcp_server_enabled_status(mcp_server_id, is_enabled) {
             Ok(updated_server) => {
+                if !is_enabled {
+                    McpSessionPool::global().shutdown(mcp_server_id, "MCP server disabled").await;
+                }
                 let _ = res.send(Ok(updated_server)).await;
             }
             Err(err) => {
@@ -3010,6 +3160,41 @@ impl Node {
         Ok(())
     }
 
+    /// The pooled session status of an MCP server and the most recent entries of its log: stderr
+    /// of command servers, log notifications and session lifecycle events.
+    pub async fn v2_api_get_mcp_server_logs(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        mcp_server_id: i64,
+        limit: Option<usize>,
+        res: Sender<Result<Value, APIError>>,
+    ) -> Result<(), NodeError> {
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+        if db.get_mcp_server(mcp_server_id)?.is_none() {
+            let _ = res
+                .send(Err(APIError {
+                    code: StatusCode::BAD_REQUEST.as_u16(),
+                    error: "Invalid MCP Server ID".to_string(),
+                    message: format!("No MCP Server found with ID: {}", mcp_server_id),
+                }))
+                .await;
+            return Ok(());
+        }
+
+        let pool = McpSessionPool::global();
+        let status = pool.status(mcp_server_id).await;
+        let logs = pool.logs(mcp_server_id, limit.unwrap_or(200));
+        let _ = res
+            .send(Ok(json!({
+                "status": status,
+                "logs": logs,
+            })))
+            .await;
+        Ok(())
+    }
+
     pub async fn v2_api_docker_status(res: Sender<Result<serde_json::Value, APIError>>) -> Result<(), NodeError> {
         let docker_status = match zoo_tools_runner::tools::container_utils::is_docker_available() {
             zoo_tools_runner::tools::container_utils::DockerStatus::NotInstalled => "not-installed",
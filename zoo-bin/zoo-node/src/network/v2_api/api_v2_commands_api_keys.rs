@@ -0,0 +1,187 @@
+use std::sync::Arc;
+
+use async_channel::Sender;
+use chrono::Utc;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use zoo_http_api::node_api_router::APIError;
+use zoo_message_primitives::schemas::api_keys::{
+    ApiKey, ApiKeyScope, CreateApiKeyRequest, CreatedApiKey, API_KEY_PREFIX,
+};
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
+use zoo_sqlite::{errors::SqliteManagerError, SqliteManager};
+
+use crate::network::{node_error::NodeError, Node};
+
+impl Node {
+    /// Checks that the scoped API key `bearer` is active and has `scope`, then records the call.
+    /// A route without a scope can't be called with a scoped key.
+    pub async fn v2_api_authorize_api_key(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        route: String,
+        scope: Option<ApiKeyScope>,
+        res: Sender<Result<(), APIError>>,
+    ) -> Result<(), NodeError> {
+        let api_key = match db.get_api_key_by_key(&bearer) {
+            Ok(Some(api_key)) if api_key.is_active(Utc::now()) => api_key,
+            Ok(_) => {
+                let api_error = APIError {
+                    code: StatusCode::UNAUTHORIZED.as_u16(),
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid bearer token".to_string(),
+                };
+                let _ = res.send(Err(api_error)).await;
+                return Ok(());
+            }
+            Err(err) => {
+                let _ = res.send(Err(api_key_api_error("Failed to check API key", err))).await;
+                return Ok(());
+            }
+        };
+
+        let message = match scope {
+            Some(scope) if api_key.has_scope(scope) => None,
+            Some(scope) => Some(format!("The API key needs the {} scope to call {}", scope, route)),
+            None => Some(format!("{} can only be called with the node API key", route)),
+        };
+        if let Some(message) = message {
+            zoo_log(
+                ZooLogOption::Api,
+                ZooLogLevel::Info,
+                &format!("API key {} denied access to {}", api_key.id, route),
+            );
+            let api_error = APIError {
+                code: StatusCode::FORBIDDEN.as_u16(),
+                error: "Forbidden".to_string(),
+                message,
+            };
+            let _ = res.send(Err(api_error)).await;
+            return Ok(());
+        }
+
+        zoo_log(
+            ZooLogOption::Api,
+            ZooLogLevel::Debug,
+            &format!("API key {} ({}) calls {}", api_key.id, api_key.label, route),
+        );
+        if let Err(err) = db.record_api_key_use(&api_key.id, &route) {
+            zoo_log(
+                ZooLogOption::Api,
+                ZooLogLevel::Error,
+                &format!("Failed to record the use of API key {}: {}", api_key.id, err),
+            );
+        }
+        let _ = res.send(Ok(())).await;
+        Ok(())
+    }
+
+    /// The Streamable HTTP MCP endpoint takes the node API key or an API key with the
+    /// `tools:execute` scope.
+    pub async fn v2_api_authorize_mcp_bearer(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        res: Sender<Result<(), APIError>>,
+    ) -> Result<(), NodeError> {
+        if bearer.starts_with(API_KEY_PREFIX) {
+            return Self::v2_api_authorize_api_key(db, bearer, "mcp".to_string(), Some(ApiKeyScope::ToolsExecute), res)
+                .await;
+        }
+
+        if Self::validate_node_api_key(&bearer, db, &res).await.is_ok() {
+            let _ = res.send(Ok(())).await;
+        }
+        Ok(())
+    }
+
+    pub async fn v2_api_create_api_key(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        request: CreateApiKeyRequest,
+        res: Sender<Result<CreatedApiKey, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages API keys
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        match db.create_api_key(&request) {
+            Ok(created) => {
+                let _ = res.send(Ok(created)).await;
+            }
+            Err(err) => {
+                let _ = res.send(Err(api_key_api_error("Failed to create API key", err))).await;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn v2_api_list_api_keys(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        res: Sender<Result<Vec<ApiKey>, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages API keys
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        match db.get_all_api_keys() {
+            Ok(api_keys) => {
+                let _ = res.send(Ok(api_keys)).await;
+            }
+            Err(err) => {
+                let _ = res.send(Err(api_key_api_error("Failed to list API keys", err))).await;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn v2_api_revoke_api_key(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        id: String,
+        res: Sender<Result<Value, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages API keys
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        match db.revoke_api_key(&id) {
+            Ok(true) => {
+                let _ = res
+                    .send(Ok(json!({ "message": format!("API key {} revoked", id) })))
+                    .await;
+            }
+            Ok(false) => {
+                let api_error = APIError {
+                    code: StatusCode::NOT_FOUND.as_u16(),
+                    error: "Not Found".to_string(),
+                    message: format!("No active API key with id {}", id),
+                };
+                let _ = res.send(Err(api_error)).await;
+            }
+            Err(err) => {
+                let _ = res.send(Err(api_key_api_error("Failed to revoke API key", err))).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn api_key_api_error(context: &str, err: SqliteManagerError) -> APIError {
+    match err {
+        SqliteManagerError::ValidationError(_) | SqliteManagerError::DateTimeParseError(_) => APIError {
+            code: StatusCode::BAD_REQUEST.as_u16(),
+            error: "Bad Request".to_string(),
+            message: format!("{}: {}", context, err),
+        },
+        _ => APIError {
+            code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            error: "Internal Server Error".to_string(),
+            message: format!("{}: {}", context, err),
+        },
+    }
+}
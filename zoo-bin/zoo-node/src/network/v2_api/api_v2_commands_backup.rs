@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use async_channel::Sender;
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use reqwest::StatusCode;
+
+use zoo_http_api::node_api_router::APIError;
+use zoo_message_primitives::schemas::node_backup::{
+    CreateNodeBackupRequest, NodeBackupInfo, RestoreNodeBackupReport, RestoreNodeBackupRequest,
+};
+use zoo_message_primitives::schemas::zoo_name::ZooName;
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
+use zoo_sqlite::SqliteManager;
+
+use crate::network::node_backup::node_backup;
+use crate::network::{node_error::NodeError, Node};
+use crate::utils::environment::NodeEnvironment;
+
+/// Backups read and write whole databases and folders, so they run off the async runtime.
+async fn run_blocking<T, F>(task: F) -> Result<T, APIError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, APIError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(task).await.unwrap_or_else(|e| {
+        Err(APIError {
+            code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            error: "Internal Server Error".to_string(),
+            message: format!("Backup task failed: {}", e),
+        })
+    })
+}
+
+impl Node {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn v2_api_create_node_backup(
+        db: Arc<SqliteManager>,
+        node_name: ZooName,
+        identity_secret_key: SigningKey,
+        secrets_file_path: String,
+        node_env: NodeEnvironment,
+        bearer: String,
+        request: CreateNodeBackupRequest,
+        res: Sender<Result<NodeBackupInfo, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages backups
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let result = run_blocking(move || {
+            node_backup::create_node_backup(
+                &db,
+                &node_name,
+                &identity_secret_key,
+                &secrets_file_path,
+                &node_env,
+                request,
+            )
+        })
+        .await;
+        if let Ok(backup) = &result {
+            zoo_log(
+                ZooLogOption::Api,
+                ZooLogLevel::Info,
+                &format!("Node backup {} created ({} bytes)", backup.file_name, backup.size_bytes),
+            );
+        }
+        let _ = res.send(result).await;
+        Ok(())
+    }
+
+    pub async fn v2_api_list_node_backups(
+        db: Arc<SqliteManager>,
+        node_env: NodeEnvironment,
+        bearer: String,
+        res: Sender<Result<Vec<NodeBackupInfo>, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages backups
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let result = run_blocking(move || node_backup::list_node_backups(&node_env)).await;
+        let _ = res.send(result).await;
+        Ok(())
+    }
+
+    pub async fn v2_api_download_node_backup(
+        db: Arc<SqliteManager>,
+        node_env: NodeEnvironment,
+        bearer: String,
+        file_name: String,
+        res: Sender<Result<Vec<u8>, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages backups
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let path = match node_backup::backup_file_path(&node_env, &file_name) {
+            Ok(path) => path,
+            Err(err) => {
+                let _ = res.send(Err(err)).await;
+                return Ok(());
+            }
+        };
+        let result = tokio::fs::read(&path).await.map_err(|e| APIError {
+            code: StatusCode::NOT_FOUND.as_u16(),
+            error: "Not Found".to_string(),
+            message: format!("Backup {} not found: {}", file_name, e),
+        });
+        let _ = res.send(result).await;
+        Ok(())
+    }
+
+    pub async fn v2_api_upload_node_backup(
+        db: Arc<SqliteManager>,
+        node_env: NodeEnvironment,
+        bearer: String,
+        file_data: Vec<u8>,
+        res: Sender<Result<NodeBackupInfo, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages backups
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let result = run_blocking(move || node_backup::save_uploaded_backup(&node_env, &file_data)).await;
+        let _ = res.send(result).await;
+        Ok(())
+    }
+
+    /// Restores sections of a backup. When the report asks for a restart, the node keys or the
+    /// secrets vault changed and are only picked up once the node starts again.
+    pub async fn v2_api_restore_node_backup(
+        db: Arc<SqliteManager>,
+        identity_public_key: VerifyingKey,
+        secrets_file_path: String,
+        node_env: NodeEnvironment,
+        bearer: String,
+        request: RestoreNodeBackupRequest,
+        res: Sender<Result<RestoreNodeBackupReport, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages backups
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let validate_only = request.validate_only;
+        let restore_db = db.clone();
+        let result = run_blocking(move || {
+            node_backup::restore_node_backup(
+                &restore_db,
+                &identity_public_key,
+                &secrets_file_path,
+                &node_env,
+                request,
+            )
+        })
+        .await;
+        if let Ok(report) = &result {
+            if !validate_only {
+                // Vectors of another embedding model were left out, the restore scheduled a migration
+                Self::spawn_embedding_migration(db);
+                zoo_log(
+                    ZooLogOption::Api,
+                    ZooLogLevel::Info,
+                    &format!(
+                        "Restored {:?} from the backup of {} (restart required: {})",
+                        report.restored_sections, report.manifest.node_name, report.restart_required
+                    ),
+                );
+            }
+        }
+        let _ = res.send(result).await;
+        Ok(())
+    }
+}
@@ -8,7 +8,7 @@ use serde_json::{json, Value};
 use zoo_http_api::node_api_router::{APIError, SendResponseBody, SendResponseBodyData};
 use zoo_message_primitives::{
     schemas::{
-        identity::Identity, inbox_name::InboxName, job::{ForkedJob, JobLike}, job_config::JobConfig, llm_providers::{common_agent_llm_provider::ProviderOrAgent, serialized_llm_provider::SerializedLLMProvider}, zoo_name::{ZooName, ZooSubidentityType}, smart_inbox::{LLMProviderSubset, ProviderType, SmartInbox, V2SmartInbox}
+        identity::Identity, inbox_name::InboxName, job::{ForkedJob, JobLike}, job_config::JobConfig, message_search::{MessageSearchResult, SearchMessagesRequest}, llm_providers::{common_agent_llm_provider::ProviderOrAgent, serialized_llm_provider::SerializedLLMProvider}, zoo_name::{ZooName, ZooSubidentityType}, smart_inbox::{LLMProviderSubset, ProviderType, SmartInbox, V2SmartInbox}
     }, zoo_message::{
         zoo_message::{MessageBody, MessageData}, zoo_message_schemas::{
             APIChangeJobAgentRequest, ExportInboxMessagesFormat, JobCreationInfo, JobMessage, MessageSchemaType, V2ChatMessage
@@ -18,6 +18,7 @@ use zoo_message_primitives::{
     }
 };
 
+use zoo_sqlite::errors::SqliteManagerError;
 use zoo_sqlite::inbox_manager::PaginatedSmartInboxes;
 use zoo_sqlite::SqliteManager;
 
@@ -812,6 +813,7 @@ impl Node {
                     thinking: None,
                     reasoning_effort: None,
                     web_search_enabled: None,
+                    memory_strategy: None,
                 });
                 let _ = res.send(Ok(config)).await;
                 Ok(())
@@ -1608,6 +1610,33 @@ impl Node {
         Ok(())
     }
 
+    pub async fn v2_api_search_messages(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        request: SearchMessagesRequest,
+        res: Sender<Result<Vec<MessageSearchResult>, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let result = db.search_messages(&request).await.map_err(|err| match err {
+            SqliteManagerError::ValidationError(message) => APIError {
+                code: StatusCode::BAD_REQUEST.as_u16(),
+                error: "Bad Request".to_string(),
+                message,
+            },
+            err => APIError {
+                code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                error: "Internal Server Error".to_string(),
+                message: format!("Failed to search messages: {}", err),
+            },
+        });
+        let _ = res.send(result).await;
+        Ok(())
+    }
+
     pub async fn v2_export_messages_from_inbox(
         db: Arc<SqliteManager>,
         bearer: String,
@@ -1,5 +1,6 @@
 use crate::network::node_error::NodeError;
 use crate::network::Node;
+use crate::tools::tool_execution::oauth_refresh::{apply_token_response, send_token_request};
 
 use async_channel::Sender;
 use chrono::Utc;
@@ -8,9 +9,7 @@ use serde_json::Value;
 use zoo_sqlite::SqliteManager;
 
 use std::sync::Arc;
-use base64::Engine;
 
-use reqwest::Client;
 use zoo_http_api::node_api_router::APIError;
 
 
@@ -104,15 +103,6 @@ impl Node {
         }
         let mut oauth_data = oauth_data.unwrap();
 
-        let mut body = "application/json";
-        if let Some(request_token_content_type) = oauth_data.request_token_content_type.clone() {
-            if request_token_content_type == "application/x-www-form-urlencoded" {
-                body = "application/x-www-form-urlencoded";
-            }
-        }
-
-        let client = Client::new();
-
         let mut request_body = serde_json::json!({
             "client_id": oauth_data.client_id.as_deref().unwrap_or_default(),
             "client_secret": oauth_data.client_secret.as_deref().unwrap_or_default(),
@@ -156,53 +146,7 @@ impl Node {
         let url = &oauth_data.clone().token_url.unwrap_or_default();
 
         println!("[OAuth] Calling {} with params {:?}", url, request_body);
-        let response = if body == "application/x-www-form-urlencoded" {
-            // Convert the JSON object to URL encoded form data
-            let form_data: Vec<(String, String)> = request_body
-                .as_object()
-                .unwrap()
-                .iter()
-                .filter_map(|(k, v)| v.as_str().map(|v_str| (k.clone(), v_str.to_string())))
-                .collect();
-
-            let mut request = client
-                .post(url)
-                .header("Accept", "application/json")
-                .header("Content-Type", body);
-
-            // Add Basic auth header if specified
-            if let Some(auth_header) = &oauth_data.request_token_auth_header {
-                if auth_header.to_lowercase() == "basic" {
-                    if let (Some(client_id), Some(client_secret)) =
-                        (oauth_data.client_id.clone(), oauth_data.client_secret.clone())
-                    {
-                        let auth = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", client_id, client_secret));
-                        request = request.header("Authorization", format!("Basic {}", auth));
-                    }
-                }
-            }
-
-            request.form(&form_data).send().await
-        } else {
-            let mut request = client
-                .post(url)
-                .header("Accept", "application/json")
-                .header("Content-Type", "application/json");
-
-            // Add Basic auth header if specified
-            if let Some(auth_header) = &oauth_data.request_token_auth_header {
-                if auth_header.to_lowercase() == "basic" {
-                    if let (Some(client_id), Some(client_secret)) =
-                        (oauth_data.client_id.clone(), oauth_data.client_secret.clone())
-                    {
-                        let auth = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", client_id, client_secret));
-                        request = request.header("Authorization", format!("Basic {}", auth));
-                    }
-                }
-            }
-
-            request.json(&request_body).send().await
-        };
+        let response = send_token_request(&oauth_data, &request_body).await;
 
         if response.is_err() {
             return Err(APIError {
@@ -250,21 +194,7 @@ impl Node {
             }
         }
         oauth_data.code = Some(code);
-        if let Some(access_token) = response["access_token"].as_str() {
-            oauth_data.access_token = Some(access_token.to_string());
-        }
-        if let Some(expires_in) = response["expires_in"].as_i64() {
-            oauth_data.access_token_expires_at = Some(Utc::now() + chrono::Duration::seconds(expires_in));
-        }
-        if let Some(refresh_token) = response["refresh_token"].as_str() {
-            oauth_data.refresh_token = Some(refresh_token.to_string());
-            if let Some(expires_in) = response["expires_in"].as_i64() {
-                oauth_data.refresh_token_expires_at = Some(Utc::now() + chrono::Duration::seconds(expires_in));
-            }
-        }
-        if let Some(scope) = response["scope"].as_str() {
-            oauth_data.scope = Some(scope.to_string());
-        }
+        apply_token_response(&mut oauth_data, &response, Utc::now());
 
         let update_result = db.update_oauth_token(&oauth_data.clone());
         if update_result.is_err() {
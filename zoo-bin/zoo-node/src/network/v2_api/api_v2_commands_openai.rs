@@ -0,0 +1,665 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_channel::Sender;
+use ed25519_dalek::SigningKey;
+use reqwest::StatusCode;
+use tokio::sync::Mutex;
+use x25519_dalek::PublicKey as EncryptionPublicKey;
+use x25519_dalek::StaticSecret as EncryptionStaticKey;
+
+use zoo_http_api::api_openai::openai_types::{
+    OpenAIChatCompletionChoice, OpenAIChatCompletionChunk, OpenAIChatCompletionOutput, OpenAIChatCompletionRequest,
+    OpenAIChatCompletionResponse, OpenAIChatDelta, OpenAIChatMessage, OpenAIModel, OpenAIModelList, OpenAIUsage,
+};
+use zoo_http_api::node_api_router::APIError;
+use zoo_message_primitives::schemas::inbox_name::InboxName;
+use zoo_message_primitives::schemas::job_config::JobConfig;
+use zoo_message_primitives::schemas::ws_types::WSMessagePayload;
+use zoo_message_primitives::schemas::zoo_name::ZooName;
+use zoo_message_primitives::zoo_message::zoo_message_schemas::{JobCreationInfo, JobMessage};
+use zoo_message_primitives::zoo_utils::utils::count_tokens_from_message_llama3;
+use zoo_sqlite::SqliteManager;
+
+use crate::llm_provider::job_manager::JobManager;
+use crate::managers::IdentityManager;
+use crate::network::node_error::NodeError;
+use crate::network::ws_manager::WebSocketManager;
+use crate::network::Node;
+
+// How long a completion request waits for the job to produce an answer
+const OPENAI_COMPLETION_TIMEOUT: Duration = Duration::from_secs(60 * 10);
+// Delay between checks of the job inbox for the final answer
+const OPENAI_COMPLETION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An OpenAI `messages` array mapped onto a Zoo job: the system prompt goes into
+/// the job config, the history is replayed into the inbox and the prompt is sent
+/// as a regular job message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenAIConversation {
+    pub system_prompt: Option<String>,
+    // Alternating (user, assistant, user, ...) turns, always starting with a user turn
+    pub history: Vec<String>,
+    pub prompt: String,
+}
+
+impl OpenAIConversation {
+    pub fn from_messages(messages: &[OpenAIChatMessage]) -> Result<Self, String> {
+        let mut system_parts: Vec<String> = Vec::new();
+        // (is_user, content) with consecutive turns of the same role merged
+        let mut turns: Vec<(bool, String)> = Vec::new();
+
+        for message in messages {
+            let content = message.text_content();
+            match message.role.as_str() {
+                "system" | "developer" => system_parts.push(content),
+                role => {
+                    let is_user = role != "assistant";
+                    match turns.last_mut() {
+                        Some((last_is_user, last_content)) if *last_is_user == is_user => {
+                            last_content.push_str("\n\n");
+                            last_content.push_str(&content);
+                        }
+                        _ => turns.push((is_user, content)),
+                    }
+                }
+            }
+        }
+
+        let prompt = match turns.pop() {
+            Some((true, content)) => content,
+            Some((false, _)) => return Err("The last message must have the role 'user'".to_string()),
+            None => return Err("At least one user message is required".to_string()),
+        };
+
+        // The inbox history must start with a user turn
+        if let Some((false, _)) = turns.first() {
+            turns.insert(0, (true, String::new()));
+        }
+
+        let system_prompt = if system_parts.is_empty() {
+            None
+        } else {
+            Some(system_parts.join("\n\n"))
+        };
+
+        Ok(Self {
+            system_prompt,
+            history: turns.into_iter().map(|(_, content)| content).collect(),
+            prompt,
+        })
+    }
+
+    pub fn estimated_prompt_tokens(&self) -> u64 {
+        let mut text = self.system_prompt.clone().unwrap_or_default();
+        for turn in self.history.iter().chain(std::iter::once(&self.prompt)) {
+            text.push('\n');
+            text.push_str(turn);
+        }
+        count_tokens_from_message_llama3(&text) as u64
+    }
+}
+
+impl Node {
+    pub async fn v2_api_openai_list_models(
+        db: Arc<SqliteManager>,
+        node_name: ZooName,
+        bearer: String,
+        res: Sender<Result<OpenAIModelList, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let mut data = Vec::new();
+
+        match db.get_all_agents() {
+            Ok(agents) => {
+                for agent in agents {
+                    data.push(OpenAIModel {
+                        id: agent.agent_id,
+                        object: "model".to_string(),
+                        created: 0,
+                        owned_by: "zoo-agent".to_string(),
+                    });
+                }
+            }
+            Err(err) => {
+                let _ = res
+                    .send(Err(APIError {
+                        code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                        error: "Internal Server Error".to_string(),
+                        message: format!("Failed to retrieve agents: {}", err),
+                    }))
+                    .await;
+                return Ok(());
+            }
+        }
+
+        match Self::internal_get_llm_providers_for_profile(db.clone(), node_name.node_name, "main".to_string()).await {
+            Ok(llm_providers) => {
+                for llm_provider in llm_providers {
+                    data.push(OpenAIModel {
+                        owned_by: llm_provider.get_provider_string(),
+                        id: llm_provider.id,
+                        object: "model".to_string(),
+                        created: 0,
+                    });
+                }
+            }
+            Err(err) => {
+                let _ = res
+                    .send(Err(APIError {
+                        code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                        error: "Internal Server Error".to_string(),
+                        message: format!("Failed to retrieve LLM providers: {}", err),
+                    }))
+                    .await;
+                return Ok(());
+            }
+        }
+
+        let _ = res
+            .send(Ok(OpenAIModelList {
+                object: "list".to_string(),
+                data,
+            }))
+            .await;
+        Ok(())
+    }
+
+    pub async fn v2_api_openai_chat_completions(
+        db: Arc<SqliteManager>,
+        node_name: ZooName,
+        identity_manager: Arc<Mutex<IdentityManager>>,
+        job_manager: Arc<Mutex<JobManager>>,
+        ws_manager: Option<Arc<Mutex<WebSocketManager>>>,
+        bearer: String,
+        payload: OpenAIChatCompletionRequest,
+        node_encryption_sk: EncryptionStaticKey,
+        node_encryption_pk: EncryptionPublicKey,
+        node_signing_sk: SigningKey,
+        res: Sender<Result<OpenAIChatCompletionOutput, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let conversation = match OpenAIConversation::from_messages(&payload.messages) {
+            Ok(conversation) => conversation,
+            Err(message) => {
+                let _ = res
+                    .send(Err(APIError {
+                        code: StatusCode::BAD_REQUEST.as_u16(),
+                        error: "Bad Request".to_string(),
+                        message,
+                    }))
+                    .await;
+                return Ok(());
+            }
+        };
+
+        // The model is either an agent or an LLM provider of the main profile
+        let is_agent = matches!(db.get_agent(&payload.model), Ok(Some(_)));
+        let is_llm_provider = !is_agent
+            && Self::internal_get_llm_providers_for_profile(
+                db.clone(),
+                node_name.node_name.clone(),
+                "main".to_string(),
+            )
+            .await
+            .map(|providers| providers.iter().any(|provider| provider.id == payload.model))
+            .unwrap_or(false);
+        if !is_agent && !is_llm_provider {
+            let _ = res
+                .send(Err(APIError {
+                    code: StatusCode::NOT_FOUND.as_u16(),
+                    error: "model_not_found".to_string(),
+                    message: format!("The model '{}' does not exist", payload.model),
+                }))
+                .await;
+            return Ok(());
+        }
+
+        let job_id = match Self::openai_prepare_job(
+            db.clone(),
+            node_name.clone(),
+            identity_manager.clone(),
+            job_manager.clone(),
+            bearer.clone(),
+            &payload,
+            &conversation,
+            node_encryption_sk.clone(),
+            node_encryption_pk,
+            node_signing_sk.clone(),
+        )
+        .await
+        {
+            Ok(job_id) => job_id,
+            Err(api_error) => {
+                let _ = res.send(Err(api_error)).await;
+                return Ok(());
+            }
+        };
+
+        let inbox_name = InboxName::get_job_inbox_name_from_params(job_id.clone())?.to_string();
+
+        // Subscribe before sending the message so no streamed token is missed
+        let listener = match (&ws_manager, payload.is_stream()) {
+            (Some(ws_manager), true) => Some(ws_manager.lock().await.add_inbox_listener(inbox_name.clone()).await),
+            _ => None,
+        };
+
+        let job_message = JobMessage {
+            job_id: job_id.clone(),
+            content: conversation.prompt.clone(),
+            reasoning_content: None,
+            parent: None,
+            sheet_job_data: None,
+            callback: None,
+            tools: None,
+            metadata: None,
+            tool_key: None,
+            fs_files_paths: vec![],
+            job_filenames: vec![],
+        };
+        let (message_sender, message_receiver) = async_channel::bounded(1);
+        let _ = Self::v2_job_message(
+            db.clone(),
+            node_name,
+            identity_manager,
+            job_manager,
+            bearer,
+            job_message,
+            node_encryption_sk,
+            node_encryption_pk,
+            node_signing_sk,
+            None,
+            message_sender,
+        )
+        .await;
+        let user_message_hash = match message_receiver.recv().await {
+            Ok(Ok(response)) => response.message_id,
+            Ok(Err(api_error)) => {
+                let _ = res.send(Err(api_error)).await;
+                return Ok(());
+            }
+            Err(e) => {
+                let _ = res.send(Err(Self::generic_api_error(&e.to_string()))).await;
+                return Ok(());
+            }
+        };
+
+        let completion_id = format!("chatcmpl-{}", uuid::Uuid::new_v4().simple());
+        let created = chrono::Utc::now().timestamp();
+
+        if payload.is_stream() {
+            let (chunk_sender, chunk_receiver) = async_channel::unbounded();
+            if res.send(Ok(OpenAIChatCompletionOutput::Stream(chunk_receiver))).await.is_err() {
+                return Ok(());
+            }
+            Self::openai_stream_job_reply(
+                db,
+                listener,
+                &job_id,
+                &inbox_name,
+                &user_message_hash,
+                &completion_id,
+                created,
+                &payload.model,
+                chunk_sender,
+            )
+            .await;
+            return Ok(());
+        }
+
+        let start = Instant::now();
+        let content = loop {
+            if let Some(content) = Self::openai_fetch_job_reply(&db, &inbox_name, &user_message_hash) {
+                break content;
+            }
+            if start.elapsed() >= OPENAI_COMPLETION_TIMEOUT {
+                let _ = res.send(Err(Self::openai_timeout_error(&job_id))).await;
+                return Ok(());
+            }
+            tokio::time::sleep(OPENAI_COMPLETION_POLL_INTERVAL).await;
+        };
+
+        let prompt_tokens = conversation.estimated_prompt_tokens();
+        let completion_tokens = count_tokens_from_message_llama3(&content) as u64;
+        let response = OpenAIChatCompletionResponse {
+            id: completion_id,
+            object: "chat.completion".to_string(),
+            created,
+            model: payload.model,
+            choices: vec![OpenAIChatCompletionChoice {
+                index: 0,
+                message: OpenAIChatMessage::assistant(content),
+                finish_reason: Some("stop".to_string()),
+            }],
+            usage: OpenAIUsage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            },
+            zoo_job_id: job_id,
+        };
+        let _ = res.send(Ok(OpenAIChatCompletionOutput::Completion(response))).await;
+        Ok(())
+    }
+
+    /// Creates a hidden job for the completion, applies the sampling parameters and
+    /// system prompt to its config and replays the previous turns into its inbox.
+    #[allow(clippy::too_many_arguments)]
+    async fn openai_prepare_job(
+        db: Arc<SqliteManager>,
+        node_name: ZooName,
+        identity_manager: Arc<Mutex<IdentityManager>>,
+        job_manager: Arc<Mutex<JobManager>>,
+        bearer: String,
+        payload: &OpenAIChatCompletionRequest,
+        conversation: &OpenAIConversation,
+        node_encryption_sk: EncryptionStaticKey,
+        node_encryption_pk: EncryptionPublicKey,
+        node_signing_sk: SigningKey,
+    ) -> Result<String, APIError> {
+        let job_creation_info = JobCreationInfo {
+            scope: payload.job_scope.clone().unwrap_or_default(),
+            is_hidden: Some(true),
+            associated_ui: None,
+        };
+
+        let (res_sender, res_receiver) = async_channel::bounded(1);
+        let _ = Self::v2_create_new_job(
+            db.clone(),
+            node_name.clone(),
+            identity_manager.clone(),
+            job_manager,
+            bearer.clone(),
+            job_creation_info,
+            payload.model.clone(),
+            node_encryption_sk.clone(),
+            node_encryption_pk,
+            node_signing_sk.clone(),
+            res_sender,
+        )
+        .await;
+        let job_id = res_receiver
+            .recv()
+            .await
+            .map_err(|e| Self::generic_api_error(&e.to_string()))??;
+
+        let (config_sender, config_receiver) = async_channel::bounded(1);
+        let _ = Self::v2_api_get_job_config(db.clone(), bearer.clone(), job_id.clone(), config_sender).await;
+        let current_config = config_receiver
+            .recv()
+            .await
+            .map_err(|e| Self::generic_api_error(&e.to_string()))??;
+
+        let requested_config = JobConfig {
+            custom_system_prompt: conversation.system_prompt.clone(),
+            temperature: payload.temperature,
+            max_tokens: payload.max_output_tokens(),
+            seed: payload.seed,
+            top_p: payload.top_p,
+            stream: Some(payload.is_stream()),
+            use_tools: payload.use_tools,
+            ..JobConfig::empty()
+        };
+        let (update_sender, update_receiver) = async_channel::bounded(1);
+        let _ = Self::v2_api_update_job_config(
+            db.clone(),
+            bearer.clone(),
+            job_id.clone(),
+            requested_config.merge(&current_config),
+            update_sender,
+        )
+        .await;
+        update_receiver
+            .recv()
+            .await
+            .map_err(|e| Self::generic_api_error(&e.to_string()))??;
+
+        if !conversation.history.is_empty() {
+            let messages = conversation
+                .history
+                .iter()
+                .map(|content| JobMessage {
+                    job_id: job_id.clone(),
+                    content: content.clone(),
+                    reasoning_content: None,
+                    parent: None,
+                    sheet_job_data: None,
+                    callback: None,
+                    tools: None,
+                    metadata: None,
+                    tool_key: None,
+                    fs_files_paths: vec![],
+                    job_filenames: vec![],
+                })
+                .collect();
+
+            let (history_sender, history_receiver) = async_channel::bounded(1);
+            let _ = Self::v2_add_messages_god_mode(
+                db,
+                node_name,
+                identity_manager,
+                bearer,
+                job_id.clone(),
+                messages,
+                node_encryption_sk,
+                node_encryption_pk,
+                node_signing_sk,
+                history_sender,
+            )
+            .await;
+            history_receiver
+                .recv()
+                .await
+                .map_err(|e| Self::generic_api_error(&e.to_string()))??;
+        }
+
+        Ok(job_id)
+    }
+
+    /// Returns the content of the answer to `user_message_hash` once it has been added to the inbox.
+    fn openai_fetch_job_reply(db: &Arc<SqliteManager>, inbox_name: &str, user_message_hash: &str) -> Option<String> {
+        let messages = db.get_last_messages_from_inbox(inbox_name.to_string(), 1, None).ok()?;
+        let last_message = messages.last()?.first()?.clone();
+        let last_hash = last_message.calculate_message_hash_for_pagination();
+        if last_hash == user_message_hash {
+            return None;
+        }
+
+        let parent_hash = db.get_parent_message_hash(inbox_name, &last_hash).ok()??;
+        if parent_hash != user_message_hash {
+            return None;
+        }
+
+        Self::convert_zoo_message_to_v2_chat_message(last_message)
+            .ok()
+            .map(|message| message.job_message.content)
+    }
+
+    fn openai_timeout_error(job_id: &str) -> APIError {
+        APIError {
+            code: StatusCode::GATEWAY_TIMEOUT.as_u16(),
+            error: "Gateway Timeout".to_string(),
+            message: format!("Timed out waiting for the answer of job {}", job_id),
+        }
+    }
+
+    /// Forwards the tokens streamed by the LLM provider as completion chunks and closes
+    /// the stream once the final answer lands in the inbox, or with a timeout error.
+    #[allow(clippy::too_many_arguments)]
+    async fn openai_stream_job_reply(
+        db: Arc<SqliteManager>,
+        mut listener: Option<async_channel::Receiver<WSMessagePayload>>,
+        job_id: &str,
+        inbox_name: &str,
+        user_message_hash: &str,
+        completion_id: &str,
+        created: i64,
+        model: &str,
+        chunks: Sender<Result<OpenAIChatCompletionChunk, APIError>>,
+    ) {
+        let role_delta = OpenAIChatDelta {
+            role: Some("assistant".to_string()),
+            content: None,
+        };
+        let _ = chunks
+            .send(Ok(OpenAIChatCompletionChunk::new(
+                completion_id,
+                created,
+                model,
+                role_delta,
+                None,
+            )))
+            .await;
+
+        let start = Instant::now();
+        let mut streamed_content = String::new();
+        loop {
+            if chunks.is_closed() {
+                // The client went away, nothing else to do
+                return;
+            }
+
+            let received = match &listener {
+                Some(receiver) => {
+                    tokio::select! {
+                        update = receiver.recv() => Some(update.ok()),
+                        _ = tokio::time::sleep(OPENAI_COMPLETION_POLL_INTERVAL) => None,
+                    }
+                }
+                None => {
+                    tokio::time::sleep(OPENAI_COMPLETION_POLL_INTERVAL).await;
+                    None
+                }
+            };
+            let update = match received {
+                Some(Some(update)) => Some(update),
+                Some(None) => {
+                    // The listener was dropped, keep polling the inbox only
+                    listener = None;
+                    None
+                }
+                None => None,
+            };
+
+            if let Some(update) = update {
+                let is_reasoning = matches!(&update.metadata, Some(metadata) if metadata.is_reasoning);
+                if update.is_stream && !is_reasoning {
+                    if let Some(content) = update.message.filter(|content| !content.is_empty()) {
+                        streamed_content.push_str(&content);
+                        let delta = OpenAIChatDelta {
+                            role: None,
+                            content: Some(content),
+                        };
+                        let _ = chunks
+                            .send(Ok(OpenAIChatCompletionChunk::new(
+                                completion_id,
+                                created,
+                                model,
+                                delta,
+                                None,
+                            )))
+                            .await;
+                    }
+                }
+                continue;
+            }
+
+            if let Some(content) = Self::openai_fetch_job_reply(&db, inbox_name, user_message_hash) {
+                // Send whatever the stream did not deliver (or the whole answer for non-streaming providers)
+                let remaining = match content.strip_prefix(streamed_content.as_str()) {
+                    Some(rest) => rest.to_string(),
+                    None if streamed_content.is_empty() => content,
+                    None => String::new(),
+                };
+                if !remaining.is_empty() {
+                    let delta = OpenAIChatDelta {
+                        role: None,
+                        content: Some(remaining),
+                    };
+                    let _ = chunks
+                        .send(Ok(OpenAIChatCompletionChunk::new(
+                            completion_id,
+                            created,
+                            model,
+                            delta,
+                            None,
+                        )))
+                        .await;
+                }
+                break;
+            }
+
+            if start.elapsed() >= OPENAI_COMPLETION_TIMEOUT {
+                let _ = chunks.send(Err(Self::openai_timeout_error(job_id))).await;
+                return;
+            }
+        }
+
+        let _ = chunks
+            .send(Ok(OpenAIChatCompletionChunk::new(
+                completion_id,
+                created,
+                model,
+                OpenAIChatDelta::default(),
+                Some("stop".to_string()),
+            )))
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(role: &str, content: &str) -> OpenAIChatMessage {
+        serde_json::from_value(json!({"role": role, "content": content})).unwrap()
+    }
+
+    #[test]
+    fn test_conversation_from_single_user_message() {
+        let conversation = OpenAIConversation::from_messages(&[message("user", "hello")]).unwrap();
+        assert_eq!(conversation.system_prompt, None);
+        assert!(conversation.history.is_empty());
+        assert_eq!(conversation.prompt, "hello");
+    }
+
+    #[test]
+    fn test_conversation_splits_system_history_and_prompt() {
+        let conversation = OpenAIConversation::from_messages(&[
+            message("system", "be brief"),
+            message("user", "q1"),
+            message("assistant", "a1"),
+            message("user", "q2"),
+            message("user", "more context"),
+        ])
+        .unwrap();
+        assert_eq!(conversation.system_prompt, Some("be brief".to_string()));
+        assert_eq!(conversation.history, vec!["q1".to_string(), "a1".to_string()]);
+        assert_eq!(conversation.prompt, "q2\n\nmore context");
+    }
+
+    #[test]
+    fn test_conversation_history_starts_with_user_turn() {
+        let conversation =
+            OpenAIConversation::from_messages(&[message("assistant", "hi, how can I help?"), message("user", "q")])
+                .unwrap();
+        assert_eq!(conversation.history, vec!["".to_string(), "hi, how can I help?".to_string()]);
+        assert_eq!(conversation.prompt, "q");
+    }
+
+    #[test]
+    fn test_conversation_requires_trailing_user_message() {
+        assert!(OpenAIConversation::from_messages(&[]).is_err());
+        assert!(OpenAIConversation::from_messages(&[message("system", "x")]).is_err());
+        assert!(OpenAIConversation::from_messages(&[message("user", "q"), message("assistant", "a")]).is_err());
+    }
+}
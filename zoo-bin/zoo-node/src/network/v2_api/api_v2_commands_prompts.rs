@@ -238,4 +238,30 @@ impl Node {
             }
         }
     }
+
+    /// The enabled prompts, published by the MCP server. Like the MCP tools, the MCP endpoints
+    /// are served by the node itself so no bearer is passed.
+    pub async fn v2_api_list_mcp_prompts(
+        db: Arc<SqliteManager>,
+        res: Sender<Result<Vec<CustomPrompt>, APIError>>,
+    ) -> Result<(), NodeError> {
+        let _bearer = Self::get_bearer_token(db.clone(), &res).await?;
+
+        match db.get_all_prompts() {
+            Ok(prompts) => {
+                let prompts = prompts.into_iter().filter(|prompt| prompt.is_enabled).collect();
+                let _ = res.send(Ok(prompts)).await;
+                Ok(())
+            }
+            Err(err) => {
+                let api_error = APIError {
+                    code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                    error: "Internal Server Error".to_string(),
+                    message: format!("Failed to get the MCP prompts: {}", err),
+                };
+                let _ = res.send(Err(api_error)).await;
+                Ok(())
+            }
+        }
+    }
 }
@@ -0,0 +1,197 @@
+use std::sync::Arc;
+
+use async_channel::Sender;
+use ed25519_dalek::SigningKey;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use zoo_http_api::node_api_router::APIError;
+use zoo_message_primitives::schemas::secrets::{
+    RotateSecretsKeyRequest, SecretInfo, SecretsExport, SecretsKeyInfo, SetSecretRequest,
+};
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
+use zoo_sqlite::secrets_vault::SecretsKeySource;
+use zoo_sqlite::{errors::SqliteManagerError, SqliteManager};
+
+use crate::network::{node_error::NodeError, Node};
+
+impl Node {
+    pub async fn v2_api_set_secret(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        request: SetSecretRequest,
+        res: Sender<Result<SecretInfo, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages secrets
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        match db.set_secret(&request) {
+            Ok(secret) => {
+                let _ = res.send(Ok(secret)).await;
+            }
+            Err(err) => {
+                let _ = res.send(Err(secrets_api_error("Failed to set secret", err))).await;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn v2_api_list_secrets(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        res: Sender<Result<Vec<SecretInfo>, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages secrets
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        match db.get_all_secrets() {
+            Ok(secrets) => {
+                let _ = res.send(Ok(secrets)).await;
+            }
+            Err(err) => {
+                let _ = res.send(Err(secrets_api_error("Failed to list secrets", err))).await;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn v2_api_remove_secret(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        name: String,
+        res: Sender<Result<Value, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages secrets
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        match db.remove_secret(&name) {
+            Ok(true) => {
+                let _ = res
+                    .send(Ok(json!({ "message": format!("Secret {} removed", name) })))
+                    .await;
+            }
+            Ok(false) => {
+                let _ = res
+                    .send(Err(secrets_api_error(
+                        "Failed to remove secret",
+                        SqliteManagerError::SecretNotFound(name),
+                    )))
+                    .await;
+            }
+            Err(err) => {
+                let _ = res.send(Err(secrets_api_error("Failed to remove secret", err))).await;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn v2_api_get_secrets_key(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        res: Sender<Result<SecretsKeyInfo, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages secrets
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        match db.get_secrets_key_info() {
+            Ok(Some(key_info)) => {
+                let _ = res.send(Ok(key_info)).await;
+            }
+            Ok(None) => {
+                let _ = res
+                    .send(Err(secrets_api_error(
+                        "Failed to get secrets key",
+                        SqliteManagerError::SecretsVaultLocked,
+                    )))
+                    .await;
+            }
+            Err(err) => {
+                let _ = res.send(Err(secrets_api_error("Failed to get secrets key", err))).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rotates the secrets data key. The new key is wrapped with the passphrase of the request if
+    /// there is one, and with the node identity otherwise.
+    pub async fn v2_api_rotate_secrets_key(
+        db: Arc<SqliteManager>,
+        identity_secret_key: SigningKey,
+        bearer: String,
+        request: RotateSecretsKeyRequest,
+        res: Sender<Result<SecretsKeyInfo, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages secrets
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let key_source = match request.passphrase {
+            Some(passphrase) => SecretsKeySource::Passphrase(passphrase),
+            None => SecretsKeySource::NodeIdentity(identity_secret_key.to_bytes().to_vec()),
+        };
+        match db.rotate_secrets_key(&key_source) {
+            Ok(key_info) => {
+                zoo_log(
+                    ZooLogOption::Api,
+                    ZooLogLevel::Info,
+                    &format!(
+                        "Secrets key rotated to {} ({})",
+                        key_info.key_id,
+                        key_info.key_source.as_str()
+                    ),
+                );
+                let _ = res.send(Ok(key_info)).await;
+            }
+            Err(err) => {
+                let _ = res
+                    .send(Err(secrets_api_error("Failed to rotate secrets key", err)))
+                    .await;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn v2_api_export_secrets(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        res: Sender<Result<SecretsExport, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Only the node API key manages secrets
+        if Self::validate_node_api_key(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        match db.export_secrets() {
+            Ok(export) => {
+                let _ = res.send(Ok(export)).await;
+            }
+            Err(err) => {
+                let _ = res.send(Err(secrets_api_error("Failed to export secrets", err))).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn secrets_api_error(context: &str, err: SqliteManagerError) -> APIError {
+    let (code, error) = match err {
+        SqliteManagerError::ValidationError(_) => (StatusCode::BAD_REQUEST, "Bad Request"),
+        SqliteManagerError::SecretNotFound(_) => (StatusCode::NOT_FOUND, "Not Found"),
+        SqliteManagerError::SecretsVaultLocked => (StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable"),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error"),
+    };
+    APIError {
+        code: code.as_u16(),
+        error: error.to_string(),
+        message: format!("{}: {}", context, err),
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     llm_provider::job_manager::JobManager, managers::{tool_router::ToolRouter, IdentityManager}, network::{
-        node_error::NodeError, node_shareable_logic::{download_zip_from_url, ZipFileContents}, zip_export_import::zip_export_import::{generate_tool_zip, import_dependencies_tools, import_tool}, Node
+        agent_payments_manager::my_agent_offerings_manager::MyAgentOfferingsManager, node_error::NodeError, node_shareable_logic::{download_zip_from_url, ZipFileContents}, zip_export_import::zip_export_import::{generate_tool_zip, import_dependencies_tools, import_tool}, Node
     }, tools::{
         tool_definitions::definition_generation::{generate_tool_definitions, get_all_tools}, tool_execution::execution_coordinator::{execute_code, execute_mcp_tool_cmd, execute_tool_cmd}, tool_generation::v2_create_and_send_job_message, tool_prompts::{generate_code_prompt, tool_metadata_implementation_prompt}
     }, utils::environment::NodeEnvironment
@@ -16,14 +16,14 @@ use zoo_embedding::embedding_generator::EmbeddingGenerator;
 use zoo_http_api::node_api_router::{APIError, SendResponseBodyData};
 use zoo_message_primitives::{
     schemas::{
-        identity::Identity, inbox_name::InboxName, indexable_version::IndexableVersion, job::JobLike, job_config::JobConfig, llm_providers::agent::Agent, zoo_name::{ZooName, ZooSubidentityType}, zoo_tools::{CodeLanguage, DynamicToolType}, tool_router_key::ToolRouterKey
+        api_keys::{ApiKeyScope, API_KEY_PREFIX}, identity::Identity, inbox_name::InboxName, indexable_version::IndexableVersion, job::JobLike, job_config::JobConfig, llm_providers::agent::Agent, zoo_name::{ZooName, ZooSubidentityType}, zoo_tools::{CodeLanguage, DynamicToolType}, tool_router_key::ToolRouterKey
     }, zoo_message::zoo_message_schemas::{CallbackAction, JobCreationInfo, JobMessage, MessageSchemaType}, zoo_utils::{
         job_scope::MinimalJobScope, zoo_message_builder::ZooMessageBuilder, signatures::clone_signature_secret_key
     }
 };
-use zoo_sqlite::{errors::SqliteManagerError, SqliteManager};
+use zoo_sqlite::{errors::SqliteManagerError, tool_version_manager::ToolVersionSelector, SqliteManager};
 use zoo_tools_primitives::tools::{
-    agent_tool_wrapper::AgentToolWrapper, deno_tools::DenoTool, error::ToolError, parameters::Parameters, python_tools::PythonTool, zoo_tool::ZooToolHeader, zoo_tool::{ZooTool, ZooToolWithAssets}, tool_config::{OAuth, ToolConfig}, tool_output_arg::ToolOutputArg, tool_playground::{ToolPlayground, ToolPlaygroundMetadata}, tool_types::{OperatingSystem, RunnerType, ToolResult}
+    agent_tool_wrapper::AgentToolWrapper, deno_tools::DenoTool, error::ToolError, parameters::Parameters, python_tools::PythonTool, schema_diff::{diff_schemas, SchemaDirection}, zoo_tool::ZooToolHeader, zoo_tool::{ZooTool, ZooToolWithAssets}, tool_config::{OAuth, ToolConfig}, tool_output_arg::ToolOutputArg, tool_playground::{ToolPlayground, ToolPlaygroundMetadata}, tool_types::{OperatingSystem, RunnerType, ToolResult}
 };
 use std::{
     collections::HashMap, env, io::Read, path::{absolute, PathBuf}, sync::Arc, time::Instant
@@ -1103,6 +1103,14 @@ impl Node {
         app_id: String,
         original_tool_key_path: Option<String>,
     ) -> Result<Value, APIError> {
+        if let Some(permissions) = &payload.metadata.permissions {
+            permissions.validate().map_err(|e| APIError {
+                code: StatusCode::BAD_REQUEST.as_u16(),
+                error: "Bad Request".to_string(),
+                message: e.to_string(),
+            })?;
+        }
+
         let mut updated_payload = payload.clone();
         let dependencies = updated_payload.metadata.tools.clone().unwrap_or_default();
         for dependency in dependencies {
@@ -1148,6 +1156,7 @@ impl Node {
                     runner: payload.metadata.runner,
                     operating_system: payload.metadata.operating_system,
                     tool_set: payload.metadata.tool_set,
+                    permissions: payload.metadata.permissions,
                 };
                 ZooTool::Deno(tool, false)
             }
@@ -1184,6 +1193,7 @@ impl Node {
                     runner: payload.metadata.runner,
                     operating_system: payload.metadata.operating_system,
                     tool_set: payload.metadata.tool_set,
+                    permissions: payload.metadata.permissions,
                 };
                 ZooTool::Python(tool, false)
             }
@@ -1481,16 +1491,39 @@ impl Node {
         extra_config: Map<String, Value>,
         identity_manager: Arc<Mutex<IdentityManager>>,
         job_manager: Arc<Mutex<JobManager>>,
+        my_agent_payments_manager: Arc<Mutex<MyAgentOfferingsManager>>,
         encryption_secret_key: EncryptionStaticKey,
         encryption_public_key: EncryptionPublicKey,
         signing_secret_key: SigningKey,
         mounts: Option<Vec<String>>,
+        max_price: Option<u64>,
         res: Sender<Result<Value, APIError>>,
     ) -> Result<(), NodeError> {
         if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
             return Ok(());
         }
 
+        // Network tools are paid from the node wallet, a scoped API key needs to be allowed to spend
+        let is_network_tool = matches!(db.get_tool_by_key(&tool_router_key), Ok(ZooTool::Network(..)));
+        if bearer.starts_with(API_KEY_PREFIX) && is_network_tool {
+            let can_spend = matches!(
+                db.get_api_key_by_key(&bearer),
+                Ok(Some(api_key)) if api_key.has_scope(ApiKeyScope::WalletSpend)
+            );
+            if !can_spend {
+                let api_error = APIError {
+                    code: StatusCode::FORBIDDEN.as_u16(),
+                    error: "Forbidden".to_string(),
+                    message: format!(
+                        "The API key needs the {} scope to run network tools",
+                        ApiKeyScope::WalletSpend
+                    ),
+                };
+                let _ = res.send(Err(api_error)).await;
+                return Ok(());
+            }
+        }
+
         // Convert extra_config to Vec<ToolConfig> using basic_config_from_value
         let tool_configs = ToolConfig::basic_config_from_value(&Value::Object(extra_config));
 
@@ -1509,10 +1542,12 @@ impl Node {
             tool_configs,
             identity_manager,
             job_manager,
+            my_agent_payments_manager,
             encryption_secret_key,
             encryption_public_key,
             signing_secret_key,
             mounts,
+            max_price,
         )
         .await;
 
@@ -1547,6 +1582,7 @@ impl Node {
         extra_config: Map<String, Value>,
         identity_manager: Arc<Mutex<IdentityManager>>,
         job_manager: Arc<Mutex<JobManager>>,
+        my_agent_payments_manager: Arc<Mutex<MyAgentOfferingsManager>>,
         encryption_secret_key: EncryptionStaticKey,
         encryption_public_key: EncryptionPublicKey,
         signing_secret_key: SigningKey,
@@ -1568,6 +1604,7 @@ impl Node {
             tool_configs,
             identity_manager,
             job_manager,
+            my_agent_payments_manager,
             encryption_secret_key,
             encryption_public_key,
             signing_secret_key,
@@ -3376,6 +3413,7 @@ impl Node {
                         runner: new_tool.get_runner(),
                         operating_system: new_tool.get_operating_system(),
                         tool_set: new_tool.get_tool_set(),
+                        permissions: new_tool.get_permissions(),
                     },
                     tool_router_key: Some(new_tool.tool_router_key().to_string_without_version()),
                     job_id: Self::create_job_for_duplicate_tool(
@@ -4009,10 +4047,16 @@ LANGUAGE={env_language}
 
                     // Extract versions
                     let versions: Vec<String> = group.iter().map(|tool| tool.version.clone()).collect();
+                    // The version used by references without one, the latest unless rolled back
+                    let active_version = match db.get_tool_active_version(&key) {
+                        Ok(Some(version)) => IndexableVersion::from_number(version).to_version_string(),
+                        _ => versions.first().cloned().unwrap_or_default(),
+                    };
 
                     result.push(json!({
                         "tool_router_key": key,
                         "versions": versions,
+                        "active_version": active_version,
                     }));
                 }
 
@@ -4031,6 +4075,252 @@ LANGUAGE={env_language}
         }
     }
 
+    pub async fn v2_api_get_tool_upgrade_plan(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        tool_router_key: String,
+        from_version: Option<String>,
+        to_version: Option<String>,
+        res: Sender<Result<Value, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let result = Self::tool_upgrade_plan(&db, &tool_router_key, from_version, to_version);
+        let _ = res.send(result).await;
+        Ok(())
+    }
+
+    /// Compares two installed versions of a tool: the changes to its input and output schemas,
+    /// and the agents that would run another version if `to_version` became the active one.
+    /// Defaults to going from the active version to the latest installed one.
+    fn tool_upgrade_plan(
+        db: &SqliteManager,
+        tool_router_key: &str,
+        from_version: Option<String>,
+        to_version: Option<String>,
+    ) -> Result<Value, APIError> {
+        let bad_request = |message: String| APIError {
+            code: StatusCode::BAD_REQUEST.as_u16(),
+            error: "Bad Request".to_string(),
+            message,
+        };
+        let internal_error = |e: SqliteManagerError| APIError {
+            code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            error: "Internal Server Error".to_string(),
+            message: format!("Failed to get tool versions: {}", e),
+        };
+
+        let tool_key = ToolRouterKey::from_string(tool_router_key)
+            .map_err(bad_request)?
+            .to_string_without_version();
+        let installed = db.get_tool_versions(&tool_key).map_err(internal_error)?;
+        let active = db.get_tool_active_version(&tool_key).map_err(internal_error)?;
+        let Some(current) = ToolVersionSelector::Active.select(&installed, active) else {
+            return Err(APIError {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                error: "Not Found".to_string(),
+                message: format!("Tool not found: {}", tool_key),
+            });
+        };
+
+        let installed_version = |version: Option<String>, default: u64| -> Result<u64, APIError> {
+            let Some(version) = version else {
+                return Ok(default);
+            };
+            let version = IndexableVersion::from_string(&version)
+                .map_err(|e| bad_request(format!("Invalid version {}: {}", version, e)))?
+                .get_version_number();
+            if !installed.contains(&version) {
+                return Err(APIError {
+                    code: StatusCode::NOT_FOUND.as_u16(),
+                    error: "Not Found".to_string(),
+                    message: format!(
+                        "Version {} of {} is not installed",
+                        IndexableVersion::from_number(version),
+                        tool_key
+                    ),
+                });
+            }
+            Ok(version)
+        };
+        let from = installed_version(from_version, current)?;
+        let to = installed_version(to_version, installed[0])?;
+
+        let from_tool = db
+            .get_tool_by_key_and_version(&tool_key, Some(IndexableVersion::from_number(from)))
+            .map_err(internal_error)?;
+        let to_tool = db
+            .get_tool_by_key_and_version(&tool_key, Some(IndexableVersion::from_number(to)))
+            .map_err(internal_error)?;
+
+        let input_schema = |tool: &ZooTool| serde_json::to_value(tool.input_args()).unwrap_or_default();
+        let output_schema = |tool: &ZooTool| serde_json::from_str::<Value>(&tool.output_arg().json).unwrap_or_default();
+        let input_changes = diff_schemas(&input_schema(&from_tool), &input_schema(&to_tool), SchemaDirection::Input);
+        let output_changes = diff_schemas(
+            &output_schema(&from_tool),
+            &output_schema(&to_tool),
+            SchemaDirection::Output,
+        );
+        let breaking = input_changes
+            .iter()
+            .chain(output_changes.iter())
+            .any(|change| change.breaking);
+
+        // Agents that reference the tool, with the version they run now and after the upgrade
+        let mut agents = Vec::new();
+        for agent in db.get_all_agents().map_err(internal_error)? {
+            for tool in agent
+                .tools
+                .iter()
+                .filter(|tool| tool.to_string_without_version() == tool_key)
+            {
+                let Ok(selector) = ToolVersionSelector::from_router_key(tool) else {
+                    continue;
+                };
+                let version_string = |version: Option<u64>| version.map(|v| IndexableVersion::from_number(v).to_string());
+                let current_version = selector.select(&installed, active);
+                let planned_version = selector.select(&installed, Some(to));
+                agents.push(json!({
+                    "agent_id": agent.agent_id,
+                    "name": agent.name,
+                    "version_requirement": tool.version,
+                    "current_version": version_string(current_version),
+                    "planned_version": version_string(planned_version),
+                    "affected": current_version != planned_version,
+                }));
+            }
+        }
+
+        Ok(json!({
+            "tool_router_key": tool_key,
+            "from_version": IndexableVersion::from_number(from).to_string(),
+            "to_version": IndexableVersion::from_number(to).to_string(),
+            "active_version": IndexableVersion::from_number(current).to_string(),
+            "installed_versions": installed
+                .iter()
+                .map(|version| IndexableVersion::from_number(*version).to_string())
+                .collect::<Vec<String>>(),
+            "breaking": breaking,
+            "input_changes": input_changes,
+            "output_changes": output_changes,
+            "agents": agents,
+        }))
+    }
+
+    pub async fn v2_api_rollback_tool(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        tool_router_key: String,
+        res: Sender<Result<Value, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let tool_key = match ToolRouterKey::from_string(&tool_router_key) {
+            Ok(key) => key.to_string_without_version(),
+            Err(e) => {
+                let api_error = APIError {
+                    code: StatusCode::BAD_REQUEST.as_u16(),
+                    error: "Bad Request".to_string(),
+                    message: e,
+                };
+                let _ = res.send(Err(api_error)).await;
+                return Ok(());
+            }
+        };
+
+        let result = match db.rollback_tool(&tool_key) {
+            Ok(tool) => Ok(json!({
+                "status": "success",
+                "message": format!("Rolled back {} to version {}", tool_key, tool.version()),
+                "tool_router_key": tool_key,
+                "active_version": tool.version(),
+            })),
+            Err(SqliteManagerError::ToolNotFound(_)) => Err(APIError {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                error: "Not Found".to_string(),
+                message: format!("Tool not found: {}", tool_key),
+            }),
+            Err(SqliteManagerError::ValidationError(message)) => Err(APIError {
+                code: StatusCode::BAD_REQUEST.as_u16(),
+                error: "Bad Request".to_string(),
+                message,
+            }),
+            Err(e) => Err(APIError {
+                code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                error: "Internal Server Error".to_string(),
+                message: format!("Failed to roll back tool: {}", e),
+            }),
+        };
+        let _ = res.send(result).await;
+        Ok(())
+    }
+
+    /// Pins the version used by references without a version, or goes back to the latest
+    /// installed version when `version` is `None`.
+    pub async fn v2_api_set_tool_active_version(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        tool_router_key: String,
+        version: Option<String>,
+        res: Sender<Result<Value, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let parsed = ToolRouterKey::from_string(&tool_router_key).and_then(|key| {
+            let version = version
+                .as_deref()
+                .map(IndexableVersion::from_string)
+                .transpose()?
+                .map(|version| version.get_version_number());
+            Ok((key.to_string_without_version(), version))
+        });
+        let (tool_key, version) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let api_error = APIError {
+                    code: StatusCode::BAD_REQUEST.as_u16(),
+                    error: "Bad Request".to_string(),
+                    message: e,
+                };
+                let _ = res.send(Err(api_error)).await;
+                return Ok(());
+            }
+        };
+
+        let result = match db
+            .set_tool_active_version(&tool_key, version)
+            .and_then(|_| db.get_tool_by_key(&tool_key))
+        {
+            Ok(tool) => Ok(json!({
+                "status": "success",
+                "tool_router_key": tool_key,
+                "active_version": tool.version(),
+                "pinned": version.is_some(),
+            })),
+            Err(SqliteManagerError::ToolNotFound(message)) => Err(APIError {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                error: "Not Found".to_string(),
+                message: format!("Tool not found: {}", message),
+            }),
+            Err(e) => Err(APIError {
+                code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                error: "Internal Server Error".to_string(),
+                message: format!("Failed to set the active version: {}", e),
+            }),
+        };
+        let _ = res.send(result).await;
+        Ok(())
+    }
+
     pub async fn v2_api_set_tool_enabled(
         db: Arc<SqliteManager>,
         bearer: String,
@@ -4383,6 +4673,7 @@ LANGUAGE={env_language}
                     RunnerType::Any,
                     vec![],
                     None,
+                    None,
                 );
                 tool.check_code(code.clone(), support_files).await
             }
@@ -4425,6 +4716,7 @@ LANGUAGE={env_language}
                     runner: RunnerType::Any,
                     operating_system: vec![],
                     tool_set: None,
+                    permissions: None,
                 };
                 tool.check_code(code.clone(), support_files).await
             }
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use async_channel::Sender;
+use chrono::Utc;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use zoo_http_api::node_api_router::APIError;
+use zoo_message_primitives::schemas::usage::{
+    BudgetPeriod, BudgetScope, UsageBudget, UsageBudgetStatus, UsageQuery, UsageSummary,
+};
+use zoo_sqlite::{errors::SqliteManagerError, SqliteManager};
+
+use crate::network::{node_error::NodeError, Node};
+
+impl Node {
+    pub async fn v2_api_get_usage(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        query: UsageQuery,
+        res: Sender<Result<UsageSummary, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        match db.get_usage_summary(&query) {
+            Ok(summary) => {
+                let _ = res.send(Ok(summary)).await;
+            }
+            Err(err) => {
+                let _ = res.send(Err(usage_api_error("Failed to get usage", err))).await;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn v2_api_get_usage_budgets(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        res: Sender<Result<Vec<UsageBudgetStatus>, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let statuses = db.get_all_usage_budgets().and_then(|budgets| {
+            budgets
+                .into_iter()
+                .map(|budget| db.get_usage_budget_status(budget, now))
+                .collect::<Result<Vec<_>, _>>()
+        });
+        match statuses {
+            Ok(statuses) => {
+                let _ = res.send(Ok(statuses)).await;
+            }
+            Err(err) => {
+                let _ = res.send(Err(usage_api_error("Failed to get usage budgets", err))).await;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn v2_api_set_usage_budget(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        budget: UsageBudget,
+        res: Sender<Result<UsageBudgetStatus, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        let result = db
+            .set_usage_budget(&budget)
+            .and_then(|_| db.get_usage_budget_status(budget, Utc::now()));
+        match result {
+            Ok(status) => {
+                let _ = res.send(Ok(status)).await;
+            }
+            Err(err) => {
+                let _ = res.send(Err(usage_api_error("Failed to set usage budget", err))).await;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn v2_api_remove_usage_budget(
+        db: Arc<SqliteManager>,
+        bearer: String,
+        scope: BudgetScope,
+        scope_id: String,
+        period: BudgetPeriod,
+        res: Sender<Result<Value, APIError>>,
+    ) -> Result<(), NodeError> {
+        // Validate the bearer token
+        if Self::validate_bearer_token(&bearer, db.clone(), &res).await.is_err() {
+            return Ok(());
+        }
+
+        match db.remove_usage_budget(scope, &scope_id, period) {
+            Ok(true) => {
+                let _ = res
+                    .send(Ok(json!({
+                        "message": format!("Usage budget of {} {} removed", scope.as_str(), scope_id)
+                    })))
+                    .await;
+            }
+            Ok(false) => {
+                let api_error = APIError {
+                    code: StatusCode::NOT_FOUND.as_u16(),
+                    error: "Not Found".to_string(),
+                    message: format!(
+                        "No {} usage budget for {} {}",
+                        period.as_str(),
+                        scope.as_str(),
+                        scope_id
+                    ),
+                };
+                let _ = res.send(Err(api_error)).await;
+            }
+            Err(err) => {
+                let _ = res
+                    .send(Err(usage_api_error("Failed to remove usage budget", err)))
+                    .await;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn usage_api_error(context: &str, err: SqliteManagerError) -> APIError {
+    match err {
+        SqliteManagerError::ValidationError(_) | SqliteManagerError::DateTimeParseError(_) => APIError {
+            code: StatusCode::BAD_REQUEST.as_u16(),
+            error: "Bad Request".to_string(),
+            message: format!("{}: {}", context, err),
+        },
+        _ => APIError {
+            code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            error: "Internal Server Error".to_string(),
+            message: format!("{}: {}", context, err),
+        },
+    }
+}
@@ -12,7 +12,9 @@ use zoo_fs::{
 };
 use zoo_http_api::node_api_router::APIError;
 use zoo_message_primitives::{
-    schemas::zoo_fs::ZooFileChunkCollection, zoo_message::zoo_message_schemas::{
+    schemas::{
+        mcp_resources::{McpResource, McpResourceContents}, zoo_fs::ZooFileChunkCollection
+    }, zoo_message::zoo_message_schemas::{
         APIVecFsCopyFolder, APIVecFsCopyItem, APIVecFsCreateFolder, APIVecFsDeleteFolder, APIVecFsDeleteItem, APIVecFsMoveFolder, APIVecFsMoveItem, APIVecFsRetrievePathSimplifiedJson, APIVecFsRetrieveSourceFile, APIVecFsSearchItems
     }, zoo_utils::zoo_path::ZooPath
 };
@@ -950,4 +952,85 @@ impl Node {
 
         Ok(())
     }
+
+    pub async fn v2_api_list_mcp_resources(
+        db: Arc<SqliteManager>,
+        res: Sender<Result<Vec<McpResource>, APIError>>,
+    ) -> Result<(), NodeError> {
+        let _bearer = Self::get_bearer_token(db.clone(), &res).await?;
+
+        match ZooFileManager::list_mcp_resources(&db) {
+            Ok(resources) => {
+                let _ = res.send(Ok(resources)).await;
+            }
+            Err(e) => {
+                let api_error = APIError {
+                    code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                    error: "Internal Server Error".to_string(),
+                    message: format!("Failed to list the MCP resources: {}", e),
+                };
+                let _ = res.send(Err(api_error)).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn v2_api_read_mcp_resource(
+        db: Arc<SqliteManager>,
+        uri: String,
+        res: Sender<Result<McpResourceContents, APIError>>,
+    ) -> Result<(), NodeError> {
+        let _bearer = Self::get_bearer_token(db.clone(), &res).await?;
+
+        match ZooFileManager::read_mcp_resource(&db, &uri) {
+            Ok(contents) => {
+                let _ = res.send(Ok(contents)).await;
+            }
+            Err(e) => {
+                let _ = res.send(Err(Self::mcp_resource_error(&uri, e))).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn v2_api_get_mcp_resource_version(
+        db: Arc<SqliteManager>,
+        uri: String,
+        res: Sender<Result<Option<String>, APIError>>,
+    ) -> Result<(), NodeError> {
+        let _bearer = Self::get_bearer_token(db.clone(), &res).await?;
+
+        match ZooFileManager::mcp_resource_version(&db, &uri) {
+            Ok(version) => {
+                let _ = res.send(Ok(version)).await;
+            }
+            Err(e) => {
+                let _ = res.send(Err(Self::mcp_resource_error(&uri, e))).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn mcp_resource_error(uri: &str, e: ZooFsError) -> APIError {
+        match e {
+            ZooFsError::InvalidPathString(message) => APIError {
+                code: StatusCode::BAD_REQUEST.as_u16(),
+                error: "Bad Request".to_string(),
+                message,
+            },
+            ZooFsError::FileNotFoundWithPath(_) => APIError {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                error: "Not Found".to_string(),
+                message: format!("Resource not found: {}", uri),
+            },
+            e => APIError {
+                code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                error: "Internal Server Error".to_string(),
+                message: format!("Failed to read resource {}: {}", uri, e),
+            },
+        }
+    }
 }
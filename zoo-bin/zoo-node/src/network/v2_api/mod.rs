@@ -1,11 +1,16 @@
 pub mod api_v2_commands;
+pub mod api_v2_commands_api_keys;
+pub mod api_v2_commands_backup;
 pub mod api_v2_commands_cron;
 pub mod api_v2_commands_ext_agent_offers;
 pub mod api_v2_commands_jobs;
 pub mod api_v2_commands_my_agent_offers;
 pub mod api_v2_commands_oauth;
+pub mod api_v2_commands_openai;
 pub mod api_v2_commands_prompts;
+pub mod api_v2_commands_secrets;
 pub mod api_v2_commands_tools;
+pub mod api_v2_commands_usage;
 pub mod api_v2_commands_vecfs;
 pub mod api_v2_commands_wallets;
 
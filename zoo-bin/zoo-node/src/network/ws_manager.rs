@@ -32,6 +32,9 @@ use x25519_dalek::StaticSecret as EncryptionStaticKey;
 use super::Node;
 use crate::managers::identity_manager::IdentityManagerTrait;
 
+// In-process listeners for the updates of a given inbox (e.g. streamed OpenAI-compatible completions)
+type InboxListeners = Arc<Mutex<HashMap<String, Vec<async_channel::Sender<WSMessagePayload>>>>>;
+
 pub struct WebSocketManager {
     connections: HashMap<String, Arc<Mutex<SplitSink<WebSocket, Message>>>>,
     // TODO: maybe the first string should be a ZooName? or at least a zoo name string
@@ -42,6 +45,7 @@ pub struct WebSocketManager {
     identity_manager_trait: Arc<Mutex<dyn IdentityManagerTrait + Send>>,
     encryption_secret_key: EncryptionStaticKey,
     message_queue: MessageQueue,
+    inbox_listeners: InboxListeners,
 }
 
 impl Clone for WebSocketManager {
@@ -55,6 +59,7 @@ impl Clone for WebSocketManager {
             identity_manager_trait: Arc::clone(&self.identity_manager_trait),
             encryption_secret_key: self.encryption_secret_key.clone(),
             message_queue: Arc::clone(&self.message_queue),
+            inbox_listeners: Arc::clone(&self.inbox_listeners),
         }
     }
 }
@@ -87,6 +92,7 @@ impl WebSocketManager {
             identity_manager_trait,
             encryption_secret_key,
             message_queue: Arc::new(Mutex::new(VecDeque::new())),
+            inbox_listeners: Arc::new(Mutex::new(HashMap::new())),
         }));
 
         let manager_clone = Arc::clone(&manager);
@@ -103,6 +109,25 @@ impl WebSocketManager {
         self.zoo_db.upgrade()
     }
 
+    /// Registers an in-process listener that receives every update sent to the inbox.
+    /// The listener is dropped automatically once its receiver is closed.
+    pub async fn add_inbox_listener(&self, inbox_name: String) -> async_channel::Receiver<WSMessagePayload> {
+        let (sender, receiver) = async_channel::unbounded();
+        let mut listeners = self.inbox_listeners.lock().await;
+        listeners.entry(inbox_name).or_default().push(sender);
+        receiver
+    }
+
+    async fn notify_inbox_listeners(&self, inbox_name: &str, payload: &WSMessagePayload) {
+        let mut listeners = self.inbox_listeners.lock().await;
+        if let Some(senders) = listeners.get_mut(inbox_name) {
+            senders.retain(|sender| sender.try_send(payload.clone()).is_ok());
+            if senders.is_empty() {
+                listeners.remove(inbox_name);
+            }
+        }
+    }
+
     pub async fn start_message_sender(manager: Arc<Mutex<Self>>, message_queue: MessageQueue) {
         loop {
             let message = {
@@ -377,6 +402,10 @@ impl WebSocketManager {
             is_stream,
         };
 
+        if topic == WSTopic::Inbox {
+            self.notify_inbox_listeners(&subtopic, &payload).await;
+        }
+
         // Serialize the payload to JSON
         let payload_json = serde_json::to_string(&payload).expect("Failed to serialize WSMessagePayload");
 
@@ -2,6 +2,8 @@ use super::ws_manager::WebSocketManager;
 use futures::stream::SplitSink;
 use futures::SinkExt;
 use futures::StreamExt;
+use chrono::Utc;
+use zoo_message_primitives::schemas::api_keys::{ApiKeyScope, API_KEY_PREFIX};
 use zoo_message_primitives::schemas::ws_types::WebSocketManagerError;
 use zoo_message_primitives::zoo_message::zoo_message_schemas::AuthenticatedWSMessage;
 use zoo_message_primitives::zoo_utils::zoo_logging::zoo_log;
@@ -123,8 +125,50 @@ fn check_bearer_token(api_key: &str, bearer_token: &str) -> Result<(), WebSocket
     }
 }
 
+// Scoped API keys can subscribe to updates if they can read jobs
+fn validate_scoped_api_key(api_key: &str, manager: &WebSocketManager) -> Result<(), WebSocketManagerError> {
+    let db = manager
+        .get_db()
+        .ok_or_else(|| WebSocketManagerError::AccessDenied("Database connection not available".to_string()))?;
+    let scoped_key = match db.get_api_key_by_key(api_key) {
+        Ok(Some(scoped_key)) if scoped_key.is_active(Utc::now()) => scoped_key,
+        Ok(_) => return Err(WebSocketManagerError::AccessDenied("Invalid bearer token".to_string())),
+        Err(e) => {
+            return Err(WebSocketManagerError::AccessDenied(format!(
+                "Error reading API key from database: {}",
+                e
+            )))
+        }
+    };
+    if !scoped_key.has_scope(ApiKeyScope::JobsRead) {
+        return Err(WebSocketManagerError::AccessDenied(format!(
+            "The API key needs the {} scope",
+            ApiKeyScope::JobsRead
+        )));
+    }
+
+    if let Err(e) = db.record_api_key_use(&scoped_key.id, "ws") {
+        zoo_log(
+            ZooLogOption::WsAPI,
+            ZooLogLevel::Error,
+            &format!("Failed to record the use of API key {}: {}", scoped_key.id, e),
+        );
+    }
+    zoo_log(
+        ZooLogOption::WsAPI,
+        ZooLogLevel::Info,
+        &format!("Bearer token authentication successful with API key {}", scoped_key.id),
+    );
+    Ok(())
+}
+
 // Function to validate bearer token against API key from env or database
 async fn validate_bearer_token(bearer_token: &str, manager: &WebSocketManager) -> Result<(), WebSocketManagerError> {
+    let token = bearer_token.strip_prefix("Bearer ").unwrap_or(bearer_token);
+    if token.starts_with(API_KEY_PREFIX) {
+        return validate_scoped_api_key(token, manager);
+    }
+
     // Get API key from environment variable or database
     let api_key = match env::var("API_V2_KEY") {
         Ok(api_key) => api_key,
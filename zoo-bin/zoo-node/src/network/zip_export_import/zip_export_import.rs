@@ -117,17 +117,16 @@ async fn calculate_zip_dependencies(
         // This tool might have dependendies, so let's check them.
         // Only Deno & Python tools have get_tools()
         for dependency in tool.get_tools() {
-            let tool_dependency =
-                match db.get_tool_by_key_and_version(&dependency.to_string_without_version(), dependency.version()) {
-                    Ok(tool) => tool,
-                    Err(err) => {
-                        return Err(APIError {
-                            code: StatusCode::BAD_REQUEST.as_u16(),
-                            error: "Bad Request".to_string(),
-                            message: format!("Failed to get tool dependency: {}", err),
-                        });
-                    }
-                };
+            let tool_dependency = match db.get_tool_by_router_key(&dependency) {
+                Ok(tool) => tool,
+                Err(err) => {
+                    return Err(APIError {
+                        code: StatusCode::BAD_REQUEST.as_u16(),
+                        error: "Bad Request".to_string(),
+                        message: format!("Failed to get tool dependency: {}", err),
+                    });
+                }
+            };
             Box::pin(calculate_zip_dependencies(
                 db.clone(),
                 zoo_name.clone(),
@@ -161,17 +160,16 @@ async fn calculate_zip_dependencies(
         tool_dependencies.insert(tool.tool_router_key().to_string_with_version(), tool);
 
         for tool in agent.tools {
-            let tool_dependency =
-                match db.get_tool_by_key_and_version(&tool.to_string_without_version(), tool.version()) {
-                    Ok(tool) => tool,
-                    Err(err) => {
-                        return Err(APIError {
-                            code: StatusCode::BAD_REQUEST.as_u16(),
-                            error: "Bad Request".to_string(),
-                            message: format!("Failed to get tool dependency: {}", err),
-                        });
-                    }
-                };
+            let tool_dependency = match db.get_tool_by_router_key(&tool) {
+                Ok(tool) => tool,
+                Err(err) => {
+                    return Err(APIError {
+                        code: StatusCode::BAD_REQUEST.as_u16(),
+                        error: "Bad Request".to_string(),
+                        message: format!("Failed to get tool dependency: {}", err),
+                    });
+                }
+            };
             Box::pin(calculate_zip_dependencies(
                 db.clone(),
                 zoo_name.clone(),
@@ -1000,6 +998,14 @@ pub async fn import_tool(
         tool.enable();
     }
 
+    if let Some(permissions) = tool.get_permissions() {
+        permissions.validate().map_err(|e| APIError {
+            code: StatusCode::BAD_REQUEST.as_u16(),
+            error: "Bad Request".to_string(),
+            message: e.to_string(),
+        })?;
+    }
+
     let tool_router_key = tool.tool_router_key().to_string_without_version();
     match tool.clone() {
         ZooTool::Deno(_, _) => {}
@@ -1027,35 +1033,31 @@ pub async fn import_tool(
         ZooTool::MCPServer(_, _) => {}
     }
 
-    // check if any version of the tool exists in the database
-    let db_tool = match db.get_tool_by_key(&tool.tool_router_key().to_string_without_version()) {
-        Ok(tool) => Some(tool),
-        Err(_) => None,
-    };
-
-    // if the tool exists in the database, check if the version is the same or newer
-    if let Some(db_tool) = db_tool.clone() {
-        let version_db = db_tool.version_number()?;
-        let version_zip = tool.version_number()?;
-        if version_db >= version_zip {
-            // No need to update
-            return Ok(json!({
-                "status": "success",
-                "message": "Tool already up-to-date",
-                "tool_key": tool.tool_router_key().to_string_without_version(),
-                "tool": tool.clone()
-            }));
-        }
+    // Versions are installed side by side, references without a version keep using the active one
+    let installed_versions = db.get_tool_versions(&tool_router_key).map_err(|e| APIError {
+        code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+        error: "Database Error".to_string(),
+        message: format!("Failed to get installed versions: {}", e),
+    })?;
+    let version_zip = tool.version_number()?;
+    if installed_versions.contains(&version_zip) {
+        // No need to update
+        return Ok(json!({
+            "status": "success",
+            "message": "Tool already up-to-date",
+            "tool_key": tool_router_key,
+            "tool": tool.clone()
+        }));
     }
 
-    // Save the tool to the database
-    let tool = match db_tool {
-        None => db.add_tool(tool).await.map_err(|e| APIError {
+    // Save the tool to the database. Adding a version to an installed tool keeps its configuration.
+    let tool = match installed_versions.is_empty() {
+        true => db.add_tool(tool).await.map_err(|e| APIError {
             code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
             error: "Database Error".to_string(),
             message: format!("Failed to save tool to database: {}", e),
         })?,
-        Some(_) => db.upgrade_tool(tool).await.map_err(|e| APIError {
+        false => db.upgrade_tool(tool).await.map_err(|e| APIError {
             code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
             error: "Database Error".to_string(),
             message: format!("Failed to upgrade tool: {}", e),
@@ -1144,6 +1146,7 @@ pub async fn import_agent(
     if install {
         match db.add_agent(agent.clone(), &agent.full_identity_name) {
             Ok(_) => {
+                db.embed_agent_memories(&agent.agent_id.to_lowercase()).await;
                 let agent_tool_wrapper = AgentToolWrapper::new(
                     agent.agent_id.clone(),
                     agent.name.clone(),
@@ -1421,6 +1424,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_b_name = "Tool B";
@@ -1456,6 +1460,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_c_name = "Tool C";
@@ -1491,6 +1496,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
         tool_a
             .tools
@@ -1606,6 +1612,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_b_name = "Tool B";
@@ -1641,6 +1648,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
         tool_a
             .tools
@@ -1721,6 +1729,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_b_name = "Tool B";
@@ -1756,6 +1765,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         // Add tools to database
@@ -4,6 +4,10 @@ use crate::utils::args::parse_args;
 use crate::utils::cli::cli_handle_create_message;
 use crate::utils::environment::{fetch_llm_provider_env, fetch_node_environment};
 use crate::utils::keys::generate_or_load_keys;
+use crate::utils::node_config::{
+    apply_logging_config, config_file_path, effective_node_config, format_config_issues, init_node_config,
+    run_config_command,
+};
 use async_channel::{bounded, Receiver, Sender};
 use ed25519_dalek::VerifyingKey;
 use zoo_embedding::embedding_generator::RemoteEmbeddingGenerator;
@@ -16,13 +20,14 @@ use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLo
 use zoo_message_primitives::zoo_utils::signatures::{
     clone_signature_secret_key, hash_signature_public_key, signature_public_key_to_string, signature_secret_key_to_string
 };
+use zoo_sqlite::SqliteManager;
 use std::collections::HashMap;
 use std::error::Error as StdError;
 use std::fmt;
 use std::net::TcpListener;
 use std::path::Path;
 use std::sync::{Arc, Weak};
-use std::{env, fs};
+use std::fs;
 
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
@@ -68,6 +73,26 @@ pub async fn initialize_node() -> Result<
 
     // Fetch Env vars/args
     let args = parse_args();
+    init_node_config(args.config.as_deref());
+    if args.check_config || args.print_config {
+        std::process::exit(run_config_command(args.print_config));
+    }
+
+    let (node_config, config_issues) = effective_node_config();
+    if !config_issues.is_empty() {
+        return Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Invalid node configuration:\n{}", format_config_issues(&config_issues)),
+        )));
+    }
+    apply_logging_config(&node_config.logging);
+    if let Some(path) = config_file_path() {
+        zoo_log(
+            ZooLogOption::Node,
+            ZooLogLevel::Info,
+            &format!("Using config file {}", path.display()),
+        );
+    }
     let node_env = fetch_node_environment();
 
     // Check if required ports are available
@@ -119,13 +144,43 @@ pub async fn initialize_node() -> Result<
     // Storage db filesystem
     let main_db_path = get_main_db_path(main_db, &node_keys.identity_public_key, node_storage_path.clone());
 
+    if args.migrate_dry_run {
+        match SqliteManager::migration_dry_run(&main_db_path) {
+            Ok(report) => {
+                println!("{}", report);
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("Failed to check the database migrations of {}: {}", main_db_path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(target_version) = args.migrate_rollback {
+        match SqliteManager::rollback_database_migrations(&main_db_path, target_version) {
+            Ok(rolled_back) if rolled_back.is_empty() => {
+                println!("{} has no migration above version {}.", main_db_path, target_version);
+                std::process::exit(0);
+            }
+            Ok(rolled_back) => {
+                println!("Rolled back migration(s) {:?} of {}.", rolled_back, main_db_path);
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("Failed to roll back the database migrations of {}: {}", main_db_path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     // Acquire the Node's keys.
     // TODO: Should check with on and then it's with onchain data for matching with the keys provided
     let secrets = parse_secrets_file(&secrets_file_path);
     let global_identity_name = secrets
         .get("GLOBAL_IDENTITY_NAME")
         .cloned()
-        .unwrap_or_else(|| env::var("GLOBAL_IDENTITY_NAME").unwrap_or("@@localhost.sep-zoo".to_string()));
+        .unwrap_or_else(|| node_env.global_identity_name.clone());
 
     let global_identity_name = if global_identity_name.is_empty() {
         "@@localhost.sep-zoo".to_string()
@@ -134,7 +189,12 @@ pub async fn initialize_node() -> Result<
     };
 
     // Initialization, creating Tokio runtime and fetching needed startup data
-    let initial_llm_providers = fetch_llm_provider_env(global_identity_name.clone());
+    let initial_llm_providers = fetch_llm_provider_env(global_identity_name.clone()).map_err(|issue| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Invalid node configuration: {}", issue),
+        )
+    })?;
     let identity_secret_key_string =
         signature_secret_key_to_string(clone_signature_secret_key(&node_keys.identity_secret_key));
     let identity_public_key_string = signature_public_key_to_string(node_keys.identity_public_key);
@@ -236,7 +296,7 @@ pub async fn initialize_node() -> Result<
         node_env.supported_embedding_models.clone(),
         node_env.api_v2_key.clone(),
     )
-    .await;
+    .await?;
 
     // Put the Node in an Arc<Mutex<Node>> for use in a task
     let start_node = Arc::clone(&node);
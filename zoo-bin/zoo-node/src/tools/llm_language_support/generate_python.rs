@@ -346,6 +346,7 @@ mod tests {
             config: None,
             usage_type: None,
             tool_offering: None,
+            permissions: None,
         };
 
         // Create a test tool result
@@ -397,6 +398,7 @@ mod tests {
             config: None,
             usage_type: None,
             tool_offering: None,
+            permissions: None,
         };
 
         // Create SQL tables and queries
@@ -440,6 +442,7 @@ mod tests {
             config: None,
             usage_type: None,
             tool_offering: None,
+            permissions: None,
         };
 
         let tool_result = ToolResult::new(
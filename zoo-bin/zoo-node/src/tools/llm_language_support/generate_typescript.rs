@@ -337,6 +337,7 @@ mod tests {
             config: None,
             usage_type: None,
             tool_offering: None,
+            permissions: None,
         };
 
         // Create a test tool result
@@ -388,6 +389,7 @@ mod tests {
             config: None,
             usage_type: None,
             tool_offering: None,
+            permissions: None,
         };
 
         // Create SQL tables and queries
@@ -431,6 +433,7 @@ mod tests {
             config: None,
             usage_type: None,
             tool_offering: None,
+            permissions: None,
         };
 
         let tool_result = ToolResult::new(
@@ -516,6 +519,7 @@ mod tests {
             config: None,
             usage_type: None,
             tool_offering: None,
+            permissions: None,
         };
 
         let tool_result = ToolResult::new(
@@ -571,6 +575,7 @@ mod tests {
             config: None,
             usage_type: None,
             tool_offering: None,
+            permissions: None,
         };
 
         let tool_result = ToolResult::new(
@@ -618,6 +623,7 @@ mod tests {
                 config: None,
                 usage_type: None,
                 tool_offering: None,
+                permissions: None,
             };
 
             let function_name = create_function_name_set(&tool);
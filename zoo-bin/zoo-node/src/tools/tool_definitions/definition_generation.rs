@@ -26,6 +26,8 @@ pub fn get_rust_tools() -> Vec<ZooToolHeader> {
     custom_tools.push(tool_implementation::native_tools::sql_processor::SQLProcessorTool::new().tool);
     custom_tools.push(tool_implementation::native_tools::tool_knowledge::KnowledgeTool::new().tool);
     custom_tools.push(tool_implementation::native_tools::config_setup::ConfigSetupTool::new().tool);
+    custom_tools.push(tool_implementation::native_tools::agent_memory::AgentRememberTool::new().tool);
+    custom_tools.push(tool_implementation::native_tools::agent_memory::AgentRecallTool::new().tool);
     custom_tools
 }
 
@@ -182,3 +182,70 @@ pub async fn execute_agent_tool(
         Err(ToolError::ExecutionError("No agent response received".to_string()))
     }
 }
+
+/// The arguments `execute_agent_tool` reads.
+const AGENT_TOOL_ARGUMENTS: [&str; 3] = ["agent_id", "prompt", "session_id"];
+
+/// Static checks for an agent call from the playground: the session it continues, or the agent
+/// it starts a new one with, must exist, and so must the tools the agent uses.
+pub fn check_agent_tool(db: &SqliteManager, arguments: &Map<String, Value>) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for key in arguments.keys() {
+        if !AGENT_TOOL_ARGUMENTS.contains(&key.as_str()) {
+            warnings.push(format!(
+                "Unknown parameter '{}', expected one of: {}",
+                key,
+                AGENT_TOOL_ARGUMENTS.join(", ")
+            ));
+        }
+    }
+
+    match arguments.get("prompt") {
+        Some(Value::String(prompt)) if !prompt.trim().is_empty() => {}
+        Some(Value::String(_)) | None => warnings.push("Parameter 'prompt' is empty".to_string()),
+        Some(_) => warnings.push("Parameter 'prompt' must be a string".to_string()),
+    }
+
+    match arguments.get("session_id") {
+        Some(Value::String(session_id)) => {
+            if InboxName::get_job_inbox_name_from_params(session_id.clone()).is_err() {
+                warnings.push(format!("Session '{}' is not a valid job id", session_id));
+            } else if db.get_job(session_id).is_err() {
+                warnings.push(format!("Session '{}' not found", session_id));
+            }
+            return warnings;
+        }
+        Some(_) => {
+            warnings.push("Parameter 'session_id' must be a string".to_string());
+            return warnings;
+        }
+        None => {}
+    }
+
+    let agent_id = match arguments.get("agent_id") {
+        Some(Value::String(agent_id)) => agent_id,
+        Some(_) => {
+            warnings.push("Parameter 'agent_id' must be a string".to_string());
+            return warnings;
+        }
+        None => {
+            warnings.push("Parameter 'agent_id' is required to start a new session".to_string());
+            return warnings;
+        }
+    };
+    match db.get_agent(agent_id) {
+        Ok(Some(agent)) => {
+            for tool in agent.tools {
+                if db.get_tool_by_router_key(&tool).is_err() {
+                    warnings.push(format!(
+                        "Agent '{}' uses tool '{}', which is not installed",
+                        agent_id,
+                        tool.to_string_with_version()
+                    ));
+                }
+            }
+        }
+        _ => warnings.push(format!("Agent '{}' not found", agent_id)),
+    }
+    warnings
+}
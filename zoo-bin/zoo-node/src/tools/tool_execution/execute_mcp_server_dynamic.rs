@@ -1,3 +1,4 @@
+use super::execution_header_generator::tool_parameter_errors;
 use serde_json::{Map, Value};
 use zoo_sqlite::SqliteManager;
 use zoo_tools_primitives::tools::error::ToolError;
@@ -42,3 +43,31 @@ pub async fn execute_mcp_server_dynamic(
         _ => return Err(ToolError::ExecutionError("Tool is not an MCP server".to_string())),
     }
 }
+
+/// Static checks for an MCP server tool call from the playground: the tool and its server must
+/// exist and be enabled, and the arguments must match the tool's input schema.
+pub fn check_mcp_server_dynamic(db: &SqliteManager, tool_id: &str, arguments: &Map<String, Value>) -> Vec<String> {
+    let tool = match db.get_tool_by_key(tool_id) {
+        Ok(ZooTool::MCPServer(tool, _)) => tool,
+        Ok(_) => return vec![format!("Tool '{}' is not an MCP server tool", tool_id)],
+        Err(_) => return vec![format!("Tool '{}' not found", tool_id)],
+    };
+
+    let mut warnings = Vec::new();
+    match tool.mcp_server_ref.parse::<i64>().map(|id| db.get_mcp_server(id)) {
+        Ok(Ok(Some(mcp_server))) if !mcp_server.is_enabled => {
+            warnings.push(format!("MCP server '{}' is disabled", mcp_server.name))
+        }
+        Ok(Ok(Some(_))) => {}
+        _ => warnings.push(format!("MCP server '{}' of tool '{}' not found", tool.mcp_server_ref, tool_id)),
+    }
+    if !tool.activated {
+        warnings.push(format!("Tool '{}' is not activated", tool_id));
+    }
+
+    warnings.extend(tool_parameter_errors(&tool.input_args, arguments));
+    for key in arguments.keys().filter(|key| !tool.input_args.properties.contains_key(*key)) {
+        warnings.push(format!("Unknown parameter '{}' for tool '{}'", key, tool.mcp_server_tool));
+    }
+    warnings
+}
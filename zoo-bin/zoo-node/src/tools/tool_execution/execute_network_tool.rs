@@ -0,0 +1,184 @@
+use crate::network::agent_payments_manager::my_agent_offerings_manager::MyAgentOfferingsManager;
+use crate::tools::tool_execution::execution_header_generator::check_tool;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use zoo_message_primitives::schemas::invoices::{Invoice, InvoiceStatusEnum};
+use zoo_message_primitives::schemas::zoo_name::ZooName;
+use zoo_message_primitives::schemas::zoo_tool_offering::{ToolPrice, UsageTypeInquiry};
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
+use zoo_sqlite::SqliteManager;
+use zoo_tools_primitives::tools::error::ToolError;
+use zoo_tools_primitives::tools::network_tool::NetworkTool;
+
+/// How long the provider has to send the invoice, and then the result once it's paid.
+const INVOICE_TIMEOUT_SECS: u64 = 300;
+const INVOICE_POLL_INTERVAL_MS: u64 = 100;
+
+/// Runs a network tool outside of a job: requests an invoice from the provider, pays it with the
+/// parameters as the tool data and waits for the provider to process it. There's no payment widget
+/// to answer, so the invoice is only paid if the caller set a `max_price` that covers it.
+pub async fn execute_network_tool(
+    db: Arc<SqliteManager>,
+    my_agent_payments_manager: Arc<Mutex<MyAgentOfferingsManager>>,
+    network_tool: NetworkTool,
+    parameters: Map<String, Value>,
+    node_name: ZooName,
+    max_price: Option<u64>,
+) -> Result<Value, ToolError> {
+    check_tool(
+        network_tool.tool_router_key.clone(),
+        network_tool.config.clone(),
+        parameters.clone(),
+        network_tool.input_args.clone(),
+        &None,
+    )?;
+
+    let invoice_request = my_agent_payments_manager
+        .lock()
+        .await
+        .network_request_invoice(network_tool.clone(), UsageTypeInquiry::PerUse, None)
+        .await
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to request invoice: {}", e)))?;
+
+    let invoice = wait_for_invoice(&db, &invoice_request.unique_id, |invoice| {
+        Ok(invoice.status == InvoiceStatusEnum::Pending)
+    })
+    .await
+    .map_err(|e| ToolError::ExecutionError(format!("Failed to get the invoice of {}: {}", network_tool.name, e)))?;
+    check_invoice_price(
+        invoice.zoo_offering.get_price_for_usage(&invoice.usage_type_inquiry),
+        max_price,
+    )?;
+
+    zoo_log(
+        ZooLogOption::Node,
+        ZooLogLevel::Info,
+        &format!("Paying invoice {} to run {}", invoice.invoice_id, network_tool.name),
+    );
+    my_agent_payments_manager
+        .lock()
+        .await
+        .pay_invoice_and_send_receipt(invoice.invoice_id.clone(), Value::Object(parameters), node_name, None)
+        .await
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to pay invoice: {}", e)))?;
+
+    let invoice = wait_for_invoice(&db, &invoice_request.unique_id, |invoice| match invoice.status {
+        InvoiceStatusEnum::Processed => Ok(true),
+        InvoiceStatusEnum::Rejected => Err("Invoice rejected".to_string()),
+        _ => Ok(false),
+    })
+    .await
+    .map_err(|e| ToolError::ExecutionError(format!("Failed to get the result of {}: {}", network_tool.name, e)))?;
+
+    Ok(network_tool_response(invoice.result_str.as_deref().unwrap_or_default()))
+}
+
+/// Fails unless the tool is free or what the invoice charges is within `max_price`. Amounts are in
+/// the atomic units of the payment asset, like the `maxAmountRequired` of the offering.
+fn check_invoice_price(price: Option<&ToolPrice>, max_price: Option<u64>) -> Result<(), ToolError> {
+    let payment = match price {
+        Some(ToolPrice::Free) => return Ok(()),
+        Some(ToolPrice::Payment(payments)) => payments
+            .first()
+            .ok_or_else(|| ToolError::ExecutionError("The invoice has no payment requirements".to_string()))?,
+        _ => {
+            return Err(ToolError::ExecutionError(
+                "The invoice price isn't supported".to_string(),
+            ))
+        }
+    };
+    let amount = payment
+        .max_amount_required
+        .parse::<u128>()
+        .map_err(|e| ToolError::ExecutionError(format!("Invalid invoice amount: {}", e)))?;
+    match max_price {
+        Some(max_price) if amount <= max_price as u128 => Ok(()),
+        Some(max_price) => Err(ToolError::ExecutionError(format!(
+            "The invoice charges {} of {}, over the max_price of {}",
+            amount, payment.asset, max_price
+        ))),
+        None => Err(ToolError::ExecutionError(format!(
+            "The invoice charges {} of {}, set max_price to pay it",
+            amount, payment.asset
+        ))),
+    }
+}
+
+/// Polls the invoice until `is_ready` accepts it. Fails with the provider's message if the
+/// invoice request failed on the network, or after `INVOICE_TIMEOUT_SECS`.
+async fn wait_for_invoice(
+    db: &SqliteManager,
+    unique_id: &str,
+    is_ready: impl Fn(&Invoice) -> Result<bool, String>,
+) -> Result<Invoice, String> {
+    let start_time = Instant::now();
+    loop {
+        if start_time.elapsed() > Duration::from_secs(INVOICE_TIMEOUT_SECS) {
+            return Err("Timeout while waiting for the provider".to_string());
+        }
+
+        match db.get_invoice(unique_id) {
+            Ok(invoice) => {
+                if is_ready(&invoice)? {
+                    return Ok(invoice);
+                }
+            }
+            Err(_) => {
+                if let Ok(network_error) = db.get_invoice_network_error(unique_id) {
+                    return Err(network_error
+                        .user_error_message
+                        .unwrap_or_else(|| "Invoice network error encountered".to_string()));
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(INVOICE_POLL_INTERVAL_MS)).await;
+    }
+}
+
+/// Providers reply with `{"data": ...}`, the data is the result. Anything else is returned as is.
+fn network_tool_response(result_str: &str) -> Value {
+    match serde_json::from_str::<Value>(result_str) {
+        Ok(Value::Object(mut parsed)) if parsed.contains_key("data") => parsed.remove("data").unwrap_or_default(),
+        Ok(parsed) => parsed,
+        Err(_) => Value::String(result_str.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use zoo_message_primitives::schemas::x402_types::{Network, PaymentRequirements};
+
+    #[test]
+    fn test_network_tool_response() {
+        assert_eq!(
+            network_tool_response(r#"{"data": {"message": "hello"}}"#),
+            json!({"message": "hello"})
+        );
+        assert_eq!(
+            network_tool_response(r#"{"message": "hello"}"#),
+            json!({"message": "hello"})
+        );
+        assert_eq!(network_tool_response("plain text"), json!("plain text"));
+        assert_eq!(network_tool_response(""), json!(""));
+    }
+
+    #[test]
+    fn test_check_invoice_price() {
+        let price = ToolPrice::Payment(vec![PaymentRequirements::new(
+            Network::BaseSepolia,
+            "1000".to_string(),
+            "0x036CbD53842c5426634e7929541eC2318f3dCF7e".to_string(),
+            "0x036CbD53842c5426634e7929541eC2318f3dCF7e".to_string(),
+            "https://zoo.ngo".to_string(),
+        )]);
+        assert!(check_invoice_price(Some(&ToolPrice::Free), None).is_ok());
+        assert!(check_invoice_price(Some(&price), Some(1000)).is_ok());
+        assert!(check_invoice_price(Some(&price), Some(999)).is_err());
+        assert!(check_invoice_price(Some(&price), None).is_err());
+        assert!(check_invoice_price(None, Some(1000)).is_err());
+    }
+}
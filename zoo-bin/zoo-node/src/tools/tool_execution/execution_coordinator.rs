@@ -1,26 +1,33 @@
 use crate::llm_provider::job_manager::JobManager;
 use crate::managers::IdentityManager;
+use crate::network::agent_payments_manager::my_agent_offerings_manager::MyAgentOfferingsManager;
 use crate::tools::tool_definitions::definition_generation::generate_tool_definitions;
-use crate::tools::tool_execution::execute_agent_dynamic::execute_agent_tool;
-use crate::tools::tool_execution::execute_mcp_server_dynamic::execute_mcp_server_dynamic;
+use crate::tools::tool_execution::execute_agent_dynamic::{check_agent_tool, execute_agent_tool};
+use crate::tools::tool_execution::execute_mcp_server_dynamic::{check_mcp_server_dynamic, execute_mcp_server_dynamic};
+use crate::tools::tool_execution::execute_network_tool::execute_network_tool;
 use crate::tools::tool_execution::execution_custom::try_to_execute_rust_tool;
 use crate::tools::tool_execution::execution_deno_dynamic::{check_deno_tool, execute_deno_tool};
 use crate::tools::tool_execution::execution_header_generator::{check_tool, generate_execution_environment};
-use crate::tools::tool_execution::execution_python_dynamic::execute_python_tool;
+use crate::tools::tool_execution::execution_python_dynamic::{check_python_tool, execute_python_tool};
+use crate::tools::tool_implementation::native_tools::agent_memory::is_agent_memory_tool;
+use crate::tools::tool_execution::oauth_refresh::{
+    is_unauthorized_result, refresh_oauth_token, refresh_tool_oauth_tokens, token_needs_refresh, OAuthRefreshError,
+};
 use crate::utils::environment::fetch_node_environment;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::Utc;
 use ed25519_dalek::SigningKey;
 use regex::Regex;
-use reqwest::Client;
 use serde_json::json;
 use serde_json::{Map, Value};
 use sha2::{Digest, Sha256};
 use zoo_message_primitives::schemas::llm_providers::agent::Agent;
+use zoo_message_primitives::schemas::secrets::secret_reference_name;
 use zoo_message_primitives::schemas::zoo_name::ZooName;
 use zoo_message_primitives::schemas::zoo_tools::CodeLanguage;
 use zoo_message_primitives::schemas::zoo_tools::DynamicToolType;
 use zoo_message_primitives::schemas::tool_router_key::ToolRouterKey;
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
 use zoo_sqlite::oauth_manager::OAuthToken;
 use zoo_sqlite::SqliteManager;
 use zoo_tools_primitives::tools::error::ToolError;
@@ -49,97 +56,33 @@ pub async fn handle_oauth(
                 .ok()
                 .unwrap_or(None);
 
-            let (state_uuid, pkce_uuid) = if let Some(token) = existing_token.clone() {
-                if let Some(_) = token.access_token.clone() {
-                    // push to access_token
-
-                    // check if token expiored
-                    let mut u_token = token.clone();
-                    if let Some(refresh_token_expires_at) = token.refresh_token_expires_at {
-                        let now = Utc::now();
-                        let five_minutes = chrono::Duration::minutes(5);
-
-                        if now + five_minutes > refresh_token_expires_at {
-                            // Need to refresh the token
-                            if let Some(refresh_token) = &token.refresh_token {
-                                if let Some(token_url) = &token.token_url {
-                                    let client = Client::new();
-                                    let request_body = serde_json::json!({
-                                        "refresh_token": refresh_token,
-                                        "grant_type": "refresh_token",
-                                        "client_id": token.client_id.as_deref().unwrap_or_default(),
-                                    });
-                                    println!("[OAuth] Refresh request {}, {}", token_url, request_body.to_string());
-                                    let response = client
-                                        .post(token_url)
-                                        .header("Accept", "application/json")
-                                        .header("Content-Type", "application/x-www-form-urlencoded")
-                                        .form(&request_body)
-                                        .send()
-                                        .await;
-
-                                    match response {
-                                        Ok(response) => {
-                                            if !response.status().is_success() {
-                                                return Err(ToolError::ExecutionError(format!(
-                                                    "Failed to refresh OAuth token: {}",
-                                                    response.status()
-                                                )));
-                                            }
-
-                                            if let Ok(response_json) = response.json::<serde_json::Value>().await {
-                                                println!("[OAuth] Response {}", response_json.to_string());
-                                                if let Some(error) = response_json["error"].as_str() {
-                                                    if !error.is_empty() {
-                                                        return Err(ToolError::ExecutionError(format!(
-                                                            "Failed to refresh OAuth token: {}",
-                                                            response_json
-                                                        )));
-                                                    }
-                                                }
-                                                // Update token with new values
-                                                let mut updated_token = token.clone();
-                                                if let Some(access_token) = response_json["access_token"].as_str() {
-                                                    updated_token.access_token = Some(access_token.to_string());
-                                                }
-                                                if let Some(expires_in) = response_json["expires_in"].as_i64() {
-                                                    updated_token.access_token_expires_at =
-                                                        Some(Utc::now() + chrono::Duration::seconds(expires_in));
-                                                }
-                                                if let Some(new_refresh_token) = response_json["refresh_token"].as_str()
-                                                {
-                                                    updated_token.refresh_token = Some(new_refresh_token.to_string());
-                                                    if let Some(expires_in) = response_json["expires_in"].as_i64() {
-                                                        updated_token.refresh_token_expires_at =
-                                                            Some(Utc::now() + chrono::Duration::seconds(expires_in));
-                                                    }
-                                                }
-                                                if let Some(refresh_token) = response_json["refresh_token"].as_str() {
-                                                    updated_token.refresh_token = Some(refresh_token.to_string());
-                                                }
-
-                                                // Update token in database
-                                                let _ = db.update_oauth_token(&updated_token);
-                                                u_token = updated_token.clone();
-                                            }
-                                        }
-
-                                        Err(e) => {
-                                            println!("[OAuth] Response error {}", e.to_string());
-                                        }
-                                    }
-                                }
-                            }
+            let (state_uuid, pkce_uuid) = if let Some(mut token) = existing_token.clone() {
+                // Renew the access token if it expires soon
+                if token_needs_refresh(&token, Utc::now()) {
+                    match refresh_oauth_token(db, &token).await {
+                        Ok(refreshed_token) => token = refreshed_token,
+                        // The refresh token was cleared, the user has to log in again
+                        Err(OAuthRefreshError::Rejected(e)) => {
+                            zoo_log(
+                                ZooLogOption::Node,
+                                ZooLogLevel::Error,
+                                &format!("OAuth refresh rejected for {}: {}", token.connection_name, e),
+                            );
+                            token.access_token = None;
                         }
+                        // Keep the current token, it's retried on the next execution
+                        Err(e) => zoo_log(ZooLogOption::Node, ZooLogLevel::Error, &format!("OAuth: {}", e)),
                     }
+                }
 
+                if let Some(access_token) = token.access_token.clone() {
                     let mut oauth = HashMap::new();
                     // TODO: Add more fields (?)
-                    oauth.insert("name".to_string(), u_token.connection_name.clone());
-                    oauth.insert("accessToken".to_string(), u_token.access_token.unwrap_or_default());
+                    oauth.insert("name".to_string(), token.connection_name.clone());
+                    oauth.insert("accessToken".to_string(), access_token);
                     oauth.insert(
                         "expiresAt".to_string(),
-                        u_token.expires_at.unwrap_or_default().to_string(),
+                        token.expires_at.unwrap_or_default().to_string(),
                     );
                     access_tokens.push(oauth);
                     continue;
@@ -294,6 +237,29 @@ pub fn override_tool_config(tool_router_key: String, agent: Agent, extra_config:
     final_config
 }
 
+/// Replaces the config values that reference a named secret (`secret://name`) with the secret.
+/// Only done right before running the tool, so the secret never ends up in the stored config.
+pub fn resolve_secret_references(db: &SqliteManager, configs: &mut [ToolConfig]) -> Result<(), ToolError> {
+    for config in configs.iter_mut() {
+        let ToolConfig::BasicConfig(config) = config;
+        let name = match config.key_value.as_ref().and_then(|value| value.as_str()) {
+            Some(value) => match secret_reference_name(value) {
+                Some(name) => name.to_string(),
+                None => continue,
+            },
+            None => continue,
+        };
+        let secret = db
+            .get_secret_value(&name)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to get secret {}: {}", name, e)))?
+            .ok_or_else(|| {
+                ToolError::MissingConfigError(format!("Secret {} referenced by {} not found", name, config.key_name))
+            })?;
+        config.key_value = Some(Value::String(secret));
+    }
+    Ok(())
+}
+
 pub async fn execute_tool_cmd(
     bearer: String,
     node_name: ZooName,
@@ -307,13 +273,15 @@ pub async fn execute_tool_cmd(
     extra_config: Vec<ToolConfig>,
     identity_manager: Arc<Mutex<IdentityManager>>,
     job_manager: Arc<Mutex<JobManager>>,
+    my_agent_payments_manager: Arc<Mutex<MyAgentOfferingsManager>>,
     encryption_secret_key: EncryptionStaticKey,
     encryption_public_key: EncryptionPublicKey,
     signing_secret_key: SigningKey,
     mounts: Option<Vec<String>>,
+    max_price: Option<u64>,
 ) -> Result<Value, ToolError> {
     println!("[execute_tool] with tool_router_key: {}", tool_router_key);
-    let tool = db
+    let mut tool = db
         .get_tool_by_key(&tool_router_key)
         .map_err(|e| ToolError::ExecutionError(format!("Failed to get tool: {}", e)))?;
 
@@ -325,6 +293,11 @@ pub async fn execute_tool_cmd(
         }
     }
 
+    if let Some(config) = tool.config_mut() {
+        resolve_secret_references(&db, config)?;
+    }
+    resolve_secret_references(&db, &mut extra_config)?;
+
     match tool {
         ZooTool::MCPServer(mcp_server_tool, _) => {
             let mcp_server_ref = mcp_server_tool
@@ -345,6 +318,11 @@ pub async fn execute_tool_cmd(
             }
         }
         ZooTool::Rust(_, _) => {
+            if is_agent_memory_tool(&tool_router_key) {
+                return Err(ToolError::ExecutionError(
+                    "The agent memory tools can only be used by an agent in a job".to_string(),
+                ));
+            }
             try_to_execute_rust_tool(
                 &tool_router_key,
                 parameters,
@@ -386,7 +364,7 @@ pub async fn execute_tool_cmd(
             .await
         }
         ZooTool::Python(python_tool, _) => {
-            let env = generate_execution_environment(
+            let mut env = generate_execution_environment(
                 db.clone(),
                 llm_provider.clone(),
                 app_id.clone(),
@@ -422,30 +400,53 @@ pub async fn execute_tool_cmd(
                 })
                 .collect();
 
-            let support_files = generate_tool_definitions(tools, CodeLanguage::Python, db, false)
+            let support_files = generate_tool_definitions(tools, CodeLanguage::Python, db.clone(), false)
                 .await
                 .map_err(|_| ToolError::ExecutionError("Failed to generate tool definitions".to_string()))?;
-            python_tool
-                .run(
-                    env,
-                    node_env.api_listen_address.ip().to_string(),
-                    node_env.api_listen_address.port(),
-                    support_files,
-                    parameters,
-                    extra_config,
-                    node_storage_path,
-                    app_id.clone(),
-                    tool_id.clone(),
-                    node_name,
-                    true,
-                    Some(tool_router_key),
-                    mounts,
-                )
-                .await
-                .map(|result| json!(result.data))
+            let mut oauth_retried = false;
+            loop {
+                let result = python_tool
+                    .run(
+                        env,
+                        node_env.api_listen_address.ip().to_string(),
+                        node_env.api_listen_address.port(),
+                        support_files.clone(),
+                        parameters.clone(),
+                        extra_config.clone(),
+                        node_storage_path.clone(),
+                        app_id.clone(),
+                        tool_id.clone(),
+                        node_name.clone(),
+                        true,
+                        Some(tool_router_key.clone()),
+                        mounts.clone(),
+                    )
+                    .await;
+                match result {
+                    // The provider didn't accept the access token: refresh it and run the tool once more
+                    _ if !oauth_retried
+                        && is_unauthorized_result(&result)
+                        && refresh_tool_oauth_tokens(&db, &tool_router_key, &python_tool.oauth).await =>
+                    {
+                        oauth_retried = true;
+                        env = generate_execution_environment(
+                            db.clone(),
+                            llm_provider.clone(),
+                            app_id.clone(),
+                            tool_id.clone(),
+                            agent_id.clone(),
+                            tool_router_key.clone(),
+                            "".to_string(), // TODO Pass data from the API
+                            &python_tool.oauth,
+                        )
+                        .await?;
+                    }
+                    result => break result.map(|result| json!(result.data)),
+                }
+            }
         }
         ZooTool::Deno(deno_tool, _) => {
-            let env = generate_execution_environment(
+            let mut env = generate_execution_environment(
                 db.clone(),
                 llm_provider.clone(),
                 app_id.clone(),
@@ -480,28 +481,65 @@ pub async fn execute_tool_cmd(
                 })
                 .collect();
 
-            let support_files = generate_tool_definitions(tools, CodeLanguage::Typescript, db, false)
+            let support_files = generate_tool_definitions(tools, CodeLanguage::Typescript, db.clone(), false)
                 .await
                 .map_err(|_| ToolError::ExecutionError("Failed to generate tool definitions".to_string()))?;
-            deno_tool
-                .run(
-                    env,
-                    node_env.api_listen_address.ip().to_string(),
-                    node_env.api_listen_address.port(),
-                    support_files,
-                    parameters,
-                    extra_config,
-                    node_storage_path,
-                    app_id.clone(),
-                    tool_id.clone(),
-                    node_name,
-                    true,
-                    Some(tool_router_key),
-                    mounts,
-                )
-                .await
-                .map(|result| json!(result.data))
-                .map_err(|e| ToolError::ExecutionError(e.to_string()))
+            let mut oauth_retried = false;
+            loop {
+                let result = deno_tool
+                    .run(
+                        env,
+                        node_env.api_listen_address.ip().to_string(),
+                        node_env.api_listen_address.port(),
+                        support_files.clone(),
+                        parameters.clone(),
+                        extra_config.clone(),
+                        node_storage_path.clone(),
+                        app_id.clone(),
+                        tool_id.clone(),
+                        node_name.clone(),
+                        true,
+                        Some(tool_router_key.clone()),
+                        mounts.clone(),
+                    )
+                    .await;
+                match result {
+                    // The provider didn't accept the access token: refresh it and run the tool once more
+                    _ if !oauth_retried
+                        && is_unauthorized_result(&result)
+                        && refresh_tool_oauth_tokens(&db, &tool_router_key, &deno_tool.oauth).await =>
+                    {
+                        oauth_retried = true;
+                        env = generate_execution_environment(
+                            db.clone(),
+                            llm_provider.clone(),
+                            app_id.clone(),
+                            tool_id.clone(),
+                            agent_id.clone(),
+                            tool_router_key.clone(),
+                            "".to_string(), // TODO Pass data from the API
+                            &deno_tool.oauth,
+                        )
+                        .await?;
+                    }
+                    result => {
+                        break result
+                            .map(|result| json!(result.data))
+                            .map_err(|e| ToolError::ExecutionError(e.to_string()))
+                    }
+                }
+            }
+        }
+        ZooTool::Network(network_tool, _) => {
+            execute_network_tool(
+                db,
+                my_agent_payments_manager,
+                network_tool,
+                parameters,
+                node_name,
+                max_price,
+            )
+            .await
         }
         _ => Err(ToolError::ExecutionError(format!("Unsupported tool type: {:?}", tool))),
     }
@@ -519,6 +557,7 @@ pub async fn execute_mcp_tool_cmd(
     extra_config: Vec<ToolConfig>,
     identity_manager: Arc<Mutex<IdentityManager>>,
     job_manager: Arc<Mutex<JobManager>>,
+    my_agent_payments_manager: Arc<Mutex<MyAgentOfferingsManager>>,
     encryption_secret_key: EncryptionStaticKey,
     encryption_public_key: EncryptionPublicKey,
     signing_secret_key: SigningKey,
@@ -588,10 +627,13 @@ pub async fn execute_mcp_tool_cmd(
         extra_config,
         identity_manager,
         job_manager,
+        my_agent_payments_manager,
         encryption_secret_key,
         encryption_public_key,
         signing_secret_key,
         mounts,
+        // MCP clients can't set a price, so they don't run paid network tools
+        None,
     )
     .await
 }
@@ -620,6 +662,8 @@ pub async fn execute_code(
     signing_secret_key_clone: SigningKey,
 ) -> Result<Value, ToolError> {
     eprintln!("[execute_code] tool_type: {}", tool_type);
+    let mut extra_config = extra_config;
+    resolve_secret_references(&db, &mut extra_config)?;
     // Route based on the prefix
     let tools: Vec<ToolRouterKey> = db
         .clone()
@@ -735,9 +779,32 @@ pub async fn check_code(
             // Since `check_deno_tool` is synchronous, run it in a blocking task
             check_deno_tool(tool_id, app_id, support_files, code_extracted).await
         }
-        DynamicToolType::PythonDynamic => Err(ToolError::ExecutionError("NYI Python".to_string())),
-        DynamicToolType::AgentDynamic => Err(ToolError::ExecutionError("NYI Agent".to_string())),
-        DynamicToolType::McpServerDynamic => Err(ToolError::ExecutionError("NYI MCP".to_string())),
+        DynamicToolType::PythonDynamic => {
+            let support_files = generate_tool_definitions(tools, CodeLanguage::Python, sqlite_manager, false)
+                .await
+                .map_err(|_| ToolError::ExecutionError("Failed to generate tool definitions".to_string()))?;
+            check_python_tool(support_files, code_extracted).await
+        }
+        // Agent and MCP tools have no code, the playground sends the arguments of the call as JSON
+        DynamicToolType::AgentDynamic => match check_arguments(&code_extracted) {
+            Ok(arguments) => Ok(check_agent_tool(&sqlite_manager, &arguments)),
+            Err(e) => Ok(vec![e]),
+        },
+        DynamicToolType::McpServerDynamic => match check_arguments(&code_extracted) {
+            Ok(arguments) => Ok(check_mcp_server_dynamic(&sqlite_manager, &tool_id, &arguments)),
+            Err(e) => Ok(vec![e]),
+        },
+    }
+}
+
+fn check_arguments(code: &str) -> Result<Map<String, Value>, String> {
+    if code.trim().is_empty() {
+        return Ok(Map::new());
+    }
+    match serde_json::from_str::<Value>(code) {
+        Ok(Value::Object(arguments)) => Ok(arguments),
+        Ok(_) => Err("Arguments must be a JSON object".to_string()),
+        Err(e) => Err(format!("Invalid JSON arguments: {}", e)),
     }
 }
 
@@ -1048,4 +1115,13 @@ export async function run(config: CONFIG, inputs: INPUTS): Promise<OUTPUT> {
         // The result should be the same as the input extra_config
         assert_eq!(result, extra_config);
     }
+
+    #[test]
+    fn test_check_arguments() {
+        assert_eq!(check_arguments("  ").unwrap(), Map::new());
+        let arguments = check_arguments(r#"{"prompt": "hello", "agent_id": "my_agent"}"#).unwrap();
+        assert_eq!(arguments.get("prompt"), Some(&json!("hello")));
+        assert!(check_arguments("[1, 2]").is_err());
+        assert!(check_arguments("{\"prompt\": ").is_err());
+    }
 }
@@ -161,6 +161,40 @@ pub async fn try_to_execute_rust_tool(
             )
             .await
         }
+        s if s == "local:::__official_zoo:::zoo_agent_remember" => {
+            tool_implementation::native_tools::agent_memory::AgentRememberTool::execute(
+                bearer,
+                tool_id,
+                app_id,
+                db,
+                node_name,
+                identity_manager,
+                job_manager,
+                encryption_secret_key,
+                encryption_public_key,
+                signing_secret_key,
+                &parameters,
+                llm_provider,
+            )
+            .await
+        }
+        s if s == "local:::__official_zoo:::zoo_agent_recall" => {
+            tool_implementation::native_tools::agent_memory::AgentRecallTool::execute(
+                bearer,
+                tool_id,
+                app_id,
+                db,
+                node_name,
+                identity_manager,
+                job_manager,
+                encryption_secret_key,
+                encryption_public_key,
+                signing_secret_key,
+                &parameters,
+                llm_provider,
+            )
+            .await
+        }
         _ => return Err(ToolError::ToolNotFound(tool_router_key.to_string())),
     };
     let text_result = format!("{:?}", result);
@@ -70,6 +70,7 @@ pub async fn execute_deno_tool(
             OperatingSystem::Windows,
         ]),
         tool_set: None,
+        permissions: None,
     };
 
     let env = generate_execution_environment(
@@ -182,6 +183,7 @@ pub async fn check_deno_tool(
         runner: RunnerType::Any,
         operating_system: vec![OperatingSystem::Linux, OperatingSystem::MacOS, OperatingSystem::Windows],
         tool_set: None,
+        permissions: None,
     };
 
     let node_env = fetch_node_environment();
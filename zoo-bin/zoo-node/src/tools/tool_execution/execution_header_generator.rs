@@ -166,6 +166,20 @@ fn validate_type(param_name: &str, param_value: &Value, property: &Property, err
 }
 
 fn check_tool_parameters(parameters: Parameters, value: Map<String, Value>) -> Result<(), ToolError> {
+    let errors = tool_parameter_errors(&parameters, &value);
+    if !errors.is_empty() {
+        return Err(ToolError::InvalidFunctionArguments(format!(
+            "Parameter validation failed:\n{}",
+            errors.join("\n")
+        )));
+    }
+
+    Ok(())
+}
+
+/// The problems found in the arguments of a tool call: missing or empty required parameters and
+/// values that don't match their declared type.
+pub fn tool_parameter_errors(parameters: &Parameters, value: &Map<String, Value>) -> Vec<String> {
     let mut errors = Vec::new();
 
     // Check if all required parameters are present and not null
@@ -198,14 +212,7 @@ fn check_tool_parameters(parameters: Parameters, value: Map<String, Value>) -> R
         }
     }
 
-    if !errors.is_empty() {
-        return Err(ToolError::InvalidFunctionArguments(format!(
-            "Parameter validation failed:\n{}",
-            errors.join("\n")
-        )));
-    }
-
-    Ok(())
+    errors
 }
 
 #[cfg(test)]
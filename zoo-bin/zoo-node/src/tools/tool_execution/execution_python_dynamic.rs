@@ -1,7 +1,8 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf, process::Stdio};
 
 use super::execution_header_generator::{check_tool, generate_execution_environment};
 use crate::utils::environment::fetch_node_environment;
+use serde::Deserialize;
 use serde_json::{Map, Value};
 use zoo_message_primitives::schemas::{zoo_name::ZooName, tool_router_key::ToolRouterKey};
 use zoo_sqlite::SqliteManager;
@@ -9,6 +10,9 @@ use zoo_tools_primitives::tools::{
     error::ToolError, parameters::Parameters, python_tools::PythonTool, tool_config::{OAuth, ToolConfig}, tool_output_arg::ToolOutputArg, tool_types::{OperatingSystem, RunnerType, ToolResult}
 };
 use std::sync::Arc;
+use tokio::{
+    io::AsyncWriteExt, process::Command, time::{timeout, Duration}
+};
 
 pub async fn execute_python_tool(
     _bearer: String,
@@ -64,6 +68,7 @@ pub async fn execute_python_tool(
             OperatingSystem::Windows,
         ]),
         tool_set: None,
+        permissions: None,
     };
 
     let env = generate_execution_environment(
@@ -136,3 +141,179 @@ pub async fn execute_python_tool(
         Err(e) => Err(e),
     }
 }
+
+/// Resolving imports can install the script dependencies first, so give it some time.
+const IMPORT_CHECK_TIMEOUT_SECS: u64 = 120;
+
+/// Prints, as a JSON list, the top level modules imported by the code on stdin that can't be
+/// found. The modules passed as arguments are provided by the node and skipped.
+const IMPORT_CHECK_SCRIPT: &str = r#"
+import ast, importlib.util, json, sys
+local = set(sys.argv[1:])
+missing = []
+for node in ast.walk(ast.parse(sys.stdin.read())):
+    if isinstance(node, ast.Import):
+        names = [alias.name for alias in node.names]
+    elif isinstance(node, ast.ImportFrom) and node.level == 0 and node.module:
+        names = [node.module]
+    else:
+        continue
+    for name in names:
+        module = name.split(".")[0]
+        if module not in local and module not in missing and importlib.util.find_spec(module) is None:
+            missing.append(module)
+print(json.dumps(missing))
+"#;
+
+/// Checks the syntax of a playground Python tool and, if it parses, that every module it imports
+/// resolves once the dependencies of its `# /// script` block are installed.
+pub async fn check_python_tool(support_files: HashMap<String, String>, code: String) -> Result<Vec<String>, ToolError> {
+    let tool = PythonTool {
+        name: "python_runtime".to_string(),
+        tool_router_key: None,
+        homepage: None,
+        version: "1.0.0".to_string(),
+        author: "@@official.zoo".to_string(),
+        py_code: code.clone(),
+        mcp_enabled: Some(false),
+        tools: vec![],
+        config: vec![],
+        description: "Python runtime execution".to_string(),
+        keywords: vec![],
+        input_args: Parameters::new(),
+        output_arg: ToolOutputArg { json: "".to_string() },
+        activated: true,
+        embedding: None,
+        result: ToolResult::new("object".to_string(), Value::Null, vec![]),
+        sql_tables: None,
+        sql_queries: None,
+        file_inbox: None,
+        oauth: None,
+        assets: None,
+        runner: RunnerType::Any,
+        operating_system: vec![OperatingSystem::Linux, OperatingSystem::MacOS, OperatingSystem::Windows],
+        tool_set: None,
+        permissions: None,
+    };
+
+    let warnings = tool.check_code(code.clone(), support_files.clone()).await?;
+    if !warnings.is_empty() {
+        return Ok(warnings);
+    }
+    check_python_imports(&code, support_files.keys().cloned().collect()).await
+}
+
+async fn check_python_imports(code: &str, local_modules: Vec<String>) -> Result<Vec<String>, ToolError> {
+    let (dependencies, requires_python) = script_metadata(code).map_err(ToolError::ExecutionError)?;
+
+    let uv_binary_path = std::env::var("ZOO_TOOLS_RUNNER_UV_BINARY_PATH")
+        .unwrap_or_else(|_| "./zoo-tools-runner-resources/uv".to_string());
+    let mut command = Command::new(uv_binary_path);
+    command.args(["run", "--no-project", "--quiet"]);
+    if let Some(requires_python) = requires_python {
+        command.args(["--python", &requires_python]);
+    }
+    for dependency in &dependencies {
+        command.args(["--with", dependency]);
+    }
+    command
+        .args(["python", "-c", IMPORT_CHECK_SCRIPT])
+        .args(&local_modules)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to start uv: {}", e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(code.as_bytes())
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to send code to uv: {}", e)))?;
+    }
+
+    let output = match timeout(Duration::from_secs(IMPORT_CHECK_TIMEOUT_SECS), child.wait_with_output()).await {
+        Ok(output) => output.map_err(|e| ToolError::ExecutionError(format!("Failed to run uv: {}", e)))?,
+        Err(_) => return Ok(vec!["Timed out while resolving imports".to_string()]),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Ok(vec![format!("Failed to install dependencies: {}", stderr.trim())]);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let missing: Vec<String> = stdout
+        .lines()
+        .last()
+        .and_then(|line| serde_json::from_str(line).ok())
+        .ok_or_else(|| ToolError::ExecutionError(format!("Unexpected import check output: {}", stdout)))?;
+    Ok(missing
+        .into_iter()
+        .map(|module| {
+            format!(
+                "Module '{}' could not be resolved, add its package to the dependencies of the script block",
+                module
+            )
+        })
+        .collect())
+}
+
+/// The `dependencies` and `requires-python` of the inline script metadata (PEP 723) block.
+fn script_metadata(code: &str) -> Result<(Vec<String>, Option<String>), String> {
+    let mut lines = code.lines().skip_while(|line| line.trim_end() != "# /// script");
+    if lines.next().is_none() {
+        return Ok((vec![], None));
+    }
+
+    let mut metadata = String::new();
+    for line in lines {
+        let line = line.trim_end();
+        if line == "# ///" {
+            #[derive(Deserialize)]
+            struct ScriptMetadata {
+                #[serde(default)]
+                dependencies: Vec<String>,
+                #[serde(rename = "requires-python")]
+                requires_python: Option<String>,
+            }
+            let parsed: ScriptMetadata =
+                toml::from_str(&metadata).map_err(|e| format!("Invalid script block: {}", e))?;
+            return Ok((parsed.dependencies, parsed.requires_python));
+        }
+        let content = line
+            .strip_prefix("# ")
+            .or_else(|| line.strip_prefix('#'))
+            .ok_or_else(|| "Invalid script block: every line must start with '#'".to_string())?;
+        metadata.push_str(content);
+        metadata.push('\n');
+    }
+    Err("Invalid script block: missing closing '# ///'".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_metadata() {
+        let code = r#"# /// script
+# requires-python = ">=3.10"
+# dependencies = [
+#   "requests",
+#   "beautifulsoup4>=4.12",
+# ]
+# ///
+
+import requests
+from bs4 import BeautifulSoup
+"#;
+        let (dependencies, requires_python) = script_metadata(code).unwrap();
+        assert_eq!(dependencies, vec!["requests", "beautifulsoup4>=4.12"]);
+        assert_eq!(requires_python.as_deref(), Some(">=3.10"));
+
+        assert_eq!(script_metadata("import json\n").unwrap(), (vec![], None));
+        assert!(script_metadata("# /// script\n# dependencies = [\n").is_err());
+    }
+}
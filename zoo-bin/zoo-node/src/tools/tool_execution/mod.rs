@@ -1,7 +1,9 @@
 pub mod execute_agent_dynamic;
 pub mod execute_mcp_server_dynamic;
+pub mod execute_network_tool;
 pub mod execution_coordinator;
 pub mod execution_custom;
 pub mod execution_deno_dynamic;
 pub mod execution_header_generator;
 pub mod execution_python_dynamic;
+pub mod oauth_refresh;
@@ -0,0 +1,459 @@
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use zoo_message_primitives::schemas::ws_types::{OAuthMetadata, WSMessageType, WSUpdateHandler, WidgetMetadata};
+use zoo_message_primitives::zoo_message::zoo_message_schemas::WSTopic;
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
+use zoo_sqlite::oauth_manager::OAuthToken;
+use zoo_sqlite::SqliteManager;
+use zoo_tools_primitives::tools::error::ToolError;
+use zoo_tools_primitives::tools::tool_config::OAuth;
+use zoo_tools_runner::tools::run_result::RunResult;
+
+/// Access tokens are renewed when they expire within this many seconds.
+pub const OAUTH_REFRESH_MARGIN_SECS: i64 = 5 * 60;
+/// How often the refresh service looks for access tokens about to expire.
+pub const OAUTH_REFRESH_INTERVAL_SECS: u64 = 60;
+/// What a tool puts in its error when the provider answered 401 to its access token, e.g.
+/// `throw new Error("ZOO_OAUTH_UNAUTHORIZED: token rejected")`. The token is refreshed and the tool
+/// runs once more.
+pub const OAUTH_UNAUTHORIZED_MARKER: &str = "ZOO_OAUTH_UNAUTHORIZED";
+
+#[derive(Debug)]
+pub enum OAuthRefreshError {
+    /// The provider refused the refresh token (revoked or expired). The user has to log in again.
+    Rejected(String),
+    /// The provider couldn't be reached or failed. The current token is kept and the refresh is
+    /// tried again later.
+    Failed(String),
+}
+
+impl std::fmt::Display for OAuthRefreshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OAuthRefreshError::Rejected(e) => write!(f, "OAuth refresh token rejected: {}", e),
+            OAuthRefreshError::Failed(e) => write!(f, "Failed to refresh OAuth token: {}", e),
+        }
+    }
+}
+
+/// Whether the access token of `token` can be refreshed and expires within the refresh margin.
+pub fn token_needs_refresh(token: &OAuthToken, now: DateTime<Utc>) -> bool {
+    if token.access_token.is_none() || token.refresh_token.is_none() || token.token_url.is_none() {
+        return false;
+    }
+    // Tokens stored before access_token_expires_at was tracked only have the refresh expiration
+    match token.access_token_expires_at.or(token.refresh_token_expires_at) {
+        Some(expires_at) => now + chrono::Duration::seconds(OAUTH_REFRESH_MARGIN_SECS) >= expires_at,
+        None => false,
+    }
+}
+
+/// Sends `body` to the token endpoint of `token`, as a form or as JSON depending on the tool's
+/// OAuth config, with a Basic auth header if the config asks for one.
+pub async fn send_token_request(token: &OAuthToken, body: &Value) -> Result<reqwest::Response, reqwest::Error> {
+    let url = token.token_url.clone().unwrap_or_default();
+    let client = Client::new();
+    let is_form = token.request_token_content_type.as_deref() == Some("application/x-www-form-urlencoded");
+
+    let mut request = client.post(&url).header("Accept", "application/json");
+    if let Some(auth_header) = &token.request_token_auth_header {
+        if auth_header.to_lowercase() == "basic" {
+            if let (Some(client_id), Some(client_secret)) = (&token.client_id, &token.client_secret) {
+                let auth = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", client_id, client_secret));
+                request = request.header("Authorization", format!("Basic {}", auth));
+            }
+        }
+    }
+
+    if is_form {
+        let form_data: Vec<(String, String)> = body
+            .as_object()
+            .map(|body| {
+                body.iter()
+                    .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        request
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .form(&form_data)
+            .send()
+            .await
+    } else {
+        request
+            .header("Content-Type", "application/json")
+            .json(body)
+            .send()
+            .await
+    }
+}
+
+/// Copies the tokens and expirations of a token endpoint response into `token`.
+pub fn apply_token_response(token: &mut OAuthToken, response: &Value, now: DateTime<Utc>) {
+    if let Some(access_token) = response["access_token"].as_str() {
+        token.access_token = Some(access_token.to_string());
+    }
+    if let Some(expires_in) = response["expires_in"].as_i64() {
+        token.access_token_expires_at = Some(now + chrono::Duration::seconds(expires_in));
+    }
+    // Providers that rotate refresh tokens send a new one with every refresh
+    if let Some(refresh_token) = response["refresh_token"].as_str() {
+        token.refresh_token = Some(refresh_token.to_string());
+        token.refresh_token_expires_at = response["refresh_token_expires_in"]
+            .as_i64()
+            .map(|expires_in| now + chrono::Duration::seconds(expires_in));
+    }
+    if let Some(scope) = response["scope"].as_str() {
+        token.scope = Some(scope.to_string());
+    }
+    if let Some(id_token) = response["id_token"].as_str() {
+        token.id_token = Some(id_token.to_string());
+    }
+}
+
+/// Renews the access token of `token` with the refresh_token grant and stores it. When the
+/// provider rejects the refresh token, the stored tokens are cleared so the next execution of the
+/// tool asks the user to log in again.
+pub async fn refresh_oauth_token(db: &SqliteManager, token: &OAuthToken) -> Result<OAuthToken, OAuthRefreshError> {
+    let refresh_token = token
+        .refresh_token
+        .clone()
+        .ok_or_else(|| OAuthRefreshError::Rejected("no refresh token".to_string()))?;
+    let body = serde_json::json!({
+        "grant_type": "refresh_token",
+        "refresh_token": refresh_token,
+        "client_id": token.client_id.as_deref().unwrap_or_default(),
+        "client_secret": token.client_secret.as_deref().unwrap_or_default(),
+    });
+
+    let response = send_token_request(token, &body)
+        .await
+        .map_err(|e| OAuthRefreshError::Failed(e.to_string()))?;
+    let status = response.status();
+    let response_json = response.json::<Value>().await.unwrap_or(Value::Null);
+    let provider_error = response_json["error"].as_str().filter(|error| !error.is_empty());
+
+    // Rate limits are transient, any other client error means the refresh token is no longer valid
+    let rejected = status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS;
+    if rejected || (status.is_success() && provider_error.is_some()) {
+        let reason = provider_error.map(str::to_string).unwrap_or_else(|| status.to_string());
+        let mut cleared_token = token.clone();
+        cleared_token.access_token = None;
+        cleared_token.access_token_expires_at = None;
+        cleared_token.refresh_token = None;
+        cleared_token.refresh_token_expires_at = None;
+        if let Err(e) = db.update_oauth_token(&cleared_token) {
+            zoo_log(
+                ZooLogOption::Node,
+                ZooLogLevel::Error,
+                &format!("Failed to clear OAuth token {}: {}", token.connection_name, e),
+            );
+        }
+        return Err(OAuthRefreshError::Rejected(reason));
+    }
+    if !status.is_success() {
+        return Err(OAuthRefreshError::Failed(status.to_string()));
+    }
+    if response_json["access_token"].as_str().is_none() {
+        return Err(OAuthRefreshError::Failed("no access token in the response".to_string()));
+    }
+
+    let mut refreshed_token = token.clone();
+    apply_token_response(&mut refreshed_token, &response_json, Utc::now());
+    db.update_oauth_token(&refreshed_token)
+        .map_err(|e| OAuthRefreshError::Failed(format!("failed to store the token: {}", e)))?;
+    zoo_log(
+        ZooLogOption::Node,
+        ZooLogLevel::Info,
+        &format!(
+            "Refreshed OAuth token {} for {}",
+            refreshed_token.connection_name, refreshed_token.tool_key
+        ),
+    );
+    Ok(refreshed_token)
+}
+
+/// Refreshes every stored access token that expires soon. Returns the tokens that couldn't be
+/// refreshed because the provider rejected their refresh token.
+pub async fn refresh_expiring_oauth_tokens(db: &SqliteManager) -> Vec<(OAuthToken, OAuthRefreshError)> {
+    let tokens = match db.get_all_oauth_tokens() {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            zoo_log(
+                ZooLogOption::Node,
+                ZooLogLevel::Error,
+                &format!("Failed to read OAuth tokens: {}", e),
+            );
+            return vec![];
+        }
+    };
+
+    let now = Utc::now();
+    let mut rejected = vec![];
+    for token in tokens.into_iter().filter(|token| token_needs_refresh(token, now)) {
+        match refresh_oauth_token(db, &token).await {
+            Ok(_) => {}
+            Err(e @ OAuthRefreshError::Rejected(_)) => rejected.push((token, e)),
+            Err(e) => zoo_log(
+                ZooLogOption::Node,
+                ZooLogLevel::Error,
+                &format!("{} ({} for {})", e, token.connection_name, token.tool_key),
+            ),
+        }
+    }
+    rejected
+}
+
+/// Forces a refresh of the OAuth tokens of a tool, after it failed with an unauthorized error.
+/// Returns true if at least one token was renewed, so the tool is worth running again.
+pub async fn refresh_tool_oauth_tokens(db: &SqliteManager, tool_router_key: &str, oauth: &Option<Vec<OAuth>>) -> bool {
+    let mut refreshed = false;
+    for o in oauth.iter().flatten() {
+        let token = match db.get_oauth_token(o.name.clone(), tool_router_key.to_string()) {
+            Ok(Some(token)) if token.access_token.is_some() && token.refresh_token.is_some() => token,
+            _ => continue,
+        };
+        match refresh_oauth_token(db, &token).await {
+            Ok(_) => refreshed = true,
+            Err(e) => zoo_log(
+                ZooLogOption::Node,
+                ZooLogLevel::Error,
+                &format!("{} ({} for {})", e, token.connection_name, tool_router_key),
+            ),
+        }
+    }
+    refreshed
+}
+
+/// Whether a tool run failed because the provider didn't accept its access token: a request of the
+/// node answered with a 401, or the tool failed with an error containing `OAUTH_UNAUTHORIZED_MARKER`.
+/// The runners turn the failures of the tool code into an `"error"` result, so both are checked.
+pub fn is_unauthorized_result(result: &Result<RunResult, ToolError>) -> bool {
+    match result {
+        Ok(result) => {
+            result.data.get("status").and_then(Value::as_str) == Some("error")
+                && result
+                    .data
+                    .get("error")
+                    .and_then(Value::as_str)
+                    .is_some_and(|error| error.contains(OAUTH_UNAUTHORIZED_MARKER))
+        }
+        Err(ToolError::RequestError(e)) => e.status() == Some(reqwest::StatusCode::UNAUTHORIZED),
+        Err(ToolError::ExecutionError(message)) => message.contains(OAUTH_UNAUTHORIZED_MARKER),
+        Err(_) => false,
+    }
+}
+
+/// The URL where the user logs in again for `token`. It reuses the state and PKCE verifier of the
+/// token, so the callback updates the same row.
+pub fn oauth_login_url(token: &OAuthToken) -> String {
+    let mut query_params = vec![
+        ("response_type", token.response_type.clone()),
+        ("client_id", token.client_id.clone().unwrap_or_default()),
+        ("redirect_uri", token.redirect_url.clone().unwrap_or_default()),
+        ("scope", token.scope.clone().unwrap_or_default()),
+        ("state", token.state.clone()),
+    ];
+    if let (Some(pkce_type), Some(verifier)) = (&token.pkce_type, &token.pkce_code_verifier) {
+        match pkce_type.to_lowercase().as_str() {
+            "plain" => {
+                query_params.push(("code_challenge", verifier.clone()));
+                query_params.push(("code_challenge_method", "plain".to_string()));
+            }
+            "s256" => {
+                let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+                query_params.push(("code_challenge", challenge));
+                query_params.push(("code_challenge_method", "S256".to_string()));
+            }
+            _ => {}
+        }
+    }
+    let query_string = query_params
+        .iter()
+        .map(|(k, v)| format!("{}={}", urlencoding::encode(k), urlencoding::encode(v)))
+        .collect::<Vec<String>>()
+        .join("&");
+    format!(
+        "{}?{}",
+        token.authorization_url.clone().unwrap_or_default(),
+        query_string
+    )
+}
+
+/// Sends a widget asking the user to log in again to the OAuth connection of a tool. `inbox` is
+/// the job that needed the tool, or empty when the refresh service found the problem.
+pub async fn notify_oauth_reauth_required(
+    ws_manager: &Option<Arc<Mutex<dyn WSUpdateHandler + Send>>>,
+    inbox: String,
+    metadata: OAuthMetadata,
+) {
+    if let Some(ws_manager) = ws_manager {
+        let widget = WSMessageType::Widget(WidgetMetadata::OAuthRequired(metadata));
+        ws_manager
+            .lock()
+            .await
+            .queue_message(WSTopic::Widget, inbox, "".to_string(), widget, false)
+            .await;
+    }
+}
+
+/// Renews the access tokens in the background before they expire, so tools don't find them
+/// expired. Stops when the database is dropped.
+pub fn spawn_oauth_refresh_service(
+    db: Weak<SqliteManager>,
+    ws_manager: Option<Arc<Mutex<dyn WSUpdateHandler + Send>>>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(OAUTH_REFRESH_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let Some(db) = db.upgrade() else {
+                break;
+            };
+            for (token, error) in refresh_expiring_oauth_tokens(&db).await {
+                zoo_log(
+                    ZooLogOption::Node,
+                    ZooLogLevel::Info,
+                    &format!(
+                        "OAuth connection {} for {} needs to log in again: {}",
+                        token.connection_name, token.tool_key, error
+                    ),
+                );
+                let metadata = OAuthMetadata {
+                    tool_key: token.tool_key.clone(),
+                    connection_name: Some(token.connection_name.clone()),
+                    authorization_url: oauth_login_url(&token),
+                    error_message: Some(error.to_string()),
+                };
+                notify_oauth_reauth_required(&ws_manager, "".to_string(), metadata).await;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oauth_token() -> OAuthToken {
+        OAuthToken {
+            id: 1,
+            connection_name: "google".to_string(),
+            response_type: "code".to_string(),
+            state: "state-1".to_string(),
+            code: None,
+            app_id: "app".to_string(),
+            tool_id: "tool".to_string(),
+            tool_key: "local:::dev:::gmail".to_string(),
+            access_token: Some("access".to_string()),
+            access_token_expires_at: None,
+            refresh_token: Some("refresh".to_string()),
+            refresh_token_enabled: Some(true),
+            refresh_token_expires_at: None,
+            token_secret: None,
+            id_token: None,
+            scope: Some("mail.read".to_string()),
+            pkce_type: Some("s256".to_string()),
+            pkce_code_verifier: Some("verifier".to_string()),
+            expires_at: None,
+            metadata_json: None,
+            authorization_url: Some("https://accounts.example.com/auth".to_string()),
+            token_url: Some("https://accounts.example.com/token".to_string()),
+            client_id: Some("client".to_string()),
+            client_secret: Some("secret".to_string()),
+            redirect_url: Some("https://secrets.zoo.ngo/redirect".to_string()),
+            version: "2.0".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            request_token_auth_header: None,
+            request_token_content_type: None,
+        }
+    }
+
+    #[test]
+    fn test_token_needs_refresh() {
+        let now = Utc::now();
+        let mut token = oauth_token();
+        // Unknown expiration
+        assert!(!token_needs_refresh(&token, now));
+
+        token.access_token_expires_at = Some(now + chrono::Duration::hours(1));
+        assert!(!token_needs_refresh(&token, now));
+        token.access_token_expires_at = Some(now + chrono::Duration::minutes(2));
+        assert!(token_needs_refresh(&token, now));
+
+        // Nothing to refresh with
+        token.refresh_token = None;
+        assert!(!token_needs_refresh(&token, now));
+    }
+
+    #[test]
+    fn test_apply_token_response() {
+        let now = Utc::now();
+        let mut token = oauth_token();
+        apply_token_response(
+            &mut token,
+            &serde_json::json!({ "access_token": "new-access", "expires_in": 3600 }),
+            now,
+        );
+        assert_eq!(token.access_token.as_deref(), Some("new-access"));
+        assert_eq!(
+            token.access_token_expires_at,
+            Some(now + chrono::Duration::seconds(3600))
+        );
+        // The refresh token is kept when the provider doesn't rotate it
+        assert_eq!(token.refresh_token.as_deref(), Some("refresh"));
+
+        apply_token_response(
+            &mut token,
+            &serde_json::json!({ "access_token": "a", "refresh_token": "r2", "refresh_token_expires_in": 60 }),
+            now,
+        );
+        assert_eq!(token.refresh_token.as_deref(), Some("r2"));
+        assert_eq!(
+            token.refresh_token_expires_at,
+            Some(now + chrono::Duration::seconds(60))
+        );
+    }
+
+    #[test]
+    fn test_oauth_login_url() {
+        let url = oauth_login_url(&oauth_token());
+        assert!(url.starts_with("https://accounts.example.com/auth?response_type=code"));
+        assert!(url.contains("state=state-1"));
+        assert!(url.contains("code_challenge_method=S256"));
+        assert!(!url.contains("verifier"));
+    }
+
+    #[test]
+    fn test_is_unauthorized_result() {
+        let failed = |error: &str| {
+            Ok(RunResult {
+                data: serde_json::json!({ "status": "error", "message": "Tool failed.", "error": error }),
+            })
+        };
+        assert!(is_unauthorized_result(&failed(
+            "Uncaught Error: ZOO_OAUTH_UNAUTHORIZED: invalid credentials"
+        )));
+        // Errors that only mention a 401 don't trigger a refresh
+        assert!(!is_unauthorized_result(&failed("Expected 401 rows, got 12")));
+        assert!(!is_unauthorized_result(&failed("Unauthorized file access")));
+        assert!(!is_unauthorized_result(&Ok(RunResult {
+            data: serde_json::json!({ "status": "ok", "error": OAUTH_UNAUTHORIZED_MARKER }),
+        })));
+        assert!(is_unauthorized_result(&Err(ToolError::ExecutionError(format!(
+            "{}: token expired",
+            OAUTH_UNAUTHORIZED_MARKER
+        )))));
+        assert!(!is_unauthorized_result(&Err(ToolError::ExecutionError(
+            "HTTP 401 Unauthorized".to_string()
+        ))));
+    }
+}
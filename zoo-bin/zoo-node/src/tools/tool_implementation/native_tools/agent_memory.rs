@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ed25519_dalek::SigningKey;
+use serde_json::{json, Map, Value};
+use tokio::sync::Mutex;
+use x25519_dalek::PublicKey as EncryptionPublicKey;
+use x25519_dalek::StaticSecret as EncryptionStaticKey;
+use zoo_message_primitives::schemas::tool_router_key::ToolRouterKey;
+use zoo_message_primitives::schemas::zoo_name::ZooName;
+use zoo_sqlite::SqliteManager;
+use zoo_tools_primitives::tools::error::ToolError;
+use zoo_tools_primitives::tools::parameters::Parameters;
+use zoo_tools_primitives::tools::{tool_output_arg::ToolOutputArg, zoo_tool::ZooToolHeader};
+
+use crate::llm_provider::job_manager::JobManager;
+use crate::managers::IdentityManager;
+use crate::tools::tool_implementation::tool_traits::ToolExecutor;
+
+pub const AGENT_REMEMBER_TOOL_KEY: &str = "local:::__official_zoo:::zoo_agent_remember";
+pub const AGENT_RECALL_TOOL_KEY: &str = "local:::__official_zoo:::zoo_agent_recall";
+
+const DEFAULT_RECALL_LIMIT: usize = 5;
+const MAX_RECALL_LIMIT: usize = 20;
+
+/// Whether the tool reads the agent from the job running it. These tools can only run inside a
+/// job: anywhere else the caller would choose whose memory to use.
+pub fn is_agent_memory_tool(tool_router_key: &str) -> bool {
+    let key = ToolRouterKey::from_string(tool_router_key)
+        .map(|key| key.to_string_without_version())
+        .unwrap_or_else(|_| tool_router_key.to_string());
+    key == AGENT_REMEMBER_TOOL_KEY || key == AGENT_RECALL_TOOL_KEY
+}
+
+/// The agent running the tool. It isn't an input of the tools: the tool router sets it from the
+/// job, so an agent only reaches its own memories.
+fn agent_id_parameter(parameters: &Map<String, Value>) -> Result<String, ToolError> {
+    parameters
+        .get("agent_id")
+        .and_then(|value| value.as_str())
+        .map(|agent_id| agent_id.to_string())
+        .ok_or_else(|| ToolError::ExecutionError("Agent memory is only available to agents".to_string()))
+}
+
+pub struct AgentRememberTool {
+    pub tool: ZooToolHeader,
+}
+
+impl AgentRememberTool {
+    pub fn new() -> Self {
+        Self {
+            tool: ZooToolHeader {
+                name: "Zoo Agent Remember".to_string(),
+                description: r#"Stores a fact in your long-term memory so you know it in your next conversations.
+Use it for lasting information: user preferences, decisions, names, recurring tasks.
+Write one self-contained fact per call, for example: "The user prefers answers in Spanish"."#
+                    .to_string(),
+                tool_router_key: AGENT_REMEMBER_TOOL_KEY.to_string(),
+                tool_type: "Rust".to_string(),
+                formatted_tool_summary_for_ui: "Remember a fact across conversations".to_string(),
+                author: "@@official.zoo".to_string(),
+                version: "1.0".to_string(),
+                enabled: true,
+                mcp_enabled: Some(false),
+                input_args: {
+                    let mut params = Parameters::new();
+                    params.add_property(
+                        "content".to_string(),
+                        "string".to_string(),
+                        "The fact to remember".to_string(),
+                        true,
+                        None,
+                    );
+                    params
+                },
+                output_arg: ToolOutputArg {
+                    json: r#"{"type": "object", "properties": {"memory_id": {"type": "string"}, "content": {"type": "string"}}}"#
+                        .to_string(),
+                },
+                config: None,
+                usage_type: None,
+                tool_offering: None,
+                permissions: None,
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for AgentRememberTool {
+    async fn execute(
+        _bearer: String,
+        _tool_id: String,
+        _app_id: String,
+        db_clone: Arc<SqliteManager>,
+        _node_name: ZooName,
+        _identity_manager_clone: Arc<Mutex<IdentityManager>>,
+        _job_manager: Arc<Mutex<JobManager>>,
+        _encryption_secret_key_clone: EncryptionStaticKey,
+        _encryption_public_key_clone: EncryptionPublicKey,
+        _signing_secret_key_clone: SigningKey,
+        parameters: &Map<String, Value>,
+        _llm_provider: String,
+    ) -> Result<Value, ToolError> {
+        let agent_id = agent_id_parameter(parameters)?;
+        let content = parameters
+            .get("content")
+            .and_then(|value| value.as_str())
+            .ok_or_else(|| ToolError::InvalidFunctionArguments("content is required".to_string()))?;
+        let job_id = parameters
+            .get("job_id")
+            .and_then(|value| value.as_str())
+            .map(|job_id| job_id.to_string());
+
+        let memory = db_clone
+            .add_agent_memory(&agent_id, content, job_id)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to remember: {}", e)))?;
+        db_clone.embed_agent_memories(&agent_id).await;
+        Ok(json!({
+            "memory_id": memory.memory_id,
+            "content": memory.content,
+        }))
+    }
+}
+
+pub struct AgentRecallTool {
+    pub tool: ZooToolHeader,
+}
+
+impl AgentRecallTool {
+    pub fn new() -> Self {
+        Self {
+            tool: ZooToolHeader {
+                name: "Zoo Agent Recall".to_string(),
+                description: r#"Searches your long-term memory: your knowledge and the facts you remembered in previous conversations.
+The most relevant memories are already in your prompt, use it to look for something else."#
+                    .to_string(),
+                tool_router_key: AGENT_RECALL_TOOL_KEY.to_string(),
+                tool_type: "Rust".to_string(),
+                formatted_tool_summary_for_ui: "Search the agent memory".to_string(),
+                author: "@@official.zoo".to_string(),
+                version: "1.0".to_string(),
+                enabled: true,
+                mcp_enabled: Some(false),
+                input_args: {
+                    let mut params = Parameters::new();
+                    params.add_property(
+                        "query".to_string(),
+                        "string".to_string(),
+                        "What to look for".to_string(),
+                        true,
+                        None,
+                    );
+                    params.add_property(
+                        "limit".to_string(),
+                        "number".to_string(),
+                        format!("How many memories to return, {} by default", DEFAULT_RECALL_LIMIT),
+                        false,
+                        None,
+                    );
+                    params
+                },
+                output_arg: ToolOutputArg {
+                    json: r#"{"type": "object", "properties": {"memories": {"type": "array", "items": {"type": "string"}}}}"#
+                        .to_string(),
+                },
+                config: None,
+                usage_type: None,
+                tool_offering: None,
+                permissions: None,
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for AgentRecallTool {
+    async fn execute(
+        _bearer: String,
+        _tool_id: String,
+        _app_id: String,
+        db_clone: Arc<SqliteManager>,
+        _node_name: ZooName,
+        _identity_manager_clone: Arc<Mutex<IdentityManager>>,
+        _job_manager: Arc<Mutex<JobManager>>,
+        _encryption_secret_key_clone: EncryptionStaticKey,
+        _encryption_public_key_clone: EncryptionPublicKey,
+        _signing_secret_key_clone: SigningKey,
+        parameters: &Map<String, Value>,
+        _llm_provider: String,
+    ) -> Result<Value, ToolError> {
+        let agent_id = agent_id_parameter(parameters)?;
+        let query = parameters
+            .get("query")
+            .and_then(|value| value.as_str())
+            .ok_or_else(|| ToolError::InvalidFunctionArguments("query is required".to_string()))?;
+        let limit = parameters
+            .get("limit")
+            .and_then(|value| value.as_u64())
+            .map_or(DEFAULT_RECALL_LIMIT, |limit| limit as usize)
+            .clamp(1, MAX_RECALL_LIMIT);
+
+        let memories = db_clone
+            .search_agent_memories(&agent_id, query, limit)
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to recall: {}", e)))?;
+        Ok(json!({
+            "memories": memories.into_iter().map(|memory| memory.content).collect::<Vec<String>>(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_id_is_not_an_input() {
+        for tool in [AgentRememberTool::new().tool, AgentRecallTool::new().tool] {
+            assert!(!tool.input_args.properties.contains_key("agent_id"));
+        }
+        assert!(matches!(
+            agent_id_parameter(&Map::new()),
+            Err(ToolError::ExecutionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_is_agent_memory_tool() {
+        assert!(is_agent_memory_tool(AGENT_REMEMBER_TOOL_KEY));
+        assert!(is_agent_memory_tool(&format!("{}:::1.0", AGENT_RECALL_TOOL_KEY)));
+        assert!(!is_agent_memory_tool(
+            "local:::__official_zoo:::zoo_llm_map_reduce_processor"
+        ));
+    }
+}
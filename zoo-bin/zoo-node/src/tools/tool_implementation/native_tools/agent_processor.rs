@@ -64,6 +64,7 @@ This can be used to process complex requests, text analysis, text matching, text
                 config: None,
                 usage_type: None,
                 tool_offering: None,
+                permissions: None,
             },
         }
     }
@@ -62,6 +62,7 @@ Example usage:
                 config: None,
                 usage_type: None,
                 tool_offering: None,
+                permissions: None,
             },
             _tool_embedding: None,
         }
@@ -385,6 +386,7 @@ mod tests {
                 runner: RunnerType::Any,
                 operating_system: vec![OperatingSystem::Windows],
                 tool_set: None,
+                permissions: None,
             },
             true,
         );
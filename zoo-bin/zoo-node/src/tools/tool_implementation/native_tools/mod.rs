@@ -5,6 +5,7 @@
 // Hay que crear una herramienta igual, en un archivo igual que ese.
 // Solo tiene que mantener la firma del run
 
+pub mod agent_memory;
 pub mod agent_processor;
 pub mod config_setup;
 pub mod llm_map_reduce_processor;
@@ -80,6 +80,7 @@ SELECT field_1, field_3 FROM table_name WHERE field_3 > 100 ORDER BY field_2 DES
                 config: None,
                 usage_type: None,
                 tool_offering: None,
+                permissions: None,
             },
             _tool_embedding: None, // TODO: add tool embedding
         }
@@ -62,6 +62,7 @@ Example usage:
                 config: None,
                 usage_type: None,
                 tool_offering: None,
+                permissions: None,
             }
         }
     }
@@ -22,8 +22,9 @@ use crate::tools::tool_implementation::tool_traits::ToolExecutor;
 use tokio::sync::Mutex;
 
 use async_trait::async_trait;
-use std::process::{Command, Stdio};
+use std::process::Stdio;
 use std::{env, fs};
+use tokio::process::Command;
 
 // LLM Tool
 pub struct TypescriptUnsafeProcessorTool {
@@ -82,6 +83,7 @@ impl TypescriptUnsafeProcessorTool {
                 config: None,
                 usage_type: None,
                 tool_offering: None,
+                permissions: None,
             },
             _tool_embedding: None,
         }
@@ -138,7 +140,10 @@ impl TypescriptUnsafeProcessorTool {
                 .current_dir(temp_path.clone())
                 .stdout(Stdio::inherit())
                 // .stderr(Stdio::inherit())
+                // Stopping the job drops this future, which must not leave the child behind
+                .kill_on_drop(true)
                 .output()
+                .await
                 .map_err(|e| {
                     if e.kind() == std::io::ErrorKind::NotFound {
                         ToolError::ExecutionError(
@@ -165,7 +170,9 @@ impl TypescriptUnsafeProcessorTool {
             .arg("index.ts")
             .current_dir(temp_path.clone())
             .stderr(Stdio::inherit())
+            .kill_on_drop(true)
             .output()
+            .await
             .map_err(|e| {
                 if e.kind() == std::io::ErrorKind::NotFound {
                     ToolError::ExecutionError(
@@ -10,6 +10,11 @@ pub struct Args {
     pub receiver_subidentity: Option<String>,
     pub inbox: Option<String>,
     pub body_content: Option<String>,
+    pub config: Option<String>,
+    pub check_config: bool,
+    pub print_config: bool,
+    pub migrate_dry_run: bool,
+    pub migrate_rollback: Option<u32>,
 }
 
 pub fn parse_args() -> Args {
@@ -59,6 +64,38 @@ pub fn parse_args() -> Args {
                 .long("body_content")
                 .takes_value(true),
         )
+        .arg(
+            clap::Arg::new("config")
+                .long("config")
+                .takes_value(true)
+                .help("Path of the zoo-node.toml config file"),
+        )
+        .arg(
+            clap::Arg::new("check_config")
+                .long("check-config")
+                .takes_value(false)
+                .help("Report every problem in the configuration and exit"),
+        )
+        .arg(
+            clap::Arg::new("print_config")
+                .long("print-config")
+                .takes_value(false)
+                .help("Print the effective configuration, API keys hidden, and exit"),
+        )
+        .arg(
+            clap::Arg::new("migrate_dry_run")
+                .long("migrate-dry-run")
+                .takes_value(false)
+                .help("Print the SQL of the pending database migrations and exit"),
+        )
+        .arg(
+            clap::Arg::new("migrate_rollback")
+                .long("migrate-rollback")
+                .takes_value(true)
+                .value_name("VERSION")
+                .validator(|version| version.parse::<u32>())
+                .help("Undo the database migrations above VERSION, e.g. to go back to an older release, and exit"),
+        )
         .get_matches();
 
     Args {
@@ -71,5 +108,10 @@ pub fn parse_args() -> Args {
         receiver_subidentity: matches.value_of("receiver_subidentity").map(String::from),
         inbox: matches.value_of("inbox").map(String::from),
         body_content: matches.value_of("body_content").map(String::from),
+        config: matches.value_of("config").map(String::from),
+        check_config: matches.is_present("check_config"),
+        print_config: matches.is_present("print_config"),
+        migrate_dry_run: matches.is_present("migrate_dry_run"),
+        migrate_rollback: matches.value_of_t("migrate_rollback").ok(),
     }
 }
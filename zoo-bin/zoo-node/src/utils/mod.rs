@@ -4,5 +4,6 @@ pub mod environment;
 pub mod github_mcp;
 pub mod keys;
 pub mod logging_helpers;
+pub mod node_config;
 pub mod printer;
 pub mod update_global_identity;
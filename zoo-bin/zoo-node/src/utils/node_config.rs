@@ -0,0 +1,1095 @@
+//! Node configuration. Settings are read from a `zoo-node.toml` file, when there is one, and each of
+//! them can still be overridden with the environment variable the node has always used for it
+//! (`NODE_PORT`, `API_V2_KEY`, `INITIAL_AGENT_NAMES`, ...).
+//!
+//! ```toml
+//! [node]
+//! global_identity_name = "@@my_node.sep-zoo"
+//! port = 9552
+//!
+//! [api]
+//! port = 9550
+//! ws_port = 9551
+//!
+//! [embeddings]
+//! server_url = "http://localhost:11434"
+//!
+//! [logging]
+//! options = ["node", "job_execution"]
+//!
+//! [preferences]
+//! default_llm_provider = "my_gpt"
+//!
+//! [[llm_providers]]
+//! name = "my_gpt"
+//! model = "openai:gpt-4o-mini"
+//! url = "https://api.openai.com"
+//! api_key = "sk-..."
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+use zoo_message_primitives::schemas::llm_providers::serialized_llm_provider::{
+    LLMProviderInterface, SerializedLLMProvider,
+};
+use zoo_message_primitives::schemas::secrets::secret_reference_name;
+use zoo_message_primitives::schemas::zoo_name::ZooName;
+use zoo_message_primitives::zoo_utils::zoo_logging::{set_log_config, ZooLogConfig, ZooLogOption};
+
+use super::environment::NodeEnvironment;
+
+pub const CONFIG_FILE_NAME: &str = "zoo-node.toml";
+/// Path of the config file to use when none is passed with `--config`.
+pub const CONFIG_PATH_ENV: &str = "ZOO_NODE_CONFIG";
+
+const DEFAULT_GLOBAL_IDENTITY_NAME: &str = "@@localhost.sep-zoo";
+const REDACTED: &str = "********";
+
+/// The `[logging] options`, the variable that also enables each of them and its `zoo_log` option.
+const LOG_OPTIONS: &[(&str, &str, ZooLogOption)] = &[
+    ("blockchain", "LOG_BLOCKCHAIN", ZooLogOption::Blockchain),
+    ("database", "LOG_DATABASE", ZooLogOption::Database),
+    ("identity", "LOG_IDENTITY", ZooLogOption::Identity),
+    (
+        "identity_network",
+        "LOG_IDENTITY_NETWORK",
+        ZooLogOption::IdentityNetwork,
+    ),
+    (
+        "ext_subscriptions",
+        "LOG_EXT_SUBSCRIPTIONS",
+        ZooLogOption::ExtSubscriptions,
+    ),
+    (
+        "my_subscriptions",
+        "LOG_MY_SUBSCRIPTIONS",
+        ZooLogOption::MySubscriptions,
+    ),
+    (
+        "subscription_http_uploader",
+        "LOG_SUBSCRIPTION_HTTP_UPLOADER",
+        ZooLogOption::SubscriptionHTTPUploader,
+    ),
+    (
+        "subscription_http_downloader",
+        "LOG_SUBSCRIPTION_HTTP_DOWNLOADER",
+        ZooLogOption::SubscriptionHTTPDownloader,
+    ),
+    ("crypto_identity", "LOG_CRYPTO_IDENTITY", ZooLogOption::CryptoIdentity),
+    ("api", "LOG_API", ZooLogOption::Api),
+    ("ws_api", "LOG_WS_API", ZooLogOption::WsAPI),
+    ("detailed_api", "LOG_DETAILED_API", ZooLogOption::DetailedAPI),
+    ("node", "LOG_NODE", ZooLogOption::Node),
+    ("internal_api", "LOG_INTERNAL_API", ZooLogOption::InternalAPI),
+    ("internal_network", "LOG_INTERNAL_NETWORK", ZooLogOption::Network),
+    ("tests", "LOG_TESTS", ZooLogOption::Tests),
+    ("job_execution", "LOG_JOB_EXECUTION", ZooLogOption::JobExecution),
+    ("cron_execution", "LOG_CRON_EXECUTION", ZooLogOption::CronExecution),
+];
+
+/// A problem with a setting. `key` is the path of the setting in the config file (`api.port`,
+/// `llm_providers[1].model`) or the environment variable it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        ConfigIssue {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.key, self.message)
+        }
+    }
+}
+
+pub fn format_config_issues(issues: &[ConfigIssue]) -> String {
+    issues
+        .iter()
+        .map(|issue| format!("  - {}", issue))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeSection {
+    /// `GLOBAL_IDENTITY_NAME`
+    pub global_identity_name: String,
+    /// `NODE_IP`
+    pub ip: IpAddr,
+    /// `NODE_PORT`
+    pub port: u16,
+    /// `PING_INTERVAL_SECS`
+    pub ping_interval_secs: u64,
+    /// `NODE_STORAGE_PATH`
+    pub storage_path: String,
+    /// `PROXY_IDENTITY`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_identity: Option<String>,
+    /// `FIRST_DEVICE_NEEDS_REGISTRATION_CODE`
+    pub first_device_needs_registration_code: bool,
+    /// `NO_SECRET_FILE`
+    pub no_secrets_file: bool,
+    /// `AUTO_DETECT_LOCAL_LLMS`
+    pub auto_detect_local_llms: bool,
+}
+
+impl Default for NodeSection {
+    fn default() -> Self {
+        NodeSection {
+            global_identity_name: DEFAULT_GLOBAL_IDENTITY_NAME.to_string(),
+            ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 9552,
+            ping_interval_secs: 10,
+            storage_path: "storage".to_string(),
+            proxy_identity: None,
+            first_device_needs_registration_code: true,
+            no_secrets_file: false,
+            auto_detect_local_llms: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApiSection {
+    /// `NODE_API_IP`
+    pub ip: IpAddr,
+    /// `NODE_API_PORT`
+    pub port: u16,
+    /// `NODE_API_HTTPS_PORT`
+    pub https_port: u16,
+    /// `NODE_WS_PORT`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ws_port: Option<u16>,
+    /// `API_V2_KEY`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+}
+
+impl Default for ApiSection {
+    fn default() -> Self {
+        ApiSection {
+            ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 9550,
+            https_port: 9553,
+            ws_port: None,
+            api_key: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmbeddingsSection {
+    /// `EMBEDDINGS_SERVER_URL`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_url: Option<String>,
+    /// `EMBEDDINGS_SERVER_API_KEY`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_api_key: Option<String>,
+    /// `DEFAULT_EMBEDDING_MODEL`
+    pub default_model: String,
+    /// `SUPPORTED_EMBEDDING_MODELS`, comma separated
+    pub supported_models: Vec<String>,
+}
+
+impl Default for EmbeddingsSection {
+    fn default() -> Self {
+        let default_model = OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM.to_string();
+        EmbeddingsSection {
+            server_url: None,
+            server_api_key: None,
+            supported_models: vec![default_model.clone()],
+            default_model,
+        }
+    }
+}
+
+/// Reloaded while the node runs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LoggingSection {
+    /// `LOG_ALL`
+    pub all: bool,
+    /// `LOG_SIMPLE`
+    pub simple: bool,
+    /// `LOG_NODE`, `LOG_API`, ... as `node`, `api`, ...
+    pub options: Vec<String>,
+}
+
+/// An LLM provider added to the main profile. Replaces the `INITIAL_AGENT_NAMES`,
+/// `INITIAL_AGENT_URLS`, `INITIAL_AGENT_MODELS` and `INITIAL_AGENT_API_KEYS` lists.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LlmProviderConfig {
+    pub name: String,
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl LlmProviderConfig {
+    pub fn to_llm_provider(&self, global_identity: &str) -> Result<SerializedLLMProvider, String> {
+        let model = LLMProviderInterface::from_str(&self.model)
+            .map_err(|_| format!("unknown model `{}`, expected e.g. `openai:gpt-4o-mini`", self.model))?;
+        let full_identity_name = ZooName::new(format!("{}/main/agent/{}", global_identity, self.name))
+            .map_err(|e| format!("`{}` can't be used as a provider name: {}", self.name, e))?;
+        Ok(SerializedLLMProvider {
+            id: self.name.clone(),
+            name: Some(self.name.clone()),
+            description: Some(self.description.clone().unwrap_or_else(|| self.name.clone())),
+            full_identity_name,
+            external_url: self.url.clone(),
+            api_key: self.api_key.clone(),
+            model,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct NodeConfig {
+    pub node: NodeSection,
+    pub api: ApiSection,
+    pub embeddings: EmbeddingsSection,
+    pub logging: LoggingSection,
+    /// Stored as node preferences on start and on reload.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub preferences: BTreeMap<String, toml::Value>,
+    /// Added to the main profile when it's created. Reloading the file adds the new ones and
+    /// updates the changed ones.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub llm_providers: Vec<LlmProviderConfig>,
+}
+
+impl NodeConfig {
+    /// Parses a config file. All the problems are reported at once; a setting with a problem
+    /// keeps its default value.
+    pub fn from_toml_str(contents: &str) -> (NodeConfig, Vec<ConfigIssue>) {
+        let mut config = NodeConfig::default();
+        let mut issues = Vec::new();
+        let mut root = match contents.parse::<toml::Table>() {
+            Ok(root) => root,
+            Err(e) => {
+                issues.push(ConfigIssue::new("", e.to_string().trim().to_string()));
+                return (config, issues);
+            }
+        };
+
+        if let Some(mut section) = Section::take(&mut root, "node", &mut issues) {
+            let node = &mut config.node;
+            section.read("global_identity_name", &mut node.global_identity_name);
+            section.read("ip", &mut node.ip);
+            section.read("port", &mut node.port);
+            section.read("ping_interval_secs", &mut node.ping_interval_secs);
+            section.read("storage_path", &mut node.storage_path);
+            section.read("proxy_identity", &mut node.proxy_identity);
+            section.read(
+                "first_device_needs_registration_code",
+                &mut node.first_device_needs_registration_code,
+            );
+            section.read("no_secrets_file", &mut node.no_secrets_file);
+            section.read("auto_detect_local_llms", &mut node.auto_detect_local_llms);
+            section.finish();
+        }
+
+        if let Some(mut section) = Section::take(&mut root, "api", &mut issues) {
+            let api = &mut config.api;
+            section.read("ip", &mut api.ip);
+            section.read("port", &mut api.port);
+            section.read("https_port", &mut api.https_port);
+            section.read("ws_port", &mut api.ws_port);
+            section.read("api_key", &mut api.api_key);
+            section.finish();
+        }
+
+        if let Some(mut section) = Section::take(&mut root, "embeddings", &mut issues) {
+            let embeddings = &mut config.embeddings;
+            section.read("server_url", &mut embeddings.server_url);
+            section.read("server_api_key", &mut embeddings.server_api_key);
+            section.read("default_model", &mut embeddings.default_model);
+            section.read("supported_models", &mut embeddings.supported_models);
+            section.finish();
+        }
+
+        if let Some(mut section) = Section::take(&mut root, "logging", &mut issues) {
+            let logging = &mut config.logging;
+            section.read("all", &mut logging.all);
+            section.read("simple", &mut logging.simple);
+            section.read("options", &mut logging.options);
+            section.finish();
+        }
+
+        match root.remove("preferences") {
+            Some(toml::Value::Table(preferences)) => config.preferences = preferences.into_iter().collect(),
+            Some(other) => issues.push(ConfigIssue::new(
+                "preferences",
+                format!("expected a table, found {}", other.type_str()),
+            )),
+            None => {}
+        }
+
+        match root.remove("llm_providers") {
+            Some(toml::Value::Array(entries)) => {
+                for (i, entry) in entries.into_iter().enumerate() {
+                    let key = format!("llm_providers[{}]", i);
+                    let table = match entry {
+                        toml::Value::Table(table) => table,
+                        other => {
+                            issues.push(ConfigIssue::new(
+                                key,
+                                format!("expected a table, found {}", other.type_str()),
+                            ));
+                            continue;
+                        }
+                    };
+                    // Missing names and models are reported by `validate`
+                    let mut section = Section {
+                        name: key,
+                        table,
+                        issues: &mut issues,
+                    };
+                    let mut provider = LlmProviderConfig::default();
+                    section.read("name", &mut provider.name);
+                    section.read("model", &mut provider.model);
+                    section.read("url", &mut provider.url);
+                    section.read("api_key", &mut provider.api_key);
+                    section.read("description", &mut provider.description);
+                    section.finish();
+                    config.llm_providers.push(provider);
+                }
+            }
+            Some(other) => issues.push(ConfigIssue::new(
+                "llm_providers",
+                format!(
+                    "expected an array of tables ([[llm_providers]]), found {}",
+                    other.type_str()
+                ),
+            )),
+            None => {}
+        }
+
+        for key in root.keys() {
+            issues.push(ConfigIssue::new(key.clone(), "unknown setting"));
+        }
+
+        (config, issues)
+    }
+
+    /// Overrides the settings with the environment variables that are set. Empty variables count
+    /// as not set.
+    pub fn apply_env_overrides(&mut self, issues: &mut Vec<ConfigIssue>) {
+        let node = &mut self.node;
+        if let Some(name) = env_value("GLOBAL_IDENTITY_NAME", issues) {
+            node.global_identity_name = name;
+        }
+        if let Some(ip) = env_value("NODE_IP", issues) {
+            node.ip = ip;
+        }
+        if let Some(port) = env_value("NODE_PORT", issues) {
+            node.port = port;
+        }
+        if let Some(interval) = env_value("PING_INTERVAL_SECS", issues) {
+            node.ping_interval_secs = interval;
+        }
+        if let Some(path) = env_value("NODE_STORAGE_PATH", issues) {
+            node.storage_path = path;
+        }
+        if let Some(proxy) = env_value("PROXY_IDENTITY", issues) {
+            node.proxy_identity = Some(proxy);
+        }
+        if let Some(needs_code) = env_value("FIRST_DEVICE_NEEDS_REGISTRATION_CODE", issues) {
+            node.first_device_needs_registration_code = needs_code;
+        }
+        if let Some(no_secrets_file) = env_value("NO_SECRET_FILE", issues) {
+            node.no_secrets_file = no_secrets_file;
+        }
+        if let Some(auto_detect) = env_value("AUTO_DETECT_LOCAL_LLMS", issues) {
+            node.auto_detect_local_llms = auto_detect;
+        }
+
+        let api = &mut self.api;
+        if let Some(ip) = env_value("NODE_API_IP", issues) {
+            api.ip = ip;
+        }
+        if let Some(port) = env_value("NODE_API_PORT", issues) {
+            api.port = port;
+        }
+        if let Some(port) = env_value("NODE_API_HTTPS_PORT", issues) {
+            api.https_port = port;
+        }
+        if let Some(port) = env_value("NODE_WS_PORT", issues) {
+            api.ws_port = Some(port);
+        }
+        if let Some(key) = env_value("API_V2_KEY", issues) {
+            api.api_key = Some(key);
+        }
+
+        let embeddings = &mut self.embeddings;
+        if let Some(url) = env_value("EMBEDDINGS_SERVER_URL", issues) {
+            embeddings.server_url = Some(url);
+        }
+        if let Some(key) = env_value("EMBEDDINGS_SERVER_API_KEY", issues) {
+            embeddings.server_api_key = Some(key);
+        }
+        if let Some(model) = env_value("DEFAULT_EMBEDDING_MODEL", issues) {
+            embeddings.default_model = model;
+        }
+        if let Some(models) = env_value::<String>("SUPPORTED_EMBEDDING_MODELS", issues) {
+            embeddings.supported_models = split_list(&models).into_iter().filter(|m| !m.is_empty()).collect();
+        }
+
+        let log_var_set = |var: &str| env::var(var).is_ok();
+        self.logging.all |= log_var_set("LOG_ALL");
+        self.logging.simple |= log_var_set("LOG_SIMPLE");
+        for (option, var, _) in LOG_OPTIONS {
+            if log_var_set(var) && !self.logging.options.iter().any(|o| o == option) {
+                self.logging.options.push(option.to_string());
+            }
+        }
+
+        if let Some(providers) = env_llm_providers(issues) {
+            self.llm_providers = providers;
+        }
+    }
+
+    /// Checks the settings that parsed but can't be used together or aren't known values.
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+
+        let identity_is_valid = ZooName::new(self.node.global_identity_name.clone()).is_ok();
+        if !identity_is_valid {
+            issues.push(ConfigIssue::new(
+                "node.global_identity_name",
+                format!("`{}` is not a valid identity name", self.node.global_identity_name),
+            ));
+        }
+
+        let mut listeners = vec![
+            ("node.port", SocketAddr::new(self.node.ip, self.node.port)),
+            ("api.port", SocketAddr::new(self.api.ip, self.api.port)),
+            ("api.https_port", SocketAddr::new(self.api.ip, self.api.https_port)),
+        ];
+        if let Some(ws_port) = self.api.ws_port {
+            // The WebSocket server listens on the node IP
+            listeners.push(("api.ws_port", SocketAddr::new(self.node.ip, ws_port)));
+        }
+        for (i, (key, address)) in listeners.iter().enumerate() {
+            if let Some((other_key, _)) = listeners[..i].iter().find(|(_, other)| other == address) {
+                issues.push(ConfigIssue::new(
+                    *key,
+                    format!("{} is already used by {}", address, other_key),
+                ));
+            }
+        }
+
+        if EmbeddingModelType::from_string(&self.embeddings.default_model).is_err() {
+            issues.push(ConfigIssue::new(
+                "embeddings.default_model",
+                format!("unknown embedding model `{}`", self.embeddings.default_model),
+            ));
+        }
+        if self.embeddings.supported_models.is_empty() {
+            issues.push(ConfigIssue::new(
+                "embeddings.supported_models",
+                "at least one model is required",
+            ));
+        }
+        for (i, model) in self.embeddings.supported_models.iter().enumerate() {
+            if EmbeddingModelType::from_string(model).is_err() {
+                issues.push(ConfigIssue::new(
+                    format!("embeddings.supported_models[{}]", i),
+                    format!("unknown embedding model `{}`", model),
+                ));
+            }
+        }
+
+        for (i, option) in self.logging.options.iter().enumerate() {
+            if !LOG_OPTIONS.iter().any(|(name, _, _)| name == option) {
+                let known: Vec<&str> = LOG_OPTIONS.iter().map(|(name, _, _)| *name).collect();
+                issues.push(ConfigIssue::new(
+                    format!("logging.options[{}]", i),
+                    format!("unknown log option `{}`, expected one of {}", option, known.join(", ")),
+                ));
+            }
+        }
+
+        for (i, provider) in self.llm_providers.iter().enumerate() {
+            let key = format!("llm_providers[{}]", i);
+            if provider.name.is_empty() {
+                issues.push(ConfigIssue::new(format!("{}.name", key), "is required"));
+            }
+            if provider.model.is_empty() {
+                issues.push(ConfigIssue::new(format!("{}.model", key), "is required"));
+            }
+            if provider.name.is_empty() || provider.model.is_empty() {
+                continue;
+            }
+            if self.llm_providers[..i].iter().any(|other| other.name == provider.name) {
+                issues.push(ConfigIssue::new(
+                    format!("{}.name", key),
+                    format!("`{}` is used by another provider", provider.name),
+                ));
+            }
+            if LLMProviderInterface::from_str(&provider.model).is_err() {
+                issues.push(ConfigIssue::new(
+                    format!("{}.model", key),
+                    format!("unknown model `{}`, expected e.g. `openai:gpt-4o-mini`", provider.model),
+                ));
+            } else if identity_is_valid {
+                if let Err(e) = provider.to_llm_provider(&self.node.global_identity_name) {
+                    issues.push(ConfigIssue::new(format!("{}.name", key), e));
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// The environment the node runs with. Unknown embedding models, which `validate` reports, are
+    /// left out.
+    pub fn node_environment(&self) -> NodeEnvironment {
+        let default_embedding_model = EmbeddingModelType::from_string(&self.embeddings.default_model).unwrap_or(
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM),
+        );
+
+        NodeEnvironment {
+            global_identity_name: self.node.global_identity_name.clone(),
+            listen_address: SocketAddr::new(self.node.ip, self.node.port),
+            api_listen_address: SocketAddr::new(self.api.ip, self.api.port),
+            api_https_listen_address: SocketAddr::new(self.api.ip, self.api.https_port),
+            ws_address: self.api.ws_port.map(|port| SocketAddr::new(self.node.ip, port)),
+            ping_interval: self.node.ping_interval_secs,
+            first_device_needs_registration_code: self.node.first_device_needs_registration_code,
+            no_secrets_file: self.node.no_secrets_file,
+            node_storage_path: Some(self.node.storage_path.clone()),
+            embeddings_server_url: self.embeddings.server_url.clone(),
+            embeddings_server_api_key: self.embeddings.server_api_key.clone(),
+            _auto_detect_local_llms: self.node.auto_detect_local_llms,
+            proxy_identity: self.node.proxy_identity.clone(),
+            default_embedding_model,
+            supported_embedding_models: self
+                .embeddings
+                .supported_models
+                .iter()
+                .filter_map(|model| EmbeddingModelType::from_string(model).ok())
+                .collect(),
+            api_v2_key: self.api.api_key.clone(),
+        }
+    }
+
+    pub fn llm_providers(&self, global_identity: &str) -> Result<Vec<SerializedLLMProvider>, ConfigIssue> {
+        self.llm_providers
+            .iter()
+            .enumerate()
+            .map(|(i, provider)| {
+                provider
+                    .to_llm_provider(global_identity)
+                    .map_err(|e| ConfigIssue::new(format!("llm_providers[{}]", i), e))
+            })
+            .collect()
+    }
+
+    /// A copy with the API keys hidden, for printing. Secret references are kept since they
+    /// don't contain the secret.
+    pub fn redacted(&self) -> NodeConfig {
+        let redact = |value: &mut Option<String>| {
+            if let Some(value) = value {
+                if !value.is_empty() && secret_reference_name(value).is_none() {
+                    *value = REDACTED.to_string();
+                }
+            }
+        };
+
+        let mut config = self.clone();
+        redact(&mut config.api.api_key);
+        redact(&mut config.embeddings.server_api_key);
+        for provider in config.llm_providers.iter_mut() {
+            redact(&mut provider.api_key);
+        }
+        config
+    }
+}
+
+/// Reads the settings of one table of the config file.
+struct Section<'a> {
+    name: String,
+    table: toml::Table,
+    issues: &'a mut Vec<ConfigIssue>,
+}
+
+impl<'a> Section<'a> {
+    fn take(root: &mut toml::Table, name: &str, issues: &'a mut Vec<ConfigIssue>) -> Option<Self> {
+        match root.remove(name)? {
+            toml::Value::Table(table) => Some(Section {
+                name: name.to_string(),
+                table,
+                issues,
+            }),
+            other => {
+                issues.push(ConfigIssue::new(
+                    name,
+                    format!("expected a table, found {}", other.type_str()),
+                ));
+                None
+            }
+        }
+    }
+
+    /// Sets `target` if the key is there and has the right type.
+    fn read<T: DeserializeOwned>(&mut self, key: &str, target: &mut T) {
+        let Some(value) = self.table.remove(key) else {
+            return;
+        };
+        match value.try_into::<T>() {
+            Ok(value) => *target = value,
+            Err(e) => self.issues.push(ConfigIssue::new(
+                format!("{}.{}", self.name, key),
+                e.message().to_string(),
+            )),
+        }
+    }
+
+    /// Reports the keys nothing read.
+    fn finish(self) {
+        for key in self.table.keys() {
+            self.issues
+                .push(ConfigIssue::new(format!("{}.{}", self.name, key), "unknown setting"));
+        }
+    }
+}
+
+fn env_value<T: FromStr>(var: &str, issues: &mut Vec<ConfigIssue>) -> Option<T>
+where
+    T::Err: fmt::Display,
+{
+    let value = env::var(var).ok().filter(|value| !value.is_empty())?;
+    match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            issues.push(ConfigIssue::new(var, format!("invalid value `{}`: {}", value, e)));
+            None
+        }
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value.split(',').map(|s| s.trim().to_string()).collect()
+}
+
+/// The providers of the `INITIAL_AGENT_*` (or `INITIAL_LLM_PROVIDER_*`) lists, if they are set.
+fn env_llm_providers(issues: &mut Vec<ConfigIssue>) -> Option<Vec<LlmProviderConfig>> {
+    let list = |var: &str, alias: &str| {
+        env::var(var)
+            .or_else(|_| env::var(alias))
+            .map(|value| split_list(&value))
+            .unwrap_or_default()
+    };
+
+    let names: Vec<String> = list("INITIAL_AGENT_NAMES", "INITIAL_LLM_PROVIDER_NAMES")
+        .into_iter()
+        .filter(|name| !name.is_empty())
+        .collect();
+    if names.is_empty() {
+        return None;
+    }
+    let urls = list("INITIAL_AGENT_URLS", "INITIAL_LLM_PROVIDER_URLS");
+    let models = list("INITIAL_AGENT_MODELS", "INITIAL_LLM_PROVIDER_MODELS");
+    let api_keys = list("INITIAL_AGENT_API_KEYS", "INITIAL_LLM_PROVIDER_API_KEYS");
+
+    let mut lengths_match = true;
+    for (var, values) in [
+        ("INITIAL_AGENT_URLS", &urls),
+        ("INITIAL_AGENT_MODELS", &models),
+        ("INITIAL_AGENT_API_KEYS", &api_keys),
+    ] {
+        if values.len() != names.len() {
+            issues.push(ConfigIssue::new(
+                var,
+                format!(
+                    "has {} entries but INITIAL_AGENT_NAMES has {}; consider [[llm_providers]] tables in {} instead",
+                    values.len(),
+                    names.len(),
+                    CONFIG_FILE_NAME
+                ),
+            ));
+            lengths_match = false;
+        }
+    }
+    if !lengths_match {
+        return None;
+    }
+
+    Some(
+        names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| LlmProviderConfig {
+                name,
+                model: models[i].clone(),
+                url: Some(urls[i].clone()),
+                api_key: Some(api_keys[i].clone()),
+                description: None,
+            })
+            .collect(),
+    )
+}
+
+/// The config file as parsed, and the config the node runs with: the file with the environment
+/// overrides applied. Both are only computed when the file is loaded.
+struct ConfigFileState {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    /// The config file as read, without the environment overrides.
+    file_config: NodeConfig,
+    effective: NodeConfig,
+    issues: Vec<ConfigIssue>,
+    environment: NodeEnvironment,
+}
+
+impl ConfigFileState {
+    fn load(path: Option<PathBuf>) -> Self {
+        let Some(path) = path else {
+            return ConfigFileState::new(None, None, NodeConfig::default(), Vec::new());
+        };
+
+        let modified = file_modified(&path);
+        let (config, issues) = match fs::read_to_string(&path) {
+            Ok(contents) => NodeConfig::from_toml_str(&contents),
+            Err(e) => (
+                NodeConfig::default(),
+                vec![ConfigIssue::new(
+                    path.display().to_string(),
+                    format!("failed to read the config file: {}", e),
+                )],
+            ),
+        };
+        ConfigFileState::new(Some(path), modified, config, issues)
+    }
+
+    fn new(
+        path: Option<PathBuf>,
+        modified: Option<SystemTime>,
+        file_config: NodeConfig,
+        mut issues: Vec<ConfigIssue>,
+    ) -> Self {
+        let mut config = file_config.clone();
+        config.apply_env_overrides(&mut issues);
+        issues.extend(config.validate());
+        ConfigFileState {
+            path,
+            modified,
+            file_config,
+            environment: config.node_environment(),
+            effective: config,
+            issues,
+        }
+    }
+}
+
+/// Set by `init_node_config`. Until then the environment isn't cached, so nodes started
+/// without it, as the tests do, see the variables they set.
+static CONFIG_INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+static CONFIG_FILE: Lazy<RwLock<ConfigFileState>> =
+    Lazy::new(|| RwLock::new(ConfigFileState::load(default_config_path())));
+
+/// `ZOO_NODE_CONFIG`, or `zoo-node.toml` in the working directory if there is one.
+fn default_config_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var(CONFIG_PATH_ENV) {
+        if !path.is_empty() {
+            return Some(PathBuf::from(path));
+        }
+    }
+    let path = PathBuf::from(CONFIG_FILE_NAME);
+    path.exists().then_some(path)
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Loads the config file passed with `--config`, or the default one. Called on start, before
+/// anything reads the node environment.
+pub fn init_node_config(path: Option<&str>) {
+    let path = path.map(PathBuf::from).or_else(default_config_path);
+    *CONFIG_FILE.write().unwrap() = ConfigFileState::load(path);
+    CONFIG_INITIALIZED.store(true, Ordering::SeqCst);
+}
+
+pub fn config_file_path() -> Option<PathBuf> {
+    CONFIG_FILE.read().unwrap().path.clone()
+}
+
+/// The config the node runs with, the config file with the environment overrides applied, and
+/// every problem found in either.
+pub fn effective_node_config() -> (NodeConfig, Vec<ConfigIssue>) {
+    let state = CONFIG_FILE.read().unwrap();
+    (state.effective.clone(), state.issues.clone())
+}
+
+/// The environment of the config the node runs with. The node checks the config when it starts
+/// and a reload with problems is ignored, so this doesn't validate it again.
+pub fn current_node_environment() -> NodeEnvironment {
+    let state = CONFIG_FILE.read().unwrap();
+    if CONFIG_INITIALIZED.load(Ordering::SeqCst) {
+        return state.environment.clone();
+    }
+    let mut config = state.file_config.clone();
+    config.apply_env_overrides(&mut Vec::new());
+    config.node_environment()
+}
+
+/// Runs `--check-config` and `--print-config`. The config goes to stdout and everything else to
+/// stderr, so the output of `--print-config` can be saved as a config file. Returns the exit code.
+pub fn run_config_command(print_config: bool) -> i32 {
+    let (config, issues) = effective_node_config();
+    let source = config_file_path()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| "no config file".to_string());
+
+    if print_config {
+        match toml::to_string_pretty(&config.redacted()) {
+            Ok(contents) => println!("{}", contents),
+            Err(e) => eprintln!("Failed to print the config: {}", e),
+        }
+    }
+
+    if issues.is_empty() {
+        eprintln!("Configuration OK ({}, with environment overrides)", source);
+        0
+    } else {
+        eprintln!("Found {} problem(s) in the configuration ({}):", issues.len(), source);
+        eprintln!("{}", format_config_issues(&issues));
+        1
+    }
+}
+
+/// Enables the `[logging]` settings in `zoo_log`. The `LOG_*` environment variables keep working
+/// on their own.
+pub fn apply_logging_config(logging: &LoggingSection) {
+    set_log_config(ZooLogConfig {
+        all: logging.all,
+        simple: logging.simple,
+        options: LOG_OPTIONS
+            .iter()
+            .filter(|(option, _, _)| logging.options.iter().any(|o| o == option))
+            .map(|(_, _, log_option)| *log_option)
+            .collect(),
+    });
+}
+
+/// A change of the config file picked up by `reload_node_config`.
+pub struct ConfigReload {
+    pub previous: NodeConfig,
+    pub current: NodeConfig,
+}
+
+impl ConfigReload {
+    /// The sections that changed but are only read when the node starts.
+    pub fn restart_required(&self) -> Vec<&'static str> {
+        let mut sections = Vec::new();
+        if self.previous.node != self.current.node {
+            sections.push("node");
+        }
+        if self.previous.api != self.current.api {
+            sections.push("api");
+        }
+        if self.previous.embeddings != self.current.embeddings {
+            sections.push("embeddings");
+        }
+        sections
+    }
+
+    pub fn changed_preferences(&self) -> Vec<(&String, &toml::Value)> {
+        self.current
+            .preferences
+            .iter()
+            .filter(|(key, value)| self.previous.preferences.get(*key) != Some(*value))
+            .collect()
+    }
+
+    pub fn changed_llm_providers(&self) -> Vec<&LlmProviderConfig> {
+        self.current
+            .llm_providers
+            .iter()
+            .filter(|provider| !self.previous.llm_providers.contains(provider))
+            .collect()
+    }
+}
+
+/// Reads the config file again if it changed since it was loaded. `None` if it didn't change. If
+/// the new contents have problems they are returned and the node keeps the previous config.
+pub fn reload_node_config() -> Option<Result<ConfigReload, Vec<ConfigIssue>>> {
+    let (path, modified) = {
+        let state = CONFIG_FILE.read().unwrap();
+        (state.path.clone()?, state.modified)
+    };
+    if file_modified(&path) == modified {
+        return None;
+    }
+
+    let (previous, _) = effective_node_config();
+    let state = ConfigFileState::load(Some(path));
+    if !state.issues.is_empty() {
+        // Remember the change so the problems are reported once
+        CONFIG_FILE.write().unwrap().modified = state.modified;
+        return Some(Err(state.issues));
+    }
+    let current = state.effective.clone();
+    *CONFIG_FILE.write().unwrap() = state;
+    Some(Ok(ConfigReload { previous, current }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config_file() {
+        let (config, issues) = NodeConfig::from_toml_str(
+            r#"
+            [node]
+            global_identity_name = "@@my_node.sep-zoo"
+            port = 9652
+
+            [api]
+            port = 9650
+            ws_port = 9651
+
+            [logging]
+            options = ["node", "api"]
+
+            [preferences]
+            max_iterations = 5
+
+            [[llm_providers]]
+            name = "my_gpt"
+            model = "openai:gpt-4o-mini"
+            url = "https://api.openai.com"
+            api_key = "secret://openai"
+            "#,
+        );
+        assert!(issues.is_empty(), "{:?}", issues);
+        assert_eq!(config.node.global_identity_name, "@@my_node.sep-zoo");
+        assert_eq!(config.node.port, 9652);
+        assert_eq!(config.node.ping_interval_secs, 10);
+        assert_eq!(config.api.ws_port, Some(9651));
+        assert_eq!(config.preferences.get("max_iterations"), Some(&toml::Value::Integer(5)));
+        assert!(config.validate().is_empty());
+
+        let providers = config.llm_providers(&config.node.global_identity_name).unwrap();
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].id, "my_gpt");
+        assert_eq!(
+            providers[0].full_identity_name.full_name,
+            "@@my_node.sep-zoo/main/agent/my_gpt"
+        );
+
+        let env = config.node_environment();
+        assert_eq!(env.listen_address.port(), 9652);
+        assert_eq!(env.ws_address.map(|address| address.port()), Some(9651));
+    }
+
+    #[test]
+    fn test_reports_every_issue() {
+        let (config, mut issues) = NodeConfig::from_toml_str(
+            r#"
+            color = "blue"
+
+            [node]
+            port = "not a port"
+            prot = 9552
+
+            [api]
+            port = 70000
+
+            [logging]
+            options = ["nodes"]
+
+            [[llm_providers]]
+            name = "no_model"
+
+            [[llm_providers]]
+            name = "bad_model"
+            model = "not-a-provider:model"
+            "#,
+        );
+        issues.extend(config.validate());
+
+        let keys: Vec<&str> = issues.iter().map(|issue| issue.key.as_str()).collect();
+        for key in [
+            "node.port",
+            "node.prot",
+            "api.port",
+            "color",
+            "logging.options[0]",
+            "llm_providers[0].model",
+            "llm_providers[1].model",
+        ] {
+            assert!(keys.contains(&key), "missing issue for {}: {:?}", key, keys);
+        }
+        // The invalid values keep their defaults
+        assert_eq!(config.node.port, 9552);
+        assert_eq!(config.api.port, 9550);
+    }
+
+    #[test]
+    fn test_conflicting_ports() {
+        let mut config = NodeConfig::default();
+        config.api.port = config.node.port;
+        let issues = config.validate();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].key, "api.port");
+    }
+
+    #[test]
+    fn test_redacted() {
+        let mut config = NodeConfig::default();
+        config.api.api_key = Some("my-api-key".to_string());
+        config.llm_providers = vec![
+            LlmProviderConfig {
+                name: "a".to_string(),
+                model: "openai:gpt-4o-mini".to_string(),
+                api_key: Some("sk-1234".to_string()),
+                ..Default::default()
+            },
+            LlmProviderConfig {
+                name: "b".to_string(),
+                model: "openai:gpt-4o-mini".to_string(),
+                api_key: Some("secret://openai".to_string()),
+                ..Default::default()
+            },
+        ];
+
+        let printed = toml::to_string_pretty(&config.redacted()).unwrap();
+        assert!(!printed.contains("my-api-key"));
+        assert!(!printed.contains("sk-1234"));
+        assert!(printed.contains("secret://openai"));
+        assert!(printed.contains("[[llm_providers]]"));
+    }
+}
@@ -210,7 +210,8 @@ fn micropayment_flow_test() {
             supported_embedding_models(),
             Some(api_v2_key.to_string()),
         )
-        .await;
+        .await
+        .unwrap();
 
         let addr2 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 12001);
         let node2 = Node::new(
@@ -233,7 +234,8 @@ fn micropayment_flow_test() {
             supported_embedding_models(),
             Some(api_v2_key.to_string()),
         )
-        .await;
+        .await
+        .unwrap();
 
         // Printing
         eprintln!(
@@ -449,6 +451,7 @@ fn micropayment_flow_test() {
                 config: Some(vec![]),
                 usage_type: None,
                 tool_offering: Some(zoo_tool_offering.clone()),
+                permissions: None,
             };
 
             {
@@ -80,7 +80,7 @@ fn change_nodes_name_test() {
         );
 
         let node1_handler = tokio::spawn(async move {
-            let _ = node1.await.lock().await.start().await;
+            let _ = node1.await.unwrap().lock().await.start().await;
         });
 
         let abort_handler = node1_handler.abort_handle();
@@ -189,7 +189,7 @@ fn change_nodes_name_test() {
         );
 
         let node1_handler = tokio::spawn(async move {
-            let _ = node1.await.lock().await.start().await;
+            let _ = node1.await.unwrap().lock().await.start().await;
         });
 
         let abort_handler = node1_handler.abort_handle();
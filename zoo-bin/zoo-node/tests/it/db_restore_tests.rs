@@ -77,7 +77,7 @@ mod tests {
                     ZooLogLevel::Debug,
                     &format!("Starting Node 1"),
                 );
-                let _ = node1.await.lock().await.start().await;
+                let _ = node1.await.unwrap().lock().await.start().await;
             });
             let abort_handler = node1_handler.abort_handle();
 
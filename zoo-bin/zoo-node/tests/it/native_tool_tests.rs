@@ -155,7 +155,7 @@ fn native_tool_test_knowledge() {
 
         let node1_handler = tokio::spawn(async move {
             zoo_log(ZooLogOption::Tests, ZooLogLevel::Debug, "Starting Node 1");
-            let _ = node1.await.lock().await.start().await;
+            let _ = node1.await.unwrap().lock().await.start().await;
         });
 
         let abort_handler = node1_handler.abort_handle();
@@ -114,7 +114,8 @@ fn subidentity_registration() {
             supported_embedding_models(),
             Some("debug".to_string()),
         )
-        .await;
+        .await
+        .unwrap();
 
         let addr2 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 12007);
         let node2 = Node::new(
@@ -137,7 +138,8 @@ fn subidentity_registration() {
             supported_embedding_models(),
             Some("debug".to_string()),
         )
-        .await;
+        .await
+        .unwrap();
 
         // Printing
         eprintln!(
@@ -735,7 +737,8 @@ fn test_relay_server_communication() {
             supported_embedding_models(),
             Some("debug".to_string()),
         )
-        .await;
+        .await
+        .unwrap();
 
         let node2 = Node::new(
             node2_identity_name.to_string(),
@@ -757,7 +760,8 @@ fn test_relay_server_communication() {
             supported_embedding_models(),
             Some("debug".to_string()),
         )
-        .await;
+        .await
+        .unwrap();
 
         eprintln!(">> Starting relay test with real identities and relay server");
         
@@ -1110,7 +1114,8 @@ fn test_localhost_relay_server_communication() {
             supported_embedding_models(),
             Some("debug".to_string()),
         )
-        .await;
+        .await
+        .unwrap();
 
         let node2 = Node::new(
             node2_identity_name.to_string(),
@@ -1132,7 +1137,8 @@ fn test_localhost_relay_server_communication() {
             supported_embedding_models(),
             Some("debug".to_string()),
         )
-        .await;
+        .await
+        .unwrap();
 
         eprintln!(">> Starting relay test with real identities and relay server");
         
@@ -1458,7 +1464,8 @@ fn test_send_message_to_localhost_node() {
             supported_embedding_models(),
             Some("debug".to_string()),
         )
-        .await;
+        .await
+        .unwrap();
 
         let node2 = Node::new(
             node2_identity_name.to_string(),
@@ -1480,7 +1487,8 @@ fn test_send_message_to_localhost_node() {
             supported_embedding_models(),
             Some("debug".to_string()),
         )
-        .await;
+        .await
+        .unwrap();
 
         eprintln!(">> Starting relay test with real identities and relay server");
         
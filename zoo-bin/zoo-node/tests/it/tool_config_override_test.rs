@@ -127,7 +127,7 @@ fn test_tool_execution_with_config_override() {
 
         let node1_handler = tokio::spawn(async move {
             zoo_log(ZooLogOption::Tests, ZooLogLevel::Debug, "Starting Node 1");
-            let _ = node1.await.lock().await.start().await;
+            let _ = node1.await.unwrap().lock().await.start().await;
         });
 
         let abort_handler = node1_handler.abort_handle();
@@ -269,7 +269,8 @@ fn test_tool_execution_with_config_override() {
                         assets: None,
                         runner: RunnerType::Any,
                         operating_system: vec![OperatingSystem::Linux, OperatingSystem::MacOS, OperatingSystem::Windows],
-                        tool_set: Some("".to_string())
+                        tool_set: Some("".to_string()),
+                        permissions: None,
                       }, true),
                     assets: None,
                 },
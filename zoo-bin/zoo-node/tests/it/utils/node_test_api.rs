@@ -652,6 +652,7 @@ pub async fn api_execute_tool(
             llm_provider,
             extra_config,
             mounts,
+            max_price: None,
             res: res_sender,
         })
         .await
@@ -58,15 +58,33 @@ impl OllamaTextEmbeddingsInference {
     const SNOWFLAKE_ARCTIC_EMBED_M: &'static str = "snowflake-arctic-embed:xs";
     const JINA_EMBEDDINGS_V2_BASE_ES: &'static str = "jina/jina-embeddings-v2-base-es:latest";
 
+    /// Ollama models that are not first-class variants but whose vector size is known,
+    /// keyed by model name without the tag (e.g. `nomic-embed-text` for `nomic-embed-text:latest`).
+    const OTHER_MODEL_DIMENSIONS: &'static [(&'static str, usize)] = &[
+        ("nomic-embed-text", 768),
+        ("mxbai-embed-large", 1024),
+        ("bge-m3", 1024),
+        ("snowflake-arctic-embed2", 1024),
+    ];
+
     pub fn from_string(s: &str) -> Result<Self, ZooEmbeddingError> {
         match s {
             Self::ALL_MINI_LML6V2 => Ok(Self::AllMiniLML6v2),
             Self::SNOWFLAKE_ARCTIC_EMBED_M => Ok(Self::SnowflakeArcticEmbedM),
             Self::JINA_EMBEDDINGS_V2_BASE_ES => Ok(Self::JinaEmbeddingsV2BaseEs),
+            _ if Self::other_model_dimensions(s).is_some() => Ok(Self::Other(s.to_string())),
             _ => Err(ZooEmbeddingError::InvalidModelArchitecture),
         }
     }
 
+    fn other_model_dimensions(name: &str) -> Option<usize> {
+        let base_name = name.split(':').next().unwrap_or(name);
+        Self::OTHER_MODEL_DIMENSIONS
+            .iter()
+            .find(|(model, _)| *model == base_name)
+            .map(|(_, dimensions)| *dimensions)
+    }
+
     pub fn max_input_token_count(&self) -> usize {
         match self {
             Self::JinaEmbeddingsV2BaseEs => 1024,
@@ -83,12 +101,11 @@ impl OllamaTextEmbeddingsInference {
 
     pub fn vector_dimensions(&self) -> Result<usize, ZooEmbeddingError> {
         match self {
+            Self::AllMiniLML6v2 => Ok(384),
             Self::SnowflakeArcticEmbedM => Ok(384),
             Self::JinaEmbeddingsV2BaseEs => Ok(768),
-            _ => Err(ZooEmbeddingError::UnimplementedModelDimensions(format!(
-                "{:?}",
-                self
-            ))),
+            Self::Other(name) => Self::other_model_dimensions(name)
+                .ok_or_else(|| ZooEmbeddingError::UnimplementedModelDimensions(format!("{:?}", self))),
         }
     }
 }
@@ -145,4 +162,28 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn test_parse_other_models_with_known_dimensions() {
+        let parsed_model = EmbeddingModelType::from_string("nomic-embed-text:latest").unwrap();
+        assert_eq!(
+            parsed_model,
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::Other(
+                "nomic-embed-text:latest".to_string()
+            ))
+        );
+        assert_eq!(parsed_model.vector_dimensions(), Ok(768));
+        assert_eq!(parsed_model.to_string(), "nomic-embed-text:latest");
+
+        let parsed_model = EmbeddingModelType::from_string("mxbai-embed-large").unwrap();
+        assert_eq!(parsed_model.vector_dimensions(), Ok(1024));
+    }
+
+    #[test]
+    fn test_unknown_models_are_rejected() {
+        assert!(EmbeddingModelType::from_string("some-unknown-model:latest").is_err());
+
+        let model = OllamaTextEmbeddingsInference::Other("some-unknown-model".to_string());
+        assert!(model.vector_dimensions().is_err());
+    }
 }
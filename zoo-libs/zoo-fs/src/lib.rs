@@ -1,5 +1,6 @@
 pub mod zoo_file_manager;
 pub mod zoo_file_manager_ops;
 pub mod zoo_fs_error;
+pub mod mcp_resources;
 pub mod simple_parser;
 pub mod test_utils;
@@ -0,0 +1,297 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::json;
+use zoo_message_primitives::schemas::mcp_resources::{McpResource, McpResourceContents, McpResourceUri};
+use zoo_message_primitives::schemas::zoo_fs::ParsedFile;
+use zoo_sqlite::SqliteManager;
+
+use crate::zoo_file_manager::ZooFileManager;
+use crate::zoo_fs_error::ZooFsError;
+
+const FOLDER_MIME_TYPE: &str = "application/json";
+
+impl ZooFileManager {
+    /// Lists every parsed file of the vector FS, and every folder that contains one, as MCP
+    /// resources. Files come first, then folders, each sorted by path.
+    pub fn list_mcp_resources(sqlite_manager: &SqliteManager) -> Result<Vec<McpResource>, ZooFsError> {
+        let files = Self::parsed_files_by_path(sqlite_manager)?;
+
+        let mut folders = BTreeSet::new();
+        for path in files.keys() {
+            let mut parent = path.as_str();
+            while let Some((folder, _)) = parent.rsplit_once('/') {
+                folders.insert(folder.to_string());
+                parent = folder;
+            }
+        }
+
+        let mut resources: Vec<McpResource> = files.values().map(Self::file_resource).collect();
+        for folder in folders {
+            let version = Self::folder_version(&files, &folder);
+            resources.push(Self::folder_resource(&folder, version));
+        }
+        Ok(resources)
+    }
+
+    /// The current version of a resource, or None if it doesn't exist anymore.
+    pub fn mcp_resource_version(sqlite_manager: &SqliteManager, uri: &str) -> Result<Option<String>, ZooFsError> {
+        let uri = McpResourceUri::parse(uri).map_err(ZooFsError::InvalidPathString)?;
+        match uri {
+            McpResourceUri::File(path) => Ok(sqlite_manager
+                .get_parsed_file_by_rel_path(&path)?
+                .map(|file| Self::file_version(&file))),
+            McpResourceUri::Folder(folder) => {
+                let files = Self::parsed_files_by_path(sqlite_manager)?;
+                if !folder.is_empty() && !files.keys().any(|path| Self::is_in_folder(path, &folder)) {
+                    return Ok(None);
+                }
+                Ok(Some(Self::folder_version(&files, &folder)))
+            }
+        }
+    }
+
+    /// Reads a resource. A file returns its parsed text chunks in order, a folder returns a JSON
+    /// listing of the files and folders directly inside it.
+    pub fn read_mcp_resource(sqlite_manager: &SqliteManager, uri: &str) -> Result<McpResourceContents, ZooFsError> {
+        let uri = McpResourceUri::parse(uri).map_err(ZooFsError::InvalidPathString)?;
+        match &uri {
+            McpResourceUri::File(path) => {
+                let file = sqlite_manager
+                    .get_parsed_file_by_rel_path(path)?
+                    .ok_or_else(|| ZooFsError::FileNotFoundWithPath(path.clone()))?;
+                let parsed_file_id = file.id.ok_or(ZooFsError::FailedToRetrieveParsedFileID)?;
+                let mut chunks = sqlite_manager.get_chunks_for_parsed_file(parsed_file_id)?;
+                chunks.sort_by_key(|chunk| chunk.position);
+
+                Ok(McpResourceContents {
+                    uri: uri.to_uri(),
+                    mime_type: Some(Self::file_mime_type(&file).to_string()),
+                    chunks: chunks.into_iter().map(|chunk| chunk.content).collect(),
+                })
+            }
+            McpResourceUri::Folder(folder) => {
+                let files = Self::parsed_files_by_path(sqlite_manager)?;
+                let mut child_files = Vec::new();
+                let mut child_folders = BTreeSet::new();
+                for (path, file) in &files {
+                    if !Self::is_in_folder(path, folder) {
+                        continue;
+                    }
+                    let rest = if folder.is_empty() {
+                        path.as_str()
+                    } else {
+                        &path[folder.len() + 1..]
+                    };
+                    match rest.split_once('/') {
+                        Some((child, _)) => {
+                            child_folders.insert(Self::join_path(folder, child));
+                        }
+                        None => child_files.push(Self::file_resource(file)),
+                    }
+                }
+                if !folder.is_empty() && child_files.is_empty() && child_folders.is_empty() {
+                    return Err(ZooFsError::FileNotFoundWithPath(folder.clone()));
+                }
+
+                let listing = json!({
+                    "folders": child_folders
+                        .iter()
+                        .map(|path| McpResourceUri::Folder(path.clone()).to_uri())
+                        .collect::<Vec<_>>(),
+                    "files": child_files
+                        .iter()
+                        .map(|file| json!({ "uri": file.uri, "name": file.name, "description": file.description }))
+                        .collect::<Vec<_>>(),
+                });
+                Ok(McpResourceContents {
+                    uri: uri.to_uri(),
+                    mime_type: Some(FOLDER_MIME_TYPE.to_string()),
+                    chunks: vec![listing.to_string()],
+                })
+            }
+        }
+    }
+
+    fn parsed_files_by_path(sqlite_manager: &SqliteManager) -> Result<BTreeMap<String, ParsedFile>, ZooFsError> {
+        Ok(sqlite_manager
+            .get_parsed_files_by_prefix("")?
+            .into_iter()
+            .map(|file| (file.relative_path.trim_matches('/').to_string(), file))
+            .collect())
+    }
+
+    fn is_in_folder(path: &str, folder: &str) -> bool {
+        folder.is_empty() || path.strip_prefix(folder).is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    fn join_path(folder: &str, name: &str) -> String {
+        if folder.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", folder, name)
+        }
+    }
+
+    fn file_resource(file: &ParsedFile) -> McpResource {
+        let path = file.relative_path.trim_matches('/');
+        McpResource {
+            uri: McpResourceUri::File(path.to_string()).to_uri(),
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            description: file.description.clone(),
+            mime_type: Some(Self::file_mime_type(file).to_string()),
+            version: Self::file_version(file),
+        }
+    }
+
+    fn folder_resource(folder: &str, version: String) -> McpResource {
+        McpResource {
+            uri: McpResourceUri::Folder(folder.to_string()).to_uri(),
+            name: folder.rsplit('/').next().unwrap_or(folder).to_string(),
+            description: Some(format!("Vector FS folder /{}", folder)),
+            mime_type: Some(FOLDER_MIME_TYPE.to_string()),
+            version,
+        }
+    }
+
+    /// Files are re-parsed into a new entry when their content changes, so the id, parse time and
+    /// size identify a version.
+    fn file_version(file: &ParsedFile) -> String {
+        format!(
+            "{}-{}-{}",
+            file.id.unwrap_or_default(),
+            file.created_time.unwrap_or_default(),
+            file.total_characters.unwrap_or_default()
+        )
+    }
+
+    /// A hash of the paths and versions of every file under the folder.
+    fn folder_version(files: &BTreeMap<String, ParsedFile>, folder: &str) -> String {
+        let mut hasher = blake3::Hasher::new();
+        for (path, file) in files.iter().filter(|(path, _)| Self::is_in_folder(path, folder)) {
+            hasher.update(path.as_bytes());
+            hasher.update(Self::file_version(file).as_bytes());
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    /// The chunks of a parsed file are plain text, except for markdown which keeps its syntax.
+    fn file_mime_type(file: &ParsedFile) -> &'static str {
+        match file
+            .original_extension
+            .as_deref()
+            .map(|ext| ext.to_lowercase())
+            .as_deref()
+        {
+            Some("md") => "text/markdown",
+            _ => "text/plain",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::NamedTempFile;
+    use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+    use zoo_message_primitives::schemas::zoo_fs::ZooFileChunk;
+
+    fn setup_test_db() -> SqliteManager {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = PathBuf::from(temp_file.path());
+        let model_type =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM);
+
+        SqliteManager::new(db_path, String::new(), model_type).unwrap()
+    }
+
+    fn add_file(db: &SqliteManager, relative_path: &str, chunks: &[&str]) {
+        let parsed_file = ParsedFile {
+            id: None,
+            relative_path: relative_path.to_string(),
+            original_extension: relative_path.rsplit_once('.').map(|(_, ext)| ext.to_string()),
+            description: None,
+            source: None,
+            embedding_model_used: None,
+            keywords: None,
+            distribution_info: None,
+            created_time: Some(1),
+            tags: None,
+            total_tokens: None,
+            total_characters: Some(chunks.iter().map(|c| c.len() as i64).sum()),
+        };
+        db.add_parsed_file(&parsed_file).unwrap();
+        let parsed_file_id = db
+            .get_parsed_file_by_rel_path(relative_path)
+            .unwrap()
+            .unwrap()
+            .id
+            .unwrap();
+
+        // Insert out of order, reads must follow the position
+        for (position, content) in chunks.iter().enumerate().rev() {
+            let chunk = ZooFileChunk {
+                chunk_id: None,
+                parsed_file_id,
+                position: position as i64,
+                content: content.to_string(),
+            };
+            db.create_chunk_with_embedding(&chunk, None).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_list_and_read_mcp_resources() {
+        let db = setup_test_db();
+        add_file(&db, "docs/guides/setup.md", &["# Setup\n", "Run the node."]);
+        add_file(&db, "docs/notes.txt", &["Some notes"]);
+
+        let resources = ZooFileManager::list_mcp_resources(&db).unwrap();
+        let uris: Vec<&str> = resources.iter().map(|r| r.uri.as_str()).collect();
+        assert_eq!(
+            uris,
+            vec![
+                "zoo://files/docs/guides/setup.md",
+                "zoo://files/docs/notes.txt",
+                "zoo://folders/docs",
+                "zoo://folders/docs/guides",
+            ]
+        );
+        assert_eq!(resources[0].mime_type.as_deref(), Some("text/markdown"));
+
+        let file = ZooFileManager::read_mcp_resource(&db, "zoo://files/docs/guides/setup.md").unwrap();
+        assert_eq!(file.chunks, vec!["# Setup\n", "Run the node."]);
+
+        let folder = ZooFileManager::read_mcp_resource(&db, "zoo://folders/docs").unwrap();
+        let listing: serde_json::Value = serde_json::from_str(&folder.chunks[0]).unwrap();
+        assert_eq!(listing["folders"], json!(["zoo://folders/docs/guides"]));
+        assert_eq!(listing["files"][0]["uri"], "zoo://files/docs/notes.txt");
+
+        assert!(ZooFileManager::read_mcp_resource(&db, "zoo://files/docs/missing.txt").is_err());
+        assert!(ZooFileManager::read_mcp_resource(&db, "zoo://folders/missing").is_err());
+    }
+
+    #[test]
+    fn test_mcp_resource_version_changes() {
+        let db = setup_test_db();
+        add_file(&db, "docs/notes.txt", &["Some notes"]);
+
+        let file_version = ZooFileManager::mcp_resource_version(&db, "zoo://files/docs/notes.txt").unwrap();
+        let folder_version = ZooFileManager::mcp_resource_version(&db, "zoo://folders/docs").unwrap();
+        assert!(file_version.is_some());
+
+        add_file(&db, "docs/more.txt", &["More notes"]);
+        assert_eq!(
+            ZooFileManager::mcp_resource_version(&db, "zoo://files/docs/notes.txt").unwrap(),
+            file_version
+        );
+        assert_ne!(
+            ZooFileManager::mcp_resource_version(&db, "zoo://folders/docs").unwrap(),
+            folder_version
+        );
+        assert_eq!(
+            ZooFileManager::mcp_resource_version(&db, "zoo://files/docs/gone.txt").unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use std::convert::Infallible;
+
+use async_channel::Sender;
+use futures::StreamExt;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use utoipa::OpenApi;
+use warp::sse::Event;
+use warp::Filter;
+
+use crate::api_v2::api_v2_router::with_sender;
+use crate::node_api_router::APIError;
+use crate::node_commands::NodeCommand;
+
+use super::openai_types::{
+    OpenAIChatCompletionChoice, OpenAIChatCompletionChunk, OpenAIChatCompletionChunkChoice,
+    OpenAIChatCompletionOutput, OpenAIChatCompletionRequest, OpenAIChatCompletionResponse, OpenAIChatDelta,
+    OpenAIChatMessage, OpenAIModel, OpenAIModelList, OpenAIUsage,
+};
+
+pub fn openai_routes(
+    node_commands_sender: Sender<NodeCommand>,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let chat_completions_route = warp::path("chat")
+        .and(warp::path("completions"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(chat_completions_handler);
+
+    let models_route = warp::path("models")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and_then(list_models_handler);
+
+    chat_completions_route.or(models_route)
+}
+
+/// OpenAI clients expect errors wrapped in an `error` object instead of the
+/// flat `APIError` used by the v2 API.
+fn openai_error_body(error: APIError) -> (StatusCode, Value) {
+    let status = StatusCode::from_u16(error.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let error_type = match status {
+        StatusCode::UNAUTHORIZED => "authentication_error",
+        StatusCode::NOT_FOUND => "not_found_error",
+        StatusCode::BAD_REQUEST => "invalid_request_error",
+        _ => "server_error",
+    };
+    let body = json!({
+        "error": {
+            "message": error.message,
+            "type": error_type,
+            "code": error.error,
+        }
+    });
+    (status, body)
+}
+
+fn openai_error_reply(error: APIError) -> warp::reply::WithStatus<warp::reply::Json> {
+    let (status, body) = openai_error_body(error);
+    warp::reply::with_status(warp::reply::json(&body), status)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/chat/completions",
+    request_body = OpenAIChatCompletionRequest,
+    responses(
+        (status = 200, description = "Chat completion (or an SSE stream of chunks when `stream` is true)", body = OpenAIChatCompletionResponse),
+        (status = 400, description = "Bad request", body = APIError),
+        (status = 404, description = "Model not found", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn chat_completions_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: OpenAIChatCompletionRequest,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiOpenAIChatCompletions {
+            bearer,
+            payload,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(OpenAIChatCompletionOutput::Completion(response)) => Ok(Box::new(warp::reply::with_status(
+            warp::reply::json(&response),
+            StatusCode::OK,
+        ))),
+        Ok(OpenAIChatCompletionOutput::Stream(chunks)) => {
+            let events = chunks
+                .map(|chunk| {
+                    // The status is already sent, an error goes in the stream like OpenAI does
+                    let data = match chunk {
+                        Ok(chunk) => serde_json::to_string(&chunk).unwrap_or_default(),
+                        Err(error) => openai_error_body(error).1.to_string(),
+                    };
+                    Ok::<Event, Infallible>(Event::default().data(data))
+                })
+                .chain(futures::stream::once(async {
+                    Ok::<Event, Infallible>(Event::default().data("[DONE]"))
+                }));
+            Ok(Box::new(warp::sse::reply(warp::sse::keep_alive().stream(events))))
+        }
+        Err(error) => Ok(Box::new(openai_error_reply(error))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/models",
+    responses(
+        (status = 200, description = "Agents and LLM providers available as models", body = OpenAIModelList),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn list_models_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiOpenAIListModels {
+            bearer,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => Ok(Box::new(warp::reply::with_status(
+            warp::reply::json(&response),
+            StatusCode::OK,
+        ))),
+        Err(error) => Ok(Box::new(openai_error_reply(error))),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        chat_completions_handler,
+        list_models_handler,
+    ),
+    components(
+        schemas(
+            APIError,
+            OpenAIChatCompletionRequest,
+            OpenAIChatCompletionResponse,
+            OpenAIChatCompletionChoice,
+            OpenAIChatCompletionChunk,
+            OpenAIChatCompletionChunkChoice,
+            OpenAIChatDelta,
+            OpenAIChatMessage,
+            OpenAIModel,
+            OpenAIModelList,
+            OpenAIUsage,
+        )
+    ),
+    tags(
+        (name = "openai", description = "OpenAI-compatible API endpoints")
+    )
+)]
+pub struct OpenAIApiDoc;
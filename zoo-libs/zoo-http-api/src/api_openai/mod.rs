@@ -0,0 +1,9 @@
+//! OpenAI-compatible API (`/v1/chat/completions`, `/v1/models`).
+//!
+//! Every completion is backed by a regular job on the node, so agents, tools
+//! and the vector FS scope are available to any client speaking the OpenAI protocol.
+
+pub mod api_openai_handlers;
+pub mod openai_types;
+
+pub use api_openai_handlers::openai_routes;
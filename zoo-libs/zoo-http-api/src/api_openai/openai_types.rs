@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use zoo_message_primitives::zoo_utils::job_scope::MinimalJobScope;
+
+use crate::node_api_router::APIError;
+
+/// A single message in the OpenAI chat format.
+///
+/// `content` is either a plain string or an array of content parts
+/// (`[{"type": "text", "text": "..."}]`), so it is kept as a raw value.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OpenAIChatMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl OpenAIChatMessage {
+    pub fn assistant(content: String) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: Some(Value::String(content)),
+            name: None,
+        }
+    }
+
+    /// Returns the text of the message, joining all the text parts if the
+    /// content was sent as an array of parts.
+    pub fn text_content(&self) -> String {
+        match &self.content {
+            Some(Value::String(text)) => text.clone(),
+            Some(Value::Array(parts)) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    Value::String(text) => Some(text.clone()),
+                    Value::Object(obj) => obj.get("text").and_then(|t| t.as_str()).map(|t| t.to_string()),
+                    _ => None,
+                })
+                .collect::<Vec<String>>()
+                .join("\n"),
+            Some(Value::Null) | None => String::new(),
+            Some(other) => other.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OpenAIChatCompletionRequest {
+    /// Id of the agent or LLM provider that should answer the request.
+    pub model: String,
+    pub messages: Vec<OpenAIChatMessage>,
+    #[serde(default)]
+    pub stream: Option<bool>,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub top_p: Option<f64>,
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+    #[serde(default)]
+    pub max_completion_tokens: Option<u64>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub user: Option<String>,
+    /// Zoo extension: vector FS items and folders the job can read from.
+    #[serde(default)]
+    pub job_scope: Option<MinimalJobScope>,
+    /// Zoo extension: let the agent use its tools while answering.
+    #[serde(default)]
+    pub use_tools: Option<bool>,
+}
+
+impl OpenAIChatCompletionRequest {
+    pub fn is_stream(&self) -> bool {
+        self.stream.unwrap_or(false)
+    }
+
+    pub fn max_output_tokens(&self) -> Option<u64> {
+        self.max_completion_tokens.or(self.max_tokens)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OpenAIUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OpenAIChatCompletionChoice {
+    pub index: u32,
+    pub message: OpenAIChatMessage,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OpenAIChatCompletionResponse {
+    pub id: String,
+    pub object: String,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<OpenAIChatCompletionChoice>,
+    pub usage: OpenAIUsage,
+    /// Zoo extension: the job that produced the completion.
+    pub zoo_job_id: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct OpenAIChatDelta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OpenAIChatCompletionChunkChoice {
+    pub index: u32,
+    pub delta: OpenAIChatDelta,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OpenAIChatCompletionChunk {
+    pub id: String,
+    pub object: String,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<OpenAIChatCompletionChunkChoice>,
+}
+
+impl OpenAIChatCompletionChunk {
+    pub fn new(id: &str, created: i64, model: &str, delta: OpenAIChatDelta, finish_reason: Option<String>) -> Self {
+        Self {
+            id: id.to_string(),
+            object: "chat.completion.chunk".to_string(),
+            created,
+            model: model.to_string(),
+            choices: vec![OpenAIChatCompletionChunkChoice {
+                index: 0,
+                delta,
+                finish_reason,
+            }],
+        }
+    }
+}
+
+/// What the node hands back for a chat completion: either the full
+/// completion or a channel of chunks that is closed once the answer is done.
+/// An error (e.g. the answer timed out) is the last item of the channel.
+pub enum OpenAIChatCompletionOutput {
+    Completion(OpenAIChatCompletionResponse),
+    Stream(async_channel::Receiver<Result<OpenAIChatCompletionChunk, APIError>>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OpenAIModel {
+    pub id: String,
+    pub object: String,
+    pub created: i64,
+    pub owned_by: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OpenAIModelList {
+    pub object: String,
+    pub data: Vec<OpenAIModel>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_text_content_from_string_and_parts() {
+        let message: OpenAIChatMessage = serde_json::from_value(json!({
+            "role": "user",
+            "content": "hello"
+        }))
+        .unwrap();
+        assert_eq!(message.text_content(), "hello");
+
+        let message: OpenAIChatMessage = serde_json::from_value(json!({
+            "role": "user",
+            "content": [
+                {"type": "text", "text": "first"},
+                {"type": "image_url", "image_url": {"url": "http://example.com/a.png"}},
+                {"type": "text", "text": "second"}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(message.text_content(), "first\nsecond");
+
+        let message: OpenAIChatMessage = serde_json::from_value(json!({
+            "role": "assistant",
+            "content": null
+        }))
+        .unwrap();
+        assert_eq!(message.text_content(), "");
+    }
+
+    #[test]
+    fn test_deserialize_minimal_request() {
+        let request: OpenAIChatCompletionRequest = serde_json::from_value(json!({
+            "model": "my_agent",
+            "messages": [{"role": "user", "content": "hi"}],
+            "max_tokens": 100,
+            "max_completion_tokens": 50
+        }))
+        .unwrap();
+        assert_eq!(request.model, "my_agent");
+        assert!(!request.is_stream());
+        assert_eq!(request.max_output_tokens(), Some(50));
+        assert!(request.job_scope.is_none());
+    }
+}
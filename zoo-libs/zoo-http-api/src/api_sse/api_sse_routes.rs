@@ -1,12 +1,20 @@
 use crate::api_sse::api_sse_handlers::{
     sse_handler, post_event_handler, update_tools_cache_handler,
     McpState, IoError, PayloadTooLarge, SessionExpired};
+use crate::api_sse::api_streamable_http_handlers::{
+    streamable_http_delete_handler, streamable_http_get_handler, streamable_http_post_handler, StreamableHttpState,
+    LAST_EVENT_ID_HEADER, MCP_SESSION_ID_HEADER};
 use crate::api_sse::mcp_tools_service::McpToolsService;
+use crate::node_api_router::APIError;
 use crate::node_commands::NodeCommand;
 use async_channel::Sender;
 use std::sync::Arc;
+use std::time::Duration;
 use warp::{http::StatusCode, Filter, Rejection, Reply};
 
+/// How often idle Streamable HTTP sessions are looked for.
+const SESSION_CLEANUP_INTERVAL_SECS: u64 = 60;
+
 /// Handle rejections from the routes
 async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
     let status;
@@ -28,6 +36,10 @@ async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
         status = StatusCode::NOT_FOUND; // Or perhaps GONE (410)
         message = "Session not found or expired".to_string();
         tracing::warn!("SSE route rejection: {}", message);
+    } else if let Some(e) = err.find::<APIError>() {
+        status = StatusCode::from_u16(e.code).unwrap_or(StatusCode::UNAUTHORIZED);
+        message = e.message.clone();
+        tracing::warn!("MCP route rejection: {}", message);
     } else if let Some(e) = err.find::<warp::reject::MethodNotAllowed>() {
          status = StatusCode::METHOD_NOT_ALLOWED;
          message = format!("Method not allowed: {}", e);
@@ -63,11 +75,12 @@ pub fn mcp_sse_routes(
     let state = Arc::new(McpState::new());
     tracing::info!("Created MCP state");
     
-    // SSE endpoint
+    // SSE endpoint. It takes no bearer, so it only serves tools
+    let sse_tools_service = Arc::new(tools_service.without_documents());
     let sse = warp::path("sse")
         .and(warp::get())
         .and(with_state(state.clone()))
-        .and(with_tools_service(tools_service.clone())) // sse_handler needs the service instance
+        .and(with_tools_service(sse_tools_service)) // sse_handler needs the service instance
         .and_then(sse_handler);
     tracing::info!("Set up GET /sse endpoint for SSE connections");
 
@@ -85,6 +98,47 @@ pub fn mcp_sse_routes(
         .and_then(post_event_handler);
     tracing::info!("Set up POST /sse endpoint for client messages");
 
+    // Streamable HTTP endpoint, serving the same tools service as the SSE one
+    let streamable_state = Arc::new(StreamableHttpState::new());
+    let state_for_cleanup = streamable_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(SESSION_CLEANUP_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let closed = state_for_cleanup.clean_idle_sessions().await;
+            if closed > 0 {
+                tracing::info!("Closed {} idle MCP Streamable HTTP sessions", closed);
+            }
+        }
+    });
+
+    let streamable_post = warp::path::end()
+        .and(warp::post())
+        .and(with_mcp_bearer(node_commands_sender.clone()))
+        .and(warp::header::optional::<String>(MCP_SESSION_ID_HEADER))
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::body::content_length_limit(1024 * 1024 * 4))
+        .and(warp::body::bytes())
+        .and(with_streamable_state(streamable_state.clone()))
+        .and(with_tools_service(tools_service.clone()))
+        .and_then(streamable_http_post_handler);
+
+    let streamable_get = warp::path::end()
+        .and(warp::get())
+        .and(with_mcp_bearer(node_commands_sender.clone()))
+        .and(warp::header::optional::<String>(MCP_SESSION_ID_HEADER))
+        .and(warp::header::optional::<String>(LAST_EVENT_ID_HEADER))
+        .and(with_streamable_state(streamable_state.clone()))
+        .and_then(streamable_http_get_handler);
+
+    let streamable_delete = warp::path::end()
+        .and(warp::delete())
+        .and(with_mcp_bearer(node_commands_sender.clone()))
+        .and(warp::header::optional::<String>(MCP_SESSION_ID_HEADER))
+        .and(with_streamable_state(streamable_state.clone()))
+        .and_then(streamable_http_delete_handler);
+    tracing::info!("Set up POST, GET and DELETE / endpoints for Streamable HTTP");
+
     let update_cache_route = warp::path("update_tools_cache")
         .and(warp::post()) // Use POST for actions
         .and(with_tools_service(tools_service.clone())) // Inject the service
@@ -94,6 +148,9 @@ pub fn mcp_sse_routes(
     // Combine the routes and add rejection handling
     tracing::info!("MCP SSE routes configured successfully");
     sse.or(post_event)
+        .or(streamable_post)
+        .or(streamable_get)
+        .or(streamable_delete)
         .or(update_cache_route)
         .recover(handle_rejection)
 }
@@ -105,6 +162,48 @@ fn with_state(
     warp::any().map(move || state.clone())
 }
 
+fn with_streamable_state(
+    state: Arc<StreamableHttpState>,
+) -> impl Filter<Extract = (Arc<StreamableHttpState>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || state.clone())
+}
+
+/// Requires a bearer the node accepts for MCP: the node API key or an API key with the
+/// `tools:execute` scope. Extracts the bearer, sessions are bound to the one that created them.
+fn with_mcp_bearer(
+    node_commands_sender: Sender<NodeCommand>,
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization").and_then(move |authorization: Option<String>| {
+        let node_commands_sender = node_commands_sender.clone();
+        async move {
+            let Some(bearer) = authorization
+                .as_deref()
+                .and_then(|auth| auth.strip_prefix("Bearer "))
+                .map(str::to_string)
+            else {
+                return Err(warp::reject::custom(APIError {
+                    code: StatusCode::UNAUTHORIZED.as_u16(),
+                    error: "Unauthorized".to_string(),
+                    message: "Missing bearer token".to_string(),
+                }));
+            };
+
+            let (res_sender, res_receiver) = async_channel::bounded(1);
+            node_commands_sender
+                .send(NodeCommand::V2ApiAuthorizeMcpBearer {
+                    bearer: bearer.clone(),
+                    res: res_sender,
+                })
+                .await
+                .map_err(|_| warp::reject::reject())?;
+            match res_receiver.recv().await.map_err(|_| warp::reject::reject())? {
+                Ok(()) => Ok(bearer),
+                Err(error) => Err(warp::reject::custom(error)),
+            }
+        }
+    })
+}
+
 /// Helper to pass the tools service to handlers (only needed by sse_handler now)
 fn with_tools_service(
     service: Arc<McpToolsService>,
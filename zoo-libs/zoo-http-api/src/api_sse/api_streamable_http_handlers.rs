@@ -0,0 +1,529 @@
+//! Streamable HTTP transport of the MCP server (protocol revision 2025-03-26 and later).
+//!
+//! Every JSON-RPC message from the client is a `POST /mcp`. Requests get their response either as
+//! a single JSON body or as a short SSE stream, depending on what the client accepts. `GET /mcp`
+//! opens a stream for the messages the server sends on its own (e.g. resource updates), and
+//! `DELETE /mcp` ends the session. Every message sent to the client gets an event id, so a client
+//! that lost a stream can reconnect with `Last-Event-ID` and get what it missed.
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt as FuturesStreamExt, TryStreamExt};
+use rand::random;
+use rmcp::{
+    model::{ClientJsonRpcMessage, InitializeRequestParam, ServerJsonRpcMessage}, service::serve_directly
+};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, RwLock};
+use tokio_stream::wrappers::{ReceiverStream, UnboundedReceiverStream};
+use tokio_stream::StreamExt as TokioStreamExt;
+use warp::{
+    http::{Response, StatusCode}, hyper::Body, reject, Rejection
+};
+
+use crate::api_sse::api_sse_handlers::IoError;
+use crate::api_sse::mcp_tools_service::McpToolsService;
+
+pub const MCP_SESSION_ID_HEADER: &str = "mcp-session-id";
+pub const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// Sessions without any request for this long are closed.
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// How many sent messages each session keeps for clients that resume a stream.
+const SESSION_EVENT_HISTORY: usize = 256;
+
+type Result<T> = std::result::Result<T, Rejection>;
+type EventSender = mpsc::UnboundedSender<(u64, String)>;
+
+/// The messages sent to the client in a session, and where the next ones go.
+struct SessionEvents {
+    next_event_id: u64,
+    history: VecDeque<(u64, String)>,
+    /// Requests waiting for their response, by JSON encoded request id.
+    pending_requests: HashMap<String, EventSender>,
+    /// The `GET /mcp` stream, for messages that aren't a response to a pending request.
+    standalone_stream: Option<EventSender>,
+    last_seen: Instant,
+}
+
+pub struct StreamableHttpSession {
+    /// The bearer that created the session. Other bearers can't use it.
+    bearer: String,
+    service_tx: mpsc::Sender<ClientJsonRpcMessage>,
+    events: Mutex<SessionEvents>,
+}
+
+impl StreamableHttpSession {
+    fn touch(&self) {
+        self.events.lock().unwrap().last_seen = Instant::now();
+    }
+
+    /// Records a message for the client and delivers it to the request waiting for it, or to the
+    /// standalone stream. Messages that can't be delivered stay in the history.
+    fn send_to_client(&self, message: &ServerJsonRpcMessage) {
+        let value = match serde_json::to_value(message) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::error!("Failed to serialize MCP message: {}", e);
+                return;
+            }
+        };
+        let response_to = match (value.get("id"), value.get("method")) {
+            (Some(id), None) => Some(id.to_string()),
+            _ => None,
+        };
+
+        let mut events = self.events.lock().unwrap();
+        let event_id = events.next_event_id;
+        events.next_event_id += 1;
+        let data = value.to_string();
+        events.history.push_back((event_id, data.clone()));
+        if events.history.len() > SESSION_EVENT_HISTORY {
+            events.history.pop_front();
+        }
+
+        if let Some(pending) = response_to.and_then(|id| events.pending_requests.remove(&id)) {
+            let _ = pending.send((event_id, data));
+        } else if let Some(stream) = &events.standalone_stream {
+            if stream.send((event_id, data)).is_err() {
+                events.standalone_stream = None;
+            }
+        }
+    }
+
+    fn events_after(&self, last_event_id: u64) -> Vec<(u64, String)> {
+        let events = self.events.lock().unwrap();
+        events
+            .history
+            .iter()
+            .filter(|(event_id, _)| *event_id > last_event_id)
+            .cloned()
+            .collect()
+    }
+}
+
+/// The sessions of the Streamable HTTP endpoint.
+pub struct StreamableHttpState {
+    sessions: RwLock<HashMap<String, Arc<StreamableHttpSession>>>,
+    ping_interval: Option<Duration>,
+}
+
+impl StreamableHttpState {
+    pub fn new() -> Self {
+        Self {
+            sessions: RwLock::new(HashMap::new()),
+            ping_interval: Some(Duration::from_secs(30)),
+        }
+    }
+
+    /// Starts a session serving its own clone of the tools service.
+    async fn create_session(
+        &self,
+        bearer: String,
+        tools_service: &McpToolsService,
+    ) -> (String, Arc<StreamableHttpSession>) {
+        let session_id = format!("{:032x}", random::<u128>());
+        let (service_tx, service_rx) = mpsc::channel::<ClientJsonRpcMessage>(64);
+        let session = Arc::new(StreamableHttpSession {
+            bearer,
+            service_tx,
+            events: Mutex::new(SessionEvents {
+                next_event_id: 1,
+                history: VecDeque::new(),
+                pending_requests: HashMap::new(),
+                standalone_stream: None,
+                last_seen: Instant::now(),
+            }),
+        });
+        self.sessions.write().await.insert(session_id.clone(), session.clone());
+
+        // The transport only holds a weak reference, so removing the session from the map drops
+        // the sender and ends the service
+        let transport = StreamableHttpTransport {
+            session: Arc::downgrade(&session),
+            service_rx: ReceiverStream::new(service_rx),
+        };
+        let service = tools_service.clone();
+        let id = session_id.clone();
+        tokio::spawn(async move {
+            let running_service = serve_directly(service, transport, Some(InitializeRequestParam::default()));
+            tracing::info!("MCP Streamable HTTP session started: {}", id);
+            if let Err(e) = running_service.waiting().await {
+                tracing::error!("MCP service error for session {}: {:?}", id, e);
+            }
+            tracing::info!("MCP Streamable HTTP session ended: {}", id);
+        });
+
+        (session_id, session)
+    }
+
+    async fn get_session(&self, session_id: &str, bearer: &str) -> Option<Arc<StreamableHttpSession>> {
+        let sessions = self.sessions.read().await;
+        sessions
+            .get(session_id)
+            .filter(|session| session.bearer == bearer)
+            .cloned()
+    }
+
+    async fn remove_session(&self, session_id: &str) -> bool {
+        self.sessions.write().await.remove(session_id).is_some()
+    }
+
+    /// Closes the sessions that have been idle for longer than `SESSION_IDLE_TIMEOUT`.
+    pub async fn clean_idle_sessions(&self) -> usize {
+        let mut sessions = self.sessions.write().await;
+        let before = sessions.len();
+        sessions.retain(|_, session| session.events.lock().unwrap().last_seen.elapsed() < SESSION_IDLE_TIMEOUT);
+        before - sessions.len()
+    }
+}
+
+impl Default for StreamableHttpState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn json_rpc_error_response(status: StatusCode, code: i32, message: &str) -> Response<Body> {
+    let body = json!({
+        "jsonrpc": "2.0",
+        "id": Value::Null,
+        "error": { "code": code, "message": message },
+    });
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap_or_default()
+}
+
+fn sse_event(event_id: u64, data: &str) -> String {
+    format!("id: {}\nevent: message\ndata: {}\n\n", event_id, data)
+}
+
+fn sse_response(
+    session_id: &str,
+    stream: Pin<Box<dyn Stream<Item = std::result::Result<String, Infallible>> + Send>>,
+) -> Result<Response<Body>> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/event-stream")
+        .header("Cache-Control", "no-cache, no-transform")
+        .header("X-Accel-Buffering", "no")
+        .header(MCP_SESSION_ID_HEADER, session_id)
+        .body(Body::wrap_stream(stream.map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::Other, "infallible stream error")
+        })))
+        .map_err(|e| {
+            tracing::error!("Failed to build MCP stream response: {}", e);
+            reject::custom(IoError)
+        })
+}
+
+/// Handles `POST /mcp`: one JSON-RPC message from the client.
+pub async fn streamable_http_post_handler(
+    bearer: String,
+    session_id: Option<String>,
+    accept: Option<String>,
+    body: Bytes,
+    state: Arc<StreamableHttpState>,
+    tools_service: Arc<McpToolsService>,
+) -> Result<Response<Body>> {
+    let value: Value = match serde_json::from_slice(&body) {
+        Ok(value) => value,
+        Err(e) => {
+            return Ok(json_rpc_error_response(
+                StatusCode::BAD_REQUEST,
+                -32700,
+                &format!("Invalid JSON: {}", e),
+            ))
+        }
+    };
+    if value.is_array() {
+        return Ok(json_rpc_error_response(
+            StatusCode::BAD_REQUEST,
+            -32600,
+            "Batched JSON-RPC messages are not supported",
+        ));
+    }
+    let message: ClientJsonRpcMessage = match serde_json::from_value(value.clone()) {
+        Ok(message) => message,
+        Err(e) => {
+            return Ok(json_rpc_error_response(
+                StatusCode::BAD_REQUEST,
+                -32600,
+                &format!("Invalid JSON-RPC message: {}", e),
+            ))
+        }
+    };
+
+    let is_initialize = value.get("method").and_then(Value::as_str) == Some("initialize");
+    let (session_id, session) = match session_id {
+        Some(session_id) => match state.get_session(&session_id, &bearer).await {
+            Some(session) => (session_id, session),
+            None => {
+                return Ok(json_rpc_error_response(
+                    StatusCode::NOT_FOUND,
+                    -32001,
+                    "Session not found or expired",
+                ))
+            }
+        },
+        None if is_initialize => state.create_session(bearer, &tools_service).await,
+        None => {
+            return Ok(json_rpc_error_response(
+                StatusCode::BAD_REQUEST,
+                -32000,
+                "Missing Mcp-Session-Id header",
+            ))
+        }
+    };
+    session.touch();
+
+    // Only requests get a response, notifications and responses from the client are just accepted
+    let request_id = match (value.get("method"), value.get("id")) {
+        (Some(_), Some(id)) => Some(id.to_string()),
+        _ => None,
+    };
+    let response_rx = request_id.map(|request_id| {
+        let (tx, rx) = mpsc::unbounded_channel();
+        session.events.lock().unwrap().pending_requests.insert(request_id, tx);
+        rx
+    });
+
+    if let Err(e) = session.service_tx.send(message).await {
+        tracing::error!("Failed to forward MCP message for session {}: {}", session_id, e);
+        state.remove_session(&session_id).await;
+        return Err(reject::custom(IoError));
+    }
+
+    let Some(mut response_rx) = response_rx else {
+        return Response::builder()
+            .status(StatusCode::ACCEPTED)
+            .header(MCP_SESSION_ID_HEADER, session_id.as_str())
+            .body(Body::empty())
+            .map_err(|_| reject::custom(IoError));
+    };
+
+    let wants_stream = accept
+        .as_deref()
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+    if wants_stream {
+        let stream = TokioStreamExt::map(
+            TokioStreamExt::take(UnboundedReceiverStream::new(response_rx), 1),
+            |(event_id, data)| Ok::<_, Infallible>(sse_event(event_id, &data)),
+        );
+        return sse_response(&session_id, Box::pin(stream));
+    }
+
+    match response_rx.recv().await {
+        Some((_, data)) => Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .header(MCP_SESSION_ID_HEADER, session_id.as_str())
+            .body(Body::from(data))
+            .map_err(|_| reject::custom(IoError)),
+        None => Err(reject::custom(IoError)),
+    }
+}
+
+/// Handles `GET /mcp`: the stream of messages the server sends on its own. With `Last-Event-ID`,
+/// the messages sent after that event are replayed first.
+pub async fn streamable_http_get_handler(
+    bearer: String,
+    session_id: Option<String>,
+    last_event_id: Option<String>,
+    state: Arc<StreamableHttpState>,
+) -> Result<Response<Body>> {
+    let Some(session_id) = session_id else {
+        return Ok(json_rpc_error_response(
+            StatusCode::BAD_REQUEST,
+            -32000,
+            "Missing Mcp-Session-Id header",
+        ));
+    };
+    let Some(session) = state.get_session(&session_id, &bearer).await else {
+        return Ok(json_rpc_error_response(
+            StatusCode::NOT_FOUND,
+            -32001,
+            "Session not found or expired",
+        ));
+    };
+    session.touch();
+
+    let replayed = match last_event_id.and_then(|id| id.trim().parse::<u64>().ok()) {
+        Some(last_event_id) => session.events_after(last_event_id),
+        None => Vec::new(),
+    };
+    // A new stream replaces the previous one
+    let (tx, rx) = mpsc::unbounded_channel();
+    session.events.lock().unwrap().standalone_stream = Some(tx);
+
+    let events = TokioStreamExt::map(
+        TokioStreamExt::chain(tokio_stream::iter(replayed), UnboundedReceiverStream::new(rx)),
+        |(event_id, data)| Ok::<_, Infallible>(sse_event(event_id, &data)),
+    );
+    let stream: Pin<Box<dyn Stream<Item = std::result::Result<String, Infallible>> + Send>> = match state.ping_interval
+    {
+        Some(interval) => {
+            let pings = TokioStreamExt::map(
+                tokio_stream::wrappers::IntervalStream::new(tokio::time::interval(interval)),
+                |_| Ok::<_, Infallible>(": ping\n\n".to_string()),
+            );
+            Box::pin(TokioStreamExt::merge(events, pings))
+        }
+        None => Box::pin(events),
+    };
+    sse_response(&session_id, stream)
+}
+
+/// Handles `DELETE /mcp`: the client ends its session.
+pub async fn streamable_http_delete_handler(
+    bearer: String,
+    session_id: Option<String>,
+    state: Arc<StreamableHttpState>,
+) -> Result<Response<Body>> {
+    let status = match session_id {
+        Some(session_id) if state.get_session(&session_id, &bearer).await.is_some() => {
+            state.remove_session(&session_id).await;
+            StatusCode::NO_CONTENT
+        }
+        Some(_) => StatusCode::NOT_FOUND,
+        None => StatusCode::BAD_REQUEST,
+    };
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .map_err(|_| reject::custom(IoError))
+}
+
+/// Bridges a session and the MCP service serving it.
+struct StreamableHttpTransport {
+    session: Weak<StreamableHttpSession>,
+    service_rx: ReceiverStream<ClientJsonRpcMessage>,
+}
+
+impl Stream for StreamableHttpTransport {
+    type Item = ClientJsonRpcMessage;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        FuturesStreamExt::poll_next_unpin(&mut self.service_rx, cx)
+    }
+}
+
+impl futures::Sink<ServerJsonRpcMessage> for StreamableHttpTransport {
+    type Error = std::io::Error;
+
+    fn poll_ready(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::result::Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: std::pin::Pin<&mut Self>, item: ServerJsonRpcMessage) -> std::result::Result<(), Self::Error> {
+        match self.session.upgrade() {
+            Some(session) => {
+                session.send_to_client(&item);
+                Ok(())
+            }
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "MCP session closed",
+            )),
+        }
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::result::Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::result::Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_session() -> StreamableHttpSession {
+        let (service_tx, _) = mpsc::channel(1);
+        StreamableHttpSession {
+            bearer: "token".to_string(),
+            service_tx,
+            events: Mutex::new(SessionEvents {
+                next_event_id: 1,
+                history: VecDeque::new(),
+                pending_requests: HashMap::new(),
+                standalone_stream: None,
+                last_seen: Instant::now(),
+            }),
+        }
+    }
+
+    fn message(value: Value) -> ServerJsonRpcMessage {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_responses_go_to_their_request() {
+        let session = test_session();
+        let (request_tx, mut request_rx) = mpsc::unbounded_channel();
+        let (stream_tx, mut stream_rx) = mpsc::unbounded_channel();
+        {
+            let mut events = session.events.lock().unwrap();
+            events.pending_requests.insert("7".to_string(), request_tx);
+            events.standalone_stream = Some(stream_tx);
+        }
+
+        session.send_to_client(&message(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/resources/updated",
+            "params": { "uri": "zoo://files/a.txt" }
+        })));
+        session.send_to_client(&message(json!({ "jsonrpc": "2.0", "id": 7, "result": {} })));
+
+        let (event_id, data) = request_rx.try_recv().unwrap();
+        assert_eq!(event_id, 2);
+        assert!(data.contains("\"id\":7"));
+        let (event_id, data) = stream_rx.try_recv().unwrap();
+        assert_eq!(event_id, 1);
+        assert!(data.contains("resources/updated"));
+        assert!(stream_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_events_are_kept_for_resuming() {
+        let session = test_session();
+        for id in 0..(SESSION_EVENT_HISTORY as u64 + 10) {
+            session.send_to_client(&message(json!({ "jsonrpc": "2.0", "id": id, "result": {} })));
+        }
+
+        let missed = session.events_after(SESSION_EVENT_HISTORY as u64 + 5);
+        assert_eq!(
+            missed.iter().map(|(event_id, _)| *event_id).collect::<Vec<_>>(),
+            vec![
+                SESSION_EVENT_HISTORY as u64 + 6,
+                SESSION_EVENT_HISTORY as u64 + 7,
+                SESSION_EVENT_HISTORY as u64 + 8,
+                SESSION_EVENT_HISTORY as u64 + 9,
+                SESSION_EVENT_HISTORY as u64 + 10,
+            ]
+        );
+        assert_eq!(session.events_after(0).len(), SESSION_EVENT_HISTORY);
+    }
+}
@@ -1,27 +1,120 @@
+use crate::node_api_router::APIError;
 use crate::node_commands::NodeCommand;
 use async_channel::Sender;
 use async_trait::async_trait;
 use once_cell::sync::Lazy;
 use rmcp::{
     model::ErrorData as McpError, model::{
-        CallToolRequestParam, CallToolResult, Content, ErrorData, Implementation, InitializeRequestParam, InitializeResult, ListPromptsResult, ListResourcesResult, ListToolsResult, PaginatedRequestParam, ProtocolVersion, ServerCapabilities, ServerInfo, Tool
-    }, service::RequestContext, RoleServer, ServerHandler
+        AnnotateAble, CallToolRequestParam, CallToolResult, Content, ErrorData, GetPromptRequestParam, GetPromptResult, Implementation, InitializeRequestParam, InitializeResult, ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, ListToolsResult, PaginatedRequestParam, Prompt, PromptArgument, PromptMessage, PromptMessageRole, ProtocolVersion, RawResource, RawResourceTemplate, ReadResourceRequestParam, ReadResourceResult, ResourceContents, ResourceUpdatedNotificationParam, ServerCapabilities, ServerInfo, SubscribeRequestParam, Tool, UnsubscribeRequestParam
+    }, service::{Peer, RequestContext}, RoleServer, ServerHandler
 };
 use serde_json::{Map, Value};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::future::{self, Future};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::Duration;
+use zoo_message_primitives::schemas::custom_prompt::CustomPrompt;
+use zoo_message_primitives::schemas::mcp_resources::MCP_FILE_URI_PREFIX;
 
 // Singleton for the tools cache using once_cell::sync::Lazy
 pub static TOOLS_CACHE: Lazy<RwLock<Vec<Tool>>> = Lazy::new(|| RwLock::new(Vec::new()));
 // Singleton map from user-facing tool name to internal tool_router_key
 pub static TOOL_NAME_TO_KEY_MAP: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
 
-#[derive(Clone)]
+/// How often subscribed resources are checked for changes.
+const RESOURCE_WATCH_INTERVAL_SECS: u64 = 10;
+/// Every prompt also takes this optional argument, appended to the rendered prompt.
+const PROMPT_INPUT_ARGUMENT: &str = "input";
+const PROMPT_DESCRIPTION_MAX_CHARS: usize = 120;
+
+/// The resources a client subscribed to, with the version they had when last checked.
+#[derive(Default)]
+struct ResourceSubscriptions {
+    versions: HashMap<String, String>,
+    watching: bool,
+}
+
 pub struct McpToolsService {
     node_commands_sender: Sender<NodeCommand>,
     node_name: String,
+    resource_subscriptions: Arc<Mutex<ResourceSubscriptions>>,
+    /// Prompts and vector FS resources are only served to authenticated transports.
+    serves_documents: bool,
+}
+
+/// Every MCP session serves its own clone, so clones share the node but not the subscriptions.
+impl Clone for McpToolsService {
+    fn clone(&self) -> Self {
+        Self {
+            node_commands_sender: self.node_commands_sender.clone(),
+            node_name: self.node_name.clone(),
+            resource_subscriptions: Arc::new(Mutex::new(ResourceSubscriptions::default())),
+            serves_documents: self.serves_documents,
+        }
+    }
+}
+
+/// Sends a command to the node and maps its API error to the closest MCP error.
+async fn send_node_command<T>(
+    node_commands_sender: &Sender<NodeCommand>,
+    command: impl FnOnce(Sender<Result<T, APIError>>) -> NodeCommand,
+) -> Result<T, McpError> {
+    let (tx, rx) = async_channel::bounded(1);
+    node_commands_sender
+        .send(command(tx))
+        .await
+        .map_err(|e| McpError::internal_error(format!("Failed to send node command: {:?}", e), None))?;
+    match rx.recv().await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) if e.code == 400 => Err(McpError::invalid_params(e.message, None)),
+        Ok(Err(e)) if e.code == 404 => Err(McpError::resource_not_found(e.message, None)),
+        Ok(Err(e)) => Err(McpError::internal_error(e.message, None)),
+        Err(e) => Err(McpError::internal_error(
+            format!("Failed to receive node response: {:?}", e),
+            None,
+        )),
+    }
+}
+
+fn server_capabilities(serves_documents: bool) -> ServerCapabilities {
+    let mut capabilities = ServerCapabilities::builder()
+        .enable_tools()
+        .enable_tool_list_changed()
+        .enable_prompts()
+        .enable_resources()
+        .enable_resources_subscribe()
+        .build();
+    if !serves_documents {
+        capabilities.prompts = None;
+        capabilities.resources = None;
+    }
+    capabilities
+}
+
+fn prompt_to_mcp(prompt: &CustomPrompt) -> Prompt {
+    let mut arguments: Vec<PromptArgument> = prompt
+        .template_arguments()
+        .into_iter()
+        .map(|name| PromptArgument {
+            name,
+            description: None,
+            required: Some(true),
+        })
+        .collect();
+    if !arguments.iter().any(|argument| argument.name == PROMPT_INPUT_ARGUMENT) {
+        arguments.push(PromptArgument {
+            name: PROMPT_INPUT_ARGUMENT.to_string(),
+            description: Some("Text appended after the prompt".to_string()),
+            required: Some(false),
+        });
+    }
+
+    let mut description: String = prompt.prompt.chars().take(PROMPT_DESCRIPTION_MAX_CHARS).collect();
+    if prompt.prompt.chars().count() > PROMPT_DESCRIPTION_MAX_CHARS {
+        description.push_str("...");
+    }
+    Prompt::new(prompt.name.clone(), Some(description), Some(arguments))
 }
 
 impl McpToolsService {
@@ -29,6 +122,8 @@ impl McpToolsService {
         let service = Self {
             node_commands_sender,
             node_name,
+            resource_subscriptions: Arc::new(Mutex::new(ResourceSubscriptions::default())),
+            serves_documents: true,
         };
 
         // Spawn a task to update the cache
@@ -42,6 +137,25 @@ impl McpToolsService {
         service
     }
 
+    /// A service for the unauthenticated SSE transport: it runs tools but refuses prompts and
+    /// resources, which expose the node's prompts and the parsed text of its files.
+    pub fn without_documents(&self) -> Self {
+        Self {
+            serves_documents: false,
+            ..self.clone()
+        }
+    }
+
+    fn require_documents(&self) -> Result<(), McpError> {
+        if self.serves_documents {
+            return Ok(());
+        }
+        Err(McpError::invalid_request(
+            "Prompts and resources are only available through the authenticated Streamable HTTP endpoint",
+            None,
+        ))
+    }
+
     /// Get the current list of tools from the cache
     pub fn list_tools(&self) -> Vec<Tool> {
         TOOLS_CACHE.read().expect("Failed to read tools cache").clone()
@@ -209,15 +323,90 @@ impl McpToolsService {
     }
 }
 
+impl McpToolsService {
+    async fn list_enabled_prompts(&self) -> Result<Vec<CustomPrompt>, McpError> {
+        send_node_command(&self.node_commands_sender, |res| NodeCommand::V2ApiListMcpPrompts { res }).await
+    }
+
+    /// Checks the subscribed resources of this session every few seconds and notifies the client
+    /// when one changes. Stops when the session is gone or has no subscriptions left.
+    fn spawn_resource_watcher(&self, peer: Peer<RoleServer>) {
+        let node_commands_sender = self.node_commands_sender.clone();
+        let subscriptions: Weak<Mutex<ResourceSubscriptions>> = Arc::downgrade(&self.resource_subscriptions);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(RESOURCE_WATCH_INTERVAL_SECS)).await;
+
+                let Some(state) = subscriptions.upgrade() else {
+                    return;
+                };
+                let watched: Vec<(String, String)> = {
+                    let mut state = state.lock().unwrap();
+                    if state.versions.is_empty() {
+                        state.watching = false;
+                        return;
+                    }
+                    state
+                        .versions
+                        .iter()
+                        .map(|(uri, version)| (uri.clone(), version.clone()))
+                        .collect()
+                };
+                drop(state);
+
+                for (uri, version) in watched {
+                    let current = send_node_command(&node_commands_sender, |res| {
+                        NodeCommand::V2ApiGetMcpResourceVersion { uri: uri.clone(), res }
+                    })
+                    .await;
+                    // Deleted resources are reported once as updated, reading them then fails
+                    let current = match current {
+                        Ok(current) => current.unwrap_or_default(),
+                        Err(e) => {
+                            tracing::warn!("Failed to check MCP resource {}: {:?}", uri, e);
+                            continue;
+                        }
+                    };
+                    if current == version {
+                        continue;
+                    }
+
+                    let Some(state) = subscriptions.upgrade() else {
+                        return;
+                    };
+                    let still_subscribed = match state.lock().unwrap().versions.get_mut(&uri) {
+                        Some(stored) => {
+                            *stored = current;
+                            true
+                        }
+                        None => false,
+                    };
+                    drop(state);
+                    if !still_subscribed {
+                        continue;
+                    }
+                    if let Err(e) = peer
+                        .notify_resource_updated(ResourceUpdatedNotificationParam { uri: uri.clone() })
+                        .await
+                    {
+                        tracing::debug!("Stopping MCP resource watcher, the client is gone: {:?}", e);
+                        if let Some(subscriptions) = subscriptions.upgrade() {
+                            subscriptions.lock().unwrap().watching = false;
+                        }
+                        return;
+                    }
+                }
+            }
+        });
+    }
+}
+
 #[async_trait]
 impl ServerHandler for McpToolsService {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::default(),
-            capabilities: ServerCapabilities::builder()
-                .enable_tools()
-                .enable_tool_list_changed()
-                .build(),
+            capabilities: server_capabilities(self.serves_documents),
             server_info: Implementation {
                 name: "Zoo MCP Server".to_string(),
                 version: "1.0.0".to_string(),
@@ -239,10 +428,7 @@ impl ServerHandler for McpToolsService {
         // Wrap existing logic in std::future::ready
         let result = InitializeResult {
             protocol_version: ProtocolVersion::default(),
-            capabilities: ServerCapabilities::builder()
-                .enable_tools()
-                .enable_tool_list_changed()
-                .build(),
+            capabilities: server_capabilities(self.serves_documents),
             server_info: Implementation {
                 name: "Zoo MCP Server".to_string(),
                 version: "1.0.0".to_string(),
@@ -258,8 +444,55 @@ impl ServerHandler for McpToolsService {
         _request: Option<PaginatedRequestParam>,
         _context: RequestContext<RoleServer>,
     ) -> impl Future<Output = Result<ListPromptsResult, ErrorData>> + Send + '_ {
-        // Use ErrorData and ListPromptsResult::default()
-        future::ready(Ok(ListPromptsResult::default()))
+        async move {
+            self.require_documents()?;
+            let prompts = self.list_enabled_prompts().await?;
+            Ok(ListPromptsResult {
+                prompts: prompts.iter().map(prompt_to_mcp).collect(),
+                next_cursor: None,
+            })
+        }
+    }
+
+    fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<GetPromptResult, ErrorData>> + Send + '_ {
+        async move {
+            self.require_documents()?;
+            let prompts = self.list_enabled_prompts().await?;
+            let prompt = prompts
+                .into_iter()
+                .find(|prompt| prompt.name == request.name)
+                .ok_or_else(|| McpError::invalid_params(format!("Prompt '{}' not found", request.name), None))?;
+
+            let arguments: HashMap<String, String> = request
+                .arguments
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, value)| match value {
+                    Value::String(value) => (name, value),
+                    value => (name, value.to_string()),
+                })
+                .collect();
+            let mut text = prompt.render(&arguments).map_err(|missing| {
+                McpError::invalid_params(
+                    format!("Prompt '{}' is missing arguments: {}", prompt.name, missing.join(", ")),
+                    None,
+                )
+            })?;
+            if !prompt.template_arguments().iter().any(|name| name == PROMPT_INPUT_ARGUMENT) {
+                if let Some(input) = arguments.get(PROMPT_INPUT_ARGUMENT).filter(|input| !input.is_empty()) {
+                    text = format!("{}\n\n{}", text, input);
+                }
+            }
+
+            Ok(GetPromptResult {
+                description: prompt_to_mcp(&prompt).description,
+                messages: vec![PromptMessage::new_text(PromptMessageRole::User, text)],
+            })
+        }
     }
 
     fn list_resources(
@@ -267,8 +500,114 @@ impl ServerHandler for McpToolsService {
         _request: Option<PaginatedRequestParam>,
         _context: RequestContext<RoleServer>,
     ) -> impl Future<Output = Result<ListResourcesResult, ErrorData>> + Send + '_ {
-        // Use ErrorData and ListResourcesResult::default()
-        future::ready(Ok(ListResourcesResult::default()))
+        async move {
+            self.require_documents()?;
+            let resources = send_node_command(&self.node_commands_sender, |res| {
+                NodeCommand::V2ApiListMcpResources { res }
+            })
+            .await?;
+
+            Ok(ListResourcesResult {
+                resources: resources
+                    .into_iter()
+                    .map(|resource| {
+                        let mut raw = RawResource::new(resource.uri, resource.name);
+                        raw.description = resource.description;
+                        raw.mime_type = resource.mime_type;
+                        raw.no_annotation()
+                    })
+                    .collect(),
+                next_cursor: None,
+            })
+        }
+    }
+
+    fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<ListResourceTemplatesResult, ErrorData>> + Send + '_ {
+        if let Err(e) = self.require_documents() {
+            return future::ready(Err(e));
+        }
+        let template = RawResourceTemplate {
+            uri_template: format!("{}{{path}}", MCP_FILE_URI_PREFIX),
+            name: "Vector FS file".to_string(),
+            description: Some("The parsed text of a file of the node vector FS".to_string()),
+            mime_type: Some("text/plain".to_string()),
+        };
+        future::ready(Ok(ListResourceTemplatesResult {
+            resource_templates: vec![template.no_annotation()],
+            next_cursor: None,
+        }))
+    }
+
+    fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<ReadResourceResult, ErrorData>> + Send + '_ {
+        async move {
+            self.require_documents()?;
+            let contents = send_node_command(&self.node_commands_sender, |res| NodeCommand::V2ApiReadMcpResource {
+                uri: request.uri.clone(),
+                res,
+            })
+            .await?;
+
+            Ok(ReadResourceResult {
+                contents: contents
+                    .chunks
+                    .into_iter()
+                    .map(|text| ResourceContents::TextResourceContents {
+                        uri: contents.uri.clone(),
+                        mime_type: contents.mime_type.clone(),
+                        text,
+                    })
+                    .collect(),
+            })
+        }
+    }
+
+    fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<(), ErrorData>> + Send + '_ {
+        async move {
+            self.require_documents()?;
+            let version = send_node_command(&self.node_commands_sender, |res| {
+                NodeCommand::V2ApiGetMcpResourceVersion {
+                    uri: request.uri.clone(),
+                    res,
+                }
+            })
+            .await?
+            .ok_or_else(|| McpError::resource_not_found(format!("Resource not found: {}", request.uri), None))?;
+
+            let start_watcher = {
+                let mut subscriptions = self.resource_subscriptions.lock().unwrap();
+                subscriptions.versions.insert(request.uri, version);
+                !std::mem::replace(&mut subscriptions.watching, true)
+            };
+            if start_watcher {
+                self.spawn_resource_watcher(context.peer.clone());
+            }
+            Ok(())
+        }
+    }
+
+    fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<(), ErrorData>> + Send + '_ {
+        self.resource_subscriptions
+            .lock()
+            .unwrap()
+            .versions
+            .remove(&request.uri);
+        future::ready(Ok(()))
     }
 
     // Override the call_tool method
@@ -383,6 +722,39 @@ impl ServerHandler for McpToolsService {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_to_mcp_arguments() {
+        let prompt = CustomPrompt {
+            rowid: Some(1),
+            name: "Translate".to_string(),
+            prompt: "Translate the following text to {{language}}.".to_string(),
+            is_system: false,
+            is_enabled: true,
+            version: "1".to_string(),
+            is_favorite: false,
+        };
+
+        let mcp_prompt = prompt_to_mcp(&prompt);
+        assert_eq!(mcp_prompt.name, "Translate");
+        let arguments = mcp_prompt.arguments.unwrap();
+        assert_eq!(arguments.len(), 2);
+        assert_eq!(arguments[0].name, "language");
+        assert_eq!(arguments[0].required, Some(true));
+        assert_eq!(arguments[1].name, PROMPT_INPUT_ARGUMENT);
+        assert_eq!(arguments[1].required, Some(false));
+    }
+
+    #[test]
+    fn test_unauthenticated_capabilities_have_no_documents() {
+        let capabilities = server_capabilities(false);
+        assert!(capabilities.tools.is_some());
+        assert!(capabilities.prompts.is_none());
+        assert!(capabilities.resources.is_none());
 
-    // Add tests as needed
+        let capabilities = server_capabilities(true);
+        assert!(capabilities.prompts.is_some());
+        assert!(capabilities.resources.is_some());
+    }
 }
@@ -1,13 +1,16 @@
 //! Model Context Protocol (MCP) Server-Sent Events (SSE) implementation.
 //!
-//! This module provides a Warp-based implementation of the MCP protocol using SSE.
+//! This module provides a Warp-based implementation of the MCP protocol using SSE, and of the
+//! Streamable HTTP transport that replaces it.
 
 mod api_sse_handlers;
 pub mod api_sse_routes;
+mod api_streamable_http_handlers;
 mod mcp_tools_service;
 
 // Re-export the public components
 pub use api_sse_routes::{mcp_sse_routes, SessionQuery};
 
 // Re-export the state for custom integrations
-pub use api_sse_handlers::McpState; 
\ No newline at end of file
+pub use api_sse_handlers::McpState;
+pub use api_streamable_http_handlers::StreamableHttpState; 
\ No newline at end of file
@@ -0,0 +1,207 @@
+use zoo_message_primitives::schemas::api_keys::ApiKeyScope;
+
+/// The scope a scoped API key needs to call `route` (see `api_route_name`). Routes that return
+/// `None` can only be called with the node API key: node settings, registration, wallet setup,
+/// OAuth tokens and the API keys themselves.
+pub fn required_api_key_scope(route: &str) -> Option<ApiKeyScope> {
+    let scope = match route {
+        // Jobs, their files, prompts and cron tasks
+        "last_messages"
+        | "last_messages_with_branches"
+        | "all_inboxes"
+        | "all_inboxes_paginated"
+        | "available_models"
+        | "get_job_config"
+        | "get_job_scope"
+        | "get_tooling_logs"
+        | "get_message_traces"
+        | "export_messages_from_inbox"
+        | "retrieve_path_simplified"
+        | "retrieve_vector_resource"
+        | "search_items"
+        | "download_file"
+        | "retrieve_files_for_job"
+        | "get_folder_name_for_job"
+        | "search_files_by_name"
+        | "list_all_cron_tasks"
+        | "get_specific_cron_task"
+        | "get_cron_task_logs"
+        | "get_cron_schedule"
+        | "export_cron_task"
+        | "get_all_custom_prompts"
+        | "get_custom_prompt"
+        | "search_custom_prompts"
+        | "search_messages" => ApiKeyScope::JobsRead,
+        "create_job"
+        | "job_message"
+        | "update_smart_inbox_name"
+        | "create_files_inbox"
+        | "add_file_to_inbox"
+        | "change_job_llm_provider"
+        | "update_job_config"
+        | "retry_message"
+        | "update_job_scope"
+        | "fork_job_messages"
+        | "remove_job"
+        | "stop_llm"
+        | "create_folder"
+        | "move_item"
+        | "copy_item"
+        | "move_folder"
+        | "copy_folder"
+        | "delete_folder"
+        | "delete_item"
+        | "upload_file_to_folder"
+        | "upload_file_to_job"
+        | "add_cron_task"
+        | "remove_cron_task"
+        | "update_cron_task"
+        | "force_execute_cron_task"
+        | "import_cron_task"
+        | "add_custom_prompt"
+        | "delete_custom_prompt"
+        | "update_custom_prompt" => ApiKeyScope::JobsWrite,
+        // OpenAI compatible API
+        "v1/models" => ApiKeyScope::JobsRead,
+        "v1/chat/completions" => ApiKeyScope::JobsWrite,
+
+        // Tools and MCP servers
+        "list_all_zoo_tools"
+        | "list_all_network_zoo_tools"
+        | "list_all_zoo_tools_versions"
+        | "tool_upgrade_plan"
+        | "get_zoo_tool"
+        | "get_zoo_tool_metadata"
+        | "search_zoo_tool"
+        | "tools_from_toolset"
+        | "tool_definitions"
+        | "tool_execution"
+        | "resolve_zoo_file_protocol"
+        | "check_default_tools_sync"
+        | "list_playground_tools"
+        | "get_playground_tool"
+        | "get_tool_offering"
+        | "get_tool_with_offering"
+        | "get_tools_with_offerings"
+        | "get_all_tool_offerings"
+        | "get_agent_network_offering" => ApiKeyScope::ToolsExecute,
+        "set_zoo_tool"
+        | "add_zoo_tool"
+        | "add_network_agent"
+        | "remove_tool"
+        | "duplicate_tool"
+        | "enable_all_tools"
+        | "disable_all_tools"
+        | "set_tool_enabled"
+        | "set_tool_active_version"
+        | "rollback_tool"
+        | "set_tool_mcp_enabled"
+        | "set_common_toolset_config"
+        | "tool_implementation"
+        | "tool_metadata_implementation"
+        | "tool_implementation_undo_to"
+        | "tool_implementation_code_update"
+        | "get_tool_implementation_prompt"
+        | "set_playground_tool"
+        | "remove_playground_tool"
+        | "playground_file"
+        | "tools_standalone_playground"
+        | "tool_check"
+        | "tool_asset"
+        | "list_tool_asset"
+        | "tool_store_proxy"
+        | "import_tool"
+        | "import_tool_zip"
+        | "code_execution"
+        | "export_tool"
+        | "publish_tool"
+        | "set_tool_offering"
+        | "remove_tool_offering"
+        | "mcp_servers"
+        | "list_mcp_servers"
+        | "add_mcp_server"
+        | "update_mcp_server"
+        | "delete_mcp_server"
+        | "set_enable_mcp_server"
+        | "import_mcp_server_from_github_url"
+        | "mcp_server_tools"
+        | "get_all_mcp_server_tools" => ApiKeyScope::ToolsAdmin,
+
+        // Wallet
+        "list_wallets" | "get_wallet_balance" => ApiKeyScope::WalletRead,
+        "pay_invoice" | "reject_invoice" => ApiKeyScope::WalletSpend,
+
+        // LLM providers, their usage and agents
+        "add_llm_provider"
+        | "modify_llm_provider"
+        | "remove_llm_provider"
+        | "test_llm_provider"
+        | "scan_ollama_models"
+        | "add_ollama_models"
+        | "get_usage"
+        | "get_usage_budgets"
+        | "set_usage_budget"
+        | "remove_usage_budget" => ApiKeyScope::LLMProvidersAdmin,
+        "add_agent"
+        | "update_agent"
+        | "remove_agent"
+        | "get_agent"
+        | "get_all_agents"
+        | "export_agent"
+        | "publish_agent"
+        | "import_agent"
+        | "import_agent_zip"
+        | "list_agent_memories"
+        | "update_agent_memory"
+        | "remove_agent_memory" => ApiKeyScope::AgentsAdmin,
+
+        _ => return None,
+    };
+    Some(scope)
+}
+
+/// The route of a request path as used by `required_api_key_scope`: the v2 route for the v2 API
+/// (`create_job` for `/v2/create_job`) and the whole path otherwise (`v1/chat/completions`).
+pub fn api_route_name(path: &str) -> &str {
+    let path = path.trim_start_matches('/');
+    match path.strip_prefix("v2/") {
+        Some(route) => route.split('/').next().unwrap_or_default(),
+        None => path.trim_end_matches('/'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_api_key_scope() {
+        assert_eq!(api_route_name("/v2/create_job"), "create_job");
+        assert_eq!(api_route_name("/v2/tool_asset/extra"), "tool_asset");
+        assert_eq!(api_route_name("/v1/chat/completions"), "v1/chat/completions");
+
+        assert_eq!(required_api_key_scope("create_job"), Some(ApiKeyScope::JobsWrite));
+        assert_eq!(required_api_key_scope("last_messages"), Some(ApiKeyScope::JobsRead));
+        assert_eq!(
+            required_api_key_scope("tool_execution"),
+            Some(ApiKeyScope::ToolsExecute)
+        );
+        // Running arbitrary code is more than running the installed tools
+        assert_eq!(required_api_key_scope("code_execution"), Some(ApiKeyScope::ToolsAdmin));
+        assert_eq!(required_api_key_scope("pay_invoice"), Some(ApiKeyScope::WalletSpend));
+        assert_eq!(required_api_key_scope("remove_agent"), Some(ApiKeyScope::AgentsAdmin));
+        assert_eq!(
+            required_api_key_scope("update_agent_memory"),
+            Some(ApiKeyScope::AgentsAdmin)
+        );
+        assert_eq!(
+            required_api_key_scope("v1/chat/completions"),
+            Some(ApiKeyScope::JobsWrite)
+        );
+
+        // Only the node API key manages the node and the API keys
+        assert_eq!(required_api_key_scope("set_preferences"), None);
+        assert_eq!(required_api_key_scope("create_api_key"), None);
+        assert_eq!(required_api_key_scope("restore_local_wallet"), None);
+    }
+}
@@ -0,0 +1,172 @@
+use async_channel::Sender;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use utoipa::{OpenApi, ToSchema};
+use warp::Filter;
+use zoo_message_primitives::schemas::api_keys::{ApiKey, ApiKeyScope, CreateApiKeyRequest, CreatedApiKey};
+
+use super::api_v2_router::{create_success_response, with_sender};
+use crate::{node_api_router::APIError, node_commands::NodeCommand};
+
+#[derive(Deserialize, ToSchema)]
+pub struct RevokeApiKeyRequest {
+    pub id: String,
+}
+
+pub fn api_key_routes(
+    node_commands_sender: Sender<NodeCommand>,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let create_api_key_route = warp::path("create_api_key")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(create_api_key_handler);
+
+    let list_api_keys_route = warp::path("list_api_keys")
+        .and(warp::get())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and_then(list_api_keys_handler);
+
+    let revoke_api_key_route = warp::path("revoke_api_key")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(revoke_api_key_handler);
+
+    create_api_key_route.or(list_api_keys_route).or(revoke_api_key_route)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/create_api_key",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 200, description = "Successfully created the API key. The key is only returned here", body = CreatedApiKey),
+        (status = 400, description = "Bad request", body = APIError),
+        (status = 401, description = "Only the node API key can manage API keys", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn create_api_key_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: CreateApiKeyRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiCreateApiKey {
+            bearer,
+            request: payload,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/list_api_keys",
+    responses(
+        (status = 200, description = "Successfully listed the API keys, revoked ones included", body = Vec<ApiKey>),
+        (status = 401, description = "Only the node API key can manage API keys", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn list_api_keys_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiListApiKeys {
+            bearer,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/revoke_api_key",
+    request_body = RevokeApiKeyRequest,
+    responses(
+        (status = 200, description = "Successfully revoked the API key", body = Value),
+        (status = 401, description = "Only the node API key can manage API keys", body = APIError),
+        (status = 404, description = "API key not found or already revoked", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn revoke_api_key_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: RevokeApiKeyRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiRevokeApiKey {
+            bearer,
+            id: payload.id,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        create_api_key_handler,
+        list_api_keys_handler,
+        revoke_api_key_handler
+    ),
+    components(
+        schemas(APIError, ApiKey, ApiKeyScope, CreateApiKeyRequest, CreatedApiKey, RevokeApiKeyRequest)
+    ),
+    tags(
+        (name = "api_keys", description = "Scoped API Keys API endpoints")
+    )
+)]
+pub struct ApiKeysApiDoc;
@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+
+use async_channel::Sender;
+use bytes::Buf;
+use futures::TryStreamExt;
+use reqwest::StatusCode;
+use utoipa::OpenApi;
+use warp::filters::multipart::FormData;
+use warp::Filter;
+use zoo_message_primitives::schemas::node_backup::{
+    BackupManifest, BackupSection, CreateNodeBackupRequest, NodeBackupInfo, RestoreNodeBackupReport,
+    RestoreNodeBackupRequest,
+};
+
+use super::api_v2_router::{create_success_response, with_sender};
+use crate::{node_api_router::APIError, node_commands::NodeCommand};
+
+/// Backups hold the whole database and vector FS, so uploads get a much larger limit than tool
+/// or agent zips.
+const MAX_BACKUP_UPLOAD_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+pub fn backup_routes(
+    node_commands_sender: Sender<NodeCommand>,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let create_node_backup_route = warp::path("create_node_backup")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(create_node_backup_handler);
+
+    let list_node_backups_route = warp::path("list_node_backups")
+        .and(warp::get())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and_then(list_node_backups_handler);
+
+    let download_node_backup_route = warp::path("download_node_backup")
+        .and(warp::get())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(download_node_backup_handler);
+
+    let upload_node_backup_route = warp::path("upload_node_backup")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::multipart::form().max_length(MAX_BACKUP_UPLOAD_BYTES))
+        .and_then(upload_node_backup_handler);
+
+    let restore_node_backup_route = warp::path("restore_node_backup")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(restore_node_backup_handler);
+
+    create_node_backup_route
+        .or(list_node_backups_route)
+        .or(download_node_backup_route)
+        .or(upload_node_backup_route)
+        .or(restore_node_backup_route)
+}
+
+fn error_reply(error: APIError) -> warp::reply::WithStatus<warp::reply::Json> {
+    let status = StatusCode::from_u16(error.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    warp::reply::with_status(warp::reply::json(&error), status)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/create_node_backup",
+    request_body = CreateNodeBackupRequest,
+    responses(
+        (status = 200, description = "Successfully created the backup in the backups folder of the node", body = NodeBackupInfo),
+        (status = 400, description = "Bad request", body = APIError),
+        (status = 401, description = "Only the node API key can manage backups", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn create_node_backup_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: CreateNodeBackupRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiCreateNodeBackup {
+            bearer,
+            request: payload,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(error_reply(error)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/list_node_backups",
+    responses(
+        (status = 200, description = "Successfully listed the backups, newest first", body = Vec<NodeBackupInfo>),
+        (status = 401, description = "Only the node API key can manage backups", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn list_node_backups_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiListNodeBackups {
+            bearer,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(error_reply(error)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/download_node_backup",
+    params(
+        ("file_name" = String, Query, description = "File name of the backup, as listed by list_node_backups")
+    ),
+    responses(
+        (status = 200, description = "The backup archive", body = Vec<u8>),
+        (status = 400, description = "Invalid backup file name", body = APIError),
+        (status = 401, description = "Only the node API key can manage backups", body = APIError),
+        (status = 404, description = "Backup not found", body = APIError)
+    )
+)]
+pub async fn download_node_backup_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    query_params: HashMap<String, String>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let file_name = query_params
+        .get("file_name")
+        .ok_or_else(|| {
+            warp::reject::custom(APIError {
+                code: 400,
+                error: "Invalid backup file name".to_string(),
+                message: "file_name is required".to_string(),
+            })
+        })?
+        .to_string();
+
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiDownloadNodeBackup {
+            bearer,
+            file_name: file_name.clone(),
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(file_bytes) => Ok(warp::reply::with_header(
+            warp::reply::with_header(
+                warp::reply::with_status(file_bytes, StatusCode::OK),
+                "Content-Type",
+                "application/octet-stream",
+            ),
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", file_name),
+        )),
+        Err(error) => Ok(warp::reply::with_header(
+            warp::reply::with_header(
+                warp::reply::with_status(
+                    error.message.as_bytes().to_vec(),
+                    StatusCode::from_u16(error.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                ),
+                "Content-Type",
+                "text/plain",
+            ),
+            "Content-Disposition",
+            "inline".to_string(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/upload_node_backup",
+    responses(
+        (status = 200, description = "Successfully checked and stored the backup, restore it with restore_node_backup", body = NodeBackupInfo),
+        (status = 400, description = "The archive is missing, invalid, or its signature or hashes don't match", body = APIError),
+        (status = 401, description = "Only the node API key can manage backups", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn upload_node_backup_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    mut form: FormData,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+
+    let mut file_data: Option<Vec<u8>> = None;
+    while let Ok(Some(part)) = form.try_next().await {
+        if part.name() == "file" {
+            let mut bytes = Vec::new();
+            let mut stream = part.stream();
+            while let Ok(Some(chunk)) = stream.try_next().await {
+                bytes.extend_from_slice(chunk.chunk());
+            }
+            file_data = Some(bytes);
+        }
+    }
+
+    let file_data = match file_data {
+        Some(data) if !data.is_empty() => data,
+        _ => {
+            return Ok(error_reply(APIError {
+                code: 400,
+                error: "Missing file".to_string(),
+                message: "The backup archive is required in the file field".to_string(),
+            }))
+        }
+    };
+
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiUploadNodeBackup {
+            bearer,
+            file_data,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(error_reply(error)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/restore_node_backup",
+    request_body = RestoreNodeBackupRequest,
+    responses(
+        (status = 200, description = "Successfully checked the backup and restored the selected sections", body = RestoreNodeBackupReport),
+        (status = 400, description = "Invalid backup, wrong passphrase or unknown section", body = APIError),
+        (status = 401, description = "Only the node API key can manage backups", body = APIError),
+        (status = 403, description = "The backup was signed by another node and trusted_signer doesn't name its key", body = APIError),
+        (status = 404, description = "Backup not found", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn restore_node_backup_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: RestoreNodeBackupRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiRestoreNodeBackup {
+            bearer,
+            request: payload,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(error_reply(error)),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        create_node_backup_handler,
+        list_node_backups_handler,
+        download_node_backup_handler,
+        upload_node_backup_handler,
+        restore_node_backup_handler
+    ),
+    components(
+        schemas(APIError, BackupManifest, BackupSection, CreateNodeBackupRequest, NodeBackupInfo,
+            RestoreNodeBackupReport, RestoreNodeBackupRequest)
+    ),
+    tags(
+        (name = "backup", description = "Node backup and restore API endpoints")
+    )
+)]
+pub struct BackupApiDoc;
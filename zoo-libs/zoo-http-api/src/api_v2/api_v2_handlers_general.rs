@@ -4,6 +4,9 @@ use futures::TryStreamExt;
 use reqwest::StatusCode;
 use serde::Deserialize;
 use serde_json::json;
+use zoo_message_primitives::schemas::agent_memory::{
+    AgentMemory, AgentMemorySource, RemoveAgentMemoryRequest, UpdateAgentMemoryRequest
+};
 use zoo_message_primitives::schemas::llm_providers::agent::Agent;
 use zoo_message_primitives::schemas::llm_providers::serialized_llm_provider::{
     Exo, Gemini, Groq, LLMProviderInterface, Ollama, OpenAI, ZooBackend
@@ -216,6 +219,27 @@ pub fn general_routes(
         .and(warp::multipart::form().max_length(50 * 1024 * 1024))
         .and_then(import_agent_zip_handler);
 
+    let list_agent_memories_route = warp::path("list_agent_memories")
+        .and(warp::get())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(list_agent_memories_handler);
+
+    let update_agent_memory_route = warp::path("update_agent_memory")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(update_agent_memory_handler);
+
+    let remove_agent_memory_route = warp::path("remove_agent_memory")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(remove_agent_memory_handler);
+
     let test_llm_provider_route = warp::path("test_llm_provider")
         .and(warp::post())
         .and(with_sender(node_commands_sender.clone()))
@@ -312,6 +336,9 @@ pub fn general_routes(
         .or(publish_agent_route)
         .or(import_agent_route)
         .or(import_agent_zip_route)
+        .or(list_agent_memories_route)
+        .or(update_agent_memory_route)
+        .or(remove_agent_memory_route)
         .or(test_llm_provider_route)
         .or(add_regex_pattern_route)
         .or(compute_quests_status_route)
@@ -1053,6 +1080,122 @@ pub async fn get_all_agents_handler(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/v2/list_agent_memories",
+    params(
+        ("agent_id" = String, Query, description = "Agent identifier")
+    ),
+    responses(
+        (status = 200, description = "The knowledge and the remembered facts of the agent", body = Vec<AgentMemory>),
+        (status = 400, description = "Invalid agent identifier", body = APIError),
+        (status = 404, description = "Agent not found", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn list_agent_memories_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    query_params: HashMap<String, String>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let agent_id = query_params
+        .get("agent_id")
+        .ok_or_else(|| {
+            warp::reject::custom(APIError {
+                code: 400,
+                error: "Invalid agent identifier".to_string(),
+                message: "Agent identifier is required".to_string(),
+            })
+        })?
+        .to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiListAgentMemories {
+            bearer,
+            agent_id,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(memories) => Ok(warp::reply::json(&memories)),
+        Err(error) => Err(warp::reject::custom(error)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/update_agent_memory",
+    request_body = UpdateAgentMemoryRequest,
+    responses(
+        (status = 200, description = "Successfully updated agent memory", body = AgentMemory),
+        (status = 400, description = "Invalid memory content", body = APIError),
+        (status = 404, description = "Memory not found", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn update_agent_memory_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: UpdateAgentMemoryRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiUpdateAgentMemory {
+            bearer,
+            request: payload,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(memory) => Ok(warp::reply::json(&memory)),
+        Err(error) => Err(warp::reject::custom(error)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/remove_agent_memory",
+    request_body = RemoveAgentMemoryRequest,
+    responses(
+        (status = 200, description = "Successfully removed agent memory", body = String),
+        (status = 404, description = "Memory not found", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn remove_agent_memory_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: RemoveAgentMemoryRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiRemoveAgentMemory {
+            bearer,
+            request: payload,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => Ok(warp::reply::json(&response)),
+        Err(error) => Err(warp::reject::custom(error)),
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/v2/export_agent",
@@ -1627,6 +1770,9 @@ pub async fn docker_status_handler(sender: Sender<NodeCommand>) -> Result<impl w
         export_agent_handler,
         get_agent_handler,
         get_all_agents_handler,
+        list_agent_memories_handler,
+        update_agent_memory_handler,
+        remove_agent_memory_handler,
         test_llm_provider_handler,
         add_regex_pattern_handler,
         compute_quests_status_handler,
@@ -1643,7 +1789,8 @@ pub async fn docker_status_handler(sender: Sender<NodeCommand>) -> Result<impl w
             ZooSubidentityType, ZooBackend, InternalMetadata, MessageData, StopLLMRequest,
             NodeApiData, EncryptedZooData, ZooData, MessageSchemaType,
             APIUseRegistrationCodeSuccessResponse, GetPublicKeysResponse, APIError, Agent,
-            AddRegexPatternRequest, QuotaResponse)
+            AddRegexPatternRequest, QuotaResponse, AgentMemory, AgentMemorySource,
+            UpdateAgentMemoryRequest, RemoveAgentMemoryRequest)
     ),
     tags(
         (name = "general", description = "General API endpoints")
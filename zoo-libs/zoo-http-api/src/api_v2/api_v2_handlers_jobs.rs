@@ -6,7 +6,7 @@ use serde::Deserialize;
 use serde_json::json;
 use zoo_message_primitives::{
     schemas::{
-        job_config::JobConfig, llm_providers::serialized_llm_provider::{
+        job_config::{ConversationMemoryStrategy, JobConfig}, message_search::{MessageSearchMatch, MessageSearchResult, SearchMessagesRequest}, llm_providers::serialized_llm_provider::{
             Exo, Gemini, Groq, LLMProviderInterface, Ollama, OpenAI, SerializedLLMProvider, ZooBackend
         }, zoo_name::{ZooName, ZooSubidentityType}, smart_inbox::{LLMProviderSubset, V2SmartInbox}
     }, zoo_message::{
@@ -188,6 +188,13 @@ pub fn job_routes(
         .and(warp::query::<GetJobProviderRequest>())
         .and_then(get_job_provider_handler);
 
+    let search_messages_route = warp::path("search_messages")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(search_messages_handler);
+
     create_job_route
         .or(job_message_route)
         .or(get_last_messages_route)
@@ -211,6 +218,7 @@ pub fn job_routes(
         .or(export_messages_from_inbox_route)
         .or(add_messages_god_mode_route)
         .or(get_job_provider_route)
+        .or(search_messages_route)
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -1368,6 +1376,45 @@ pub async fn get_job_provider_handler(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/v2/search_messages",
+    request_body = SearchMessagesRequest,
+    responses(
+        (status = 200, description = "Successfully searched the messages, most relevant first", body = Vec<MessageSearchResult>),
+        (status = 400, description = "Bad request", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn search_messages_handler(
+    node_commands_sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: SearchMessagesRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    node_commands_sender
+        .send(NodeCommand::V2ApiSearchMessages {
+            bearer,
+            request: payload,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
 #[derive(OpenApi)]
 #[openapi(
     paths(
@@ -1390,6 +1437,7 @@ pub async fn get_job_provider_handler(
         get_message_traces_handler,
         fork_job_messages_handler,
         remove_job_handler,
+        search_messages_handler,
     ),
     components(
         schemas(AddFileToFolder, V2SmartInbox, APIChangeJobAgentRequest, CreateJobRequest, JobConfig,
@@ -1397,7 +1445,8 @@ pub async fn get_job_provider_handler(
             UpdateJobConfigRequest, UpdateSmartInboxNameRequest, SerializedLLMProvider, JobCreationInfo,
             JobMessage, NodeApiData, LLMProviderSubset, AssociatedUI, MinimalJobScope, CallbackAction, ZooName,
             LLMProviderInterface, RetryMessageRequest, UpdateJobScopeRequest, ExportInboxMessagesFormat, ExportInboxMessagesRequest,
-            ZooSubidentityType, OpenAI, Ollama, Groq, Gemini, Exo, ZooBackend, SendResponseBody, SendResponseBodyData, APIError, GetToolingLogsRequest, GetMessageTracesRequest, ForkJobMessagesRequest, RemoveJobRequest)
+            ZooSubidentityType, OpenAI, Ollama, Groq, Gemini, Exo, ZooBackend, SendResponseBody, SendResponseBodyData, APIError, GetToolingLogsRequest, GetMessageTracesRequest, ForkJobMessagesRequest, RemoveJobRequest,
+            SearchMessagesRequest, MessageSearchResult, MessageSearchMatch, ConversationMemoryStrategy)
     ),
     tags(
         (name = "jobs", description = "Job API endpoints")
@@ -1,5 +1,6 @@
 use async_channel::Sender;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use zoo_message_primitives::schemas::mcp_server::{MCPServer, MCPServerEnv, MCPServerType};
 use utoipa::{OpenApi, ToSchema};
 use warp::Filter;
@@ -39,6 +40,13 @@ pub struct GetAllMCPServerToolsRequest {
     pub mcp_server_id: i64,
 }
 
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct GetMCPServerLogsRequest {
+    pub mcp_server_id: i64,
+    /// Most recent entries to return, 200 by default.
+    pub limit: Option<usize>,
+}
+
 #[derive(Deserialize, ToSchema, Debug)]
 pub struct DeleteMCPServerRequest {
     pub mcp_server_id: i64,
@@ -94,6 +102,13 @@ pub fn mcp_server_routes(
         .and(warp::query::<GetAllMCPServerToolsRequest>())
         .and_then(get_all_mcp_server_tools_handler);
 
+    let get_mcp_server_logs_route = warp::path("mcp_server_logs")
+        .and(warp::get())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::query::<GetMCPServerLogsRequest>())
+        .and_then(get_mcp_server_logs_handler);
+
     let delete_mcp_server_route = warp::path("delete_mcp_server")
         .and(warp::post())
         .and(with_sender(node_commands_sender.clone()))
@@ -111,6 +126,7 @@ pub fn mcp_server_routes(
     list_mcp_servers_route
         .or(add_mcp_server_route)
         .or(get_all_mcp_server_tools_route)
+        .or(get_mcp_server_logs_route)
         .or(delete_mcp_server_route)
         .or(import_mcp_server_from_github_url_route)
         .or(set_enable_mcp_server_route)
@@ -248,6 +264,44 @@ pub async fn get_all_mcp_server_tools_handler(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/v2/mcp_server_logs",
+    params(
+        ("mcp_server_id" = i64, Query, description = "ID of the MCP server"),
+        ("limit" = Option<usize>, Query, description = "Most recent entries to return, 200 by default")
+    ),
+    responses(
+        (status = 200, description = "Session status and captured stderr, server and session logs of the MCP server", body = Value),
+        (status = 400, description = "Bad request", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn get_mcp_server_logs_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: GetMCPServerLogsRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiGetMCPServerLogs {
+            bearer,
+            mcp_server_id: payload.mcp_server_id,
+            limit: payload.limit,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => Ok(warp::reply::json(&response)),
+        Err(error) => Err(warp::reject::custom(error)),
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/v2/import_mcp_server_from_github_url",
@@ -357,6 +411,7 @@ pub async fn update_mcp_server_handler(
         list_mcp_servers_handler,
         add_mcp_server_handler,
         get_all_mcp_server_tools_handler,
+        get_mcp_server_logs_handler,
         import_mcp_server_from_github_url_handler,
         delete_mcp_server_handler,
         set_enable_mcp_server_handler,
@@ -0,0 +1,319 @@
+use async_channel::Sender;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use utoipa::{OpenApi, ToSchema};
+use warp::Filter;
+use zoo_message_primitives::schemas::secrets::{
+    ExportedSecret, RotateSecretsKeyRequest, SecretInfo, SecretLocation, SecretsExport, SecretsKeyInfo,
+    SecretsKeySourceKind, SetSecretRequest,
+};
+
+use super::api_v2_router::{create_success_response, with_sender};
+use crate::{node_api_router::APIError, node_commands::NodeCommand};
+
+#[derive(Deserialize, ToSchema)]
+pub struct RemoveSecretRequest {
+    pub name: String,
+}
+
+pub fn secret_routes(
+    node_commands_sender: Sender<NodeCommand>,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let set_secret_route = warp::path("set_secret")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(set_secret_handler);
+
+    let list_secrets_route = warp::path("list_secrets")
+        .and(warp::get())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and_then(list_secrets_handler);
+
+    let remove_secret_route = warp::path("remove_secret")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(remove_secret_handler);
+
+    let get_secrets_key_route = warp::path("get_secrets_key")
+        .and(warp::get())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and_then(get_secrets_key_handler);
+
+    let rotate_secrets_key_route = warp::path("rotate_secrets_key")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(rotate_secrets_key_handler);
+
+    let export_secrets_route = warp::path("export_secrets")
+        .and(warp::get())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and_then(export_secrets_handler);
+
+    set_secret_route
+        .or(list_secrets_route)
+        .or(remove_secret_route)
+        .or(get_secrets_key_route)
+        .or(rotate_secrets_key_route)
+        .or(export_secrets_route)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/set_secret",
+    request_body = SetSecretRequest,
+    responses(
+        (status = 200, description = "Successfully stored the secret. Reference it in tool configs as secret://<name>", body = SecretInfo),
+        (status = 400, description = "Bad request", body = APIError),
+        (status = 401, description = "Only the node API key can manage secrets", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError),
+        (status = 503, description = "The secrets vault is locked", body = APIError)
+    )
+)]
+pub async fn set_secret_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: SetSecretRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiSetSecret {
+            bearer,
+            request: payload,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/list_secrets",
+    responses(
+        (status = 200, description = "Successfully listed the secrets, without their values", body = Vec<SecretInfo>),
+        (status = 401, description = "Only the node API key can manage secrets", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn list_secrets_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiListSecrets {
+            bearer,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/remove_secret",
+    request_body = RemoveSecretRequest,
+    responses(
+        (status = 200, description = "Successfully removed the secret", body = Value),
+        (status = 401, description = "Only the node API key can manage secrets", body = APIError),
+        (status = 404, description = "Secret not found", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn remove_secret_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: RemoveSecretRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiRemoveSecret {
+            bearer,
+            name: payload.name,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/get_secrets_key",
+    responses(
+        (status = 200, description = "The data key currently used to encrypt the secrets", body = SecretsKeyInfo),
+        (status = 401, description = "Only the node API key can manage secrets", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError),
+        (status = 503, description = "The secrets vault is locked", body = APIError)
+    )
+)]
+pub async fn get_secrets_key_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiGetSecretsKey {
+            bearer,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/rotate_secrets_key",
+    request_body = RotateSecretsKeyRequest,
+    responses(
+        (status = 200, description = "Successfully rotated the data key and re-encrypted all the stored secrets", body = SecretsKeyInfo),
+        (status = 400, description = "Bad request", body = APIError),
+        (status = 401, description = "Only the node API key can manage secrets", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError),
+        (status = 503, description = "The secrets vault is locked", body = APIError)
+    )
+)]
+pub async fn rotate_secrets_key_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: RotateSecretsKeyRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiRotateSecretsKey {
+            bearer,
+            request: payload,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/export_secrets",
+    responses(
+        (status = 200, description = "All the stored secrets, encrypted. The export never contains plaintext values", body = SecretsExport),
+        (status = 401, description = "Only the node API key can manage secrets", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError),
+        (status = 503, description = "The secrets vault is locked", body = APIError)
+    )
+)]
+pub async fn export_secrets_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiExportSecrets {
+            bearer,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        set_secret_handler,
+        list_secrets_handler,
+        remove_secret_handler,
+        get_secrets_key_handler,
+        rotate_secrets_key_handler,
+        export_secrets_handler
+    ),
+    components(
+        schemas(APIError, ExportedSecret, RemoveSecretRequest, RotateSecretsKeyRequest, SecretInfo, SecretLocation,
+            SecretsExport, SecretsKeyInfo, SecretsKeySourceKind, SetSecretRequest)
+    ),
+    tags(
+        (name = "secrets", description = "Secrets Vault API endpoints")
+    )
+)]
+pub struct SecretsApiDoc;
@@ -304,6 +304,27 @@ pub fn tool_routes(
         .and(warp::body::json())
         .and_then(set_tool_enabled_handler);
 
+    let tool_upgrade_plan_route = warp::path("tool_upgrade_plan")
+        .and(warp::get())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(tool_upgrade_plan_handler);
+
+    let rollback_tool_route = warp::path("rollback_tool")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(rollback_tool_handler);
+
+    let set_tool_active_version_route = warp::path("set_tool_active_version")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(set_tool_active_version_handler);
+
     let set_tool_mcp_enabled_route = warp::path("set_tool_mcp_enabled")
         .and(warp::post())
         .and(with_sender(node_commands_sender.clone()))
@@ -370,6 +391,9 @@ pub fn tool_routes(
         .or(standalone_playground_route)
         .or(list_all_zoo_tools_versions_route)
         .or(set_tool_enabled_route)
+        .or(tool_upgrade_plan_route)
+        .or(rollback_tool_route)
+        .or(set_tool_active_version_route)
         .or(set_tool_mcp_enabled_route)
         .or(copy_tool_asset_route)
         .or(tool_check_route)
@@ -465,6 +489,9 @@ pub struct ToolExecutionRequest {
     #[serde(default = "default_map")]
     pub extra_config: Value,
     pub mounts: Option<Vec<String>>,
+    /// The most a network tool can charge for this call, in the atomic units of its payment asset.
+    /// Paid network tools aren't run without it.
+    pub max_price: Option<u64>,
 }
 
 #[utoipa::path(
@@ -522,6 +549,7 @@ pub async fn tool_execution_handler(
             llm_provider: payload.llm_provider.clone(),
             extra_config,
             mounts: payload.mounts,
+            max_price: payload.max_price,
             res: res_sender,
         })
         .await
@@ -2457,6 +2485,161 @@ pub async fn set_tool_enabled_handler(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/v2/tool_upgrade_plan",
+    params(
+        ("tool_router_key" = String, Query, description = "Tool router key of the tool to upgrade"),
+        ("from_version" = Option<String>, Query, description = "Installed version to upgrade from, the active one by default"),
+        ("to_version" = Option<String>, Query, description = "Installed version to upgrade to, the latest one by default")
+    ),
+    responses(
+        (status = 200, description = "Schema changes between the two versions and the agents they affect", body = Value),
+        (status = 400, description = "Bad request", body = APIError),
+        (status = 404, description = "Tool or version not installed", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn tool_upgrade_plan_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    query_params: HashMap<String, String>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let tool_router_key = query_params
+        .get("tool_router_key")
+        .ok_or_else(|| {
+            warp::reject::custom(APIError {
+                code: 400,
+                error: "Invalid Query".to_string(),
+                message: "The request query string is invalid, missing tool_router_key.".to_string(),
+            })
+        })?
+        .to_string();
+
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiGetToolUpgradePlan {
+            bearer,
+            tool_router_key,
+            from_version: query_params.get("from_version").cloned(),
+            to_version: query_params.get("to_version").cloned(),
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RollbackToolRequest {
+    pub tool_router_key: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/rollback_tool",
+    request_body = RollbackToolRequest,
+    responses(
+        (status = 200, description = "Made the previous installed version of the tool active", body = Value),
+        (status = 400, description = "Bad request", body = APIError),
+        (status = 404, description = "Tool not found", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn rollback_tool_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: RollbackToolRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+
+    sender
+        .send(NodeCommand::V2ApiRollbackTool {
+            bearer,
+            tool_router_key: payload.tool_router_key,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetToolActiveVersionRequest {
+    pub tool_router_key: String,
+    /// Installed version to make active. Without one the tool follows its latest version again.
+    pub version: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/set_tool_active_version",
+    request_body = SetToolActiveVersionRequest,
+    responses(
+        (status = 200, description = "Successfully set the active version of the tool", body = Value),
+        (status = 400, description = "Bad request", body = APIError),
+        (status = 404, description = "Tool or version not installed", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn set_tool_active_version_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: SetToolActiveVersionRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+
+    sender
+        .send(NodeCommand::V2ApiSetToolActiveVersion {
+            bearer,
+            tool_router_key: payload.tool_router_key,
+            version: payload.version,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap(),
+        )),
+    }
+}
+
 #[derive(Deserialize, ToSchema)]
 pub struct SetToolMcpEnabledRequest {
     pub tool_router_key: String,
@@ -2762,6 +2945,9 @@ pub async fn tool_check_handler(
         tool_store_proxy_handler,
         standalone_playground_handler,
         set_tool_enabled_handler,
+        tool_upgrade_plan_handler,
+        rollback_tool_handler,
+        set_tool_active_version_handler,
         set_tool_mcp_enabled_handler,
         copy_tool_assets_handler,
         tool_check_handler,
@@ -2775,6 +2961,8 @@ pub async fn tool_check_handler(
             APIError,
             ToolExecutionRequest,
             SetToolEnabledRequest,
+            RollbackToolRequest,
+            SetToolActiveVersionRequest,
             SetToolMcpEnabledRequest,
             GetZooToolMetadataResponse,
             SetCommonToolSetConfigRequest,
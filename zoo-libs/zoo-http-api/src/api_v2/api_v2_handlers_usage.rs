@@ -0,0 +1,245 @@
+use async_channel::Sender;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use utoipa::{OpenApi, ToSchema};
+use warp::Filter;
+use zoo_message_primitives::schemas::usage::{
+    BudgetEnforcement, BudgetPeriod, BudgetScope, UsageBudget, UsageBudgetStatus, UsageByLLMProvider, UsageQuery,
+    UsageSummary, UsageTotals,
+};
+
+use super::api_v2_router::{create_success_response, with_sender};
+use crate::{node_api_router::APIError, node_commands::NodeCommand};
+
+#[derive(Deserialize, ToSchema)]
+pub struct RemoveUsageBudgetRequest {
+    pub scope: BudgetScope,
+    pub scope_id: String,
+    pub period: BudgetPeriod,
+}
+
+pub fn usage_routes(
+    node_commands_sender: Sender<NodeCommand>,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let get_usage_route = warp::path("get_usage")
+        .and(warp::get())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::query::<UsageQuery>())
+        .and_then(get_usage_handler);
+
+    let get_usage_budgets_route = warp::path("get_usage_budgets")
+        .and(warp::get())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and_then(get_usage_budgets_handler);
+
+    let set_usage_budget_route = warp::path("set_usage_budget")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(set_usage_budget_handler);
+
+    let remove_usage_budget_route = warp::path("remove_usage_budget")
+        .and(warp::post())
+        .and(with_sender(node_commands_sender.clone()))
+        .and(warp::header::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(remove_usage_budget_handler);
+
+    get_usage_route
+        .or(get_usage_budgets_route)
+        .or(set_usage_budget_route)
+        .or(remove_usage_budget_route)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/get_usage",
+    params(
+        ("profile" = Option<String>, Query, description = "Only the usage of this profile"),
+        ("agent_id" = Option<String>, Query, description = "Only the usage of this agent"),
+        ("job_id" = Option<String>, Query, description = "Only the usage of this job"),
+        ("llm_provider_id" = Option<String>, Query, description = "Only the usage of this LLM provider"),
+        ("from" = Option<String>, Query, description = "RFC3339 start of the time range"),
+        ("to" = Option<String>, Query, description = "RFC3339 end of the time range")
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved usage", body = UsageSummary),
+        (status = 400, description = "Bad request", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn get_usage_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    query: UsageQuery,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiGetUsage {
+            bearer,
+            query,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/get_usage_budgets",
+    responses(
+        (status = 200, description = "Successfully retrieved usage budgets and their current usage", body = Vec<UsageBudgetStatus>),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn get_usage_budgets_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiGetUsageBudgets {
+            bearer,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/set_usage_budget",
+    request_body = UsageBudget,
+    responses(
+        (status = 200, description = "Successfully set usage budget", body = UsageBudgetStatus),
+        (status = 400, description = "Bad request", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn set_usage_budget_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: UsageBudget,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiSetUsageBudget {
+            bearer,
+            budget: payload,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/remove_usage_budget",
+    request_body = RemoveUsageBudgetRequest,
+    responses(
+        (status = 200, description = "Successfully removed usage budget", body = Value),
+        (status = 404, description = "Usage budget not found", body = APIError),
+        (status = 500, description = "Internal server error", body = APIError)
+    )
+)]
+pub async fn remove_usage_budget_handler(
+    sender: Sender<NodeCommand>,
+    authorization: String,
+    payload: RemoveUsageBudgetRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let bearer = authorization.strip_prefix("Bearer ").unwrap_or("").to_string();
+    let (res_sender, res_receiver) = async_channel::bounded(1);
+    sender
+        .send(NodeCommand::V2ApiRemoveUsageBudget {
+            bearer,
+            scope: payload.scope,
+            scope_id: payload.scope_id,
+            period: payload.period,
+            res: res_sender,
+        })
+        .await
+        .map_err(|_| warp::reject::reject())?;
+    let result = res_receiver.recv().await.map_err(|_| warp::reject::reject())?;
+
+    match result {
+        Ok(response) => {
+            let response = create_success_response(response);
+            Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::OK))
+        }
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&error),
+            StatusCode::from_u16(error.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        )),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_usage_handler,
+        get_usage_budgets_handler,
+        set_usage_budget_handler,
+        remove_usage_budget_handler
+    ),
+    components(
+        schemas(
+            APIError,
+            BudgetEnforcement,
+            BudgetPeriod,
+            BudgetScope,
+            RemoveUsageBudgetRequest,
+            UsageBudget,
+            UsageBudgetStatus,
+            UsageByLLMProvider,
+            UsageQuery,
+            UsageSummary,
+            UsageTotals
+        )
+    ),
+    tags(
+        (name = "usage", description = "Usage and Budgets API endpoints")
+    )
+)]
+pub struct UsageApiDoc;
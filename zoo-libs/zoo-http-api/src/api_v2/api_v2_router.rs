@@ -1,5 +1,8 @@
 use crate::node_commands::NodeCommand;
 
+use super::api_v2_api_key_scopes::{api_route_name, required_api_key_scope};
+use super::api_v2_handlers_api_keys::api_key_routes;
+use super::api_v2_handlers_backup::backup_routes;
 use super::api_v2_handlers_ext_agent_offers::ext_agent_offers_routes;
 use super::api_v2_handlers_general::general_routes;
 use super::api_v2_handlers_jobs::job_routes;
@@ -7,16 +10,20 @@ use super::api_v2_handlers_mcp_servers::mcp_server_routes;
 use super::api_v2_handlers_ngrok::ngrok_routes;
 use super::api_v2_handlers_oauth::oauth_routes;
 use super::api_v2_handlers_prompts::prompt_routes;
+use super::api_v2_handlers_secrets::secret_routes;
 #[cfg(feature = "swagger-ui")]
 use super::api_v2_handlers_swagger_ui::swagger_ui_routes;
 use super::api_v2_handlers_tools::tool_routes;
+use super::api_v2_handlers_usage::usage_routes;
 use super::api_v2_handlers_vecfs::vecfs_routes;
 use super::api_v2_handlers_wallets::wallet_routes;
 use super::{api_v2_handlers_cron::cron_routes, api_v2_handlers_mcp_servers::add_mcp_server_handler};
 use async_channel::Sender;
 use serde::Serialize;
 use serde_json::{json, Value};
+use zoo_message_primitives::schemas::api_keys::API_KEY_PREFIX;
 
+use warp::path::FullPath;
 use warp::Filter;
 
 pub fn v2_routes(
@@ -36,9 +43,13 @@ pub fn v2_routes(
     let oauth_routes = oauth_routes(node_commands_sender.clone());
     let mcp_server_routes = mcp_server_routes(node_commands_sender.clone());
     let ngrok_routes = ngrok_routes(node_commands_sender.clone());
+    let usage_routes = usage_routes(node_commands_sender.clone());
+    let api_key_routes = api_key_routes(node_commands_sender.clone());
+    let secret_routes = secret_routes(node_commands_sender.clone());
+    let backup_routes = backup_routes(node_commands_sender.clone());
 
     #[cfg(feature = "swagger-ui")]
-    return general_routes
+    let routes = general_routes
         .or(vecfs_routes)
         .or(job_routes)
         .or(ext_agent_offers)
@@ -49,10 +60,14 @@ pub fn v2_routes(
         .or(cron_routes)
         .or(oauth_routes)
         .or(mcp_server_routes)
-        .or(ngrok_routes);
+        .or(ngrok_routes)
+        .or(usage_routes)
+        .or(api_key_routes)
+        .or(secret_routes)
+        .or(backup_routes);
 
     #[cfg(not(feature = "swagger-ui"))]
-    return general_routes
+    let routes = general_routes
         .or(vecfs_routes)
         .or(job_routes)
         .or(ext_agent_offers)
@@ -62,7 +77,47 @@ pub fn v2_routes(
         .or(cron_routes)
         .or(oauth_routes)
         .or(mcp_server_routes)
-        .or(ngrok_routes);
+        .or(ngrok_routes)
+        .or(usage_routes)
+        .or(api_key_routes)
+        .or(secret_routes)
+        .or(backup_routes);
+
+    with_api_key_scope(node_commands_sender).and(routes)
+}
+
+/// Checks that a scoped API key has the scope the route requires and records the call. Requests
+/// without one (no token or the node API key) go through untouched, the handlers validate them.
+pub fn with_api_key_scope(
+    node_commands_sender: Sender<NodeCommand>,
+) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::path::full()
+        .and(warp::header::optional::<String>("authorization"))
+        .and(with_sender(node_commands_sender))
+        .and_then(
+            |path: FullPath, authorization: Option<String>, sender: Sender<NodeCommand>| async move {
+                let bearer = match authorization.as_deref().and_then(|auth| auth.strip_prefix("Bearer ")) {
+                    Some(bearer) if bearer.starts_with(API_KEY_PREFIX) => bearer.to_string(),
+                    _ => return Ok::<(), warp::Rejection>(()),
+                };
+                let route = api_route_name(path.as_str()).to_string();
+                let (res_sender, res_receiver) = async_channel::bounded(1);
+                sender
+                    .send(NodeCommand::V2ApiAuthorizeApiKey {
+                        bearer,
+                        scope: required_api_key_scope(&route),
+                        route,
+                        res: res_sender,
+                    })
+                    .await
+                    .map_err(|_| warp::reject::reject())?;
+                match res_receiver.recv().await.map_err(|_| warp::reject::reject())? {
+                    Ok(()) => Ok(()),
+                    Err(error) => Err(warp::reject::custom(error)),
+                }
+            },
+        )
+        .untuple_one()
 }
 
 pub fn with_sender(
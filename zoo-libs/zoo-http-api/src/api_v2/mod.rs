@@ -1,3 +1,6 @@
+pub mod api_v2_api_key_scopes;
+pub mod api_v2_handlers_api_keys;
+pub mod api_v2_handlers_backup;
 pub mod api_v2_handlers_cron;
 pub mod api_v2_handlers_ext_agent_offers;
 pub mod api_v2_handlers_general;
@@ -6,9 +9,11 @@ pub mod api_v2_handlers_mcp_servers;
 pub mod api_v2_handlers_my_agent_offers;
 pub mod api_v2_handlers_oauth;
 pub mod api_v2_handlers_prompts;
+pub mod api_v2_handlers_secrets;
 #[cfg(feature = "swagger-ui")]
 pub mod api_v2_handlers_swagger_ui;
 pub mod api_v2_handlers_tools;
+pub mod api_v2_handlers_usage;
 pub mod api_v2_handlers_vecfs;
 pub mod api_v2_handlers_wallets;
 pub mod api_v2_handlers_ngrok;
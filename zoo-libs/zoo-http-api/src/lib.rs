@@ -1,4 +1,5 @@
 #![recursion_limit = "512"]
+pub mod api_openai;
 pub mod api_sse;
 pub mod api_v2;
 pub mod api_ws;
@@ -1,3 +1,4 @@
+use crate::api_openai;
 use crate::api_sse;
 use crate::api_v2;
 use crate::api_ws;
@@ -142,7 +143,12 @@ pub async fn run_api(
             "x-zoo-llm-provider",
             "x-zoo-original-tool-router-key",
             "ngrok-skip-browser-warning",
-        ]);
+            "Accept",
+            "Mcp-Session-Id",
+            "Mcp-Protocol-Version",
+            "Last-Event-ID",
+        ])
+        .expose_headers(vec!["Mcp-Session-Id"]);
 
     let v2_routes = warp::path("v2").and(
         api_v2::api_v2_router::v2_routes(node_commands_sender.clone(), node_name.clone())
@@ -159,6 +165,15 @@ pub async fn run_api(
             .with(cors.clone()),
     );
 
+    // OpenAI-compatible routes are not gzipped so streamed completions are flushed as they arrive
+    let openai_routes = warp::path("v1").and(
+        api_v2::api_v2_router::with_api_key_scope(node_commands_sender.clone())
+            .and(api_openai::openai_routes(node_commands_sender.clone()))
+            .recover(handle_rejection)
+            .with(log)
+            .with(cors.clone()),
+    );
+
     let ws_routes = warp::path("ws").and(
         api_ws::api_ws_routes::ws_routes(ws_address)
             .recover(handle_rejection)
@@ -167,7 +182,7 @@ pub async fn run_api(
     );
 
     // Combine all routes (avoid applying gzip compression globally so SSE is not compressed)
-    let routes = v2_routes.or(mcp_routes).or(ws_routes).with(log).with(cors);
+    let routes = v2_routes.or(openai_routes).or(mcp_routes).or(ws_routes).with(log).with(cors);
 
     // Wrap the HTTP server in an async block that returns a Result
     let http_server = async {
@@ -14,6 +14,20 @@ use zoo_message_primitives::{
     }, zoo_utils::job_scope::MinimalJobScope
 };
 
+use zoo_message_primitives::schemas::agent_memory::{AgentMemory, RemoveAgentMemoryRequest, UpdateAgentMemoryRequest};
+use zoo_message_primitives::schemas::api_keys::{ApiKey, ApiKeyScope, CreateApiKeyRequest, CreatedApiKey};
+use zoo_message_primitives::schemas::mcp_resources::{McpResource, McpResourceContents};
+use zoo_message_primitives::schemas::message_search::{MessageSearchResult, SearchMessagesRequest};
+use zoo_message_primitives::schemas::node_backup::{
+    CreateNodeBackupRequest, NodeBackupInfo, RestoreNodeBackupReport, RestoreNodeBackupRequest,
+};
+use zoo_message_primitives::schemas::secrets::{
+    RotateSecretsKeyRequest, SecretInfo, SecretsExport, SecretsKeyInfo, SetSecretRequest,
+};
+use zoo_message_primitives::schemas::usage::{
+    BudgetPeriod, BudgetScope, UsageBudget, UsageBudgetStatus, UsageQuery, UsageSummary,
+};
+
 use zoo_tools_primitives::tools::{
     mcp_server_tool::MCPServerTool, zoo_tool::{ZooTool, ZooToolHeader, ZooToolWithAssets}, tool_config::OAuth, tool_playground::ToolPlayground, tool_types::{OperatingSystem, RunnerType}
 };
@@ -25,7 +39,7 @@ use zoo_tools_primitives::tools::{
 use x25519_dalek::PublicKey as EncryptionPublicKey;
 
 use crate::{
-    api_v2::api_v2_handlers_mcp_servers::{AddMCPServerRequest, DeleteMCPServerResponse, UpdateMCPServerRequest}, node_api_router::{APIUseRegistrationCodeSuccessResponse, SendResponseBody}
+    api_openai::openai_types::{OpenAIChatCompletionOutput, OpenAIChatCompletionRequest, OpenAIModelList}, api_v2::api_v2_handlers_mcp_servers::{AddMCPServerRequest, DeleteMCPServerResponse, UpdateMCPServerRequest}, node_api_router::{APIUseRegistrationCodeSuccessResponse, SendResponseBody}
 };
 
 use super::{
@@ -239,6 +253,15 @@ pub enum NodeCommand {
         job_message: JobMessage,
         res: Sender<Result<SendResponseBodyData, APIError>>,
     },
+    V2ApiOpenAIChatCompletions {
+        bearer: String,
+        payload: OpenAIChatCompletionRequest,
+        res: Sender<Result<OpenAIChatCompletionOutput, APIError>>,
+    },
+    V2ApiOpenAIListModels {
+        bearer: String,
+        res: Sender<Result<OpenAIModelList, APIError>>,
+    },
     V2ApiAddMessagesGodMode {
         bearer: String,
         job_id: String,
@@ -675,6 +698,7 @@ pub enum NodeCommand {
         llm_provider: String,
         extra_config: Map<String, Value>,
         mounts: Option<Vec<String>>,
+        max_price: Option<u64>,
         res: Sender<Result<Value, APIError>>,
     },
     V2ApiExecuteMcpTool {
@@ -1109,4 +1133,163 @@ pub enum NodeCommand {
         bearer: String,
         res: Sender<Result<Value, APIError>>,
     },
+    V2ApiGetUsage {
+        bearer: String,
+        query: UsageQuery,
+        res: Sender<Result<UsageSummary, APIError>>,
+    },
+    V2ApiGetUsageBudgets {
+        bearer: String,
+        res: Sender<Result<Vec<UsageBudgetStatus>, APIError>>,
+    },
+    V2ApiSetUsageBudget {
+        bearer: String,
+        budget: UsageBudget,
+        res: Sender<Result<UsageBudgetStatus, APIError>>,
+    },
+    V2ApiRemoveUsageBudget {
+        bearer: String,
+        scope: BudgetScope,
+        scope_id: String,
+        period: BudgetPeriod,
+        res: Sender<Result<Value, APIError>>,
+    },
+    V2ApiAuthorizeApiKey {
+        bearer: String,
+        route: String,
+        scope: Option<ApiKeyScope>,
+        res: Sender<Result<(), APIError>>,
+    },
+    V2ApiCreateApiKey {
+        bearer: String,
+        request: CreateApiKeyRequest,
+        res: Sender<Result<CreatedApiKey, APIError>>,
+    },
+    V2ApiListApiKeys {
+        bearer: String,
+        res: Sender<Result<Vec<ApiKey>, APIError>>,
+    },
+    V2ApiRevokeApiKey {
+        bearer: String,
+        id: String,
+        res: Sender<Result<Value, APIError>>,
+    },
+    V2ApiSetSecret {
+        bearer: String,
+        request: SetSecretRequest,
+        res: Sender<Result<SecretInfo, APIError>>,
+    },
+    V2ApiListSecrets {
+        bearer: String,
+        res: Sender<Result<Vec<SecretInfo>, APIError>>,
+    },
+    V2ApiRemoveSecret {
+        bearer: String,
+        name: String,
+        res: Sender<Result<Value, APIError>>,
+    },
+    V2ApiGetSecretsKey {
+        bearer: String,
+        res: Sender<Result<SecretsKeyInfo, APIError>>,
+    },
+    V2ApiRotateSecretsKey {
+        bearer: String,
+        request: RotateSecretsKeyRequest,
+        res: Sender<Result<SecretsKeyInfo, APIError>>,
+    },
+    V2ApiExportSecrets {
+        bearer: String,
+        res: Sender<Result<SecretsExport, APIError>>,
+    },
+    // MCP server: prompts and vector FS resources
+    V2ApiListMcpPrompts {
+        res: Sender<Result<Vec<CustomPrompt>, APIError>>,
+    },
+    V2ApiListMcpResources {
+        res: Sender<Result<Vec<McpResource>, APIError>>,
+    },
+    V2ApiReadMcpResource {
+        uri: String,
+        res: Sender<Result<McpResourceContents, APIError>>,
+    },
+    V2ApiGetMcpResourceVersion {
+        uri: String,
+        res: Sender<Result<Option<String>, APIError>>,
+    },
+    V2ApiAuthorizeMcpBearer {
+        bearer: String,
+        res: Sender<Result<(), APIError>>,
+    },
+    V2ApiGetMCPServerLogs {
+        bearer: String,
+        mcp_server_id: i64,
+        limit: Option<usize>,
+        res: Sender<Result<Value, APIError>>,
+    },
+    // Tool versions
+    V2ApiGetToolUpgradePlan {
+        bearer: String,
+        tool_router_key: String,
+        from_version: Option<String>,
+        to_version: Option<String>,
+        res: Sender<Result<Value, APIError>>,
+    },
+    V2ApiRollbackTool {
+        bearer: String,
+        tool_router_key: String,
+        res: Sender<Result<Value, APIError>>,
+    },
+    V2ApiSetToolActiveVersion {
+        bearer: String,
+        tool_router_key: String,
+        version: Option<String>,
+        res: Sender<Result<Value, APIError>>,
+    },
+    // Node backup
+    V2ApiCreateNodeBackup {
+        bearer: String,
+        request: CreateNodeBackupRequest,
+        res: Sender<Result<NodeBackupInfo, APIError>>,
+    },
+    V2ApiListNodeBackups {
+        bearer: String,
+        res: Sender<Result<Vec<NodeBackupInfo>, APIError>>,
+    },
+    V2ApiDownloadNodeBackup {
+        bearer: String,
+        file_name: String,
+        res: Sender<Result<Vec<u8>, APIError>>,
+    },
+    V2ApiUploadNodeBackup {
+        bearer: String,
+        file_data: Vec<u8>,
+        res: Sender<Result<NodeBackupInfo, APIError>>,
+    },
+    V2ApiRestoreNodeBackup {
+        bearer: String,
+        request: RestoreNodeBackupRequest,
+        res: Sender<Result<RestoreNodeBackupReport, APIError>>,
+    },
+    // Message search
+    V2ApiSearchMessages {
+        bearer: String,
+        request: SearchMessagesRequest,
+        res: Sender<Result<Vec<MessageSearchResult>, APIError>>,
+    },
+    // Agent memory
+    V2ApiListAgentMemories {
+        bearer: String,
+        agent_id: String,
+        res: Sender<Result<Vec<AgentMemory>, APIError>>,
+    },
+    V2ApiUpdateAgentMemory {
+        bearer: String,
+        request: UpdateAgentMemoryRequest,
+        res: Sender<Result<AgentMemory, APIError>>,
+    },
+    V2ApiRemoveAgentMemory {
+        bearer: String,
+        request: RemoveAgentMemoryRequest,
+        res: Sender<Result<String, APIError>>,
+    },
 }
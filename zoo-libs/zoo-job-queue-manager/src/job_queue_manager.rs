@@ -4,15 +4,18 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use zoo_message_primitives::schemas::zoo_name::ZooName;
 use zoo_message_primitives::zoo_message::zoo_message_schemas::JobMessage;
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
 use zoo_sqlite::errors::SqliteManagerError;
+pub use zoo_sqlite::job_queue_manager::{JobQueueItem, JobQueueItemStatus};
 use zoo_sqlite::SqliteManager;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
 
-type MutexQueue<T> = Arc<Mutex<Vec<T>>>;
 type Subscriber<T> = mpsc::Sender<T>;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -75,61 +78,110 @@ impl PartialEq for OrdJsonValue {
     }
 }
 
+/// Settings of a `JobQueueManager`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JobQueueOptions {
+    /// Seconds a worker can hold an item before it's considered abandoned and leased again. A
+    /// worker keeps the lease of a long item with `keep_lease_alive`.
+    pub lease_secs: u64,
+    /// Times an item can be leased before it's moved to the dead letters.
+    pub max_attempts: u32,
+    /// Seconds the done items are kept before being purged (on startup).
+    pub done_retention_secs: u64,
+}
+
+impl Default for JobQueueOptions {
+    fn default() -> Self {
+        JobQueueOptions {
+            lease_secs: 30 * 60,
+            max_attempts: 3,
+            done_retention_secs: 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// Queues backed by the `job_queue_items` table, one row per item. The items stay in the database
+/// until they are done, so whatever was queued or in flight when the node stopped is picked up
+/// again on the next start.
 #[derive(Debug)]
 pub struct JobQueueManager<T: Debug> {
-    queues: Arc<Mutex<HashMap<String, MutexQueue<T>>>>,
     subscribers: Arc<Mutex<HashMap<String, Vec<Subscriber<T>>>>>,
     all_subscribers: Arc<Mutex<Vec<Subscriber<T>>>>,
     db: Weak<SqliteManager>,
     prefix: Option<String>,
+    options: JobQueueOptions,
 }
 
-// Note: these are the ones that are kept in memory but the complete list is kept in the database
+// Note: size of the buffer of the subscriber channels
 static BUFFER_SIZE: usize = 10;
 
+/// Renews the lease of an item in the background until dropped. See
+/// `JobQueueManager::keep_lease_alive`.
+#[derive(Debug)]
+pub struct LeaseKeeper {
+    handle: JoinHandle<()>,
+}
+
+impl Drop for LeaseKeeper {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
 impl<T: Clone + Send + 'static + DeserializeOwned + Serialize + PartialOrd + Debug> JobQueueManager<T> {
     pub async fn new(db: Weak<SqliteManager>, prefix: Option<String>) -> Result<Self, SqliteManagerError> {
-        // Lock the db for safe access
-        let db_arc = db.upgrade().ok_or("Failed to upgrade zoo_db").unwrap();
-
-        // Call the get_all_queues method to get all queue data from the db
-        match db_arc.get_all_queues(prefix.clone()) {
-            Ok(db_queues) => {
-                // Initialize the queues field with Mutex-wrapped Vecs from the db data
-                let manager_queues = db_queues
-                    .into_iter()
-                    .map(|(key, vec)| (key, Arc::new(Mutex::new(vec))))
-                    .collect();
-
-                // Return a new SharedJobQueueManager with the loaded queue data
-                Ok(JobQueueManager {
-                    queues: Arc::new(Mutex::new(manager_queues)),
-                    subscribers: Arc::new(Mutex::new(HashMap::new())),
-                    all_subscribers: Arc::new(Mutex::new(Vec::new())),
-                    db: db.clone(),
-                    prefix,
-                })
-            }
-            Err(e) => Err(e),
-        }
+        Self::new_with_options(db, prefix, JobQueueOptions::default()).await
     }
 
-    pub async fn push(&mut self, key: &str, value: T) -> Result<(), SqliteManagerError> {
-        // Lock the Mutex to get mutable access to the HashMap
-        let mut queues = self.queues.lock().await;
+    /// Creates the manager and recovers the items left leased by a previous run: they are queued
+    /// again (or dead lettered if they used all their attempts) so the next lease runs them.
+    pub async fn new_with_options(
+        db: Weak<SqliteManager>,
+        prefix: Option<String>,
+        options: JobQueueOptions,
+    ) -> Result<Self, SqliteManagerError> {
+        let db_arc = db
+            .upgrade()
+            .ok_or(SqliteManagerError::SomeError("Failed to upgrade zoo_db".to_string()))?;
+
+        let (requeued, dead_lettered) = db_arc.recover_job_queue_items(&prefix)?;
+        if requeued > 0 || dead_lettered > 0 {
+            zoo_log(
+                ZooLogOption::JobExecution,
+                ZooLogLevel::Info,
+                &format!(
+                    "Recovered job queue {:?}: {} abandoned items queued again, {} dead lettered",
+                    prefix, requeued, dead_lettered
+                ),
+            );
+        }
+        db_arc.purge_done_job_queue_items(&prefix, options.done_retention_secs)?;
+
+        Ok(JobQueueManager {
+            subscribers: Arc::new(Mutex::new(HashMap::new())),
+            all_subscribers: Arc::new(Mutex::new(Vec::new())),
+            db,
+            prefix,
+            options,
+        })
+    }
 
-        // Ensure the specified key exists in the queues hashmap, initializing it with an empty queue if necessary
-        let queue = queues
-            .entry(key.to_string())
-            .or_insert_with(|| Arc::new(Mutex::new(Vec::new())));
+    fn db(&self) -> Result<Arc<SqliteManager>, SqliteManagerError> {
+        self.db
+            .upgrade()
+            .ok_or(SqliteManagerError::SomeError("Failed to upgrade zoo_db".to_string()))
+    }
 
-        let mut guarded_queue = queue.lock().await;
-        guarded_queue.push(value.clone());
+    pub async fn push(&mut self, key: &str, value: T) -> Result<(), SqliteManagerError> {
+        self.push_with_priority(key, value, 0).await.map(|_| ())
+    }
 
-        // Persist queue to the database
-        let db_arc = self.db.upgrade().ok_or("Failed to upgrade zoo_db").unwrap();
-        db_arc.persist_queue(key, &guarded_queue, self.prefix.clone())?;
-        drop(db_arc);
+    /// Adds `value` to the queue `key`. Items with a higher priority are leased first. Returns the
+    /// id of the item.
+    pub async fn push_with_priority(&mut self, key: &str, value: T, priority: i64) -> Result<i64, SqliteManagerError> {
+        let id = self
+            .db()?
+            .add_job_queue_item(&self.prefix, key, &value, priority, self.options.max_attempts)?;
 
         // Notify subscribers
         let subscribers = self.subscribers.lock().await;
@@ -150,49 +202,93 @@ impl<T: Clone + Send + 'static + DeserializeOwned + Serialize + PartialOrd + Deb
                 let _ = sub.send(value.clone()).await;
             }
         }
-        Ok(())
+        Ok(id)
     }
 
-    pub async fn dequeue(&mut self, key: &str) -> Result<Option<T>, SqliteManagerError> {
-        // Lock the Mutex to get mutable access to the HashMap
-        let mut queues = self.queues.lock().await;
+    /// Leases up to `limit` items, at most one per queue key. Each one must then be `complete`d or
+    /// `fail`ed; if the worker disappears the lease expires and the item is leased again.
+    pub async fn lease(&self, limit: usize) -> Result<Vec<JobQueueItem<T>>, SqliteManagerError> {
+        self.db()?
+            .lease_job_queue_items(&self.prefix, limit, self.options.lease_secs)
+    }
 
-        // Ensure the specified key exists in the queues hashmap, initializing it with an empty queue if necessary
-        let queue = queues
-            .entry(key.to_string())
-            .or_insert_with(|| Arc::new(Mutex::new(Vec::new())));
+    /// Extends the lease of an item still being processed. Returns false if it isn't leased
+    /// anymore (done, cancelled or dead lettered).
+    pub async fn renew_lease(&self, item_id: i64) -> Result<bool, SqliteManagerError> {
+        self.db()?.renew_job_queue_lease(item_id, self.options.lease_secs)
+    }
 
-        let mut guarded_queue = queue.lock().await;
+    /// Renews the lease of the item every third of `lease_secs` while the returned keeper is
+    /// alive, so an item that takes longer than its lease isn't leased again while it runs. Drop
+    /// the keeper once the item is completed or failed.
+    pub fn keep_lease_alive(&self, item_id: i64) -> LeaseKeeper {
+        let db = self.db.clone();
+        let lease_secs = self.options.lease_secs;
+        let every = Duration::from_secs((lease_secs / 3).max(1));
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(every).await;
+                let Some(db) = db.upgrade() else {
+                    return;
+                };
+                match db.renew_job_queue_lease(item_id, lease_secs) {
+                    Ok(true) => {}
+                    Ok(false) => return,
+                    Err(e) => zoo_log(
+                        ZooLogOption::JobExecution,
+                        ZooLogLevel::Error,
+                        &format!("Failed to renew the lease of job queue item {}: {}", item_id, e),
+                    ),
+                }
+            }
+        });
+        LeaseKeeper { handle }
+    }
 
-        // Check if there's an element to dequeue, and remove it if so
-        let result = if guarded_queue.first().is_some() {
-            Some(guarded_queue.remove(0))
-        } else {
-            None
-        };
+    pub async fn complete(&self, item_id: i64) -> Result<(), SqliteManagerError> {
+        self.db()?.complete_job_queue_item(item_id)
+    }
+
+    /// Records a failed attempt: the item is leased again later unless it used all its attempts,
+    /// in which case it's dead lettered.
+    pub async fn fail(&self, item_id: i64, error: &str) -> Result<JobQueueItemStatus, SqliteManagerError> {
+        self.db()?.fail_job_queue_item(item_id, error, true)
+    }
+
+    /// Moves the item straight to the dead letters.
+    pub async fn dead_letter(&self, item_id: i64, error: &str) -> Result<(), SqliteManagerError> {
+        self.db()?.fail_job_queue_item(item_id, error, false).map(|_| ())
+    }
+
+    pub async fn get_dead_letters(&self) -> Result<Vec<JobQueueItem<T>>, SqliteManagerError> {
+        self.db()?.get_failed_job_queue_items(&self.prefix)
+    }
+
+    /// Queues a dead letter again with a fresh set of attempts.
+    pub async fn retry_dead_letter(&self, item_id: i64) -> Result<bool, SqliteManagerError> {
+        self.db()?.requeue_failed_job_queue_item(item_id)
+    }
 
-        // Persist queue to the database
-        let db_arc = self.db.upgrade().ok_or("Failed to upgrade zoo_db").unwrap();
-        db_arc.persist_queue(key, &guarded_queue, self.prefix.clone())?;
+    /// Takes the head of the queue `key` out of it, leased or not.
+    pub async fn dequeue(&mut self, key: &str) -> Result<Option<T>, SqliteManagerError> {
+        self.db()?.remove_job_queue_head(&self.prefix, key)
+    }
 
-        Ok(result)
+    /// Cancels everything pending in the queue `key`, the item being processed included. Returns
+    /// how many items were cancelled.
+    pub async fn cancel(&self, key: &str, reason: &str) -> Result<usize, SqliteManagerError> {
+        self.db()?.cancel_job_queue_items(&self.prefix, key, reason)
     }
 
     pub async fn peek(&self, key: &str) -> Result<Option<T>, SqliteManagerError> {
-        let queues = self.queues.lock().await;
-        if let Some(queue) = queues.get(key) {
-            let guarded_queue = queue.lock().await;
-            if let Some(first) = guarded_queue.first() {
-                return Ok(Some(first.clone()));
-            }
-        }
-        Ok(None)
+        Ok(self
+            .db()?
+            .get_job_queue_head::<T>(&self.prefix, key)?
+            .map(|item| item.value))
     }
 
     pub async fn get_all_elements_interleave(&self) -> Result<Vec<T>, SqliteManagerError> {
-        let db_arc = self.db.upgrade().ok_or("Failed to upgrade zoo_db")?;
-        let mut db_queues: HashMap<_, _> = db_arc.get_all_queues::<T>(self.prefix.clone())?;
-
+        let mut db_queues: HashMap<_, _> = self.db()?.get_all_queues::<T>(&self.prefix)?;
         // Sort the keys based on the first element in each queue, falling back to key names
         let mut keys: Vec<_> = db_queues.keys().cloned().collect();
         keys.sort_by(|a, b| {
@@ -245,11 +341,11 @@ impl<T: Clone + Send + 'static + DeserializeOwned + Serialize + PartialOrd + Deb
 impl<T: Clone + Send + 'static + Debug> Clone for JobQueueManager<T> {
     fn clone(&self) -> Self {
         JobQueueManager {
-            queues: Arc::clone(&self.queues),
             subscribers: Arc::clone(&self.subscribers),
             all_subscribers: Arc::clone(&self.all_subscribers),
             db: self.db.clone(),
             prefix: self.prefix.clone(),
+            options: self.options,
         }
     }
 }
@@ -419,6 +515,76 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_queue_manager_releases_abandoned_leases_on_restart() {
+        let db = setup();
+        let db_arc = Arc::new(db);
+        let db_weak = Arc::downgrade(&db_arc);
+        let mut manager = JobQueueManager::<OrdJsonValue>::new(db_weak.clone(), None)
+            .await
+            .unwrap();
+
+        manager
+            .push("my_queue", OrdJsonValue(JsonValue::String("first".to_string())))
+            .await
+            .unwrap();
+        manager
+            .push_with_priority("other_queue", OrdJsonValue(JsonValue::String("urgent".to_string())), 10)
+            .await
+            .unwrap();
+
+        let leased = manager.lease(10).await.unwrap();
+        assert_eq!(leased.len(), 2);
+        assert_eq!(leased[0].value, OrdJsonValue(JsonValue::String("urgent".to_string())));
+        manager.complete(leased[0].id).await.unwrap();
+
+        // The node "crashes" while "first" is in flight: a new manager gets it back
+        drop(manager);
+        let new_manager = JobQueueManager::<OrdJsonValue>::new(db_weak.clone(), None).await.unwrap();
+        let leased_again = new_manager.lease(10).await.unwrap();
+        assert_eq!(leased_again.len(), 1);
+        assert_eq!(leased_again[0].id, leased[1].id);
+        assert_eq!(leased_again[0].attempts, 2);
+
+        new_manager.dead_letter(leased_again[0].id, "boom").await.unwrap();
+        assert!(new_manager.lease(10).await.unwrap().is_empty());
+        assert_eq!(new_manager.get_dead_letters().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_queue_manager_keeps_running_items_leased() {
+        let db = setup();
+        let db_arc = Arc::new(db);
+        let db_weak = Arc::downgrade(&db_arc);
+        let options = JobQueueOptions {
+            lease_secs: 2,
+            ..Default::default()
+        };
+        let mut manager = JobQueueManager::<OrdJsonValue>::new_with_options(db_weak.clone(), None, options)
+            .await
+            .unwrap();
+        manager
+            .push("my_queue", OrdJsonValue(JsonValue::String("slow".to_string())))
+            .await
+            .unwrap();
+
+        let leased = manager.lease(10).await.unwrap();
+        let keeper = manager.keep_lease_alive(leased[0].id);
+        tokio::time::sleep(Duration::from_secs(4)).await;
+        assert!(manager.lease(10).await.unwrap().is_empty());
+
+        // Without the keeper the lease expires and the item is leased again
+        drop(keeper);
+        tokio::time::sleep(Duration::from_secs(4)).await;
+        let leased_again = manager.lease(10).await.unwrap();
+        assert_eq!(leased_again.len(), 1);
+        assert_eq!(leased_again[0].attempts, 2);
+        assert_eq!(
+            manager.fail(leased_again[0].id, "boom").await.unwrap(),
+            JobQueueItemStatus::Queued
+        );
+    }
+
     #[tokio::test]
     async fn test_queue_manager_with_jsonvalue() {
         let db = setup();
@@ -2,3 +2,4 @@ mod command;
 mod utils;
 pub mod mcp_methods;
 pub mod error;
+pub mod session_pool;
@@ -0,0 +1,734 @@
+use crate::{command::CommandWrappedInShellBuilder, error::McpError, utils::disect_command};
+
+type Result<T> = std::result::Result<T, McpError>;
+use once_cell::sync::Lazy;
+use rmcp::{
+    model::{
+        CallToolRequestParam, CallToolResult, ClientCapabilities, ClientInfo, Implementation, LoggingMessageNotificationParam, Tool
+    }, service::{NotificationContext, RunningService, ServiceError}, transport::{SseClientTransport, StreamableHttpClientTransport, TokioChildProcess}, ClientHandler, RoleClient, ServiceExt
+};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex as StdMutex, Once};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{ChildStderr, Command};
+use tokio::sync::{broadcast, Mutex};
+
+/// How often running sessions are checked for idleness and health.
+pub const MAINTENANCE_INTERVAL_SECS: u64 = 30;
+/// A session is pinged when it hasn't been used or checked for this long.
+pub const HEALTH_CHECK_INTERVAL_SECS: u64 = 60;
+/// A session that doesn't answer a health check within this time is restarted.
+pub const HEALTH_CHECK_TIMEOUT_SECS: u64 = 15;
+/// Sessions unused for this long are shut down. The next call starts a new one.
+pub const IDLE_SHUTDOWN_SECS: u64 = 10 * 60;
+/// Delay before the first restart after a failure, doubled on each consecutive failure.
+pub const RESTART_BACKOFF_BASE_SECS: u64 = 1;
+pub const RESTART_BACKOFF_MAX_SECS: u64 = 60;
+/// Log entries kept per MCP server, across restarts.
+pub const MAX_LOG_ENTRIES: usize = 1000;
+const MAX_LOG_MESSAGE_LEN: usize = 4096;
+
+/// How to reach an MCP server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum McpTransportSpec {
+    Command {
+        command: String,
+        env: HashMap<String, String>,
+    },
+    Sse {
+        url: String,
+    },
+    Http {
+        url: String,
+    },
+}
+
+/// An MCP server record as seen by the pool. Sessions are keyed by `id`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpServerSpec {
+    pub id: i64,
+    pub name: String,
+    pub transport: McpTransportSpec,
+}
+
+impl McpServerSpec {
+    /// Identifies the command or URL and environment the session was started with. A running
+    /// session is restarted when the spec it is asked for has a different fingerprint.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        match &self.transport {
+            McpTransportSpec::Command { command, env } => {
+                "command".hash(&mut hasher);
+                command.hash(&mut hasher);
+                let mut env: Vec<_> = env.iter().collect();
+                env.sort();
+                env.hash(&mut hasher);
+            }
+            McpTransportSpec::Sse { url } => {
+                "sse".hash(&mut hasher);
+                url.hash(&mut hasher);
+            }
+            McpTransportSpec::Http { url } => {
+                "http".hash(&mut hasher);
+                url.hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum McpServerLogSource {
+    /// A line the server process wrote to stderr.
+    Stderr,
+    /// A `notifications/message` log sent by the server.
+    Server,
+    /// Session lifecycle events: start, restart, health check failures, shutdown.
+    Session,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpServerLogEntry {
+    pub timestamp_ms: u64,
+    pub source: McpServerLogSource,
+    pub level: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum McpSessionState {
+    Running,
+    Stopped,
+    /// The last start failed, calls fail until `retry_in_secs` has passed.
+    BackingOff,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpSessionStatus {
+    pub mcp_server_id: i64,
+    pub state: McpSessionState,
+    pub started_at_ms: Option<u64>,
+    pub restarts: u32,
+    pub consecutive_failures: u32,
+    pub retry_in_secs: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+struct PooledSession {
+    service: RunningService<RoleClient, PoolClientHandler>,
+    fingerprint: u64,
+    started_at_ms: u64,
+}
+
+#[derive(Default)]
+struct ServerSlot {
+    spec: Option<McpServerSpec>,
+    session: Option<Arc<PooledSession>>,
+    last_used: Option<Instant>,
+    last_checked: Option<Instant>,
+    restarts: u32,
+    failures: u32,
+    retry_at: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl ServerSlot {
+    /// A running session died. The first restart is immediate, later ones back off.
+    fn lost(&mut self, error: String) {
+        self.session = None;
+        self.failures += 1;
+        self.retry_at = None;
+        self.last_error = Some(error);
+    }
+
+    /// A session failed to start.
+    fn fail(&mut self, error: String) {
+        self.session = None;
+        self.failures += 1;
+        self.retry_at = Some(Instant::now() + restart_backoff(self.failures));
+        self.last_error = Some(error);
+    }
+}
+
+/// Keeps one long-lived client session per MCP server instead of spawning a client per call.
+/// Sessions are started on first use, health checked while in use, restarted with exponential
+/// backoff when they fail and shut down when idle.
+pub struct McpSessionPool {
+    slots: StdMutex<HashMap<i64, Arc<Mutex<ServerSlot>>>>,
+    logs: StdMutex<HashMap<i64, VecDeque<McpServerLogEntry>>>,
+    tool_list_changed: broadcast::Sender<i64>,
+    maintenance: Once,
+}
+
+static POOL: Lazy<McpSessionPool> = Lazy::new(McpSessionPool::new);
+
+impl McpSessionPool {
+    fn new() -> Self {
+        let (tool_list_changed, _) = broadcast::channel(64);
+        McpSessionPool {
+            slots: StdMutex::new(HashMap::new()),
+            logs: StdMutex::new(HashMap::new()),
+            tool_list_changed,
+            maintenance: Once::new(),
+        }
+    }
+
+    pub fn global() -> &'static McpSessionPool {
+        &POOL
+    }
+
+    /// Receives the id of an MCP server each time it sends `notifications/tools/list_changed`.
+    pub fn subscribe_tool_list_changes(&self) -> broadcast::Receiver<i64> {
+        self.tool_list_changed.subscribe()
+    }
+
+    pub async fn call_tool(
+        &'static self,
+        spec: &McpServerSpec,
+        tool: String,
+        parameters: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<CallToolResult> {
+        let mut retried = false;
+        loop {
+            let session = self.session(spec).await?;
+            let result = session
+                .service
+                .call_tool(CallToolRequestParam {
+                    name: tool.clone().into(),
+                    arguments: Some(parameters.clone()),
+                })
+                .await;
+            match result {
+                Ok(result) => return Ok(result),
+                // The request never reached a live server, retry once on a new session
+                Err(e @ (ServiceError::TransportClosed | ServiceError::TransportSend(_))) if !retried => {
+                    self.session_failed(spec.id, &session, format!("Tool call failed: {}", e))
+                        .await;
+                    retried = true;
+                }
+                Err(e) => {
+                    if !matches!(e, ServiceError::McpError(_)) {
+                        self.session_failed(spec.id, &session, format!("Tool call failed: {}", e))
+                            .await;
+                    }
+                    return Err(McpError {
+                        message: format!("{}", e),
+                    });
+                }
+            }
+        }
+    }
+
+    pub async fn list_tools(&'static self, spec: &McpServerSpec) -> Result<Vec<Tool>> {
+        let session = self.session(spec).await?;
+        match session.service.list_all_tools().await {
+            Ok(tools) => Ok(tools),
+            Err(e) => {
+                if !matches!(e, ServiceError::McpError(_)) {
+                    self.session_failed(spec.id, &session, format!("Listing tools failed: {}", e))
+                        .await;
+                }
+                Err(McpError {
+                    message: format!("{}", e),
+                })
+            }
+        }
+    }
+
+    /// Stops the session of a server, if any, and forgets its failures. Used when the server is
+    /// deleted, disabled or updated. Logs are kept.
+    pub async fn shutdown(&self, mcp_server_id: i64, reason: &str) {
+        let slot = self.slots.lock().unwrap().remove(&mcp_server_id);
+        let Some(slot) = slot else {
+            return;
+        };
+        let session = slot.lock().await.session.take();
+        if let Some(session) = session {
+            self.log(
+                mcp_server_id,
+                McpServerLogSource::Session,
+                Some("info"),
+                format!("Session stopped: {}", reason),
+            );
+            Self::close(session).await;
+        }
+    }
+
+    pub fn logs(&self, mcp_server_id: i64, limit: usize) -> Vec<McpServerLogEntry> {
+        let logs = self.logs.lock().unwrap();
+        logs.get(&mcp_server_id)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .skip(entries.len().saturating_sub(limit))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub async fn status(&self, mcp_server_id: i64) -> McpSessionStatus {
+        let slot = self.slots.lock().unwrap().get(&mcp_server_id).cloned();
+        let mut status = McpSessionStatus {
+            mcp_server_id,
+            state: McpSessionState::Stopped,
+            started_at_ms: None,
+            restarts: 0,
+            consecutive_failures: 0,
+            retry_in_secs: None,
+            last_error: None,
+        };
+        let Some(slot) = slot else {
+            return status;
+        };
+        let slot = slot.lock().await;
+        status.restarts = slot.restarts;
+        status.consecutive_failures = slot.failures;
+        status.last_error = slot.last_error.clone();
+        if let Some(session) = &slot.session {
+            status.state = McpSessionState::Running;
+            status.started_at_ms = Some(session.started_at_ms);
+        } else if let Some(retry_at) = slot.retry_at.filter(|retry_at| *retry_at > Instant::now()) {
+            status.state = McpSessionState::BackingOff;
+            status.retry_in_secs = Some(retry_at.saturating_duration_since(Instant::now()).as_secs() + 1);
+        }
+        status
+    }
+
+    fn slot(&self, mcp_server_id: i64) -> Arc<Mutex<ServerSlot>> {
+        self.slots.lock().unwrap().entry(mcp_server_id).or_default().clone()
+    }
+
+    /// The running session of the server, started or restarted as needed.
+    async fn session(&'static self, spec: &McpServerSpec) -> Result<Arc<PooledSession>> {
+        self.maintenance.call_once(|| {
+            tokio::spawn(self.run_maintenance());
+        });
+
+        let slot = self.slot(spec.id);
+        let mut slot = slot.lock().await;
+        slot.last_used = Some(Instant::now());
+        slot.spec = Some(spec.clone());
+
+        if let Some(session) = &slot.session {
+            if session.fingerprint == spec.fingerprint() {
+                return Ok(session.clone());
+            }
+            let session = slot.session.take().unwrap();
+            self.log(
+                spec.id,
+                McpServerLogSource::Session,
+                Some("info"),
+                "Configuration changed, restarting the session".to_string(),
+            );
+            Self::close(session).await;
+        }
+
+        self.start(spec, &mut slot).await
+    }
+
+    async fn start(&self, spec: &McpServerSpec, slot: &mut ServerSlot) -> Result<Arc<PooledSession>> {
+        if let Some(retry_at) = slot.retry_at.filter(|retry_at| *retry_at > Instant::now()) {
+            return Err(McpError {
+                message: format!(
+                    "MCP server {} failed to start and will be retried in {}s: {}",
+                    spec.name,
+                    retry_at.saturating_duration_since(Instant::now()).as_secs() + 1,
+                    slot.last_error.clone().unwrap_or_default()
+                ),
+            });
+        }
+
+        match self.connect(spec).await {
+            Ok(service) => {
+                if slot.failures > 0 {
+                    slot.restarts += 1;
+                }
+                slot.failures = 0;
+                slot.retry_at = None;
+                slot.last_checked = Some(Instant::now());
+                self.log(
+                    spec.id,
+                    McpServerLogSource::Session,
+                    Some("info"),
+                    format!("Session started for {}", spec.name),
+                );
+                let session = Arc::new(PooledSession {
+                    service,
+                    fingerprint: spec.fingerprint(),
+                    started_at_ms: now_ms(),
+                });
+                slot.session = Some(session.clone());
+                Ok(session)
+            }
+            Err(e) => {
+                slot.fail(e.message.clone());
+                self.log(
+                    spec.id,
+                    McpServerLogSource::Session,
+                    Some("error"),
+                    format!(
+                        "Session failed to start, retrying in {}s: {}",
+                        restart_backoff(slot.failures).as_secs(),
+                        e.message
+                    ),
+                );
+                Err(e)
+            }
+        }
+    }
+
+    async fn connect(&self, spec: &McpServerSpec) -> Result<RunningService<RoleClient, PoolClientHandler>> {
+        let handler = PoolClientHandler { mcp_server_id: spec.id };
+        match &spec.transport {
+            McpTransportSpec::Command { command, env } => {
+                let (_, cmd_executable, cmd_args) = disect_command(command.clone());
+                let (adapted_program, adapted_args, adapted_envs) =
+                    CommandWrappedInShellBuilder::wrap_in_shell_as_values(
+                        cmd_executable,
+                        Some(cmd_args),
+                        Some(env.clone()),
+                    );
+                let mut cmd = Command::new(adapted_program);
+                cmd.kill_on_drop(true);
+                cmd.envs(adapted_envs);
+                cmd.envs(env);
+                cmd.args(adapted_args);
+
+                let (transport, stderr) =
+                    TokioChildProcess::builder(cmd)
+                        .stderr(Stdio::piped())
+                        .spawn()
+                        .map_err(|e| McpError {
+                            message: format!("{}", e),
+                        })?;
+                if let Some(stderr) = stderr {
+                    self.capture_stderr(spec.id, stderr);
+                }
+                handler.serve(transport).await.map_err(|e| McpError {
+                    message: format!("{}", e),
+                })
+            }
+            McpTransportSpec::Sse { url } => {
+                let transport = SseClientTransport::start(url.clone()).await.map_err(|e| McpError {
+                    message: format!("{}", e),
+                })?;
+                handler.serve(transport).await.map_err(|e| McpError {
+                    message: format!("SSE client connection error: {:?}", e),
+                })
+            }
+            McpTransportSpec::Http { url } => {
+                let transport = StreamableHttpClientTransport::from_uri(url.clone());
+                handler.serve(transport).await.map_err(|e| McpError {
+                    message: format!("HTTP client connection error: {:?}", e),
+                })
+            }
+        }
+    }
+
+    fn capture_stderr(&self, mcp_server_id: i64, stderr: ChildStderr) {
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                Self::global().log(mcp_server_id, McpServerLogSource::Stderr, None, line);
+            }
+        });
+    }
+
+    /// Drops the session if it is still the current one and schedules its restart.
+    async fn session_failed(&self, mcp_server_id: i64, session: &Arc<PooledSession>, error: String) {
+        let slot = self.slot(mcp_server_id);
+        let mut slot = slot.lock().await;
+        if !slot
+            .session
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, session))
+        {
+            return;
+        }
+        slot.lost(error.clone());
+        self.log(mcp_server_id, McpServerLogSource::Session, Some("error"), error);
+    }
+
+    async fn run_maintenance(&'static self) {
+        let mut interval = tokio::time::interval(Duration::from_secs(MAINTENANCE_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let slots: Vec<(i64, Arc<Mutex<ServerSlot>>)> = self
+                .slots
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(id, slot)| (*id, slot.clone()))
+                .collect();
+            for (mcp_server_id, slot) in slots {
+                self.maintain(mcp_server_id, &slot).await;
+            }
+        }
+    }
+
+    async fn maintain(&self, mcp_server_id: i64, slot: &Mutex<ServerSlot>) {
+        let mut slot = slot.lock().await;
+        let now = Instant::now();
+        let idle = slot
+            .last_used
+            .is_none_or(|last_used| now.duration_since(last_used) >= Duration::from_secs(IDLE_SHUTDOWN_SECS));
+
+        if idle {
+            if let Some(session) = slot.session.take() {
+                self.log(
+                    mcp_server_id,
+                    McpServerLogSource::Session,
+                    Some("info"),
+                    "Session stopped after being idle".to_string(),
+                );
+                Self::close(session).await;
+            }
+            return;
+        }
+
+        match slot.session.clone() {
+            Some(session) => {
+                let due = slot.last_checked.is_none_or(|last_checked| {
+                    now.duration_since(last_checked) >= Duration::from_secs(HEALTH_CHECK_INTERVAL_SECS)
+                });
+                if !due {
+                    return;
+                }
+                slot.last_checked = Some(now);
+                let check = tokio::time::timeout(
+                    Duration::from_secs(HEALTH_CHECK_TIMEOUT_SECS),
+                    session.service.list_tools(None),
+                )
+                .await;
+                let error = match check {
+                    Ok(Ok(_)) | Ok(Err(ServiceError::McpError(_))) => return,
+                    Ok(Err(e)) => format!("Health check failed: {}", e),
+                    Err(_) => format!("Health check timed out after {}s", HEALTH_CHECK_TIMEOUT_SECS),
+                };
+                slot.lost(error.clone());
+                self.log(mcp_server_id, McpServerLogSource::Session, Some("error"), error);
+                Self::close(session).await;
+            }
+            // Restart a failed session that is still in use once its backoff has passed
+            None if slot.failures > 0 => {
+                if let Some(spec) = slot.spec.clone() {
+                    let _ = self.start(&spec, &mut slot).await;
+                }
+            }
+            None => {}
+        }
+    }
+
+    async fn close(session: Arc<PooledSession>) {
+        // Calls still running hold a reference, the session closes when the last one ends
+        if let Ok(session) = Arc::try_unwrap(session) {
+            let _ = session
+                .service
+                .cancel()
+                .await
+                .inspect_err(|e| log::error!("error cancelling mcp session: {:?}", e));
+        }
+    }
+
+    fn log(&self, mcp_server_id: i64, source: McpServerLogSource, level: Option<&str>, mut message: String) {
+        if message.len() > MAX_LOG_MESSAGE_LEN {
+            let mut end = MAX_LOG_MESSAGE_LEN;
+            while !message.is_char_boundary(end) {
+                end -= 1;
+            }
+            message.truncate(end);
+        }
+        let mut logs = self.logs.lock().unwrap();
+        let entries = logs.entry(mcp_server_id).or_default();
+        if entries.len() >= MAX_LOG_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(McpServerLogEntry {
+            timestamp_ms: now_ms(),
+            source,
+            level: level.map(str::to_string),
+            message,
+        });
+    }
+}
+
+/// Client side of a pooled session. Records server logs and forwards tool list changes.
+#[derive(Clone)]
+struct PoolClientHandler {
+    mcp_server_id: i64,
+}
+
+impl ClientHandler for PoolClientHandler {
+    fn get_info(&self) -> ClientInfo {
+        ClientInfo {
+            protocol_version: Default::default(),
+            capabilities: ClientCapabilities::default(),
+            client_info: Implementation {
+                name: "Zoo Node Client".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+        }
+    }
+
+    fn on_tool_list_changed(&self, _context: NotificationContext<RoleClient>) -> impl Future<Output = ()> + Send + '_ {
+        let pool = McpSessionPool::global();
+        pool.log(
+            self.mcp_server_id,
+            McpServerLogSource::Session,
+            Some("info"),
+            "Server reported that its tool list changed".to_string(),
+        );
+        // No receivers just means nobody refreshes tools, e.g. in tests
+        let _ = pool.tool_list_changed.send(self.mcp_server_id);
+        std::future::ready(())
+    }
+
+    fn on_logging_message(
+        &self,
+        params: LoggingMessageNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) -> impl Future<Output = ()> + Send + '_ {
+        let data = match params.data {
+            serde_json::Value::String(message) => message,
+            data => data.to_string(),
+        };
+        let message = match params.logger {
+            Some(logger) => format!("[{}] {}", logger, data),
+            None => data,
+        };
+        let level = serde_json::to_value(params.level)
+            .ok()
+            .and_then(|level| level.as_str().map(str::to_string));
+        McpSessionPool::global().log(
+            self.mcp_server_id,
+            McpServerLogSource::Server,
+            level.as_deref(),
+            message,
+        );
+        std::future::ready(())
+    }
+}
+
+fn restart_backoff(failures: u32) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    Duration::from_secs((RESTART_BACKOFF_BASE_SECS << exponent).min(RESTART_BACKOFF_MAX_SECS))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command_spec(command: &str, env: &[(&str, &str)]) -> McpServerSpec {
+        McpServerSpec {
+            id: 1,
+            name: "everything".to_string(),
+            transport: McpTransportSpec::Command {
+                command: command.to_string(),
+                env: env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_follows_configuration() {
+        let spec = command_spec("npx -y server", &[("A", "1"), ("B", "2")]);
+        assert_eq!(
+            spec.fingerprint(),
+            command_spec("npx -y server", &[("B", "2"), ("A", "1")]).fingerprint()
+        );
+        assert_ne!(
+            spec.fingerprint(),
+            command_spec("npx -y server", &[("A", "1"), ("B", "3")]).fingerprint()
+        );
+        assert_ne!(spec.fingerprint(), command_spec("npx -y other", &[]).fingerprint());
+
+        let sse = McpServerSpec {
+            id: 1,
+            name: "remote".to_string(),
+            transport: McpTransportSpec::Sse {
+                url: "http://localhost:8000/sse".to_string(),
+            },
+        };
+        let http = McpServerSpec {
+            transport: McpTransportSpec::Http {
+                url: "http://localhost:8000/sse".to_string(),
+            },
+            ..sse.clone()
+        };
+        assert_ne!(sse.fingerprint(), http.fingerprint());
+    }
+
+    #[test]
+    fn test_restart_backoff() {
+        assert_eq!(restart_backoff(1), Duration::from_secs(1));
+        assert_eq!(restart_backoff(2), Duration::from_secs(2));
+        assert_eq!(restart_backoff(4), Duration::from_secs(8));
+        assert_eq!(restart_backoff(7), Duration::from_secs(RESTART_BACKOFF_MAX_SECS));
+        assert_eq!(restart_backoff(u32::MAX), Duration::from_secs(RESTART_BACKOFF_MAX_SECS));
+    }
+
+    #[test]
+    fn test_logs_are_bounded() {
+        let pool = McpSessionPool::new();
+        for i in 0..MAX_LOG_ENTRIES + 5 {
+            pool.log(7, McpServerLogSource::Stderr, None, format!("line {}", i));
+        }
+        pool.log(
+            7,
+            McpServerLogSource::Server,
+            Some("info"),
+            "é".repeat(MAX_LOG_MESSAGE_LEN),
+        );
+
+        let logs = pool.logs(7, usize::MAX);
+        assert_eq!(logs.len(), MAX_LOG_ENTRIES);
+        assert_eq!(logs[0].message, "line 6");
+        assert!(logs.last().unwrap().message.len() <= MAX_LOG_MESSAGE_LEN);
+
+        let last = pool.logs(7, 2);
+        assert_eq!(last.len(), 2);
+        assert_eq!(last[1].source, McpServerLogSource::Server);
+        assert!(pool.logs(8, 10).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_start_backs_off() {
+        let pool = McpSessionPool::global();
+        let spec = McpServerSpec {
+            id: -1,
+            name: "unreachable".to_string(),
+            transport: McpTransportSpec::Http {
+                url: "http://127.0.0.1:1/mcp".to_string(),
+            },
+        };
+        assert!(pool.list_tools(&spec).await.is_err());
+
+        let status = pool.status(spec.id).await;
+        assert_eq!(status.state, McpSessionState::BackingOff);
+        assert_eq!(status.consecutive_failures, 1);
+
+        // Calls during the backoff fail without trying to connect
+        let error = pool.list_tools(&spec).await.unwrap_err();
+        assert!(error.message.contains("will be retried"));
+        assert_eq!(pool.status(spec.id).await.consecutive_failures, 1);
+
+        pool.shutdown(spec.id, "test").await;
+        assert_eq!(pool.status(spec.id).await.state, McpSessionState::Stopped);
+    }
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// A fact an agent keeps across all of its jobs. The most relevant ones are added to every prompt
+/// of the agent.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct AgentMemory {
+    pub memory_id: String,
+    pub agent_id: String,
+    pub content: String,
+    pub source: AgentMemorySource,
+    /// The job the agent was running when it remembered it.
+    pub job_id: Option<String>,
+    /// RFC3339 timestamps.
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentMemorySource {
+    /// An entry of the `knowledge` of the agent. Editing or removing the memory changes it too.
+    Knowledge,
+    /// Stored by the agent with the remember tool.
+    Remembered,
+}
+
+impl AgentMemorySource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AgentMemorySource::Knowledge => "knowledge",
+            AgentMemorySource::Remembered => "remembered",
+        }
+    }
+}
+
+impl FromStr for AgentMemorySource {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "knowledge" => Ok(AgentMemorySource::Knowledge),
+            "remembered" => Ok(AgentMemorySource::Remembered),
+            _ => Err(format!("Unknown agent memory source: {}", value)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct UpdateAgentMemoryRequest {
+    pub memory_id: String,
+    pub content: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct RemoveAgentMemoryRequest {
+    pub memory_id: String,
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Every scoped API key starts with this, which tells them apart from the node API key.
+pub const API_KEY_PREFIX: &str = "zk_";
+
+/// What a scoped API key is allowed to do. The node API key (`API_V2_KEY`) can do everything,
+/// including the routes no scope covers.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, ToSchema)]
+pub enum ApiKeyScope {
+    /// Read jobs, their messages and their files.
+    #[serde(rename = "jobs:read")]
+    JobsRead,
+    /// Create jobs, send messages and manage their files and cron tasks.
+    #[serde(rename = "jobs:write")]
+    JobsWrite,
+    /// List tools and run them. Network tools also need `wallet:spend`.
+    #[serde(rename = "tools:execute")]
+    ToolsExecute,
+    /// Add, change and remove tools and MCP servers.
+    #[serde(rename = "tools:admin")]
+    ToolsAdmin,
+    /// Read the wallets and their balances.
+    #[serde(rename = "wallet:read")]
+    WalletRead,
+    /// Pay (or reject) invoices with the node wallet.
+    #[serde(rename = "wallet:spend")]
+    WalletSpend,
+    /// Add, change and remove LLM providers.
+    #[serde(rename = "llm_providers:admin")]
+    LLMProvidersAdmin,
+    /// Add, change and remove agents.
+    #[serde(rename = "agents:admin")]
+    AgentsAdmin,
+}
+
+impl ApiKeyScope {
+    pub const ALL: [ApiKeyScope; 8] = [
+        ApiKeyScope::JobsRead,
+        ApiKeyScope::JobsWrite,
+        ApiKeyScope::ToolsExecute,
+        ApiKeyScope::ToolsAdmin,
+        ApiKeyScope::WalletRead,
+        ApiKeyScope::WalletSpend,
+        ApiKeyScope::LLMProvidersAdmin,
+        ApiKeyScope::AgentsAdmin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::JobsRead => "jobs:read",
+            ApiKeyScope::JobsWrite => "jobs:write",
+            ApiKeyScope::ToolsExecute => "tools:execute",
+            ApiKeyScope::ToolsAdmin => "tools:admin",
+            ApiKeyScope::WalletRead => "wallet:read",
+            ApiKeyScope::WalletSpend => "wallet:spend",
+            ApiKeyScope::LLMProvidersAdmin => "llm_providers:admin",
+            ApiKeyScope::AgentsAdmin => "agents:admin",
+        }
+    }
+}
+
+impl fmt::Display for ApiKeyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ApiKeyScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ApiKeyScope::ALL
+            .iter()
+            .find(|scope| scope.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown API key scope: {}", s))
+    }
+}
+
+/// A scoped API key. Only its hash is stored, the key itself is shown once when it's created.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ApiKey {
+    pub id: String,
+    pub label: String,
+    /// The first characters of the key, enough to recognize it.
+    pub key_prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// RFC3339 timestamps.
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub revoked_at: Option<String>,
+    pub last_used_at: Option<String>,
+    /// The v2 route of the last call made with the key.
+    pub last_used_route: Option<String>,
+}
+
+impl ApiKey {
+    /// Generates a new random key, returned together with its hash.
+    pub fn generate_key() -> (String, String) {
+        let mut bytes = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut bytes);
+        let key = format!("{}{}", API_KEY_PREFIX, hex::encode(bytes));
+        let hash = Self::hash_key(&key);
+        (key, hash)
+    }
+
+    pub fn hash_key(key: &str) -> String {
+        blake3::hash(key.as_bytes()).to_hex().to_string()
+    }
+
+    /// Whether the key can be used at `now`: not revoked and not expired.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        if self.revoked_at.is_some() {
+            return false;
+        }
+        match &self.expires_at {
+            Some(expires_at) => DateTime::parse_from_rfc3339(expires_at)
+                .map(|expires_at| expires_at.with_timezone(&Utc) > now)
+                .unwrap_or(false),
+            None => true,
+        }
+    }
+
+    pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct CreateApiKeyRequest {
+    pub label: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// RFC3339 timestamp after which the key stops working. Keys without one don't expire.
+    pub expires_at: Option<String>,
+}
+
+/// The answer to the creation of a key: the only time the key itself is returned.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct CreatedApiKey {
+    pub key: String,
+    pub api_key: ApiKey,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn api_key(expires_at: Option<String>) -> ApiKey {
+        ApiKey {
+            id: "id".to_string(),
+            label: "ci".to_string(),
+            key_prefix: "zk_1234".to_string(),
+            scopes: vec![ApiKeyScope::JobsRead],
+            created_at: Utc::now().to_rfc3339(),
+            expires_at,
+            revoked_at: None,
+            last_used_at: None,
+            last_used_route: None,
+        }
+    }
+
+    #[test]
+    fn test_scope_names() {
+        for scope in ApiKeyScope::ALL {
+            assert_eq!(ApiKeyScope::from_str(scope.as_str()).unwrap(), scope);
+            assert_eq!(serde_json::to_string(&scope).unwrap(), format!("\"{}\"", scope));
+        }
+        assert!(ApiKeyScope::from_str("wallet:drain").is_err());
+    }
+
+    #[test]
+    fn test_key_hash_and_expiry() {
+        let (key, hash) = ApiKey::generate_key();
+        assert!(key.starts_with(API_KEY_PREFIX));
+        assert_eq!(ApiKey::hash_key(&key), hash);
+        assert_ne!(ApiKey::hash_key("zk_other"), hash);
+
+        let now = Utc::now();
+        assert!(api_key(None).is_active(now));
+        assert!(api_key(Some((now + Duration::hours(1)).to_rfc3339())).is_active(now));
+        assert!(!api_key(Some((now - Duration::hours(1)).to_rfc3339())).is_active(now));
+
+        let mut revoked = api_key(None);
+        revoked.revoked_at = Some(now.to_rfc3339());
+        assert!(!revoked.is_active(now));
+    }
+}
@@ -1,4 +1,9 @@
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Matches `{{name}}` placeholders in a prompt, with optional spaces inside the braces.
+const PROMPT_ARGUMENT_PATTERN: &str = r"\{\{\s*([A-Za-z0-9_\-]+)\s*\}\}";
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CustomPrompt {
@@ -10,3 +15,78 @@ pub struct CustomPrompt {
     pub version: String,
     pub is_favorite: bool,
 }
+
+impl CustomPrompt {
+    /// The names of the `{{name}}` placeholders of the prompt, in order of first appearance.
+    pub fn template_arguments(&self) -> Vec<String> {
+        let re = Regex::new(PROMPT_ARGUMENT_PATTERN).unwrap();
+        let mut arguments: Vec<String> = Vec::new();
+        for captures in re.captures_iter(&self.prompt) {
+            let name = captures[1].to_string();
+            if !arguments.contains(&name) {
+                arguments.push(name);
+            }
+        }
+        arguments
+    }
+
+    /// Replaces the placeholders with `arguments`. Returns the names of the placeholders that
+    /// have no value if any are missing.
+    pub fn render(&self, arguments: &HashMap<String, String>) -> Result<String, Vec<String>> {
+        let missing: Vec<String> = self
+            .template_arguments()
+            .into_iter()
+            .filter(|name| !arguments.contains_key(name))
+            .collect();
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+
+        let re = Regex::new(PROMPT_ARGUMENT_PATTERN).unwrap();
+        Ok(re
+            .replace_all(&self.prompt, |captures: &regex::Captures| {
+                arguments[&captures[1]].clone()
+            })
+            .into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(text: &str) -> CustomPrompt {
+        CustomPrompt {
+            rowid: None,
+            name: "Summarize".to_string(),
+            prompt: text.to_string(),
+            is_system: false,
+            is_enabled: true,
+            version: "1".to_string(),
+            is_favorite: false,
+        }
+    }
+
+    #[test]
+    fn test_template_arguments_and_render() {
+        let prompt = prompt("Summarize {{ text }} in {{language}}. Keep {{text}} short.");
+        assert_eq!(prompt.template_arguments(), vec!["text", "language"]);
+
+        let mut arguments = HashMap::new();
+        arguments.insert("text".to_string(), "the report".to_string());
+        assert_eq!(prompt.render(&arguments), Err(vec!["language".to_string()]));
+
+        arguments.insert("language".to_string(), "French".to_string());
+        assert_eq!(
+            prompt.render(&arguments).unwrap(),
+            "Summarize the report in French. Keep the report short."
+        );
+    }
+
+    #[test]
+    fn test_prompt_without_arguments() {
+        let prompt = prompt("You are a helpful assistant. {not a placeholder}");
+        assert!(prompt.template_arguments().is_empty());
+        assert_eq!(prompt.render(&HashMap::new()).unwrap(), prompt.prompt);
+    }
+}
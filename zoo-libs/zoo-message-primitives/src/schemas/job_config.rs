@@ -17,9 +17,24 @@ pub struct JobConfig {
     pub thinking: Option<bool>,
     pub reasoning_effort: Option<String>,
     pub web_search_enabled: Option<bool>,
+    /// What happens to old messages once the conversation no longer fits in the context window.
+    /// Truncate when not set.
+    pub memory_strategy: Option<ConversationMemoryStrategy>,
     // TODO: add ctx_...
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversationMemoryStrategy {
+    /// The oldest messages are dropped from the prompt.
+    #[default]
+    Truncate,
+    /// Older messages are folded into a rolling summary that is always part of the prompt.
+    Summarize,
+    /// Like summarize, and the summarized messages most related to the new message are added back.
+    SummarizeAndRetrieve,
+}
+
 impl JobConfig {
     /// Merges two JobConfig instances, preferring values from `self` over `other`.
     pub fn merge(&self, other: &JobConfig) -> JobConfig {
@@ -37,6 +52,7 @@ impl JobConfig {
             thinking: self.thinking.or(other.thinking),
             reasoning_effort: self.reasoning_effort.clone().or_else(|| other.reasoning_effort.clone()),
             web_search_enabled: self.web_search_enabled.or(other.web_search_enabled),
+            memory_strategy: self.memory_strategy.or(other.memory_strategy),
             other_model_params: self
                 .other_model_params
                 .clone()
@@ -60,6 +76,7 @@ impl JobConfig {
             thinking: None,
             reasoning_effort: None,
             web_search_enabled: None,
+            memory_strategy: None,
         }
     }
 }
@@ -102,5 +119,32 @@ mod tests {
         assert_eq!(job_config.thinking, Some(true));
         assert_eq!(job_config.reasoning_effort, Some("medium".to_string()));
         assert_eq!(job_config.web_search_enabled, Some(false));
+        assert_eq!(job_config.memory_strategy, None);
+    }
+
+    #[test]
+    fn test_deserialize_memory_strategy() {
+        let json_data = r#"{
+            "custom_system_prompt": null,
+            "custom_prompt": null,
+            "temperature": null,
+            "max_tokens": null,
+            "seed": null,
+            "top_k": null,
+            "top_p": null,
+            "stream": null,
+            "other_model_params": null,
+            "use_tools": null,
+            "thinking": null,
+            "reasoning_effort": null,
+            "web_search_enabled": null,
+            "memory_strategy": "summarize_and_retrieve"
+        }"#;
+
+        let job_config: JobConfig = serde_json::from_str(json_data).expect("Failed to deserialize JSON");
+        assert_eq!(
+            JobConfig::empty().merge(&job_config).memory_strategy,
+            Some(ConversationMemoryStrategy::SummarizeAndRetrieve)
+        );
     }
 }
@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// Kinds of inference failures that can be handled differently by an `LLMFallbackPolicy`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LLMErrorClass {
+    /// The provider answered with a 429 or an equivalent "slow down" error.
+    RateLimit,
+    /// The provider is overloaded or failing (5xx, "overloaded", "service unavailable").
+    Overloaded,
+    /// The request timed out or the provider couldn't be reached.
+    Timeout,
+    /// The prompt doesn't fit in the model's context window.
+    ContextLength,
+    /// The API key is missing, invalid or not allowed to use the model.
+    Auth,
+    /// A daily or monthly quota is used up. Retrying soon won't help.
+    QuotaExceeded,
+    /// Anything else.
+    Other,
+}
+
+/// How to react to one class of error.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct LLMRetryRule {
+    /// Retries against the same provider before giving up on it.
+    pub max_retries: u32,
+    /// Wait before the first retry. It doubles on every following retry.
+    pub initial_backoff_ms: u64,
+    /// Upper bound for the wait between retries.
+    pub max_backoff_ms: u64,
+    /// Whether to move on to the next provider of the chain once the retries are exhausted.
+    pub fallback: bool,
+}
+
+impl LLMRetryRule {
+    /// Nothing is retried unless a policy sets `max_retries`: a node without a fallback config
+    /// behaves as if there were none. The backoffs are the ones used once retries are enabled.
+    pub fn default_for(class: LLMErrorClass) -> Self {
+        match class {
+            LLMErrorClass::RateLimit => Self {
+                max_retries: 0,
+                initial_backoff_ms: 2_000,
+                max_backoff_ms: 30_000,
+                fallback: true,
+            },
+            LLMErrorClass::Overloaded | LLMErrorClass::Timeout => Self {
+                max_retries: 0,
+                initial_backoff_ms: 1_000,
+                max_backoff_ms: 10_000,
+                fallback: true,
+            },
+            LLMErrorClass::ContextLength | LLMErrorClass::Auth | LLMErrorClass::QuotaExceeded => Self {
+                max_retries: 0,
+                initial_backoff_ms: 0,
+                max_backoff_ms: 0,
+                fallback: true,
+            },
+            LLMErrorClass::Other => Self {
+                max_retries: 0,
+                initial_backoff_ms: 0,
+                max_backoff_ms: 0,
+                fallback: false,
+            },
+        }
+    }
+
+    /// Wait before the retry number `retry` (starting at 0).
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff_ms = self
+            .initial_backoff_ms
+            .saturating_mul(2u64.saturating_pow(retry))
+            .min(self.max_backoff_ms);
+        Duration::from_millis(backoff_ms)
+    }
+}
+
+/// Retry and fallback behavior for the inferences of one LLM provider.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct LLMFallbackPolicy {
+    /// Ids of the LLM providers to try, in order, once the original provider gives up.
+    #[serde(default)]
+    pub fallback_provider_ids: Vec<String>,
+    /// Cheaper model (e.g. `openai:gpt-4o-mini`) tried with the same provider settings before
+    /// moving on to the fallback providers.
+    #[serde(default)]
+    pub downgrade_model: Option<String>,
+    /// Overrides of the default rule of each error class.
+    #[serde(default)]
+    pub rules: HashMap<LLMErrorClass, LLMRetryRule>,
+}
+
+impl LLMFallbackPolicy {
+    pub fn rule_for(&self, class: LLMErrorClass) -> LLMRetryRule {
+        self.rules
+            .get(&class)
+            .cloned()
+            .unwrap_or_else(|| LLMRetryRule::default_for(class))
+    }
+}
+
+/// Node wide fallback configuration, stored in the `llm_fallback_config` preference.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct LLMFallbackConfig {
+    /// Policy for the providers that don't have their own.
+    #[serde(default)]
+    pub default: LLMFallbackPolicy,
+    /// Policies by LLM provider id.
+    #[serde(default)]
+    pub providers: HashMap<String, LLMFallbackPolicy>,
+}
+
+impl LLMFallbackConfig {
+    pub const PREFERENCE_KEY: &'static str = "llm_fallback_config";
+
+    pub fn policy_for(&self, llm_provider_id: &str) -> &LLMFallbackPolicy {
+        self.providers.get(llm_provider_id).unwrap_or(&self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_max() {
+        let rule = LLMRetryRule::default_for(LLMErrorClass::RateLimit);
+        assert_eq!(rule.backoff(0), Duration::from_millis(2_000));
+        assert_eq!(rule.backoff(1), Duration::from_millis(4_000));
+        assert_eq!(rule.backoff(3), Duration::from_millis(16_000));
+        assert_eq!(rule.backoff(10), Duration::from_millis(30_000));
+        assert_eq!(rule.backoff(u32::MAX), Duration::from_millis(30_000));
+    }
+
+    #[test]
+    fn test_no_retries_by_default() {
+        let policy = LLMFallbackConfig::default().policy_for("any_llm").clone();
+        for class in [
+            LLMErrorClass::RateLimit,
+            LLMErrorClass::Overloaded,
+            LLMErrorClass::Timeout,
+            LLMErrorClass::ContextLength,
+            LLMErrorClass::Auth,
+            LLMErrorClass::QuotaExceeded,
+            LLMErrorClass::Other,
+        ] {
+            assert_eq!(policy.rule_for(class).max_retries, 0);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_fallback_config() {
+        let config: LLMFallbackConfig = serde_json::from_value(json!({
+            "default": {
+                "fallback_provider_ids": ["backup_llm"]
+            },
+            "providers": {
+                "main_llm": {
+                    "fallback_provider_ids": ["cheap_llm", "backup_llm"],
+                    "downgrade_model": "openai:gpt-4o-mini",
+                    "rules": {
+                        "rate_limit": {
+                            "max_retries": 5,
+                            "initial_backoff_ms": 500,
+                            "max_backoff_ms": 8000,
+                            "fallback": false
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let policy = config.policy_for("main_llm");
+        assert_eq!(policy.fallback_provider_ids, vec!["cheap_llm", "backup_llm"]);
+        assert_eq!(policy.downgrade_model.as_deref(), Some("openai:gpt-4o-mini"));
+        assert_eq!(policy.rule_for(LLMErrorClass::RateLimit).max_retries, 5);
+        assert!(!policy.rule_for(LLMErrorClass::RateLimit).fallback);
+        // Classes without an override keep their default rule
+        assert_eq!(
+            policy.rule_for(LLMErrorClass::Overloaded),
+            LLMRetryRule::default_for(LLMErrorClass::Overloaded)
+        );
+
+        let policy = config.policy_for("another_llm");
+        assert_eq!(policy.fallback_provider_ids, vec!["backup_llm"]);
+        assert!(policy.downgrade_model.is_none());
+    }
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Settings of the LLM response cache, stored in the `llm_response_cache` preference.
+/// The cache is off unless `enabled` is set.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(default)]
+pub struct LLMResponseCacheConfig {
+    pub enabled: bool,
+    /// Seconds a cached response stays valid.
+    pub ttl_secs: u64,
+    /// Maximum number of cached responses. The least recently used ones are evicted first.
+    pub max_entries: usize,
+    /// Also reuse the response of a prompt that is only similar (not identical) to the new one,
+    /// comparing their embeddings.
+    pub semantic: bool,
+    /// Minimum cosine similarity for a semantic hit.
+    pub similarity_threshold: f32,
+}
+
+impl Default for LLMResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: 24 * 60 * 60,
+            max_entries: 1000,
+            semantic: false,
+            similarity_threshold: 0.97,
+        }
+    }
+}
+
+impl LLMResponseCacheConfig {
+    pub const PREFERENCE_KEY: &'static str = "llm_response_cache";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let config: LLMResponseCacheConfig = serde_json::from_value(json!({
+            "enabled": true,
+            "semantic": true
+        }))
+        .unwrap();
+        assert!(config.enabled);
+        assert!(config.semantic);
+        assert_eq!(config.ttl_secs, 24 * 60 * 60);
+        assert_eq!(config.max_entries, 1000);
+    }
+}
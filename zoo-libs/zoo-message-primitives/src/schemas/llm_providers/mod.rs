@@ -1,4 +1,6 @@
 pub mod serialized_llm_provider;
 pub mod agent;
 pub mod common_agent_llm_provider;
+pub mod llm_fallback;
+pub mod llm_response_cache;
 pub mod zoo_backend;
\ No newline at end of file
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Vector FS files are published over MCP as `zoo://files/<relative path>`.
+pub const MCP_FILE_URI_PREFIX: &str = "zoo://files/";
+/// Vector FS folders are published over MCP as `zoo://folders/<relative path>`.
+pub const MCP_FOLDER_URI_PREFIX: &str = "zoo://folders/";
+
+/// A vector FS item addressed by an MCP resource URI. Paths are relative to the vector FS root,
+/// without leading or trailing `/`. The root folder is `zoo://folders/`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum McpResourceUri {
+    File(String),
+    Folder(String),
+}
+
+impl McpResourceUri {
+    pub fn parse(uri: &str) -> Result<Self, String> {
+        let (path, is_file) = if let Some(path) = uri.strip_prefix(MCP_FILE_URI_PREFIX) {
+            (path, true)
+        } else if let Some(path) = uri.strip_prefix(MCP_FOLDER_URI_PREFIX) {
+            (path, false)
+        } else {
+            return Err(format!(
+                "Unsupported resource URI {}, expected {}<path> or {}<path>",
+                uri, MCP_FILE_URI_PREFIX, MCP_FOLDER_URI_PREFIX
+            ));
+        };
+
+        let path = percent_decode(path).ok_or_else(|| format!("Invalid percent encoding in {}", uri))?;
+        let path = path.trim_matches('/').to_string();
+        if path.split('/').any(|segment| segment == "..") {
+            return Err(format!("Resource URI {} points outside of the vector FS", uri));
+        }
+
+        match (is_file, path.is_empty()) {
+            (true, true) => Err(format!("Resource URI {} has no file path", uri)),
+            (true, false) => Ok(McpResourceUri::File(path)),
+            (false, _) => Ok(McpResourceUri::Folder(path)),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        match self {
+            McpResourceUri::File(path) | McpResourceUri::Folder(path) => path,
+        }
+    }
+
+    pub fn to_uri(&self) -> String {
+        match self {
+            McpResourceUri::File(path) => format!("{}{}", MCP_FILE_URI_PREFIX, percent_encode(path)),
+            McpResourceUri::Folder(path) => format!("{}{}", MCP_FOLDER_URI_PREFIX, percent_encode(path)),
+        }
+    }
+}
+
+impl fmt::Display for McpResourceUri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_uri())
+    }
+}
+
+/// A vector FS file or folder as listed to MCP clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpResource {
+    pub uri: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+    /// Changes whenever the content of the resource changes. Used to notify subscribers.
+    pub version: String,
+}
+
+/// What `resources/read` returns: the parsed text chunks of a file, in order, or a JSON listing
+/// of the items of a folder as a single chunk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpResourceContents {
+    pub uri: String,
+    pub mime_type: Option<String>,
+    pub chunks: Vec<String>,
+}
+
+/// Encodes everything but RFC 3986 unreserved characters and `/`.
+fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_uri_round_trip() {
+        let file = McpResourceUri::File("docs/Release notes (v2).md".to_string());
+        let uri = file.to_uri();
+        assert_eq!(uri, "zoo://files/docs/Release%20notes%20%28v2%29.md");
+        assert_eq!(McpResourceUri::parse(&uri).unwrap(), file);
+
+        assert_eq!(
+            McpResourceUri::parse("zoo://folders/docs/").unwrap(),
+            McpResourceUri::Folder("docs".to_string())
+        );
+        assert_eq!(
+            McpResourceUri::parse("zoo://folders/").unwrap(),
+            McpResourceUri::Folder(String::new())
+        );
+    }
+
+    #[test]
+    fn test_invalid_resource_uris() {
+        assert!(McpResourceUri::parse("file:///etc/passwd").is_err());
+        assert!(McpResourceUri::parse("zoo://files/").is_err());
+        assert!(McpResourceUri::parse("zoo://files/docs/../../secret.txt").is_err());
+        assert!(McpResourceUri::parse("zoo://files/docs/%2E%2E/secret.txt").is_err());
+        assert!(McpResourceUri::parse("zoo://files/bad%2").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Searches the content of the messages of every inbox.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct SearchMessagesRequest {
+    /// Words to look for. Every word must appear in a message for a text match, unless `match_any` is set.
+    pub query: String,
+    /// Only messages of jobs run by this agent or LLM provider.
+    pub agent_id: Option<String>,
+    pub inbox_name: Option<String>,
+    /// RFC3339 timestamps, both inclusive.
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// 20 by default, at most 100.
+    pub limit: Option<usize>,
+    /// Also finds messages with a similar meaning. Only messages stored while the
+    /// `message_search_embeddings` preference was enabled have the embeddings it needs.
+    #[serde(default)]
+    pub semantic: bool,
+    /// Matches messages with any of the words instead of all of them. The ones with more, and
+    /// rarer, words rank first.
+    #[serde(default)]
+    pub match_any: bool,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageSearchMatch {
+    Text,
+    Semantic,
+    Both,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct MessageSearchResult {
+    /// Same hash as the message in the inbox, to jump to it.
+    pub message_hash: String,
+    pub inbox_name: String,
+    /// The name of the conversation, as shown in the inbox list.
+    pub inbox_title: String,
+    pub job_id: Option<String>,
+    /// The agent or LLM provider of the job.
+    pub agent_id: Option<String>,
+    pub time_key: String,
+    /// The matching part of the message as escaped HTML, with the matched words between `<mark>` and
+    /// `</mark>`.
+    pub snippet: String,
+    /// Higher is more relevant.
+    pub score: f64,
+    pub matched_by: MessageSearchMatch,
+}
@@ -1,3 +1,5 @@
+pub mod agent_memory;
+pub mod api_keys;
 pub mod coinbase_mpc_config;
 pub mod cron_task;
 pub mod crontab;
@@ -12,9 +14,13 @@ pub mod job;
 pub mod job_config;
 pub mod llm_message;
 pub mod llm_providers;
+pub mod mcp_resources;
+pub mod message_search;
+pub mod node_backup;
 pub mod prompts;
 pub mod registration_code;
 pub mod retry;
+pub mod secrets;
 pub mod zoo_fs;
 pub mod zoo_name;
 pub mod zoo_network;
@@ -25,6 +31,7 @@ pub mod zoo_tools;
 pub mod smart_inbox;
 pub mod subprompts;
 pub mod tool_router_key;
+pub mod usage;
 pub mod wallet_complementary;
 pub mod wallet_mixed;
 pub mod ws_types;
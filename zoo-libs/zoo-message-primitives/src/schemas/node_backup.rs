@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Version of the node backup archive layout. Archives with a newer format are refused.
+pub const NODE_BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// A part of the node that a backup holds and that can be restored on its own.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupSection {
+    /// The node keys, local identities, devices and API keys.
+    Identity,
+    /// The secrets vault: its wrapped data key, named secrets and OAuth tokens.
+    Secrets,
+    LlmProviders,
+    Agents,
+    /// Tools with their versions, assets, playgrounds and MCP servers.
+    Tools,
+    CronTasks,
+    Prompts,
+    /// Preferences, settings and regex patterns.
+    Preferences,
+    /// Vector FS files with their parsed files, chunks and embeddings.
+    VectorFs,
+    /// Inboxes, their messages and jobs.
+    Inbox,
+}
+
+impl BackupSection {
+    pub const ALL: [BackupSection; 10] = [
+        BackupSection::Identity,
+        BackupSection::Secrets,
+        BackupSection::LlmProviders,
+        BackupSection::Agents,
+        BackupSection::Tools,
+        BackupSection::CronTasks,
+        BackupSection::Prompts,
+        BackupSection::Preferences,
+        BackupSection::VectorFs,
+        BackupSection::Inbox,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupSection::Identity => "identity",
+            BackupSection::Secrets => "secrets",
+            BackupSection::LlmProviders => "llm_providers",
+            BackupSection::Agents => "agents",
+            BackupSection::Tools => "tools",
+            BackupSection::CronTasks => "cron_tasks",
+            BackupSection::Prompts => "prompts",
+            BackupSection::Preferences => "preferences",
+            BackupSection::VectorFs => "vector_fs",
+            BackupSection::Inbox => "inbox",
+        }
+    }
+}
+
+/// Describes a backup archive. It's signed with the identity key of the node that made it and
+/// lists the blake3 hash of every other entry, so a changed or truncated archive is refused.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    pub node_name: String,
+    /// The identity public key of the node that made the backup and signed this manifest.
+    pub identity_public_key: String,
+    pub node_version: String,
+    /// Latest database migration applied when the backup was made.
+    pub schema_version: u32,
+    /// Default embedding model of the node. Restoring into a node with another model embeds the
+    /// restored files, tools, prompts and memories again.
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    /// RFC3339 timestamp.
+    pub created_at: String,
+    pub sections: Vec<BackupSection>,
+    /// Whether the node keys in the archive are encrypted with a passphrase.
+    pub secrets_encrypted: bool,
+    /// Archive entry name to the hex encoded blake3 hash of its content.
+    pub entries: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct NodeBackupInfo {
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub manifest: BackupManifest,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct CreateNodeBackupRequest {
+    /// Encrypts the node keys of the identity section. Without one they are stored in plaintext.
+    pub passphrase: Option<String>,
+    /// Sections to back up, all of them by default.
+    pub sections: Option<Vec<BackupSection>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct RestoreNodeBackupRequest {
+    /// A backup in the backups folder of the node, created or uploaded before.
+    pub file_name: String,
+    /// Decrypts the node keys, required to restore the identity of an encrypted backup.
+    pub passphrase: Option<String>,
+    /// Sections to restore, every section of the backup by default.
+    pub sections: Option<Vec<BackupSection>>,
+    /// The identity public key of the node that made the backup, required to restore a backup
+    /// of another node. Without it only backups signed by this node are restored.
+    #[serde(default)]
+    pub trusted_signer: Option<String>,
+    /// Only checks the signature, the hashes and the passphrase.
+    #[serde(default)]
+    pub validate_only: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct RestoreNodeBackupReport {
+    pub manifest: BackupManifest,
+    /// Whether the backup was made by this node, or by another one such as the previous machine.
+    pub signed_by_this_node: bool,
+    pub restored_sections: Vec<BackupSection>,
+    /// Rows restored per table.
+    pub restored_rows: BTreeMap<String, usize>,
+    pub restored_files: usize,
+    /// The node keys, identities or secrets vault were replaced and are only loaded on start.
+    pub restart_required: bool,
+    pub warnings: Vec<String>,
+}
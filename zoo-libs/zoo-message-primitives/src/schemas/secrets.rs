@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Tool configs can point to a named secret instead of holding the value, e.g. `secret://openai`.
+/// The reference is replaced by the secret right before the tool runs.
+pub const SECRET_REFERENCE_PREFIX: &str = "secret://";
+
+/// The name of the secret `value` points to, if it's a secret reference.
+pub fn secret_reference_name(value: &str) -> Option<&str> {
+    value
+        .strip_prefix(SECRET_REFERENCE_PREFIX)
+        .filter(|name| !name.is_empty())
+}
+
+/// Secret names are used in references, so they are kept to letters, digits, `_`, `-` and `.`.
+pub fn is_valid_secret_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 128
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// A named secret, without its value.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct SecretInfo {
+    pub name: String,
+    pub description: Option<String>,
+    /// RFC3339 timestamps.
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct SetSecretRequest {
+    pub name: String,
+    pub value: String,
+    pub description: Option<String>,
+}
+
+/// Where the key that wraps the secrets data key comes from.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretsKeySourceKind {
+    /// Derived from the identity secret key of the node.
+    NodeIdentity,
+    /// Derived from the passphrase in `NODE_SECRETS_PASSPHRASE`.
+    Passphrase,
+}
+
+impl SecretsKeySourceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecretsKeySourceKind::NodeIdentity => "node_identity",
+            SecretsKeySourceKind::Passphrase => "passphrase",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "node_identity" => Some(SecretsKeySourceKind::NodeIdentity),
+            "passphrase" => Some(SecretsKeySourceKind::Passphrase),
+            _ => None,
+        }
+    }
+}
+
+/// The data key currently used to encrypt the secrets.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct SecretsKeyInfo {
+    pub key_id: String,
+    pub key_source: SecretsKeySourceKind,
+    pub created_at: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct RotateSecretsKeyRequest {
+    /// Wrap the new data key with this passphrase instead of the node identity. The node then has
+    /// to be started with the same `NODE_SECRETS_PASSPHRASE`.
+    pub passphrase: Option<String>,
+}
+
+/// What an exported secret protects.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretLocation {
+    Secret,
+    LlmProviderApiKey,
+    OAuthToken,
+    ToolConfig,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ExportedSecret {
+    pub location: SecretLocation,
+    /// Secret name, LLM provider id, `connection_name:::tool_key:::field` or `tool_key:::key_name`.
+    pub name: String,
+    /// The encrypted value, as stored. The export never contains the plaintext.
+    pub sealed_value: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct SecretsExport {
+    pub key: SecretsKeyInfo,
+    pub exported_at: String,
+    pub secrets: Vec<ExportedSecret>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_references() {
+        assert_eq!(secret_reference_name("secret://openai"), Some("openai"));
+        assert_eq!(secret_reference_name("secret://"), None);
+        assert_eq!(secret_reference_name("sk-1234"), None);
+
+        assert!(is_valid_secret_name("openai.prod_key-2"));
+        assert!(!is_valid_secret_name("with space"));
+        assert!(!is_valid_secret_name(""));
+    }
+}
@@ -0,0 +1,254 @@
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// One inference as recorded in the usage ledger.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct UsageRecord {
+    pub id: Option<i64>,
+    /// RFC3339 timestamp of the inference.
+    pub created_at: String,
+    pub profile: String,
+    pub agent_id: Option<String>,
+    pub job_id: Option<String>,
+    pub llm_provider_id: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Cost from the `UsagePricing` of the node, or from the cost tier of the model when it has no
+    /// price there. 0 for the models of an unknown tier.
+    pub estimated_cost_usd: f64,
+}
+
+/// Aggregated usage of a set of ledger records.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct UsageTotals {
+    pub inferences: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub estimated_cost_usd: f64,
+}
+
+impl UsageTotals {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+/// Filters for a usage query. Every field is optional; `from` and `to` are RFC3339 timestamps.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct UsageQuery {
+    pub profile: Option<String>,
+    pub agent_id: Option<String>,
+    pub job_id: Option<String>,
+    pub llm_provider_id: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct UsageByLLMProvider {
+    pub llm_provider_id: String,
+    pub model: String,
+    pub totals: UsageTotals,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct UsageSummary {
+    pub totals: UsageTotals,
+    pub by_llm_provider: Vec<UsageByLLMProvider>,
+}
+
+/// Price of a model in USD per million tokens.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ModelPrice {
+    pub input_usd_per_million: f64,
+    pub output_usd_per_million: f64,
+}
+
+impl ModelPrice {
+    pub fn cost_usd(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input_usd_per_million + output_tokens as f64 * self.output_usd_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Prices used to estimate the cost of the inferences, stored in the `usage_pricing` preference.
+/// The node doesn't know what providers charge: the models without a price here are estimated from
+/// their cost tier.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct UsagePricing {
+    /// Prices by model, as recorded in the ledger (e.g. `openai:gpt-4o`).
+    #[serde(default)]
+    pub models: HashMap<String, ModelPrice>,
+}
+
+impl UsagePricing {
+    pub const PREFERENCE_KEY: &'static str = "usage_pricing";
+
+    pub fn estimate_cost_usd(&self, model: &str, input_tokens: u64, output_tokens: u64) -> f64 {
+        self.models
+            .get(model)
+            .map_or(0.0, |price| price.cost_usd(input_tokens, output_tokens))
+    }
+}
+
+/// What a budget applies to.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetScope {
+    /// Every inference of the profile (e.g. `@@node.sep-zoo/main`).
+    Profile,
+    /// The inferences made on behalf of an agent.
+    Agent,
+}
+
+impl BudgetScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetScope::Profile => "profile",
+            BudgetScope::Agent => "agent",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPeriod {
+    Daily,
+    Monthly,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Daily => "daily",
+            BudgetPeriod::Monthly => "monthly",
+        }
+    }
+
+    /// Start (UTC) of the period that contains `now`.
+    pub fn period_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let (year, month, day) = match self {
+            BudgetPeriod::Daily => (now.year(), now.month(), now.day()),
+            BudgetPeriod::Monthly => (now.year(), now.month(), 1),
+        };
+        Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).single().unwrap_or(now)
+    }
+}
+
+/// What happens once a budget is used up.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetEnforcement {
+    /// Inferences are rejected until the next period.
+    Hard,
+    /// Inferences go on, downgraded to `downgrade_llm_provider_id` if set.
+    Soft,
+}
+
+impl BudgetEnforcement {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetEnforcement::Hard => "hard",
+            BudgetEnforcement::Soft => "soft",
+        }
+    }
+}
+
+/// Token and/or cost limit for a profile or an agent over a period.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct UsageBudget {
+    pub scope: BudgetScope,
+    /// Profile name or agent id, depending on `scope`.
+    pub scope_id: String,
+    pub period: BudgetPeriod,
+    pub max_tokens: Option<u64>,
+    /// Inferences of models without a price and of an unknown cost tier don't count towards it.
+    pub max_cost_usd: Option<f64>,
+    pub enforcement: BudgetEnforcement,
+    /// LLM provider used instead once a soft budget is used up.
+    #[serde(default)]
+    pub downgrade_llm_provider_id: Option<String>,
+}
+
+impl UsageBudget {
+    pub fn is_exceeded_by(&self, usage: &UsageTotals) -> bool {
+        self.max_tokens.map_or(false, |max| usage.total_tokens() >= max)
+            || self.max_cost_usd.map_or(false, |max| usage.estimated_cost_usd >= max)
+    }
+}
+
+/// A budget together with what has been used of it in the current period.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct UsageBudgetStatus {
+    pub budget: UsageBudget,
+    /// RFC3339 start of the current period.
+    pub period_start: String,
+    pub usage: UsageTotals,
+    pub exceeded: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_period_start() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 15, 17, 42, 5).unwrap();
+        assert_eq!(
+            BudgetPeriod::Daily.period_start(now),
+            Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            BudgetPeriod::Monthly.period_start(now),
+            Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_budget_is_exceeded_by() {
+        let budget = UsageBudget {
+            scope: BudgetScope::Agent,
+            scope_id: "my_agent".to_string(),
+            period: BudgetPeriod::Daily,
+            max_tokens: Some(1000),
+            max_cost_usd: Some(1.0),
+            enforcement: BudgetEnforcement::Hard,
+            downgrade_llm_provider_id: None,
+        };
+
+        let mut usage = UsageTotals {
+            inferences: 3,
+            input_tokens: 400,
+            output_tokens: 200,
+            estimated_cost_usd: 0.5,
+        };
+        assert!(!budget.is_exceeded_by(&usage));
+
+        usage.output_tokens = 600;
+        assert!(budget.is_exceeded_by(&usage));
+
+        usage.output_tokens = 100;
+        usage.estimated_cost_usd = 1.2;
+        assert!(budget.is_exceeded_by(&usage));
+    }
+
+    #[test]
+    fn test_estimate_cost_of_priced_models_only() {
+        let pricing: UsagePricing = serde_json::from_value(serde_json::json!({
+            "models": {
+                "openai:gpt-4o": {"input_usd_per_million": 2.5, "output_usd_per_million": 10.0}
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(pricing.estimate_cost_usd("openai:gpt-4o", 200_000, 100_000), 1.5);
+        assert_eq!(pricing.estimate_cost_usd("ollama:llama3.1:8b", 200_000, 100_000), 0.0);
+        assert_eq!(
+            UsagePricing::default().estimate_cost_usd("openai:gpt-4o", 200_000, 100_000),
+            0.0
+        );
+    }
+}
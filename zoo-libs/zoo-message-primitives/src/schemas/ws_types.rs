@@ -49,6 +49,15 @@ pub struct PaymentMetadata {
     pub error_message: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthMetadata {
+    pub tool_key: String,
+    pub connection_name: Option<String>,
+    /// Where the user logs in to authorize the tool again.
+    pub authorization_url: String,
+    pub error_message: Option<String>,
+}
+
 #[derive(Debug)]
 pub enum WebSocketManagerError {
     UserValidationFailed(String),
@@ -113,6 +122,7 @@ pub enum ToolStatusType {
 pub enum WidgetMetadata {
     PaymentRequest(PaymentMetadata),
     ToolRequest(ToolMetadata),
+    OAuthRequired(OAuthMetadata),
 }
 
 pub type MessageQueue = Arc<Mutex<VecDeque<(WSTopic, String, String, WSMessageType, bool)>>>;
@@ -98,4 +98,17 @@ mod tests {
         assert_eq!(deserialized.vector_fs_folders[0].relative_path(), os_path::OsPath::from("My Files (Private)").to_string());
         assert_eq!(deserialized.vector_search_mode, VectorSearchMode::FillUpTo25k); // Check default
     }
+
+    #[test]
+    fn test_deserialize_minimal_job_scope_with_top_k_search_mode() {
+        let json_data = json!({
+            "vector_fs_items": [],
+            "vector_fs_folders": ["/My Files (Private)"],
+            "vector_search_mode": {"TopK": 8}
+        });
+
+        let deserialized: MinimalJobScope = serde_json::from_value(json_data).expect("Failed to deserialize");
+
+        assert_eq!(deserialized.vector_search_mode, VectorSearchMode::TopK(8));
+    }
 }
@@ -3,5 +3,41 @@ use utoipa::ToSchema;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
 pub enum VectorSearchMode {
+    /// Ranks chunks by embedding distance and fills the prompt with them (and their neighbors) up to 25k tokens.
     FillUpTo25k,
+    /// Like `FillUpTo25k`, but ranks chunks by fusing the embedding and the BM25 keyword rankings.
+    Hybrid,
+    /// Like `FillUpTo25k`, but ranks chunks only by BM25 keyword relevance. No embeddings are needed.
+    KeywordOnly,
+    /// Returns only the `n` best chunks of the hybrid ranking, without neighbors.
+    TopK(usize),
+}
+
+impl VectorSearchMode {
+    /// Whether the query needs to be embedded for this mode.
+    pub fn uses_embeddings(&self) -> bool {
+        !matches!(self, VectorSearchMode::KeywordOnly)
+    }
+
+    /// Whether the results are expanded with neighboring chunks to fill the prompt.
+    pub fn fills_prompt(&self) -> bool {
+        !matches!(self, VectorSearchMode::TopK(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_serialize_search_modes() {
+        assert_eq!(serde_json::to_value(VectorSearchMode::Hybrid).unwrap(), json!("Hybrid"));
+        assert_eq!(serde_json::to_value(VectorSearchMode::TopK(5)).unwrap(), json!({"TopK": 5}));
+
+        let mode: VectorSearchMode = serde_json::from_value(json!("KeywordOnly")).unwrap();
+        assert_eq!(mode, VectorSearchMode::KeywordOnly);
+        let mode: VectorSearchMode = serde_json::from_value(json!({"TopK": 3})).unwrap();
+        assert_eq!(mode, VectorSearchMode::TopK(3));
+    }
 }
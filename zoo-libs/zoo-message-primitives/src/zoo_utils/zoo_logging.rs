@@ -1,6 +1,6 @@
 use chrono::Local;
 
-use std::sync::{Arc, Mutex, Once};
+use std::sync::{Arc, Mutex, Once, RwLock};
 
 // Conditional compilation: Only include tracing imports for non-WASM targets
 #[cfg(not(target_arch = "wasm32"))]
@@ -8,6 +8,24 @@ use tracing::{debug, error, info, span, Level};
 
 static INIT: Once = Once::new();
 static TELEMETRY: Mutex<Option<Arc<dyn ZooTelemetry + Send + Sync>>> = Mutex::new(None);
+static LOG_CONFIG: RwLock<ZooLogConfig> = RwLock::new(ZooLogConfig {
+    all: false,
+    simple: false,
+    options: Vec::new(),
+});
+
+/// Logging enabled by the node config, on top of the `LOG_*` environment variables.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ZooLogConfig {
+    pub all: bool,
+    pub simple: bool,
+    pub options: Vec<ZooLogOption>,
+}
+
+/// Replaces the logging enabled by the node config. Can be called while the node runs.
+pub fn set_log_config(config: ZooLogConfig) {
+    *LOG_CONFIG.write().unwrap() = config;
+}
 
 pub fn set_telemetry(telemetry: Arc<dyn ZooTelemetry + Send + Sync>) {
     let mut telemetry_option = TELEMETRY.lock().unwrap();
@@ -18,7 +36,7 @@ pub trait ZooTelemetry {
     fn log(&self, option: ZooLogOption, level: ZooLogLevel, message: &str);
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum ZooLogOption {
     Blockchain,
     Database,
@@ -61,7 +79,8 @@ impl ZooLogLevel {
 }
 
 fn active_log_options() -> Vec<ZooLogOption> {
-    if std::env::var("LOG_ALL").is_ok() {
+    let config = LOG_CONFIG.read().unwrap();
+    if config.all || std::env::var("LOG_ALL").is_ok() {
         return vec![
             ZooLogOption::Blockchain,
             ZooLogOption::Database,
@@ -139,13 +158,14 @@ fn active_log_options() -> Vec<ZooLogOption> {
     if std::env::var("LOG_CRON_EXECUTION").is_ok() {
         active_options.push(ZooLogOption::CronExecution);
     }
+    active_options.extend(config.options.iter().copied());
     active_options
 }
 
 pub fn zoo_log(option: ZooLogOption, level: ZooLogLevel, message: &str) {
     let active_options = active_log_options();
     if active_options.contains(&option) {
-        let is_simple_log = std::env::var("LOG_SIMPLE").is_ok();
+        let is_simple_log = LOG_CONFIG.read().unwrap().simple || std::env::var("LOG_SIMPLE").is_ok();
         let time = Local::now().format("%Y-%m-%d %H:%M:%S");
 
         let option_str = format!("{:?}", option);
@@ -54,6 +54,7 @@ impl SqliteManager {
                 agent.edited,
             ],
         )?;
+        Self::sync_agent_knowledge_memories(&tx, &agent.agent_id.to_lowercase(), &agent.knowledge)?;
 
         tx.commit()?;
         Ok(())
@@ -74,6 +75,7 @@ impl SqliteManager {
         }
 
         tx.execute("DELETE FROM zoo_agents WHERE agent_id = ?", [&agent_id])?;
+        Self::remove_agent_memories(&tx, agent_id)?;
 
         tx.commit()?;
         Ok(())
@@ -258,6 +260,7 @@ impl SqliteManager {
                 
             ],
         )?;
+        Self::sync_agent_knowledge_memories(&tx, &updated_agent.agent_id, &updated_agent.knowledge)?;
 
         tx.commit()?;
         Ok(())
@@ -0,0 +1,594 @@
+use crate::{errors::SqliteManagerError, SqliteManager};
+use bytemuck::cast_slice;
+use chrono::{SecondsFormat, Utc};
+use rand::RngCore;
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use std::collections::HashSet;
+use zoo_message_primitives::schemas::agent_memory::{AgentMemory, AgentMemorySource};
+
+const MAX_AGENT_MEMORY_CHARS: usize = 4000;
+
+fn agent_memory_timestamp() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn new_agent_memory_id() -> String {
+    let mut id_bytes = [0u8; 8];
+    rand::rngs::OsRng.fill_bytes(&mut id_bytes);
+    hex::encode(id_bytes)
+}
+
+fn validate_agent_memory_content(content: &str) -> Result<String, SqliteManagerError> {
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(SqliteManagerError::ValidationError(
+            "The memory content is empty".to_string(),
+        ));
+    }
+    if content.chars().count() > MAX_AGENT_MEMORY_CHARS {
+        return Err(SqliteManagerError::ValidationError(format!(
+            "The memory content is longer than {} characters",
+            MAX_AGENT_MEMORY_CHARS
+        )));
+    }
+    Ok(content.to_string())
+}
+
+const AGENT_MEMORY_COLUMNS: &str = "id, memory_id, agent_id, content, source, job_id, created_at, updated_at";
+
+fn agent_memory_from_row(row: &Row) -> Result<(i64, AgentMemory)> {
+    let source: String = row.get(4)?;
+    let source = source
+        .parse::<AgentMemorySource>()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, e.into()))?;
+    Ok((
+        row.get(0)?,
+        AgentMemory {
+            memory_id: row.get(1)?,
+            agent_id: row.get(2)?,
+            content: row.get(3)?,
+            source,
+            job_id: row.get(5)?,
+            created_at: row.get(6)?,
+            updated_at: row.get(7)?,
+        },
+    ))
+}
+
+impl SqliteManager {
+    pub fn initialize_agent_memories_table(conn: &Connection) -> Result<()> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS agent_memories (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                memory_id TEXT NOT NULL UNIQUE,
+                agent_id TEXT NOT NULL,
+                content TEXT NOT NULL,
+                source TEXT NOT NULL,
+                job_id TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_agent_memories_agent_id ON agent_memories (agent_id);",
+            [],
+        )?;
+        Ok(())
+    }
+
+    // The rows of agent_memory_vec_items share their rowid with agent_memories. Memories are
+    // embedded by `embed_agent_memories` once they're written, searches only embed the query.
+    pub(crate) fn initialize_agent_memory_vec_table(conn: &Connection, vector_dimensions: usize) -> Result<()> {
+        conn.execute(
+            &format!(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS agent_memory_vec_items USING vec0(
+                embedding float[{}],
+                agent_id text
+            )",
+                vector_dimensions
+            ),
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Makes the knowledge memories of an agent match its `knowledge`. Called in the transaction
+    /// that stores the agent.
+    pub(crate) fn sync_agent_knowledge_memories(
+        conn: &Connection,
+        agent_id: &str,
+        knowledge: &[String],
+    ) -> Result<(), SqliteManagerError> {
+        let existing: Vec<(i64, String)> = {
+            let mut stmt =
+                conn.prepare("SELECT id, content FROM agent_memories WHERE agent_id = ?1 AND source = ?2")?;
+            let rows = stmt
+                .query_map(params![agent_id, AgentMemorySource::Knowledge.as_str()], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        let wanted: HashSet<&str> = knowledge
+            .iter()
+            .map(|entry| entry.trim())
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        let mut kept = HashSet::new();
+        for (id, content) in existing {
+            if wanted.contains(content.as_str()) && kept.insert(content) {
+                continue;
+            }
+            conn.execute("DELETE FROM agent_memory_vec_items WHERE rowid = ?1", params![id])?;
+            conn.execute("DELETE FROM agent_memories WHERE id = ?1", params![id])?;
+        }
+
+        let now = agent_memory_timestamp();
+        for entry in knowledge.iter().map(|entry| entry.trim()) {
+            if entry.is_empty() || kept.contains(entry) {
+                continue;
+            }
+            conn.execute(
+                "INSERT INTO agent_memories (memory_id, agent_id, content, source, job_id, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, NULL, ?5, ?5)",
+                params![
+                    new_agent_memory_id(),
+                    agent_id,
+                    entry,
+                    AgentMemorySource::Knowledge.as_str(),
+                    now
+                ],
+            )?;
+            kept.insert(entry.to_string());
+        }
+        Ok(())
+    }
+
+    /// Turns the knowledge of the agents stored before agent memories existed into memories. They
+    /// are embedded by `embed_pending_agent_memories` once the node starts.
+    pub(crate) fn sync_all_agent_knowledge_memories(conn: &Connection) -> Result<()> {
+        let agents: Vec<(String, String)> = {
+            let mut stmt = conn.prepare("SELECT agent_id, knowledge FROM zoo_agents")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<_>>>()?;
+            rows
+        };
+        for (agent_id, knowledge) in agents {
+            let knowledge: Vec<String> = serde_json::from_str(&knowledge).map_err(|e| {
+                rusqlite::Error::ToSqlConversionFailure(Box::new(SqliteManagerError::SerializationError(e.to_string())))
+            })?;
+            Self::sync_agent_knowledge_memories(conn, &agent_id.to_lowercase(), &knowledge).map_err(|e| match e {
+                SqliteManagerError::DatabaseError(e) => e,
+                e => rusqlite::Error::ToSqlConversionFailure(Box::new(e)),
+            })?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn remove_agent_memories(conn: &Connection, agent_id: &str) -> Result<(), SqliteManagerError> {
+        conn.execute(
+            "DELETE FROM agent_memory_vec_items WHERE rowid IN (SELECT id FROM agent_memories WHERE agent_id = ?1)",
+            params![agent_id],
+        )?;
+        conn.execute("DELETE FROM agent_memories WHERE agent_id = ?1", params![agent_id])?;
+        Ok(())
+    }
+
+    /// Stores something an agent wants to keep across its jobs. Remembering the same content
+    /// again returns the memory already stored.
+    pub fn add_agent_memory(
+        &self,
+        agent_id: &str,
+        content: &str,
+        job_id: Option<String>,
+    ) -> Result<AgentMemory, SqliteManagerError> {
+        let content = validate_agent_memory_content(content)?;
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+
+        let agent_exists: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM zoo_agents WHERE agent_id = ?1)",
+            params![agent_id],
+            |row| row.get(0),
+        )?;
+        if !agent_exists {
+            return Err(SqliteManagerError::DataNotFound);
+        }
+
+        let existing = tx
+            .query_row(
+                &format!(
+                    "SELECT {} FROM agent_memories WHERE agent_id = ?1 AND content = ?2",
+                    AGENT_MEMORY_COLUMNS
+                ),
+                params![agent_id, content],
+                agent_memory_from_row,
+            )
+            .optional()?;
+        if let Some((_, memory)) = existing {
+            return Ok(memory);
+        }
+
+        let now = agent_memory_timestamp();
+        let memory = AgentMemory {
+            memory_id: new_agent_memory_id(),
+            agent_id: agent_id.to_string(),
+            content,
+            source: AgentMemorySource::Remembered,
+            job_id,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        tx.execute(
+            "INSERT INTO agent_memories (memory_id, agent_id, content, source, job_id, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                memory.memory_id,
+                memory.agent_id,
+                memory.content,
+                memory.source.as_str(),
+                memory.job_id,
+                memory.created_at,
+                memory.updated_at
+            ],
+        )?;
+        tx.commit()?;
+        Ok(memory)
+    }
+
+    /// The memories of an agent, oldest first.
+    pub fn list_agent_memories(&self, agent_id: &str) -> Result<Vec<AgentMemory>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM agent_memories WHERE agent_id = ?1 ORDER BY id",
+            AGENT_MEMORY_COLUMNS
+        ))?;
+        let memories = stmt
+            .query_map(params![agent_id], |row| {
+                agent_memory_from_row(row).map(|(_, memory)| memory)
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(memories)
+    }
+
+    fn get_agent_memory_row(conn: &Connection, memory_id: &str) -> Result<(i64, AgentMemory), SqliteManagerError> {
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM agent_memories WHERE memory_id = ?1",
+                AGENT_MEMORY_COLUMNS
+            ),
+            params![memory_id],
+            agent_memory_from_row,
+        )
+        .optional()?
+        .ok_or(SqliteManagerError::DataNotFound)
+    }
+
+    /// Replaces the entries of `knowledge` equal to `old_content` in the stored agent. `new_content`
+    /// None removes them.
+    fn replace_agent_knowledge_entry(
+        conn: &Connection,
+        agent_id: &str,
+        old_content: &str,
+        new_content: Option<&str>,
+    ) -> Result<(), SqliteManagerError> {
+        let knowledge: Option<String> = conn
+            .query_row(
+                "SELECT knowledge FROM zoo_agents WHERE agent_id = ?1",
+                params![agent_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(knowledge) = knowledge else {
+            return Ok(());
+        };
+        let knowledge: Vec<String> =
+            serde_json::from_str(&knowledge).map_err(|e| SqliteManagerError::SerializationError(e.to_string()))?;
+        let knowledge: Vec<String> = knowledge
+            .into_iter()
+            .filter_map(|entry| match (entry.trim() == old_content, new_content) {
+                (true, Some(new_content)) => Some(new_content.to_string()),
+                (true, None) => None,
+                (false, _) => Some(entry),
+            })
+            .collect();
+        let knowledge =
+            serde_json::to_string(&knowledge).map_err(|e| SqliteManagerError::SerializationError(e.to_string()))?;
+        conn.execute(
+            "UPDATE zoo_agents SET knowledge = ?1 WHERE agent_id = ?2",
+            params![knowledge, agent_id],
+        )?;
+        Ok(())
+    }
+
+    /// Edits a memory. Editing a knowledge memory edits the knowledge of the agent too.
+    pub fn update_agent_memory(&self, memory_id: &str, content: &str) -> Result<AgentMemory, SqliteManagerError> {
+        let content = validate_agent_memory_content(content)?;
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+
+        let (id, mut memory) = Self::get_agent_memory_row(&tx, memory_id)?;
+        if memory.content == content {
+            return Ok(memory);
+        }
+        let duplicate: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM agent_memories WHERE agent_id = ?1 AND content = ?2)",
+            params![memory.agent_id, content],
+            |row| row.get(0),
+        )?;
+        if duplicate {
+            return Err(SqliteManagerError::ValidationError(
+                "The agent already has a memory with this content".to_string(),
+            ));
+        }
+        if memory.source == AgentMemorySource::Knowledge {
+            Self::replace_agent_knowledge_entry(&tx, &memory.agent_id, &memory.content, Some(&content))?;
+        }
+
+        memory.content = content;
+        memory.updated_at = agent_memory_timestamp();
+        tx.execute(
+            "UPDATE agent_memories SET content = ?1, updated_at = ?2 WHERE id = ?3",
+            params![memory.content, memory.updated_at, id],
+        )?;
+        // Embedded again with its new content by `embed_agent_memories`
+        tx.execute("DELETE FROM agent_memory_vec_items WHERE rowid = ?1", params![id])?;
+        tx.commit()?;
+        Ok(memory)
+    }
+
+    /// Deletes a memory. Deleting a knowledge memory removes it from the knowledge of the agent too.
+    pub fn remove_agent_memory(&self, memory_id: &str) -> Result<(), SqliteManagerError> {
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+
+        let (id, memory) = Self::get_agent_memory_row(&tx, memory_id)?;
+        if memory.source == AgentMemorySource::Knowledge {
+            Self::replace_agent_knowledge_entry(&tx, &memory.agent_id, &memory.content, None)?;
+        }
+        tx.execute("DELETE FROM agent_memory_vec_items WHERE rowid = ?1", params![id])?;
+        tx.execute("DELETE FROM agent_memories WHERE id = ?1", params![id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Embeds the memories that have no vector yet: new, edited, or dropped with their table
+    /// when the embedding model changed. `agent_id` None embeds the memories of every agent.
+    pub async fn embed_pending_agent_memories(&self, agent_id: Option<&str>) -> Result<(), SqliteManagerError> {
+        let pending: Vec<(i64, String, String)> = {
+            let conn = self.get_connection()?;
+            let mut stmt = conn.prepare(
+                "SELECT id, agent_id, content FROM agent_memories
+                 WHERE (?1 IS NULL OR agent_id = ?1)
+                 AND id NOT IN (SELECT rowid FROM agent_memory_vec_items)",
+            )?;
+            let rows = stmt
+                .query_map(params![agent_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        for (id, agent_id, content) in pending {
+            let embedding = self.generate_embeddings(&content).await?;
+            let mut conn = self.get_connection()?;
+            let tx = conn.transaction()?;
+            // The memory may have been edited or removed while it was being embedded
+            let unchanged: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM agent_memories WHERE id = ?1 AND content = ?2)",
+                params![id, content],
+                |row| row.get(0),
+            )?;
+            if unchanged {
+                tx.execute("DELETE FROM agent_memory_vec_items WHERE rowid = ?1", params![id])?;
+                tx.execute(
+                    "INSERT INTO agent_memory_vec_items (rowid, embedding, agent_id) VALUES (?1, ?2, ?3)",
+                    params![id, cast_slice(&embedding), agent_id],
+                )?;
+            }
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Embeds the memories of an agent written since the last call. Called after the memories or
+    /// the knowledge of the agent change. A memory that can't be embedded now is left for the next
+    /// call or the re-embedding migration, the memory itself is already stored.
+    pub async fn embed_agent_memories(&self, agent_id: &str) {
+        if let Err(e) = self.embed_pending_agent_memories(Some(agent_id)).await {
+            log::warn!("Failed to embed the memories of agent {}: {}", agent_id, e);
+        }
+    }
+
+    /// Embeds every agent memory again with the current model. Used by the re-embedding migration.
+    pub(crate) async fn reembed_agent_memories(&self) -> Result<(), SqliteManagerError> {
+        {
+            let conn = self.get_connection()?;
+            conn.execute("DELETE FROM agent_memory_vec_items", [])?;
+        }
+        self.embed_pending_agent_memories(None).await
+    }
+
+    /// The memories of an agent closest in meaning to `query`, closest first. When the query
+    /// can't be embedded, the newest ones are returned instead so the agent still gets its memory.
+    pub async fn search_agent_memories(
+        &self,
+        agent_id: &str,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<AgentMemory>, SqliteManagerError> {
+        let has_memories: bool = self.get_connection()?.query_row(
+            "SELECT EXISTS(SELECT 1 FROM agent_memories WHERE agent_id = ?1)",
+            params![agent_id],
+            |row| row.get(0),
+        )?;
+        // Most agents have no memories, don't embed the query for nothing
+        if limit == 0 || !has_memories {
+            return Ok(Vec::new());
+        }
+        let embedding = match self.generate_embeddings(query).await {
+            Ok(embedding) => embedding,
+            Err(e) => {
+                log::warn!(
+                    "Failed to embed the search of the memories of agent {}: {}",
+                    agent_id,
+                    e
+                );
+                let mut memories = self.list_agent_memories(agent_id)?;
+                memories.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+                memories.truncate(limit);
+                return Ok(memories);
+            }
+        };
+
+        let vector_json =
+            serde_json::to_string(&embedding).map_err(|e| SqliteManagerError::SerializationError(e.to_string()))?;
+        let conn = self.get_connection()?;
+        let nearest: Vec<i64> = {
+            let mut stmt = conn.prepare(
+                "SELECT rowid FROM agent_memory_vec_items
+                 WHERE embedding MATCH json(?1)
+                 AND agent_id = ?2
+                 ORDER BY distance
+                 LIMIT ?3",
+            )?;
+            let rows = stmt
+                .query_map(params![vector_json, agent_id, limit as i64], |row| row.get(0))?
+                .collect::<Result<Vec<i64>, _>>()?;
+            rows
+        };
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM agent_memories WHERE id = ?1",
+            AGENT_MEMORY_COLUMNS
+        ))?;
+        let mut memories = Vec::new();
+        for id in nearest {
+            if let Some((_, memory)) = stmt.query_row(params![id], agent_memory_from_row).optional()? {
+                memories.push(memory);
+            }
+        }
+        Ok(memories)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+    use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+    use zoo_message_primitives::schemas::{llm_providers::agent::Agent, zoo_name::ZooName};
+
+    fn setup_test_db() -> SqliteManager {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = temp_file.path().to_path_buf();
+        let api_url = String::new();
+        let model_type =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM);
+
+        SqliteManager::new(db_path, api_url, model_type).unwrap()
+    }
+
+    fn test_agent(knowledge: Vec<String>) -> Agent {
+        Agent {
+            name: "test_agent".to_string(),
+            agent_id: "test_agent".to_string(),
+            llm_provider_id: "test_llm_provider".to_string(),
+            ui_description: String::new(),
+            knowledge,
+            storage_path: String::new(),
+            tools: Default::default(),
+            debug_mode: false,
+            config: None,
+            full_identity_name: ZooName::new("@@test_user.zoo/main/agent/test_agent".to_string()).unwrap(),
+            scope: Default::default(),
+            cron_tasks: None,
+            tools_config_override: None,
+            edited: false,
+        }
+    }
+
+    fn contents(memories: &[AgentMemory]) -> Vec<(&str, AgentMemorySource)> {
+        memories.iter().map(|m| (m.content.as_str(), m.source)).collect()
+    }
+
+    #[test]
+    fn test_agent_memories_follow_knowledge() {
+        let db = setup_test_db();
+        let profile = ZooName::new("@@test_user.zoo/main".to_string()).unwrap();
+        let mut agent = test_agent(vec![
+            "The user lives in Lisbon".to_string(),
+            "Answer in English".to_string(),
+        ]);
+        db.add_agent(agent.clone(), &profile).unwrap();
+
+        let remembered = db
+            .add_agent_memory(
+                "test_agent",
+                " The user prefers short answers ",
+                Some("job1".to_string()),
+            )
+            .unwrap();
+        assert_eq!(remembered.content, "The user prefers short answers");
+        // Remembering the same thing twice keeps one memory
+        let again = db
+            .add_agent_memory("test_agent", "The user prefers short answers", None)
+            .unwrap();
+        assert_eq!(again.memory_id, remembered.memory_id);
+        assert!(matches!(
+            db.add_agent_memory("unknown_agent", "Anything", None),
+            Err(SqliteManagerError::DataNotFound)
+        ));
+
+        // Editing the agent only changes the memories of the knowledge that changed
+        agent.knowledge = vec!["Answer in English".to_string(), "The user works at night".to_string()];
+        db.update_agent(agent.clone()).unwrap();
+        let memories = db.list_agent_memories("test_agent").unwrap();
+        assert_eq!(
+            contents(&memories),
+            vec![
+                ("Answer in English", AgentMemorySource::Knowledge),
+                ("The user prefers short answers", AgentMemorySource::Remembered),
+                ("The user works at night", AgentMemorySource::Knowledge),
+            ]
+        );
+
+        // Editing or removing a knowledge memory edits the agent knowledge
+        let english = memories[0].memory_id.clone();
+        db.update_agent_memory(&english, "Answer in Portuguese").unwrap();
+        db.remove_agent_memory(&memories[2].memory_id).unwrap();
+        assert_eq!(
+            db.get_agent("test_agent").unwrap().unwrap().knowledge,
+            vec!["Answer in Portuguese".to_string()]
+        );
+        assert!(matches!(
+            db.update_agent_memory(&english, "The user prefers short answers"),
+            Err(SqliteManagerError::ValidationError(_))
+        ));
+
+        db.remove_agent("test_agent").unwrap();
+        assert!(db.list_agent_memories("test_agent").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sync_all_agent_knowledge_memories() {
+        let db = setup_test_db();
+        let profile = ZooName::new("@@test_user.zoo/main".to_string()).unwrap();
+        db.add_agent(test_agent(vec!["Answer in English".to_string()]), &profile)
+            .unwrap();
+
+        // An agent stored before agent memories existed has none
+        let conn = db.get_connection().unwrap();
+        conn.execute("DELETE FROM agent_memories", []).unwrap();
+        SqliteManager::sync_all_agent_knowledge_memories(&conn).unwrap();
+        drop(conn);
+
+        let memories = db.list_agent_memories("test_agent").unwrap();
+        assert_eq!(
+            contents(&memories),
+            vec![("Answer in English", AgentMemorySource::Knowledge)]
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{errors::SqliteManagerError, SqliteManager};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rand::RngCore;
+use rusqlite::{params, OptionalExtension, Result};
+use zoo_message_primitives::schemas::api_keys::{ApiKey, ApiKeyScope, CreateApiKeyRequest, CreatedApiKey};
+
+const API_KEY_COLUMNS: &str =
+    "id, label, key_prefix, scopes, created_at, expires_at, revoked_at, last_used_at, last_used_route";
+
+/// How many characters of a key are kept in clear to recognize it.
+const API_KEY_VISIBLE_PREFIX_LEN: usize = 10;
+
+fn api_key_timestamp(datetime: DateTime<Utc>) -> String {
+    datetime.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl SqliteManager {
+    pub fn initialize_api_keys_table(conn: &rusqlite::Connection) -> Result<()> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS api_keys (
+                id TEXT PRIMARY KEY,
+                label TEXT NOT NULL,
+                key_hash TEXT NOT NULL UNIQUE,
+                key_prefix TEXT NOT NULL,
+                scopes TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT,
+                revoked_at TEXT,
+                last_used_at TEXT,
+                last_used_route TEXT
+            );",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Creates a new scoped API key. The returned key is not stored anywhere, only its hash.
+    pub fn create_api_key(&self, request: &CreateApiKeyRequest) -> Result<CreatedApiKey, SqliteManagerError> {
+        if request.label.trim().is_empty() {
+            return Err(SqliteManagerError::ValidationError(
+                "The API key needs a label".to_string(),
+            ));
+        }
+        if request.scopes.is_empty() {
+            return Err(SqliteManagerError::ValidationError(
+                "The API key needs at least one scope".to_string(),
+            ));
+        }
+        let now = Utc::now();
+        let expires_at = match &request.expires_at {
+            Some(expires_at) => {
+                let expires_at = DateTime::parse_from_rfc3339(expires_at)
+                    .map_err(|e| SqliteManagerError::DateTimeParseError(format!("{}: {}", expires_at, e)))?
+                    .with_timezone(&Utc);
+                if expires_at <= now {
+                    return Err(SqliteManagerError::ValidationError(
+                        "The expiry of the API key is in the past".to_string(),
+                    ));
+                }
+                Some(api_key_timestamp(expires_at))
+            }
+            None => None,
+        };
+
+        let mut scopes = request.scopes.clone();
+        scopes.sort();
+        scopes.dedup();
+        let (key, key_hash) = ApiKey::generate_key();
+        let mut id_bytes = [0u8; 8];
+        rand::rngs::OsRng.fill_bytes(&mut id_bytes);
+        let api_key = ApiKey {
+            id: hex::encode(id_bytes),
+            label: request.label.trim().to_string(),
+            key_prefix: key.chars().take(API_KEY_VISIBLE_PREFIX_LEN).collect(),
+            scopes,
+            created_at: api_key_timestamp(now),
+            expires_at,
+            revoked_at: None,
+            last_used_at: None,
+            last_used_route: None,
+        };
+
+        let conn = self.get_connection()?;
+        conn.execute(
+            "INSERT INTO api_keys (id, label, key_hash, key_prefix, scopes, created_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                api_key.id,
+                api_key.label,
+                key_hash,
+                api_key.key_prefix,
+                serde_json::to_string(&api_key.scopes)?,
+                api_key.created_at,
+                api_key.expires_at,
+            ],
+        )?;
+
+        Ok(CreatedApiKey { key, api_key })
+    }
+
+    /// Looks a key up by its value. Revoked and expired keys are returned too, callers check
+    /// `ApiKey::is_active`.
+    pub fn get_api_key_by_key(&self, key: &str) -> Result<Option<ApiKey>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let row = conn
+            .query_row(
+                &format!("SELECT {} FROM api_keys WHERE key_hash = ?1", API_KEY_COLUMNS),
+                params![ApiKey::hash_key(key)],
+                read_api_key_row,
+            )
+            .optional()?;
+        row.map(parse_api_key_row).transpose()
+    }
+
+    pub fn get_all_api_keys(&self) -> Result<Vec<ApiKey>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM api_keys ORDER BY created_at, id",
+            API_KEY_COLUMNS
+        ))?;
+        let rows = stmt.query_map([], read_api_key_row)?;
+
+        let mut api_keys = Vec::new();
+        for row in rows {
+            api_keys.push(parse_api_key_row(row?)?);
+        }
+        Ok(api_keys)
+    }
+
+    /// Revokes a key. Returns false if there's no such key or it was already revoked.
+    pub fn revoke_api_key(&self, id: &str) -> Result<bool, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let updated = conn.execute(
+            "UPDATE api_keys SET revoked_at = ?1 WHERE id = ?2 AND revoked_at IS NULL",
+            params![api_key_timestamp(Utc::now()), id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Records a call made with the key.
+    pub fn record_api_key_use(&self, id: &str, route: &str) -> Result<(), SqliteManagerError> {
+        let conn = self.get_connection()?;
+        conn.execute(
+            "UPDATE api_keys SET last_used_at = ?1, last_used_route = ?2 WHERE id = ?3",
+            params![api_key_timestamp(Utc::now()), route, id],
+        )?;
+        Ok(())
+    }
+}
+
+type ApiKeyRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
+fn read_api_key_row(row: &rusqlite::Row) -> Result<ApiKeyRow> {
+    Ok((
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+        row.get(6)?,
+        row.get(7)?,
+        row.get(8)?,
+    ))
+}
+
+fn parse_api_key_row(row: ApiKeyRow) -> Result<ApiKey, SqliteManagerError> {
+    let (id, label, key_prefix, scopes, created_at, expires_at, revoked_at, last_used_at, last_used_route) = row;
+    let scopes: Vec<ApiKeyScope> = serde_json::from_str(&scopes)?;
+    Ok(ApiKey {
+        id,
+        label,
+        key_prefix,
+        scopes,
+        created_at,
+        expires_at,
+        revoked_at,
+        last_used_at,
+        last_used_route,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::NamedTempFile;
+    use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+
+    fn setup_test_db() -> SqliteManager {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = PathBuf::from(temp_file.path());
+        let api_url = String::new();
+        let model_type =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM);
+
+        SqliteManager::new(db_path, api_url, model_type).unwrap()
+    }
+
+    fn create_request(expires_at: Option<String>) -> CreateApiKeyRequest {
+        CreateApiKeyRequest {
+            label: "ci".to_string(),
+            scopes: vec![ApiKeyScope::JobsRead, ApiKeyScope::ToolsExecute],
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn test_create_lookup_and_revoke_api_key() {
+        let db = setup_test_db();
+        let created = db.create_api_key(&create_request(None)).unwrap();
+        assert!(created.key.starts_with(&created.api_key.key_prefix));
+
+        // The key is only stored hashed
+        let conn = db.get_connection().unwrap();
+        let stored_hash: String = conn
+            .query_row("SELECT key_hash FROM api_keys", [], |row| row.get(0))
+            .unwrap();
+        assert_ne!(stored_hash, created.key);
+
+        let found = db.get_api_key_by_key(&created.key).unwrap().unwrap();
+        assert_eq!(found, created.api_key);
+        assert!(found.is_active(Utc::now()));
+        assert!(db.get_api_key_by_key("zk_unknown").unwrap().is_none());
+
+        db.record_api_key_use(&found.id, "create_job").unwrap();
+        let used = db.get_all_api_keys().unwrap().pop().unwrap();
+        assert!(used.last_used_at.is_some());
+        assert_eq!(used.last_used_route.as_deref(), Some("create_job"));
+
+        assert!(db.revoke_api_key(&found.id).unwrap());
+        assert!(!db.revoke_api_key(&found.id).unwrap());
+        let revoked = db.get_api_key_by_key(&created.key).unwrap().unwrap();
+        assert!(!revoked.is_active(Utc::now()));
+    }
+
+    #[test]
+    fn test_create_api_key_validation() {
+        let db = setup_test_db();
+        let past = api_key_timestamp(Utc::now() - chrono::Duration::hours(1));
+        assert!(matches!(
+            db.create_api_key(&create_request(Some(past))),
+            Err(SqliteManagerError::ValidationError(_))
+        ));
+        assert!(matches!(
+            db.create_api_key(&create_request(Some("tomorrow".to_string()))),
+            Err(SqliteManagerError::DateTimeParseError(_))
+        ));
+
+        let mut no_scopes = create_request(None);
+        no_scopes.scopes.clear();
+        assert!(db.create_api_key(&no_scopes).is_err());
+    }
+
+    #[test]
+    fn test_create_api_key_dedups_scopes() {
+        let db = setup_test_db();
+        let mut request = create_request(None);
+        request.scopes = vec![ApiKeyScope::ToolsExecute, ApiKeyScope::JobsRead, ApiKeyScope::ToolsExecute];
+        let created = db.create_api_key(&request).unwrap();
+        assert_eq!(
+            created.api_key.scopes,
+            vec![ApiKeyScope::JobsRead, ApiKeyScope::ToolsExecute]
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use zoo_message_primitives::schemas::node_backup::BackupSection;
+
+use crate::schema_migrations::{latest_migration_version, MIGRATIONS};
+use crate::{SqliteManager, SqliteManagerError};
+
+/// The tables that hold a backup section. Tables left out (invoices, usage, traces, caches) are
+/// tied to the node that made them and aren't restored.
+pub fn backup_section_tables(section: BackupSection) -> &'static [&'static str] {
+    match section {
+        BackupSection::Identity => &[
+            "local_node_keys",
+            "standard_identities",
+            "device_identities",
+            "inbox_profile_permissions",
+            "api_keys",
+        ],
+        BackupSection::Secrets => &["secrets_keys", "secrets", "oauth_tokens"],
+        BackupSection::LlmProviders => &["llm_providers"],
+        BackupSection::Agents => &["zoo_agents", "agent_memories", "agent_memory_vec_items"],
+        BackupSection::Tools => &[
+            "zoo_tools",
+            "zoo_tools_vec_items",
+            "zoo_tool_active_versions",
+            "tool_playground",
+            "tool_playground_code_history",
+            "tool_micropayments_requirements",
+            "mcp_servers",
+        ],
+        BackupSection::CronTasks => &["cron_tasks", "cron_task_executions"],
+        BackupSection::Prompts => &["zoo_prompts", "prompt_vec_items"],
+        BackupSection::Preferences => &["preferences", "zoo_settings", "regex_patterns"],
+        BackupSection::VectorFs => &["parsed_files", "chunks", "chunk_vec"],
+        BackupSection::Inbox => &[
+            "inboxes",
+            "inbox_messages",
+            "inbox_message_vec_items",
+            "jobs",
+            "forked_jobs",
+            "job_conversation_summaries",
+        ],
+    }
+}
+
+/// Vectors of the backup sections. They are only restored into a node with the same embedding
+/// model, otherwise they are embedded again.
+const VECTOR_TABLES: &[&str] = &[
+    "agent_memory_vec_items",
+    "zoo_tools_vec_items",
+    "prompt_vec_items",
+    "chunk_vec",
+    "inbox_message_vec_items",
+];
+
+fn table_exists(conn: &Connection, schema: &str, table: &str) -> rusqlite::Result<bool> {
+    let count: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM {}.sqlite_master WHERE name = ?1", schema),
+        params![table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn table_columns(conn: &Connection, schema: &str, table: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1, ?2)")?;
+    let columns = stmt
+        .query_map(params![table, schema], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(columns)
+}
+
+impl SqliteManager {
+    /// The file of the main database.
+    pub fn database_path(&self) -> Result<PathBuf, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let file: String = conn.query_row("SELECT file FROM pragma_database_list WHERE name = 'main'", [], |row| {
+            row.get(0)
+        })?;
+        Ok(PathBuf::from(file))
+    }
+
+    /// Copies the database to `path`, consistent even while the node keeps writing. The rows of
+    /// `excluded` sections are removed from the copy. Returns the schema version of the copy.
+    pub fn snapshot_database(&self, path: &Path, excluded: &[BackupSection]) -> Result<u32, SqliteManagerError> {
+        {
+            let conn = self.get_connection()?;
+            conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])?;
+        }
+
+        if !excluded.is_empty() {
+            let snapshot = Connection::open(path)?;
+            for section in excluded {
+                for table in backup_section_tables(*section) {
+                    if table_exists(&snapshot, "main", table)? {
+                        snapshot.execute(&format!("DELETE FROM {}", table), [])?;
+                    }
+                }
+            }
+            snapshot.execute("VACUUM", [])?;
+        }
+        Ok(latest_migration_version(MIGRATIONS))
+    }
+
+    /// Replaces the rows of `sections` with the ones in the database snapshot at `snapshot`, in a
+    /// single transaction. Columns missing from an older snapshot get their default value, a
+    /// snapshot from a newer schema is refused. Vectors made with another embedding model than the
+    /// current one are left out and an embedding migration is scheduled to rebuild them. Returns
+    /// the rows restored per table.
+    pub fn restore_from_snapshot(
+        &self,
+        snapshot: &Path,
+        sections: &[BackupSection],
+    ) -> Result<BTreeMap<String, usize>, SqliteManagerError> {
+        let snapshot_model: Option<String> = {
+            let snapshot_conn = Connection::open_with_flags(snapshot, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            Self::check_schema_version(&snapshot_conn, MIGRATIONS)?;
+            snapshot_conn
+                .query_row("SELECT model_type FROM embedding_model_type LIMIT 1", [], |row| {
+                    row.get(0)
+                })
+                .optional()?
+        };
+        let current_model = self.get_default_embedding_model()?.to_string();
+        let skip_vectors = snapshot_model.as_deref() != Some(current_model.as_str());
+
+        let conn = self.get_connection()?;
+        conn.execute("ATTACH DATABASE ?1 AS backup", params![snapshot.to_string_lossy()])?;
+        let result = Self::copy_sections_from_backup(&conn, sections, skip_vectors);
+        let _ = conn.execute("DETACH DATABASE backup", []);
+        let restored = result?;
+
+        let restores_vectors = sections.iter().any(|section| {
+            backup_section_tables(*section)
+                .iter()
+                .any(|table| VECTOR_TABLES.contains(table))
+        });
+        if skip_vectors && restores_vectors {
+            Self::schedule_embedding_migration(&conn, snapshot_model.as_deref(), &current_model)?;
+        }
+
+        if sections.contains(&BackupSection::Tools) {
+            self.sync_tools_fts_table()?;
+        }
+        if sections.contains(&BackupSection::Prompts) {
+            self.sync_prompts_fts_table()?;
+        }
+        if sections.contains(&BackupSection::Inbox) {
+            let conn = self.get_connection()?;
+            let tx = conn.unchecked_transaction()?;
+            tx.execute("DELETE FROM inbox_messages_fts", [])?;
+            Self::index_existing_inbox_messages(&tx)?;
+            tx.commit()?;
+        }
+        Ok(restored)
+    }
+
+    fn copy_sections_from_backup(
+        conn: &Connection,
+        sections: &[BackupSection],
+        skip_vectors: bool,
+    ) -> Result<BTreeMap<String, usize>, SqliteManagerError> {
+        let tx = conn.unchecked_transaction()?;
+        // Tables are replaced one after the other, references are checked once all of them are
+        tx.execute_batch("PRAGMA defer_foreign_keys = ON;")?;
+
+        let mut restored = BTreeMap::new();
+        for section in sections {
+            for table in backup_section_tables(*section) {
+                if !table_exists(&tx, "main", table)? || !table_exists(&tx, "backup", table)? {
+                    continue;
+                }
+                let backup_columns = table_columns(&tx, "backup", table)?;
+                let columns = table_columns(&tx, "main", table)?
+                    .into_iter()
+                    .filter(|column| backup_columns.contains(column))
+                    .map(|column| format!("\"{}\"", column))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                tx.execute(&format!("DELETE FROM main.{}", table), [])?;
+                if skip_vectors && VECTOR_TABLES.contains(table) {
+                    continue;
+                }
+                let rows = tx.execute(
+                    &format!("INSERT INTO main.{table} (rowid, {columns}) SELECT rowid, {columns} FROM backup.{table}"),
+                    [],
+                )?;
+                restored.insert(table.to_string(), rows);
+            }
+        }
+        tx.commit()?;
+        Ok(restored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+
+    fn setup_test_db(dir: &TempDir, name: &str) -> SqliteManager {
+        let model_type =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM);
+        SqliteManager::new(dir.path().join(name), String::new(), model_type).unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_and_restore_sections() {
+        let dir = TempDir::new().unwrap();
+        let db = setup_test_db(&dir, "source");
+        db.set_preference("theme", &"dark", None).unwrap();
+        db.execute(
+            "INSERT INTO cron_tasks (name, cron, created_at, last_modified, action, paused)
+             VALUES ('hourly', '0 * * * *', '2024-01-01', '2024-01-01', '{}', 0)",
+            &[],
+        )
+        .unwrap();
+
+        // The cron tasks are left out of the snapshot
+        let snapshot_path = dir.path().join("snapshot.db");
+        db.snapshot_database(&snapshot_path, &[BackupSection::CronTasks])
+            .unwrap();
+
+        let target = setup_test_db(&dir, "target");
+        target.set_preference("theme", &"light", None).unwrap();
+        target.set_preference("only_here", &true, None).unwrap();
+
+        let restored = target
+            .restore_from_snapshot(&snapshot_path, &[BackupSection::Preferences, BackupSection::CronTasks])
+            .unwrap();
+        assert_eq!(restored.get("preferences"), Some(&1));
+        assert_eq!(restored.get("cron_tasks"), Some(&0));
+        assert_eq!(
+            target.get_preference::<String>("theme").unwrap(),
+            Some("dark".to_string())
+        );
+        assert_eq!(target.get_preference::<bool>("only_here").unwrap(), None);
+        assert!(target.database_path().unwrap().ends_with("target.db"));
+    }
+
+    #[test]
+    fn test_restore_refuses_newer_snapshot() {
+        let dir = TempDir::new().unwrap();
+        let db = setup_test_db(&dir, "source");
+        let snapshot_path = dir.path().join("snapshot.db");
+        db.snapshot_database(&snapshot_path, &[]).unwrap();
+
+        let snapshot = Connection::open(&snapshot_path).unwrap();
+        snapshot
+            .execute(
+                "INSERT INTO schema_migrations (version, name, checksum) VALUES (?1, 'future', '')",
+                params![latest_migration_version(MIGRATIONS) + 1],
+            )
+            .unwrap();
+        drop(snapshot);
+
+        assert!(matches!(
+            db.restore_from_snapshot(&snapshot_path, &[BackupSection::Preferences]),
+            Err(SqliteManagerError::SchemaTooNew { .. })
+        ));
+    }
+
+    #[test]
+    fn test_restore_skips_vectors_of_another_model() {
+        let dir = TempDir::new().unwrap();
+        let db = setup_test_db(&dir, "source");
+        let snapshot_path = dir.path().join("snapshot.db");
+        db.snapshot_database(&snapshot_path, &[]).unwrap();
+
+        // Same model: the vectors are restored
+        let restored = db
+            .restore_from_snapshot(&snapshot_path, &[BackupSection::Agents])
+            .unwrap();
+        assert!(restored.contains_key("agent_memory_vec_items"));
+        assert!(db.get_unfinished_embedding_migration().unwrap().is_none());
+
+        let snapshot = Connection::open(&snapshot_path).unwrap();
+        snapshot
+            .execute("UPDATE embedding_model_type SET model_type = 'other-model'", [])
+            .unwrap();
+        drop(snapshot);
+
+        let restored = db
+            .restore_from_snapshot(&snapshot_path, &[BackupSection::Agents])
+            .unwrap();
+        assert_eq!(restored.get("zoo_agents"), Some(&0));
+        assert!(!restored.contains_key("agent_memory_vec_items"));
+        let migration = db.get_unfinished_embedding_migration().unwrap().unwrap();
+        assert_eq!(migration.from_model.as_deref(), Some("other-model"));
+        assert_eq!(
+            migration.to_model,
+            db.get_default_embedding_model().unwrap().to_string()
+        );
+    }
+}
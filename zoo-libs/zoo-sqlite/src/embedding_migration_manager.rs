@@ -0,0 +1,547 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+
+use bytemuck::cast_slice;
+use rusqlite::{params, OptionalExtension, Result};
+
+use crate::{SqliteManager, SqliteManagerError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmbeddingMigrationStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl fmt::Display for EmbeddingMigrationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            EmbeddingMigrationStatus::Pending => "pending",
+            EmbeddingMigrationStatus::Running => "running",
+            EmbeddingMigrationStatus::Completed => "completed",
+            EmbeddingMigrationStatus::Failed => "failed",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+impl FromStr for EmbeddingMigrationStatus {
+    type Err = SqliteManagerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(EmbeddingMigrationStatus::Pending),
+            "running" => Ok(EmbeddingMigrationStatus::Running),
+            "completed" => Ok(EmbeddingMigrationStatus::Completed),
+            "failed" => Ok(EmbeddingMigrationStatus::Failed),
+            _ => Err(SqliteManagerError::InvalidData),
+        }
+    }
+}
+
+/// Re-embedding of the stored files, tools and prompts after the default embedding model changed.
+#[derive(Debug, Clone)]
+pub struct EmbeddingMigration {
+    pub id: i64,
+    pub from_model: Option<String>,
+    pub to_model: String,
+    pub status: EmbeddingMigrationStatus,
+    pub total_files: i64,
+    pub migrated_files: i64,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl SqliteManager {
+    pub fn initialize_embedding_migrations_table(conn: &rusqlite::Connection) -> Result<()> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS embedding_migrations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                from_model TEXT,
+                to_model TEXT NOT NULL,
+                status TEXT NOT NULL,
+                total_files INTEGER NOT NULL DEFAULT 0,
+                migrated_files INTEGER NOT NULL DEFAULT 0,
+                error TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Reads the vector size of a sqlite-vec table from its definition.
+    /// Returns `None` if the table doesn't exist.
+    pub fn vector_table_dimensions(conn: &rusqlite::Connection, table: &str) -> Result<Option<usize>> {
+        let sql: Option<String> = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                [table],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(sql.and_then(|sql| {
+            let start = sql.find("float[")? + "float[".len();
+            let end = start + sql[start..].find(']')?;
+            sql[start..end].trim().parse().ok()
+        }))
+    }
+
+    /// Drops and recreates the vector tables that don't match `vector_dimensions`.
+    /// The stored vectors can't be converted, so they are rebuilt by the re-embedding migration.
+    /// Returns true if any table was recreated.
+    pub(crate) fn ensure_vector_tables_dimensions(
+        conn: &rusqlite::Connection,
+        vector_dimensions: usize,
+    ) -> Result<bool> {
+        let mut recreated = false;
+
+        if Self::vector_table_dimensions(conn, "chunk_vec")? != Some(vector_dimensions) {
+            conn.execute("DROP TABLE IF EXISTS chunk_vec;", [])?;
+            Self::initialize_chunk_vec_table(conn, vector_dimensions)?;
+            recreated = true;
+        }
+        if Self::vector_table_dimensions(conn, "zoo_tools_vec_items")? != Some(vector_dimensions) {
+            conn.execute("DROP TABLE IF EXISTS zoo_tools_vec_items;", [])?;
+            Self::initialize_tools_vector_table(conn, vector_dimensions)?;
+            recreated = true;
+        }
+        if Self::vector_table_dimensions(conn, "prompt_vec_items")? != Some(vector_dimensions) {
+            conn.execute("DROP TABLE IF EXISTS prompt_vec_items;", [])?;
+            Self::initialize_prompt_vector_tables(conn, vector_dimensions)?;
+            recreated = true;
+        }
+        if Self::vector_table_dimensions(conn, "inbox_message_vec_items")? != Some(vector_dimensions) {
+            conn.execute("DROP TABLE IF EXISTS inbox_message_vec_items;", [])?;
+            Self::initialize_inbox_message_vec_table(conn, vector_dimensions)?;
+            recreated = true;
+        }
+        if Self::vector_table_dimensions(conn, "agent_memory_vec_items")? != Some(vector_dimensions) {
+            conn.execute("DROP TABLE IF EXISTS agent_memory_vec_items;", [])?;
+            Self::initialize_agent_memory_vec_table(conn, vector_dimensions)?;
+            recreated = true;
+        }
+        // Cached prompts aren't embedded again, their responses are only reused by exact matches
+        if Self::vector_table_dimensions(conn, "llm_response_cache_vec_items")? != Some(vector_dimensions) {
+            conn.execute("DROP TABLE IF EXISTS llm_response_cache_vec_items;", [])?;
+            Self::initialize_llm_response_cache_vec_table(conn, vector_dimensions)?;
+        }
+
+        Ok(recreated)
+    }
+
+    /// Schedules a re-embedding migration to `to_model`. Unfinished migrations are superseded.
+    pub(crate) fn schedule_embedding_migration(
+        conn: &rusqlite::Connection,
+        from_model: Option<&str>,
+        to_model: &str,
+    ) -> Result<()> {
+        conn.execute(
+            "UPDATE embedding_migrations
+             SET status = ?1, error = 'Superseded by a newer migration', updated_at = CURRENT_TIMESTAMP
+             WHERE status IN (?2, ?3)",
+            params![
+                EmbeddingMigrationStatus::Failed.to_string(),
+                EmbeddingMigrationStatus::Pending.to_string(),
+                EmbeddingMigrationStatus::Running.to_string()
+            ],
+        )?;
+        conn.execute(
+            "INSERT INTO embedding_migrations (from_model, to_model, status) VALUES (?1, ?2, ?3)",
+            params![from_model, to_model, EmbeddingMigrationStatus::Pending.to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_latest_embedding_migration(&self) -> Result<Option<EmbeddingMigration>, SqliteManagerError> {
+        self.query_embedding_migration("SELECT * FROM embedding_migrations ORDER BY id DESC LIMIT 1", [])
+    }
+
+    /// Returns the migration that still has to run. A `running` migration means the node
+    /// stopped halfway through, so it is picked up again.
+    pub fn get_unfinished_embedding_migration(&self) -> Result<Option<EmbeddingMigration>, SqliteManagerError> {
+        self.query_embedding_migration(
+            "SELECT * FROM embedding_migrations WHERE status IN (?1, ?2) ORDER BY id DESC LIMIT 1",
+            params![
+                EmbeddingMigrationStatus::Pending.to_string(),
+                EmbeddingMigrationStatus::Running.to_string()
+            ],
+        )
+    }
+
+    fn query_embedding_migration<P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+    ) -> Result<Option<EmbeddingMigration>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let migration = conn
+            .query_row(sql, params, |row| {
+                let status: String = row.get("status")?;
+                Ok(EmbeddingMigration {
+                    id: row.get("id")?,
+                    from_model: row.get("from_model")?,
+                    to_model: row.get("to_model")?,
+                    status: status.parse().map_err(|_| rusqlite::Error::InvalidQuery)?,
+                    total_files: row.get("total_files")?,
+                    migrated_files: row.get("migrated_files")?,
+                    error: row.get("error")?,
+                    created_at: row.get("created_at")?,
+                    updated_at: row.get("updated_at")?,
+                })
+            })
+            .optional()?;
+        Ok(migration)
+    }
+
+    fn update_embedding_migration(
+        &self,
+        id: i64,
+        status: EmbeddingMigrationStatus,
+        total_files: i64,
+        migrated_files: i64,
+        error: Option<String>,
+    ) -> Result<(), SqliteManagerError> {
+        let conn = self.get_connection()?;
+        conn.execute(
+            "UPDATE embedding_migrations
+             SET status = ?1, total_files = ?2, migrated_files = ?3, error = ?4, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?5",
+            params![status.to_string(), total_files, migrated_files, error, id],
+        )?;
+        Ok(())
+    }
+
+    /// Runs the pending re-embedding migration, if any. The current default model gives new vectors
+    /// to the chunks of every parsed file embedded with another model (`chunk_vec`), all the tools
+    /// (`zoo_tools_vec_items`), all the prompts (`prompt_vec_items`), the searchable messages if
+    /// message embeddings are enabled (`inbox_message_vec_items`) and all the agent memories
+    /// (`agent_memory_vec_items`). Cached LLM responses aren't embedded again: the semantic cache
+    /// (`llm_response_cache_vec_items`) only compares prompts of the same model. Files are migrated
+    /// one at a time, so an interrupted migration resumes where it stopped. Returns the final state
+    /// of the migration.
+    pub async fn run_pending_embedding_migration(&self) -> Result<Option<EmbeddingMigration>, SqliteManagerError> {
+        if self
+            .embedding_migration_running
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Ok(None);
+        }
+
+        let result = self.run_embedding_migration_inner().await;
+        self.embedding_migration_running.store(false, Ordering::SeqCst);
+        result
+    }
+
+    async fn run_embedding_migration_inner(&self) -> Result<Option<EmbeddingMigration>, SqliteManagerError> {
+        let migration = match self.get_unfinished_embedding_migration()? {
+            Some(migration) => migration,
+            None => return Ok(None),
+        };
+
+        let stale_file_ids: Vec<i64> = {
+            let conn = self.get_connection()?;
+            let mut stmt = conn.prepare(
+                "SELECT id FROM parsed_files WHERE embedding_model_used IS NULL OR embedding_model_used != ?1",
+            )?;
+            let ids = stmt
+                .query_map([&migration.to_model], |row| row.get(0))?
+                .collect::<Result<Vec<i64>, _>>()?;
+            ids
+        };
+        let total_files = migration.migrated_files + stale_file_ids.len() as i64;
+        let mut migrated_files = migration.migrated_files;
+        self.update_embedding_migration(
+            migration.id,
+            EmbeddingMigrationStatus::Running,
+            total_files,
+            migrated_files,
+            None,
+        )?;
+
+        let result = async {
+            for parsed_file_id in stale_file_ids {
+                // Stop if the default model changed again; the newer migration takes over
+                if self.get_default_embedding_model()?.to_string() != migration.to_model {
+                    return Ok(false);
+                }
+                self.reembed_parsed_file(parsed_file_id, &migration.to_model).await?;
+                migrated_files += 1;
+                self.update_embedding_migration(
+                    migration.id,
+                    EmbeddingMigrationStatus::Running,
+                    total_files,
+                    migrated_files,
+                    None,
+                )?;
+            }
+            self.reembed_tools().await?;
+            self.reembed_prompts().await?;
+            self.reembed_inbox_messages().await?;
+            self.reembed_agent_memories().await?;
+            Ok::<bool, SqliteManagerError>(true)
+        }
+        .await;
+
+        match result {
+            Ok(true) => self.update_embedding_migration(
+                migration.id,
+                EmbeddingMigrationStatus::Completed,
+                total_files,
+                migrated_files,
+                None,
+            )?,
+            Ok(false) => {}
+            Err(e) => {
+                self.update_embedding_migration(
+                    migration.id,
+                    EmbeddingMigrationStatus::Failed,
+                    total_files,
+                    migrated_files,
+                    Some(e.to_string()),
+                )?;
+                return Err(e);
+            }
+        }
+
+        self.query_embedding_migration("SELECT * FROM embedding_migrations WHERE id = ?1", [migration.id])
+    }
+
+    async fn reembed_parsed_file(&self, parsed_file_id: i64, model: &str) -> Result<(), SqliteManagerError> {
+        let chunks = self.get_chunks_for_parsed_file(parsed_file_id)?;
+        let mut embeddings = Vec::with_capacity(chunks.len());
+        for chunk in &chunks {
+            embeddings.push(self.generate_embeddings(&chunk.content).await?);
+        }
+
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM chunk_vec WHERE parsed_file_id = ?1", [parsed_file_id])?;
+        for (chunk, embedding) in chunks.iter().zip(embeddings.iter()) {
+            tx.execute(
+                "INSERT INTO chunk_vec (embedding, parsed_file_id, chunk_id) VALUES (?1, ?2, ?3)",
+                params![cast_slice(embedding), parsed_file_id, chunk.chunk_id],
+            )?;
+        }
+        tx.execute(
+            "UPDATE parsed_files SET embedding_model_used = ?1 WHERE id = ?2",
+            params![model, parsed_file_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    // The rows of zoo_tools_vec_items share their rowid with zoo_tools
+    async fn reembed_tools(&self) -> Result<(), SqliteManagerError> {
+        let tools: Vec<(i64, String, String, i32, i32)> = {
+            let conn = self.get_connection()?;
+            let mut stmt =
+                conn.prepare("SELECT rowid, tool_key, embedding_seo, is_enabled, is_network FROM zoo_tools")?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        for (rowid, tool_key, embedding_seo, is_enabled, is_network) in tools {
+            let embedding = self.generate_embeddings(&embedding_seo).await?;
+            let mut conn = self.get_connection()?;
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM zoo_tools_vec_items WHERE rowid = ?1", [rowid])?;
+            tx.execute(
+                "INSERT INTO zoo_tools_vec_items (rowid, embedding, is_enabled, is_network, tool_key)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    rowid,
+                    cast_slice(&embedding),
+                    is_enabled,
+                    is_network,
+                    tool_key.to_lowercase()
+                ],
+            )?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    // The rows of prompt_vec_items share their rowid with zoo_prompts
+    async fn reembed_prompts(&self) -> Result<(), SqliteManagerError> {
+        let prompts: Vec<(i64, String, i32)> = {
+            let conn = self.get_connection()?;
+            let mut stmt = conn.prepare("SELECT rowid, prompt, is_enabled FROM zoo_prompts")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        for (rowid, prompt, is_enabled) in prompts {
+            let embedding = self.generate_embeddings(&prompt).await?;
+            let mut conn = self.get_connection()?;
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM prompt_vec_items WHERE rowid = ?1", [rowid])?;
+            tx.execute(
+                "INSERT INTO prompt_vec_items (rowid, prompt_id, embedding, is_enabled) VALUES (?1, ?2, ?3, ?4)",
+                params![rowid, rowid, cast_slice(&embedding), is_enabled],
+            )?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::NamedTempFile;
+    use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+    use zoo_message_primitives::schemas::zoo_fs::{ParsedFile, ZooFileChunk};
+
+    fn setup_test_db() -> SqliteManager {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = PathBuf::from(temp_file.path());
+        let api_url = String::new();
+        let model_type =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM);
+
+        SqliteManager::new(db_path, api_url, model_type).unwrap()
+    }
+
+    fn add_file_with_chunk(db: &SqliteManager, relative_path: &str, model: Option<String>) -> i64 {
+        let parsed_file = ParsedFile {
+            id: None,
+            relative_path: relative_path.to_string(),
+            original_extension: None,
+            description: None,
+            source: None,
+            embedding_model_used: model,
+            keywords: None,
+            distribution_info: None,
+            created_time: None,
+            tags: None,
+            total_tokens: None,
+            total_characters: None,
+        };
+        db.add_parsed_file(&parsed_file).unwrap();
+        let parsed_file_id = db
+            .get_parsed_file_by_rel_path(relative_path)
+            .unwrap()
+            .unwrap()
+            .id
+            .unwrap();
+        let chunk = ZooFileChunk {
+            chunk_id: None,
+            parsed_file_id,
+            position: 1,
+            content: format!("Content of {}", relative_path),
+        };
+        db.create_chunk_with_embedding(&chunk, Some(&SqliteManager::generate_vector_for_testing(0.5)))
+            .unwrap();
+        parsed_file_id
+    }
+
+    #[test]
+    fn test_vector_tables_are_sized_from_the_model() {
+        let db = setup_test_db();
+        let conn = db.get_connection().unwrap();
+        for table in [
+            "chunk_vec",
+            "zoo_tools_vec_items",
+            "prompt_vec_items",
+            "inbox_message_vec_items",
+            "agent_memory_vec_items",
+            "llm_response_cache_vec_items",
+        ] {
+            assert_eq!(SqliteManager::vector_table_dimensions(&conn, table).unwrap(), Some(384));
+        }
+        assert_eq!(
+            SqliteManager::vector_table_dimensions(&conn, "missing_table").unwrap(),
+            None
+        );
+        assert!(db.get_latest_embedding_migration().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_changing_model_resizes_tables_and_schedules_migration() {
+        let db = setup_test_db();
+        let snowflake =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM);
+        let jina =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::JinaEmbeddingsV2BaseEs);
+        add_file_with_chunk(&db, "file1.txt", Some(snowflake.to_string()));
+
+        // Same model: nothing to do
+        db.update_default_embedding_model(snowflake.clone()).unwrap();
+        assert!(db.get_latest_embedding_migration().unwrap().is_none());
+
+        db.update_default_embedding_model(jina.clone()).unwrap();
+        {
+            let conn = db.get_connection().unwrap();
+            for table in [
+                "chunk_vec",
+                "zoo_tools_vec_items",
+                "prompt_vec_items",
+                "inbox_message_vec_items",
+                "agent_memory_vec_items",
+                "llm_response_cache_vec_items",
+            ] {
+                assert_eq!(SqliteManager::vector_table_dimensions(&conn, table).unwrap(), Some(768));
+            }
+        }
+
+        let migration = db.get_unfinished_embedding_migration().unwrap().unwrap();
+        assert_eq!(migration.status, EmbeddingMigrationStatus::Pending);
+        assert_eq!(migration.from_model, Some(snowflake.to_string()));
+        assert_eq!(migration.to_model, jina.to_string());
+
+        // Switching again supersedes the pending migration
+        db.update_default_embedding_model(snowflake.clone()).unwrap();
+        let latest = db.get_latest_embedding_migration().unwrap().unwrap();
+        assert_eq!(latest.to_model, snowflake.to_string());
+        assert_eq!(latest.status, EmbeddingMigrationStatus::Pending);
+        assert_eq!(db.get_unfinished_embedding_migration().unwrap().unwrap().id, latest.id);
+    }
+
+    #[test]
+    fn test_unknown_model_dimensions_are_rejected() {
+        let db = setup_test_db();
+        let unknown = EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::Other(
+            "unknown-model".to_string(),
+        ));
+        let result = db.update_default_embedding_model(unknown);
+        assert!(matches!(result, Err(SqliteManagerError::UnsupportedEmbeddingModel(_))));
+    }
+
+    #[test]
+    fn test_search_skips_files_embedded_with_another_model() {
+        let db = setup_test_db();
+        let current_model = db.get_default_embedding_model().unwrap().to_string();
+        let current_file = add_file_with_chunk(&db, "current.txt", Some(current_model));
+        let stale_file = add_file_with_chunk(&db, "stale.txt", Some("all-minilm:l6-v2".to_string()));
+
+        let results = db
+            .search_chunks(
+                &[current_file, stale_file],
+                SqliteManager::generate_vector_for_testing(0.5),
+                10,
+            )
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.parsed_file_id, current_file);
+
+        let wrong_size = db.search_chunks(&[current_file], vec![0.5; 768], 10);
+        assert!(matches!(
+            wrong_size,
+            Err(SqliteManagerError::UnsupportedEmbeddingLength(768))
+        ));
+    }
+}
@@ -68,6 +68,8 @@ pub enum SqliteManagerError {
     DirectoryNotFound,
     #[error("Unsupported embedding length: {0}")]
     UnsupportedEmbeddingLength(usize),
+    #[error("Unsupported embedding model (unknown vector dimensions): {0}")]
+    UnsupportedEmbeddingModel(String),
     #[error("Deserialization error")]
     DeserializationError,
     #[error("Chrono parse error: {0}")]
@@ -90,6 +92,20 @@ pub enum SqliteManagerError {
     ValidationError(String),
     #[error("Tool type mismatch")]
     ToolTypeMismatch,
+    #[error("The secrets vault is locked")]
+    SecretsVaultLocked,
+    #[error("Secrets vault error: {0}")]
+    SecretsVaultError(String),
+    #[error("Secret not found: {0}")]
+    SecretNotFound(String),
+    #[error("The database schema is at version {database}, newer than the latest version this binary supports ({supported})")]
+    SchemaTooNew { database: u32, supported: u32 },
+    #[error("Migration {version} ({name}) was changed after it was applied")]
+    MigrationChecksumMismatch { version: u32, name: String },
+    #[error("Migration {version} ({name}) failed and was rolled back: {message}")]
+    MigrationFailed { version: u32, name: String, message: String },
+    #[error("Migration {0} can't be rolled back")]
+    IrreversibleMigration(u32),
     // Add other error variants as needed
 }
 
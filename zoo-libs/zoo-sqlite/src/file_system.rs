@@ -1,8 +1,30 @@
 use crate::{SqliteManager, SqliteManagerError};
 use rusqlite::params;
+use std::collections::{HashMap, HashSet};
 use zoo_message_primitives::{
     schemas::zoo_fs::{ParsedFile, ZooFileChunk}, zoo_utils::zoo_path::ZooPath
 };
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
+
+/// Constant of the reciprocal-rank fusion formula `1 / (k + rank)`, 60 as in the original paper.
+const RRF_K: f64 = 60.0;
+/// How many candidates each ranking contributes to the hybrid search, as a multiple of the limit.
+const HYBRID_SEARCH_CANDIDATES_FACTOR: usize = 3;
+
+/// Merges several rankings of ids (best first) into one using reciprocal-rank fusion.
+/// Ties are broken by id so the result is deterministic.
+pub fn reciprocal_rank_fusion(rankings: &[Vec<i64>], k: f64) -> Vec<(i64, f64)> {
+    let mut scores: HashMap<i64, f64> = HashMap::new();
+    for ranking in rankings {
+        for (rank, id) in ranking.iter().enumerate() {
+            *scores.entry(*id).or_insert(0.0) += 1.0 / (k + rank as f64 + 1.0);
+        }
+    }
+
+    let mut fused: Vec<(i64, f64)> = scores.into_iter().collect();
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    fused
+}
 
 impl SqliteManager {
     // TODO: This is a temporary workaround for Windows paths. We should handle this more robustly.
@@ -56,13 +78,60 @@ impl SqliteManager {
             [],
         )?;
 
-        // Create our new virtual table for chunk embeddings using sqlite-vec
+        Self::initialize_chunks_fts_table(conn)?;
+
+        Ok(())
+    }
+
+    /// Creates the FTS5 index over `chunks` used for keyword (BM25) search. It is an
+    /// external content table kept in sync with `chunks` by triggers.
+    fn initialize_chunks_fts_table(conn: &rusqlite::Connection) -> Result<(), rusqlite::Error> {
+        let fts_exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'chunks_fts')",
+            [],
+            |row| row.get(0),
+        )?;
+
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(chunk, content='chunks', content_rowid='id');
+
+            CREATE TRIGGER IF NOT EXISTS chunks_fts_insert AFTER INSERT ON chunks BEGIN
+                INSERT INTO chunks_fts(rowid, chunk) VALUES (new.id, new.chunk);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS chunks_fts_delete AFTER DELETE ON chunks BEGIN
+                INSERT INTO chunks_fts(chunks_fts, rowid, chunk) VALUES ('delete', old.id, old.chunk);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS chunks_fts_update AFTER UPDATE ON chunks BEGIN
+                INSERT INTO chunks_fts(chunks_fts, rowid, chunk) VALUES ('delete', old.id, old.chunk);
+                INSERT INTO chunks_fts(rowid, chunk) VALUES (new.id, new.chunk);
+            END;",
+        )?;
+
+        // Index the chunks that were stored before the FTS table existed
+        if !fts_exists {
+            conn.execute("INSERT INTO chunks_fts(chunks_fts) VALUES ('rebuild')", [])?;
+        }
+
+        Ok(())
+    }
+
+    /// Creates the `chunk_vec` virtual table for chunk embeddings using sqlite-vec,
+    /// sized for the default embedding model.
+    pub fn initialize_chunk_vec_table(
+        conn: &rusqlite::Connection,
+        vector_dimensions: usize,
+    ) -> Result<(), rusqlite::Error> {
         conn.execute(
-            "CREATE VIRTUAL TABLE IF NOT EXISTS chunk_vec USING vec0(
-                embedding float[384],
+            &format!(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS chunk_vec USING vec0(
+                embedding float[{}],
                 parsed_file_id INTEGER,
                 +chunk_id INTEGER  -- Normal column recognized as chunk_id
             );",
+                vector_dimensions
+            ),
             [],
         )?;
 
@@ -405,6 +474,19 @@ impl SqliteManager {
     ) -> Result<Vec<(ZooFileChunk, f64)>, SqliteManagerError> {
         let conn = self.get_connection()?;
 
+        // The query has to live in the same vector space as the stored chunks
+        if let Some(dimensions) = Self::vector_table_dimensions(&conn, "chunk_vec")? {
+            if query_embedding.len() != dimensions {
+                return Err(SqliteManagerError::UnsupportedEmbeddingLength(query_embedding.len()));
+            }
+        }
+
+        // Skip files embedded with another model; they are waiting for the re-embedding migration
+        let parsed_file_ids = self.filter_parsed_files_by_current_embedding_model(&conn, parsed_file_ids)?;
+        if parsed_file_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
         // Serialize the vector to a JSON array string
         let vector_json = serde_json::to_string(&query_embedding).map_err(|e| {
             eprintln!("Vector serialization error: {}", e);
@@ -457,6 +539,157 @@ impl SqliteManager {
         Ok(results)
     }
 
+    /// Keyword search over the chunks of the given files using the FTS5 index.
+    /// Returns the chunks ordered by BM25 relevance with their BM25 score (lower is better).
+    pub fn search_chunks_by_keywords(
+        &self,
+        parsed_file_ids: &[i64],
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<(ZooFileChunk, f64)>, SqliteManagerError> {
+        let fts_query = match Self::build_chunks_fts_query(query) {
+            Some(fts_query) => fts_query,
+            None => return Ok(Vec::new()),
+        };
+        if parsed_file_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let conn = self.get_connection()?;
+        let placeholders = parsed_file_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let sql = format!(
+            r#"
+            SELECT c.id, c.parsed_file_id, c.position, c.chunk, bm25(chunks_fts) AS score
+            FROM chunks_fts
+            JOIN chunks c ON c.id = chunks_fts.rowid
+            WHERE chunks_fts MATCH ?
+            AND c.parsed_file_id IN ({})
+            ORDER BY score
+            LIMIT ?
+        "#,
+            placeholders
+        );
+
+        let limit_binding = limit as i64;
+        let mut params: Vec<&dyn rusqlite::ToSql> = vec![&fts_query];
+        params.extend(parsed_file_ids.iter().map(|id| id as &dyn rusqlite::ToSql));
+        params.push(&limit_binding);
+
+        let mut stmt = conn.prepare(&sql)?;
+        let results = stmt
+            .query_map(params.as_slice(), |row| {
+                Ok((
+                    ZooFileChunk {
+                        chunk_id: Some(row.get(0)?),
+                        parsed_file_id: row.get(1)?,
+                        position: row.get(2)?,
+                        content: row.get(3)?,
+                    },
+                    row.get(4)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(results)
+    }
+
+    /// Hybrid search: runs the vector and the keyword searches and merges both rankings
+    /// with reciprocal-rank fusion. Returns the chunks with their fused score (higher is better).
+    pub fn search_chunks_hybrid(
+        &self,
+        parsed_file_ids: &[i64],
+        query: &str,
+        query_embedding: Vec<f32>,
+        limit: usize,
+    ) -> Result<Vec<(ZooFileChunk, f64)>, SqliteManagerError> {
+        let candidates = limit * HYBRID_SEARCH_CANDIDATES_FACTOR;
+        let vector_results = self.search_chunks(parsed_file_ids, query_embedding, candidates)?;
+        let keyword_results = self.search_chunks_by_keywords(parsed_file_ids, query, candidates)?;
+
+        let mut chunks_by_id = HashMap::new();
+        let mut rankings = Vec::new();
+        for results in [vector_results, keyword_results] {
+            let mut ranking = Vec::new();
+            for (chunk, _score) in results {
+                if let Some(chunk_id) = chunk.chunk_id {
+                    ranking.push(chunk_id);
+                    chunks_by_id.entry(chunk_id).or_insert(chunk);
+                }
+            }
+            rankings.push(ranking);
+        }
+
+        Ok(reciprocal_rank_fusion(&rankings, RRF_K)
+            .into_iter()
+            .take(limit)
+            .filter_map(|(chunk_id, score)| chunks_by_id.remove(&chunk_id).map(|chunk| (chunk, score)))
+            .collect())
+    }
+
+    /// Turns free text into an FTS5 query that matches any of its terms. Every term is quoted so
+    /// identifiers like `ERR-404` or `SKU_12.B` are matched as phrases instead of being parsed as
+    /// FTS5 syntax. Returns `None` if the text has no searchable terms.
+    fn build_chunks_fts_query(query: &str) -> Option<String> {
+        let mut seen = HashSet::new();
+        let terms: Vec<String> = query
+            .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.'))
+            .map(|term| term.trim_matches(|c: char| !c.is_alphanumeric()))
+            .filter(|term| !term.is_empty())
+            .filter(|term| seen.insert(term.to_lowercase()))
+            .map(|term| format!("\"{}\"", term))
+            .collect();
+
+        if terms.is_empty() {
+            None
+        } else {
+            Some(terms.join(" OR "))
+        }
+    }
+
+    /// Returns the ids of `parsed_file_ids` whose chunks were embedded with the current default
+    /// embedding model. Files without a recorded model are kept as they predate the check, the
+    /// re-embedding migration records their model.
+    fn filter_parsed_files_by_current_embedding_model(
+        &self,
+        conn: &rusqlite::Connection,
+        parsed_file_ids: &[i64],
+    ) -> Result<Vec<i64>, SqliteManagerError> {
+        if parsed_file_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let current_model = self.get_default_embedding_model()?.to_string();
+        let placeholders = parsed_file_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let sql = format!(
+            "SELECT id, embedding_model_used FROM parsed_files WHERE id IN ({})",
+            placeholders
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(parsed_file_ids.iter()), |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut compatible_ids = Vec::new();
+        for (id, embedding_model_used) in rows {
+            match embedding_model_used {
+                Some(model) if model != current_model => {
+                    zoo_log(
+                        ZooLogOption::Database,
+                        ZooLogLevel::Debug,
+                        &format!(
+                            "Skipping parsed file {} in vector search: embedded with {} but the current model is {}",
+                            id, model, current_model
+                        ),
+                    );
+                }
+                _ => compatible_ids.push(id),
+            }
+        }
+        Ok(compatible_ids)
+    }
+
     // -------------------------
     // Folder Paths
     // -------------------------
@@ -931,4 +1164,95 @@ mod tests {
             .iter()
             .any(|pf| pf.relative_path == "docs/other/2024/march.txt"));
     }
+
+    #[test]
+    fn test_keyword_search_finds_exact_identifiers() {
+        let db = setup_test_db();
+        let parsed_file = create_test_parsed_file(1, "errors.txt");
+        db.add_parsed_file(&parsed_file).unwrap();
+
+        let contents = [
+            "The printer may report a paper jam.",
+            "Error code ERR-4012 means the toner cartridge SKU TX-88B is not supported.",
+            "Restart the device to clear most errors.",
+        ];
+        for (position, content) in contents.iter().enumerate() {
+            let chunk = ZooFileChunk {
+                chunk_id: None,
+                parsed_file_id: 1,
+                position: position as i64,
+                content: content.to_string(),
+            };
+            db.create_chunk_with_embedding(&chunk, None).unwrap();
+        }
+
+        let results = db.search_chunks_by_keywords(&[1], "what does ERR-4012 mean?", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.position, 1);
+
+        let results = db.search_chunks_by_keywords(&[1], "tx-88b", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.position, 1);
+
+        // FTS5 syntax in the query is treated as plain text
+        assert!(db.search_chunks_by_keywords(&[1], "\"* AND (", 10).unwrap().is_empty());
+        // Other files are not searched
+        assert!(db.search_chunks_by_keywords(&[2], "ERR-4012", 10).unwrap().is_empty());
+
+        // Removing the file removes its chunks from the keyword index
+        db.remove_parsed_file(1).unwrap();
+        let conn = db.get_connection().unwrap();
+        let indexed: i64 = conn
+            .query_row("SELECT COUNT(*) FROM chunks_fts WHERE chunks_fts MATCH '\"ERR-4012\"'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(indexed, 0);
+    }
+
+    #[test]
+    fn test_hybrid_search_fuses_vector_and_keyword_results() {
+        let db = setup_test_db();
+        let parsed_file = create_test_parsed_file(1, "manual.txt");
+        db.add_parsed_file(&parsed_file).unwrap();
+
+        // The chunk closest to the query embedding doesn't contain the identifier
+        let chunks = [
+            ("General maintenance instructions.", 0.1),
+            ("Replace filter FLT-220 every six months.", 0.9),
+            ("Warranty information.", 0.5),
+        ];
+        for (position, (content, value)) in chunks.iter().enumerate() {
+            let chunk = ZooFileChunk {
+                chunk_id: None,
+                parsed_file_id: 1,
+                position: position as i64,
+                content: content.to_string(),
+            };
+            db.create_chunk_with_embedding(&chunk, Some(&SqliteManager::generate_vector_for_testing(*value)))
+                .unwrap();
+        }
+
+        let query_embedding = SqliteManager::generate_vector_for_testing(0.1);
+        let vector_results = db.search_chunks(&[1], query_embedding.clone(), 1).unwrap();
+        assert_eq!(vector_results[0].0.position, 0);
+
+        let hybrid_results = db
+            .search_chunks_hybrid(&[1], "when to replace FLT-220", query_embedding, 2)
+            .unwrap();
+        assert_eq!(hybrid_results.len(), 2);
+        assert!(hybrid_results.iter().any(|(chunk, _)| chunk.position == 1));
+        assert!(hybrid_results[0].1 >= hybrid_results[1].1);
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let fused = reciprocal_rank_fusion(&[vec![1, 2, 3], vec![3, 4]], 60.0);
+        let ids: Vec<i64> = fused.iter().map(|(id, _)| *id).collect();
+        // 3 is in both rankings, 2 and 4 tie on rank and are ordered by id
+        assert_eq!(ids, vec![3, 1, 2, 4]);
+        assert!((fused[0].1 - (1.0 / 63.0 + 1.0 / 61.0)).abs() < f64::EPSILON);
+
+        assert!(reciprocal_rank_fusion(&[], 60.0).is_empty());
+    }
 }
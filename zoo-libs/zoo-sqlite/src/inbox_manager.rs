@@ -1,7 +1,7 @@
 use std::{collections::HashMap, str::FromStr, sync::Arc};
 
 use chrono::{DateTime, Utc};
-use rusqlite::params;
+use rusqlite::{params, OptionalExtension};
 use serde_json::Value;
 use zoo_message_primitives::{
     schemas::{
@@ -12,6 +12,7 @@ use zoo_message_primitives::{
 };
 use tokio::sync::Mutex;
 
+use crate::message_search_manager::searchable_message_text;
 use crate::{SqliteManager, SqliteManagerError};
 
 #[derive(Debug)]
@@ -117,17 +118,34 @@ impl SqliteManager {
         let encoded_message = updated_message
             .encode_message()
             .map_err(|e| SqliteManagerError::SomeError(e.to_string()))?;
+        let searchable_text = searchable_message_text(message);
         {
             let mut conn = self.get_connection()?;
 
             // Start a transaction to ensure both operations are atomic
             let tx = conn.transaction()?;
 
+            // A replaced message gets a new rowid, drop the search entry of the old one
+            let previous_rowid: Option<i64> = tx
+                .query_row(
+                    "SELECT rowid FROM inbox_messages WHERE message_hash = ?1",
+                    params![hash_key],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if let Some(previous_rowid) = previous_rowid {
+                Self::unindex_inbox_message(&tx, previous_rowid)?;
+            }
+
             // Update the message in inbox_messages
             tx.execute(
             "INSERT OR REPLACE INTO inbox_messages (message_hash, inbox_name, zoo_message, parent_message_hash, time_key) VALUES (?1, ?2, ?3, ?4, ?5)",
         params![hash_key, inbox_name, encoded_message, parent_key, time_key],
         )?;
+            if let Some(text) = &searchable_text {
+                let rowid = tx.last_insert_rowid();
+                Self::index_inbox_message(&tx, rowid, &hash_key, &inbox_name, &time_key, text)?;
+            }
 
             // Update the last_modified timestamp in inboxes
             tx.execute(
@@ -139,6 +157,10 @@ impl SqliteManager {
             tx.commit()?;
         }
 
+        if let Some(text) = &searchable_text {
+            self.embed_inbox_message_for_search(&hash_key, text).await;
+        }
+
         {
             // Note: this is the code for enabling WS
             if let Some(manager) = ws_manager {
@@ -849,6 +871,7 @@ impl SqliteManager {
         let tx = conn.transaction()?;
 
         // Delete all messages from the inbox
+        Self::unindex_inbox_messages(&tx, inbox_name)?;
         tx.execute("DELETE FROM inbox_messages WHERE inbox_name = ?1", params![inbox_name])?;
 
         // Reset the read_up_to_message_hash to null since there are no messages
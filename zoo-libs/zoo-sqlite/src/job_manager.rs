@@ -366,6 +366,7 @@ impl SqliteManager {
             "DELETE FROM inbox_profile_permissions WHERE inbox_name = ?1",
             params![inbox_name.to_string()],
         )?;
+        Self::unindex_inbox_messages(&tx, &inbox_name.to_string())?;
         tx.execute(
             "DELETE FROM inbox_messages WHERE inbox_name = ?1",
             params![inbox_name.to_string()],
@@ -376,6 +377,10 @@ impl SqliteManager {
         )?;
 
         tx.execute("DELETE FROM jobs WHERE job_id = ?1", params![job_id])?;
+        tx.execute(
+            "DELETE FROM job_conversation_summaries WHERE job_id = ?1",
+            params![job_id],
+        )?;
 
         tx.commit()?;
 
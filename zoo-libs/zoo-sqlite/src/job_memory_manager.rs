@@ -0,0 +1,121 @@
+use crate::{errors::SqliteManagerError, SqliteManager};
+use rusqlite::{params, OptionalExtension, Result};
+
+/// Rolling summary of the older messages of a job, kept when its memory strategy summarizes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobConversationSummary {
+    pub job_id: String,
+    pub summary: String,
+    /// Hash of the newest message folded into the summary. The messages after it aren't summarized yet.
+    pub last_message_hash: String,
+    pub summarized_messages: u64,
+    pub updated_at: String,
+}
+
+impl SqliteManager {
+    pub fn initialize_job_conversation_summaries_table(conn: &rusqlite::Connection) -> Result<()> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS job_conversation_summaries (
+                job_id TEXT PRIMARY KEY,
+                summary TEXT NOT NULL,
+                last_message_hash TEXT NOT NULL,
+                summarized_messages INTEGER NOT NULL,
+                updated_at TEXT NOT NULL
+            );",
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_job_conversation_summary(
+        &self,
+        job_id: &str,
+    ) -> Result<Option<JobConversationSummary>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let summary = conn
+            .query_row(
+                "SELECT job_id, summary, last_message_hash, summarized_messages, updated_at
+                 FROM job_conversation_summaries WHERE job_id = ?1",
+                params![job_id],
+                |row| {
+                    Ok(JobConversationSummary {
+                        job_id: row.get(0)?,
+                        summary: row.get(1)?,
+                        last_message_hash: row.get(2)?,
+                        summarized_messages: row.get::<_, i64>(3)? as u64,
+                        updated_at: row.get(4)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(summary)
+    }
+
+    /// Stores the summary of a job, replacing the previous one.
+    pub fn set_job_conversation_summary(&self, summary: &JobConversationSummary) -> Result<(), SqliteManagerError> {
+        let conn = self.get_connection()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO job_conversation_summaries
+                (job_id, summary, last_message_hash, summarized_messages, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                summary.job_id,
+                summary.summary,
+                summary.last_message_hash,
+                summary.summarized_messages as i64,
+                summary.updated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_job_conversation_summary(&self, job_id: &str) -> Result<(), SqliteManagerError> {
+        let conn = self.get_connection()?;
+        conn.execute(
+            "DELETE FROM job_conversation_summaries WHERE job_id = ?1",
+            params![job_id],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+    use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+
+    fn setup_test_db() -> SqliteManager {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = temp_file.path().to_path_buf();
+        let api_url = String::new();
+        let model_type =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM);
+
+        SqliteManager::new(db_path, api_url, model_type).unwrap()
+    }
+
+    #[test]
+    fn test_job_conversation_summary_replaces_previous() {
+        let db = setup_test_db();
+        assert_eq!(db.get_job_conversation_summary("job1").unwrap(), None);
+
+        let mut summary = JobConversationSummary {
+            job_id: "job1".to_string(),
+            summary: "The user is comparing two databases.".to_string(),
+            last_message_hash: "hash1".to_string(),
+            summarized_messages: 6,
+            updated_at: "2024-01-01T00:00:00Z".to_string(),
+        };
+        db.set_job_conversation_summary(&summary).unwrap();
+
+        summary.summary = "The user picked SQLite over Postgres.".to_string();
+        summary.last_message_hash = "hash2".to_string();
+        summary.summarized_messages = 12;
+        db.set_job_conversation_summary(&summary).unwrap();
+        assert_eq!(db.get_job_conversation_summary("job1").unwrap(), Some(summary));
+
+        db.remove_job_conversation_summary("job1").unwrap();
+        assert_eq!(db.get_job_conversation_summary("job1").unwrap(), None);
+    }
+}
@@ -1,70 +1,701 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use rusqlite::params;
+use rusqlite::{params, OptionalExtension, Result};
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{SqliteManager, SqliteManagerError};
 
+/// Lifecycle of a queued item: `Queued` until a worker leases it, `Leased` while it's being
+/// processed and then `Done`, or `Failed` once it ran out of attempts (dead letter). Items of a
+/// job stopped by the user are `Cancelled`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobQueueItemStatus {
+    Queued,
+    Leased,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobQueueItemStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobQueueItemStatus::Queued => "queued",
+            JobQueueItemStatus::Leased => "leased",
+            JobQueueItemStatus::Done => "done",
+            JobQueueItemStatus::Failed => "failed",
+            JobQueueItemStatus::Cancelled => "cancelled",
+        }
+    }
+
+    fn from_db(value: &str) -> Result<Self, SqliteManagerError> {
+        match value {
+            "queued" => Ok(JobQueueItemStatus::Queued),
+            "leased" => Ok(JobQueueItemStatus::Leased),
+            "done" => Ok(JobQueueItemStatus::Done),
+            "failed" => Ok(JobQueueItemStatus::Failed),
+            "cancelled" => Ok(JobQueueItemStatus::Cancelled),
+            other => Err(SqliteManagerError::SomeError(format!(
+                "Unknown job queue item status: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// One item of a job queue. `queue_key` is given without the queue prefix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobQueueItem<T> {
+    pub id: i64,
+    pub queue_key: String,
+    pub value: T,
+    pub status: JobQueueItemStatus,
+    pub priority: i64,
+    pub attempts: u32,
+    pub max_attempts: u32,
+    /// Unix timestamp after which a leased item is considered abandoned.
+    pub lease_expires_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+}
+
+const JOB_QUEUE_ITEM_COLUMNS: &str = "id, queue_key, item_data, status, priority, attempts, max_attempts, \
+     lease_expires_at, last_error, created_at";
+
+fn full_queue_key(prefix: &Option<String>, key: &str) -> String {
+    match prefix {
+        Some(p) => format!("{}{}", p, key),
+        None => key.to_string(),
+    }
+}
+
+/// The queues of a manager without a prefix have an empty one, so they never see the items of
+/// the prefixed queues.
+fn queue_prefix(prefix: &Option<String>) -> &str {
+    prefix.as_deref().unwrap_or("")
+}
+
 impl SqliteManager {
-    pub fn persist_queue<T: Serialize>(
+    pub fn initialize_job_queue_tables(conn: &rusqlite::Connection) -> Result<()> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS job_queue_items (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                queue_key TEXT NOT NULL,
+                queue_prefix TEXT NOT NULL DEFAULT '',
+                item_data TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'queued',
+                priority INTEGER NOT NULL DEFAULT 0,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                max_attempts INTEGER NOT NULL DEFAULT 3,
+                lease_expires_at INTEGER,
+                last_error TEXT,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_job_queue_items_status ON job_queue_items (status, queue_key);",
+            [],
+        )?;
+
+        // Queues used to be stored as one JSON array per snapshot
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS job_queues (
+                job_id TEXT NOT NULL,
+                queue_data TEXT NOT NULL
+            );",
+            [],
+        )?;
+        Self::migrate_legacy_job_queues(conn)?;
+
+        Ok(())
+    }
+
+    /// Moves the items of the latest snapshot of every legacy queue into `job_queue_items`.
+    fn migrate_legacy_job_queues(conn: &rusqlite::Connection) -> Result<()> {
+        let mut latest_snapshots: Vec<(String, String)> = Vec::new();
+        {
+            let mut stmt = conn.prepare("SELECT job_id, queue_data FROM job_queues ORDER BY rowid")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (job_id, queue_data) = row?;
+                match latest_snapshots.iter_mut().find(|(id, _)| *id == job_id) {
+                    Some(snapshot) => snapshot.1 = queue_data,
+                    None => latest_snapshots.push((job_id, queue_data)),
+                }
+            }
+        }
+        if latest_snapshots.is_empty() {
+            return Ok(());
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        let now = chrono::Utc::now().timestamp();
+        for (job_id, queue_data) in latest_snapshots {
+            let items: Vec<serde_json::Value> = serde_json::from_str(&queue_data).unwrap_or_default();
+            for item in items {
+                tx.execute(
+                    "INSERT INTO job_queue_items (queue_key, item_data, status, created_at, updated_at)
+                     VALUES (?1, ?2, 'queued', ?3, ?3)",
+                    params![job_id, item.to_string(), now],
+                )?;
+            }
+        }
+        tx.execute("DELETE FROM job_queues", [])?;
+        tx.commit()
+    }
+
+    /// Adds an item at the end of the queue `key`. Items with a higher `priority` are leased first.
+    pub fn add_job_queue_item<T: Serialize>(
         &self,
-        job_id: &str,
-        queue: &Vec<T>,
-        prefix: Option<String>,
-    ) -> Result<(), SqliteManagerError> {
-        let full_job_id = match &prefix {
-            Some(p) => format!("{}{}", p, job_id),
-            None => job_id.to_string(),
+        prefix: &Option<String>,
+        key: &str,
+        value: &T,
+        priority: i64,
+        max_attempts: u32,
+    ) -> Result<i64, SqliteManagerError> {
+        let item_data =
+            serde_json::to_string(value).map_err(|e| SqliteManagerError::SerializationError(e.to_string()))?;
+        let now = chrono::Utc::now().timestamp();
+
+        let conn = self.get_connection()?;
+        conn.execute(
+            "INSERT INTO job_queue_items
+                (queue_key, queue_prefix, item_data, status, priority, max_attempts, created_at, updated_at)
+             VALUES (?1, ?2, ?3, 'queued', ?4, ?5, ?6, ?6)",
+            params![
+                full_queue_key(prefix, key),
+                queue_prefix(prefix),
+                item_data,
+                priority,
+                max_attempts,
+                now
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Items that are still pending (queued or leased), highest priority and oldest first.
+    pub fn get_active_job_queue_items<T: DeserializeOwned>(
+        &self,
+        prefix: &Option<String>,
+    ) -> Result<Vec<JobQueueItem<T>>, SqliteManagerError> {
+        self.query_job_queue_items(prefix, "status IN ('queued', 'leased')", "priority DESC, id ASC")
+    }
+
+    /// Dead letters: items that failed or were abandoned too many times.
+    pub fn get_failed_job_queue_items<T: DeserializeOwned>(
+        &self,
+        prefix: &Option<String>,
+    ) -> Result<Vec<JobQueueItem<T>>, SqliteManagerError> {
+        self.query_job_queue_items(prefix, "status = 'failed'", "updated_at DESC, id DESC")
+    }
+
+    /// Leases up to `limit` items, at most one per queue key so the items of a queue are processed
+    /// in order. Items whose lease expired are leased again; the ones that already used all their
+    /// attempts are moved to the dead letters instead.
+    pub fn lease_job_queue_items<T: DeserializeOwned>(
+        &self,
+        prefix: &Option<String>,
+        limit: usize,
+        lease_secs: u64,
+    ) -> Result<Vec<JobQueueItem<T>>, SqliteManagerError> {
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+        let now = chrono::Utc::now().timestamp();
+        let prefix_str = queue_prefix(prefix);
+
+        let candidates: Vec<(i64, String, u32, u32)> = {
+            let mut stmt = tx.prepare(
+                "SELECT i.id, i.queue_key, i.attempts, i.max_attempts FROM job_queue_items i
+                 WHERE i.queue_prefix = ?1
+                   AND (i.status = 'queued' OR (i.status = 'leased' AND i.lease_expires_at < ?2))
+                   AND NOT EXISTS (
+                       SELECT 1 FROM job_queue_items l
+                       WHERE l.queue_key = i.queue_key AND l.status = 'leased' AND l.lease_expires_at >= ?2
+                   )
+                 ORDER BY i.priority DESC, i.id ASC",
+            )?;
+            let rows = stmt.query_map(params![prefix_str, now], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
         };
 
-        let serialized_queue =
-            serde_json::to_string(queue).map_err(|e| SqliteManagerError::SerializationError(e.to_string()))?;
+        let mut seen_keys = HashSet::new();
+        let mut leased_ids = Vec::new();
+        for (id, queue_key, attempts, max_attempts) in candidates {
+            if leased_ids.len() >= limit {
+                break;
+            }
+            if !seen_keys.insert(queue_key) {
+                continue;
+            }
+            if attempts >= max_attempts {
+                tx.execute(
+                    "UPDATE job_queue_items SET status = 'failed', lease_expires_at = NULL, updated_at = ?1,
+                        last_error = COALESCE(last_error, 'Abandoned too many times')
+                     WHERE id = ?2",
+                    params![now, id],
+                )?;
+                continue;
+            }
+            tx.execute(
+                "UPDATE job_queue_items SET status = 'leased', attempts = attempts + 1, lease_expires_at = ?1,
+                    updated_at = ?2
+                 WHERE id = ?3",
+                params![lease_expiry(now, lease_secs), now, id],
+            )?;
+            leased_ids.push(id);
+        }
+        tx.commit()?;
+
+        let mut leased = Vec::new();
+        for id in leased_ids {
+            if let Some(item) = self.get_job_queue_item(prefix, id)? {
+                leased.push(item);
+            }
+        }
+        Ok(leased)
+    }
+
+    pub fn get_job_queue_item<T: DeserializeOwned>(
+        &self,
+        prefix: &Option<String>,
+        id: i64,
+    ) -> Result<Option<JobQueueItem<T>>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let row = conn
+            .query_row(
+                &format!("SELECT {} FROM job_queue_items WHERE id = ?1", JOB_QUEUE_ITEM_COLUMNS),
+                params![id],
+                read_job_queue_row,
+            )
+            .optional()?;
+        row.map(|row| parse_job_queue_row(prefix, row)).transpose()
+    }
 
+    /// Extends the lease of an item still being processed, so it isn't leased again meanwhile.
+    /// Returns false if the item isn't leased anymore.
+    pub fn renew_job_queue_lease(&self, id: i64, lease_secs: u64) -> Result<bool, SqliteManagerError> {
         let conn = self.get_connection()?;
-        conn.execute(
-            "INSERT INTO job_queues (job_id, queue_data) VALUES (?1, ?2)",
-            params![full_job_id, serialized_queue],
+        let now = chrono::Utc::now().timestamp();
+        let renewed = conn.execute(
+            "UPDATE job_queue_items SET lease_expires_at = ?1, updated_at = ?2 WHERE id = ?3 AND status = 'leased'",
+            params![lease_expiry(now, lease_secs), now, id],
         )?;
+        Ok(renewed > 0)
+    }
 
+    /// Marks a pending item as done. Items cancelled or dead lettered meanwhile keep their status.
+    pub fn complete_job_queue_item(&self, id: i64) -> Result<(), SqliteManagerError> {
+        let conn = self.get_connection()?;
+        conn.execute(
+            "UPDATE job_queue_items SET status = 'done', lease_expires_at = NULL, updated_at = ?1
+             WHERE id = ?2 AND status IN ('queued', 'leased')",
+            params![chrono::Utc::now().timestamp(), id],
+        )?;
         Ok(())
     }
 
-    pub fn get_all_queues<T: DeserializeOwned>(
+    /// Cancels every pending item of the queue `key`, including the one being processed. Returns
+    /// how many items were cancelled.
+    pub fn cancel_job_queue_items(
         &self,
-        prefix: Option<String>,
-    ) -> Result<HashMap<String, Vec<T>>, SqliteManagerError> {
+        prefix: &Option<String>,
+        key: &str,
+        reason: &str,
+    ) -> Result<usize, SqliteManagerError> {
         let conn = self.get_connection()?;
-        let mut queues = HashMap::new();
-        let mut query = "SELECT job_id, queue_data FROM job_queues WHERE 1=1".to_string();
+        let cancelled = conn.execute(
+            "UPDATE job_queue_items SET status = 'cancelled', last_error = ?1, lease_expires_at = NULL, updated_at = ?2
+             WHERE queue_key = ?3 AND status IN ('queued', 'leased')",
+            params![reason, chrono::Utc::now().timestamp(), full_queue_key(prefix, key)],
+        )?;
+        Ok(cancelled)
+    }
 
-        if let Some(p) = &prefix {
-            query.push_str(&format!(" AND job_id LIKE '{}%'", p));
+    /// Records a failed attempt of a leased item. The item is queued again if `retry` is set and it
+    /// has attempts left, otherwise it becomes a dead letter. Items that aren't leased anymore (e.g.
+    /// removed from the queue meanwhile) are left as they are. Returns the new status.
+    pub fn fail_job_queue_item(
+        &self,
+        id: i64,
+        error: &str,
+        retry: bool,
+    ) -> Result<JobQueueItemStatus, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let (status, attempts, max_attempts): (String, u32, u32) = conn.query_row(
+            "SELECT status, attempts, max_attempts FROM job_queue_items WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let current_status = JobQueueItemStatus::from_db(&status)?;
+        if current_status != JobQueueItemStatus::Leased {
+            return Ok(current_status);
         }
 
-        let mut stmt = conn.prepare(&query)?;
+        let status = if retry && attempts < max_attempts {
+            JobQueueItemStatus::Queued
+        } else {
+            JobQueueItemStatus::Failed
+        };
+        conn.execute(
+            "UPDATE job_queue_items SET status = ?1, last_error = ?2, lease_expires_at = NULL, updated_at = ?3
+             WHERE id = ?4",
+            params![status.as_str(), error, chrono::Utc::now().timestamp(), id],
+        )?;
+        Ok(status)
+    }
 
-        let rows = stmt.query_map(params![], |row| {
-            let mut job_id: String = row.get(0)?;
-            let serialized_queue: String = row.get(1)?;
-            let queue: Vec<T> = serde_json::from_str(&serialized_queue).map_err(|e| {
-                rusqlite::Error::ToSqlConversionFailure(Box::new(SqliteManagerError::SerializationError(e.to_string())))
-            })?;
+    /// Queues a dead letter again with its attempts reset. Returns whether it was a dead letter.
+    pub fn requeue_failed_job_queue_item(&self, id: i64) -> Result<bool, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let updated = conn.execute(
+            "UPDATE job_queue_items SET status = 'queued', attempts = 0, updated_at = ?1
+             WHERE id = ?2 AND status = 'failed'",
+            params![chrono::Utc::now().timestamp(), id],
+        )?;
+        Ok(updated > 0)
+    }
 
-            // If a prefix is provided, remove it from the job_id
-            if let Some(p) = &prefix {
-                if job_id.starts_with(p) {
-                    job_id = job_id[p.len()..].to_string();
-                }
-            }
+    /// Takes the head of the queue `key` out of it (marking it as done) and returns it. A leased
+    /// item is the head of its queue.
+    pub fn remove_job_queue_head<T: DeserializeOwned>(
+        &self,
+        prefix: &Option<String>,
+        key: &str,
+    ) -> Result<Option<T>, SqliteManagerError> {
+        let head = self.get_job_queue_head::<T>(prefix, key)?;
+        if let Some(item) = &head {
+            self.complete_job_queue_item(item.id)?;
+        }
+        Ok(head.map(|item| item.value))
+    }
 
-            Ok((job_id, queue))
-        })?;
+    pub fn get_job_queue_head<T: DeserializeOwned>(
+        &self,
+        prefix: &Option<String>,
+        key: &str,
+    ) -> Result<Option<JobQueueItem<T>>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let row = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM job_queue_items
+                     WHERE queue_key = ?1 AND status IN ('queued', 'leased')
+                     ORDER BY CASE status WHEN 'leased' THEN 0 ELSE 1 END, priority DESC, id ASC
+                     LIMIT 1",
+                    JOB_QUEUE_ITEM_COLUMNS
+                ),
+                params![full_queue_key(prefix, key)],
+                read_job_queue_row,
+            )
+            .optional()?;
+        row.map(|row| parse_job_queue_row(prefix, row)).transpose()
+    }
 
-        for row in rows {
-            let (job_id, queue) = row?;
-            queues.insert(job_id, queue);
+    /// Crash recovery: every item left leased by a previous run is queued again, or becomes a dead
+    /// letter if it already used all its attempts. Returns (requeued, dead lettered).
+    /// The items moved from the legacy queues have no prefix, they are claimed first by the queue
+    /// whose prefix their key starts with.
+    pub fn recover_job_queue_items(&self, prefix: &Option<String>) -> Result<(usize, usize), SqliteManagerError> {
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+        let now = chrono::Utc::now().timestamp();
+        let prefix_str = queue_prefix(prefix);
+
+        if !prefix_str.is_empty() {
+            tx.execute(
+                "UPDATE job_queue_items SET queue_prefix = ?1
+                 WHERE queue_prefix = '' AND substr(queue_key, 1, length(?1)) = ?1",
+                params![prefix_str],
+            )?;
         }
 
+        let dead_lettered = tx.execute(
+            "UPDATE job_queue_items SET status = 'failed', lease_expires_at = NULL, updated_at = ?2,
+                last_error = COALESCE(last_error, 'Abandoned too many times')
+             WHERE queue_prefix = ?1 AND status = 'leased' AND attempts >= max_attempts",
+            params![prefix_str, now],
+        )?;
+        let requeued = tx.execute(
+            "UPDATE job_queue_items SET status = 'queued', lease_expires_at = NULL, updated_at = ?2
+             WHERE queue_prefix = ?1 AND status = 'leased'",
+            params![prefix_str, now],
+        )?;
+        tx.commit()?;
+        Ok((requeued, dead_lettered))
+    }
+
+    /// Removes the done and cancelled items last updated more than `older_than_secs` ago.
+    pub fn purge_done_job_queue_items(
+        &self,
+        prefix: &Option<String>,
+        older_than_secs: u64,
+    ) -> Result<usize, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let removed = conn.execute(
+            "DELETE FROM job_queue_items
+             WHERE queue_prefix = ?1 AND status IN ('done', 'cancelled') AND updated_at < ?2",
+            params![
+                queue_prefix(prefix),
+                chrono::Utc::now().timestamp() - older_than_secs as i64
+            ],
+        )?;
+        Ok(removed)
+    }
+
+    /// Pending items grouped by queue key, each queue in processing order.
+    pub fn get_all_queues<T: DeserializeOwned>(
+        &self,
+        prefix: &Option<String>,
+    ) -> Result<HashMap<String, Vec<T>>, SqliteManagerError> {
+        let mut queues: HashMap<String, Vec<T>> = HashMap::new();
+        for item in self.get_active_job_queue_items::<T>(prefix)? {
+            queues.entry(item.queue_key).or_default().push(item.value);
+        }
         Ok(queues)
     }
+
+    fn query_job_queue_items<T: DeserializeOwned>(
+        &self,
+        prefix: &Option<String>,
+        condition: &str,
+        order_by: &str,
+    ) -> Result<Vec<JobQueueItem<T>>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM job_queue_items WHERE queue_prefix = ?1 AND {} ORDER BY {}",
+            JOB_QUEUE_ITEM_COLUMNS, condition, order_by
+        ))?;
+        let rows = stmt.query_map(params![queue_prefix(prefix)], read_job_queue_row)?;
+
+        let mut items = Vec::new();
+        for row in rows {
+            items.push(parse_job_queue_row(prefix, row?)?);
+        }
+        Ok(items)
+    }
+}
+
+fn lease_expiry(now: i64, lease_secs: u64) -> i64 {
+    now.saturating_add(i64::try_from(lease_secs).unwrap_or(i64::MAX))
+}
+
+type JobQueueRow = (
+    i64,
+    String,
+    String,
+    String,
+    i64,
+    u32,
+    u32,
+    Option<i64>,
+    Option<String>,
+    i64,
+);
+
+fn read_job_queue_row(row: &rusqlite::Row) -> Result<JobQueueRow> {
+    Ok((
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+        row.get(6)?,
+        row.get(7)?,
+        row.get(8)?,
+        row.get(9)?,
+    ))
+}
+
+fn parse_job_queue_row<T: DeserializeOwned>(
+    prefix: &Option<String>,
+    row: JobQueueRow,
+) -> Result<JobQueueItem<T>, SqliteManagerError> {
+    let (id, queue_key, item_data, status, priority, attempts, max_attempts, lease_expires_at, last_error, created_at) =
+        row;
+    let value = serde_json::from_str(&item_data).map_err(|e| SqliteManagerError::SerializationError(e.to_string()))?;
+    let queue_key = queue_key
+        .strip_prefix(queue_prefix(prefix))
+        .map(|key| key.to_string())
+        .unwrap_or(queue_key);
+
+    Ok(JobQueueItem {
+        id,
+        queue_key,
+        value,
+        status: JobQueueItemStatus::from_db(&status)?,
+        priority,
+        attempts,
+        max_attempts,
+        lease_expires_at,
+        last_error,
+        created_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::NamedTempFile;
+    use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+
+    fn setup_test_db() -> SqliteManager {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = PathBuf::from(temp_file.path());
+        let api_url = String::new();
+        let model_type =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM);
+
+        SqliteManager::new(db_path, api_url, model_type).unwrap()
+    }
+
+    #[test]
+    fn test_lease_one_item_per_queue_by_priority() {
+        let db = setup_test_db();
+        let prefix = Some("test_".to_string());
+        db.add_job_queue_item(&prefix, "a", &"a1", 0, 3).unwrap();
+        db.add_job_queue_item(&prefix, "a", &"a2", 0, 3).unwrap();
+        db.add_job_queue_item(&prefix, "b", &"b1", 0, 3).unwrap();
+        db.add_job_queue_item(&prefix, "c", &"c1", 5, 3).unwrap();
+        // Other prefixes are other queues
+        db.add_job_queue_item(&Some("other_".to_string()), "a", &"x", 0, 3)
+            .unwrap();
+
+        let leased = db.lease_job_queue_items::<String>(&prefix, 10, 60).unwrap();
+        let values: Vec<_> = leased.iter().map(|item| item.value.as_str()).collect();
+        assert_eq!(values, vec!["c1", "a1", "b1"]);
+        assert!(leased
+            .iter()
+            .all(|item| item.status == JobQueueItemStatus::Leased && item.attempts == 1));
+        assert_eq!(leased[1].queue_key, "a");
+
+        // a2 waits for a1
+        assert!(db.lease_job_queue_items::<String>(&prefix, 10, 60).unwrap().is_empty());
+        db.complete_job_queue_item(leased[1].id).unwrap();
+        let leased = db.lease_job_queue_items::<String>(&prefix, 10, 60).unwrap();
+        assert_eq!(leased.len(), 1);
+        assert_eq!(leased[0].value, "a2");
+    }
+
+    #[test]
+    fn test_failures_and_dead_letters() {
+        let db = setup_test_db();
+        let prefix = None;
+        let id = db.add_job_queue_item(&prefix, "a", &"a1", 0, 2).unwrap();
+
+        db.lease_job_queue_items::<String>(&prefix, 1, 60).unwrap();
+        assert_eq!(
+            db.fail_job_queue_item(id, "boom", true).unwrap(),
+            JobQueueItemStatus::Queued
+        );
+        db.lease_job_queue_items::<String>(&prefix, 1, 60).unwrap();
+        assert_eq!(
+            db.fail_job_queue_item(id, "boom again", true).unwrap(),
+            JobQueueItemStatus::Failed
+        );
+
+        assert!(db.lease_job_queue_items::<String>(&prefix, 1, 60).unwrap().is_empty());
+        let dead_letters = db.get_failed_job_queue_items::<String>(&prefix).unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].last_error.as_deref(), Some("boom again"));
+
+        assert!(db.requeue_failed_job_queue_item(id).unwrap());
+        let leased = db.lease_job_queue_items::<String>(&prefix, 1, 60).unwrap();
+        assert_eq!(leased[0].attempts, 1);
+    }
+
+    #[test]
+    fn test_recover_abandoned_leases() {
+        let db = setup_test_db();
+        let prefix = None;
+        let first = db.add_job_queue_item(&prefix, "a", &"a1", 0, 1).unwrap();
+        db.add_job_queue_item(&prefix, "b", &"b1", 0, 3).unwrap();
+        db.lease_job_queue_items::<String>(&prefix, 10, 60).unwrap();
+
+        // a1 had a single attempt, so it's dead lettered instead of being run again
+        assert_eq!(db.recover_job_queue_items(&prefix).unwrap(), (1, 1));
+        let active = db.get_active_job_queue_items::<String>(&prefix).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].value, "b1");
+        assert_eq!(active[0].status, JobQueueItemStatus::Queued);
+        assert_eq!(db.get_failed_job_queue_items::<String>(&prefix).unwrap()[0].id, first);
+
+        // An expired lease is leased again without waiting for a restart
+        let conn = db.get_connection().unwrap();
+        db.lease_job_queue_items::<String>(&prefix, 10, 60).unwrap();
+        conn.execute("UPDATE job_queue_items SET lease_expires_at = 0", [])
+            .unwrap();
+        let leased = db.lease_job_queue_items::<String>(&prefix, 10, 60).unwrap();
+        assert_eq!(leased.len(), 1);
+        assert_eq!(leased[0].attempts, 2);
+    }
+
+    #[test]
+    fn test_cancel_queue() {
+        let db = setup_test_db();
+        let prefix = None;
+        let first = db.add_job_queue_item(&prefix, "a", &"a1", 0, 3).unwrap();
+        db.add_job_queue_item(&prefix, "a", &"a2", 0, 3).unwrap();
+        db.add_job_queue_item(&prefix, "b", &"b1", 0, 3).unwrap();
+        db.lease_job_queue_items::<String>(&prefix, 10, 60).unwrap();
+
+        assert_eq!(db.cancel_job_queue_items(&prefix, "a", "Stopped").unwrap(), 2);
+
+        // The worker finishing the leased item doesn't undo the cancellation
+        db.complete_job_queue_item(first).unwrap();
+        let item = db.get_job_queue_item::<String>(&prefix, first).unwrap().unwrap();
+        assert_eq!(item.status, JobQueueItemStatus::Cancelled);
+        assert_eq!(item.last_error.as_deref(), Some("Stopped"));
+
+        let active = db.get_active_job_queue_items::<String>(&prefix).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].value, "b1");
+    }
+
+    #[test]
+    fn test_queues_without_prefix_are_separate() {
+        let db = setup_test_db();
+        let prefix = Some("test_".to_string());
+        db.add_job_queue_item(&prefix, "a", &"a1", 0, 3).unwrap();
+        db.add_job_queue_item(&None, "b", &"b1", 0, 3).unwrap();
+
+        let leased = db.lease_job_queue_items::<String>(&None, 10, 60).unwrap();
+        assert_eq!(leased.len(), 1);
+        assert_eq!(leased[0].value, "b1");
+        assert_eq!(db.get_all_queues::<String>(&None).unwrap().len(), 1);
+
+        // Items stored before queue_prefix existed are claimed by the queue of their prefix
+        let conn = db.get_connection().unwrap();
+        conn.execute("UPDATE job_queue_items SET queue_prefix = ''", [])
+            .unwrap();
+        assert_eq!(db.get_active_job_queue_items::<String>(&prefix).unwrap().len(), 0);
+        db.recover_job_queue_items(&prefix).unwrap();
+        let active = db.get_active_job_queue_items::<String>(&prefix).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].value, "a1");
+    }
+
+    #[test]
+    fn test_renew_lease() {
+        let db = setup_test_db();
+        let prefix = None;
+        let id = db.add_job_queue_item(&prefix, "a", &"a1", 0, 3).unwrap();
+        db.lease_job_queue_items::<String>(&prefix, 10, 60).unwrap();
+
+        // A renewed lease isn't taken over, even after the original one expired
+        let conn = db.get_connection().unwrap();
+        conn.execute("UPDATE job_queue_items SET lease_expires_at = 0", [])
+            .unwrap();
+        assert!(db.renew_job_queue_lease(id, 60).unwrap());
+        assert!(db.lease_job_queue_items::<String>(&prefix, 10, 60).unwrap().is_empty());
+
+        db.complete_job_queue_item(id).unwrap();
+        assert!(!db.renew_job_queue_lease(id, 60).unwrap());
+    }
 }
@@ -2,18 +2,25 @@ use embedding_function::EmbeddingFunction;
 use errors::SqliteManagerError;
 use log::info;
 use r2d2::Pool;
+use schema_migrations::MIGRATIONS;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::{ffi::sqlite3_auto_extension, Result, Row, ToSql};
+use rusqlite::{ffi::sqlite3_auto_extension, OptionalExtension, Result, Row, ToSql};
 use zoo_embedding::model_type::EmbeddingModelType;
 use zoo_message_primitives::schemas::zoo_name::ZooName;
 use sqlite_vec::sqlite3_vec_init;
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use secrets_vault::SecretsVault;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 pub mod agent_manager;
+pub mod agent_memory_manager;
+pub mod api_key_manager;
+pub mod backup_manager;
 pub mod cron_task_manager;
 pub mod embedding_function;
+pub mod embedding_migration_manager;
 pub mod errors;
 pub mod file_inbox_manager;
 pub mod file_system;
@@ -24,21 +31,29 @@ pub mod inbox_manager;
 pub mod invoice_manager;
 pub mod invoice_request_manager;
 pub mod job_manager;
+pub mod job_memory_manager;
 pub mod job_queue_manager;
 pub mod keys_manager;
 pub mod llm_provider_manager;
+pub mod llm_response_cache_manager;
 pub mod mcp_server_manager;
+pub mod message_search_manager;
 pub mod oauth_manager;
 pub mod preferences;
 pub mod prompt_manager;
 pub mod regex_pattern_manager;
 pub mod retry_manager;
+pub mod schema_migrations;
+pub mod secrets_manager;
+pub mod secrets_vault;
 pub mod settings_manager;
 pub mod zoo_tool_manager;
 pub mod source_file_manager;
 pub mod tool_payment_req_manager;
 pub mod tool_playground;
+pub mod tool_version_manager;
 pub mod tracing;
+pub mod usage_manager;
 pub mod wallet_manager;
 
 // Updated struct to manage SQLite connections using a connection pool
@@ -46,6 +61,9 @@ pub struct SqliteManager {
     pool: Arc<Pool<SqliteConnectionManager>>,
     fts_pool: Arc<Pool<SqliteConnectionManager>>,
     api_url: String,
+    embedding_migration_running: Arc<AtomicBool>,
+    // Locked (None) until the node unlocks it with its identity or passphrase
+    secrets_vault: Arc<RwLock<Option<SecretsVault>>>,
 }
 
 impl std::fmt::Debug for SqliteManager {
@@ -61,15 +79,17 @@ impl SqliteManager {
         api_url: String,
         model_type: EmbeddingModelType,
     ) -> Result<Self, SqliteManagerError> {
+        // The vector tables are sized for the default embedding model
+        let vector_dimensions = model_type
+            .vector_dimensions()
+            .map_err(|_| SqliteManagerError::UnsupportedEmbeddingModel(model_type.to_string()))?;
+
         // Register the sqlite-vec extension
         unsafe {
             sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_vec_init as *const ())));
         }
 
-        let mut db_path = db_path.as_ref().to_path_buf();
-        if db_path.extension().and_then(|ext| ext.to_str()) != Some("db") {
-            db_path.set_extension("db");
-        }
+        let db_path = Self::db_file_path(db_path);
 
         // Create all subfolders if they don't exist
         if let Some(parent) = db_path.parent() {
@@ -77,7 +97,7 @@ impl SqliteManager {
                 .map_err(|e| rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(1), Some(e.to_string())))?;
         }
 
-        let manager = SqliteConnectionManager::file(db_path);
+        let manager = SqliteConnectionManager::file(&db_path);
         let pool = Pool::builder()
             .max_size(10)
             .connection_timeout(Duration::from_secs(60))
@@ -99,9 +119,13 @@ impl SqliteManager {
                  PRAGMA foreign_keys = ON;", // Enable foreign key support
         )?;
 
+        // Refuse a database from a newer binary before touching its schema
+        Self::check_schema_version(&conn, MIGRATIONS)?;
+        let new_database = Self::is_new_database(&conn)?;
+
         // Initialize tables in the persistent database
-        Self::initialize_tables(&conn)?;
-        Self::migrate_tables(&conn)?;
+        Self::initialize_tables(&conn, vector_dimensions)?;
+        Self::run_migrations(&conn, MIGRATIONS, &db_path, new_database)?;
 
         // Create a connection pool for the in-memory database
         let fts_manager = SqliteConnectionManager::memory();
@@ -128,6 +152,8 @@ impl SqliteManager {
             pool: Arc::new(pool),
             fts_pool: Arc::new(fts_pool), // Use the in-memory connection pool
             api_url,
+            embedding_migration_running: Arc::new(AtomicBool::new(false)),
+            secrets_vault: Arc::new(RwLock::new(None)),
         };
         let fts_sync_result = manager.sync_tools_fts_table();
         if let Err(e) = fts_sync_result {
@@ -164,8 +190,9 @@ impl SqliteManager {
     }
 
     // Initializes the required tables in the SQLite database
-    fn initialize_tables(conn: &rusqlite::Connection) -> Result<()> {
+    fn initialize_tables(conn: &rusqlite::Connection, vector_dimensions: usize) -> Result<()> {
         Self::initialize_agents_table(conn)?;
+        Self::initialize_agent_memories_table(conn)?;
         Self::initialize_cron_tasks_table(conn)?;
         Self::initialize_cron_task_executions_table(conn)?;
         Self::initialize_device_identities_table(conn)?;
@@ -178,13 +205,14 @@ impl SqliteManager {
         Self::initialize_invoice_table(conn)?;
         Self::initialize_jobs_table(conn)?;
         Self::initialize_forked_jobs_table(conn)?;
-        Self::initialize_job_queue_table(conn)?;
+        Self::initialize_job_conversation_summaries_table(conn)?;
+        Self::initialize_job_queue_tables(conn)?;
         Self::initialize_llm_providers_table(conn)?;
+        Self::initialize_llm_response_cache_table(conn)?;
         Self::initialize_local_node_keys_table(conn)?;
         Self::initialize_message_box_symmetric_keys_table(conn)?;
         Self::initialize_preferences_table(conn)?;
         Self::initialize_prompt_table(conn)?;
-        Self::initialize_prompt_vector_tables(conn)?;
         Self::initialize_registration_code_table(conn)?;
         Self::initialize_retry_messages_table(conn)?;
         Self::initialize_settings_table(conn)?;
@@ -193,30 +221,39 @@ impl SqliteManager {
         Self::initialize_tool_micropayments_requirements_table(conn)?;
         Self::initialize_tool_playground_table(conn)?;
         Self::initialize_tool_playground_code_history_table(conn)?;
+        Self::initialize_tool_active_versions_table(conn)?;
         Self::initialize_version_table(conn)?;
         Self::initialize_wallets_table(conn)?;
         Self::initialize_filesystem_tables(conn)?;
         Self::initialize_oauth_table(conn)?;
         Self::initialize_regex_patterns_table(conn)?;
         Self::initialize_tracing_table(conn)?;
+        Self::initialize_usage_tables(conn)?;
+        Self::initialize_api_keys_table(conn)?;
+        Self::initialize_secrets_tables(conn)?;
 
         // Vector tables
-        Self::initialize_tools_vector_table(conn)?;
+        Self::initialize_tools_vector_table(conn, vector_dimensions)?;
+        Self::initialize_prompt_vector_tables(conn, vector_dimensions)?;
+        Self::initialize_chunk_vec_table(conn, vector_dimensions)?;
+        Self::initialize_message_search_tables(conn, vector_dimensions)?;
+        Self::initialize_agent_memory_vec_table(conn, vector_dimensions)?;
+        Self::initialize_llm_response_cache_vec_table(conn, vector_dimensions)?;
         // Initialize the embedding model type table
         Self::initialize_embedding_model_type_table(conn)?;
+        Self::initialize_embedding_migrations_table(conn)?;
         // Initialize MCP servers table
         Self::initialize_mcp_servers_table(conn)?;
         Ok(())
     }
 
-    fn migrate_tables(conn: &rusqlite::Connection) -> Result<()> {
-        Self::migrate_agents_table(conn)?;
-        Self::migrate_llm_providers_table(conn)?;
-        Self::migrate_invoices_table(conn)?;
-        Self::migrate_tools_table(conn)?;
-        Self::migrate_invoice_requests_table(conn)?;
-        Self::migrate_mcp_servers_table(conn)?;
-        Ok(())
+    // The database file always has the .db extension
+    pub(crate) fn db_file_path<P: AsRef<Path>>(db_path: P) -> PathBuf {
+        let mut db_path = db_path.as_ref().to_path_buf();
+        if db_path.extension().and_then(|ext| ext.to_str()) != Some("db") {
+            db_path.set_extension("db");
+        }
+        db_path
     }
 
     fn migrate_agents_table(conn: &rusqlite::Connection) -> Result<()> {
@@ -611,18 +648,6 @@ impl SqliteManager {
         Ok(())
     }
 
-    fn initialize_job_queue_table(conn: &rusqlite::Connection) -> Result<()> {
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS job_queues (
-                job_id TEXT NOT NULL,
-                queue_data TEXT NOT NULL
-            );",
-            [],
-        )?;
-
-        Ok(())
-    }
-
     fn initialize_llm_providers_table(conn: &rusqlite::Connection) -> Result<()> {
         conn.execute(
             "CREATE TABLE IF NOT EXISTS llm_providers (
@@ -742,14 +767,17 @@ impl SqliteManager {
     }
 
     // New method to initialize prompt vector and associated information tables
-    fn initialize_prompt_vector_tables(conn: &rusqlite::Connection) -> Result<()> {
+    pub(crate) fn initialize_prompt_vector_tables(conn: &rusqlite::Connection, vector_dimensions: usize) -> Result<()> {
         // Create a table for prompt vector embeddings
         conn.execute(
-            "CREATE VIRTUAL TABLE IF NOT EXISTS prompt_vec_items USING vec0(
-                embedding float[384],
+            &format!(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS prompt_vec_items USING vec0(
+                embedding float[{}],
                 is_enabled integer,
                 +prompt_id integer
             )",
+                vector_dimensions
+            ),
             [],
         )?;
 
@@ -799,15 +827,18 @@ impl SqliteManager {
     }
 
     // New method to initialize the tools vector table
-    fn initialize_tools_vector_table(conn: &rusqlite::Connection) -> Result<()> {
+    pub(crate) fn initialize_tools_vector_table(conn: &rusqlite::Connection, vector_dimensions: usize) -> Result<()> {
         // Create a table for tool vector embeddings with metadata columns
         conn.execute(
-            "CREATE VIRTUAL TABLE IF NOT EXISTS zoo_tools_vec_items USING vec0(
-                embedding float[384],
+            &format!(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS zoo_tools_vec_items USING vec0(
+                embedding float[{}],
                 is_enabled integer,
                 is_network integer,
                 +tool_key text
             )",
+                vector_dimensions
+            ),
             [],
         )?;
 
@@ -1113,14 +1144,35 @@ impl SqliteManager {
         Ok(())
     }
 
-    // New method to update the embedding model type
+    // Updates the embedding model type. If the model (or the size of its vectors) changed, the
+    // vector tables are resized and a re-embedding migration is scheduled, see
+    // `run_pending_embedding_migration`.
     pub fn update_default_embedding_model(&self, model_type: EmbeddingModelType) -> Result<(), SqliteManagerError> {
-        let conn = self.get_connection()?;
-        conn.execute("DELETE FROM embedding_model_type;", [])?;
-        conn.execute(
+        let vector_dimensions = model_type
+            .vector_dimensions()
+            .map_err(|_| SqliteManagerError::UnsupportedEmbeddingModel(model_type.to_string()))?;
+        let new_model = model_type.to_string();
+
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+
+        let previous_model: Option<String> = tx
+            .query_row("SELECT model_type FROM embedding_model_type LIMIT 1;", [], |row| row.get(0))
+            .optional()?;
+        let tables_recreated = Self::ensure_vector_tables_dimensions(&tx, vector_dimensions)?;
+
+        tx.execute("DELETE FROM embedding_model_type;", [])?;
+        tx.execute(
             "INSERT INTO embedding_model_type (model_type) VALUES (?);",
-            [&model_type.to_string() as &dyn ToSql],
+            [&new_model as &dyn ToSql],
         )?;
+
+        let model_changed = previous_model.as_ref().is_some_and(|previous| *previous != new_model);
+        if model_changed || tables_recreated {
+            Self::schedule_embedding_migration(&tx, previous_model.as_deref(), &new_model)?;
+        }
+
+        tx.commit()?;
         Ok(())
     }
     pub fn migrate_mcp_servers_table(conn: &rusqlite::Connection) -> Result<()> {
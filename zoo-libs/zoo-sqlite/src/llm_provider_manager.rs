@@ -39,7 +39,9 @@ impl SqliteManager {
 
         let mut result = Vec::new();
         for llm_provider in llm_providers {
-            result.push(llm_provider?);
+            let mut llm_provider = llm_provider?;
+            llm_provider.api_key = self.open_optional_secret(llm_provider.api_key)?;
+            result.push(llm_provider);
         }
 
         Ok(result)
@@ -56,12 +58,13 @@ impl SqliteManager {
         let llm_provider_id = Self::db_llm_provider_id(&llm_provider.id, profile)?;
         let model = serde_json::to_string(&llm_provider.model)
             .map_err(|e| SqliteManagerError::SerializationError(e.to_string()))?;
+        let api_key = self.seal_optional_secret(&llm_provider.api_key)?;
         stmt.execute(params![
             &llm_provider_id,
             &llm_provider.id,
             &llm_provider.full_identity_name.full_name,
             &llm_provider.external_url,
-            &llm_provider.api_key,
+            &api_key,
             &model,
             &llm_provider.name,
             &llm_provider.description,
@@ -81,11 +84,12 @@ impl SqliteManager {
         let llm_provider_id = Self::db_llm_provider_id(&updated_llm_provider.id, profile)?;
         let model = serde_json::to_string(&updated_llm_provider.model)
             .map_err(|e| SqliteManagerError::SerializationError(e.to_string()))?;
+        let api_key = self.seal_optional_secret(&updated_llm_provider.api_key)?;
         stmt.execute(params![
             &llm_provider_id,
             &updated_llm_provider.full_identity_name.full_name,
             &updated_llm_provider.external_url,
-            &updated_llm_provider.api_key,
+            &api_key,
             &model,
             &updated_llm_provider.name,
             &updated_llm_provider.description,
@@ -148,7 +152,9 @@ impl SqliteManager {
 
         let mut result = Vec::new();
         for llm_provider in llm_providers {
-            result.push(llm_provider?);
+            let mut llm_provider = llm_provider?;
+            llm_provider.api_key = self.open_optional_secret(llm_provider.api_key)?;
+            result.push(llm_provider);
         }
 
         if result.is_empty() {
@@ -193,7 +199,9 @@ impl SqliteManager {
 
         let mut result = Vec::new();
         for llm_provider in llm_providers {
-            result.push(llm_provider?);
+            let mut llm_provider = llm_provider?;
+            llm_provider.api_key = self.open_optional_secret(llm_provider.api_key)?;
+            result.push(llm_provider);
         }
 
         Ok(result)
@@ -0,0 +1,315 @@
+use crate::{errors::SqliteManagerError, SqliteManager};
+use bytemuck::cast_slice;
+use rusqlite::{params, OptionalExtension, Result};
+
+impl SqliteManager {
+    pub fn initialize_llm_response_cache_table(conn: &rusqlite::Connection) -> Result<()> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS llm_response_cache (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                cache_key TEXT NOT NULL UNIQUE,
+                scope_key TEXT NOT NULL,
+                response TEXT NOT NULL,
+                embedding_model TEXT,
+                created_at INTEGER NOT NULL,
+                last_hit_at INTEGER NOT NULL,
+                hits INTEGER NOT NULL DEFAULT 0
+            );",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_llm_response_cache_scope_key ON llm_response_cache (scope_key);",
+            [],
+        )?;
+        Ok(())
+    }
+
+    // The rows of llm_response_cache_vec_items share their rowid with llm_response_cache. Prompts
+    // are only compared with the ones of the same scope and embedding model.
+    pub(crate) fn initialize_llm_response_cache_vec_table(
+        conn: &rusqlite::Connection,
+        vector_dimensions: usize,
+    ) -> Result<()> {
+        conn.execute(
+            &format!(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS llm_response_cache_vec_items USING vec0(
+                embedding float[{}] distance_metric=cosine,
+                scope_key text,
+                embedding_model text
+            )",
+                vector_dimensions
+            ),
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the cached response stored under `cache_key` if it is younger than `ttl_secs`.
+    pub fn get_cached_llm_response(&self, cache_key: &str, ttl_secs: u64) -> Result<Option<String>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let now = chrono::Utc::now().timestamp();
+        let entry: Option<(i64, String)> = conn
+            .query_row(
+                "SELECT id, response FROM llm_response_cache WHERE cache_key = ?1 AND created_at >= ?2",
+                params![cache_key, expiry_cutoff(now, ttl_secs)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        match entry {
+            Some((id, response)) => {
+                Self::record_llm_response_cache_hit(&conn, id, now)?;
+                Ok(Some(response))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the cached response, and its similarity, whose prompt embedding is the closest to
+    /// `embedding` within the same `scope_key`, as long as it reaches `similarity_threshold`.
+    /// Only embeddings generated with `embedding_model` are compared.
+    pub fn find_similar_cached_llm_response(
+        &self,
+        scope_key: &str,
+        embedding: &[f32],
+        embedding_model: &str,
+        similarity_threshold: f32,
+        ttl_secs: u64,
+    ) -> Result<Option<(String, f32)>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let now = chrono::Utc::now().timestamp();
+        let vector_json =
+            serde_json::to_string(embedding).map_err(|e| SqliteManagerError::SerializationError(e.to_string()))?;
+        // Expired entries are removed on every write, so one of the nearest few is still valid
+        let nearest: Vec<(i64, f32)> = {
+            let mut stmt = conn.prepare(
+                "SELECT rowid, distance FROM llm_response_cache_vec_items
+                 WHERE embedding MATCH json(?1)
+                 AND k = ?2
+                 AND scope_key = ?3
+                 AND embedding_model = ?4
+                 ORDER BY distance",
+            )?;
+            let rows = stmt
+                .query_map(
+                    params![vector_json, SEMANTIC_CANDIDATES, scope_key, embedding_model],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        let mut stmt = conn.prepare("SELECT response FROM llm_response_cache WHERE id = ?1 AND created_at >= ?2")?;
+        for (id, distance) in nearest {
+            // Cosine distance is 1 - cosine similarity
+            let similarity = 1.0 - distance;
+            if similarity < similarity_threshold {
+                break;
+            }
+            let response: Option<String> = stmt
+                .query_row(params![id, expiry_cutoff(now, ttl_secs)], |row| row.get(0))
+                .optional()?;
+            if let Some(response) = response {
+                Self::record_llm_response_cache_hit(&conn, id, now)?;
+                return Ok(Some((response, similarity)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Stores a response, replacing the previous one with the same `cache_key`. Expired entries are
+    /// removed and, if the cache still holds more than `max_entries`, the least recently used ones
+    /// are evicted.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_cached_llm_response(
+        &self,
+        cache_key: &str,
+        scope_key: &str,
+        response: &str,
+        embedding: Option<&[f32]>,
+        embedding_model: Option<&str>,
+        ttl_secs: u64,
+        max_entries: usize,
+    ) -> Result<(), SqliteManagerError> {
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+        let now = chrono::Utc::now().timestamp();
+
+        tx.execute(
+            "INSERT OR REPLACE INTO llm_response_cache
+                (cache_key, scope_key, response, embedding_model, created_at, last_hit_at, hits)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5, 0)",
+            params![cache_key, scope_key, response, embedding_model, now],
+        )?;
+        if let (Some(embedding), Some(embedding_model)) = (embedding, embedding_model) {
+            tx.execute(
+                "INSERT INTO llm_response_cache_vec_items (rowid, embedding, scope_key, embedding_model)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    tx.last_insert_rowid(),
+                    cast_slice(embedding),
+                    scope_key,
+                    embedding_model
+                ],
+            )?;
+        }
+        tx.execute(
+            "DELETE FROM llm_response_cache WHERE created_at < ?1",
+            params![expiry_cutoff(now, ttl_secs)],
+        )?;
+        tx.execute(
+            "DELETE FROM llm_response_cache WHERE id NOT IN (
+                SELECT id FROM llm_response_cache ORDER BY last_hit_at DESC, id DESC LIMIT ?1
+             )",
+            params![i64::try_from(max_entries).unwrap_or(i64::MAX)],
+        )?;
+        // Also drops the vectors of the entries replaced above
+        tx.execute(
+            "DELETE FROM llm_response_cache_vec_items WHERE rowid NOT IN (SELECT id FROM llm_response_cache)",
+            [],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Removes every cached response. Returns how many were removed.
+    pub fn clear_llm_response_cache(&self) -> Result<usize, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        conn.execute("DELETE FROM llm_response_cache_vec_items", [])?;
+        let removed = conn.execute("DELETE FROM llm_response_cache", [])?;
+        Ok(removed)
+    }
+
+    fn record_llm_response_cache_hit(conn: &rusqlite::Connection, id: i64, now: i64) -> Result<(), SqliteManagerError> {
+        conn.execute(
+            "UPDATE llm_response_cache SET hits = hits + 1, last_hit_at = ?1 WHERE id = ?2",
+            params![now, id],
+        )?;
+        Ok(())
+    }
+}
+
+/// How many of the nearest prompts a semantic lookup considers.
+const SEMANTIC_CANDIDATES: i64 = 5;
+
+/// The oldest `created_at` still valid. A TTL too large for a timestamp never expires.
+fn expiry_cutoff(now: i64, ttl_secs: u64) -> i64 {
+    now.saturating_sub(i64::try_from(ttl_secs).unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::NamedTempFile;
+    use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+
+    fn setup_test_db() -> SqliteManager {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = PathBuf::from(temp_file.path());
+        let api_url = String::new();
+        let model_type =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM);
+
+        SqliteManager::new(db_path, api_url, model_type).unwrap()
+    }
+
+    #[test]
+    fn test_exact_cache_hit_and_ttl() {
+        let db = setup_test_db();
+        db.add_cached_llm_response("key1", "scope", "{\"a\":1}", None, None, 3600, 10)
+            .unwrap();
+
+        assert_eq!(
+            db.get_cached_llm_response("key1", 3600).unwrap(),
+            Some("{\"a\":1}".to_string())
+        );
+        assert_eq!(db.get_cached_llm_response("missing", 3600).unwrap(), None);
+
+        // Pretend the entry was created two hours ago
+        let conn = db.get_connection().unwrap();
+        conn.execute(
+            "UPDATE llm_response_cache SET created_at = created_at - 7200 WHERE cache_key = 'key1'",
+            [],
+        )
+        .unwrap();
+        assert_eq!(db.get_cached_llm_response("key1", 3600).unwrap(), None);
+    }
+
+    /// A vector of the size of the test model, starting with `values`.
+    fn vector(values: &[f32]) -> Vec<f32> {
+        let mut vector = values.to_vec();
+        vector.resize(384, 0.0);
+        vector
+    }
+
+    #[test]
+    fn test_semantic_cache_hit() {
+        let db = setup_test_db();
+        let (x, y) = (vector(&[1.0, 0.0]), vector(&[0.0, 1.0]));
+        db.add_cached_llm_response("key1", "scope", "first", Some(&x), Some("model"), 3600, 10)
+            .unwrap();
+        db.add_cached_llm_response("key2", "scope", "second", Some(&y), Some("model"), 3600, 10)
+            .unwrap();
+
+        let (response, similarity) = db
+            .find_similar_cached_llm_response("scope", &vector(&[0.99, 0.1]), "model", 0.95, 3600)
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, "first");
+        assert!(similarity > 0.99);
+
+        // Below the threshold, another scope or another embedding model are misses
+        assert!(db
+            .find_similar_cached_llm_response("scope", &vector(&[0.7, 0.7]), "model", 0.95, 3600)
+            .unwrap()
+            .is_none());
+        assert!(db
+            .find_similar_cached_llm_response("other_scope", &x, "model", 0.95, 3600)
+            .unwrap()
+            .is_none());
+        assert!(db
+            .find_similar_cached_llm_response("scope", &x, "other_model", 0.95, 3600)
+            .unwrap()
+            .is_none());
+
+        // Replacing an entry replaces its vector
+        db.add_cached_llm_response("key1", "scope", "third", Some(&y), Some("model"), 3600, 10)
+            .unwrap();
+        assert!(db
+            .find_similar_cached_llm_response("scope", &x, "model", 0.95, 3600)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_ttl_larger_than_a_timestamp() {
+        let db = setup_test_db();
+        db.add_cached_llm_response("key1", "scope", "1", None, None, u64::MAX, 10)
+            .unwrap();
+        assert!(db.get_cached_llm_response("key1", u64::MAX).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let db = setup_test_db();
+        db.add_cached_llm_response("key1", "scope", "1", None, None, 3600, 2).unwrap();
+        db.add_cached_llm_response("key2", "scope", "2", None, None, 3600, 2).unwrap();
+
+        // key1 was used recently, so key2 is the one evicted
+        let conn = db.get_connection().unwrap();
+        conn.execute(
+            "UPDATE llm_response_cache SET last_hit_at = last_hit_at + 10 WHERE cache_key = 'key1'",
+            [],
+        )
+        .unwrap();
+        db.add_cached_llm_response("key3", "scope", "3", None, None, 3600, 2).unwrap();
+
+        assert!(db.get_cached_llm_response("key1", 3600).unwrap().is_some());
+        assert!(db.get_cached_llm_response("key2", 3600).unwrap().is_none());
+        assert!(db.get_cached_llm_response("key3", 3600).unwrap().is_some());
+
+        assert_eq!(db.clear_llm_response_cache().unwrap(), 2);
+    }
+}
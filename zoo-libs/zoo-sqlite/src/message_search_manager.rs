@@ -0,0 +1,689 @@
+use std::collections::HashMap;
+
+use bytemuck::cast_slice;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, params_from_iter, types::Value as SqlValue, Connection, OptionalExtension, Result};
+use zoo_message_primitives::schemas::inbox_name::InboxName;
+use zoo_message_primitives::schemas::message_search::{MessageSearchMatch, MessageSearchResult, SearchMessagesRequest};
+use zoo_message_primitives::zoo_message::zoo_message::ZooMessage;
+use zoo_message_primitives::zoo_message::zoo_message_schemas::JobMessage;
+
+use crate::{SqliteManager, SqliteManagerError};
+
+/// Preference that makes the node embed new messages, so semantic search can find them.
+pub const MESSAGE_SEARCH_EMBEDDINGS_PREFERENCE: &str = "message_search_embeddings";
+
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+const MAX_SEARCH_LIMIT: usize = 100;
+// Reciprocal rank fusion constant, keeps the first results of one ranking from outweighing the other
+const RANK_FUSION_K: f64 = 60.0;
+const SEMANTIC_SNIPPET_CHARS: usize = 200;
+const SNIPPET_MATCH_START: char = '\u{2}';
+const SNIPPET_MATCH_END: char = '\u{3}';
+
+/// The text of a message that search indexes: the content of job messages, the raw content of
+/// any other message.
+pub fn searchable_message_text(message: &ZooMessage) -> Option<String> {
+    let raw_content = message.get_message_content().ok()?;
+    let text = match serde_json::from_str::<JobMessage>(&raw_content) {
+        Ok(job_message) => job_message.content,
+        Err(_) => raw_content,
+    };
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Quotes every word of the user query, so operators or punctuation typed in the search box
+/// can't break the FTS5 syntax. A message must contain all of them, or any of them with `match_any`.
+fn fts_match_query(query: &str, match_any: bool) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .filter(|term| term.chars().any(char::is_alphanumeric))
+        .map(|term| format!("\"{}\"", term.replace('"', "")))
+        .collect();
+    let separator = if match_any { " OR " } else { " " };
+    (!terms.is_empty()).then(|| terms.join(separator))
+}
+
+/// Escapes the text of a message for the HTML of the search results.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// The FTS5 snippet marks the matches with control characters, replaced after the text is escaped
+fn highlighted_snippet(snippet: &str) -> String {
+    escape_html(snippet)
+        .replace(SNIPPET_MATCH_START, "<mark>")
+        .replace(SNIPPET_MATCH_END, "</mark>")
+}
+
+// Same format as the time keys of inbox_messages, so they compare as strings
+fn time_key_bound(value: &str) -> Result<String, SqliteManagerError> {
+    let time = DateTime::parse_from_rfc3339(value)
+        .map_err(|e| SqliteManagerError::ValidationError(format!("Invalid date {}: {}", value, e)))?;
+    Ok(time.with_timezone(&Utc).format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string())
+}
+
+/// The conditions of a search request on `inbox_messages_fts`, with their parameters, and the
+/// same conditions on the metadata columns of `inbox_message_vec_items`, so the nearest
+/// neighbours are only looked for among the matching messages.
+struct MessageSearchFilter {
+    sql: String,
+    values: Vec<SqlValue>,
+    // None when no message can match: the agent has no jobs
+    vector_sql: Option<String>,
+    vector_values: Vec<SqlValue>,
+}
+
+impl MessageSearchFilter {
+    fn from_request(conn: &Connection, request: &SearchMessagesRequest) -> Result<Self, SqliteManagerError> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        let mut vector_conditions = Vec::new();
+        let mut vector_values = Vec::new();
+        if let Some(inbox_name) = &request.inbox_name {
+            conditions.push("inbox_name = ?".to_string());
+            values.push(SqlValue::Text(inbox_name.clone()));
+        }
+        if let Some(from) = &request.from {
+            conditions.push("time_key >= ?".to_string());
+            values.push(SqlValue::Text(time_key_bound(from)?));
+        }
+        if let Some(to) = &request.to {
+            conditions.push("time_key <= ?".to_string());
+            values.push(SqlValue::Text(time_key_bound(to)?));
+        }
+        vector_conditions.extend(conditions.iter().cloned());
+        vector_values.extend(values.iter().cloned());
+
+        let mut no_match = false;
+        if let Some(agent_id) = &request.agent_id {
+            conditions.push(
+                "EXISTS (SELECT 1 FROM jobs j WHERE j.parent_agent_or_llm_provider_id = ?
+                 AND inbox_messages_fts.inbox_name LIKE 'job_inbox::' || j.job_id || '::%')"
+                    .to_string(),
+            );
+            values.push(SqlValue::Text(agent_id.clone()));
+
+            let mut stmt =
+                conn.prepare("SELECT conversation_inbox_name FROM jobs WHERE parent_agent_or_llm_provider_id = ?1")?;
+            let agent_inboxes = stmt
+                .query_map(params![agent_id], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<String>, _>>()?;
+            no_match = agent_inboxes.is_empty();
+            vector_conditions.push(format!("inbox_name IN ({})", vec!["?"; agent_inboxes.len()].join(", ")));
+            vector_values.extend(agent_inboxes.into_iter().map(SqlValue::Text));
+        }
+
+        let and_all = |conditions: &[String]| conditions.iter().map(|c| format!(" AND {}", c)).collect::<String>();
+        Ok(Self {
+            sql: and_all(&conditions),
+            values,
+            vector_sql: (!no_match).then(|| and_all(&vector_conditions)),
+            vector_values,
+        })
+    }
+}
+
+struct MessageHit {
+    message_hash: String,
+    inbox_name: String,
+    time_key: String,
+    snippet: String,
+}
+
+impl SqliteManager {
+    // Message content is indexed next to inbox_messages, which stores encoded messages
+    pub fn initialize_message_search_tables(conn: &Connection, vector_dimensions: usize) -> Result<()> {
+        conn.execute(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS inbox_messages_fts USING fts5(
+                content,
+                message_hash UNINDEXED,
+                inbox_name UNINDEXED,
+                time_key UNINDEXED,
+                tokenize = 'porter unicode61'
+            )",
+            [],
+        )?;
+        Self::initialize_inbox_message_vec_table(conn, vector_dimensions)?;
+        Ok(())
+    }
+
+    // The rows of inbox_messages_fts and inbox_message_vec_items share their rowid with inbox_messages.
+    // inbox_name and time_key are metadata columns, so the search filters apply within the KNN query.
+    pub(crate) fn initialize_inbox_message_vec_table(conn: &Connection, vector_dimensions: usize) -> Result<()> {
+        conn.execute(
+            &format!(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS inbox_message_vec_items USING vec0(
+                embedding float[{}],
+                inbox_name text,
+                time_key text,
+                +message_hash text
+            )",
+                vector_dimensions
+            ),
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Indexes the text of the inbox_messages row `message_rowid`, replacing its previous entry.
+    pub(crate) fn index_inbox_message(
+        conn: &Connection,
+        message_rowid: i64,
+        message_hash: &str,
+        inbox_name: &str,
+        time_key: &str,
+        text: &str,
+    ) -> Result<()> {
+        Self::unindex_inbox_message(conn, message_rowid)?;
+        conn.execute(
+            "INSERT INTO inbox_messages_fts (rowid, content, message_hash, inbox_name, time_key)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![message_rowid, text, message_hash, inbox_name, time_key],
+        )?;
+        Ok(())
+    }
+
+    /// Removes a message from the index, before its inbox_messages row is replaced or deleted.
+    pub(crate) fn unindex_inbox_message(conn: &Connection, message_rowid: i64) -> Result<()> {
+        conn.execute(
+            "DELETE FROM inbox_message_vec_items WHERE rowid = ?1",
+            params![message_rowid],
+        )?;
+        conn.execute(
+            "DELETE FROM inbox_messages_fts WHERE rowid = ?1",
+            params![message_rowid],
+        )?;
+        Ok(())
+    }
+
+    /// Removes the messages of an inbox from the index, before they are deleted.
+    pub(crate) fn unindex_inbox_messages(conn: &Connection, inbox_name: &str) -> Result<()> {
+        let mut stmt = conn.prepare("SELECT rowid FROM inbox_messages WHERE inbox_name = ?1")?;
+        let rowids = stmt
+            .query_map(params![inbox_name], |row| row.get::<_, i64>(0))?
+            .collect::<Result<Vec<i64>, _>>()?;
+        for rowid in rowids {
+            Self::unindex_inbox_message(conn, rowid)?;
+        }
+        Ok(())
+    }
+
+    /// Indexes the messages stored before message search existed.
+    pub(crate) fn index_existing_inbox_messages(conn: &Connection) -> Result<()> {
+        let mut stmt =
+            conn.prepare("SELECT rowid, message_hash, inbox_name, zoo_message, time_key FROM inbox_messages")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let rowid: i64 = row.get(0)?;
+            let message_hash: String = row.get(1)?;
+            let inbox_name: String = row.get(2)?;
+            let encoded_message: Vec<u8> = row.get(3)?;
+            let time_key: String = row.get(4)?;
+
+            let Ok(message) = ZooMessage::decode_message_result(encoded_message) else {
+                continue;
+            };
+            if let Some(text) = searchable_message_text(&message) {
+                Self::index_inbox_message(conn, rowid, &message_hash, &inbox_name, &time_key, &text)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn message_search_embeddings_enabled(&self) -> bool {
+        matches!(
+            self.get_preference::<bool>(MESSAGE_SEARCH_EMBEDDINGS_PREFERENCE),
+            Ok(Some(true))
+        )
+    }
+
+    /// Embeds an indexed message for semantic search, if the preference enables it. A failure
+    /// only leaves the message out of semantic results, it's still found by its text.
+    pub(crate) async fn embed_inbox_message_for_search(&self, message_hash: &str, text: &str) {
+        if !self.message_search_embeddings_enabled() {
+            return;
+        }
+        let embedding = match self.generate_embeddings(text).await {
+            Ok(embedding) => embedding,
+            Err(e) => {
+                log::warn!("Failed to embed message {} for search: {}", message_hash, e);
+                return;
+            }
+        };
+        if let Err(e) = self.store_inbox_message_embedding(message_hash, &embedding) {
+            log::warn!(
+                "Failed to store the search embedding of message {}: {}",
+                message_hash,
+                e
+            );
+        }
+    }
+
+    fn store_inbox_message_embedding(&self, message_hash: &str, embedding: &[f32]) -> Result<(), SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let message: Option<(i64, String, String)> = conn
+            .query_row(
+                "SELECT rowid, inbox_name, time_key FROM inbox_messages WHERE message_hash = ?1",
+                params![message_hash],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let Some((rowid, inbox_name, time_key)) = message else {
+            return Ok(());
+        };
+        conn.execute("DELETE FROM inbox_message_vec_items WHERE rowid = ?1", params![rowid])?;
+        conn.execute(
+            "INSERT INTO inbox_message_vec_items (rowid, embedding, inbox_name, time_key, message_hash)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![rowid, cast_slice(embedding), inbox_name, time_key, message_hash],
+        )?;
+        Ok(())
+    }
+
+    /// Embeds every indexed message again with the current model. Used by the re-embedding
+    /// migration and only when message embeddings are enabled.
+    pub(crate) async fn reembed_inbox_messages(&self) -> Result<(), SqliteManagerError> {
+        if !self.message_search_embeddings_enabled() {
+            return Ok(());
+        }
+        let messages: Vec<(String, String)> = {
+            let conn = self.get_connection()?;
+            let mut stmt = conn.prepare("SELECT message_hash, content FROM inbox_messages_fts")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        for (message_hash, content) in messages {
+            let embedding = self.generate_embeddings(&content).await?;
+            self.store_inbox_message_embedding(&message_hash, &embedding)?;
+        }
+        Ok(())
+    }
+
+    /// Searches the messages of every inbox by their words, and by meaning if the request asks
+    /// for it. Both rankings are merged, a message found by both comes first.
+    pub async fn search_messages(
+        &self,
+        request: &SearchMessagesRequest,
+    ) -> Result<Vec<MessageSearchResult>, SqliteManagerError> {
+        if request.query.trim().is_empty() {
+            return Err(SqliteManagerError::ValidationError(
+                "The search query is empty".to_string(),
+            ));
+        }
+        let limit = request.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+        let filter = MessageSearchFilter::from_request(&self.get_connection()?, request)?;
+
+        let text_hits = match fts_match_query(&request.query, request.match_any) {
+            Some(match_query) => self.text_search_messages(&match_query, &filter, limit)?,
+            None => Vec::new(),
+        };
+        let semantic_hits = if request.semantic {
+            let embedding = self.generate_embeddings(&request.query).await?;
+            self.semantic_search_messages(&embedding, &filter, limit)?
+        } else {
+            Vec::new()
+        };
+
+        let mut fused: HashMap<String, (MessageHit, f64, MessageSearchMatch)> = HashMap::new();
+        for (rank, hit) in text_hits.into_iter().enumerate() {
+            let score = 1.0 / (RANK_FUSION_K + rank as f64 + 1.0);
+            fused.insert(hit.message_hash.clone(), (hit, score, MessageSearchMatch::Text));
+        }
+        for (rank, hit) in semantic_hits.into_iter().enumerate() {
+            let score = 1.0 / (RANK_FUSION_K + rank as f64 + 1.0);
+            match fused.get_mut(&hit.message_hash) {
+                // Keep the text snippet, it has the highlights
+                Some((_, total, matched_by)) => {
+                    *total += score;
+                    *matched_by = MessageSearchMatch::Both;
+                }
+                None => {
+                    fused.insert(hit.message_hash.clone(), (hit, score, MessageSearchMatch::Semantic));
+                }
+            }
+        }
+
+        let mut ranked: Vec<(MessageHit, f64, MessageSearchMatch)> = fused.into_values().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| b.0.time_key.cmp(&a.0.time_key)));
+        ranked.truncate(limit);
+
+        let conn = self.get_connection()?;
+        let mut inbox_details: HashMap<String, (String, Option<String>, Option<String>)> = HashMap::new();
+        let mut results = Vec::with_capacity(ranked.len());
+        for (hit, score, matched_by) in ranked {
+            if !inbox_details.contains_key(&hit.inbox_name) {
+                let details = Self::search_inbox_details(&conn, &hit.inbox_name)?;
+                inbox_details.insert(hit.inbox_name.clone(), details);
+            }
+            let (inbox_title, job_id, agent_id) = inbox_details[&hit.inbox_name].clone();
+            results.push(MessageSearchResult {
+                message_hash: hit.message_hash,
+                inbox_name: hit.inbox_name,
+                inbox_title,
+                job_id,
+                agent_id,
+                time_key: hit.time_key,
+                snippet: hit.snippet,
+                score,
+                matched_by,
+            });
+        }
+        Ok(results)
+    }
+
+    fn text_search_messages(
+        &self,
+        match_query: &str,
+        filter: &MessageSearchFilter,
+        limit: usize,
+    ) -> Result<Vec<MessageHit>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT message_hash, inbox_name, time_key,
+                    snippet(inbox_messages_fts, 0, char(2), char(3), '…', 24)
+             FROM inbox_messages_fts
+             WHERE inbox_messages_fts MATCH ?{}
+             ORDER BY bm25(inbox_messages_fts)
+             LIMIT ?",
+            filter.sql
+        ))?;
+
+        let mut values = vec![SqlValue::Text(match_query.to_string())];
+        values.extend(filter.values.iter().cloned());
+        values.push(SqlValue::Integer(limit as i64));
+        let hits = stmt
+            .query_map(params_from_iter(values), |row| {
+                Ok(MessageHit {
+                    message_hash: row.get(0)?,
+                    inbox_name: row.get(1)?,
+                    time_key: row.get(2)?,
+                    snippet: highlighted_snippet(&row.get::<_, String>(3)?),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(hits)
+    }
+
+    fn semantic_search_messages(
+        &self,
+        embedding: &[f32],
+        filter: &MessageSearchFilter,
+        limit: usize,
+    ) -> Result<Vec<MessageHit>, SqliteManagerError> {
+        let Some(vector_sql) = &filter.vector_sql else {
+            return Ok(Vec::new());
+        };
+        let vector_json =
+            serde_json::to_string(embedding).map_err(|e| SqliteManagerError::SerializationError(e.to_string()))?;
+        let conn = self.get_connection()?;
+
+        let nearest: Vec<i64> = {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT rowid FROM inbox_message_vec_items
+                 WHERE embedding MATCH json(?){}
+                 ORDER BY distance
+                 LIMIT ?",
+                vector_sql
+            ))?;
+            let mut values = vec![SqlValue::Text(vector_json)];
+            values.extend(filter.vector_values.iter().cloned());
+            values.push(SqlValue::Integer(limit as i64));
+            let rows = stmt
+                .query_map(params_from_iter(values), |row| row.get(0))?
+                .collect::<Result<Vec<i64>, _>>()?;
+            rows
+        };
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT message_hash, inbox_name, time_key, substr(content, 1, {})
+             FROM inbox_messages_fts
+             WHERE rowid = ?1",
+            SEMANTIC_SNIPPET_CHARS
+        ))?;
+        let mut hits = Vec::new();
+        for rowid in nearest {
+            let hit = stmt
+                .query_row(params![rowid], |row| {
+                    Ok(MessageHit {
+                        message_hash: row.get(0)?,
+                        inbox_name: row.get(1)?,
+                        time_key: row.get(2)?,
+                        snippet: escape_html(&row.get::<_, String>(3)?),
+                    })
+                })
+                .optional()?;
+            hits.extend(hit);
+        }
+        Ok(hits)
+    }
+
+    // The title of the inbox, and its job and agent for job inboxes
+    fn search_inbox_details(
+        conn: &Connection,
+        inbox_name: &str,
+    ) -> Result<(String, Option<String>, Option<String>), SqliteManagerError> {
+        let title: Option<String> = conn
+            .query_row(
+                "SELECT smart_inbox_name FROM inboxes WHERE inbox_name = ?1",
+                params![inbox_name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let job_id = match InboxName::new(inbox_name.to_string()) {
+            Ok(InboxName::JobInbox { unique_id, .. }) => Some(unique_id),
+            _ => None,
+        };
+        let agent_id = match &job_id {
+            Some(job_id) => conn
+                .query_row(
+                    "SELECT parent_agent_or_llm_provider_id FROM jobs WHERE job_id = ?1",
+                    params![job_id],
+                    |row| row.get(0),
+                )
+                .optional()?,
+            None => None,
+        };
+        Ok((title.unwrap_or_else(|| inbox_name.to_string()), job_id, agent_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+    use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+
+    fn setup_test_db() -> SqliteManager {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = temp_file.path().to_path_buf();
+        let api_url = String::new();
+        let model_type =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM);
+
+        SqliteManager::new(db_path, api_url, model_type).unwrap()
+    }
+
+    fn index(db: &SqliteManager, hash: &str, inbox_name: &str, time_key: &str, text: &str) {
+        let conn = db.get_connection().unwrap();
+        conn.execute(
+            "INSERT INTO inbox_messages (message_hash, inbox_name, zoo_message, time_key) VALUES (?1, ?2, x'00', ?3)",
+            params![hash, inbox_name, time_key],
+        )
+        .unwrap();
+        let rowid = conn.last_insert_rowid();
+        SqliteManager::index_inbox_message(&conn, rowid, hash, inbox_name, time_key, text).unwrap();
+    }
+
+    fn search(query: &str) -> SearchMessagesRequest {
+        SearchMessagesRequest {
+            query: query.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fts_match_query() {
+        assert_eq!(
+            fts_match_query("rust  borrow-checker", false),
+            Some("\"rust\" \"borrow-checker\"".to_string())
+        );
+        assert_eq!(
+            fts_match_query("say \"hi\" OR", false),
+            Some("\"say\" \"hi\" \"OR\"".to_string())
+        );
+        assert_eq!(
+            fts_match_query("rust borrow", true),
+            Some("\"rust\" OR \"borrow\"".to_string())
+        );
+        assert_eq!(fts_match_query(" ?! ", false), None);
+    }
+
+    #[tokio::test]
+    async fn test_search_messages_with_filters() {
+        let db = setup_test_db();
+        let job_inbox = "job_inbox::job_1::false";
+        db.create_empty_inbox(job_inbox.to_string(), None).unwrap();
+        db.execute(
+            "INSERT INTO jobs (job_id, is_hidden, datetime_created, is_finished, parent_agent_or_llm_provider_id,
+                scope, conversation_inbox_name)
+             VALUES ('job_1', 0, '2024-01-01T00:00:00.000Z', 0, 'research_agent', '{}', ?1)",
+            &[&job_inbox],
+        )
+        .unwrap();
+
+        index(
+            &db,
+            "hash_1",
+            job_inbox,
+            "2024-03-01T10:00:00.000Z",
+            "Rust lifetimes explained with examples",
+        );
+        index(
+            &db,
+            "hash_2",
+            job_inbox,
+            "2024-05-01T10:00:00.000Z",
+            "Cooking pasta for dinner",
+        );
+        index(
+            &db,
+            "hash_3",
+            "inbox::other::false",
+            "2024-06-01T10:00:00.000Z",
+            "More about lifetimes in Rust",
+        );
+
+        let results = db.search_messages(&search("rust lifetime")).await.unwrap();
+        let hashes: Vec<&str> = results.iter().map(|r| r.message_hash.as_str()).collect();
+        assert_eq!(hashes.len(), 2);
+        assert!(hashes.contains(&"hash_1") && hashes.contains(&"hash_3"));
+        assert!(results.iter().all(|r| r.snippet.contains("<mark>")));
+        assert!(results.iter().all(|r| r.matched_by == MessageSearchMatch::Text));
+
+        let by_agent = SearchMessagesRequest {
+            agent_id: Some("research_agent".to_string()),
+            ..search("rust")
+        };
+        let results = db.search_messages(&by_agent).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].message_hash, "hash_1");
+        assert_eq!(results[0].job_id.as_deref(), Some("job_1"));
+        assert_eq!(results[0].agent_id.as_deref(), Some("research_agent"));
+
+        let by_date = SearchMessagesRequest {
+            from: Some("2024-04-01T00:00:00+00:00".to_string()),
+            ..search("rust")
+        };
+        let results = db.search_messages(&by_date).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].message_hash, "hash_3");
+
+        let by_inbox = SearchMessagesRequest {
+            inbox_name: Some(job_inbox.to_string()),
+            ..search("pasta")
+        };
+        assert_eq!(db.search_messages(&by_inbox).await.unwrap().len(), 1);
+
+        let any_word = SearchMessagesRequest {
+            match_any: true,
+            ..search("pasta lifetimes")
+        };
+        assert_eq!(db.search_messages(&any_word).await.unwrap().len(), 3);
+        assert!(db.search_messages(&search("pasta lifetimes")).await.unwrap().is_empty());
+
+        // Clearing the inbox removes its messages from the index
+        db.clear_inbox_messages(job_inbox).unwrap();
+        assert!(db.search_messages(&by_inbox).await.unwrap().is_empty());
+        assert!(db.search_messages(&search("  ")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_snippets_are_escaped() {
+        let db = setup_test_db();
+        index(
+            &db,
+            "hash_1",
+            "inbox::a::false",
+            "2024-03-01T10:00:00.000Z",
+            "Rust <script>alert('x')</script> & more",
+        );
+
+        let results = db.search_messages(&search("rust")).await.unwrap();
+        assert_eq!(
+            results[0].snippet,
+            "<mark>Rust</mark> &lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; &amp; more"
+        );
+    }
+
+    #[test]
+    fn test_semantic_search_filters_within_the_nearest_messages() {
+        let db = setup_test_db();
+        let vector = |values: &[f32]| {
+            let mut vector = values.to_vec();
+            vector.resize(384, 0.0);
+            vector
+        };
+        index(&db, "hash_1", "inbox::a::false", "2024-03-01T10:00:00.000Z", "Near");
+        index(&db, "hash_2", "inbox::b::false", "2024-03-01T10:00:00.000Z", "Far");
+        db.store_inbox_message_embedding("hash_1", &vector(&[1.0, 0.0]))
+            .unwrap();
+        db.store_inbox_message_embedding("hash_2", &vector(&[0.0, 1.0]))
+            .unwrap();
+
+        let conn = db.get_connection().unwrap();
+        let request = SearchMessagesRequest {
+            inbox_name: Some("inbox::b::false".to_string()),
+            ..search("near")
+        };
+        let filter = MessageSearchFilter::from_request(&conn, &request).unwrap();
+        let hits = db.semantic_search_messages(&vector(&[1.0, 0.0]), &filter, 1).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message_hash, "hash_2");
+
+        // An agent without jobs has no messages
+        let request = SearchMessagesRequest {
+            agent_id: Some("no_jobs_agent".to_string()),
+            ..search("near")
+        };
+        let filter = MessageSearchFilter::from_request(&conn, &request).unwrap();
+        assert!(db
+            .semantic_search_messages(&vector(&[1.0, 0.0]), &filter, 1)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -45,6 +45,28 @@ pub struct OAuthToken {
 }
 
 impl SqliteManager {
+    /// A copy of `token` with its credentials encrypted, to be stored.
+    fn seal_oauth_token(&self, token: &OAuthToken) -> Result<OAuthToken, SqliteManagerError> {
+        let mut sealed = token.clone();
+        sealed.access_token = self.seal_optional_secret(&token.access_token)?;
+        sealed.refresh_token = self.seal_optional_secret(&token.refresh_token)?;
+        sealed.token_secret = self.seal_optional_secret(&token.token_secret)?;
+        sealed.id_token = self.seal_optional_secret(&token.id_token)?;
+        sealed.client_secret = self.seal_optional_secret(&token.client_secret)?;
+        sealed.pkce_code_verifier = self.seal_optional_secret(&token.pkce_code_verifier)?;
+        Ok(sealed)
+    }
+
+    fn open_oauth_token(&self, mut token: OAuthToken) -> Result<OAuthToken, SqliteManagerError> {
+        token.access_token = self.open_optional_secret(token.access_token)?;
+        token.refresh_token = self.open_optional_secret(token.refresh_token)?;
+        token.token_secret = self.open_optional_secret(token.token_secret)?;
+        token.id_token = self.open_optional_secret(token.id_token)?;
+        token.client_secret = self.open_optional_secret(token.client_secret)?;
+        token.pkce_code_verifier = self.open_optional_secret(token.pkce_code_verifier)?;
+        Ok(token)
+    }
+
     pub fn add_oauth_token(&self, token: &OAuthToken) -> Result<i64, SqliteManagerError> {
         let mut conn = self.get_connection()?;
         let tx = conn.transaction()?;
@@ -69,6 +91,7 @@ impl SqliteManager {
         if token.token_url.clone().unwrap_or_default().is_empty() {
             return Err(SqliteManagerError::MissingValue("Token URL is empty".to_string()));
         }
+        let token = &self.seal_oauth_token(token)?;
 
         tx.execute(
             "INSERT INTO oauth_tokens (
@@ -145,7 +168,7 @@ impl SqliteManager {
         let mut rows = stmt.query(params![connection_name, tool_key])?;
 
         if let Some(row) = rows.next()? {
-            Ok(Some(OAuthToken {
+            Ok(Some(self.open_oauth_token(OAuthToken {
                 id: row.get(0)?,
                 connection_name: row.get(1)?,
                 state: row.get(2)?,
@@ -186,13 +209,14 @@ impl SqliteManager {
                     .with_timezone(&Utc),
                 request_token_auth_header: row.get(28)?,
                 request_token_content_type: row.get(29)?,
-            }))
+            })?))
         } else {
             Ok(None)
         }
     }
 
     pub fn update_oauth_token(&self, token: &OAuthToken) -> Result<(), SqliteManagerError> {
+        let token = &self.seal_oauth_token(token)?;
         let mut conn = self.get_connection()?;
         let tx = conn.transaction()?;
 
@@ -321,7 +345,10 @@ impl SqliteManager {
 
         token_iter
             .collect::<Result<Vec<_>, _>>()
-            .map_err(SqliteManagerError::DatabaseError)
+            .map_err(SqliteManagerError::DatabaseError)?
+            .into_iter()
+            .map(|token| self.open_oauth_token(token))
+            .collect()
     }
 
     pub fn get_oauth_token_by_state(&self, state: &str) -> Result<Option<OAuthToken>, SqliteManagerError> {
@@ -340,7 +367,7 @@ impl SqliteManager {
         let mut rows = stmt.query(params![state])?;
 
         if let Some(row) = rows.next()? {
-            Ok(Some(OAuthToken {
+            Ok(Some(self.open_oauth_token(OAuthToken {
                 id: row.get(0)?,
                 connection_name: row.get(1)?,
                 state: row.get(2)?,
@@ -381,7 +408,7 @@ impl SqliteManager {
                     .with_timezone(&Utc),
                 request_token_auth_header: row.get(28)?,
                 request_token_content_type: row.get(29)?,
-            }))
+            })?))
         } else {
             Ok(None)
         }
@@ -0,0 +1,607 @@
+use crate::{SqliteManager, SqliteManagerError};
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::path::{Path, PathBuf};
+
+/// Pre-migration backups kept next to the database, the oldest ones are removed.
+const KEPT_MIGRATION_BACKUPS: usize = 3;
+
+pub enum MigrationStep {
+    Sql(&'static str),
+    /// Conditional changes SQL alone can't express, like rebuilding a table only if its
+    /// definition is outdated. The description is what the dry run prints.
+    ///
+    /// The checksum can't cover the code: it covers the description and `revision`, which must be
+    /// bumped whenever `run` changes.
+    Code {
+        description: &'static str,
+        revision: u32,
+        run: fn(&Connection) -> rusqlite::Result<()>,
+    },
+}
+
+/// A numbered schema change. Applied migrations are recorded in `schema_migrations` with the
+/// checksum of their step, so never edit a released migration: add a new one instead.
+///
+/// Migrations change tables that already exist. New tables go in `initialize_tables` with the
+/// latest schema, a fresh database records every migration without running it.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub up: MigrationStep,
+    /// SQL that undoes `up`, if it can be undone.
+    pub down: Option<&'static str>,
+}
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        let content = match &self.up {
+            MigrationStep::Sql(sql) => sql.to_string(),
+            MigrationStep::Code {
+                description, revision, ..
+            } => format!("{}\nrevision {}", description, revision),
+        };
+        blake3::hash(content.as_bytes()).to_hex().to_string()
+    }
+
+    /// The SQL the migration runs, or a comment describing it for code migrations.
+    pub fn preview(&self) -> String {
+        match &self.up {
+            MigrationStep::Sql(sql) => sql.trim().to_string(),
+            MigrationStep::Code { description, .. } => format!("-- {}", description),
+        }
+    }
+}
+
+/// The migrations of the node database, in order. Versions start at 1 and never repeat.
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "agents_tools_config_override_and_edited",
+        up: MigrationStep::Code {
+            description: "Add the tools_config_override and edited columns to zoo_agents if they are missing",
+            revision: 1,
+            run: SqliteManager::migrate_agents_table,
+        },
+        down: None,
+    },
+    Migration {
+        version: 2,
+        name: "llm_providers_name_and_description",
+        up: MigrationStep::Code {
+            description: "Add the name and description columns to llm_providers if they are missing",
+            revision: 1,
+            run: SqliteManager::migrate_llm_providers_table,
+        },
+        down: None,
+    },
+    Migration {
+        version: 3,
+        name: "invoices_nullable_offering_key",
+        up: MigrationStep::Code {
+            description: "Rebuild invoices with a nullable zoo_offering_key and add parent_message_id if it is missing",
+            revision: 1,
+            run: SqliteManager::migrate_invoices_table,
+        },
+        down: None,
+    },
+    Migration {
+        version: 4,
+        name: "zoo_tools_mcp_enabled",
+        up: MigrationStep::Code {
+            description: "Add the mcp_enabled column to zoo_tools if it is missing",
+            revision: 1,
+            run: SqliteManager::migrate_tools_table,
+        },
+        down: None,
+    },
+    Migration {
+        version: 5,
+        name: "invoice_requests_parent_message_id",
+        up: MigrationStep::Code {
+            description: "Add parent_message_id to invoice_requests and rebuild it without secret_prehash",
+            revision: 1,
+            run: SqliteManager::migrate_invoice_requests_table,
+        },
+        down: None,
+    },
+    Migration {
+        version: 6,
+        name: "mcp_servers_http_type",
+        up: MigrationStep::Code {
+            description: "Rebuild mcp_servers so its type accepts HTTP",
+            revision: 1,
+            run: SqliteManager::migrate_mcp_servers_table,
+        },
+        down: None,
+    },
+    Migration {
+        version: 7,
+        name: "index_inbox_messages_for_search",
+        up: MigrationStep::Code {
+            description: "Index the text of the existing inbox messages in inbox_messages_fts",
+            revision: 1,
+            run: SqliteManager::index_existing_inbox_messages,
+        },
+        down: Some("DELETE FROM inbox_messages_fts;"),
+    },
+    Migration {
+        version: 8,
+        name: "agent_knowledge_memories",
+        up: MigrationStep::Code {
+            description: "Turn the knowledge of every stored agent into agent memories",
+            revision: 1,
+            run: SqliteManager::sync_all_agent_knowledge_memories,
+        },
+        down: None,
+    },
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: String,
+}
+
+pub fn latest_migration_version(migrations: &[Migration]) -> u32 {
+    migrations.iter().map(|m| m.version).max().unwrap_or(0)
+}
+
+impl SqliteManager {
+    pub(crate) fn initialize_schema_migrations_table(conn: &Connection) -> rusqlite::Result<()> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (
+                version INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                checksum TEXT NOT NULL,
+                applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// True if the database has no tables yet, before `initialize_tables` creates them.
+    pub(crate) fn is_new_database(conn: &Connection) -> rusqlite::Result<bool> {
+        let tables: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(tables == 0)
+    }
+
+    fn read_applied_migrations(conn: &Connection) -> Result<Vec<AppliedMigration>, SqliteManagerError> {
+        let table_exists = conn
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'",
+                [],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if !table_exists {
+            return Ok(Vec::new());
+        }
+
+        let mut stmt =
+            conn.prepare("SELECT version, name, checksum, applied_at FROM schema_migrations ORDER BY version")?;
+        let applied = stmt
+            .query_map([], |row| {
+                Ok(AppliedMigration {
+                    version: row.get(0)?,
+                    name: row.get(1)?,
+                    checksum: row.get(2)?,
+                    applied_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(applied)
+    }
+
+    /// Refuses a database migrated by a newer binary, or whose applied migrations were changed
+    /// since they ran. Runs before anything touches the schema.
+    pub(crate) fn check_schema_version(conn: &Connection, migrations: &[Migration]) -> Result<(), SqliteManagerError> {
+        let applied = Self::read_applied_migrations(conn)?;
+        let supported = latest_migration_version(migrations);
+        if let Some(newest) = applied.iter().map(|m| m.version).max() {
+            if newest > supported {
+                return Err(SqliteManagerError::SchemaTooNew {
+                    database: newest,
+                    supported,
+                });
+            }
+        }
+
+        for applied in &applied {
+            let Some(migration) = migrations.iter().find(|m| m.version == applied.version) else {
+                continue;
+            };
+            if migration.checksum() != applied.checksum {
+                return Err(SqliteManagerError::MigrationChecksumMismatch {
+                    version: applied.version,
+                    name: applied.name.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn pending_migrations<'a>(
+        conn: &Connection,
+        migrations: &'a [Migration],
+    ) -> Result<Vec<&'a Migration>, SqliteManagerError> {
+        let applied = Self::read_applied_migrations(conn)?;
+        let mut pending: Vec<&Migration> = migrations
+            .iter()
+            .filter(|m| !applied.iter().any(|a| a.version == m.version))
+            .collect();
+        pending.sort_by_key(|m| m.version);
+        Ok(pending)
+    }
+
+    /// Applies the pending migrations, each one in its own transaction together with its
+    /// `schema_migrations` record, so a failing migration leaves the database as it was.
+    /// A new database already has the latest schema, its migrations are only recorded.
+    /// An existing database is backed up next to `db_path` before the first migration runs.
+    pub(crate) fn run_migrations(
+        conn: &Connection,
+        migrations: &[Migration],
+        db_path: &Path,
+        new_database: bool,
+    ) -> Result<(), SqliteManagerError> {
+        Self::initialize_schema_migrations_table(conn)?;
+        let pending = Self::pending_migrations(conn, migrations)?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        if new_database {
+            let tx = conn.unchecked_transaction()?;
+            for migration in &pending {
+                Self::record_migration(&tx, migration)?;
+            }
+            tx.commit()?;
+            return Ok(());
+        }
+
+        let current = Self::read_applied_migrations(conn)?
+            .iter()
+            .map(|m| m.version)
+            .max()
+            .unwrap_or(0);
+        let backup = Self::backup_before_migration(conn, db_path, current)?;
+        info!("Backed up the database to {} before migrating it", backup.display());
+
+        for migration in pending {
+            info!("Applying migration {} ({})", migration.version, migration.name);
+            let failed = |e: rusqlite::Error| SqliteManagerError::MigrationFailed {
+                version: migration.version,
+                name: migration.name.to_string(),
+                message: e.to_string(),
+            };
+
+            let tx = conn.unchecked_transaction()?;
+            match &migration.up {
+                MigrationStep::Sql(sql) => tx.execute_batch(sql).map_err(failed)?,
+                MigrationStep::Code { run, .. } => run(&tx).map_err(failed)?,
+            }
+            Self::record_migration(&tx, migration)?;
+            tx.commit().map_err(failed)?;
+        }
+        Ok(())
+    }
+
+    fn record_migration(conn: &Connection, migration: &Migration) -> rusqlite::Result<()> {
+        conn.execute(
+            "INSERT INTO schema_migrations (version, name, checksum, applied_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                migration.version,
+                migration.name,
+                migration.checksum(),
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Copies the database to `<name>.pre-migration-<timestamp>-v<version>.bak` and keeps the
+    /// last few of these copies.
+    fn backup_before_migration(conn: &Connection, db_path: &Path, version: u32) -> Result<PathBuf, SqliteManagerError> {
+        let file_name = db_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| SqliteManagerError::SomeError(format!("Invalid database path: {}", db_path.display())))?;
+        let prefix = format!("{}.pre-migration-", file_name);
+        let backup_path = db_path.with_file_name(format!(
+            "{}{}-v{}.bak",
+            prefix,
+            Utc::now().format("%Y%m%dT%H%M%S%3f"),
+            version
+        ));
+
+        conn.execute("VACUUM INTO ?1", params![backup_path.to_string_lossy()])?;
+
+        // The timestamp comes first, so the names sort from oldest to newest
+        if let Some(dir) = backup_path.parent() {
+            let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                        .filter(|path| {
+                            path.file_name()
+                                .and_then(|name| name.to_str())
+                                .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".bak"))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            backups.sort();
+            let excess = backups.len().saturating_sub(KEPT_MIGRATION_BACKUPS);
+            for old_backup in backups.into_iter().take(excess) {
+                let _ = std::fs::remove_file(old_backup);
+            }
+        }
+        Ok(backup_path)
+    }
+
+    pub fn get_applied_migrations(&self) -> Result<Vec<AppliedMigration>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        Self::read_applied_migrations(&conn)
+    }
+
+    /// Undoes the applied migrations above `target_version`, newest first. Stops at the first
+    /// migration without a `down` step, after undoing the ones above it.
+    pub fn rollback_migrations(&self, target_version: u32) -> Result<Vec<u32>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        Self::rollback_migrations_on(&conn, MIGRATIONS, target_version)
+    }
+
+    /// Undoes the migrations of the database at `db_path` above `target_version`, without opening
+    /// it as a node database (which would apply the pending migrations first). The database is
+    /// backed up before anything is undone.
+    pub fn rollback_database_migrations<P: AsRef<Path>>(
+        db_path: P,
+        target_version: u32,
+    ) -> Result<Vec<u32>, SqliteManagerError> {
+        let db_path = Self::db_file_path(db_path);
+        if !db_path.exists() {
+            return Err(SqliteManagerError::SomeError(format!(
+                "{} doesn't exist",
+                db_path.display()
+            )));
+        }
+
+        let conn = Connection::open(&db_path)?;
+        Self::check_schema_version(&conn, MIGRATIONS)?;
+        let current = Self::read_applied_migrations(&conn)?
+            .iter()
+            .map(|m| m.version)
+            .max()
+            .unwrap_or(0);
+        if current <= target_version {
+            return Ok(Vec::new());
+        }
+        let backup = Self::backup_before_migration(&conn, &db_path, current)?;
+        info!("Backed up the database to {} before rolling it back", backup.display());
+        Self::rollback_migrations_on(&conn, MIGRATIONS, target_version)
+    }
+
+    fn rollback_migrations_on(
+        conn: &Connection,
+        migrations: &[Migration],
+        target_version: u32,
+    ) -> Result<Vec<u32>, SqliteManagerError> {
+        let mut applied = Self::read_applied_migrations(conn)?;
+        applied.retain(|m| m.version > target_version);
+        applied.sort_by_key(|m| std::cmp::Reverse(m.version));
+
+        let mut rolled_back = Vec::new();
+        for applied in applied {
+            let down = migrations
+                .iter()
+                .find(|m| m.version == applied.version)
+                .and_then(|m| m.down)
+                .ok_or(SqliteManagerError::IrreversibleMigration(applied.version))?;
+
+            let tx = conn.unchecked_transaction()?;
+            tx.execute_batch(down)
+                .map_err(|e| SqliteManagerError::MigrationFailed {
+                    version: applied.version,
+                    name: applied.name.clone(),
+                    message: e.to_string(),
+                })?;
+            tx.execute(
+                "DELETE FROM schema_migrations WHERE version = ?1",
+                params![applied.version],
+            )?;
+            tx.commit()?;
+            rolled_back.push(applied.version);
+        }
+        Ok(rolled_back)
+    }
+
+    /// What opening the database at `db_path` would migrate, without changing it.
+    pub fn migration_dry_run<P: AsRef<Path>>(db_path: P) -> Result<String, SqliteManagerError> {
+        let db_path = Self::db_file_path(db_path);
+        let latest = latest_migration_version(MIGRATIONS);
+        if !db_path.exists() {
+            return Ok(format!(
+                "{} doesn't exist yet: it will be created with the latest schema (version {}), no migration runs.",
+                db_path.display(),
+                latest
+            ));
+        }
+
+        let conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Self::check_schema_version(&conn, MIGRATIONS)?;
+        let pending = Self::pending_migrations(&conn, MIGRATIONS)?;
+        if pending.is_empty() {
+            return Ok(format!(
+                "{} is up to date (schema version {}).",
+                db_path.display(),
+                latest
+            ));
+        }
+
+        let mut report = format!(
+            "{} has {} pending migration(s), a backup is taken before they run:\n",
+            db_path.display(),
+            pending.len()
+        );
+        for migration in pending {
+            report.push_str(&format!(
+                "\n-- Migration {}: {}\n{}\n",
+                migration.version,
+                migration.name,
+                migration.preview()
+            ));
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    static TEST_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            name: "notes_title",
+            up: MigrationStep::Sql("ALTER TABLE notes ADD COLUMN title TEXT;"),
+            down: Some("ALTER TABLE notes DROP COLUMN title;"),
+        },
+        Migration {
+            version: 2,
+            name: "notes_broken",
+            up: MigrationStep::Sql(
+                "ALTER TABLE notes ADD COLUMN pinned INTEGER; INSERT INTO missing_table VALUES (1);",
+            ),
+            down: None,
+        },
+    ];
+
+    fn setup_db(dir: &TempDir) -> (Connection, PathBuf) {
+        let db_path = dir.path().join("test.db");
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)", [])
+            .unwrap();
+        (conn, db_path)
+    }
+
+    fn columns(conn: &Connection) -> Vec<String> {
+        conn.prepare("SELECT name FROM pragma_table_info('notes')")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<String>, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_failed_migration_is_rolled_back_and_recorded_ones_stay() {
+        let dir = TempDir::new().unwrap();
+        let (conn, db_path) = setup_db(&dir);
+
+        let result = SqliteManager::run_migrations(&conn, TEST_MIGRATIONS, &db_path, false);
+        assert!(matches!(
+            result,
+            Err(SqliteManagerError::MigrationFailed { version: 2, .. })
+        ));
+
+        // The first migration is applied and recorded, none of the second one is
+        assert_eq!(columns(&conn), vec!["id", "body", "title"]);
+        let applied = SqliteManager::read_applied_migrations(&conn).unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].version, 1);
+        assert_eq!(applied[0].checksum, TEST_MIGRATIONS[0].checksum());
+
+        // The database was backed up before migrating
+        let backups: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with("test.db.pre-migration-"))
+            .collect();
+        assert_eq!(backups.len(), 1);
+        let backup = Connection::open(backups[0].path()).unwrap();
+        assert_eq!(columns(&backup), vec!["id", "body"]);
+
+        // Rolling back undoes the first migration
+        let rolled_back = SqliteManager::rollback_migrations_on(&conn, TEST_MIGRATIONS, 0).unwrap();
+        assert_eq!(rolled_back, vec![1]);
+        assert_eq!(columns(&conn), vec!["id", "body"]);
+        assert!(SqliteManager::read_applied_migrations(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_new_database_records_migrations_without_running_them() {
+        let dir = TempDir::new().unwrap();
+        let (conn, db_path) = setup_db(&dir);
+
+        SqliteManager::run_migrations(&conn, TEST_MIGRATIONS, &db_path, true).unwrap();
+        assert_eq!(columns(&conn), vec!["id", "body"]);
+        assert_eq!(SqliteManager::read_applied_migrations(&conn).unwrap().len(), 2);
+        assert!(SqliteManager::check_schema_version(&conn, TEST_MIGRATIONS).is_ok());
+
+        // A binary that only knows the first migration refuses the database
+        assert!(matches!(
+            SqliteManager::check_schema_version(&conn, &TEST_MIGRATIONS[..1]),
+            Err(SqliteManagerError::SchemaTooNew {
+                database: 2,
+                supported: 1
+            })
+        ));
+        // An edited migration is detected
+        conn.execute("UPDATE schema_migrations SET checksum = 'edited' WHERE version = 1", [])
+            .unwrap();
+        assert!(matches!(
+            SqliteManager::check_schema_version(&conn, TEST_MIGRATIONS),
+            Err(SqliteManagerError::MigrationChecksumMismatch { version: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_rollback_database_migrations() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("main_db");
+        assert!(SqliteManager::rollback_database_migrations(&db_path, 0).is_err());
+
+        let conn = Connection::open(dir.path().join("main_db.db")).unwrap();
+        SqliteManager::run_migrations(&conn, MIGRATIONS, &db_path, true).unwrap();
+        drop(conn);
+
+        let latest = latest_migration_version(MIGRATIONS);
+        assert!(SqliteManager::rollback_database_migrations(&db_path, latest)
+            .unwrap()
+            .is_empty());
+        // The newest migration can't be undone on this database, so nothing is
+        assert!(SqliteManager::rollback_database_migrations(&db_path, 0).is_err());
+        let conn = Connection::open(dir.path().join("main_db.db")).unwrap();
+        assert_eq!(
+            SqliteManager::read_applied_migrations(&conn).unwrap().len(),
+            MIGRATIONS.len()
+        );
+    }
+
+    #[test]
+    fn test_migration_dry_run() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("main_db");
+        let report = SqliteManager::migration_dry_run(&db_path).unwrap();
+        assert!(report.contains("doesn't exist yet"));
+
+        // A database from before the migrations were tracked
+        let conn = Connection::open(dir.path().join("main_db.db")).unwrap();
+        conn.execute("CREATE TABLE zoo_agents (agent_id TEXT)", []).unwrap();
+        drop(conn);
+
+        let report = SqliteManager::migration_dry_run(&db_path).unwrap();
+        assert!(report.contains(&format!("{} pending migration(s)", MIGRATIONS.len())));
+        assert!(report.contains("-- Migration 6: mcp_servers_http_type"));
+    }
+}
@@ -0,0 +1,699 @@
+use chrono::{SecondsFormat, Utc};
+use rusqlite::{params, OptionalExtension, Result};
+use serde_json::Value;
+use zoo_message_primitives::schemas::secrets::{
+    is_valid_secret_name, ExportedSecret, SecretInfo, SecretLocation, SecretsExport, SecretsKeyInfo,
+    SecretsKeySourceKind, SetSecretRequest,
+};
+use zoo_tools_primitives::tools::{
+    tool_config::ToolConfig,
+    zoo_tool::{ZooTool, ZooToolHeader},
+};
+
+use crate::secrets_vault::{random_salt, SecretsKeySource, SecretsVault, SEALED_SECRET_PREFIX};
+use crate::{errors::SqliteManagerError, SqliteManager};
+
+/// The `oauth_tokens` columns that hold credentials.
+const OAUTH_SECRET_COLUMNS: [&str; 6] = [
+    "access_token",
+    "refresh_token",
+    "token_secret",
+    "id_token",
+    "client_secret",
+    "pkce_code_verifier",
+];
+
+fn secrets_timestamp() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn sealed_key_id(value: &str) -> Option<&str> {
+    value
+        .strip_prefix(SEALED_SECRET_PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+        .map(|(key_id, _)| key_id)
+}
+
+/// `value` encrypted with `new`, or None if it already is. Values sealed with another key are
+/// opened with `old` first.
+fn reseal_value(
+    value: &str,
+    old: Option<&SecretsVault>,
+    new: &SecretsVault,
+) -> Result<Option<String>, SqliteManagerError> {
+    if !SecretsVault::is_sealed(value) {
+        return new.seal(value).map(Some);
+    }
+    if sealed_key_id(value) == Some(new.key_id()) {
+        return Ok(None);
+    }
+    let old = old.ok_or(SqliteManagerError::SecretsVaultLocked)?;
+    new.seal(&old.open(value)?).map(Some)
+}
+
+/// Tool config values can be any JSON value, they are sealed as their JSON text.
+fn reseal_config_value(
+    value: &Value,
+    old: Option<&SecretsVault>,
+    new: &SecretsVault,
+) -> Result<Option<Value>, SqliteManagerError> {
+    match value {
+        Value::Null => Ok(None),
+        Value::String(s) if SecretsVault::is_sealed(s) => Ok(reseal_value(s, old, new)?.map(Value::String)),
+        plain => Ok(Some(Value::String(new.seal(&serde_json::to_string(plain)?)?))),
+    }
+}
+
+/// The plaintext of a config value that isn't a credential but was sealed, or None if it's plain.
+fn unseal_config_value(
+    value: &Value,
+    old: Option<&SecretsVault>,
+    new: &SecretsVault,
+) -> Result<Option<Value>, SqliteManagerError> {
+    match value {
+        Value::String(s) if SecretsVault::is_sealed(s) => {
+            let vault = match sealed_key_id(s) == Some(new.key_id()) {
+                true => new,
+                false => old.ok_or(SqliteManagerError::SecretsVaultLocked)?,
+            };
+            Ok(Some(serde_json::from_str(&vault.open(s)?)?))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Reseals the credentials of `configs`, other values are stored in plaintext. Returns whether
+/// anything changed.
+fn reseal_tool_configs(
+    configs: &mut [ToolConfig],
+    old: Option<&SecretsVault>,
+    new: &SecretsVault,
+) -> Result<bool, SqliteManagerError> {
+    let mut changed = false;
+    for config in configs.iter_mut() {
+        let ToolConfig::BasicConfig(basic_config) = config;
+        if let Some(value) = &basic_config.key_value {
+            let resealed = match basic_config.is_secret() {
+                true => reseal_config_value(value, old, new)?,
+                false => unseal_config_value(value, old, new)?,
+            };
+            if let Some(resealed) = resealed {
+                basic_config.key_value = Some(resealed);
+                changed = true;
+            }
+        }
+    }
+    Ok(changed)
+}
+
+impl SqliteManager {
+    pub fn initialize_secrets_tables(conn: &rusqlite::Connection) -> Result<()> {
+        // The data key that encrypts the secrets, wrapped by a key derived from the node identity
+        // or a passphrase. There's only one at a time.
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS secrets_keys (
+                key_id TEXT PRIMARY KEY,
+                key_source TEXT NOT NULL,
+                salt TEXT NOT NULL,
+                wrapped_key TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS secrets (
+                name TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                description TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Unlocks the secrets vault with the node identity or passphrase, creating its data key the
+    /// first time. Credentials stored in plaintext (before the vault existed or while it was
+    /// locked) are encrypted right away.
+    pub fn unlock_secrets_vault(&self, source: &SecretsKeySource) -> Result<SecretsKeyInfo, SqliteManagerError> {
+        let mut vault_guard = self.secrets_vault.write().map_err(|_| SqliteManagerError::LockError)?;
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+
+        let stored_key = tx
+            .query_row(
+                "SELECT key_id, key_source, salt, wrapped_key, created_at FROM secrets_keys",
+                [],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let (vault, key_info) = match stored_key {
+            Some((key_id, key_source, salt, wrapped_key, created_at)) => {
+                let key_source = SecretsKeySourceKind::parse(&key_source).ok_or(SqliteManagerError::InvalidData)?;
+                if key_source != source.kind() {
+                    return Err(SqliteManagerError::SecretsVaultError(format!(
+                        "The secrets vault is protected by the {}, not the {}",
+                        key_source.as_str().replace('_', " "),
+                        source.kind().as_str().replace('_', " ")
+                    )));
+                }
+                let salt = hex::decode(salt).map_err(|_| SqliteManagerError::InvalidData)?;
+                let data_key = source.unwrap_data_key(&wrapped_key, &salt)?;
+                let key_info = SecretsKeyInfo {
+                    key_id: key_id.clone(),
+                    key_source,
+                    created_at,
+                };
+                (SecretsVault::new(key_id, &data_key), key_info)
+            }
+            None => {
+                let (vault, data_key) = SecretsVault::generate();
+                let key_info = Self::store_secrets_key(&tx, &vault, &data_key, source)?;
+                (vault, key_info)
+            }
+        };
+
+        let sealed = Self::reseal_stored_secrets(&tx, None, &vault)?;
+        tx.commit()?;
+        if sealed > 0 {
+            log::info!("Encrypted {} credentials stored in plaintext", sealed);
+        }
+
+        *vault_guard = Some(vault);
+        Ok(key_info)
+    }
+
+    pub fn is_secrets_vault_unlocked(&self) -> bool {
+        self.secrets_vault.read().map(|vault| vault.is_some()).unwrap_or(false)
+    }
+
+    /// Replaces the data key with a new one and re-encrypts every secret with it, in a single
+    /// transaction. The new key is wrapped with `source`, so this also moves the vault between the
+    /// node identity and a passphrase.
+    pub fn rotate_secrets_key(&self, source: &SecretsKeySource) -> Result<SecretsKeyInfo, SqliteManagerError> {
+        let mut vault_guard = self.secrets_vault.write().map_err(|_| SqliteManagerError::LockError)?;
+        let old_vault = vault_guard.as_ref().ok_or(SqliteManagerError::SecretsVaultLocked)?;
+
+        let (new_vault, data_key) = SecretsVault::generate();
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+        let resealed = Self::reseal_stored_secrets(&tx, Some(old_vault), &new_vault)?;
+        tx.execute("DELETE FROM secrets_keys", [])?;
+        let key_info = Self::store_secrets_key(&tx, &new_vault, &data_key, source)?;
+        tx.commit()?;
+        log::info!(
+            "Rotated the secrets key {} to {}, {} credentials re-encrypted",
+            old_vault.key_id(),
+            new_vault.key_id(),
+            resealed
+        );
+
+        *vault_guard = Some(new_vault);
+        Ok(key_info)
+    }
+
+    pub fn get_secrets_key_info(&self) -> Result<Option<SecretsKeyInfo>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let key_info = conn
+            .query_row("SELECT key_id, key_source, created_at FROM secrets_keys", [], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .optional()?;
+        key_info
+            .map(|(key_id, key_source, created_at)| {
+                Ok(SecretsKeyInfo {
+                    key_id,
+                    key_source: SecretsKeySourceKind::parse(&key_source).ok_or(SqliteManagerError::InvalidData)?,
+                    created_at,
+                })
+            })
+            .transpose()
+    }
+
+    fn store_secrets_key(
+        conn: &rusqlite::Connection,
+        vault: &SecretsVault,
+        data_key: &[u8; 32],
+        source: &SecretsKeySource,
+    ) -> Result<SecretsKeyInfo, SqliteManagerError> {
+        let salt = random_salt();
+        let key_info = SecretsKeyInfo {
+            key_id: vault.key_id().to_string(),
+            key_source: source.kind(),
+            created_at: secrets_timestamp(),
+        };
+        conn.execute(
+            "INSERT INTO secrets_keys (key_id, key_source, salt, wrapped_key, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                key_info.key_id,
+                key_info.key_source.as_str(),
+                hex::encode(salt),
+                source.wrap_data_key(data_key, &salt)?,
+                key_info.created_at,
+            ],
+        )?;
+        Ok(key_info)
+    }
+
+    /// Encrypts with `new` every credential stored by the node: the named secrets, the LLM
+    /// provider API keys, the OAuth tokens and the tool config values. Returns how many values
+    /// were (re)encrypted.
+    fn reseal_stored_secrets(
+        conn: &rusqlite::Connection,
+        old: Option<&SecretsVault>,
+        new: &SecretsVault,
+    ) -> Result<usize, SqliteManagerError> {
+        let mut resealed = 0;
+
+        let mut stmt = conn.prepare("SELECT name, value FROM secrets")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (name, value) in rows {
+            if let Some(value) = reseal_value(&value, old, new)? {
+                conn.execute("UPDATE secrets SET value = ?1 WHERE name = ?2", params![value, name])?;
+                resealed += 1;
+            }
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT db_llm_provider_id, api_key FROM llm_providers WHERE api_key IS NOT NULL AND api_key != ''",
+        )?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (id, api_key) in rows {
+            if let Some(api_key) = reseal_value(&api_key, old, new)? {
+                conn.execute(
+                    "UPDATE llm_providers SET api_key = ?1 WHERE db_llm_provider_id = ?2",
+                    params![api_key, id],
+                )?;
+                resealed += 1;
+            }
+        }
+
+        for column in OAUTH_SECRET_COLUMNS {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT id, {column} FROM oauth_tokens WHERE {column} IS NOT NULL AND {column} != ''"
+            ))?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            for (id, value) in rows {
+                if let Some(value) = reseal_value(&value, old, new)? {
+                    conn.execute(
+                        &format!("UPDATE oauth_tokens SET {column} = ?1 WHERE id = ?2"),
+                        params![value, id],
+                    )?;
+                    resealed += 1;
+                }
+            }
+        }
+
+        let mut stmt = conn.prepare("SELECT rowid, tool_data, tool_header FROM zoo_tools")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Vec<u8>>(1)?,
+                    row.get::<_, Vec<u8>>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (rowid, tool_data, tool_header) in rows {
+            let mut tool: ZooTool = serde_json::from_slice(&tool_data)?;
+            let mut header: ZooToolHeader = serde_json::from_slice(&tool_header)?;
+            let tool_changed = match tool.config_mut() {
+                Some(configs) => reseal_tool_configs(configs, old, new)?,
+                None => false,
+            };
+            let header_changed = match header.config.as_mut() {
+                Some(configs) => reseal_tool_configs(configs, old, new)?,
+                None => false,
+            };
+            if tool_changed || header_changed {
+                conn.execute(
+                    "UPDATE zoo_tools SET tool_data = ?1, tool_header = ?2 WHERE rowid = ?3",
+                    params![serde_json::to_vec(&tool)?, serde_json::to_vec(&header)?, rowid],
+                )?;
+                resealed += 1;
+            }
+        }
+
+        Ok(resealed)
+    }
+
+    /// Encrypts a credential before it's stored. While the vault is locked it's stored as is, and
+    /// encrypted when the vault is unlocked.
+    pub fn seal_secret(&self, value: &str) -> Result<String, SqliteManagerError> {
+        let vault = self.secrets_vault.read().map_err(|_| SqliteManagerError::LockError)?;
+        match vault.as_ref() {
+            Some(vault) if !SecretsVault::is_sealed(value) => vault.seal(value),
+            _ => Ok(value.to_string()),
+        }
+    }
+
+    /// Decrypts a stored credential. Plaintext values are returned as they are.
+    pub fn open_secret(&self, value: &str) -> Result<String, SqliteManagerError> {
+        if !SecretsVault::is_sealed(value) {
+            return Ok(value.to_string());
+        }
+        let vault = self.secrets_vault.read().map_err(|_| SqliteManagerError::LockError)?;
+        vault
+            .as_ref()
+            .ok_or(SqliteManagerError::SecretsVaultLocked)?
+            .open(value)
+    }
+
+    pub(crate) fn seal_optional_secret(&self, value: &Option<String>) -> Result<Option<String>, SqliteManagerError> {
+        value.as_deref().map(|value| self.seal_secret(value)).transpose()
+    }
+
+    pub(crate) fn open_optional_secret(&self, value: Option<String>) -> Result<Option<String>, SqliteManagerError> {
+        value.as_deref().map(|value| self.open_secret(value)).transpose()
+    }
+
+    pub(crate) fn seal_tool_configs(&self, configs: &mut [ToolConfig]) -> Result<(), SqliteManagerError> {
+        let vault = self.secrets_vault.read().map_err(|_| SqliteManagerError::LockError)?;
+        if let Some(vault) = vault.as_ref() {
+            reseal_tool_configs(configs, None, vault)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn open_tool_configs(&self, configs: &mut [ToolConfig]) -> Result<(), SqliteManagerError> {
+        for config in configs.iter_mut() {
+            let ToolConfig::BasicConfig(basic_config) = config;
+            if let Some(Value::String(value)) = &basic_config.key_value {
+                if SecretsVault::is_sealed(value) {
+                    basic_config.key_value = Some(serde_json::from_str(&self.open_secret(value)?)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn seal_tool(&self, tool: &mut ZooTool) -> Result<(), SqliteManagerError> {
+        match tool.config_mut() {
+            Some(configs) => self.seal_tool_configs(configs),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn open_tool(&self, tool: &mut ZooTool) -> Result<(), SqliteManagerError> {
+        match tool.config_mut() {
+            Some(configs) => self.open_tool_configs(configs),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn open_tool_header(&self, header: &mut ZooToolHeader) -> Result<(), SqliteManagerError> {
+        match header.config.as_mut() {
+            Some(configs) => self.open_tool_configs(configs),
+            None => Ok(()),
+        }
+    }
+
+    /// Stores a named secret, which tool configs can use as `secret://<name>`.
+    pub fn set_secret(&self, request: &SetSecretRequest) -> Result<SecretInfo, SqliteManagerError> {
+        if !is_valid_secret_name(&request.name) {
+            return Err(SqliteManagerError::ValidationError(format!(
+                "Invalid secret name {}: use letters, digits, '_', '-' and '.'",
+                request.name
+            )));
+        }
+        // Named secrets are never stored in plaintext
+        if !self.is_secrets_vault_unlocked() {
+            return Err(SqliteManagerError::SecretsVaultLocked);
+        }
+
+        let now = secrets_timestamp();
+        let conn = self.get_connection()?;
+        conn.execute(
+            "INSERT INTO secrets (name, value, description, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?4)
+             ON CONFLICT(name) DO UPDATE SET value = excluded.value, description = excluded.description,
+             updated_at = excluded.updated_at",
+            params![
+                request.name,
+                self.seal_secret(&request.value)?,
+                request.description,
+                now
+            ],
+        )?;
+
+        self.get_secret_info(&request.name)?
+            .ok_or(SqliteManagerError::SecretNotFound(request.name.clone()))
+    }
+
+    pub fn get_secret_value(&self, name: &str) -> Result<Option<String>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let value: Option<String> = conn
+            .query_row("SELECT value FROM secrets WHERE name = ?1", params![name], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        self.open_optional_secret(value)
+    }
+
+    pub fn get_secret_info(&self, name: &str) -> Result<Option<SecretInfo>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let info = conn
+            .query_row(
+                "SELECT name, description, created_at, updated_at FROM secrets WHERE name = ?1",
+                params![name],
+                read_secret_info_row,
+            )
+            .optional()?;
+        Ok(info)
+    }
+
+    pub fn get_all_secrets(&self) -> Result<Vec<SecretInfo>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare("SELECT name, description, created_at, updated_at FROM secrets ORDER BY name")?;
+        let secrets = stmt
+            .query_map([], read_secret_info_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(secrets)
+    }
+
+    pub fn remove_secret(&self, name: &str) -> Result<bool, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let removed = conn.execute("DELETE FROM secrets WHERE name = ?1", params![name])?;
+        Ok(removed > 0)
+    }
+
+    /// Every credential stored by the node, encrypted. Meant for backups: it can only be read
+    /// back with the data key, and never contains the plaintext.
+    pub fn export_secrets(&self) -> Result<SecretsExport, SqliteManagerError> {
+        let vault = self.secrets_vault.read().map_err(|_| SqliteManagerError::LockError)?;
+        let vault = vault.as_ref().ok_or(SqliteManagerError::SecretsVaultLocked)?;
+        let key = self
+            .get_secrets_key_info()?
+            .ok_or(SqliteManagerError::SecretsVaultLocked)?;
+        // Values are sealed once the vault is unlocked, this only guards against a row written
+        // in plaintext by hand
+        let sealed = |value: String| -> Result<String, SqliteManagerError> {
+            if SecretsVault::is_sealed(&value) {
+                Ok(value)
+            } else {
+                vault.seal(&value)
+            }
+        };
+
+        let conn = self.get_connection()?;
+        let mut secrets = Vec::new();
+
+        let mut stmt = conn.prepare("SELECT name, value FROM secrets ORDER BY name")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (name, value) in rows {
+            secrets.push(ExportedSecret {
+                location: SecretLocation::Secret,
+                name,
+                sealed_value: sealed(value)?,
+            });
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT db_llm_provider_id, api_key FROM llm_providers WHERE api_key IS NOT NULL AND api_key != ''
+             ORDER BY db_llm_provider_id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (id, api_key) in rows {
+            secrets.push(ExportedSecret {
+                location: SecretLocation::LlmProviderApiKey,
+                name: id,
+                sealed_value: sealed(api_key)?,
+            });
+        }
+
+        for column in OAUTH_SECRET_COLUMNS {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT connection_name, tool_key, {column} FROM oauth_tokens
+                 WHERE {column} IS NOT NULL AND {column} != '' ORDER BY id"
+            ))?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            for (connection_name, tool_key, value) in rows {
+                secrets.push(ExportedSecret {
+                    location: SecretLocation::OAuthToken,
+                    name: format!("{}:::{}:::{}", connection_name, tool_key, column),
+                    sealed_value: sealed(value)?,
+                });
+            }
+        }
+
+        let mut stmt = conn.prepare("SELECT tool_key, tool_data FROM zoo_tools ORDER BY tool_key, version")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (tool_key, tool_data) in rows {
+            let mut tool: ZooTool = serde_json::from_slice(&tool_data)?;
+            for config in tool.config_mut().map(std::mem::take).unwrap_or_default() {
+                let ToolConfig::BasicConfig(basic_config) = config;
+                if !basic_config.is_secret() {
+                    continue;
+                }
+                let value = match basic_config.key_value {
+                    None | Some(Value::Null) => continue,
+                    Some(Value::String(value)) if SecretsVault::is_sealed(&value) => value,
+                    Some(plain) => serde_json::to_string(&plain)?,
+                };
+                secrets.push(ExportedSecret {
+                    location: SecretLocation::ToolConfig,
+                    name: format!("{}:::{}", tool_key, basic_config.key_name),
+                    sealed_value: sealed(value)?,
+                });
+            }
+        }
+
+        Ok(SecretsExport {
+            key,
+            exported_at: secrets_timestamp(),
+            secrets,
+        })
+    }
+}
+
+fn read_secret_info_row(row: &rusqlite::Row) -> Result<SecretInfo> {
+    Ok(SecretInfo {
+        name: row.get(0)?,
+        description: row.get(1)?,
+        created_at: row.get(2)?,
+        updated_at: row.get(3)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::NamedTempFile;
+    use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+
+    fn setup_test_db() -> SqliteManager {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = PathBuf::from(temp_file.path());
+        let api_url = String::new();
+        let model_type =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM);
+
+        SqliteManager::new(db_path, api_url, model_type).unwrap()
+    }
+
+    fn raw_secret_value(db: &SqliteManager, name: &str) -> String {
+        let conn = db.get_connection().unwrap();
+        conn.query_row("SELECT value FROM secrets WHERE name = ?1", params![name], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    fn set_request(name: &str, value: &str) -> SetSecretRequest {
+        SetSecretRequest {
+            name: name.to_string(),
+            value: value.to_string(),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_named_secrets() {
+        let db = setup_test_db();
+        assert!(matches!(
+            db.set_secret(&set_request("openai", "sk-1")),
+            Err(SqliteManagerError::SecretsVaultLocked)
+        ));
+
+        let source = SecretsKeySource::NodeIdentity(vec![1u8; 32]);
+        db.unlock_secrets_vault(&source).unwrap();
+        db.set_secret(&set_request("openai", "sk-1")).unwrap();
+        db.set_secret(&set_request("openai", "sk-2")).unwrap();
+        assert!(db.set_secret(&set_request("not valid", "sk-3")).is_err());
+
+        assert_eq!(db.get_secret_value("openai").unwrap().as_deref(), Some("sk-2"));
+        assert!(!raw_secret_value(&db, "openai").contains("sk-2"));
+        assert_eq!(db.get_all_secrets().unwrap().len(), 1);
+
+        assert!(db.remove_secret("openai").unwrap());
+        assert!(db.get_secret_value("openai").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_unlock_and_rotate() {
+        let db = setup_test_db();
+        let identity = SecretsKeySource::NodeIdentity(vec![1u8; 32]);
+        let first_key = db.unlock_secrets_vault(&identity).unwrap();
+        db.set_secret(&set_request("openai", "sk-1")).unwrap();
+
+        // Unlocking again reuses the stored key, a different identity can't
+        assert_eq!(db.unlock_secrets_vault(&identity).unwrap(), first_key);
+        assert!(db
+            .unlock_secrets_vault(&SecretsKeySource::NodeIdentity(vec![2u8; 32]))
+            .is_err());
+
+        let passphrase = SecretsKeySource::Passphrase("correct horse".to_string());
+        let rotated_key = db.rotate_secrets_key(&passphrase).unwrap();
+        assert_ne!(rotated_key.key_id, first_key.key_id);
+        assert_eq!(rotated_key.key_source, SecretsKeySourceKind::Passphrase);
+        assert_eq!(
+            sealed_key_id(&raw_secret_value(&db, "openai")),
+            Some(rotated_key.key_id.as_str())
+        );
+        assert_eq!(db.get_secret_value("openai").unwrap().as_deref(), Some("sk-1"));
+
+        // The vault is now protected by the passphrase
+        assert!(db.unlock_secrets_vault(&identity).is_err());
+        assert_eq!(db.unlock_secrets_vault(&passphrase).unwrap(), rotated_key);
+
+        let export = db.export_secrets().unwrap();
+        assert_eq!(export.key, rotated_key);
+        assert_eq!(export.secrets.len(), 1);
+        assert!(SecretsVault::is_sealed(&export.secrets[0].sealed_value));
+        assert!(!serde_json::to_string(&export).unwrap().contains("sk-1"));
+    }
+}
@@ -0,0 +1,226 @@
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use rand::RngCore;
+use zoo_message_primitives::schemas::secrets::SecretsKeySourceKind;
+
+use crate::errors::SqliteManagerError;
+
+/// Every encrypted value starts with this, followed by the id of the data key and the hex encoded
+/// nonce and ciphertext: `zsec:v1:<key_id>:<hex>`.
+pub const SEALED_SECRET_PREFIX: &str = "zsec:v1:";
+
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NODE_IDENTITY_KDF_CONTEXT: &str = "zoo-node secrets vault wrapping key v1";
+const PASSPHRASE_KDF_ROUNDS: u32 = 600_000;
+
+/// Where the key that wraps the data key comes from. The data key itself is random and stored
+/// wrapped in the database, so changing the wrapping key doesn't require re-encrypting anything.
+pub enum SecretsKeySource {
+    /// The identity secret key of the node.
+    NodeIdentity(Vec<u8>),
+    /// An operator passphrase, stretched with PBKDF2-HMAC-SHA256.
+    Passphrase(String),
+}
+
+impl SecretsKeySource {
+    pub fn kind(&self) -> SecretsKeySourceKind {
+        match self {
+            SecretsKeySource::NodeIdentity(_) => SecretsKeySourceKind::NodeIdentity,
+            SecretsKeySource::Passphrase(_) => SecretsKeySourceKind::Passphrase,
+        }
+    }
+
+    fn wrapping_key(&self, salt: &[u8]) -> Result<[u8; KEY_LEN], SqliteManagerError> {
+        match self {
+            SecretsKeySource::NodeIdentity(secret) => Ok(blake3::derive_key(NODE_IDENTITY_KDF_CONTEXT, secret)),
+            SecretsKeySource::Passphrase(passphrase) => {
+                if passphrase.is_empty() {
+                    return Err(SqliteManagerError::ValidationError(
+                        "The secrets passphrase is empty".to_string(),
+                    ));
+                }
+                let mut key = [0u8; KEY_LEN];
+                pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, PASSPHRASE_KDF_ROUNDS, &mut key);
+                Ok(key)
+            }
+        }
+    }
+
+    /// Encrypts `data_key` with the key derived from this source.
+    pub(crate) fn wrap_data_key(&self, data_key: &[u8; KEY_LEN], salt: &[u8]) -> Result<String, SqliteManagerError> {
+        let cipher = cipher_for(&self.wrapping_key(salt)?);
+        Ok(hex::encode(encrypt(&cipher, data_key)?))
+    }
+
+    /// Decrypts a data key wrapped by `wrap_data_key`. Fails if the passphrase or node identity
+    /// isn't the one the key was wrapped with.
+    pub(crate) fn unwrap_data_key(&self, wrapped: &str, salt: &[u8]) -> Result<[u8; KEY_LEN], SqliteManagerError> {
+        let cipher = cipher_for(&self.wrapping_key(salt)?);
+        let wrapped = hex::decode(wrapped).map_err(|_| SqliteManagerError::InvalidData)?;
+        let data_key = decrypt(&cipher, &wrapped).map_err(|_| {
+            SqliteManagerError::SecretsVaultError(format!(
+                "Failed to unlock the secrets vault: wrong {}",
+                self.kind().as_str().replace('_', " ")
+            ))
+        })?;
+        data_key.try_into().map_err(|_| SqliteManagerError::InvalidData)
+    }
+}
+
+/// Encrypts and decrypts the secrets stored in the database with the current data key.
+pub struct SecretsVault {
+    key_id: String,
+    cipher: Aes256Gcm,
+}
+
+impl SecretsVault {
+    pub(crate) fn new(key_id: String, data_key: &[u8; KEY_LEN]) -> Self {
+        SecretsVault {
+            key_id,
+            cipher: cipher_for(data_key),
+        }
+    }
+
+    /// A vault with a new random data key, returned with it so it can be wrapped and stored.
+    pub(crate) fn generate() -> (Self, [u8; KEY_LEN]) {
+        let mut data_key = [0u8; KEY_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut data_key);
+        let mut key_id = [0u8; 8];
+        rand::rngs::OsRng.fill_bytes(&mut key_id);
+        (Self::new(hex::encode(key_id), &data_key), data_key)
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn is_sealed(value: &str) -> bool {
+        value.starts_with(SEALED_SECRET_PREFIX)
+    }
+
+    pub fn seal(&self, plaintext: &str) -> Result<String, SqliteManagerError> {
+        let sealed = encrypt(&self.cipher, plaintext.as_bytes())?;
+        Ok(format!(
+            "{}{}:{}",
+            SEALED_SECRET_PREFIX,
+            self.key_id,
+            hex::encode(sealed)
+        ))
+    }
+
+    pub fn open(&self, sealed: &str) -> Result<String, SqliteManagerError> {
+        let (key_id, data) = sealed
+            .strip_prefix(SEALED_SECRET_PREFIX)
+            .and_then(|rest| rest.split_once(':'))
+            .ok_or(SqliteManagerError::InvalidData)?;
+        if key_id != self.key_id {
+            return Err(SqliteManagerError::SecretsVaultError(format!(
+                "Secret encrypted with unknown key {}",
+                key_id
+            )));
+        }
+        let data = hex::decode(data).map_err(|_| SqliteManagerError::InvalidData)?;
+        let plaintext = decrypt(&self.cipher, &data)
+            .map_err(|_| SqliteManagerError::SecretsVaultError("Failed to decrypt secret".to_string()))?;
+        String::from_utf8(plaintext).map_err(|_| SqliteManagerError::InvalidData)
+    }
+}
+
+/// Encrypts data that leaves the node, like the keys in a backup, with a key derived from
+/// `passphrase`. The result is the hex encoded salt and sealed data: `<salt>:<nonce+ciphertext>`.
+pub fn seal_with_passphrase(passphrase: &str, plaintext: &[u8]) -> Result<String, SqliteManagerError> {
+    let salt = random_salt();
+    let key = SecretsKeySource::Passphrase(passphrase.to_string()).wrapping_key(&salt)?;
+    let sealed = encrypt(&cipher_for(&key), plaintext)?;
+    Ok(format!("{}:{}", hex::encode(salt), hex::encode(sealed)))
+}
+
+pub fn open_with_passphrase(passphrase: &str, sealed: &str) -> Result<Vec<u8>, SqliteManagerError> {
+    let (salt, data) = sealed.split_once(':').ok_or(SqliteManagerError::InvalidData)?;
+    let salt = hex::decode(salt).map_err(|_| SqliteManagerError::InvalidData)?;
+    let data = hex::decode(data).map_err(|_| SqliteManagerError::InvalidData)?;
+    let key = SecretsKeySource::Passphrase(passphrase.to_string()).wrapping_key(&salt)?;
+    decrypt(&cipher_for(&key), &data).map_err(|_| SqliteManagerError::SecretsVaultError("Wrong passphrase".to_string()))
+}
+
+pub(crate) fn random_salt() -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+fn cipher_for(key: &[u8; KEY_LEN]) -> Aes256Gcm {
+    Aes256Gcm::new(GenericArray::from_slice(key))
+}
+
+fn encrypt(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<Vec<u8>, SqliteManagerError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| SqliteManagerError::SecretsVaultError("Failed to encrypt secret".to_string()))?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(sealed)
+}
+
+fn decrypt(cipher: &Aes256Gcm, sealed: &[u8]) -> Result<Vec<u8>, aes_gcm::Error> {
+    if sealed.len() < NONCE_LEN {
+        return Err(aes_gcm::Error);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher.decrypt(Nonce::from_slice(nonce), ciphertext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let (vault, _) = SecretsVault::generate();
+        let sealed = vault.seal("sk-very-secret").unwrap();
+        assert!(SecretsVault::is_sealed(&sealed));
+        assert!(!sealed.contains("sk-very-secret"));
+        assert_eq!(vault.open(&sealed).unwrap(), "sk-very-secret");
+
+        // Another data key can't open it
+        let (other_vault, _) = SecretsVault::generate();
+        assert!(other_vault.open(&sealed).is_err());
+    }
+
+    #[test]
+    fn test_wrap_data_key() {
+        let (_, data_key) = SecretsVault::generate();
+        let salt = random_salt();
+
+        let identity = SecretsKeySource::NodeIdentity(vec![7u8; 32]);
+        let wrapped = identity.wrap_data_key(&data_key, &salt).unwrap();
+        assert_eq!(identity.unwrap_data_key(&wrapped, &salt).unwrap(), data_key);
+        assert!(SecretsKeySource::NodeIdentity(vec![8u8; 32])
+            .unwrap_data_key(&wrapped, &salt)
+            .is_err());
+
+        let passphrase = SecretsKeySource::Passphrase("correct horse".to_string());
+        let wrapped = passphrase.wrap_data_key(&data_key, &salt).unwrap();
+        assert_eq!(passphrase.unwrap_data_key(&wrapped, &salt).unwrap(), data_key);
+        assert!(SecretsKeySource::Passphrase("battery staple".to_string())
+            .unwrap_data_key(&wrapped, &salt)
+            .is_err());
+    }
+
+    #[test]
+    fn test_seal_with_passphrase() {
+        let sealed = seal_with_passphrase("correct horse", b"IDENTITY_SECRET_KEY=abc").unwrap();
+        assert!(!sealed.contains("IDENTITY_SECRET_KEY"));
+        assert_eq!(
+            open_with_passphrase("correct horse", &sealed).unwrap(),
+            b"IDENTITY_SECRET_KEY=abc".to_vec()
+        );
+        assert!(open_with_passphrase("battery staple", &sealed).is_err());
+        assert!(seal_with_passphrase("", b"data").is_err());
+    }
+}
@@ -220,6 +220,7 @@ impl SqliteManager {
                     vec![OperatingSystem::Linux, OperatingSystem::MacOS, OperatingSystem::Windows];
                 let mut runner = RunnerType::Any;
                 let mut tool_set = None;
+                let mut permissions = None;
                 if let Ok(tool_data) = self.get_tool_by_key(tool_router_key) {
                     // found data
                     sql_queries = tool_data.sql_queries();
@@ -231,6 +232,7 @@ impl SqliteManager {
                     operating_system = tool_data.get_operating_system();
                     runner = tool_data.get_runner();
                     tool_set = tool_data.get_tool_set();
+                    permissions = tool_data.get_permissions();
                 }
 
                 Ok(ToolPlayground {
@@ -252,6 +254,7 @@ impl SqliteManager {
                         operating_system,
                         runner,
                         tool_set,
+                        permissions,
                     },
                     tool_router_key: row.get(7)?,
                     job_id: row.get(8)?,
@@ -325,6 +328,7 @@ impl SqliteManager {
                     operating_system: vec![OperatingSystem::Linux, OperatingSystem::MacOS, OperatingSystem::Windows],
                     runner: RunnerType::Any,
                     tool_set: None,
+                    permissions: None,
                 },
                 tool_router_key: row.get(7)?,
                 job_id: row.get(8)?,
@@ -433,6 +437,7 @@ mod tests {
             runner: RunnerType::Any,
             operating_system: vec![OperatingSystem::Linux],
             tool_set: None,
+            permissions: None,
         };
 
         let zoo_tool = ZooTool::Deno(deno_tool, true);
@@ -465,6 +470,7 @@ mod tests {
                 operating_system: vec![OperatingSystem::Linux],
                 runner: RunnerType::Any,
                 tool_set: None,
+                permissions: None,
             },
             tool_router_key: Some(tool_router_key),
             job_id: "job_123".to_string(),
@@ -572,6 +578,7 @@ mod tests {
             runner: RunnerType::Any,
             operating_system: vec![OperatingSystem::Linux],
             tool_set: None,
+            permissions: None,
         };
 
         let zoo_tool = ZooTool::Deno(deno_tool, true);
@@ -657,6 +664,7 @@ mod tests {
             runner: RunnerType::Any,
             operating_system: vec![OperatingSystem::Linux],
             tool_set: None,
+            permissions: None,
         };
 
         let zoo_tool = ZooTool::Deno(deno_tool, true);
@@ -0,0 +1,219 @@
+use crate::{SqliteManager, SqliteManagerError};
+use rusqlite::{params, OptionalExtension, Result};
+use zoo_message_primitives::schemas::indexable_version::IndexableVersion;
+use zoo_message_primitives::schemas::tool_router_key::ToolRouterKey;
+use zoo_tools_primitives::tools::zoo_tool::ZooTool;
+
+/// Which installed version of a tool a reference resolves to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolVersionSelector {
+    /// No version: the active version of the tool, the latest unless it was rolled back.
+    Active,
+    /// A plain version like `1.2.3` or `1.2` pins exactly that version.
+    Exact(u64),
+    /// A semver requirement like `^1.2`, `~1.2.3` or `>=1.0, <2.0` picks the highest installed
+    /// version that matches it, preferring the active one.
+    Requirement(semver::VersionReq),
+}
+
+impl ToolVersionSelector {
+    pub fn parse(version: Option<&str>) -> Result<Self, SqliteManagerError> {
+        let Some(version) = version.map(str::trim).filter(|v| !v.is_empty()) else {
+            return Ok(ToolVersionSelector::Active);
+        };
+        if let Ok(exact) = IndexableVersion::from_string(version) {
+            return Ok(ToolVersionSelector::Exact(exact.get_version_number()));
+        }
+        semver::VersionReq::parse(version)
+            .map(ToolVersionSelector::Requirement)
+            .map_err(|e| SqliteManagerError::VersionParseError(format!("{}: {}", version, e)))
+    }
+
+    pub fn from_router_key(tool_router_key: &ToolRouterKey) -> Result<Self, SqliteManagerError> {
+        Self::parse(tool_router_key.version.as_deref())
+    }
+
+    /// Picks a version out of `installed`, sorted from newest to oldest.
+    pub fn select(&self, installed: &[u64], active: Option<u64>) -> Option<u64> {
+        match self {
+            ToolVersionSelector::Active => active.or_else(|| installed.first().copied()),
+            ToolVersionSelector::Exact(version) => installed.contains(version).then_some(*version),
+            ToolVersionSelector::Requirement(requirement) => {
+                let matches = |version: &u64| requirement.matches(&semver_version(*version));
+                active
+                    .filter(|active| installed.contains(active) && matches(active))
+                    .or_else(|| installed.iter().copied().find(matches))
+            }
+        }
+    }
+}
+
+fn semver_version(version_number: u64) -> semver::Version {
+    semver::Version::new(
+        version_number / 1_000_000,
+        (version_number % 1_000_000) / 1_000,
+        version_number % 1_000,
+    )
+}
+
+impl SqliteManager {
+    pub fn initialize_tool_active_versions_table(conn: &rusqlite::Connection) -> Result<()> {
+        // Tools that don't run their latest installed version, after a rollback or a manual pin
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS zoo_tool_active_versions (
+                tool_key TEXT PRIMARY KEY,
+                version INTEGER NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// The installed version numbers of a tool, from newest to oldest.
+    pub fn get_tool_versions(&self, tool_key: &str) -> Result<Vec<u64>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare("SELECT version FROM zoo_tools WHERE tool_key = ?1 ORDER BY version DESC")?;
+        let versions = stmt
+            .query_map(params![tool_key.to_lowercase()], |row| row.get::<_, i64>(0))?
+            .collect::<Result<Vec<i64>, _>>()?;
+        Ok(versions.into_iter().map(|v| v as u64).collect())
+    }
+
+    /// The version a tool is pinned to, if it doesn't follow its latest installed version.
+    pub fn get_tool_active_version(&self, tool_key: &str) -> Result<Option<u64>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let version: Option<i64> = conn
+            .query_row(
+                "SELECT version FROM zoo_tool_active_versions WHERE tool_key = ?1",
+                params![tool_key.to_lowercase()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(version.map(|v| v as u64))
+    }
+
+    /// Makes an installed version the one used by references without a version. `None` goes back
+    /// to following the latest installed version.
+    pub fn set_tool_active_version(&self, tool_key: &str, version: Option<u64>) -> Result<(), SqliteManagerError> {
+        let tool_key = tool_key.to_lowercase();
+        let conn = self.get_connection()?;
+        match version {
+            Some(version) => {
+                if !self.get_tool_versions(&tool_key)?.contains(&version) {
+                    return Err(SqliteManagerError::ToolNotFound(format!(
+                        "{} version {}",
+                        tool_key,
+                        IndexableVersion::from_number(version)
+                    )));
+                }
+                conn.execute(
+                    "INSERT INTO zoo_tool_active_versions (tool_key, version, updated_at)
+                     VALUES (?1, ?2, CURRENT_TIMESTAMP)
+                     ON CONFLICT(tool_key) DO UPDATE SET version = excluded.version, updated_at = excluded.updated_at",
+                    params![tool_key, version as i64],
+                )?;
+            }
+            None => {
+                conn.execute(
+                    "DELETE FROM zoo_tool_active_versions WHERE tool_key = ?1",
+                    params![tool_key],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes the installed version before the active one active. The newer versions stay
+    /// installed, so the tool can be moved forward again with `set_tool_active_version`.
+    pub fn rollback_tool(&self, tool_key: &str) -> Result<ZooTool, SqliteManagerError> {
+        let installed = self.get_tool_versions(tool_key)?;
+        let current = ToolVersionSelector::Active
+            .select(&installed, self.get_tool_active_version(tool_key)?)
+            .ok_or_else(|| SqliteManagerError::ToolNotFound(tool_key.to_string()))?;
+        let previous = installed
+            .iter()
+            .copied()
+            .find(|version| *version < current)
+            .ok_or_else(|| {
+                SqliteManagerError::ValidationError(format!(
+                    "{} has no version installed before {}",
+                    tool_key,
+                    IndexableVersion::from_number(current)
+                ))
+            })?;
+
+        self.set_tool_active_version(tool_key, Some(previous))?;
+        self.get_tool_by_key_and_version(tool_key, Some(IndexableVersion::from_number(previous)))
+    }
+
+    /// Resolves a tool reference, with an exact version, a semver requirement or no version at all.
+    pub fn get_tool_by_router_key(&self, tool_router_key: &ToolRouterKey) -> Result<ZooTool, SqliteManagerError> {
+        let tool_key = tool_router_key.to_string_without_version();
+        let selector = ToolVersionSelector::from_router_key(tool_router_key)?;
+        if selector == ToolVersionSelector::Active {
+            return self.get_tool_by_key(&tool_key);
+        }
+
+        let installed = self.get_tool_versions(&tool_key)?;
+        let version = selector
+            .select(&installed, self.get_tool_active_version(&tool_key)?)
+            .ok_or_else(|| SqliteManagerError::ToolNotFound(tool_router_key.to_string_with_version()))?;
+        self.get_tool_by_key_and_version(&tool_key, Some(IndexableVersion::from_number(version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_version_selector() {
+        assert_eq!(ToolVersionSelector::parse(None).unwrap(), ToolVersionSelector::Active);
+        assert_eq!(
+            ToolVersionSelector::parse(Some("")).unwrap(),
+            ToolVersionSelector::Active
+        );
+        assert_eq!(
+            ToolVersionSelector::parse(Some("1.2")).unwrap(),
+            ToolVersionSelector::Exact(1_002_000)
+        );
+        assert!(matches!(
+            ToolVersionSelector::parse(Some("^1.2")).unwrap(),
+            ToolVersionSelector::Requirement(_)
+        ));
+        assert!(matches!(
+            ToolVersionSelector::parse(Some(">=1.0, <2.0")).unwrap(),
+            ToolVersionSelector::Requirement(_)
+        ));
+        assert!(ToolVersionSelector::parse(Some("latest")).is_err());
+    }
+
+    #[test]
+    fn test_select_tool_version() {
+        let installed = vec![2_000_000, 1_004_000, 1_003_002, 1_002_000];
+
+        assert_eq!(ToolVersionSelector::Active.select(&installed, None), Some(2_000_000));
+        assert_eq!(
+            ToolVersionSelector::Active.select(&installed, Some(1_003_002)),
+            Some(1_003_002)
+        );
+        assert_eq!(
+            ToolVersionSelector::Exact(1_002_000).select(&installed, None),
+            Some(1_002_000)
+        );
+        assert_eq!(ToolVersionSelector::Exact(1_001_000).select(&installed, None), None);
+
+        let caret = ToolVersionSelector::parse(Some("^1.2")).unwrap();
+        assert_eq!(caret.select(&installed, None), Some(1_004_000));
+        // A rolled back version is kept while it still matches
+        assert_eq!(caret.select(&installed, Some(1_003_002)), Some(1_003_002));
+        assert_eq!(caret.select(&installed, Some(2_000_000)), Some(1_004_000));
+
+        let tilde = ToolVersionSelector::parse(Some("~1.3.0")).unwrap();
+        assert_eq!(tilde.select(&installed, None), Some(1_003_002));
+
+        let none = ToolVersionSelector::parse(Some("^3")).unwrap();
+        assert_eq!(none.select(&installed, None), None);
+    }
+}
@@ -0,0 +1,414 @@
+use crate::{errors::SqliteManagerError, SqliteManager};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Result, ToSql};
+use zoo_message_primitives::schemas::usage::{
+    BudgetEnforcement, BudgetPeriod, BudgetScope, UsageBudget, UsageBudgetStatus, UsageByLLMProvider, UsageQuery,
+    UsageRecord, UsageSummary, UsageTotals,
+};
+
+/// Timestamps are always stored in the same RFC3339 shape so they can be compared as text.
+pub fn usage_timestamp(datetime: DateTime<Utc>) -> String {
+    datetime.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn parse_usage_timestamp(value: &str) -> Result<String, SqliteManagerError> {
+    let datetime = DateTime::parse_from_rfc3339(value)
+        .map_err(|e| SqliteManagerError::DateTimeParseError(format!("{}: {}", value, e)))?;
+    Ok(usage_timestamp(datetime.with_timezone(&Utc)))
+}
+
+impl SqliteManager {
+    pub fn initialize_usage_tables(conn: &rusqlite::Connection) -> Result<()> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS usage_ledger (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                created_at TEXT NOT NULL,
+                profile TEXT NOT NULL,
+                agent_id TEXT,
+                job_id TEXT,
+                llm_provider_id TEXT NOT NULL,
+                model TEXT NOT NULL,
+                input_tokens INTEGER NOT NULL,
+                output_tokens INTEGER NOT NULL,
+                estimated_cost_usd REAL NOT NULL
+            );",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_ledger_profile ON usage_ledger (profile, created_at);",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_ledger_agent_id ON usage_ledger (agent_id, created_at);",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_ledger_job_id ON usage_ledger (job_id);",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS usage_budgets (
+                scope TEXT NOT NULL,
+                scope_id TEXT NOT NULL,
+                period TEXT NOT NULL,
+                max_tokens INTEGER,
+                max_cost_usd REAL,
+                enforcement TEXT NOT NULL,
+                downgrade_llm_provider_id TEXT,
+                PRIMARY KEY (scope, scope_id, period)
+            );",
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub fn add_usage_record(&self, record: &UsageRecord) -> Result<i64, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        conn.execute(
+            "INSERT INTO usage_ledger (
+                created_at, profile, agent_id, job_id, llm_provider_id, model,
+                input_tokens, output_tokens, estimated_cost_usd
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                parse_usage_timestamp(&record.created_at)?,
+                record.profile,
+                record.agent_id,
+                record.job_id,
+                record.llm_provider_id,
+                record.model,
+                record.input_tokens as i64,
+                record.output_tokens as i64,
+                record.estimated_cost_usd,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Totals and per LLM provider breakdown of the ledger records matching `query`.
+    pub fn get_usage_summary(&self, query: &UsageQuery) -> Result<UsageSummary, SqliteManagerError> {
+        let mut conditions = Vec::new();
+        let mut values: Vec<String> = Vec::new();
+        let filters = [
+            ("profile = ?", query.profile.clone()),
+            ("agent_id = ?", query.agent_id.clone()),
+            ("job_id = ?", query.job_id.clone()),
+            ("llm_provider_id = ?", query.llm_provider_id.clone()),
+            (
+                "created_at >= ?",
+                query.from.as_deref().map(parse_usage_timestamp).transpose()?,
+            ),
+            (
+                "created_at < ?",
+                query.to.as_deref().map(parse_usage_timestamp).transpose()?,
+            ),
+        ];
+        for (condition, value) in filters {
+            if let Some(value) = value {
+                conditions.push(condition);
+                values.push(value);
+            }
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT llm_provider_id, model, COUNT(*), SUM(input_tokens), SUM(output_tokens), SUM(estimated_cost_usd)
+             FROM usage_ledger {}
+             GROUP BY llm_provider_id, model
+             ORDER BY llm_provider_id, model",
+            where_clause
+        ))?;
+        let params: Vec<&dyn ToSql> = values.iter().map(|v| v as &dyn ToSql).collect();
+        let rows = stmt.query_map(params.as_slice(), |row| {
+            Ok(UsageByLLMProvider {
+                llm_provider_id: row.get(0)?,
+                model: row.get(1)?,
+                totals: UsageTotals {
+                    inferences: row.get::<_, i64>(2)? as u64,
+                    input_tokens: row.get::<_, i64>(3)? as u64,
+                    output_tokens: row.get::<_, i64>(4)? as u64,
+                    estimated_cost_usd: row.get(5)?,
+                },
+            })
+        })?;
+
+        let mut summary = UsageSummary::default();
+        for row in rows {
+            let by_llm_provider = row?;
+            summary.totals.inferences += by_llm_provider.totals.inferences;
+            summary.totals.input_tokens += by_llm_provider.totals.input_tokens;
+            summary.totals.output_tokens += by_llm_provider.totals.output_tokens;
+            summary.totals.estimated_cost_usd += by_llm_provider.totals.estimated_cost_usd;
+            summary.by_llm_provider.push(by_llm_provider);
+        }
+        Ok(summary)
+    }
+
+    /// Usage of a profile or an agent since `since`.
+    pub fn get_usage_totals_since(
+        &self,
+        scope: BudgetScope,
+        scope_id: &str,
+        since: DateTime<Utc>,
+    ) -> Result<UsageTotals, SqliteManagerError> {
+        let column = match scope {
+            BudgetScope::Profile => "profile",
+            BudgetScope::Agent => "agent_id",
+        };
+        let conn = self.get_connection()?;
+        let totals = conn.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(input_tokens), 0), COALESCE(SUM(output_tokens), 0),
+                        COALESCE(SUM(estimated_cost_usd), 0.0)
+                 FROM usage_ledger WHERE {} = ?1 AND created_at >= ?2",
+                column
+            ),
+            params![scope_id, usage_timestamp(since)],
+            |row| {
+                Ok(UsageTotals {
+                    inferences: row.get::<_, i64>(0)? as u64,
+                    input_tokens: row.get::<_, i64>(1)? as u64,
+                    output_tokens: row.get::<_, i64>(2)? as u64,
+                    estimated_cost_usd: row.get(3)?,
+                })
+            },
+        )?;
+        Ok(totals)
+    }
+
+    /// Adds a budget, or replaces the one with the same scope, scope id and period.
+    pub fn set_usage_budget(&self, budget: &UsageBudget) -> Result<(), SqliteManagerError> {
+        if budget.max_tokens.is_none() && budget.max_cost_usd.is_none() {
+            return Err(SqliteManagerError::ValidationError(
+                "A budget needs max_tokens, max_cost_usd or both".to_string(),
+            ));
+        }
+        let conn = self.get_connection()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO usage_budgets (
+                scope, scope_id, period, max_tokens, max_cost_usd, enforcement, downgrade_llm_provider_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                budget.scope.as_str(),
+                budget.scope_id,
+                budget.period.as_str(),
+                budget.max_tokens.map(|v| v as i64),
+                budget.max_cost_usd,
+                budget.enforcement.as_str(),
+                budget.downgrade_llm_provider_id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Removes a budget. Returns whether it existed.
+    pub fn remove_usage_budget(
+        &self,
+        scope: BudgetScope,
+        scope_id: &str,
+        period: BudgetPeriod,
+    ) -> Result<bool, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let removed = conn.execute(
+            "DELETE FROM usage_budgets WHERE scope = ?1 AND scope_id = ?2 AND period = ?3",
+            params![scope.as_str(), scope_id, period.as_str()],
+        )?;
+        Ok(removed > 0)
+    }
+
+    pub fn get_all_usage_budgets(&self) -> Result<Vec<UsageBudget>, SqliteManagerError> {
+        self.query_usage_budgets("SELECT * FROM usage_budgets ORDER BY scope, scope_id, period", &[])
+    }
+
+    pub fn get_usage_budgets_for(
+        &self,
+        scope: BudgetScope,
+        scope_id: &str,
+    ) -> Result<Vec<UsageBudget>, SqliteManagerError> {
+        self.query_usage_budgets(
+            "SELECT * FROM usage_budgets WHERE scope = ?1 AND scope_id = ?2 ORDER BY period",
+            &[&scope.as_str() as &dyn ToSql, &scope_id],
+        )
+    }
+
+    /// The budget with what has been used of it in the period that contains `now`.
+    pub fn get_usage_budget_status(
+        &self,
+        budget: UsageBudget,
+        now: DateTime<Utc>,
+    ) -> Result<UsageBudgetStatus, SqliteManagerError> {
+        let period_start = budget.period.period_start(now);
+        let usage = self.get_usage_totals_since(budget.scope, &budget.scope_id, period_start)?;
+        Ok(UsageBudgetStatus {
+            exceeded: budget.is_exceeded_by(&usage),
+            period_start: usage_timestamp(period_start),
+            budget,
+            usage,
+        })
+    }
+
+    fn query_usage_budgets(&self, query: &str, params: &[&dyn ToSql]) -> Result<Vec<UsageBudget>, SqliteManagerError> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(query)?;
+        let rows = stmt.query_map(params, |row| {
+            let scope: String = row.get("scope")?;
+            let period: String = row.get("period")?;
+            let enforcement: String = row.get("enforcement")?;
+            Ok((
+                scope,
+                period,
+                enforcement,
+                row.get::<_, String>("scope_id")?,
+                row.get::<_, Option<i64>>("max_tokens")?,
+                row.get::<_, Option<f64>>("max_cost_usd")?,
+                row.get::<_, Option<String>>("downgrade_llm_provider_id")?,
+            ))
+        })?;
+
+        let mut budgets = Vec::new();
+        for row in rows {
+            let (scope, period, enforcement, scope_id, max_tokens, max_cost_usd, downgrade_llm_provider_id) = row?;
+            budgets.push(UsageBudget {
+                scope: serde_json::from_value(serde_json::Value::String(scope))?,
+                scope_id,
+                period: serde_json::from_value(serde_json::Value::String(period))?,
+                max_tokens: max_tokens.map(|v| v as u64),
+                max_cost_usd,
+                enforcement: serde_json::from_value::<BudgetEnforcement>(serde_json::Value::String(enforcement))?,
+                downgrade_llm_provider_id,
+            });
+        }
+        Ok(budgets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use std::path::PathBuf;
+    use tempfile::NamedTempFile;
+    use zoo_embedding::model_type::{EmbeddingModelType, OllamaTextEmbeddingsInference};
+
+    fn setup_test_db() -> SqliteManager {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = PathBuf::from(temp_file.path());
+        let api_url = String::new();
+        let model_type =
+            EmbeddingModelType::OllamaTextEmbeddingsInference(OllamaTextEmbeddingsInference::SnowflakeArcticEmbedM);
+
+        SqliteManager::new(db_path, api_url, model_type).unwrap()
+    }
+
+    fn record(created_at: DateTime<Utc>, agent_id: Option<&str>, llm_provider_id: &str, tokens: u64) -> UsageRecord {
+        UsageRecord {
+            id: None,
+            created_at: created_at.to_rfc3339(),
+            profile: "@@node.sep-zoo/main".to_string(),
+            agent_id: agent_id.map(|s| s.to_string()),
+            job_id: Some("jobid_1".to_string()),
+            llm_provider_id: llm_provider_id.to_string(),
+            model: "openai:gpt-4o".to_string(),
+            input_tokens: tokens,
+            output_tokens: tokens / 2,
+            estimated_cost_usd: tokens as f64 / 1000.0,
+        }
+    }
+
+    #[test]
+    fn test_usage_summary() {
+        let db = setup_test_db();
+        let now = Utc::now();
+        db.add_usage_record(&record(now, Some("agent_a"), "llm_1", 100))
+            .unwrap();
+        db.add_usage_record(&record(now, Some("agent_b"), "llm_1", 200))
+            .unwrap();
+        db.add_usage_record(&record(now, None, "llm_2", 1000)).unwrap();
+        db.add_usage_record(&record(now - Duration::days(40), None, "llm_2", 1000))
+            .unwrap();
+
+        let summary = db.get_usage_summary(&UsageQuery::default()).unwrap();
+        assert_eq!(summary.totals.inferences, 4);
+        assert_eq!(summary.totals.input_tokens, 2300);
+        assert_eq!(summary.by_llm_provider.len(), 2);
+        assert_eq!(summary.by_llm_provider[0].llm_provider_id, "llm_1");
+        assert_eq!(summary.by_llm_provider[0].totals.output_tokens, 150);
+
+        let summary = db
+            .get_usage_summary(&UsageQuery {
+                agent_id: Some("agent_b".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(summary.totals.input_tokens, 200);
+
+        let summary = db
+            .get_usage_summary(&UsageQuery {
+                from: Some((now - Duration::days(1)).to_rfc3339()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(summary.totals.inferences, 3);
+
+        assert!(db
+            .get_usage_summary(&UsageQuery {
+                from: Some("yesterday".to_string()),
+                ..Default::default()
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_budgets_and_status() {
+        let db = setup_test_db();
+        let budget = UsageBudget {
+            scope: BudgetScope::Agent,
+            scope_id: "agent_a".to_string(),
+            period: BudgetPeriod::Daily,
+            max_tokens: Some(500),
+            max_cost_usd: None,
+            enforcement: BudgetEnforcement::Soft,
+            downgrade_llm_provider_id: Some("cheap_llm".to_string()),
+        };
+        db.set_usage_budget(&budget).unwrap();
+        assert_eq!(db.get_all_usage_budgets().unwrap(), vec![budget.clone()]);
+        assert_eq!(
+            db.get_usage_budgets_for(BudgetScope::Agent, "agent_a").unwrap(),
+            vec![budget.clone()]
+        );
+        assert!(db
+            .get_usage_budgets_for(BudgetScope::Profile, "agent_a")
+            .unwrap()
+            .is_empty());
+
+        let now = Utc.with_ymd_and_hms(2024, 3, 15, 12, 0, 0).unwrap();
+        db.add_usage_record(&record(now, Some("agent_a"), "llm_1", 200))
+            .unwrap();
+        // Yesterday's usage doesn't count for a daily budget
+        db.add_usage_record(&record(now - Duration::days(1), Some("agent_a"), "llm_1", 1000))
+            .unwrap();
+
+        let status = db.get_usage_budget_status(budget.clone(), now).unwrap();
+        assert_eq!(status.usage.total_tokens(), 300);
+        assert!(!status.exceeded);
+
+        db.add_usage_record(&record(now, Some("agent_a"), "llm_1", 200))
+            .unwrap();
+        let status = db.get_usage_budget_status(budget.clone(), now).unwrap();
+        assert!(status.exceeded);
+
+        assert!(db
+            .remove_usage_budget(BudgetScope::Agent, "agent_a", BudgetPeriod::Daily)
+            .unwrap());
+        assert!(db.get_all_usage_budgets().unwrap().is_empty());
+
+        let mut invalid_budget = budget;
+        invalid_budget.max_tokens = None;
+        assert!(db.set_usage_budget(&invalid_budget).is_err());
+    }
+}
@@ -4,6 +4,7 @@ use keyphrases::KeyPhraseExtractor;
 use rusqlite::{params, Result};
 use serde_json::Value;
 use zoo_message_primitives::schemas::indexable_version::IndexableVersion;
+use zoo_message_primitives::zoo_utils::zoo_logging::{zoo_log, ZooLogLevel, ZooLogOption};
 use zoo_tools_primitives::tools::mcp_server_tool::MCPServerTool;
 use zoo_tools_primitives::tools::zoo_tool::{ZooTool, ZooToolHeader};
 use zoo_tools_primitives::tools::tool_config::{BasicConfig, ToolConfig};
@@ -45,7 +46,7 @@ impl SqliteManager {
 
         let tool_seos = tool.format_embedding_string();
         let tool_type = tool.tool_type().to_string();
-        let tool_header = serde_json::to_vec(&tool.to_header()).unwrap();
+        let tool_header = self.sealed_tool_header_data(&tool)?;
 
         // Clone the tool to make it mutable
         let mut tool_clone = tool.clone();
@@ -58,10 +59,7 @@ impl SqliteManager {
             tool_clone.disable();
         }
 
-        let tool_data = serde_json::to_vec(&tool_clone).map_err(|e| {
-            eprintln!("Serialization error: {}", e);
-            SqliteManagerError::SerializationError(e.to_string())
-        })?;
+        let tool_data = self.sealed_tool_data(&tool_clone)?;
 
         // Extract on_demand_price and is_network
         let (on_demand_price, is_network) = match tool_clone {
@@ -343,11 +341,37 @@ impl SqliteManager {
         self.tool_vector_search_with_vector(embedding, num_results, include_disabled, include_network)
     }
 
+    /// The tool serialized for storage, with the credentials in its config encrypted
+    fn sealed_tool_data(&self, tool: &ZooTool) -> Result<Vec<u8>, SqliteManagerError> {
+        let mut sealed_tool = tool.clone();
+        self.seal_tool(&mut sealed_tool)?;
+        serde_json::to_vec(&sealed_tool).map_err(|e| {
+            zoo_log(
+                ZooLogOption::Database,
+                ZooLogLevel::Error,
+                &format!("Failed to serialize tool {}: {}", tool.tool_router_key().to_string_without_version(), e),
+            );
+            SqliteManagerError::SerializationError(e.to_string())
+        })
+    }
+
+    /// The header of the tool serialized for storage, with the credentials in its config encrypted
+    fn sealed_tool_header_data(&self, tool: &ZooTool) -> Result<Vec<u8>, SqliteManagerError> {
+        let mut header = tool.to_header();
+        if let Some(configs) = header.config.as_mut() {
+            self.seal_tool_configs(configs)?;
+        }
+        serde_json::to_vec(&header).map_err(|e| SqliteManagerError::SerializationError(e.to_string()))
+    }
+
     /// Retrieves a ZooToolHeader based on its tool_key
     pub fn get_tool_header_by_key(&self, tool_key: &str) -> Result<ZooToolHeader, SqliteManagerError> {
         let conn = self.get_connection()?;
-        let mut stmt =
-            conn.prepare("SELECT tool_header FROM zoo_tools WHERE tool_key = ?1 ORDER BY version DESC LIMIT 1")?;
+        // The active version: the latest one unless the tool was rolled back
+        let mut stmt = conn.prepare(
+            "SELECT tool_header FROM zoo_tools WHERE tool_key = ?1
+             ORDER BY version = (SELECT version FROM zoo_tool_active_versions WHERE tool_key = ?1) DESC, version DESC LIMIT 1",
+        )?;
 
         let tool_header_data: Vec<u8> = stmt
             .query_row(params![tool_key.to_lowercase()], |row| row.get(0))
@@ -361,10 +385,11 @@ impl SqliteManager {
                 }
             })?;
 
-        let tool_header: ZooToolHeader = serde_json::from_slice(&tool_header_data).map_err(|e| {
+        let mut tool_header: ZooToolHeader = serde_json::from_slice(&tool_header_data).map_err(|e| {
             eprintln!("Deserialization error: {}", e);
             SqliteManagerError::SerializationError(e.to_string())
         })?;
+        self.open_tool_header(&mut tool_header)?;
 
         Ok(tool_header)
     }
@@ -405,11 +430,15 @@ impl SqliteManager {
         }
     }
 
-    /// Retrieves a ZooTool based on its tool_key, sorted by descending version
+    /// Retrieves the active version of a ZooTool based on its tool_key: the latest one, unless the
+    /// tool was rolled back or pinned to another installed version
     pub fn get_tool_by_key(&self, tool_key: &str) -> Result<ZooTool, SqliteManagerError> {
         let conn = self.get_connection()?;
-        let mut stmt =
-            conn.prepare("SELECT tool_data FROM zoo_tools WHERE tool_key = ?1 ORDER BY version DESC LIMIT 1")?;
+        // The active version: the latest one unless the tool was rolled back
+        let mut stmt = conn.prepare(
+            "SELECT tool_data FROM zoo_tools WHERE tool_key = ?1
+             ORDER BY version = (SELECT version FROM zoo_tool_active_versions WHERE tool_key = ?1) DESC, version DESC LIMIT 1",
+        )?;
 
         let tool_data: Vec<u8> = stmt
             .query_row(params![tool_key.to_lowercase()], |row| row.get(0))
@@ -424,10 +453,11 @@ impl SqliteManager {
             })?;
 
         // Deserialize the tool_data to get the ZooTool
-        let tool: ZooTool = serde_json::from_slice(&tool_data).map_err(|e| {
+        let mut tool: ZooTool = serde_json::from_slice(&tool_data).map_err(|e| {
             eprintln!("Deserialization error: {}", e);
             SqliteManagerError::SerializationError(e.to_string())
         })?;
+        self.open_tool(&mut tool)?;
 
         Ok(tool)
     }
@@ -461,13 +491,10 @@ impl SqliteManager {
             })?;
 
         // Serialize the updated tool data
-        let tool_data = serde_json::to_vec(&tool).map_err(|e| {
-            eprintln!("Serialization error: {}", e);
-            SqliteManagerError::SerializationError(e.to_string())
-        })?;
+        let tool_data = self.sealed_tool_data(&tool)?;
 
         // Generate the tool header
-        let tool_header = serde_json::to_vec(&tool.to_header()).unwrap();
+        let tool_header = self.sealed_tool_header_data(&tool)?;
 
         // Determine if the tool can be enabled
         let is_enabled = tool.is_enabled() && tool.can_be_enabled();
@@ -556,10 +583,12 @@ impl SqliteManager {
 
         let mut headers = Vec::new();
         for header in header_iter {
-            headers.push(header.map_err(|e| {
+            let mut header: ZooToolHeader = header.map_err(|e| {
                 eprintln!("Database error: {}", e);
                 SqliteManagerError::DatabaseError(e)
-            })?);
+            })?;
+            self.open_tool_header(&mut header)?;
+            headers.push(header);
         }
 
         Ok(headers)
@@ -650,6 +679,13 @@ impl SqliteManager {
             tx.execute("DELETE FROM zoo_tools_vec_items WHERE rowid = ?1", params![rowid])?;
         }
 
+        // A removed version can't stay active
+        tx.execute(
+            "DELETE FROM zoo_tool_active_versions WHERE tool_key = ?1
+             AND version NOT IN (SELECT version FROM zoo_tools WHERE tool_key = ?1)",
+            params![tool_key_lower],
+        )?;
+
         tx.commit()?;
 
         // Now remove those rowids from the FTS table in the separate in-memory DB
@@ -734,7 +770,7 @@ impl SqliteManager {
                 SqliteManagerError::DatabaseError(e)
             })?;
 
-        Ok(count >= 7)
+        Ok(count >= 9)
     }
 
     // Update the FTS table when inserting or updating a tool
@@ -822,10 +858,11 @@ impl SqliteManager {
                             SqliteManagerError::DatabaseError(e)
                         })?;
 
-                    let tool_header: ZooToolHeader = serde_json::from_slice(&tool_header_data).map_err(|e| {
+                    let mut tool_header: ZooToolHeader = serde_json::from_slice(&tool_header_data).map_err(|e| {
                         eprintln!("Deserialization error: {}", e);
                         SqliteManagerError::SerializationError(e.to_string())
                     })?;
+                    self.open_tool_header(&mut tool_header)?;
 
                     tool_headers.push(tool_header);
                 }
@@ -964,7 +1001,7 @@ impl SqliteManager {
         let conn = self.get_connection()?;
         let tool_key_lower = tool_key.to_lowercase();
 
-        let tool: ZooTool = if let Some(version) = version {
+        let mut tool: ZooTool = if let Some(version) = version {
             let version_number = version.get_version_number();
             conn.query_row(
                 "SELECT tool_data FROM zoo_tools WHERE tool_key = ?1 AND version = ?2",
@@ -979,7 +1016,8 @@ impl SqliteManager {
             )?
         } else {
             conn.query_row(
-                "SELECT tool_data FROM zoo_tools WHERE tool_key = ?1 ORDER BY version DESC LIMIT 1",
+                "SELECT tool_data FROM zoo_tools WHERE tool_key = ?1
+                 ORDER BY version = (SELECT version FROM zoo_tool_active_versions WHERE tool_key = ?1) DESC, version DESC LIMIT 1",
                 params![tool_key_lower],
                 |row| {
                     let tool_data: Vec<u8> = row.get(0)?;
@@ -990,6 +1028,7 @@ impl SqliteManager {
                 },
             )?
         };
+        self.open_tool(&mut tool)?;
 
         Ok(tool)
     }
@@ -1009,13 +1048,14 @@ impl SqliteManager {
 
         let mut tools = Vec::new();
         for tool_result in tool_iter {
-            let tool: ZooTool = tool_result.map_err(|e| {
+            let mut tool: ZooTool = tool_result.map_err(|e| {
                 eprintln!("Database error: {}", e);
                 SqliteManagerError::DatabaseError(e)
             })?;
 
             if let Some(ts) = tool.get_tool_set() {
                 if ts == tool_set_name {
+                    self.open_tool(&mut tool)?;
                     tools.push(tool);
                 }
             }
@@ -1108,10 +1148,11 @@ impl SqliteManager {
         let mut tools = Vec::new();
         while let Some(row) = rows.next()? {
             let tool_data: Vec<u8> = row.get(0)?;
-            let tool: ZooTool = serde_json::from_slice(&tool_data).map_err(|e| {
+            let mut tool: ZooTool = serde_json::from_slice(&tool_data).map_err(|e| {
                 eprintln!("Deserialization error: {}", e);
                 SqliteManagerError::SerializationError(e.to_string())
             })?;
+            self.open_tool(&mut tool)?;
             if let ZooTool::MCPServer(mcp_tool, _) = tool {
                 tools.push(mcp_tool);
             }
@@ -1205,6 +1246,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         // Wrap the DenoTool in a ZooTool::Deno variant
@@ -1289,6 +1331,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_router_key = ToolRouterKey::new(
@@ -1323,6 +1366,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_router_key = ToolRouterKey::new(
@@ -1357,6 +1401,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let zoo_tool_1 = ZooTool::Deno(deno_tool_1, true);
@@ -1428,6 +1473,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_router_key = ToolRouterKey::new(
@@ -1462,6 +1508,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_router_key = ToolRouterKey::new(
@@ -1496,6 +1543,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         // Wrap the DenoTools in ZooTool::Deno variants
@@ -1597,6 +1645,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         // Wrap the DenoTool in a ZooTool::Deno variant
@@ -1669,6 +1718,7 @@ mod tests {
                 runner: RunnerType::OnlyHost,
                 operating_system: vec![OperatingSystem::Windows],
                 tool_set: None,
+                permissions: None,
             },
             DenoTool {
                 name: "Text Analysis Helper".to_string(),
@@ -1695,6 +1745,7 @@ mod tests {
                 runner: RunnerType::OnlyHost,
                 operating_system: vec![OperatingSystem::Windows],
                 tool_set: None,
+                permissions: None,
             },
             DenoTool {
                 name: "Data Visualization Tool".to_string(),
@@ -1721,6 +1772,7 @@ mod tests {
                 runner: RunnerType::OnlyHost,
                 operating_system: vec![OperatingSystem::Windows],
                 tool_set: None,
+                permissions: None,
             },
         ];
 
@@ -1797,6 +1849,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_router_key = ToolRouterKey::new(
@@ -1831,6 +1884,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         // Add both tools to the database
@@ -1932,6 +1986,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_router_key = ToolRouterKey::new(
@@ -1966,6 +2021,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let usage_type = UsageType::PerUse(ToolPrice::Payment(vec![PaymentRequirements {
@@ -2139,6 +2195,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_router_key = ToolRouterKey::new(
@@ -2173,6 +2230,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_router_key = ToolRouterKey::new(
@@ -2207,6 +2265,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         // Add tools to database with specific vectors
@@ -2301,6 +2360,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let tool_router_key = ToolRouterKey::new(
@@ -2335,6 +2395,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         // Wrap the DenoTools in ZooTool::Deno variants
@@ -2398,6 +2459,92 @@ mod tests {
         assert_eq!(fts_results[0].version, "2.0");
     }
 
+    #[tokio::test]
+    async fn test_rollback_tool_and_version_requirements() {
+        let manager = setup_test_db().await;
+
+        let versioned_tool = |version: &str| {
+            ZooTool::Deno(
+                DenoTool {
+                    name: "Pinned Tool".to_string(),
+                    tool_router_key: Some(ToolRouterKey::new(
+                        "local".to_string(),
+                        "Version Author".to_string(),
+                        "Pinned Tool".to_string(),
+                        None,
+                    )),
+                    homepage: None,
+                    author: "Version Author".to_string(),
+                    version: version.to_string(),
+                    mcp_enabled: Some(false),
+                    js_code: format!("console.log('Version {}');", version),
+                    tools: vec![],
+                    config: vec![],
+                    description: format!("A tool with version {}", version),
+                    keywords: vec![],
+                    input_args: Parameters::new(),
+                    output_arg: ToolOutputArg::empty(),
+                    activated: true,
+                    embedding: None,
+                    result: ToolResult::new("object".to_string(), serde_json::Value::Null, vec![]),
+                    sql_tables: None,
+                    sql_queries: None,
+                    file_inbox: None,
+                    oauth: None,
+                    assets: None,
+                    runner: RunnerType::Any,
+                    operating_system: vec![OperatingSystem::Linux],
+                    tool_set: None,
+                    permissions: None,
+                },
+                true,
+            )
+        };
+
+        for (i, version) in ["1.2.0", "1.3.0", "2.0.0"].iter().enumerate() {
+            manager
+                .add_tool_with_vector(
+                    versioned_tool(version),
+                    SqliteManager::generate_vector_for_testing(0.1 * (i + 1) as f32),
+                )
+                .unwrap();
+        }
+        let tool_key = versioned_tool("1.2.0").tool_router_key().to_string_without_version();
+        let reference = |version: Option<&str>| {
+            let mut key = ToolRouterKey::from_string(&tool_key).unwrap();
+            key.version = version.map(|v| v.to_string());
+            manager.get_tool_by_router_key(&key).map(|tool| tool.version())
+        };
+
+        assert_eq!(manager.get_tool_versions(&tool_key).unwrap(), vec![2_000_000, 1_003_000, 1_002_000]);
+        assert_eq!(reference(None).unwrap(), "2.0.0");
+        assert_eq!(reference(Some("1.2")).unwrap(), "1.2.0");
+        assert_eq!(reference(Some("^1.2")).unwrap(), "1.3.0");
+        assert!(reference(Some("^3")).is_err());
+
+        // Rolling back keeps the newer version installed but stops using it
+        let rolled_back = manager.rollback_tool(&tool_key).unwrap();
+        assert_eq!(rolled_back.version(), "1.3.0");
+        assert_eq!(manager.get_tool_by_key(&tool_key).unwrap().version(), "1.3.0");
+        assert_eq!(manager.get_tool_header_by_key(&tool_key).unwrap().version, "1.3.0");
+        assert_eq!(reference(Some("2.0.0")).unwrap(), "2.0.0");
+
+        assert_eq!(manager.rollback_tool(&tool_key).unwrap().version(), "1.2.0");
+        assert!(manager.rollback_tool(&tool_key).is_err());
+        assert_eq!(reference(Some("^1.2")).unwrap(), "1.2.0");
+
+        // Removing the active version goes back to the latest one
+        manager.remove_tool(&tool_key, Some("1.2.0".to_string())).unwrap();
+        assert_eq!(manager.get_tool_active_version(&tool_key).unwrap(), None);
+        assert_eq!(manager.get_tool_by_key(&tool_key).unwrap().version(), "2.0.0");
+
+        manager.set_tool_active_version(&tool_key, Some(1_003_000)).unwrap();
+        assert_eq!(manager.get_tool_by_key(&tool_key).unwrap().version(), "1.3.0");
+        assert!(manager.set_tool_active_version(&tool_key, Some(1_001_000)).is_err());
+        manager.set_tool_active_version(&tool_key, None).unwrap();
+        assert_eq!(manager.get_tool_by_key(&tool_key).unwrap().version(), "2.0.0");
+    }
+
     #[tokio::test]
     async fn test_upgrade_tool_preserves_config() {
         let manager = setup_test_db().await;
@@ -2441,6 +2588,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
         let zoo_tool_v1 = ZooTool::Deno(deno_tool_v1.clone(), true);
         let vector_v1 = SqliteManager::generate_vector_for_testing(0.1);
@@ -2487,6 +2635,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
         let zoo_tool_v2 = ZooTool::Deno(deno_tool_v2.clone(), true);
 
@@ -2565,6 +2714,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
         let zoo_tool_v1 = ZooTool::Python(python_tool_v1, true);
         manager
@@ -2608,6 +2758,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
         let zoo_tool_v2 = ZooTool::Python(python_tool_v2, true);
         let upgraded = manager
@@ -2651,6 +2802,7 @@ mod tests {
             js_code: "console.log('A1');".to_string(),
             description: "Tool A1 description".to_string(),
             tool_set: Some("Set A".to_string()),
+            permissions: None,
             homepage: None,
             mcp_enabled: Some(false),
             tools: vec![],
@@ -2679,6 +2831,7 @@ mod tests {
             js_code: "console.log('B1');".to_string(),
             description: "Tool B1 description".to_string(),
             tool_set: Some("Set B".to_string()),
+            permissions: None,
             homepage: None,
             mcp_enabled: Some(false),
             tools: vec![],
@@ -2707,6 +2860,7 @@ mod tests {
             py_code: "print('A2')".to_string(),
             description: "Tool A2 description".to_string(),
             tool_set: Some("Set A".to_string()),
+            permissions: None,
             homepage: None,
             mcp_enabled: Some(false),
             tools: vec![],
@@ -2735,6 +2889,7 @@ mod tests {
             js_code: "console.log('C1');".to_string(),
             description: "Tool C1 description".to_string(),
             tool_set: None, // No tool set assigned
+            permissions: None,
             homepage: None,
             mcp_enabled: Some(false),
             tools: vec![],
@@ -2802,6 +2957,7 @@ mod tests {
             js_code: "console.log('TS1');".to_string(),
             description: "Tool TS1 description".to_string(),
             tool_set: Some(tool_set_name.to_string()),
+            permissions: None,
             config: vec![
                 ToolConfig::BasicConfig(BasicConfig {
                     key_name: "api_key".to_string(),
@@ -2851,6 +3007,7 @@ mod tests {
             py_code: "print('PY1')".to_string(),
             description: "Tool PY1 description".to_string(),
             tool_set: Some(tool_set_name.to_string()),
+            permissions: None,
             config: vec![
                 ToolConfig::BasicConfig(BasicConfig {
                     key_name: "api_key".to_string(), // Same key as tool1
@@ -2901,6 +3058,7 @@ mod tests {
             js_code: "console.log('TS2');".to_string(),
             description: "Tool TS2 description".to_string(),
             tool_set: Some("AnotherSet".to_string()), // Different set
+            permissions: None,
             config: vec![ToolConfig::BasicConfig(BasicConfig {
                 key_name: "api_key".to_string(),
                 description: "API Key".to_string(),
@@ -3044,6 +3202,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Linux],
             tool_set: None,
+            permissions: None,
         };
 
         // Wrap the PythonTool in a ZooTool::Python variant
@@ -3128,6 +3287,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
         ZooTool::Deno(deno_tool_data, true)
     }
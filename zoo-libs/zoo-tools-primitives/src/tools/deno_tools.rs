@@ -1,6 +1,7 @@
 use super::parameters::Parameters;
 use super::tool_config::{OAuth, ToolConfig};
 use super::tool_output_arg::ToolOutputArg;
+use super::tool_permissions::ToolPermissions;
 use super::tool_playground::{SqlQuery, SqlTable};
 use super::tool_types::{OperatingSystem, RunnerType, ToolResult};
 use crate::tools::error::ToolError;
@@ -49,6 +50,7 @@ pub struct DenoTool {
     pub runner: RunnerType,
     pub operating_system: Vec<OperatingSystem>,
     pub tool_set: Option<String>,
+    pub permissions: Option<ToolPermissions>,
 }
 
 impl<'de> serde::Deserialize<'de> for DenoTool {
@@ -86,6 +88,8 @@ impl<'de> serde::Deserialize<'de> for DenoTool {
             runner: RunnerType,
             operating_system: Vec<OperatingSystem>,
             tool_set: Option<String>,
+            #[serde(default)]
+            permissions: Option<ToolPermissions>,
         }
 
         let helper = Helper::deserialize(deserializer)?;
@@ -125,6 +129,7 @@ impl<'de> serde::Deserialize<'de> for DenoTool {
             runner: helper.runner,
             operating_system: helper.operating_system,
             tool_set: helper.tool_set,
+            permissions: helper.permissions,
         })
     }
 }
@@ -135,7 +140,7 @@ impl serde::Serialize for DenoTool {
         S: serde::Serializer,
     {
         use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("DenoTool", 24)?;
+        let mut state = serializer.serialize_struct("DenoTool", 25)?;
         state.serialize_field("name", &self.name)?;
         if let Some(key) = &self.tool_router_key {
             state.serialize_field("tool_router_key", &key.to_string_with_version())?;
@@ -165,6 +170,7 @@ impl serde::Serialize for DenoTool {
         state.serialize_field("runner", &self.runner)?;
         state.serialize_field("operating_system", &self.operating_system)?;
         state.serialize_field("tool_set", &self.tool_set)?;
+        state.serialize_field("permissions", &self.permissions)?;
         state.end()
     }
 }
@@ -194,6 +200,7 @@ impl DenoTool {
         runner: RunnerType,
         operating_system: Vec<OperatingSystem>,
         tool_set: Option<String>,
+        permissions: Option<ToolPermissions>,
     ) -> Self {
         let tool_router_key = ToolRouterKey::new("local".to_string(), author.clone(), name.clone(), None);
 
@@ -222,6 +229,7 @@ impl DenoTool {
             runner,
             operating_system,
             tool_set,
+            permissions,
         }
     }
 
@@ -371,6 +379,28 @@ impl DenoTool {
             code_files.insert(format!("{}.ts", file_name), file_code.clone());
         });
 
+        let mut deno_binary_path = PathBuf::from(
+            env::var("ZOO_TOOLS_RUNNER_DENO_BINARY_PATH")
+                .unwrap_or_else(|_| "./zoo-tools-runner-resources/deno".to_string()),
+        );
+
+        // Tools with a permission manifest run on the host through a launcher that replaces the
+        // runner's permission flags with the ones from the manifest. Tools that only run in Docker
+        // keep their container
+        let permissions = self
+            .permissions
+            .as_ref()
+            .map(|p| p.for_execution(&full_path, &format!("{}:{}", api_ip, api_port)));
+        let mut force_runner_type = None;
+        if let Some(permissions) = permissions
+            .as_ref()
+            .filter(|_| ToolPermissions::runs_on_host(&self.runner))
+        {
+            let sandbox_path = ToolPermissions::sandbox_path(Path::new(&node_storage_path), &app_id);
+            deno_binary_path = permissions.write_deno_launcher(&deno_binary_path, &sandbox_path)?;
+            force_runner_type = Some(zoo_tools_runner::tools::runner_type::RunnerType::Host);
+        }
+
         // Setup the engine with the code files and config
         let tool = DenoRunner::new(
             CodeFiles {
@@ -387,27 +417,32 @@ impl DenoTool {
                     assets_files,
                     mount_files,
                 },
-                deno_binary_path: PathBuf::from(
-                    env::var("ZOO_TOOLS_RUNNER_DENO_BINARY_PATH")
-                        .unwrap_or_else(|_| "./zoo-tools-runner-resources/deno".to_string()),
-                ),
+                deno_binary_path,
                 zoo_node_location: ZooNodeLocation {
                     protocol: String::from("http"),
                     host: api_ip,
                     port: api_port,
                 },
+                force_runner_type,
                 ..Default::default()
             }),
         );
 
         // Run the tool with DENO
         let result = tool
-            .run(Some(envs), serde_json::Value::Object(parameters.clone()), None)
+            .run(
+                Some(envs),
+                serde_json::Value::Object(parameters.clone()),
+                permissions.as_ref().and_then(|p| p.execution_timeout()),
+            )
             .await;
 
         print_result(&result);
         match result {
             Ok(result) => {
+                if let Some(permissions) = &permissions {
+                    permissions.check_output(&result.data)?;
+                }
                 update_result_with_modified_files(result, start_time, &home_path, &logs_path, &node_name, &app_id)
             }
             Err(e) => {
@@ -685,6 +720,7 @@ impl DenoTool {
             runner: self.runner.clone(),
             operating_system: self.operating_system.clone(),
             tool_set: self.tool_set.clone(),
+            permissions: self.permissions.clone(),
         }
     }
 }
@@ -899,6 +935,7 @@ mod tests {
             runner: RunnerType::Any,
             operating_system: vec![OperatingSystem::Linux],
             tool_set: None,
+            permissions: None,
         };
 
         let serialized = serde_json::to_string_pretty(&tool).expect("Failed to serialize DenoTool");
@@ -1025,6 +1062,7 @@ mod tests {
             runner: RunnerType::OnlyDocker,
             operating_system: vec![],
             tool_set: None,
+            permissions: None,
         };
 
         // Test serialization/deserialization with RunnerType
@@ -1073,6 +1111,7 @@ mod tests {
             runner: RunnerType::Any,
             operating_system: vec![OperatingSystem::Linux, OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         // Test serialization/deserialization with operating systems
@@ -1116,6 +1155,7 @@ mod tests {
             runner: RunnerType::Any,
             operating_system: vec![],
             tool_set: Some("test-tool-set".to_string()),
+            permissions: None,
         };
 
         // Test serialization/deserialization with tool_set
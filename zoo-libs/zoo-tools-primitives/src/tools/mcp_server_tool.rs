@@ -7,6 +7,7 @@ use crate::tools::error::ToolError;
 use rmcp::model::{CallToolResult, Content};
 use serde_json::Value;
 use zoo_mcp::mcp_methods::{run_tool_via_command, run_tool_via_http, run_tool_via_sse};
+use zoo_mcp::session_pool::{McpServerSpec, McpSessionPool, McpTransportSpec};
 use zoo_message_primitives::schemas::mcp_server::{MCPServer, MCPServerType};
 use zoo_message_primitives::schemas::tool_router_key::ToolRouterKey;
 use zoo_tools_runner::tools::run_result::RunResult;
@@ -72,6 +73,7 @@ impl MCPServerTool {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Linux, OperatingSystem::MacOS, OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         }
     }
 
@@ -119,21 +121,47 @@ impl MCPServerTool {
         })
     }
 
+    /// Runs the tool on the pooled session of the server. Servers that aren't stored yet, and so
+    /// have no id, get a one-off client instead.
     pub async fn run_tool(
         mcp_server: MCPServer,
         tool: String,
         env: HashMap<String, String>,
         parameters: serde_json::Map<String, serde_json::Value>,
     ) -> Result<CallToolResult, zoo_mcp::error::McpError> {
-        match mcp_server.r#type {
-            MCPServerType::Command => {
-                run_tool_via_command(mcp_server.command.unwrap_or_default(), tool, env, parameters).await
-            }
-            MCPServerType::Sse => run_tool_via_sse(mcp_server.url.unwrap_or_default(), tool, parameters).await,
-            MCPServerType::Http => run_tool_via_http(mcp_server.url.unwrap_or_default(), tool, parameters).await,
+        match MCPServerTool::session_spec(&mcp_server, env.clone()) {
+            Some(spec) => McpSessionPool::global().call_tool(&spec, tool, parameters).await,
+            None => match mcp_server.r#type {
+                MCPServerType::Command => {
+                    run_tool_via_command(mcp_server.command.unwrap_or_default(), tool, env, parameters).await
+                }
+                MCPServerType::Sse => run_tool_via_sse(mcp_server.url.unwrap_or_default(), tool, parameters).await,
+                MCPServerType::Http => run_tool_via_http(mcp_server.url.unwrap_or_default(), tool, parameters).await,
+            },
         }
     }
 
+    /// How the session pool reaches a stored MCP server.
+    pub fn session_spec(mcp_server: &MCPServer, env: HashMap<String, String>) -> Option<McpServerSpec> {
+        let transport = match mcp_server.r#type {
+            MCPServerType::Command => McpTransportSpec::Command {
+                command: mcp_server.command.clone().unwrap_or_default(),
+                env,
+            },
+            MCPServerType::Sse => McpTransportSpec::Sse {
+                url: mcp_server.url.clone().unwrap_or_default(),
+            },
+            MCPServerType::Http => McpTransportSpec::Http {
+                url: mcp_server.url.clone().unwrap_or_default(),
+            },
+        };
+        Some(McpServerSpec {
+            id: mcp_server.id?,
+            name: mcp_server.name.clone(),
+            transport,
+        })
+    }
+
     pub async fn map_content_to_error_message(content: Vec<Content>) -> String {
         content
             .iter()
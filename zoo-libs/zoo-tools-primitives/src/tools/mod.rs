@@ -7,10 +7,12 @@ pub mod network_tool;
 pub mod parameters;
 pub mod python_tools;
 pub mod rust_tools;
+pub mod schema_diff;
 pub mod shared_execution;
 pub mod zoo_tool;
 pub mod tool_config;
 pub mod tool_output_arg;
+pub mod tool_permissions;
 pub mod tool_playground;
 pub mod tool_router_dep;
 pub mod tool_types;
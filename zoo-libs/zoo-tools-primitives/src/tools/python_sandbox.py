@@ -0,0 +1,129 @@
+# Loaded as `sitecustomize` by the Python interpreter that runs a tool with a permission
+# manifest. It applies the manifest from ZOO_TOOL_PERMISSIONS before the tool code is imported.
+# The audit hooks run in the tool's own process and native code skips them, so this catches
+# mistakes but doesn't contain a hostile tool: only running the tool in Docker does.
+import json
+import os
+import sys
+
+
+def _install(manifest):
+    import socket
+    import tempfile
+
+    def _real(path):
+        if isinstance(path, bytes):
+            path = os.fsdecode(path)
+        return os.path.realpath(os.path.abspath(path))
+
+    def _under(path, roots):
+        return any(path == root or path.startswith(root.rstrip(os.sep) + os.sep) for root in roots)
+
+    write_roots = [_real(p) for p in manifest.get("write_paths", [])]
+    write_roots.append(_real(tempfile.gettempdir()))
+    read_roots = write_roots + [_real(p) for p in manifest.get("read_paths", [])]
+    # The interpreter, its standard library and the installed dependencies
+    for path in [sys.prefix, sys.base_prefix, sys.exec_prefix, sys.base_exec_prefix] + sys.path:
+        if path:
+            read_roots.append(_real(path))
+    read_roots += ["/dev/null", "/dev/urandom", "/dev/random", "/usr/share/zoneinfo", "/etc/ssl"]
+
+    allowed_hosts = manifest.get("allowed_domains", [])
+    allow_subprocess = manifest.get("allow_subprocess", False)
+    resolved = set()
+
+    def _host_allowed(host, port):
+        host = str(host).lower().strip("[]")
+        for entry in allowed_hosts:
+            entry_host, _, entry_port = entry.lower().partition(":")
+            if entry_host == host and (not entry_port or entry_port == str(port)):
+                return True
+        return False
+
+    def _check_read(path):
+        if isinstance(path, (str, bytes)) and not _under(_real(path), read_roots):
+            raise PermissionError("Tool permissions don't allow reading {}".format(os.fsdecode(path)))
+
+    def _check_write(path):
+        if isinstance(path, (str, bytes)) and not _under(_real(path), write_roots):
+            raise PermissionError("Tool permissions don't allow writing {}".format(os.fsdecode(path)))
+
+    write_flags = os.O_WRONLY | os.O_RDWR | os.O_APPEND | os.O_CREAT | os.O_TRUNC
+    write_events = {
+        "os.remove", "os.rmdir", "os.mkdir", "os.chmod", "os.chown", "os.truncate", "os.utime",
+        "os.symlink", "os.link", "shutil.rmtree", "shutil.copyfile", "shutil.copymode", "shutil.copystat",
+    }
+    subprocess_events = {
+        "subprocess.Popen", "os.system", "os.exec", "os.posix_spawn", "os.spawn", "os.fork",
+        "os.forkpty", "os.startfile", "pty.spawn",
+    }
+
+    def _hook(event, args):
+        if event == "open":
+            path, mode, flags = args
+            if (mode and any(c in mode for c in "wax+")) or (mode is None and flags & write_flags):
+                _check_write(path)
+            else:
+                _check_read(path)
+        elif event in ("os.listdir", "os.scandir"):
+            _check_read(args[0])
+        elif event in write_events:
+            _check_write(args[0])
+            if event in ("os.symlink", "os.link", "shutil.copyfile"):
+                _check_write(args[1])
+        elif event == "os.rename":
+            _check_write(args[0])
+            _check_write(args[1])
+        elif event in subprocess_events and not allow_subprocess:
+            raise PermissionError("Tool permissions don't allow starting processes")
+        elif event == "socket.getaddrinfo":
+            if args[0] is not None and not _host_allowed(args[0], args[1]):
+                raise PermissionError("Tool permissions don't allow connecting to {}".format(args[0]))
+        elif event == "socket.connect":
+            address = args[1]
+            if isinstance(address, tuple):
+                if address[0] not in resolved and not _host_allowed(address[0], address[1]):
+                    raise PermissionError("Tool permissions don't allow connecting to {}".format(address[0]))
+            else:
+                _check_write(address)
+
+    getaddrinfo = socket.getaddrinfo
+
+    # Remember the addresses of allowed hosts, connections are made to the resolved address
+    def _getaddrinfo(host, port, *args, **kwargs):
+        results = getaddrinfo(host, port, *args, **kwargs)
+        if host is not None and _host_allowed(host, port):
+            resolved.update(result[4][0] for result in results)
+        return results
+
+    socket.getaddrinfo = _getaddrinfo
+
+    def _env_allowed(name):
+        return any(
+            name.startswith(pattern[:-1]) if pattern.endswith("*") else name == pattern
+            for pattern in manifest.get("env_vars", [])
+        )
+
+    for name in list(os.environ):
+        if not _env_allowed(name):
+            del os.environ[name]
+
+    try:
+        import resource
+
+        if manifest.get("max_cpu_time_secs"):
+            seconds = int(manifest["max_cpu_time_secs"])
+            resource.setrlimit(resource.RLIMIT_CPU, (seconds, seconds))
+        if manifest.get("max_memory_mb"):
+            memory = int(manifest["max_memory_mb"]) * 1024 * 1024
+            resource.setrlimit(resource.RLIMIT_AS, (memory, memory))
+    except ImportError:
+        # Not available on Windows, the node still applies the time limit
+        pass
+
+    sys.addaudithook(_hook)
+
+
+_manifest = os.environ.get("ZOO_TOOL_PERMISSIONS")
+if _manifest:
+    _install(json.loads(_manifest))
@@ -2,6 +2,7 @@ use super::parameters::Parameters;
 use super::shared_execution::update_result_with_modified_files;
 use super::tool_config::{OAuth, ToolConfig};
 use super::tool_output_arg::ToolOutputArg;
+use super::tool_permissions::ToolPermissions;
 use super::tool_playground::{SqlQuery, SqlTable};
 use super::tool_types::{OperatingSystem, RunnerType, ToolResult};
 use crate::tools::error::ToolError;
@@ -48,6 +49,7 @@ pub struct PythonTool {
     pub runner: RunnerType,
     pub operating_system: Vec<OperatingSystem>,
     pub tool_set: Option<String>,
+    pub permissions: Option<ToolPermissions>,
 }
 
 impl PythonTool {
@@ -307,6 +309,24 @@ impl PythonTool {
             assets_files = original_path;
         }
 
+        // Tools with a permission manifest run on the host, where the interpreter loads a module
+        // that applies the manifest before the tool code runs. The module is advisory, tools that
+        // only run in Docker keep their container instead
+        let mut envs = envs;
+        let permissions = self
+            .permissions
+            .as_ref()
+            .map(|p| p.for_execution(&full_path, &format!("{}:{}", api_ip, api_port)));
+        let mut force_runner_type = None;
+        if let Some(permissions) = permissions
+            .as_ref()
+            .filter(|_| ToolPermissions::runs_on_host(&self.runner))
+        {
+            let sandbox_path = ToolPermissions::sandbox_path(Path::new(&node_storage_path), &app_id);
+            envs.extend(permissions.write_python_sandbox(&sandbox_path, &envs)?);
+            force_runner_type = Some(zoo_tools_runner::tools::runner_type::RunnerType::Host);
+        }
+
         // Setup the engine with the code files and config
         let tool = PythonRunner::new(
             CodeFiles {
@@ -337,18 +357,26 @@ impl PythonTool {
                     host: api_ip,
                     port: api_port,
                 },
+                force_runner_type,
                 ..Default::default()
             }),
         );
 
         // Run the tool with Python
         let result = tool
-            .run(Some(envs), serde_json::Value::Object(parameters.clone()), None)
+            .run(
+                Some(envs),
+                serde_json::Value::Object(parameters.clone()),
+                permissions.as_ref().and_then(|p| p.execution_timeout()),
+            )
             .await;
         print_result(&result);
 
         match result {
             Ok(result) => {
+                if let Some(permissions) = &permissions {
+                    permissions.check_output(&result.data)?;
+                }
                 update_result_with_modified_files(result, start_time, &home_path, &logs_path, &node_name, &app_id)
             }
             Err(e) => {
@@ -409,6 +437,7 @@ impl PythonTool {
             runner: self.runner.clone(),
             operating_system: self.operating_system.clone(),
             tool_set: self.tool_set.clone(),
+            permissions: self.permissions.clone(),
         }
     }
 }
@@ -446,6 +475,8 @@ impl<'de> serde::Deserialize<'de> for PythonTool {
             runner: RunnerType,
             operating_system: Vec<OperatingSystem>,
             tool_set: Option<String>,
+            #[serde(default)]
+            permissions: Option<ToolPermissions>,
         }
 
         let helper = Helper::deserialize(deserializer)?;
@@ -485,6 +516,7 @@ impl<'de> serde::Deserialize<'de> for PythonTool {
             runner: helper.runner,
             operating_system: helper.operating_system,
             tool_set: helper.tool_set,
+            permissions: helper.permissions,
         })
     }
 }
@@ -495,7 +527,7 @@ impl serde::Serialize for PythonTool {
         S: serde::Serializer,
     {
         use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("PythonTool", 24)?;
+        let mut state = serializer.serialize_struct("PythonTool", 25)?;
         state.serialize_field("name", &self.name)?;
         if let Some(key) = &self.tool_router_key {
             state.serialize_field("tool_router_key", &key.to_string_with_version())?;
@@ -525,6 +557,7 @@ impl serde::Serialize for PythonTool {
         state.serialize_field("runner", &self.runner)?;
         state.serialize_field("operating_system", &self.operating_system)?;
         state.serialize_field("tool_set", &self.tool_set)?;
+        state.serialize_field("permissions", &self.permissions)?;
         state.end()
     }
 }
@@ -565,6 +598,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         assert_eq!(tool.runner, RunnerType::OnlyHost);
@@ -602,6 +636,7 @@ mod tests {
             runner: RunnerType::Any,
             operating_system: vec![OperatingSystem::Linux, OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         assert_eq!(tool.operating_system.len(), 2);
@@ -641,6 +676,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Linux],
             tool_set: Some("test_set".to_string()),
+            permissions: None,
         };
 
         assert_eq!(tool.tool_set, Some("test_set".to_string()));
@@ -678,6 +714,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Linux],
             tool_set: Some("test_set".to_string()),
+            permissions: None,
         };
 
         let json = tool.to_json().unwrap();
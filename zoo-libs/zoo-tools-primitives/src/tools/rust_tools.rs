@@ -121,6 +121,7 @@ impl RustTool {
             runner: super::tool_types::RunnerType::Any,
             operating_system: vec![],
             tool_set: None,
+            permissions: None,
         }
     }
 }
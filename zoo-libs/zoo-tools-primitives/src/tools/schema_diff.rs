@@ -0,0 +1,218 @@
+use std::collections::{BTreeSet, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Which side of a tool a schema describes. Callers fill the input, so new requirements break
+/// them. Consumers read the output, so anything that disappears or changes type breaks them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaDirection {
+    Input,
+    Output,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum SchemaChangeKind {
+    Added { required: bool },
+    Removed,
+    TypeChanged { from: String, to: String },
+    BecameRequired,
+    BecameOptional,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaChange {
+    /// Nested properties are joined with `.`, array items are marked with `[]`.
+    pub path: String,
+    #[serde(flatten)]
+    pub kind: SchemaChangeKind,
+    pub breaking: bool,
+}
+
+/// Compares two JSON schemas property by property, including nested objects and array items.
+pub fn diff_schemas(from: &Value, to: &Value, direction: SchemaDirection) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+    diff_properties("", from, to, direction, &mut changes);
+    changes
+}
+
+fn diff_properties(
+    prefix: &str,
+    from: &Value,
+    to: &Value,
+    direction: SchemaDirection,
+    changes: &mut Vec<SchemaChange>,
+) {
+    let empty = Map::new();
+    let from_properties = from.get("properties").and_then(Value::as_object).unwrap_or(&empty);
+    let to_properties = to.get("properties").and_then(Value::as_object).unwrap_or(&empty);
+    let from_required = required_properties(from);
+    let to_required = required_properties(to);
+
+    let names: BTreeSet<&String> = from_properties.keys().chain(to_properties.keys()).collect();
+    for name in names {
+        let path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        let was_required = from_required.contains(name.as_str());
+        let is_required = to_required.contains(name.as_str());
+
+        match (from_properties.get(name), to_properties.get(name)) {
+            (None, Some(_)) => changes.push(SchemaChange {
+                path,
+                kind: SchemaChangeKind::Added { required: is_required },
+                breaking: direction == SchemaDirection::Input && is_required,
+            }),
+            (Some(_), None) => changes.push(SchemaChange {
+                path,
+                kind: SchemaChangeKind::Removed,
+                breaking: true,
+            }),
+            (Some(from_property), Some(to_property)) => {
+                if diff_property(&path, from_property, to_property, direction, changes) {
+                    continue;
+                }
+                if !was_required && is_required {
+                    changes.push(SchemaChange {
+                        path,
+                        kind: SchemaChangeKind::BecameRequired,
+                        breaking: direction == SchemaDirection::Input,
+                    });
+                } else if was_required && !is_required {
+                    changes.push(SchemaChange {
+                        path,
+                        kind: SchemaChangeKind::BecameOptional,
+                        breaking: direction == SchemaDirection::Output,
+                    });
+                }
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+/// Returns true if the type changed, in which case the nested schema isn't compared.
+fn diff_property(
+    path: &str,
+    from: &Value,
+    to: &Value,
+    direction: SchemaDirection,
+    changes: &mut Vec<SchemaChange>,
+) -> bool {
+    let from_type = schema_type(from);
+    let to_type = schema_type(to);
+    if from_type != to_type {
+        changes.push(SchemaChange {
+            path: path.to_string(),
+            kind: SchemaChangeKind::TypeChanged {
+                from: from_type,
+                to: to_type,
+            },
+            breaking: true,
+        });
+        return true;
+    }
+
+    diff_properties(path, from, to, direction, changes);
+    if let (Some(from_items), Some(to_items)) = (from.get("items"), to.get("items")) {
+        diff_property(&format!("{}[]", path), from_items, to_items, direction, changes);
+    }
+    false
+}
+
+fn schema_type(schema: &Value) -> String {
+    match schema.get("type") {
+        Some(Value::String(schema_type)) => schema_type.clone(),
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect::<Vec<_>>().join("|"),
+        _ => "any".to_string(),
+    }
+}
+
+fn required_properties(schema: &Value) -> HashSet<&str> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_input_schemas() {
+        let from = json!({
+            "type": "object",
+            "properties": {
+                "url": {"type": "string"},
+                "limit": {"type": "number"},
+                "format": {"type": "string"},
+                "options": {"type": "object", "properties": {"depth": {"type": "number"}}}
+            },
+            "required": ["url", "format"]
+        });
+        let to = json!({
+            "type": "object",
+            "properties": {
+                "url": {"type": "string"},
+                "limit": {"type": "string"},
+                "format": {"type": "string"},
+                "options": {"type": "object", "properties": {"depth": {"type": "number"}, "follow": {"type": "boolean"}}},
+                "api_version": {"type": "string"}
+            },
+            "required": ["url", "api_version"]
+        });
+
+        let changes = diff_schemas(&from, &to, SchemaDirection::Input);
+        let summary: Vec<(&str, bool)> = changes.iter().map(|c| (c.path.as_str(), c.breaking)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("api_version", true),
+                ("format", false),
+                ("limit", true),
+                ("options.follow", false)
+            ]
+        );
+        assert_eq!(changes[0].kind, SchemaChangeKind::Added { required: true });
+        assert_eq!(changes[1].kind, SchemaChangeKind::BecameOptional);
+        assert_eq!(
+            changes[2].kind,
+            SchemaChangeKind::TypeChanged {
+                from: "number".to_string(),
+                to: "string".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_diff_output_schemas() {
+        let from = json!({
+            "type": "object",
+            "properties": {
+                "items": {"type": "array", "items": {"type": "object", "properties": {"id": {"type": "string"}, "title": {"type": "string"}}}},
+                "total": {"type": "number"}
+            },
+            "required": ["items", "total"]
+        });
+        let to = json!({
+            "type": "object",
+            "properties": {
+                "items": {"type": "array", "items": {"type": "object", "properties": {"id": {"type": "string"}}}},
+                "total": {"type": "number"},
+                "next": {"type": "string"}
+            },
+            "required": ["items"]
+        });
+
+        let changes = diff_schemas(&from, &to, SchemaDirection::Output);
+        let summary: Vec<(&str, bool)> = changes.iter().map(|c| (c.path.as_str(), c.breaking)).collect();
+        assert_eq!(summary, vec![("items[].title", true), ("next", false), ("total", true)]);
+        assert!(diff_schemas(&json!({}), &json!({}), SchemaDirection::Output).is_empty());
+    }
+}
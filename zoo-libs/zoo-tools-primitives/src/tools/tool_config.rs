@@ -249,6 +249,28 @@ pub struct BasicConfig {
     pub key_value: Option<serde_json::Value>,
 }
 
+/// Words in a config name that mark its value as a credential.
+const SECRET_CONFIG_WORDS: [&str; 8] = [
+    "key",
+    "token",
+    "secret",
+    "password",
+    "passphrase",
+    "credential",
+    "auth",
+    "private",
+];
+
+impl BasicConfig {
+    /// Whether the value is a credential, going by its name or type: `apiKey`, `access_token`,
+    /// `DB_PASSWORD` or a `secret` type. Only these values are encrypted at rest.
+    pub fn is_secret(&self) -> bool {
+        let key_name = self.key_name.to_lowercase();
+        matches!(self.type_name.as_deref(), Some("secret") | Some("password"))
+            || SECRET_CONFIG_WORDS.iter().any(|word| key_name.contains(word))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -416,4 +438,21 @@ mod tests {
             panic!("object_key config not found");
         }
     }
+
+    #[test]
+    fn test_is_secret() {
+        let config = |key_name: &str, type_name: &str| BasicConfig {
+            key_name: key_name.to_string(),
+            description: String::new(),
+            required: true,
+            type_name: Some(type_name.to_string()),
+            key_value: None,
+        };
+        assert!(config("apiKey", "string").is_secret());
+        assert!(config("ACCESS_TOKEN", "string").is_secret());
+        assert!(config("db_password", "string").is_secret());
+        assert!(config("connection", "secret").is_secret());
+        assert!(!config("city", "string").is_secret());
+        assert!(!config("max_results", "number").is_secret());
+    }
 }
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::error::ToolError;
+use super::tool_types::RunnerType;
+
+/// The `sitecustomize` module that applies the manifest inside the Python interpreter. It runs in
+/// the same process as the tool, so it's advisory: it stops mistakes, not a hostile tool.
+const PYTHON_SANDBOX: &str = include_str!("python_sandbox.py");
+
+/// Environment variables the node sets for every tool, they are always readable.
+const NODE_ENV_VARS: [&str; 3] = ["BEARER", "X_ZOO_*", "ZOO_*"];
+
+/// Process basics a Python interpreter and its subprocesses rely on. Deno doesn't need them
+/// to be readable by the tool.
+const PYTHON_PROCESS_ENV_VARS: [&str; 9] = [
+    "PATH",
+    "HOME",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "LANG",
+    "LC_ALL",
+    "SYSTEMROOT",
+    "PYTHONPATH",
+];
+
+/// Declares what a Deno or Python tool is allowed to do. Tools without a manifest keep the
+/// runner defaults. With one, anything that isn't listed is denied: Deno gets the matching
+/// `--allow-*` flags, which the runtime enforces. Python runs with audit hooks that apply it
+/// from inside the interpreter, which a tool can get around, so for Python the manifest is
+/// advisory and only Docker isolates the tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolPermissions {
+    /// Hosts the tool can connect to, optionally with a port: `api.example.com`, `localhost:8080`.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    /// Absolute paths the tool can read, on top of its own storage.
+    #[serde(default)]
+    pub read_paths: Vec<String>,
+    /// Absolute paths the tool can write, on top of its own storage.
+    #[serde(default)]
+    pub write_paths: Vec<String>,
+    /// Environment variables the tool can read, on top of the ones set by the node.
+    #[serde(default)]
+    pub env_vars: Vec<String>,
+    #[serde(default)]
+    pub allow_subprocess: bool,
+    pub max_cpu_time_secs: Option<u64>,
+    pub max_memory_mb: Option<u64>,
+    pub max_output_bytes: Option<u64>,
+}
+
+impl ToolPermissions {
+    /// Checks that every entry can be translated to a runtime flag as is.
+    pub fn validate(&self) -> Result<(), ToolError> {
+        let mut errors = Vec::new();
+        for domain in &self.allowed_domains {
+            let (host, port) = match domain.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (domain.as_str(), None),
+            };
+            let valid_host = !host.is_empty()
+                && host
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_');
+            if !valid_host || port.is_some_and(|port| port.parse::<u16>().is_err()) {
+                errors.push(format!(
+                    "Invalid domain '{}', expected a host with an optional port",
+                    domain
+                ));
+            }
+        }
+        for path in self.read_paths.iter().chain(&self.write_paths) {
+            if !Path::new(path).is_absolute() || path.contains(',') {
+                errors.push(format!("Invalid path '{}', expected an absolute path", path));
+            }
+        }
+        for name in &self.env_vars {
+            let valid_name = !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid_name {
+                errors.push(format!("Invalid environment variable name '{}'", name));
+            }
+        }
+        if self.max_cpu_time_secs == Some(0) || self.max_memory_mb == Some(0) || self.max_output_bytes == Some(0) {
+            errors.push("Limits must be greater than zero".to_string());
+        }
+
+        if !errors.is_empty() {
+            return Err(ToolError::InvalidPermissions(errors.join("\n")));
+        }
+        Ok(())
+    }
+
+    /// Whether the manifest is applied on the host, through the Deno launcher or the Python module.
+    /// Tools that only run in Docker stay in their container, where only the time and output
+    /// limits apply.
+    pub fn runs_on_host(runner: &RunnerType) -> bool {
+        *runner != RunnerType::OnlyDocker
+    }
+
+    /// Where the node writes the launcher and the Python module of a tool. It's outside the tools
+    /// storage, which every manifest lets the tool write, so a tool can't replace them.
+    pub fn sandbox_path(node_storage_path: &Path, app_id: &str) -> PathBuf {
+        node_storage_path.join("tools_sandbox").join(app_id)
+    }
+
+    /// The manifest as it's enforced for one execution: the tool can always reach the node API,
+    /// use its own storage folder and read the variables the node sets.
+    pub fn for_execution(&self, storage_path: &Path, node_host: &str) -> ToolPermissions {
+        let storage_path = storage_path.to_string_lossy().to_string();
+        let mut permissions = self.clone();
+        permissions.allowed_domains.insert(0, node_host.to_string());
+        permissions.read_paths.insert(0, storage_path.clone());
+        permissions.write_paths.insert(0, storage_path);
+        permissions
+            .env_vars
+            .splice(0..0, NODE_ENV_VARS.iter().map(|name| name.to_string()));
+        permissions
+    }
+
+    /// The Deno flags that grant exactly this manifest.
+    pub fn deno_flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        // An empty list is left out, `--allow-net=` would grant everything
+        for (flag, values) in [
+            ("--allow-net", &self.allowed_domains),
+            ("--allow-read", &self.read_paths),
+            ("--allow-write", &self.write_paths),
+            ("--allow-env", &self.env_vars),
+        ] {
+            if !values.is_empty() {
+                flags.push(format!("{}={}", flag, values.join(",")));
+            }
+        }
+        if self.allow_subprocess {
+            flags.push("--allow-run".to_string());
+        }
+        if let Some(max_memory_mb) = self.max_memory_mb {
+            flags.push(format!("--v8-flags=--max-old-space-size={}", max_memory_mb));
+        }
+        flags
+    }
+
+    /// The runner can't take Deno flags, so the tool runs through a launcher that replaces the
+    /// permissions the runner grants with the ones of the manifest. Returns the launcher path,
+    /// to be used as the Deno binary.
+    pub fn write_deno_launcher(&self, deno_binary_path: &Path, sandbox_path: &Path) -> Result<PathBuf, ToolError> {
+        if cfg!(windows) {
+            return Err(ToolError::InvalidPermissions(
+                "Permission manifests for Deno tools are only enforced on Linux and macOS".to_string(),
+            ));
+        }
+
+        let deno_binary_path = std::path::absolute(deno_binary_path)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to resolve the Deno binary: {}", e)))?;
+        let flags: Vec<String> = self.deno_flags().iter().map(|flag| shell_quote(flag)).collect();
+        let deno = shell_quote(&deno_binary_path.to_string_lossy());
+        let launcher = format!(
+            r#"#!/bin/sh
+# Generated by the node from the tool permission manifest
+if [ "$1" = "run" ]; then
+  shift
+  for arg do
+    shift
+    case "$arg" in
+      -A|--allow-all|--allow-net|--allow-net=*|--allow-read|--allow-read=*|--allow-write|--allow-write=*|--allow-env|--allow-env=*|--allow-run|--allow-run=*|--allow-ffi|--allow-ffi=*|--allow-sys|--allow-sys=*) ;;
+      *) set -- "$@" "$arg" ;;
+    esac
+  done
+  exec {deno} run {flags} "$@"
+fi
+exec {deno} "$@"
+"#,
+            deno = deno,
+            flags = flags.join(" "),
+        );
+
+        std::fs::create_dir_all(sandbox_path)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to create sandbox directory: {}", e)))?;
+        let launcher_path = sandbox_path.join("deno");
+        std::fs::write(&launcher_path, launcher)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to write Deno launcher: {}", e)))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&launcher_path, std::fs::Permissions::from_mode(0o755))
+                .map_err(|e| ToolError::ExecutionError(format!("Failed to make Deno launcher executable: {}", e)))?;
+        }
+        Ok(launcher_path)
+    }
+
+    /// Writes the module that applies the manifest inside the Python interpreter and returns the
+    /// environment variables that load it. The module hooks the interpreter audit events, which
+    /// native code (`ctypes`, `_posixsubprocess`) doesn't go through: it's not a sandbox.
+    pub fn write_python_sandbox(
+        &self,
+        sandbox_path: &Path,
+        envs: &HashMap<String, String>,
+    ) -> Result<HashMap<String, String>, ToolError> {
+        std::fs::create_dir_all(sandbox_path)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to create sandbox directory: {}", e)))?;
+        std::fs::write(sandbox_path.join("sitecustomize.py"), PYTHON_SANDBOX)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to write Python sandbox: {}", e)))?;
+
+        let mut permissions = self.clone();
+        permissions
+            .env_vars
+            .extend(PYTHON_PROCESS_ENV_VARS.iter().map(|name| name.to_string()));
+        let manifest = serde_json::to_string(&permissions).map_err(|e| ToolError::SerializationError(e.to_string()))?;
+
+        let python_path = match envs.get("PYTHONPATH") {
+            Some(existing) if !existing.is_empty() => {
+                std::env::join_paths(std::iter::once(sandbox_path.to_path_buf()).chain(std::env::split_paths(existing)))
+                    .map_err(|e| ToolError::ExecutionError(format!("Invalid PYTHONPATH: {}", e)))?
+                    .to_string_lossy()
+                    .to_string()
+            }
+            _ => sandbox_path.to_string_lossy().to_string(),
+        };
+
+        let mut sandbox_envs = HashMap::new();
+        sandbox_envs.insert("PYTHONPATH".to_string(), python_path);
+        sandbox_envs.insert("ZOO_TOOL_PERMISSIONS".to_string(), manifest);
+        Ok(sandbox_envs)
+    }
+
+    /// How long the runner lets the tool run. The CPU time limit is also applied to the process
+    /// where the runtime supports it.
+    pub fn execution_timeout(&self) -> Option<Duration> {
+        self.max_cpu_time_secs.map(Duration::from_secs)
+    }
+
+    pub fn check_output(&self, data: &Value) -> Result<(), ToolError> {
+        let Some(max_output_bytes) = self.max_output_bytes else {
+            return Ok(());
+        };
+        let size = serde_json::to_vec(data)
+            .map_err(|e| ToolError::SerializationError(e.to_string()))?
+            .len() as u64;
+        if size > max_output_bytes {
+            return Err(ToolError::ExecutionError(format!(
+                "Tool output is {} bytes, over the limit of {} bytes set by its permissions",
+                size, max_output_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn permissions() -> ToolPermissions {
+        ToolPermissions {
+            allowed_domains: vec!["api.example.com".to_string(), "localhost:8080".to_string()],
+            read_paths: vec!["/data/in".to_string()],
+            write_paths: vec!["/data/out".to_string()],
+            env_vars: vec!["API_KEY".to_string()],
+            allow_subprocess: false,
+            max_cpu_time_secs: Some(30),
+            max_memory_mb: Some(256),
+            max_output_bytes: Some(16),
+        }
+    }
+
+    #[test]
+    fn test_deno_flags_for_execution() {
+        let flags = permissions()
+            .for_execution(Path::new("/storage/tools"), "127.0.0.1:9550")
+            .deno_flags();
+        assert_eq!(
+            flags,
+            vec![
+                "--allow-net=127.0.0.1:9550,api.example.com,localhost:8080",
+                "--allow-read=/storage/tools,/data/in",
+                "--allow-write=/storage/tools,/data/out",
+                "--allow-env=BEARER,X_ZOO_*,ZOO_*,API_KEY",
+                "--v8-flags=--max-old-space-size=256",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sandbox_is_not_writable() {
+        let storage = Path::new("/node/storage");
+        let permissions = permissions().for_execution(&storage.join("tools_storage"), "127.0.0.1:9550");
+        let sandbox_path = ToolPermissions::sandbox_path(storage, "app-1");
+        assert!(!permissions
+            .write_paths
+            .iter()
+            .any(|path| sandbox_path.starts_with(path)));
+        assert!(ToolPermissions::runs_on_host(&RunnerType::Any));
+        assert!(!ToolPermissions::runs_on_host(&RunnerType::OnlyDocker));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(permissions().validate().is_ok());
+
+        let invalid = ToolPermissions {
+            allowed_domains: vec!["https://api.example.com".to_string(), "host:port".to_string()],
+            read_paths: vec!["relative/path".to_string()],
+            env_vars: vec!["NOT-VALID".to_string()],
+            max_memory_mb: Some(0),
+            ..Default::default()
+        };
+        let Err(ToolError::InvalidPermissions(errors)) = invalid.validate() else {
+            panic!("Expected invalid permissions");
+        };
+        assert_eq!(errors.lines().count(), 5);
+    }
+
+    #[test]
+    fn test_check_output() {
+        assert!(permissions().check_output(&json!("short")).is_ok());
+        assert!(permissions()
+            .check_output(&json!({"data": "too long for the limit"}))
+            .is_err());
+        assert!(ToolPermissions::default()
+            .check_output(&json!("no limit at all"))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_deserialize_partial_manifest() {
+        let permissions: ToolPermissions = serde_json::from_value(json!({
+            "allowed_domains": ["api.example.com"],
+            "max_output_bytes": 1024
+        }))
+        .unwrap();
+        assert_eq!(permissions.allowed_domains, vec!["api.example.com"]);
+        assert!(permissions.read_paths.is_empty());
+        assert!(!permissions.allow_subprocess);
+        assert_eq!(permissions.max_cpu_time_secs, None);
+    }
+}
@@ -11,6 +11,7 @@ use zoo_message_primitives::schemas::{
 
 use super::agent_tool_wrapper::AgentToolWrapper;
 use super::tool_config::OAuth;
+use super::tool_permissions::ToolPermissions;
 use super::tool_playground::{SqlQuery, SqlTable, ToolPlaygroundMetadata};
 use super::tool_types::{OperatingSystem, RunnerType};
 use super::{
@@ -59,6 +60,9 @@ pub struct ZooToolHeader {
     pub usage_type: Option<UsageType>, // includes pricing
     // Note: do we need usage_type? it's already contained in the tool_offering
     pub tool_offering: Option<ZooToolOffering>,
+    /// What the tool is allowed to do when it runs, for Deno and Python tools that declare it
+    #[serde(default)]
+    pub permissions: Option<ToolPermissions>,
 }
 
 impl ZooToolHeader {
@@ -88,6 +92,7 @@ impl ZooTool {
             config: self.get_js_tool_config().cloned(),
             usage_type: self.get_usage_type(),
             tool_offering: None,
+            permissions: self.get_permissions(),
         }
     }
 
@@ -314,6 +319,14 @@ impl ZooTool {
         }
     }
 
+    pub fn get_permissions(&self) -> Option<ToolPermissions> {
+        match self {
+            ZooTool::Deno(d, _) => d.permissions.clone(),
+            ZooTool::Python(p, _) => p.permissions.clone(),
+            _ => None,
+        }
+    }
+
     /// Sets the embedding for the tool
     pub fn set_embedding(&mut self, embedding: Vec<f32>) {
         match self {
@@ -510,6 +523,17 @@ impl ZooTool {
         }
     }
 
+    /// Mutable access to the config of the tools that have one
+    pub fn config_mut(&mut self) -> Option<&mut Vec<ToolConfig>> {
+        match self {
+            ZooTool::Network(network_tool, _) => Some(&mut network_tool.config),
+            ZooTool::Deno(js_tool, _) => Some(&mut js_tool.config),
+            ZooTool::Python(python_tool, _) => Some(&mut python_tool.config),
+            ZooTool::MCPServer(mcp_tool, _) => Some(&mut mcp_tool.config),
+            ZooTool::Rust(_, _) | ZooTool::Agent(_, _) => None,
+        }
+    }
+
     /// Check if the tool can be enabled
     pub fn can_be_enabled(&self) -> bool {
         match self {
@@ -682,6 +706,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Linux],
             tool_set: None,
+            permissions: None,
         };
 
         // Create a ZooTool instance
@@ -779,6 +804,7 @@ mod tests {
             runner: RunnerType::OnlyHost,
             operating_system: vec![OperatingSystem::Windows],
             tool_set: None,
+            permissions: None,
         };
 
         let zoo_tool = ZooTool::Deno(deno_tool, true);
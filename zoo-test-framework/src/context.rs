@@ -145,7 +145,8 @@ where
             supported_embedding_models(),
             Some(api_key.clone()),
         )
-        .await;
+        .await
+        .unwrap();
 
         let abort_handle;
         {